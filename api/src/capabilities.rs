@@ -17,6 +17,8 @@ bitflags::bitflags! {
     pub struct Capabilities : u128 {
         // Represents base capabilities present in Caliptra ROM v1.0
         const ROM_BASE = 0b0001;
+        // Represents the FMC recovery mailbox service
+        const FMC_RECOVERY = 0b0010;
     }
 }
 
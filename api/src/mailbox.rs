@@ -21,6 +21,8 @@ impl CommandId {
     pub const FW_INFO: Self = Self(0x494E464F); // "INFO"
     pub const DPE_TAG_TCI: Self = Self(0x54514754); // "TAGT"
     pub const DPE_GET_TAGGED_TCI: Self = Self(0x47544744); // "GTGD"
+    pub const VERIFY_SOC_IMAGE: Self = Self(0x534F_4356); // "SOCV"
+    pub const COMMIT_SVN: Self = Self(0x434D_5356); // "CMSV"
 
     pub const TEST_ONLY_HMAC384_VERIFY: Self = Self(0x484D4143); // "HMAC"
 
@@ -133,6 +135,8 @@ pub enum MailboxResp {
     Capabilities(CapabilitiesResp),
     GetTaggedTci(GetTaggedTciResp),
     GetRtAliasCert(GetRtAliasCertResp),
+    VerifySocImage(VerifySocImageResp),
+    CommitSvn(CommitSvnResp),
 }
 
 impl MailboxResp {
@@ -150,6 +154,8 @@ impl MailboxResp {
             MailboxResp::GetTaggedTci(resp) => Ok(resp.as_bytes()),
             MailboxResp::GetFmcAliasCert(resp) => resp.as_bytes_partial(),
             MailboxResp::GetRtAliasCert(resp) => resp.as_bytes_partial(),
+            MailboxResp::VerifySocImage(resp) => Ok(resp.as_bytes()),
+            MailboxResp::CommitSvn(resp) => Ok(resp.as_bytes()),
         }
     }
 
@@ -167,6 +173,8 @@ impl MailboxResp {
             MailboxResp::GetTaggedTci(resp) => Ok(resp.as_bytes_mut()),
             MailboxResp::GetFmcAliasCert(resp) => resp.as_bytes_partial_mut(),
             MailboxResp::GetRtAliasCert(resp) => resp.as_bytes_partial_mut(),
+            MailboxResp::VerifySocImage(resp) => Ok(resp.as_bytes_mut()),
+            MailboxResp::CommitSvn(resp) => Ok(resp.as_bytes_mut()),
         }
     }
 
@@ -219,6 +227,8 @@ pub enum MailboxReq {
     GetTaggedTci(GetTaggedTciReq),
     GetFmcAliasCert(GetFmcAliasCertReq),
     GetRtAliasCert(GetRtAliasCertReq),
+    VerifySocImage(VerifySocImageReq),
+    CommitSvn(MailboxReqHeader),
 
     #[cfg(feature = "test_only_commands")]
     TestHmacVerify(HmacVerifyReq),
@@ -239,6 +249,8 @@ impl MailboxReq {
             MailboxReq::GetTaggedTci(req) => Ok(req.as_bytes()),
             MailboxReq::GetFmcAliasCert(req) => Ok(req.as_bytes()),
             MailboxReq::GetRtAliasCert(req) => Ok(req.as_bytes()),
+            MailboxReq::VerifySocImage(req) => Ok(req.as_bytes()),
+            MailboxReq::CommitSvn(req) => Ok(req.as_bytes()),
 
             #[cfg(feature = "test_only_commands")]
             MailboxReq::TestHmacVerify(req) => Ok(req.as_bytes()),
@@ -259,6 +271,8 @@ impl MailboxReq {
             MailboxReq::GetTaggedTci(req) => Ok(req.as_bytes_mut()),
             MailboxReq::GetFmcAliasCert(req) => Ok(req.as_bytes_mut()),
             MailboxReq::GetRtAliasCert(req) => Ok(req.as_bytes_mut()),
+            MailboxReq::VerifySocImage(req) => Ok(req.as_bytes_mut()),
+            MailboxReq::CommitSvn(req) => Ok(req.as_bytes_mut()),
 
             #[cfg(feature = "test_only_commands")]
             MailboxReq::TestHmacVerify(req) => Ok(req.as_bytes_mut()),
@@ -279,6 +293,8 @@ impl MailboxReq {
             MailboxReq::GetTaggedTci(_) => CommandId::DPE_GET_TAGGED_TCI,
             MailboxReq::GetFmcAliasCert(_) => CommandId::GET_FMC_ALIAS_CERT,
             MailboxReq::GetRtAliasCert(_) => CommandId::GET_RT_ALIAS_CERT,
+            MailboxReq::VerifySocImage(_) => CommandId::VERIFY_SOC_IMAGE,
+            MailboxReq::CommitSvn(_) => CommandId::COMMIT_SVN,
 
             #[cfg(feature = "test_only_commands")]
             MailboxReq::TestHmacVerify(_) => CommandId::TEST_ONLY_HMAC384_VERIFY,
@@ -672,6 +688,9 @@ pub struct FwInfoResp {
     pub min_runtime_svn: u32,
     pub fmc_manifest_svn: u32,
     pub attestation_disabled: u32,
+    pub runtime_fuse_svn: u32,
+    pub fmc_fuse_svn: u32,
+    pub svn_commit_pending: u32,
     // TODO: Decide what other information to report for general firmware
     // status.
 }
@@ -749,6 +768,50 @@ pub struct GetTaggedTciResp {
     pub tci_current: [u8; 48],
 }
 
+// VERIFY_SOC_IMAGE
+// The image content, if any, immediately follows the fixed-size request.
+#[repr(C)]
+#[derive(Debug, AsBytes, FromBytes, PartialEq, Eq)]
+pub struct VerifySocImageReq {
+    pub hdr: MailboxReqHeader,
+    pub image_id: u32,
+    pub image_size: u32,
+    pub digest: [u8; 48],
+}
+impl Default for VerifySocImageReq {
+    fn default() -> Self {
+        Self {
+            hdr: Default::default(),
+            image_id: 0,
+            image_size: 0,
+            digest: [0u8; 48],
+        }
+    }
+}
+impl Request for VerifySocImageReq {
+    const ID: CommandId = CommandId::VERIFY_SOC_IMAGE;
+    type Resp = VerifySocImageResp;
+}
+
+#[repr(C)]
+#[derive(Debug, Default, AsBytes, FromBytes, PartialEq, Eq)]
+pub struct VerifySocImageResp {
+    pub hdr: MailboxRespHeader,
+    pub dpe_result: u32,
+}
+impl Response for VerifySocImageResp {}
+
+// COMMIT_SVN
+// No command-specific input args
+#[repr(C)]
+#[derive(Debug, Default, AsBytes, FromBytes, PartialEq, Eq)]
+pub struct CommitSvnResp {
+    pub hdr: MailboxRespHeader,
+    pub fmc_key_manifest_svn: u32,
+    pub runtime_svn: [u32; 4],
+}
+impl Response for CommitSvnResp {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
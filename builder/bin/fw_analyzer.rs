@@ -0,0 +1,98 @@
+// Licensed under the Apache-2.0 license
+
+use clap::{arg, value_parser, ArgAction, Command};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Entry points of the ROM, FMC and runtime: the reset vector, trap handler
+/// and NMI handler, each of which runs on its own stack.
+const DEFAULT_ENTRIES: &[&str] = &[
+    "_start",
+    "_exception_handler",
+    "_trap_handler",
+    "_nmi_handler",
+];
+
+fn main() -> ExitCode {
+    let args = Command::new("fw-analyzer")
+        .about("Report the code size and worst-case stack usage of a firmware ELF")
+        .arg(arg!(<ELF> "Firmware ELF file").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--"top" [N] "Number of largest functions to list")
+                .value_parser(value_parser!(usize))
+                .default_value("20"),
+        )
+        .arg(
+            arg!(--"entry" [SYMBOL] "Report stack usage from this symbol instead of the defaults")
+                .action(ArgAction::Append),
+        )
+        .arg(
+            arg!(--"stack-limit" [LIMIT] "SYMBOL=BYTES; fail if the stack usage of SYMBOL exceeds BYTES")
+                .action(ArgAction::Append),
+        )
+        .get_matches();
+
+    let path = args.get_one::<PathBuf>("ELF").unwrap();
+    let elf_bytes = std::fs::read(path).unwrap();
+
+    let sizes = caliptra_builder::size_report(&elf_bytes).unwrap();
+    println!("Code size by crate ({} bytes total):", sizes.total());
+    for (krate, size) in &sizes.crates {
+        println!("  {size:>8}  {krate}");
+    }
+    let top = *args.get_one::<usize>("top").unwrap();
+    println!("Largest functions:");
+    for f in sizes.functions.iter().take(top) {
+        println!("  {:>8}  {}", f.size, f.name);
+    }
+
+    let mut limits = vec![];
+    for limit in args.get_many::<String>("stack-limit").into_iter().flatten() {
+        let Some((symbol, bytes)) = limit.split_once('=') else {
+            eprintln!("Invalid --stack-limit {limit:?}; expected SYMBOL=BYTES");
+            return ExitCode::FAILURE;
+        };
+        let Ok(bytes) = bytes.parse::<u64>() else {
+            eprintln!("Invalid byte count in --stack-limit {limit:?}");
+            return ExitCode::FAILURE;
+        };
+        limits.push((symbol.to_string(), bytes));
+    }
+
+    let mut entries: Vec<String> = match args.get_many::<String>("entry") {
+        Some(entries) => entries.cloned().collect(),
+        None => DEFAULT_ENTRIES.iter().map(|s| s.to_string()).collect(),
+    };
+    for (symbol, _) in &limits {
+        if !entries.contains(symbol) {
+            entries.push(symbol.clone());
+        }
+    }
+
+    let usage = caliptra_builder::stack_usage(&elf_bytes).unwrap();
+    let mut result = ExitCode::SUCCESS;
+    println!("Worst-case stack usage:");
+    for entry in entries {
+        let Some(depth) = usage.worst_case(&entry) else {
+            if limits.iter().any(|(symbol, _)| *symbol == entry) {
+                eprintln!("No function named {entry:?}");
+                result = ExitCode::FAILURE;
+            }
+            continue;
+        };
+        println!("  {:>8}  {}", depth.bytes, depth.path.join(" -> "));
+        for unknown in &depth.unknowns {
+            println!("            not included: {unknown}");
+        }
+        for (_, limit) in limits.iter().filter(|(symbol, _)| *symbol == entry) {
+            if depth.bytes > *limit {
+                eprintln!(
+                    "Stack usage of {entry} is {} bytes, exceeding the limit of {limit}",
+                    depth.bytes
+                );
+                result = ExitCode::FAILURE;
+            }
+        }
+    }
+    result
+}
@@ -2,9 +2,10 @@
 
 use caliptra_builder::firmware;
 use caliptra_builder::ImageOptions;
+use caliptra_image_gen::{ImageGeneratorExtension, ImageGeneratorSocImageConfig};
 use clap::{arg, value_parser, Command};
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn main() {
     let args = Command::new("image-gen")
@@ -24,6 +25,10 @@ fn main() {
         )
         .arg(arg!(--"fake-rom" [FILE] "Fake ROM").value_parser(value_parser!(PathBuf)))
         .arg(arg!(--"fake-fw" [FILE] "Fake FW bundle image").value_parser(value_parser!(PathBuf)))
+        .arg(
+            arg!(--"fuzz-corpus" [DIR] "Image verifier fuzzing seed corpus")
+                .value_parser(value_parser!(PathBuf)),
+        )
         .get_matches();
 
     if let Some(path) = args.get_one::<PathBuf>("rom-no-log") {
@@ -62,6 +67,10 @@ fn main() {
         std::fs::write(path, image.to_bytes().unwrap()).unwrap();
     }
 
+    if let Some(dir) = args.get_one::<PathBuf>("fuzz-corpus") {
+        write_fuzz_corpus(dir);
+    }
+
     let mut used_filenames = HashSet::new();
     if let Some(all_dir) = args.get_one::<PathBuf>("all_elfs") {
         for (fwid, elf_bytes) in
@@ -76,6 +85,86 @@ fn main() {
     }
 }
 
+// Seeds for the image verifier fuzzer, covering the optional parts of the
+// manifest (SoC image TOC entries and header extensions) so the fuzzer
+// doesn't have to discover their layout on its own.
+fn write_fuzz_corpus(dir: &Path) {
+    let soc_images = || {
+        vec![
+            ImageGeneratorSocImageConfig {
+                id: 0x1000,
+                version: 1,
+                svn: 1,
+                min_svn: 1,
+                load_addr: 0x8000_0000,
+                entry_point: 0x8000_0000,
+                content: (0..1024u32).map(|i| i as u8).collect(),
+                ..Default::default()
+            },
+            ImageGeneratorSocImageConfig {
+                id: 0x1001,
+                version: 2,
+                svn: 4,
+                min_svn: 2,
+                load_addr: 0x9000_0000,
+                content: vec![0xa5; 4096],
+                ..Default::default()
+            },
+        ]
+    };
+    let extensions = || {
+        vec![
+            ImageGeneratorExtension {
+                tag: 0x10,
+                critical: false,
+                value: vec![1, 2, 3, 4],
+            },
+            ImageGeneratorExtension {
+                tag: 0x20,
+                critical: true,
+                value: vec![5, 6, 7, 8, 9, 10, 11, 12],
+            },
+        ]
+    };
+
+    let seeds = [
+        ("default", ImageOptions::default()),
+        (
+            "soc_images",
+            ImageOptions {
+                soc_images: soc_images(),
+                ..Default::default()
+            },
+        ),
+        (
+            "extensions",
+            ImageOptions {
+                extensions: extensions(),
+                ..Default::default()
+            },
+        ),
+        (
+            "soc_images_extensions",
+            ImageOptions {
+                soc_images: soc_images(),
+                extensions: extensions(),
+                ..Default::default()
+            },
+        ),
+    ];
+
+    std::fs::create_dir_all(dir).unwrap();
+    for (name, opts) in seeds {
+        let image = caliptra_builder::build_and_sign_image(
+            &firmware::FMC_WITH_UART,
+            &firmware::APP_WITH_UART,
+            opts,
+        )
+        .unwrap();
+        std::fs::write(dir.join(name), image.to_bytes().unwrap()).unwrap();
+    }
+}
+
 #[test]
 #[cfg_attr(not(feature = "slow_tests"), ignore)]
 fn test_binaries_are_identical() {
@@ -272,6 +272,11 @@ pub mod driver_tests {
         ..BASE_FWID
     };
 
+    pub const CSRNG_FAIL_CONTINUOUS_TESTS: FwId = FwId {
+        bin_name: "csrng_fail_continuous_tests",
+        ..BASE_FWID
+    };
+
     pub const TRNG_DRIVER_RESPONDER: FwId = FwId {
         bin_name: "trng_driver_responder",
         ..BASE_FWID
@@ -281,6 +286,11 @@ pub mod driver_tests {
         bin_name: "persistent",
         ..BASE_FWID
     };
+
+    pub const SPI_FLASH: FwId = FwId {
+        bin_name: "spi_flash",
+        ..BASE_FWID
+    };
 }
 
 pub mod rom_tests {
@@ -409,8 +419,10 @@ pub const REGISTERED_FW: &[&FwId] = &[
     &driver_tests::CSRNG_PASS_HEALTH_TESTS,
     &driver_tests::CSRNG_FAIL_REPCNT_TESTS,
     &driver_tests::CSRNG_FAIL_ADAPTP_TESTS,
+    &driver_tests::CSRNG_FAIL_CONTINUOUS_TESTS,
     &driver_tests::TRNG_DRIVER_RESPONDER,
     &driver_tests::PERSISTENT,
+    &driver_tests::SPI_FLASH,
     &rom_tests::ASM_TESTS,
     &rom_tests::TEST_FMC_WITH_UART,
     &rom_tests::FAKE_TEST_FMC_WITH_UART,
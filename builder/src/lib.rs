@@ -13,7 +13,8 @@ use std::sync::{Arc, Mutex};
 
 use caliptra_image_elf::ElfExecutable;
 use caliptra_image_gen::{
-    ImageGenerator, ImageGeneratorConfig, ImageGeneratorOwnerConfig, ImageGeneratorVendorConfig,
+    ImageGenerator, ImageGeneratorConfig, ImageGeneratorExtension, ImageGeneratorOwnerConfig,
+    ImageGeneratorSocImageConfig, ImageGeneratorVendorConfig,
 };
 use caliptra_image_openssl::OsslCrypto;
 use caliptra_image_types::{ImageBundle, ImageRevision, RomInfo};
@@ -24,9 +25,13 @@ use zerocopy::AsBytes;
 mod elf_symbols;
 pub mod firmware;
 mod sha256;
+mod size_report;
+mod stack_usage;
 
 pub use elf_symbols::{elf_symbols, Symbol, SymbolBind, SymbolType, SymbolVisibility};
 use once_cell::sync::Lazy;
+pub use size_report::{size_report, FunctionSize, SizeReport};
+pub use stack_usage::{stack_usage, Function, StackDepth, StackUsage};
 
 pub const THIS_WORKSPACE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/..");
 
@@ -126,9 +131,26 @@ pub fn build_firmware_elf_uncached(workspace_dir: Option<&Path>, id: &FwId) -> i
 pub fn build_firmware_elfs_uncached<'a>(
     workspace_dir: Option<&Path>,
     fwids: &'a [&'a FwId<'a>],
+) -> io::Result<Vec<(&'a FwId<'a>, Vec<u8>)>> {
+    build_firmware_elfs_with_profile(workspace_dir, fwids, "firmware")
+}
+
+/// Calls out to Cargo to build a firmware elf file with DWARF line tables,
+/// for mapping addresses to source lines. The code is the same as that of
+/// [`build_firmware_elf`], which is built without debug info so that it
+/// can't affect the firmware images.
+pub fn build_firmware_elf_with_debug_info(id: &FwId) -> io::Result<Vec<u8>> {
+    let fwids = [id];
+    let result = build_firmware_elfs_with_profile(None, &fwids, "firmware-debuginfo")?;
+    Ok(result.into_iter().next().unwrap().1)
+}
+
+fn build_firmware_elfs_with_profile<'a>(
+    workspace_dir: Option<&Path>,
+    fwids: &'a [&'a FwId<'a>],
+    profile: &str,
 ) -> io::Result<Vec<(&'a FwId<'a>, Vec<u8>)>> {
     const TARGET: &str = "riscv32imc-unknown-none-elf";
-    const PROFILE: &str = "firmware";
 
     let cargo_invocations = cargo_invocations_from_fwids(fwids)?;
 
@@ -175,7 +197,7 @@ pub fn build_firmware_elfs_uncached<'a>(
             .arg(features_csv)
             .arg("--no-default-features")
             .arg("--profile")
-            .arg(PROFILE);
+            .arg(profile);
 
         cmd.arg("-p").arg(invocation.crate_name);
         for &fwid in invocation.fwids.iter() {
@@ -192,7 +214,7 @@ pub fn build_firmware_elfs_uncached<'a>(
         for &fwid in invocation.fwids.iter() {
             result_map.insert(
                 fwid,
-                fs::read(target_dir.join(TARGET).join(PROFILE).join(fwid.bin_name))?,
+                fs::read(target_dir.join(TARGET).join(profile).join(fwid.bin_name))?,
             );
         }
     }
@@ -415,6 +437,8 @@ pub struct ImageOptions {
     pub app_svn: u32,
     pub vendor_config: ImageGeneratorVendorConfig,
     pub owner_config: Option<ImageGeneratorOwnerConfig>,
+    pub soc_images: Vec<ImageGeneratorSocImageConfig>,
+    pub extensions: Vec<ImageGeneratorExtension>,
 }
 impl Default for ImageOptions {
     fn default() -> Self {
@@ -427,6 +451,8 @@ impl Default for ImageOptions {
             app_svn: Default::default(),
             vendor_config: caliptra_image_fake_keys::VENDOR_CONFIG_KEY_0,
             owner_config: Some(caliptra_image_fake_keys::OWNER_CONFIG),
+            soc_images: Vec::new(),
+            extensions: Vec::new(),
         }
     }
 }
@@ -456,6 +482,8 @@ pub fn build_and_sign_image(
         )?,
         vendor_config: opts.vendor_config,
         owner_config: opts.owner_config,
+        soc_images: opts.soc_images,
+        extensions: opts.extensions,
     })?;
     Ok(image)
 }
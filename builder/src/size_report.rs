@@ -0,0 +1,135 @@
+// Licensed under the Apache-2.0 license
+
+//! Code size of firmware broken down by crate and by function.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+
+use crate::elf_symbols::{elf_symbols, SymbolType};
+use crate::stack_usage::demangle;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionSize {
+    /// Demangled name, without the hash.
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeReport {
+    /// Sorted by size, largest first.
+    pub functions: Vec<FunctionSize>,
+
+    /// Total function size per crate, sorted by size, largest first. Symbols
+    /// that aren't Rust (assembly, C) are counted under "[unmangled]".
+    pub crates: Vec<(String, u64)>,
+}
+
+impl SizeReport {
+    pub fn total(&self) -> u64 {
+        self.functions.iter().map(|f| f.size).sum()
+    }
+}
+
+/// Report the size of every function symbol in `elf_bytes`.
+///
+/// The inner `__cfi_` functions generated by `#[cfi_impl_fn]` and
+/// `#[cfi_mod_fn]` are reported under the name of the function they wrap.
+pub fn size_report(elf_bytes: &[u8]) -> io::Result<SizeReport> {
+    let mut seen = HashSet::new();
+    let mut functions = vec![];
+    for sym in elf_symbols(elf_bytes)? {
+        if sym.ty != SymbolType::Func || sym.size == 0 || !seen.insert((sym.value, sym.size)) {
+            continue;
+        }
+        let name = demangle(sym.name);
+        functions.push(FunctionSize {
+            name: strip_cfi_prefix(&name),
+            addr: sym.value,
+            size: sym.size,
+        });
+    }
+    functions.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    let mut crates = BTreeMap::<String, u64>::new();
+    for f in &functions {
+        *crates.entry(crate_name(&f.name)).or_default() += f.size;
+    }
+    let mut crates: Vec<_> = crates.into_iter().collect();
+    crates.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(SizeReport { functions, crates })
+}
+
+fn strip_cfi_prefix(name: &str) -> String {
+    match name.rsplit_once("::") {
+        Some((path, f)) if f.starts_with("__cfi_") => format!("{path}::{}", &f[6..]),
+        None if name.starts_with("__cfi_") => name[6..].into(),
+        _ => name.into(),
+    }
+}
+
+/// The crate a demangled name belongs to. For trait impls such as
+/// `<caliptra_drivers::Sha256 as Foo>::bar` that's the crate of the type, or
+/// of the trait when the type is primitive.
+fn crate_name(name: &str) -> String {
+    let path = name.trim_start_matches(['<', '&', '*', '[', '(']);
+    let path = ["mut ", "const ", "dyn "]
+        .iter()
+        .fold(path, |s, prefix| s.strip_prefix(prefix).unwrap_or(s));
+    let ident_len = path
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(path.len());
+    if ident_len > 0 && path[ident_len..].starts_with("::") {
+        return path[..ident_len].into();
+    }
+    match name.split_once(" as ") {
+        Some((_, tr)) if tr.contains("::") => crate_name(tr),
+        _ => "[unmangled]".into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_crate_name() {
+        assert_eq!(crate_name("caliptra_rom::rom_entry"), "caliptra_rom");
+        assert_eq!(
+            crate_name("<caliptra_drivers::sha256::Sha256 as core::ops::Drop>::drop"),
+            "caliptra_drivers"
+        );
+        assert_eq!(crate_name("<&mut [u8] as core::fmt::Debug>::fmt"), "core");
+        assert_eq!(crate_name("<&mut core::fmt::Formatter>::pad"), "core");
+        assert_eq!(crate_name("memset"), "[unmangled]");
+    }
+
+    #[test]
+    fn test_strip_cfi_prefix() {
+        assert_eq!(
+            strip_cfi_prefix("caliptra_rom::fht::__cfi_initialize_fht"),
+            "caliptra_rom::fht::initialize_fht"
+        );
+        assert_eq!(strip_cfi_prefix("__cfi_foo"), "foo");
+        assert_eq!(strip_cfi_prefix("caliptra_rom::foo"), "caliptra_rom::foo");
+    }
+
+    #[test]
+    fn test_size_report() {
+        let report = size_report(include_bytes!("testdata/stack_example.elf")).unwrap();
+        let big_frame = report
+            .functions
+            .iter()
+            .find(|f| f.name == "stack_example::big_frame")
+            .unwrap();
+        assert_eq!(big_frame.addr, 0x76);
+        assert_eq!(
+            report.crates.iter().map(|(_, size)| size).sum::<u64>(),
+            report.total()
+        );
+        assert!(report.crates.iter().any(|(c, _)| c == "stack_example"));
+        assert!(report.crates.iter().any(|(c, _)| c == "[unmangled]"));
+    }
+}
@@ -0,0 +1,750 @@
+// Licensed under the Apache-2.0 license
+
+//! Worst-case stack depth of firmware, from the call graph of its RISC-V
+//! (RV32IMC) disassembly.
+//!
+//! A function's frame is the sum of the stack pointer decrements in its
+//! body, and its depth is its frame plus the depth of its deepest callee.
+//! Tail calls are counted like calls, so the result errs on the high side.
+//! Calls through function pointers, recursion and frames sized at runtime
+//! can't be followed; they are reported in [`StackDepth::unknowns`] so the
+//! caller can judge whether the result is complete.
+//!
+//! CFI-protected functions need no special handling: the `#[cfi_impl_fn]`
+//! wrapper is inlined into each caller and calls the `__cfi_` function, so
+//! both frames are counted.
+
+use std::collections::BTreeSet;
+use std::io;
+
+use elf::endian::LittleEndian;
+
+use super::other_err;
+
+const REG_ZERO: usize = 0;
+const REG_RA: usize = 1;
+const REG_SP: usize = 2;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Function {
+    /// Demangled name, without the hash.
+    pub name: String,
+
+    /// Name in the symbol table.
+    pub symbol: String,
+
+    pub addr: u64,
+    pub size: u64,
+
+    /// Bytes of stack allocated by the function itself.
+    pub frame_size: u64,
+
+    /// Addresses jumped to outside the function, by calls, tail calls or
+    /// falling through to the next label in assembly.
+    pub callees: Vec<u64>,
+
+    /// The function calls through a function pointer.
+    pub has_indirect_calls: bool,
+
+    /// The function moves the stack pointer by an amount only known at
+    /// runtime.
+    pub has_dynamic_frame: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StackDepth {
+    /// Worst-case bytes of stack used by the entry point and its callees.
+    pub bytes: u64,
+
+    /// The call chain using `bytes` of stack, starting with the entry point.
+    pub path: Vec<String>,
+
+    /// Reachable functions whose stack use couldn't be fully determined,
+    /// and why.
+    pub unknowns: Vec<String>,
+}
+
+pub struct StackUsage {
+    /// Sorted by address.
+    functions: Vec<Function>,
+}
+
+impl StackUsage {
+    pub fn functions(&self) -> &[Function] {
+        &self.functions
+    }
+
+    /// Find a function by its demangled or symbol name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.index_of(name).map(|i| &self.functions[i])
+    }
+
+    /// The worst-case stack depth of `entry` and everything it calls, or
+    /// None if there is no function called `entry`.
+    pub fn worst_case(&self, entry: &str) -> Option<StackDepth> {
+        let entry = self.index_of(entry)?;
+        let mut state = vec![Visit::New; self.functions.len()];
+        let mut unknowns = BTreeSet::new();
+        let bytes = self.depth(entry, &mut state, &mut unknowns);
+
+        let mut path = vec![];
+        let mut next = Some(entry);
+        while let Some(i) = next {
+            path.push(self.functions[i].name.clone());
+            next = match state[i] {
+                Visit::Done(_, deepest) => deepest,
+                _ => None,
+            };
+        }
+        Some(StackDepth {
+            bytes,
+            path,
+            unknowns: unknowns.into_iter().collect(),
+        })
+    }
+
+    /// Check that each `(entry, limit)` uses at most `limit` bytes of stack,
+    /// and that every unknown reachable from it is in `allowed_unknowns`,
+    /// printing the worst-case path of each entry.
+    ///
+    /// `allowed_unknowns` holds [`StackDepth::unknowns`] entries whose stack
+    /// use has been checked by hand.
+    pub fn check_limits(
+        &self,
+        limits: &[(&str, u32)],
+        allowed_unknowns: &[&str],
+    ) -> io::Result<()> {
+        for &(entry, limit) in limits {
+            let Some(depth) = self.worst_case(entry) else {
+                return Err(other_err(format!("Unknown symbol {entry}")));
+            };
+            println!(
+                "{entry}: {} bytes: {}",
+                depth.bytes,
+                depth.path.join(" -> ")
+            );
+            if depth.bytes > u64::from(limit) {
+                return Err(other_err(format!(
+                    "Worst-case stack usage of {entry} is {} bytes, exceeding the {limit} byte stack: {}",
+                    depth.bytes,
+                    depth.path.join(" -> ")
+                )));
+            }
+            let unknowns: Vec<_> = depth
+                .unknowns
+                .iter()
+                .filter(|u| !allowed_unknowns.contains(&u.as_str()))
+                .collect();
+            if !unknowns.is_empty() {
+                return Err(other_err(format!(
+                    "Stack usage of {entry} can't be determined; check these by hand and allow them: {unknowns:#?}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn index_of(&self, name: &str) -> Option<usize> {
+        self.functions
+            .iter()
+            .position(|f| f.symbol == name || f.name == name)
+    }
+
+    fn containing(&self, addr: u64) -> Option<usize> {
+        let i = self.functions.partition_point(|f| f.addr <= addr);
+        let f = self.functions.get(i.checked_sub(1)?)?;
+        (addr < f.addr + f.size).then_some(i - 1)
+    }
+
+    fn depth(&self, i: usize, state: &mut [Visit], unknowns: &mut BTreeSet<String>) -> u64 {
+        let func = &self.functions[i];
+        match state[i] {
+            Visit::Done(depth, _) => return depth,
+            Visit::InProgress => {
+                unknowns.insert(format!("{}: recursion", func.name));
+                return 0;
+            }
+            Visit::New => {}
+        }
+        state[i] = Visit::InProgress;
+        if func.has_indirect_calls {
+            unknowns.insert(format!("{}: indirect call", func.name));
+        }
+        if func.has_dynamic_frame {
+            unknowns.insert(format!("{}: dynamically sized frame", func.name));
+        }
+        let mut deepest = None;
+        let mut deepest_bytes = 0;
+        for &callee in &func.callees {
+            let Some(j) = self.containing(callee) else {
+                unknowns.insert(format!(
+                    "{}: call to {callee:#x} outside any function",
+                    func.name
+                ));
+                continue;
+            };
+            let bytes = self.depth(j, state, unknowns);
+            if deepest.is_none() || bytes > deepest_bytes {
+                deepest = Some(j);
+                deepest_bytes = bytes;
+            }
+        }
+        let depth = func.frame_size + deepest_bytes;
+        state[i] = Visit::Done(depth, deepest);
+        depth
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Visit {
+    New,
+    InProgress,
+    Done(u64, Option<usize>),
+}
+
+/// Disassemble the functions in `elf_bytes` and build their call graph.
+///
+/// Functions are the `FUNC` symbols in executable sections, plus labels in
+/// assembly (such as `_start` or the trap handlers), which extend to the
+/// next symbol.
+pub fn stack_usage(elf_bytes: &[u8]) -> io::Result<StackUsage> {
+    let elf = elf::ElfBytes::<LittleEndian>::minimal_parse(elf_bytes).map_err(other_err)?;
+    let Some(sections) = elf.section_headers() else {
+        return Err(other_err("ELF file has no section headers"));
+    };
+    let Some((symbols, strings)) = elf.symbol_table().map_err(other_err)? else {
+        return Ok(StackUsage { functions: vec![] });
+    };
+
+    struct Candidate<'a> {
+        symbol: &'a str,
+        addr: u64,
+        size: u64,
+        is_func: bool,
+        section: usize,
+    }
+    let mut candidates = vec![];
+    for sym in symbols.iter() {
+        let Ok(section) = sections.get(usize::from(sym.st_shndx)) else {
+            continue;
+        };
+        if sym.st_shndx == 0 || section.sh_flags & u64::from(elf::abi::SHF_EXECINSTR) == 0 {
+            continue;
+        }
+        let is_func = sym.st_symtype() == elf::abi::STT_FUNC;
+        if !is_func && sym.st_symtype() != elf::abi::STT_NOTYPE {
+            continue;
+        }
+        let symbol = strings.get(sym.st_name as usize).map_err(other_err)?;
+        if symbol.is_empty() || symbol.starts_with(".L") || symbol.starts_with('$') {
+            continue;
+        }
+        candidates.push(Candidate {
+            symbol,
+            addr: sym.st_value,
+            size: sym.st_size,
+            is_func,
+            section: usize::from(sym.st_shndx),
+        });
+    }
+    // Prefer FUNC symbols over labels at the same address.
+    candidates.sort_by_key(|c| (c.addr, !c.is_func));
+    candidates.dedup_by_key(|c| c.addr);
+
+    let mut functions = vec![];
+    for (i, c) in candidates.iter().enumerate() {
+        let section = sections.get(c.section).map_err(other_err)?;
+        let section_end = section.sh_addr + section.sh_size;
+        let next_addr = candidates
+            .get(i + 1)
+            .map_or(section_end, |next| next.addr.min(section_end));
+        let size = if c.is_func && c.size > 0 {
+            c.size
+        } else {
+            next_addr.saturating_sub(c.addr)
+        };
+        let (data, _) = elf.section_data(&section).map_err(other_err)?;
+        let start = (c.addr - section.sh_addr) as usize;
+        let code = data
+            .get(start..start + size as usize)
+            .ok_or_else(|| other_err(format!("{} is outside its section", c.symbol)))?;
+        let mut func = disassemble(c.addr, code);
+        if !c.is_func && !func.ends_with_jump && next_addr < section_end {
+            func.callees.push(next_addr);
+        }
+        functions.push(Function {
+            name: demangle(c.symbol),
+            symbol: c.symbol.into(),
+            addr: c.addr,
+            size,
+            frame_size: func.frame_size,
+            callees: func.callees,
+            has_indirect_calls: func.has_indirect_calls,
+            has_dynamic_frame: func.has_dynamic_frame,
+        });
+    }
+    Ok(StackUsage { functions })
+}
+
+pub(crate) fn demangle(symbol: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(symbol))
+}
+
+#[derive(Default)]
+struct Disassembly {
+    frame_size: u64,
+    callees: Vec<u64>,
+    has_indirect_calls: bool,
+    has_dynamic_frame: bool,
+    ends_with_jump: bool,
+}
+
+/// Scan the instructions of one function for stack pointer adjustments and
+/// jumps out of the function.
+///
+/// Register values are tracked through `lui`/`auipc`/`addi`/`li`, which is
+/// enough to follow `call`/`tail` sequences and large frames allocated with
+/// `sub sp, sp, t0`. Any other write to a register forgets its value. Writes
+/// to `sp` other than adjustments (switching stacks, or restoring it from
+/// the frame pointer) are ignored.
+fn disassemble(addr: u64, code: &[u8]) -> Disassembly {
+    let end = addr + code.len() as u64;
+    let mut result = Disassembly::default();
+    let mut regs: [Option<u32>; 32] = [None; 32];
+    let mut offset = 0;
+    while offset + 2 <= code.len() {
+        let pc = addr + offset as u64;
+        let lo = u16::from_le_bytes([code[offset], code[offset + 1]]);
+        let inst = if lo & 0b11 == 0b11 {
+            let Some(hi) = code.get(offset + 2..offset + 4) else {
+                break;
+            };
+            offset += 4;
+            decode32(u32::from(lo) | u32::from(u16::from_le_bytes([hi[0], hi[1]])) << 16)
+        } else {
+            offset += 2;
+            decode16(lo)
+        };
+        regs[REG_ZERO] = Some(0);
+        result.ends_with_jump = false;
+        match inst {
+            Inst::SetReg { rd, value } => set_reg(&mut regs, rd, value),
+            Inst::Auipc { rd, imm } => set_reg(&mut regs, rd, Some((pc as u32).wrapping_add(imm))),
+            Inst::AddImm { rd, rs1, imm } if rd == REG_SP && rs1 == REG_SP => {
+                adjust_sp(&mut result, Some(imm))
+            }
+            Inst::AddImm { rd, rs1, imm } => {
+                let value = regs[rs1].map(|v| v.wrapping_add(imm as u32));
+                set_reg(&mut regs, rd, value);
+            }
+            Inst::AddReg { rd, rs1, rs2, sub } if rd == REG_SP && rs1 == REG_SP => {
+                let delta = regs[rs2].map(|v| {
+                    if sub {
+                        (v as i32).wrapping_neg()
+                    } else {
+                        v as i32
+                    }
+                });
+                adjust_sp(&mut result, delta);
+            }
+            Inst::AddReg { rd, rs1, rs2, sub } => {
+                let value = match (regs[rs1], regs[rs2]) {
+                    (Some(a), Some(b)) if sub => Some(a.wrapping_sub(b)),
+                    (Some(a), Some(b)) => Some(a.wrapping_add(b)),
+                    _ => None,
+                };
+                set_reg(&mut regs, rd, value);
+            }
+            Inst::Jal { rd, offset } => {
+                let target = pc.wrapping_add(offset as i64 as u64) & 0xffff_ffff;
+                if rd != REG_ZERO || !(addr..end).contains(&target) {
+                    result.callees.push(target);
+                }
+                set_reg(&mut regs, rd, None);
+                result.ends_with_jump = rd == REG_ZERO;
+            }
+            Inst::Jalr { rd, rs1, offset } => {
+                let target = regs[rs1].map(|v| u64::from(v.wrapping_add(offset as u32)));
+                match target {
+                    Some(target) if rd != REG_ZERO || !(addr..end).contains(&target) => {
+                        result.callees.push(target)
+                    }
+                    Some(_) => {}
+                    // Calls through a function pointer
+                    None if rd != REG_ZERO => result.has_indirect_calls = true,
+                    // Returns, or jump tables within the function
+                    None => {}
+                }
+                set_reg(&mut regs, rd, None);
+                result.ends_with_jump = rd == REG_ZERO;
+            }
+            Inst::Branch { offset } => {
+                let target = pc.wrapping_add(offset as i64 as u64) & 0xffff_ffff;
+                if !(addr..end).contains(&target) {
+                    result.callees.push(target);
+                }
+            }
+            Inst::Other { rd: Some(rd) } if rd == REG_SP => {}
+            Inst::Other { rd } => {
+                if let Some(rd) = rd {
+                    set_reg(&mut regs, rd, None);
+                }
+            }
+        }
+    }
+    result.callees.sort();
+    result.callees.dedup();
+    result
+}
+
+fn set_reg(regs: &mut [Option<u32>; 32], rd: usize, value: Option<u32>) {
+    if rd != REG_SP {
+        regs[rd] = value;
+    }
+}
+
+fn adjust_sp(result: &mut Disassembly, delta: Option<i32>) {
+    match delta {
+        Some(delta) if delta < 0 => result.frame_size += u64::from(delta.unsigned_abs()),
+        Some(_) => {}
+        None => result.has_dynamic_frame = true,
+    }
+}
+
+/// The instructions that matter for stack use; everything else is `Other`.
+#[derive(Debug, Eq, PartialEq)]
+enum Inst {
+    SetReg {
+        rd: usize,
+        value: Option<u32>,
+    },
+    Auipc {
+        rd: usize,
+        imm: u32,
+    },
+    AddImm {
+        rd: usize,
+        rs1: usize,
+        imm: i32,
+    },
+    AddReg {
+        rd: usize,
+        rs1: usize,
+        rs2: usize,
+        sub: bool,
+    },
+    Jal {
+        rd: usize,
+        offset: i32,
+    },
+    Jalr {
+        rd: usize,
+        rs1: usize,
+        offset: i32,
+    },
+    Branch {
+        offset: i32,
+    },
+    Other {
+        rd: Option<usize>,
+    },
+}
+
+fn bits(inst: u32, hi: u32, lo: u32) -> u32 {
+    (inst >> lo) & ((1 << (hi - lo + 1)) - 1)
+}
+
+fn sign_extend(value: u32, bits: u32) -> i32 {
+    ((value << (32 - bits)) as i32) >> (32 - bits)
+}
+
+fn decode32(inst: u32) -> Inst {
+    let rd = bits(inst, 11, 7) as usize;
+    let funct3 = bits(inst, 14, 12);
+    let rs1 = bits(inst, 19, 15) as usize;
+    let rs2 = bits(inst, 24, 20) as usize;
+    let funct7 = bits(inst, 31, 25);
+    let imm_i = sign_extend(bits(inst, 31, 20), 12);
+    match bits(inst, 6, 0) {
+        0b011_0111 => Inst::SetReg {
+            rd,
+            value: Some(inst & 0xffff_f000),
+        },
+        0b001_0111 => Inst::Auipc {
+            rd,
+            imm: inst & 0xffff_f000,
+        },
+        0b001_0011 if funct3 == 0 => Inst::AddImm {
+            rd,
+            rs1,
+            imm: imm_i,
+        },
+        0b011_0011 if funct3 == 0 && (funct7 == 0 || funct7 == 0b010_0000) => Inst::AddReg {
+            rd,
+            rs1,
+            rs2,
+            sub: funct7 != 0,
+        },
+        0b110_1111 => Inst::Jal {
+            rd,
+            offset: sign_extend(
+                bits(inst, 31, 31) << 20
+                    | bits(inst, 30, 21) << 1
+                    | bits(inst, 20, 20) << 11
+                    | bits(inst, 19, 12) << 12,
+                21,
+            ),
+        },
+        0b110_0111 => Inst::Jalr {
+            rd,
+            rs1,
+            offset: imm_i,
+        },
+        0b110_0011 => Inst::Branch {
+            offset: sign_extend(
+                bits(inst, 31, 31) << 12
+                    | bits(inst, 30, 25) << 5
+                    | bits(inst, 11, 8) << 1
+                    | bits(inst, 7, 7) << 11,
+                13,
+            ),
+        },
+        // Stores and fences don't write a register
+        0b010_0011 | 0b000_1111 => Inst::Other { rd: None },
+        _ => Inst::Other { rd: Some(rd) },
+    }
+}
+
+fn decode16(inst: u16) -> Inst {
+    let inst = u32::from(inst);
+    let funct3 = bits(inst, 15, 13);
+    let rd = bits(inst, 11, 7) as usize;
+    let rs2 = bits(inst, 6, 2) as usize;
+    // Registers x8-x15, used by the compressed forms with 3-bit fields
+    let rd_prime = 8 + bits(inst, 9, 7) as usize;
+    let rd_prime_q0 = 8 + bits(inst, 4, 2) as usize;
+    let imm6 = sign_extend(bits(inst, 12, 12) << 5 | bits(inst, 6, 2), 6);
+    let offset_cj = sign_extend(
+        bits(inst, 12, 12) << 11
+            | bits(inst, 11, 11) << 4
+            | bits(inst, 10, 9) << 8
+            | bits(inst, 8, 8) << 10
+            | bits(inst, 7, 7) << 6
+            | bits(inst, 6, 6) << 7
+            | bits(inst, 5, 3) << 1
+            | bits(inst, 2, 2) << 5,
+        12,
+    );
+    match (bits(inst, 1, 0), funct3) {
+        // c.addi4spn, c.lw
+        (0b00, 0b000) | (0b00, 0b010) => Inst::Other {
+            rd: Some(rd_prime_q0),
+        },
+        (0b00, _) => Inst::Other { rd: None },
+        // c.addi
+        (0b01, 0b000) => Inst::AddImm {
+            rd,
+            rs1: rd,
+            imm: imm6,
+        },
+        // c.jal
+        (0b01, 0b001) => Inst::Jal {
+            rd: REG_RA,
+            offset: offset_cj,
+        },
+        // c.li
+        (0b01, 0b010) => Inst::SetReg {
+            rd,
+            value: Some(imm6 as u32),
+        },
+        // c.addi16sp
+        (0b01, 0b011) if rd == REG_SP => Inst::AddImm {
+            rd,
+            rs1: rd,
+            imm: sign_extend(
+                bits(inst, 12, 12) << 9
+                    | bits(inst, 4, 3) << 7
+                    | bits(inst, 5, 5) << 6
+                    | bits(inst, 2, 2) << 5
+                    | bits(inst, 6, 6) << 4,
+                10,
+            ),
+        },
+        // c.lui
+        (0b01, 0b011) => Inst::SetReg {
+            rd,
+            value: Some((imm6 << 12) as u32),
+        },
+        (0b01, 0b100) => Inst::Other { rd: Some(rd_prime) },
+        // c.j
+        (0b01, 0b101) => Inst::Jal {
+            rd: REG_ZERO,
+            offset: offset_cj,
+        },
+        // c.beqz, c.bnez
+        (0b01, _) => Inst::Branch {
+            offset: sign_extend(
+                bits(inst, 12, 12) << 8
+                    | bits(inst, 6, 5) << 6
+                    | bits(inst, 2, 2) << 5
+                    | bits(inst, 11, 10) << 3
+                    | bits(inst, 4, 3) << 1,
+                9,
+            ),
+        },
+        // c.jr, c.mv
+        (0b10, 0b100) if bits(inst, 12, 12) == 0 => {
+            if rs2 == 0 {
+                Inst::Jalr {
+                    rd: REG_ZERO,
+                    rs1: rd,
+                    offset: 0,
+                }
+            } else {
+                Inst::AddReg {
+                    rd,
+                    rs1: REG_ZERO,
+                    rs2,
+                    sub: false,
+                }
+            }
+        }
+        // c.ebreak
+        (0b10, 0b100) if rd == 0 && rs2 == 0 => Inst::Other { rd: None },
+        // c.jalr
+        (0b10, 0b100) if rs2 == 0 => Inst::Jalr {
+            rd: REG_RA,
+            rs1: rd,
+            offset: 0,
+        },
+        // c.add
+        (0b10, 0b100) => Inst::AddReg {
+            rd,
+            rs1: rd,
+            rs2,
+            sub: false,
+        },
+        // c.slli, c.lwsp
+        (0b10, 0b000) | (0b10, 0b010) => Inst::Other { rd: Some(rd) },
+        _ => Inst::Other { rd: None },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // addi sp, sp, -2032
+        assert_eq!(
+            decode32(0x8101_0113),
+            Inst::AddImm {
+                rd: 2,
+                rs1: 2,
+                imm: -2032
+            }
+        );
+        // c.addi16sp sp, -80
+        assert_eq!(
+            decode16(0x715d),
+            Inst::AddImm {
+                rd: 2,
+                rs1: 2,
+                imm: -80
+            }
+        );
+        // c.addi sp, -32
+        assert_eq!(
+            decode16(0x1101),
+            Inst::AddImm {
+                rd: 2,
+                rs1: 2,
+                imm: -32
+            }
+        );
+        // jalr 300(ra)
+        assert_eq!(
+            decode32(0x12c0_80e7),
+            Inst::Jalr {
+                rd: 1,
+                rs1: 1,
+                offset: 300
+            }
+        );
+        // c.jalr s0
+        assert_eq!(
+            decode16(0x9402),
+            Inst::Jalr {
+                rd: 1,
+                rs1: 8,
+                offset: 0
+            }
+        );
+        // c.j -0 (an infinite loop)
+        assert_eq!(decode16(0xa001), Inst::Jal { rd: 0, offset: 0 });
+        // c.beqz a0, +16
+        assert_eq!(decode16(0xc901), Inst::Branch { offset: 16 });
+    }
+
+    #[test]
+    fn test_stack_usage() {
+        // Built from testdata/stack_example.rs
+        let usage = stack_usage(include_bytes!("testdata/stack_example.elf")).unwrap();
+
+        let leaf = usage.function("stack_example::leaf").unwrap();
+        assert_eq!(leaf.frame_size, 80);
+        let big_frame = usage.function("stack_example::big_frame").unwrap();
+        assert_eq!(big_frame.frame_size, 2032 + 2048 + 48);
+        assert!(big_frame.callees.contains(&leaf.addr));
+        assert!(
+            usage
+                .function("stack_example::indirect")
+                .unwrap()
+                .has_indirect_calls
+        );
+
+        assert_eq!(
+            usage.worst_case("entry"),
+            Some(StackDepth {
+                bytes: 32 + 2032 + 2048 + 48 + 80,
+                path: vec![
+                    "entry".into(),
+                    "stack_example::big_frame".into(),
+                    "stack_example::leaf".into(),
+                    "memset".into(),
+                    "compiler_builtins::mem::memset".into(),
+                ],
+                unknowns: vec![
+                    "stack_example::indirect: indirect call".into(),
+                    "stack_example::recursive: recursion".into(),
+                ],
+            })
+        );
+        assert_eq!(usage.worst_case("no_such_function"), None);
+    }
+
+    #[test]
+    fn test_check_limits() {
+        let usage = stack_usage(include_bytes!("testdata/stack_example.elf")).unwrap();
+        let allowed = [
+            "stack_example::indirect: indirect call",
+            "stack_example::recursive: recursion",
+        ];
+        let depth = 32 + 2032 + 2048 + 48 + 80;
+
+        usage.check_limits(&[("entry", depth)], &allowed).unwrap();
+        let err = usage
+            .check_limits(&[("entry", depth - 1)], &allowed)
+            .unwrap_err();
+        assert!(err.to_string().contains("exceeding the"), "{err}");
+        let err = usage
+            .check_limits(&[("entry", depth)], &allowed[..1])
+            .unwrap_err();
+        assert!(err.to_string().contains("recursive: recursion"), "{err}");
+        let err = usage
+            .check_limits(&[("no_such_function", depth)], &allowed)
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown symbol"), "{err}");
+    }
+}
@@ -0,0 +1,61 @@
+// Licensed under the Apache-2.0 license
+
+// Source of stack_example.elf, used by the stack_usage tests. Built with:
+//
+// rustc +1.70 --edition 2021 --target riscv32imc-unknown-none-elf -C opt-level=s \
+//     -C panic=abort -C link-arg=-Tstack_example.ld -o stack_example.elf stack_example.rs
+
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+
+#[inline(never)]
+fn leaf(x: u32) -> u32 {
+    let mut buf = [0u32; 16];
+    buf[x as usize & 15] = x;
+    black_box(&mut buf);
+    buf[3]
+}
+
+#[inline(never)]
+fn big_frame(x: u32) -> u32 {
+    let mut buf = [0u32; 1024];
+    buf[x as usize & 1023] = x;
+    black_box(&mut buf);
+    leaf(buf[7])
+}
+
+#[inline(never)]
+fn recursive(x: u32) -> u32 {
+    let mut buf = [0u32; 4];
+    buf[0] = x;
+    black_box(&mut buf);
+    if buf[1] == 0 {
+        return buf[2];
+    }
+    recursive(buf[3]) + 1
+}
+
+#[inline(never)]
+fn indirect(f: fn(u32) -> u32) -> u32 {
+    let mut buf = [0u32; 8];
+    buf[0] = f(3);
+    black_box(&mut buf);
+    buf[1]
+}
+
+#[no_mangle]
+pub extern "C" fn entry() -> ! {
+    let mut total = black_box(leaf(1));
+    total += big_frame(total);
+    total += recursive(total);
+    total += indirect(black_box(leaf));
+    black_box(total);
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
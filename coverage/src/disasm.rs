@@ -12,3 +12,39 @@ pub fn invoke_objdump(binary_path: &str) -> std::io::Result<String> {
 
     run_cmd_stdout(&mut cmd, None)
 }
+
+/// Length in bytes of the instruction whose first halfword is `lo`.
+pub fn instruction_len(lo: u16) -> usize {
+    if lo & 0b11 == 0b11 {
+        4
+    } else {
+        2
+    }
+}
+
+/// If `instruction` is a conditional branch (`beq`, `bne`, `blt`, `bge`,
+/// `bltu`, `bgeu`, `c.beqz` or `c.bnez`), the offset of its target from its
+/// own address. Compressed instructions are passed in the low halfword.
+pub fn conditional_branch_offset(instruction: u32) -> Option<i32> {
+    let bits = |hi: u32, lo: u32| (instruction >> lo) & ((1 << (hi - lo + 1)) - 1);
+    let sign_extend = |value: u32, len: u32| ((value << (32 - len)) as i32) >> (32 - len);
+
+    if instruction & 0b11 == 0b11 {
+        // B-type; funct3 010 and 011 are reserved
+        if bits(6, 0) != 0b110_0011 || matches!(bits(14, 12), 0b010 | 0b011) {
+            return None;
+        }
+        return Some(sign_extend(
+            bits(31, 31) << 12 | bits(30, 25) << 5 | bits(11, 8) << 1 | bits(7, 7) << 11,
+            13,
+        ));
+    }
+    // CB-type in quadrant 1
+    if bits(1, 0) != 0b01 || !matches!(bits(15, 13), 0b110 | 0b111) {
+        return None;
+    }
+    Some(sign_extend(
+        bits(12, 12) << 8 | bits(6, 5) << 6 | bits(2, 2) << 5 | bits(11, 10) << 3 | bits(4, 3) << 1,
+        9,
+    ))
+}
@@ -2,7 +2,7 @@
 
 use anyhow::Context;
 use bit_vec::BitVec;
-use caliptra_builder::{build_firmware_elf, FwId, SymbolType};
+use caliptra_builder::{build_firmware_elf, FwId};
 use elf::endian::AnyEndian;
 use elf::ElfBytes;
 use std::collections::hash_map::{DefaultHasher, Entry};
@@ -13,7 +13,11 @@ use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
 mod disasm;
+mod report;
+mod source_lines;
 pub use disasm::invoke_objdump;
+pub use report::{BranchCoverage, CoverageReport, FileCoverage, FunctionCoverage};
+pub use source_lines::SourceLines;
 
 pub const CPTRA_COVERAGE_PATH: &str = "CPTRA_COVERAGE_PATH";
 
@@ -31,7 +35,14 @@ impl CoverageMap {
                         e.insert(new_entry.1);
                     }
                     Entry::Occupied(mut e) => {
-                        e.get_mut().or(&new_entry.1);
+                        let (merged, mut new_bitmap) = (e.get_mut(), new_entry.1);
+                        // BitVec::or requires equal lengths
+                        if merged.len() < new_bitmap.len() {
+                            merged.grow(new_bitmap.len() - merged.len(), false);
+                        } else {
+                            new_bitmap.grow(merged.len() - new_bitmap.len(), false);
+                        }
+                        merged.or(&new_bitmap);
                     }
                 }
             }
@@ -77,27 +88,6 @@ pub fn dump_emu_coverage_to_file(
     writer.flush()?;
     Ok(())
 }
-pub fn uncovered_functions<'a>(elf_bytes: &'a [u8], bitmap: &'a BitVec) -> std::io::Result<()> {
-    let symbols = caliptra_builder::elf_symbols(elf_bytes)?;
-
-    let filter = symbols
-        .iter()
-        .filter(|sym| sym.ty == SymbolType::Func)
-        .filter(|function| {
-            let mut pc_range = function.value..function.value + function.size;
-            !pc_range.any(|pc| bitmap.get(pc as usize).unwrap_or(false))
-        });
-
-    for f in filter {
-        println!(
-            "not covered : (NAME:{})  (start:{}) (size:{})",
-            f.name, f.value, f.size
-        );
-    }
-
-    Ok(())
-}
-
 pub fn get_bitvec_paths(dir: &str) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
     let paths = std::fs::read_dir(dir)?
         // Filter out all those directory entries which couldn't be read
@@ -159,15 +149,8 @@ pub fn collect_instr_pcs(id: &FwId<'static>) -> anyhow::Result<Vec<u32>> {
         let instruction = &text_section[index..index + 2];
         let instruction = u16::from_le_bytes([instruction[0], instruction[1]]);
 
-        match instruction & 0b11 {
-            0 | 1 | 2 => {
-                index += 2;
-            }
-            _ => {
-                index += 4;
-            }
-        }
         instr_pcs.push(load_addr + index as u32);
+        index += disasm::instruction_len(instruction);
     }
     Ok(instr_pcs)
 }
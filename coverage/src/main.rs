@@ -1,17 +1,22 @@
 // Licensed under the Apache-2.0 license
 
+use anyhow::bail;
 use bit_vec::BitVec;
-use caliptra_builder::build_firmware_elf;
+use caliptra_builder::{build_firmware_elf_with_debug_info, build_firmware_rom, elf2rom};
 use caliptra_coverage::calculator;
 use caliptra_coverage::collect_instr_pcs;
 use caliptra_coverage::get_bitvec_paths;
 use caliptra_coverage::CoverageMap;
+use caliptra_coverage::CoverageReport;
 use caliptra_coverage::CPTRA_COVERAGE_PATH;
 
 use caliptra_builder::firmware::ROM_WITH_UART;
 use caliptra_coverage::get_tag_from_fw_id;
 use caliptra_coverage::invoke_objdump;
-use caliptra_coverage::uncovered_functions;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn highlight_covered_instructions_in_objdump_output(bitmap: &BitVec, output: String) {
     let mut is_disassembly = false;
@@ -40,7 +45,7 @@ pub fn highlight_covered_instructions_in_objdump_output(bitmap: &BitVec, output:
     }
 }
 
-fn main() -> std::io::Result<()> {
+fn main() -> anyhow::Result<()> {
     let cov_path = std::env::var(CPTRA_COVERAGE_PATH).unwrap_or_else(|_| "".into());
     if cov_path.is_empty() {
         return Ok(());
@@ -64,9 +69,45 @@ fn main() -> std::io::Result<()> {
         .get(&tag)
         .expect("Coverage data  not found for image");
 
-    let elf_bytes = build_firmware_elf(&ROM_WITH_UART)?;
+    let elf_bytes = build_firmware_elf_with_debug_info(&ROM_WITH_UART)?;
+    if elf2rom(&elf_bytes)? != build_firmware_rom(&ROM_WITH_UART)? {
+        bail!("ROM_WITH_UART built with debug info differs from the tested ROM");
+    }
+
+    let mut report = CoverageReport::new(&elf_bytes, bv)?;
+    report.relative_to(&Path::new(caliptra_builder::THIS_WORKSPACE_DIR).canonicalize()?);
+
+    println!("  instrs    branches  function");
+    for f in report.functions.iter() {
+        println!(
+            "{:>4}/{:<4} {:>4}/{:<4}  {} ({}:{})",
+            f.instructions_hit,
+            f.instructions,
+            f.branches_hit,
+            f.branches,
+            f.name,
+            f.file.as_deref().unwrap_or("?"),
+            f.line.unwrap_or(0),
+        );
+    }
+    let (lines_hit, lines) = report.line_totals();
+    let (branches_hit, branches) = report.branch_totals();
+    println!("Lines covered: {lines_hit}/{lines}, branches covered: {branches_hit}/{branches}");
+
+    let lcov_path = Path::new(&cov_path).join("lcov.info");
+    let mut lcov = BufWriter::new(File::create(&lcov_path)?);
+    report.write_lcov(&mut lcov)?;
+    lcov.flush()?;
+    println!("Wrote {}", lcov_path.display());
 
-    uncovered_functions(&elf_bytes, bv)?;
+    let cobertura_path = Path::new(&cov_path).join("cobertura.xml");
+    let mut cobertura = BufWriter::new(File::create(&cobertura_path)?);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    report.write_cobertura(&mut cobertura, timestamp)?;
+    cobertura.flush()?;
+    println!("Wrote {}", cobertura_path.display());
 
     println!(
         "Coverage for ROM_WITH_UART is {}%",
@@ -0,0 +1,481 @@
+// Licensed under the Apache-2.0 license
+
+use anyhow::Context;
+use bit_vec::BitVec;
+use elf::abi::{SHF_EXECINSTR, SHT_PROGBITS, STT_FUNC};
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+use crate::disasm::{conditional_branch_offset, instruction_len};
+use crate::source_lines::SourceLines;
+
+/// A conditional branch instruction. The emulator only records which
+/// instructions executed, so a direction counts as taken when the
+/// instruction it leads to executed; this can overcount when that
+/// instruction is also reachable some other way.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BranchCoverage {
+    pub pc: u64,
+    pub executed: bool,
+    pub taken: bool,
+    pub not_taken: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FileCoverage {
+    /// Whether any instruction generated from each line executed.
+    pub lines: BTreeMap<u32, bool>,
+
+    /// Conditional branches generated from each line, in address order.
+    pub branches: BTreeMap<u32, Vec<BranchCoverage>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionCoverage {
+    /// Demangled name, without the hash.
+    pub name: String,
+    pub addr: u64,
+    pub size: u64,
+
+    /// The function's source file and first line, if it has debug info.
+    pub file: Option<String>,
+    pub line: Option<u32>,
+
+    pub instructions: u32,
+    pub instructions_hit: u32,
+
+    /// Branch directions (two per conditional branch) and how many of them
+    /// were taken.
+    pub branches: u32,
+    pub branches_hit: u32,
+}
+
+/// Instruction coverage of a firmware image mapped to source lines and
+/// functions.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CoverageReport {
+    /// Keyed by source path.
+    pub files: BTreeMap<String, FileCoverage>,
+
+    /// Sorted by address.
+    pub functions: Vec<FunctionCoverage>,
+}
+
+impl CoverageReport {
+    /// Build a report from `bitmap`, the PCs executed by the firmware in
+    /// `elf_bytes` (see [`crate::CoverageMap`]). Source lines come from the
+    /// ELF's DWARF debug info; without it, only the function summary is
+    /// populated.
+    pub fn new(elf_bytes: &[u8], bitmap: &BitVec) -> anyhow::Result<Self> {
+        let elf_file = ElfBytes::<AnyEndian>::minimal_parse(elf_bytes)
+            .with_context(|| "Failed to parse elf file")?;
+        let source_lines = SourceLines::new(elf_bytes)?;
+        let hit = |pc: u64| bitmap.get(pc as usize).unwrap_or(false);
+
+        let mut functions = vec![];
+        let mut seen = HashSet::new();
+        if let Some((symbols, strings)) = elf_file
+            .symbol_table()
+            .with_context(|| "Failed to read symbol table")?
+        {
+            for sym in symbols.iter() {
+                if sym.st_symtype() != STT_FUNC || sym.st_size == 0 || !seen.insert(sym.st_value) {
+                    continue;
+                }
+                let name = strings
+                    .get(sym.st_name as usize)
+                    .with_context(|| "Failed to read symbol name")?;
+                functions.push(FunctionCoverage {
+                    name: format!("{:#}", rustc_demangle::demangle(name)),
+                    addr: sym.st_value,
+                    size: sym.st_size,
+                    file: None,
+                    line: None,
+                    instructions: 0,
+                    instructions_hit: 0,
+                    branches: 0,
+                    branches_hit: 0,
+                });
+            }
+        }
+        functions.sort_by_key(|f| f.addr);
+
+        let mut files = BTreeMap::<String, FileCoverage>::new();
+        let sections = elf_file
+            .section_headers()
+            .with_context(|| "ELF file has no section headers")?;
+        for section in sections.iter() {
+            if section.sh_type != SHT_PROGBITS || section.sh_flags & u64::from(SHF_EXECINSTR) == 0 {
+                continue;
+            }
+            let (data, _) = elf_file
+                .section_data(&section)
+                .with_context(|| "Failed to read executable section")?;
+            let mut offset = 0;
+            while offset + 2 <= data.len() {
+                let pc = section.sh_addr + offset as u64;
+                let lo = u16::from_le_bytes([data[offset], data[offset + 1]]);
+                let len = instruction_len(lo);
+                let Some(bytes) = data.get(offset..offset + len) else {
+                    break;
+                };
+                offset += len;
+                let instruction = bytes
+                    .iter()
+                    .rev()
+                    .fold(0_u32, |acc, &b| (acc << 8) | u32::from(b));
+
+                let executed = hit(pc);
+                let branch = conditional_branch_offset(instruction).map(|branch_offset| {
+                    let target = pc.wrapping_add(branch_offset as i64 as u64) & 0xffff_ffff;
+                    BranchCoverage {
+                        pc,
+                        executed,
+                        taken: executed && hit(target),
+                        not_taken: executed && hit(pc + len as u64),
+                    }
+                });
+
+                let i = functions.partition_point(|f| f.addr <= pc);
+                if let Some(function) = i
+                    .checked_sub(1)
+                    .map(|i| &mut functions[i])
+                    .filter(|f| pc < f.addr + f.size)
+                {
+                    function.instructions += 1;
+                    function.instructions_hit += u32::from(executed);
+                    if let Some(branch) = &branch {
+                        function.branches += 2;
+                        function.branches_hit +=
+                            u32::from(branch.taken) + u32::from(branch.not_taken);
+                    }
+                    if let Some((file, line)) = source_lines.lookup(pc) {
+                        // The first line of the function is the lowest one
+                        // in the file of its first instruction.
+                        if function.file.is_none() {
+                            function.file = Some(file.into());
+                        }
+                        if function.file.as_deref() == Some(file) {
+                            function.line = Some(function.line.map_or(line, |l| l.min(line)));
+                        }
+                    }
+                }
+
+                if let Some((file, line)) = source_lines.lookup(pc) {
+                    let file_coverage = files.entry(file.into()).or_default();
+                    *file_coverage.lines.entry(line).or_default() |= executed;
+                    if let Some(branch) = branch {
+                        file_coverage.branches.entry(line).or_default().push(branch);
+                    }
+                }
+            }
+        }
+        Ok(Self { files, functions })
+    }
+
+    /// Drop the files that aren't under `root`, and make the paths of the
+    /// rest relative to it.
+    pub fn relative_to(&mut self, root: &Path) {
+        let relative = |path: &str| {
+            Path::new(path)
+                .strip_prefix(root)
+                .ok()
+                .map(|p| p.to_string_lossy().into_owned())
+        };
+        self.files = std::mem::take(&mut self.files)
+            .into_iter()
+            .filter_map(|(path, coverage)| Some((relative(&path)?, coverage)))
+            .collect();
+        for function in self.functions.iter_mut() {
+            function.file = function.file.as_deref().and_then(relative);
+        }
+    }
+
+    /// Lines hit and lines with instructions, across all files.
+    pub fn line_totals(&self) -> (usize, usize) {
+        let lines = self.files.values().flat_map(|f| f.lines.values());
+        (lines.clone().filter(|&&hit| hit).count(), lines.count())
+    }
+
+    /// Branch directions taken and branch directions, across all files.
+    pub fn branch_totals(&self) -> (usize, usize) {
+        let (mut hit, mut total) = (0, 0);
+        for branches in self.files.values().flat_map(|f| f.branches.values()) {
+            let (h, t) = count_branches(branches);
+            hit += h;
+            total += t;
+        }
+        (hit, total)
+    }
+
+    /// Write the report as an LCOV tracefile.
+    pub fn write_lcov(&self, w: &mut impl Write) -> std::io::Result<()> {
+        for (path, file) in &self.files {
+            writeln!(w, "TN:")?;
+            writeln!(w, "SF:{path}")?;
+            let functions: Vec<_> = self
+                .functions
+                .iter()
+                .filter(|f| f.file.as_deref() == Some(path.as_str()))
+                .collect();
+            for f in &functions {
+                writeln!(w, "FN:{},{}", f.line.unwrap_or(0), f.name)?;
+            }
+            for f in &functions {
+                writeln!(w, "FNDA:{},{}", u32::from(f.instructions_hit > 0), f.name)?;
+            }
+            writeln!(w, "FNF:{}", functions.len())?;
+            writeln!(
+                w,
+                "FNH:{}",
+                functions.iter().filter(|f| f.instructions_hit > 0).count()
+            )?;
+            let (mut branches_hit, mut branches) = (0, 0);
+            for (line, line_branches) in &file.branches {
+                for (block, branch) in line_branches.iter().enumerate() {
+                    for (direction, taken) in [branch.taken, branch.not_taken].iter().enumerate() {
+                        let taken = if branch.executed {
+                            u32::from(*taken).to_string()
+                        } else {
+                            "-".into()
+                        };
+                        writeln!(w, "BRDA:{line},{block},{direction},{taken}")?;
+                    }
+                }
+                let (h, t) = count_branches(line_branches);
+                branches_hit += h;
+                branches += t;
+            }
+            writeln!(w, "BRF:{branches}")?;
+            writeln!(w, "BRH:{branches_hit}")?;
+            for (line, hit) in &file.lines {
+                writeln!(w, "DA:{line},{}", u32::from(*hit))?;
+            }
+            writeln!(w, "LF:{}", file.lines.len())?;
+            writeln!(w, "LH:{}", file.lines.values().filter(|&&hit| hit).count())?;
+            writeln!(w, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    /// Write the report as Cobertura XML, with one package per directory and
+    /// one class per source file.
+    pub fn write_cobertura(&self, w: &mut impl Write, timestamp: u64) -> std::io::Result<()> {
+        let mut packages = BTreeMap::<String, Vec<(&str, &FileCoverage)>>::new();
+        for (path, file) in &self.files {
+            let dir = Path::new(path)
+                .parent()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            packages.entry(dir).or_default().push((path, file));
+        }
+
+        let (lines_hit, lines) = self.line_totals();
+        let (branches_hit, branches) = self.branch_totals();
+        writeln!(w, r#"<?xml version="1.0" ?>"#)?;
+        writeln!(
+            w,
+            r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+        )?;
+        writeln!(
+            w,
+            r#"<coverage line-rate="{}" branch-rate="{}" lines-covered="{lines_hit}" lines-valid="{lines}" branches-covered="{branches_hit}" branches-valid="{branches}" complexity="0" version="{}" timestamp="{timestamp}">"#,
+            rate(lines_hit, lines),
+            rate(branches_hit, branches),
+            env!("CARGO_PKG_VERSION"),
+        )?;
+        writeln!(w, "  <sources>")?;
+        writeln!(w, "    <source>.</source>")?;
+        writeln!(w, "  </sources>")?;
+        writeln!(w, "  <packages>")?;
+        for (dir, files) in &packages {
+            let lines = files.iter().flat_map(|(_, f)| f.lines.values());
+            let (lines_hit, lines) = (lines.clone().filter(|&&hit| hit).count(), lines.count());
+            let (mut branches_hit, mut branches) = (0, 0);
+            for branch in files.iter().flat_map(|(_, f)| f.branches.values()) {
+                let (h, t) = count_branches(branch);
+                branches_hit += h;
+                branches += t;
+            }
+            writeln!(
+                w,
+                r#"    <package name="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+                xml_escape(dir),
+                rate(lines_hit, lines),
+                rate(branches_hit, branches),
+            )?;
+            writeln!(w, "      <classes>")?;
+            for (path, file) in files {
+                let file_lines_hit = file.lines.values().filter(|&&hit| hit).count();
+                let (file_branches_hit, file_branches) = file
+                    .branches
+                    .values()
+                    .map(|b| count_branches(b))
+                    .fold((0, 0), |(h, t), (bh, bt)| (h + bh, t + bt));
+                let name = Path::new(path)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                writeln!(
+                    w,
+                    r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+                    xml_escape(&name),
+                    xml_escape(path),
+                    rate(file_lines_hit, file.lines.len()),
+                    rate(file_branches_hit, file_branches),
+                )?;
+                writeln!(w, "          <methods/>")?;
+                writeln!(w, "          <lines>")?;
+                for (line, hit) in &file.lines {
+                    let hits = u32::from(*hit);
+                    match file.branches.get(line) {
+                        Some(line_branches) => {
+                            let (h, t) = count_branches(line_branches);
+                            writeln!(
+                                w,
+                                r#"            <line number="{line}" hits="{hits}" branch="true" condition-coverage="{}% ({h}/{t})"/>"#,
+                                h * 100 / t
+                            )?;
+                        }
+                        None => writeln!(
+                            w,
+                            r#"            <line number="{line}" hits="{hits}" branch="false"/>"#
+                        )?,
+                    }
+                }
+                writeln!(w, "          </lines>")?;
+                writeln!(w, "        </class>")?;
+            }
+            writeln!(w, "      </classes>")?;
+            writeln!(w, "    </package>")?;
+        }
+        writeln!(w, "  </packages>")?;
+        writeln!(w, "</coverage>")?;
+        Ok(())
+    }
+}
+
+fn count_branches(branches: &[BranchCoverage]) -> (usize, usize) {
+    let hit = branches
+        .iter()
+        .map(|b| usize::from(b.taken) + usize::from(b.not_taken))
+        .sum();
+    (hit, branches.len() * 2)
+}
+
+fn rate(hit: usize, total: usize) -> String {
+    if total == 0 {
+        return "1".into();
+    }
+    format!("{:.4}", hit as f64 / total as f64)
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+fn example_report() -> CoverageReport {
+    // PCs executed by entry() in testdata/branch_example.rs, which calls
+    // classify(5) and skips never_called().
+    let mut bitmap = BitVec::from_elem(0x100, false);
+    for pc in [
+        0x0, 0x2, 0x6, 0x14, 0x16, 0x18, 0x1a, 0x1c, 0x1e, 0x2a, 0x2c, 0x2e, 0x30, 0x32, 0x34,
+        0x36, 0x3a, 0x3e, 0x40, 0x42, 0x44, 0x48, 0x54,
+    ] {
+        bitmap.set(pc, true);
+    }
+    CoverageReport::new(include_bytes!("testdata/branch_example.elf"), &bitmap).unwrap()
+}
+
+#[test]
+fn test_coverage_report() {
+    let mut report = example_report();
+    report.relative_to(Path::new("coverage/src/testdata"));
+    assert_eq!(
+        report.files.keys().collect::<Vec<_>>(),
+        vec!["branch_example.rs"]
+    );
+
+    let file = &report.files["branch_example.rs"];
+    assert_eq!(file.lines.get(&16), Some(&true));
+    assert_eq!(file.lines.get(&19), Some(&true));
+    assert_eq!(file.lines.get(&25), Some(&false));
+    assert_eq!(file.lines.get(&30), Some(&true));
+    assert_eq!(file.lines.get(&31), Some(&false));
+    assert_eq!(
+        file.branches.get(&16),
+        Some(&vec![BranchCoverage {
+            pc: 0x6,
+            executed: true,
+            taken: true,
+            not_taken: false,
+        }])
+    );
+    assert_eq!(report.branch_totals(), (2, 4));
+
+    let classify = &report.functions[0];
+    assert_eq!(classify.name, "branch_example::classify");
+    assert_eq!(classify.file.as_deref(), Some("branch_example.rs"));
+    assert_eq!(classify.line, Some(15));
+    assert_eq!((classify.instructions_hit, classify.instructions), (9, 14));
+    assert_eq!((classify.branches_hit, classify.branches), (1, 2));
+    let never_called = &report.functions[1];
+    assert_eq!(never_called.name, "branch_example::never_called");
+    assert_eq!(never_called.instructions_hit, 0);
+}
+
+#[test]
+fn test_write_lcov() {
+    let mut report = example_report();
+    report.relative_to(Path::new("coverage/src/testdata"));
+    let mut lcov = vec![];
+    report.write_lcov(&mut lcov).unwrap();
+    let lcov = String::from_utf8(lcov).unwrap();
+
+    assert!(lcov.starts_with("TN:\nSF:branch_example.rs\n"));
+    assert!(lcov.contains("FN:15,branch_example::classify\n"));
+    assert!(lcov.contains("FNDA:0,branch_example::never_called\n"));
+    assert!(lcov.contains("BRDA:16,0,0,1\nBRDA:16,0,1,0\n"));
+    assert!(lcov.contains("DA:31,0\n"));
+    assert!(lcov.ends_with("end_of_record\n"));
+}
+
+#[test]
+fn test_write_cobertura() {
+    let mut report = example_report();
+    report.relative_to(Path::new("coverage/src/testdata"));
+    let mut xml = vec![];
+    report.write_cobertura(&mut xml, 1234).unwrap();
+    let xml = String::from_utf8(xml).unwrap();
+
+    assert!(xml.contains(r#"branches-covered="2" branches-valid="4""#));
+    assert!(xml.contains(r#"timestamp="1234""#));
+    assert!(xml.contains(r#"<class name="branch_example.rs" filename="branch_example.rs""#));
+    assert!(xml
+        .contains(r#"<line number="16" hits="1" branch="true" condition-coverage="50% (1/2)"/>"#));
+    assert!(xml.contains(r#"<line number="31" hits="0" branch="false"/>"#));
+    assert!(xml.ends_with("</coverage>\n"));
+}
+
+#[test]
+fn test_xml_escape() {
+    assert_eq!(
+        xml_escape("<&str as core::fmt::Debug>"),
+        "&lt;&amp;str as core::fmt::Debug&gt;"
+    );
+}
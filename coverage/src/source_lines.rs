@@ -0,0 +1,165 @@
+// Licensed under the Apache-2.0 license
+
+use anyhow::Context;
+use elf::endian::AnyEndian;
+use elf::ElfBytes;
+use gimli::{EndianSlice, RunTimeEndian};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+type Reader<'a> = EndianSlice<'a, RunTimeEndian>;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct LineRange {
+    start: u64,
+    end: u64,
+    file: usize,
+    line: u32,
+}
+
+/// Maps instruction addresses to source lines, using the DWARF line tables
+/// of an ELF file. Firmware must be built with debug info for this to find
+/// anything.
+pub struct SourceLines {
+    files: Vec<String>,
+
+    // Sorted by start address
+    ranges: Vec<LineRange>,
+}
+
+impl SourceLines {
+    pub fn new(elf_bytes: &[u8]) -> anyhow::Result<Self> {
+        let elf_file = ElfBytes::<AnyEndian>::minimal_parse(elf_bytes)
+            .with_context(|| "Failed to parse elf file")?;
+        let endian = match elf_file.ehdr.endianness {
+            AnyEndian::Little => RunTimeEndian::Little,
+            AnyEndian::Big => RunTimeEndian::Big,
+        };
+        let dwarf = gimli::Dwarf::load(|id| -> anyhow::Result<Reader> {
+            let Some(section) = elf_file
+                .section_header_by_name(id.name())
+                .with_context(|| format!("Failed to find {} section", id.name()))?
+            else {
+                return Ok(EndianSlice::new(&[], endian));
+            };
+            let (data, compression) = elf_file
+                .section_data(&section)
+                .with_context(|| format!("Failed to read {} section", id.name()))?;
+            if compression.is_some() {
+                anyhow::bail!("Compressed {} section is not supported", id.name());
+            }
+            Ok(EndianSlice::new(data, endian))
+        })?;
+
+        // The linker relocates the line tables of functions it discarded to
+        // address 0, so a sequence there is only real if a function of the
+        // same size is at address 0.
+        let mut zero_func_sizes = vec![];
+        if let Some((symbols, _)) = elf_file
+            .symbol_table()
+            .with_context(|| "Failed to read symbol table")?
+        {
+            zero_func_sizes.extend(
+                symbols
+                    .iter()
+                    .filter(|sym| sym.st_symtype() == elf::abi::STT_FUNC && sym.st_value == 0)
+                    .map(|sym| sym.st_size),
+            );
+        }
+
+        let mut result = Self {
+            files: vec![],
+            ranges: vec![],
+        };
+        let mut file_indices = HashMap::<String, usize>::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            let mut rows = program.rows();
+            let mut prev: Option<(u64, Option<usize>, u32)> = None;
+            let mut sequence = vec![];
+            while let Some((header, row)) = rows.next_row()? {
+                if let Some((start, Some(file), line)) = prev.take() {
+                    if row.address() > start {
+                        sequence.push(LineRange {
+                            start,
+                            end: row.address(),
+                            file,
+                            line,
+                        });
+                    }
+                }
+                if row.end_sequence() {
+                    if let (Some(first), Some(last)) = (sequence.first(), sequence.last()) {
+                        if first.start != 0 || zero_func_sizes.contains(&last.end) {
+                            result.ranges.append(&mut sequence);
+                        }
+                    }
+                    sequence.clear();
+                    continue;
+                }
+                let Some(line) = row.line() else {
+                    // Not attributable to any line
+                    prev = Some((row.address(), None, 0));
+                    continue;
+                };
+                let file = match row.file(header) {
+                    Some(file) => {
+                        let path = file_path(&dwarf, &unit, header, file)?;
+                        let next_index = file_indices.len();
+                        let index = *file_indices.entry(path.clone()).or_insert(next_index);
+                        if index == result.files.len() {
+                            result.files.push(path);
+                        }
+                        Some(index)
+                    }
+                    None => None,
+                };
+                prev = Some((row.address(), file, line.get() as u32));
+            }
+        }
+        result.ranges.sort_by_key(|r| r.start);
+        Ok(result)
+    }
+
+    /// The source file and line of the instruction at `addr`.
+    pub fn lookup(&self, addr: u64) -> Option<(&str, u32)> {
+        let i = self.ranges.partition_point(|r| r.start <= addr);
+        let range = self.ranges.get(i.checked_sub(1)?)?;
+        if addr < range.end {
+            Some((&self.files[range.file], range.line))
+        } else {
+            None
+        }
+    }
+}
+
+fn file_path(
+    dwarf: &gimli::Dwarf<Reader>,
+    unit: &gimli::Unit<Reader>,
+    header: &gimli::LineProgramHeader<Reader>,
+    file: &gimli::FileEntry<Reader>,
+) -> anyhow::Result<String> {
+    let mut path = PathBuf::new();
+    let comp_dir = unit.comp_dir.map(|dir| dir.to_string_lossy().into_owned());
+    if let Some(comp_dir) = &comp_dir {
+        path.push(comp_dir);
+    }
+    if let Some(dir) = file.directory(header) {
+        let dir = dwarf.attr_string(unit, dir)?.to_string_lossy().into_owned();
+        // In DWARF 5, directory 0 is the compilation directory
+        if Some(&dir) != comp_dir.as_ref() {
+            path.push(dir);
+        }
+    }
+    path.push(
+        dwarf
+            .attr_string(unit, file.path_name())?
+            .to_string_lossy()
+            .as_ref(),
+    );
+    Ok(path.to_string_lossy().into_owned())
+}
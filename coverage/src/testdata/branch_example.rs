@@ -0,0 +1,39 @@
+// Licensed under the Apache-2.0 license
+
+// Source of branch_example.elf, used by the coverage report tests. Built with:
+//
+// rustc +1.70 --edition 2021 --target riscv32imc-unknown-none-elf -C opt-level=s \
+//     -C panic=abort -C debuginfo=1 --remap-path-prefix=$PWD=coverage/src/testdata \
+//     -C link-arg=-Tbranch_example.ld -o branch_example.elf branch_example.rs
+
+#![no_std]
+#![no_main]
+
+use core::hint::black_box;
+
+#[inline(never)]
+fn classify(x: u32) -> u32 {
+    if x > 100 {
+        return black_box(3);
+    }
+    black_box(x & 1)
+}
+
+#[inline(never)]
+fn never_called(x: u32) -> u32 {
+    black_box(x) * 7
+}
+
+#[no_mangle]
+pub extern "C" fn entry() -> ! {
+    let x = classify(black_box(5));
+    if black_box(x) == 42 {
+        never_called(x);
+    }
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
@@ -137,6 +137,7 @@ pub enum WarmResetEntry4 {
     ManifestAddr = 2,
     RtMinSvn = 3,
     RomUpdateResetStatus = 4,
+    RtBootFailureCount = 5,
 }
 
 impl From<WarmResetEntry4> for u8 {
@@ -481,6 +482,27 @@ impl DataVault {
         self.read_warm_reset_entry4(WarmResetEntry4::RtEntryPoint)
     }
 
+    /// Set the rt boot failure count.
+    ///
+    /// The entry is left unlocked so that FMC can count runtime boot
+    /// attempts and runtime can clear the count once it is up.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - rt boot failure count
+    pub fn set_rt_boot_failure_count(&mut self, count: u32) {
+        self.write_warm_reset_entry4(WarmResetEntry4::RtBootFailureCount, count);
+    }
+
+    /// Get the rt boot failure count.
+    ///
+    /// # Returns
+    ///
+    /// * rt boot failure count
+    pub fn rt_boot_failure_count(&self) -> u32 {
+        self.read_warm_reset_entry4(WarmResetEntry4::RtBootFailureCount)
+    }
+
     /// Set the manifest address.
     ///
     /// # Arguments
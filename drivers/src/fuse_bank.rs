@@ -275,6 +275,36 @@ impl FuseBank<'_> {
         first_set_msbit(&soc_ifc_regs.fuse_runtime_svn().read())
     }
 
+    /// Get the fmc fuse value encoding a security version number.
+    ///
+    /// SVN fuses are programmed as a bit count, so the value only ever sets
+    /// additional bits when moving to a higher SVN.
+    ///
+    /// # Arguments
+    /// * `svn` - fmc security version number
+    ///
+    /// # Returns
+    ///     fuse_fmc_key_manifest_svn value
+    ///
+    pub fn fmc_svn_fuse_value(svn: u32) -> u32 {
+        u32::MAX.checked_shr(32 - svn.min(32)).unwrap_or(0)
+    }
+
+    /// Get the runtime fuse value encoding a security version number.
+    ///
+    /// # Arguments
+    /// * `svn` - runtime security version number
+    ///
+    /// # Returns
+    ///     fuse_runtime_svn value
+    ///
+    pub fn runtime_svn_fuse_value(svn: u32) -> [u32; 4] {
+        let fuse = u128::MAX.checked_shr(128 - svn.min(128)).unwrap_or(0);
+        let mut result = [0u32; 4];
+        result.as_bytes_mut().copy_from_slice(&fuse.to_le_bytes());
+        result
+    }
+
     /// Get the lms revocation bits.
     ///
     /// # Arguments
@@ -327,4 +357,17 @@ mod tests {
             svn = (svn << 1) | 1;
         }
     }
+
+    #[test]
+    fn test_svn_fuse_value() {
+        assert_eq!(FuseBank::fmc_svn_fuse_value(0), 0);
+        assert_eq!(FuseBank::fmc_svn_fuse_value(3), 0b111);
+        assert_eq!(FuseBank::fmc_svn_fuse_value(32), u32::MAX);
+        assert_eq!(FuseBank::fmc_svn_fuse_value(33), u32::MAX);
+
+        for i in 0..=128 {
+            assert_eq!(first_set_msbit(&FuseBank::runtime_svn_fuse_value(i)), i);
+        }
+        assert_eq!(FuseBank::runtime_svn_fuse_value(33), [u32::MAX, 1, 0, 0]);
+    }
 }
@@ -16,7 +16,7 @@ Abstract:
 use crate::FirmwareHandoffTable;
 
 #[cfg(test)]
-use caliptra_image_types::ImageManifest;
+use caliptra_image_types::{ImageManifest, ImageTocEntry, MAX_SOC_TOC_ENTRY_COUNT};
 
 //
 // Memory Addresses
@@ -38,7 +38,8 @@ pub const PCR_LOG_ORG: u32 = 0x50004800;
 pub const MEASUREMENT_LOG_ORG: u32 = 0x50004C00;
 pub const FUSE_LOG_ORG: u32 = 0x50005000;
 pub const DPE_ORG: u32 = 0x50005400;
-pub const DATA_ORG: u32 = 0x50006400;
+pub const SOC_TOC_ORG: u32 = 0x50006400;
+pub const DATA_ORG: u32 = 0x50006800;
 pub const STACK_ORG: u32 = 0x5001A000;
 pub const ROM_STACK_ORG: u32 = 0x5001C000;
 pub const ESTACK_ORG: u32 = 0x5001F800;
@@ -63,7 +64,8 @@ pub const PCR_LOG_SIZE: u32 = 1024;
 pub const MEASUREMENT_LOG_SIZE: u32 = 1024;
 pub const FUSE_LOG_SIZE: u32 = 1024;
 pub const DPE_SIZE: u32 = 4 * 1024;
-pub const DATA_SIZE: u32 = 79 * 1024;
+pub const SOC_TOC_SIZE: u32 = 1024;
+pub const DATA_SIZE: u32 = 78 * 1024;
 pub const STACK_SIZE: u32 = 22 * 1024;
 pub const ROM_STACK_SIZE: u32 = 14 * 1024;
 pub const ESTACK_SIZE: u32 = 1024;
@@ -130,7 +132,17 @@ fn mem_layout_test_fuselog() {
 #[test]
 #[allow(clippy::assertions_on_constants)]
 fn mem_layout_test_dpe() {
-    assert_eq!((DATA_ORG - DPE_ORG), DPE_SIZE);
+    assert_eq!((SOC_TOC_ORG - DPE_ORG), DPE_SIZE);
+}
+
+#[test]
+#[allow(clippy::assertions_on_constants)]
+fn mem_layout_test_soc_toc() {
+    assert!(
+        SOC_TOC_SIZE as usize
+            >= MAX_SOC_TOC_ENTRY_COUNT as usize * core::mem::size_of::<ImageTocEntry>()
+    );
+    assert_eq!((DATA_ORG - SOC_TOC_ORG), SOC_TOC_SIZE);
 }
 
 #[test]
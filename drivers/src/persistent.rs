@@ -2,7 +2,7 @@
 
 use core::{marker::PhantomData, mem::size_of, ptr::addr_of};
 
-use caliptra_image_types::ImageManifest;
+use caliptra_image_types::{ImageManifest, ImageTocEntry, MAX_SOC_TOC_ENTRY_COUNT};
 #[cfg(feature = "runtime")]
 use dpe::{DpeInstance, U8Bool, MAX_HANDLES};
 use zerocopy::{AsBytes, FromBytes};
@@ -22,6 +22,7 @@ pub const MEASUREMENT_MAX_COUNT: usize = 8;
 pub type PcrLogArray = [PcrLogEntry; PCR_LOG_MAX_COUNT];
 pub type FuseLogArray = [FuseLogEntry; FUSE_LOG_MAX_COUNT];
 pub type StashMeasurementArray = [MeasurementLogEntry; MEASUREMENT_MAX_COUNT];
+pub type SocTocArray = [ImageTocEntry; MAX_SOC_TOC_ENTRY_COUNT as usize];
 
 #[derive(FromBytes, AsBytes, Zeroize)]
 #[repr(C)]
@@ -64,6 +65,9 @@ pub struct PersistentData {
         - size_of::<U8Bool>() * MAX_HANDLES],
     #[cfg(not(feature = "runtime"))]
     dpe: [u8; memory_layout::DPE_SIZE as usize],
+
+    pub soc_toc: SocTocArray,
+    reserved7: [u8; memory_layout::SOC_TOC_SIZE as usize - size_of::<SocTocArray>()],
 }
 impl PersistentData {
     pub fn assert_matches_layout() {
@@ -83,9 +87,10 @@ impl PersistentData {
             );
             assert_eq!(addr_of!((*P).fuse_log) as u32, memory_layout::FUSE_LOG_ORG);
             assert_eq!(addr_of!((*P).dpe) as u32, memory_layout::DPE_ORG);
+            assert_eq!(addr_of!((*P).soc_toc) as u32, memory_layout::SOC_TOC_ORG);
             assert_eq!(
                 P.add(1) as u32,
-                memory_layout::DPE_ORG + memory_layout::DPE_SIZE
+                memory_layout::SOC_TOC_ORG + memory_layout::SOC_TOC_SIZE
             );
         }
     }
@@ -0,0 +1,275 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    spi_host.rs
+
+Abstract:
+
+    File contains API for the SPI host controller and SPI NOR flash.
+
+--*/
+
+use crate::{wait, CaliptraError, CaliptraResult};
+use caliptra_registers::spi_host::SpiHostReg;
+
+/// Maximum number of bytes in a single command segment
+const MAX_SEGMENT_LEN: usize = 512;
+
+/// Command segment directions
+const DIRECTION_RX: u32 = 1;
+const DIRECTION_TX: u32 = 2;
+
+/// Offset of the TXDATA register
+const TXDATA_OFFSET: usize = 0x2c;
+
+pub struct SpiHost {
+    spi: SpiHostReg,
+}
+
+impl SpiHost {
+    /// Number of chip-select lines
+    pub const NUM_CS: u32 = 2;
+
+    pub fn new(spi: SpiHostReg) -> Self {
+        Self { spi }
+    }
+
+    /// Reset and enable the SPI host
+    ///
+    /// # Arguments
+    ///
+    /// * `clkdiv` - Core clock divider; SPI clock period is `2 * (clkdiv + 1)` core clocks
+    pub fn init(&mut self, clkdiv: u32) {
+        let spi = self.spi.regs_mut();
+
+        // Reset the controller and wait for the FIFOs to drain
+        spi.control().write(|w| w.sw_rst(true));
+        wait::until(|| {
+            let status = spi.status().read();
+            status.txqd() == 0 && status.rxqd() == 0
+        });
+        spi.control().write(|w| w.sw_rst(false));
+
+        for csid in 0..Self::NUM_CS as usize {
+            spi.configopts().at(csid).write(|w| w.clkdiv(clkdiv));
+        }
+        spi.control().write(|w| w.spien(true).output_en(true));
+    }
+
+    /// Transmit `tx` and then receive into `rx` in a single transaction,
+    /// holding chip-select asserted throughout.
+    ///
+    /// # Arguments
+    ///
+    /// * `csid` - Chip-select line of the device
+    /// * `tx` - Bytes to transmit
+    /// * `rx` - Buffer to receive into
+    pub fn transfer(&mut self, csid: u32, tx: &[u8], rx: &mut [u8]) -> CaliptraResult<()> {
+        if csid >= Self::NUM_CS {
+            return Err(CaliptraError::DRIVER_SPI_HOST_INVALID_CSID);
+        }
+        let spi = self.spi.regs_mut();
+        wait::until(|| spi.status().read().ready());
+        spi.csid().write(|_| csid);
+
+        let mut tx_segments = tx.chunks(MAX_SEGMENT_LEN).peekable();
+        while let Some(segment) = tx_segments.next() {
+            let last = tx_segments.peek().is_none() && rx.is_empty();
+            self.command(DIRECTION_TX, segment.len(), !last);
+            self.write_tx_fifo(segment);
+        }
+
+        let mut rx_segments = rx.chunks_mut(MAX_SEGMENT_LEN).peekable();
+        while let Some(segment) = rx_segments.next() {
+            let last = rx_segments.peek().is_none();
+            self.command(DIRECTION_RX, segment.len(), !last);
+            self.read_rx_fifo(segment);
+        }
+
+        let spi = self.spi.regs_mut();
+        wait::until(|| {
+            let status = spi.status().read();
+            status.ready() && !status.active()
+        });
+        self.check_errors()
+    }
+
+    /// Issue a command segment
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - Segment direction
+    /// * `len` - Number of bytes in the segment
+    /// * `csaat` - Keep chip-select asserted after the segment
+    fn command(&mut self, direction: u32, len: usize, csaat: bool) {
+        let spi = self.spi.regs_mut();
+        wait::until(|| spi.status().read().ready());
+        spi.command().write(|w| {
+            w.len(len as u32 - 1)
+                .csaat(csaat)
+                .speed(0)
+                .direction(direction)
+        });
+    }
+
+    fn write_tx_fifo(&mut self, data: &[u8]) {
+        let spi = self.spi.regs_mut();
+        let mut words = data.chunks_exact(4);
+        for word in &mut words {
+            wait::until(|| !spi.status().read().txfull());
+            spi.txdata()
+                .write(|_| u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        }
+        for byte in words.remainder() {
+            wait::until(|| !spi.status().read().txfull());
+            // A byte-wide write pushes a single byte into the FIFO.
+            unsafe {
+                core::ptr::write_volatile((SpiHostReg::PTR as *mut u8).add(TXDATA_OFFSET), *byte)
+            };
+        }
+    }
+
+    fn read_rx_fifo(&mut self, data: &mut [u8]) {
+        let spi = self.spi.regs_mut();
+        for chunk in data.chunks_mut(4) {
+            wait::until(|| !spi.status().read().rxempty());
+            let word = spi.rxdata().read().to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+    }
+
+    fn check_errors(&mut self) -> CaliptraResult<()> {
+        let spi = self.spi.regs_mut();
+        let errors = u32::from(spi.error_status().read());
+        if errors != 0 {
+            spi.error_status().write(|_| errors.into());
+            spi.interrupt_state().write(|w| w.error(true));
+            return Err(CaliptraError::DRIVER_SPI_HOST_TRANSFER_FAILURE);
+        }
+        Ok(())
+    }
+}
+
+/// SPI NOR flash opcodes
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_READ: u8 = 0x03;
+const CMD_READ_STATUS: u8 = 0x05;
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_SECTOR_ERASE: u8 = 0x20;
+const CMD_READ_JEDEC_ID: u8 = 0x9f;
+
+/// Write In Progress status bit
+const STATUS_WIP: u8 = 1 << 0;
+
+/// Largest address reachable with 3-byte addressing
+const MAX_ADDR: u32 = 0x00ff_ffff;
+
+/// SPI NOR flash with 3-byte addressing attached to the SPI host
+pub struct SpiFlash {
+    host: SpiHost,
+    csid: u32,
+}
+
+impl SpiFlash {
+    /// Page size in bytes
+    pub const PAGE_SIZE: usize = 256;
+
+    /// Sector size in bytes
+    pub const SECTOR_SIZE: usize = 4096;
+
+    /// Create a flash driver
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Initialized SPI host
+    /// * `csid` - Chip-select line of the flash
+    pub fn new(host: SpiHost, csid: u32) -> Self {
+        Self { host, csid }
+    }
+
+    /// Read the JEDEC manufacturer and device ID
+    pub fn read_jedec_id(&mut self) -> CaliptraResult<[u8; 3]> {
+        let mut id = [0u8; 3];
+        self.host
+            .transfer(self.csid, &[CMD_READ_JEDEC_ID], &mut id)?;
+        Ok(id)
+    }
+
+    /// Read from the flash
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Flash address to read from
+    /// * `buf` - Buffer to read into
+    pub fn read(&mut self, addr: u32, buf: &mut [u8]) -> CaliptraResult<()> {
+        Self::check_range(addr, buf.len())?;
+        self.host
+            .transfer(self.csid, &Self::header(CMD_READ, addr), buf)
+    }
+
+    /// Erase the sector containing `addr`
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Flash address within the sector
+    pub fn erase_sector(&mut self, addr: u32) -> CaliptraResult<()> {
+        Self::check_range(addr, 0)?;
+        self.write_enable()?;
+        self.host
+            .transfer(self.csid, &Self::header(CMD_SECTOR_ERASE, addr), &mut [])?;
+        self.wait_ready()
+    }
+
+    /// Program data into an erased region of a single page
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Flash address to program
+    /// * `data` - Data to program; must not cross a page boundary
+    pub fn program_page(&mut self, addr: u32, data: &[u8]) -> CaliptraResult<()> {
+        Self::check_range(addr, data.len())?;
+        let page_offset = addr as usize % Self::PAGE_SIZE;
+        if data.is_empty() || page_offset + data.len() > Self::PAGE_SIZE {
+            return Err(CaliptraError::DRIVER_SPI_FLASH_INVALID_PAGE_PROGRAM);
+        }
+        self.write_enable()?;
+
+        let mut tx = [0u8; 4 + Self::PAGE_SIZE];
+        tx[..4].copy_from_slice(&Self::header(CMD_PAGE_PROGRAM, addr));
+        tx[4..4 + data.len()].copy_from_slice(data);
+        self.host
+            .transfer(self.csid, &tx[..4 + data.len()], &mut [])?;
+        self.wait_ready()
+    }
+
+    fn write_enable(&mut self) -> CaliptraResult<()> {
+        self.host.transfer(self.csid, &[CMD_WRITE_ENABLE], &mut [])
+    }
+
+    fn wait_ready(&mut self) -> CaliptraResult<()> {
+        loop {
+            let mut status = [0u8; 1];
+            self.host
+                .transfer(self.csid, &[CMD_READ_STATUS], &mut status)?;
+            if status[0] & STATUS_WIP == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    fn header(opcode: u8, addr: u32) -> [u8; 4] {
+        let addr = addr.to_be_bytes();
+        [opcode, addr[1], addr[2], addr[3]]
+    }
+
+    fn check_range(addr: u32, len: usize) -> CaliptraResult<()> {
+        let last = (addr as usize).checked_add(len.saturating_sub(1));
+        match last {
+            Some(last) if last <= MAX_ADDR as usize => Ok(()),
+            _ => Err(CaliptraError::DRIVER_SPI_FLASH_INVALID_ADDRESS),
+        }
+    }
+}
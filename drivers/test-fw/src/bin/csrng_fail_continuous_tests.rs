@@ -0,0 +1,61 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    csrng_fail_continuous_tests.rs
+
+Abstract:
+    https://opentitan.org/book/hw/ip/entropy_src/doc/theory_of_operation.html#health-tests
+
+    File contains test cases for CSRNG API when the physical entropy source
+    passes boot-time health testing but develops a fault afterwards.
+
+    We expect the continuous health checks to fail for these tests.
+--*/
+#![no_std]
+#![no_main]
+
+use caliptra_drivers::Csrng;
+use caliptra_error::CaliptraError;
+use caliptra_registers::{csrng::CsrngReg, entropy_src::EntropySrcReg, soc_ifc::SocIfcReg};
+use caliptra_test_harness::test_suite;
+
+fn new_csrng() -> Csrng {
+    let csrng_reg = unsafe { CsrngReg::new() };
+    let entropy_src_reg = unsafe { EntropySrcReg::new() };
+    let soc_ifc_reg = unsafe { SocIfcReg::new() };
+    Csrng::new(csrng_reg, entropy_src_reg, &soc_ifc_reg).expect("construct CSRNG")
+}
+
+fn test_continuous_fail_health_check() {
+    let csrng = new_csrng();
+    let counts = csrng.health_fail_counts();
+    assert_eq!(
+        counts.total, 0,
+        "boot-time health testing should have passed"
+    );
+    csrng.uninstantiate();
+
+    // The entropy source is already enabled, so this only instantiates the
+    // DRBG, which draws entropy from the now-faulty noise source.
+    let mut csrng = new_csrng();
+
+    let counts = csrng.health_fail_counts();
+    assert_ne!(counts.total, 0, "continuous health testing should fail");
+    assert_ne!(u32::from(counts.specific), 0);
+
+    let err = csrng
+        .generate12()
+        .expect_err("CSRNG should refuse to generate after a failed health check");
+    assert!(
+        err == CaliptraError::DRIVER_CSRNG_REPCNT_HEALTH_CHECK_FAILED
+            || err == CaliptraError::DRIVER_CSRNG_ADAPTP_HEALTH_CHECK_FAILED,
+        "error code should indicate which health check failed"
+    );
+}
+
+test_suite! {
+    test_continuous_fail_health_check,
+}
@@ -0,0 +1,102 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    spi_flash_tests.rs
+
+Abstract:
+
+    File contains test cases for SPI host and SPI flash API
+
+--*/
+
+#![no_std]
+#![no_main]
+
+use caliptra_drivers::{CaliptraError, SpiFlash, SpiHost};
+use caliptra_registers::spi_host::SpiHostReg;
+use caliptra_test_harness::test_suite;
+
+/// Size of the flash image provided by the test
+const FLASH_SIZE: u32 = 64 * 1024;
+
+/// Sector erased and programmed by the test
+const TEST_SECTOR: u32 = 0x2000;
+
+fn flash() -> SpiFlash {
+    let mut host = SpiHost::new(unsafe { SpiHostReg::new() });
+    host.init(0);
+    SpiFlash::new(host, 0)
+}
+
+/// Initial contents of the flash image at `addr`
+fn initial_byte(addr: u32) -> u8 {
+    (addr % 251) as u8
+}
+
+fn test_jedec_id() {
+    assert_eq!(flash().read_jedec_id().unwrap(), [0xef, 0x40, 0x18]);
+}
+
+fn test_read() {
+    let mut flash = flash();
+
+    // Spans several command segments and ends on a partial word.
+    let mut buf = [0u8; 1283];
+    flash.read(0x1235, &mut buf).unwrap();
+    for (i, byte) in buf.iter().enumerate() {
+        assert_eq!(*byte, initial_byte(0x1235 + i as u32));
+    }
+
+    let mut buf = [0u8; 4];
+    flash.read(FLASH_SIZE - 4, &mut buf).unwrap();
+    assert_eq!(buf[3], initial_byte(FLASH_SIZE - 1));
+
+    assert_eq!(
+        flash.read(0x00ff_fffe, &mut buf),
+        Err(CaliptraError::DRIVER_SPI_FLASH_INVALID_ADDRESS)
+    );
+}
+
+fn test_erase_and_program() {
+    let mut flash = flash();
+
+    flash.erase_sector(TEST_SECTOR + 0x123).unwrap();
+    let mut buf = [0u8; 64];
+    flash.read(TEST_SECTOR, &mut buf).unwrap();
+    assert_eq!(buf, [0xff; 64]);
+
+    let mut data = [0u8; 100];
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    flash.program_page(TEST_SECTOR + 0x10, &data).unwrap();
+
+    let mut buf = [0u8; 100];
+    flash.read(TEST_SECTOR + 0x10, &mut buf).unwrap();
+    assert_eq!(buf, data);
+
+    // Crosses a page boundary
+    assert_eq!(
+        flash.program_page(TEST_SECTOR + 0xf0, &data),
+        Err(CaliptraError::DRIVER_SPI_FLASH_INVALID_PAGE_PROGRAM)
+    );
+}
+
+fn test_invalid_csid() {
+    let mut host = SpiHost::new(unsafe { SpiHostReg::new() });
+    host.init(0);
+    assert_eq!(
+        host.transfer(SpiHost::NUM_CS, &[0x9f], &mut [0u8; 3]),
+        Err(CaliptraError::DRIVER_SPI_HOST_INVALID_CSID)
+    );
+}
+
+test_suite! {
+    test_jedec_id,
+    test_read,
+    test_erase_and_program,
+    test_invalid_csid,
+}
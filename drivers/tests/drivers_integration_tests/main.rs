@@ -10,7 +10,7 @@ use caliptra_hw_model::{
     BootParams, DefaultHwModel, DeviceLifecycle, HwModel, InitParams, ModelError, SecurityState,
     TrngMode,
 };
-use caliptra_hw_model_types::EtrngResponse;
+use caliptra_hw_model_types::{EntropySrcFault, EtrngResponse, NoiseSourceFault};
 use caliptra_registers::mbox::enums::MboxStatusE;
 use caliptra_registers::soc_ifc::{
     meta::{CptraItrngEntropyConfig0, CptraItrngEntropyConfig1},
@@ -1003,6 +1003,69 @@ fn test_csrng_adaptive_proportion() {
     test_with_soc_threshold(FAIL, include_bytes!("test_data/csrng/1225_ones_823_zeros"));
 }
 
+#[test]
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_csrng_injected_faults() {
+    // Noise source faults can only be injected into the emulator.
+    fn test_with_fault(fwid: &FwId<'static>, fault: EntropySrcFault) {
+        let rom = caliptra_builder::build_firmware_rom(fwid).unwrap();
+
+        let mut model = caliptra_hw_model::new(BootParams {
+            init_params: InitParams {
+                rom: &rom,
+                itrng_nibbles: Box::new(trng_nibbles()),
+                entropy_src_fault: Some(fault),
+                ..default_init_params()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        model.step_until_exit_success().unwrap();
+    }
+
+    const FAIL_REPCNT: &FwId = &firmware::driver_tests::CSRNG_FAIL_REPCNT_TESTS;
+    const FAIL_ADAPTP: &FwId = &firmware::driver_tests::CSRNG_FAIL_ADAPTP_TESTS;
+    const FAIL_CONTINUOUS: &FwId = &firmware::driver_tests::CSRNG_FAIL_CONTINUOUS_TESTS;
+
+    // Boot-time health testing draws one 2048 bit window and instantiating
+    // the DRBG draws another.
+    const NIBBLES_BEFORE_FIRST_INSTANTIATE: usize = 2 * 2048 / 4;
+
+    let stuck_bit = NoiseSourceFault::StuckBit {
+        wire: 1,
+        value: true,
+    };
+    test_with_fault(
+        FAIL_REPCNT,
+        EntropySrcFault::at_boot(NoiseSourceFault::RepetitionCount),
+    );
+    test_with_fault(FAIL_REPCNT, EntropySrcFault::at_boot(stuck_bit));
+    test_with_fault(
+        FAIL_ADAPTP,
+        EntropySrcFault::at_boot(NoiseSourceFault::AdaptiveProportionHi),
+    );
+    test_with_fault(
+        FAIL_ADAPTP,
+        EntropySrcFault::at_boot(NoiseSourceFault::AdaptiveProportionLo),
+    );
+
+    for fault in [
+        NoiseSourceFault::RepetitionCount,
+        NoiseSourceFault::AdaptiveProportionHi,
+        NoiseSourceFault::AdaptiveProportionLo,
+        stuck_bit,
+    ] {
+        test_with_fault(
+            FAIL_CONTINUOUS,
+            EntropySrcFault {
+                fault,
+                after_nibbles: NIBBLES_BEFORE_FIRST_INSTANTIATE,
+            },
+        );
+    }
+}
+
 #[test]
 #[cfg_attr(
     all(
@@ -1107,6 +1170,44 @@ fn test_persistent() {
     run_driver_test(&firmware::driver_tests::PERSISTENT);
 }
 
+#[test]
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_spi_flash() {
+    const FLASH_SIZE: usize = 64 * 1024;
+    const TEST_SECTOR: usize = 0x2000;
+
+    let path = std::env::temp_dir().join(format!(
+        "caliptra-drivers-spi-flash-{}.bin",
+        std::process::id()
+    ));
+    let contents: Vec<u8> = (0..FLASH_SIZE).map(|i| (i % 251) as u8).collect();
+    std::fs::write(&path, &contents).unwrap();
+
+    let rom = caliptra_builder::build_firmware_rom(&firmware::driver_tests::SPI_FLASH).unwrap();
+    let mut model = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            spi_flash_path: Some(path.clone()),
+            ..default_init_params()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    model.step_until_exit_success().unwrap();
+    drop(model);
+
+    // The firmware's erase and program must have been written back to the
+    // flash image.
+    let mut expected = contents;
+    expected[TEST_SECTOR..TEST_SECTOR + 0x1000].fill(0xff);
+    for i in 0..100 {
+        expected[TEST_SECTOR + 0x10 + i] = i as u8;
+    }
+    let actual = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(actual == expected);
+}
+
 #[test]
 fn test_uart() {
     let mut model = start_driver_test(&firmware::driver_tests::TEST_UART).unwrap();
@@ -262,6 +262,18 @@ impl CaliptraError {
         CaliptraError::new_const(0x000b0040);
     pub const IMAGE_VERIFIER_ERR_DIGEST_OUT_OF_BOUNDS: CaliptraError =
         CaliptraError::new_const(0x000b0041);
+    pub const IMAGE_VERIFIER_ERR_FMC_SOC_TOC_OVERLAP: CaliptraError =
+        CaliptraError::new_const(0x000b0042);
+    pub const IMAGE_VERIFIER_ERR_RUNTIME_SOC_TOC_OVERLAP: CaliptraError =
+        CaliptraError::new_const(0x000b0043);
+    pub const IMAGE_VERIFIER_ERR_MANIFEST_FORMAT_VERSION_MISMATCH: CaliptraError =
+        CaliptraError::new_const(0x000b0044);
+    pub const IMAGE_VERIFIER_ERR_EXTENSIONS_LEN_INVALID: CaliptraError =
+        CaliptraError::new_const(0x000b0045);
+    pub const IMAGE_VERIFIER_ERR_EXTENSION_MALFORMED: CaliptraError =
+        CaliptraError::new_const(0x000b0046);
+    pub const IMAGE_VERIFIER_ERR_UNKNOWN_CRITICAL_EXTENSION: CaliptraError =
+        CaliptraError::new_const(0x000b0047);
 
     /// Driver Error: LMS
     pub const DRIVER_LMS_INVALID_LMS_ALGO_TYPE: CaliptraError =
@@ -371,6 +383,11 @@ impl CaliptraError {
         CaliptraError::new_const(0x000E0028);
     pub const RUNTIME_CONTEXT_TAG_VALIDATION_FAILED: CaliptraError =
         CaliptraError::new_const(0x000E0029);
+    pub const RUNTIME_SOC_IMAGE_NOT_FOUND: CaliptraError = CaliptraError::new_const(0x000E002A);
+    pub const RUNTIME_SOC_IMAGE_DIGEST_MISMATCH: CaliptraError =
+        CaliptraError::new_const(0x000E002B);
+    pub const RUNTIME_SOC_IMAGE_SVN_LESS_THAN_MIN_SVN: CaliptraError =
+        CaliptraError::new_const(0x000E002C);
 
     /// FMC Errors
     pub const FMC_GLOBAL_NMI: CaliptraError = CaliptraError::new_const(0x000F0001);
@@ -386,6 +403,11 @@ impl CaliptraError {
     pub const ADDRESS_NOT_IN_ICCM: CaliptraError = CaliptraError::new_const(0x000F000B);
     pub const FMC_HANDOFF_NOT_READY_FOR_RT: CaliptraError = CaliptraError::new_const(0x000F000C);
     pub const FMC_GLOBAL_WDT_EXPIRED: CaliptraError = CaliptraError::new_const(0x000F000D);
+    pub const FMC_RECOVERY_RT_BOOT_FAILURES: CaliptraError = CaliptraError::new_const(0x000F000E);
+    pub const FMC_RECOVERY_INVALID_COMMAND: CaliptraError = CaliptraError::new_const(0x000F000F);
+    pub const FMC_RECOVERY_INVALID_CHECKSUM: CaliptraError = CaliptraError::new_const(0x000F0010);
+    pub const FMC_RECOVERY_INVALID_REQUEST_LENGTH: CaliptraError =
+        CaliptraError::new_const(0x000F0011);
 
     /// TRNG_EXT Errors
     pub const DRIVER_TRNG_EXT_TIMEOUT: CaliptraError = CaliptraError::new_const(0x00100001);
@@ -398,6 +420,15 @@ impl CaliptraError {
     pub const ADDRESS_MISALIGNED: CaliptraError = CaliptraError::new_const(0x00110000);
     pub const ADDRESS_NOT_IN_ROM: CaliptraError = CaliptraError::new_const(0x00110001);
 
+    /// SPI host driver Errors
+    pub const DRIVER_SPI_HOST_INVALID_CSID: CaliptraError = CaliptraError::new_const(0x00120001);
+    pub const DRIVER_SPI_HOST_TRANSFER_FAILURE: CaliptraError =
+        CaliptraError::new_const(0x00120002);
+    pub const DRIVER_SPI_FLASH_INVALID_ADDRESS: CaliptraError =
+        CaliptraError::new_const(0x00120003);
+    pub const DRIVER_SPI_FLASH_INVALID_PAGE_PROGRAM: CaliptraError =
+        CaliptraError::new_const(0x00120004);
+
     /// Initial Device ID Errors
     pub const ROM_IDEVID_CSR_BUILDER_INIT_FAILURE: CaliptraError =
         CaliptraError::new_const(0x01000001);
@@ -436,6 +467,8 @@ impl CaliptraError {
         CaliptraError::new_const(0x01040004);
     pub const ROM_UPDATE_RESET_READ_FHT_FAILURE: CaliptraError =
         CaliptraError::new_const(0x01040005);
+    pub const ROM_UPDATE_RESET_FLOW_IMAGE_NOT_STAGED: CaliptraError =
+        CaliptraError::new_const(0x01040006);
 
     // Warm Reset Errors
     pub const ROM_WARM_RESET_UNSUCCESSFUL_PREVIOUS_COLD_RESET: CaliptraError =
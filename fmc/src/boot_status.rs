@@ -1,6 +1,7 @@
 // Licensed under the Apache-2.0 license
 use core::convert::From;
 const RTALIAS_BOOT_STATUS_BASE: u32 = 0x400;
+const RECOVERY_BOOT_STATUS_BASE: u32 = 0x440;
 
 /// Statuses used by ROM to log dice derivation progress.
 #[repr(u32)]
@@ -14,6 +15,9 @@ pub enum FmcBootStatus {
     RtAliasSubjKeyIdGenerationComplete = RTALIAS_BOOT_STATUS_BASE + 4,
     RtAliasCertSigGenerationComplete = RTALIAS_BOOT_STATUS_BASE + 5,
     RtAliasDerivationComplete = RTALIAS_BOOT_STATUS_BASE + 6,
+
+    // Recovery Statuses
+    RecoveryStarted = RECOVERY_BOOT_STATUS_BASE,
 }
 
 impl From<FmcBootStatus> for u32 {
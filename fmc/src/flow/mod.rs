@@ -15,10 +15,12 @@ Abstract:
 mod crypto;
 pub mod dice;
 mod pcr;
+mod recovery;
 mod rt_alias;
 mod tci;
 mod x509;
 
+use crate::flow::recovery::RecoveryFlow;
 use crate::flow::rt_alias::RtAliasLayer;
 
 use crate::fmc_env::FmcEnv;
@@ -30,5 +32,11 @@ use caliptra_drivers::CaliptraResult;
 ///
 /// * `env` - FMC Environment
 pub fn run(env: &mut FmcEnv) -> CaliptraResult<()> {
-    RtAliasLayer::run(env)
+    if RecoveryFlow::is_required(env) {
+        RecoveryFlow::run(env);
+    }
+
+    RtAliasLayer::run(env)?;
+    RecoveryFlow::record_rt_boot_attempt(env);
+    Ok(())
 }
@@ -0,0 +1,198 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    recovery.rs
+
+Abstract:
+
+    Recovery mailbox service used when the runtime repeatedly fails to boot
+
+--*/
+use crate::fmc_env::FmcEnv;
+use crate::FmcBootStatus;
+use caliptra_common::capabilities::Capabilities;
+use caliptra_common::cprintln;
+use caliptra_common::mailbox_api::{
+    CapabilitiesResp, CommandId, FwInfoResp, MailboxReqHeader, MailboxRespHeader, Response,
+};
+use caliptra_drivers::{
+    report_boot_status, report_fw_error_non_fatal, CaliptraError, CaliptraResult, DataVault,
+    MailboxRecvTxn, PersistentDataAccessor, SocIfc,
+};
+use zerocopy::{AsBytes, LayoutVerified};
+
+/// Number of consecutive runtime boot attempts that may fail before FMC
+/// stays resident in recovery mode.
+pub const RT_BOOT_FAILURE_THRESHOLD: u32 = 3;
+
+#[derive(Default)]
+pub struct RecoveryFlow {}
+
+impl RecoveryFlow {
+    /// Returns true if the runtime failed to boot too many times in a row
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - FMC Environment
+    pub fn is_required(env: &FmcEnv) -> bool {
+        env.data_vault.rt_boot_failure_count() >= RT_BOOT_FAILURE_THRESHOLD
+    }
+
+    /// Record an attempt to boot the runtime.
+    ///
+    /// Runtime clears the count once it is ready for commands, so the count
+    /// only accumulates while the runtime fails to come up.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - FMC Environment
+    pub fn record_rt_boot_attempt(env: &mut FmcEnv) {
+        let count = env.data_vault.rt_boot_failure_count();
+        env.data_vault
+            .set_rt_boot_failure_count(count.saturating_add(1));
+    }
+
+    /// Stay resident and service the mailbox until a new runtime is pushed
+    ///
+    /// Only `FW_INFO`, `CAPABILITIES` and `FIRMWARE_LOAD` are supported. A
+    /// `FIRMWARE_LOAD` is left in the mailbox and handed to ROM through an
+    /// update reset.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - FMC Environment
+    pub fn run(env: &mut FmcEnv) -> ! {
+        cprintln!(
+            "[recovery] Runtime failed to boot {} times",
+            env.data_vault.rt_boot_failure_count()
+        );
+        report_boot_status(FmcBootStatus::RecoveryStarted.into());
+        report_fw_error_non_fatal(CaliptraError::FMC_RECOVERY_RT_BOOT_FAILURES.into());
+
+        caliptra_common::stop_wdt(&mut env.soc_ifc);
+
+        loop {
+            let Some(txn) = env.mbox.peek_recv() else {
+                continue;
+            };
+
+            if txn.cmd() == CommandId::FIRMWARE_LOAD.into() {
+                cprintln!("[recovery] Received new firmware, triggering update reset");
+
+                // Give the new runtime a fresh set of boot attempts. If ROM
+                // rejects the image, the previous runtime is booted again.
+                env.data_vault.set_rt_boot_failure_count(0);
+
+                let cycles = env.soc_ifc.internal_fw_update_reset_wait_cycles();
+                for _ in 0..cycles {
+                    env.soc_ifc.assert_fw_update_reset();
+                }
+                continue;
+            }
+
+            let mut txn = txn.start_txn();
+            if let Err(e) = Self::handle_command(
+                &env.data_vault,
+                &env.soc_ifc,
+                &env.persistent_data,
+                &mut txn,
+            ) {
+                cprintln!("[recovery] Command failed: 0x{:08x}", u32::from(e));
+                report_fw_error_non_fatal(e.into());
+                let _ = txn.complete(false);
+            }
+        }
+    }
+
+    /// Handle a single mailbox command
+    ///
+    /// # Arguments
+    ///
+    /// * `data_vault`      - Data Vault
+    /// * `soc_ifc`         - SoC Interface
+    /// * `persistent_data` - Persistent Data
+    /// * `txn`             - Mailbox Receive Transaction
+    fn handle_command(
+        data_vault: &DataVault,
+        soc_ifc: &SocIfc,
+        persistent_data: &PersistentDataAccessor,
+        txn: &mut MailboxRecvTxn,
+    ) -> CaliptraResult<()> {
+        match CommandId::from(txn.cmd()) {
+            CommandId::FW_INFO => {
+                let mut request = MailboxReqHeader::default();
+                Self::copy_req_verify_chksum(txn, request.as_bytes_mut())?;
+
+                let fuse_bank = soc_ifc.fuse_bank();
+                let runtime_fuse_svn = fuse_bank.runtime_fuse_svn();
+                let fmc_fuse_svn = fuse_bank.fmc_fuse_svn();
+                let svn_commit_pending =
+                    data_vault.rt_svn() > runtime_fuse_svn || data_vault.fmc_svn() > fmc_fuse_svn;
+
+                let mut resp = FwInfoResp {
+                    hdr: MailboxRespHeader::default(),
+                    pl0_pauser: persistent_data.get().manifest1.header.pl0_pauser,
+                    runtime_svn: data_vault.rt_svn(),
+                    min_runtime_svn: data_vault.rt_min_svn(),
+                    fmc_manifest_svn: data_vault.fmc_svn(),
+                    // No attestation service is available in recovery mode
+                    attestation_disabled: true.into(),
+                    runtime_fuse_svn,
+                    fmc_fuse_svn,
+                    svn_commit_pending: svn_commit_pending.into(),
+                };
+                resp.populate_chksum();
+                txn.send_response(resp.as_bytes())
+            }
+            CommandId::CAPABILITIES => {
+                let mut request = MailboxReqHeader::default();
+                Self::copy_req_verify_chksum(txn, request.as_bytes_mut())?;
+
+                let mut capabilities = Capabilities::default();
+                capabilities |= Capabilities::ROM_BASE;
+                capabilities |= Capabilities::FMC_RECOVERY;
+
+                let mut resp = CapabilitiesResp {
+                    hdr: MailboxRespHeader::default(),
+                    capabilities: capabilities.to_bytes(),
+                };
+                resp.populate_chksum();
+                txn.send_response(resp.as_bytes())
+            }
+            _ => Err(CaliptraError::FMC_RECOVERY_INVALID_COMMAND),
+        }
+    }
+
+    /// Read a fixed size request and verify its checksum
+    ///
+    /// # Arguments
+    ///
+    /// * `txn`  - Mailbox Receive Transaction
+    /// * `data` - Buffer for the request
+    fn copy_req_verify_chksum(txn: &mut MailboxRecvTxn, data: &mut [u8]) -> CaliptraResult<()> {
+        if txn.dlen() as usize != data.len() {
+            return Err(CaliptraError::FMC_RECOVERY_INVALID_REQUEST_LENGTH);
+        }
+
+        txn.copy_request(data)?;
+
+        let req_hdr: &MailboxReqHeader = LayoutVerified::<&[u8], MailboxReqHeader>::new(
+            &data[..core::mem::size_of::<MailboxReqHeader>()],
+        )
+        .ok_or(CaliptraError::FMC_RECOVERY_INVALID_REQUEST_LENGTH)?
+        .into_ref();
+
+        if !caliptra_common::checksum::verify_checksum(
+            req_hdr.chksum,
+            txn.cmd(),
+            &data[core::mem::size_of_val(&req_hdr.chksum)..],
+        ) {
+            return Err(CaliptraError::FMC_RECOVERY_INVALID_CHECKSUM);
+        }
+
+        Ok(())
+    }
+}
@@ -2,4 +2,6 @@
 
 mod test_hand_off;
 mod test_panic_missing;
+mod test_recovery;
 mod test_rtalias;
+mod test_stack_usage;
@@ -0,0 +1,96 @@
+// Licensed under the Apache-2.0 license
+use caliptra_builder::{
+    firmware::{self, fmc_tests::MOCK_RT_INTERACTIVE, FMC_WITH_UART},
+    ImageOptions,
+};
+use caliptra_common::capabilities::Capabilities;
+use caliptra_common::mailbox_api::{CapabilitiesResp, CommandId, FwInfoResp, MailboxReqHeader};
+use caliptra_common::RomBootStatus::*;
+use caliptra_error::CaliptraError;
+use caliptra_hw_model::{BootParams, DefaultHwModel, HwModel, InitParams};
+use zerocopy::{AsBytes, FromBytes};
+
+const TEST_CMD_READ_FHT: u32 = 0x1000_0001;
+
+const RT_ALIAS_DERIVATION_COMPLETE: u32 = 0x406;
+const RECOVERY_STARTED: u32 = 0x440;
+
+const RT_BOOT_FAILURE_THRESHOLD: u32 = 3;
+
+fn update_reset(hw: &mut DefaultHwModel, image: &[u8]) {
+    hw.start_mailbox_execute(CommandId::FIRMWARE_LOAD.into(), image)
+        .unwrap();
+
+    hw.step_until_boot_status(KatStarted.into(), true);
+    hw.step_until_boot_status(KatComplete.into(), true);
+    hw.step_until_boot_status(UpdateResetStarted.into(), false);
+
+    assert_eq!(hw.finish_mailbox_execute(), Ok(None));
+}
+
+fn execute_no_args(hw: &mut DefaultHwModel, cmd: CommandId) -> Vec<u8> {
+    let payload = MailboxReqHeader {
+        chksum: caliptra_common::checksum::calc_checksum(u32::from(cmd), &[]),
+    };
+    hw.mailbox_execute(u32::from(cmd), payload.as_bytes())
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn test_recovery_after_rt_boot_failures() {
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let image = caliptra_builder::build_and_sign_image(
+        &FMC_WITH_UART,
+        &MOCK_RT_INTERACTIVE,
+        ImageOptions::default(),
+    )
+    .unwrap()
+    .to_bytes()
+    .unwrap();
+
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            ..Default::default()
+        },
+        fw_image: Some(&image),
+        ..Default::default()
+    })
+    .unwrap();
+    hw.step_until_boot_status(RT_ALIAS_DERIVATION_COMPLETE, true);
+
+    // The mock runtime never reports that it booted successfully, so every
+    // update reset counts as another failed runtime boot.
+    for _ in 1..RT_BOOT_FAILURE_THRESHOLD {
+        update_reset(&mut hw, &image);
+        hw.step_until_boot_status(RT_ALIAS_DERIVATION_COMPLETE, true);
+    }
+    update_reset(&mut hw, &image);
+    hw.step_until_boot_status(RECOVERY_STARTED, true);
+    assert_eq!(
+        hw.soc_ifc().cptra_fw_error_non_fatal().read(),
+        u32::from(CaliptraError::FMC_RECOVERY_RT_BOOT_FAILURES)
+    );
+
+    let resp = execute_no_args(&mut hw, CommandId::FW_INFO);
+    let fw_info = FwInfoResp::read_from(resp.as_slice()).unwrap();
+    assert_eq!(fw_info.attestation_disabled, 1);
+
+    let resp = execute_no_args(&mut hw, CommandId::CAPABILITIES);
+    let caps = CapabilitiesResp::read_from(resp.as_slice()).unwrap();
+    let caps = Capabilities::try_from(&caps.capabilities[..]).unwrap();
+    assert!(caps.contains(Capabilities::ROM_BASE | Capabilities::FMC_RECOVERY));
+
+    // Commands other than FW_INFO, CAPABILITIES and FIRMWARE_LOAD are rejected
+    assert!(hw.mailbox_execute(TEST_CMD_READ_FHT, &[]).is_err());
+    assert_eq!(
+        hw.soc_ifc().cptra_fw_error_non_fatal().read(),
+        u32::from(CaliptraError::FMC_RECOVERY_INVALID_COMMAND)
+    );
+
+    // Pushing a new runtime leaves recovery mode
+    update_reset(&mut hw, &image);
+    hw.step_until_boot_status(RT_ALIAS_DERIVATION_COMPLETE, true);
+    hw.mailbox_execute(TEST_CMD_READ_FHT, &[]).unwrap().unwrap();
+}
@@ -0,0 +1,20 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::firmware;
+use caliptra_drivers::memory_layout;
+
+#[test]
+fn test_stack_usage_within_limits() {
+    let elf_bytes = caliptra_builder::build_firmware_elf(&firmware::FMC_WITH_UART).unwrap();
+    caliptra_builder::stack_usage(&elf_bytes)
+        .unwrap()
+        .check_limits(
+            &[
+                ("_start", memory_layout::STACK_SIZE),
+                ("_trap_handler", memory_layout::ESTACK_SIZE),
+                ("_nmi_handler", memory_layout::NSTACK_SIZE),
+            ],
+            &[],
+        )
+        .unwrap();
+}
@@ -72,8 +72,19 @@ fn main() {
 
     let mut make_cmd = process::Command::new("make");
     make_cmd.current_dir(&manifest_dir);
+    let mut extra_verilator_flags = vec![];
     if std::env::var_os("CARGO_FEATURE_ITRNG").is_some() {
-        make_cmd.arg("EXTRA_VERILATOR_FLAGS=-DCALIPTRA_INTERNAL_TRNG");
+        extra_verilator_flags.push("-DCALIPTRA_INTERNAL_TRNG");
+    }
+    if std::env::var_os("CARGO_FEATURE_SAVABLE").is_some() {
+        extra_verilator_flags.push("--savable");
+        make_cmd.arg("EXTRA_CFLAGS=-DCALIPTRA_VERILATED_SAVABLE");
+    }
+    if !extra_verilator_flags.is_empty() {
+        make_cmd.arg(format!(
+            "EXTRA_VERILATOR_FLAGS={}",
+            extra_verilator_flags.join(" ")
+        ));
     }
 
     run_command(&mut make_cmd);
@@ -3,15 +3,70 @@ Licensed under the Apache-2.0 license.
 --*/
 #include "caliptra_verilated.h"
 
+#include <stdio.h>
+
+#include <string>
+
 #include "Vcaliptra_verilated.h"
+#include "verilated_save.h"
 #include "verilated_vcd_c.h"
 
+// A VCD "file" that appends to a string, used for trace windows.
+class MemoryVcdFile : public VerilatedVcdFile {
+ public:
+  explicit MemoryVcdFile(std::string* buf) : buf_(buf) {}
+  bool open(const std::string&) override { return true; }
+  void close() override {}
+  ssize_t write(const char* bufp, ssize_t len) override {
+    buf_->append(bufp, len);
+    return len;
+  }
+
+ private:
+  std::string* buf_;
+};
+
 struct caliptra_verilated {
   Vcaliptra_verilated v;
+
+  // Trace window state (see caliptra_verilated_trace_window). Declared
+  // before tfp, which writes to window_file until it is destroyed.
+  int window_depth = 0;
+  uint64_t window_ticks = 0;
+  uint64_t window_start = 0;
+  std::string window_prev;
+  std::string window_cur;
+  MemoryVcdFile window_file{&window_cur};
+
   std::unique_ptr<VerilatedVcdC> tfp;
   uint64_t sim_time = 0;
 };
 
+static void close_trace(struct caliptra_verilated* model) {
+  if (model->tfp.get()) {
+    model->tfp->close();
+  }
+  model->tfp.reset(NULL);
+  model->window_ticks = 0;
+  model->window_prev.clear();
+  model->window_cur.clear();
+}
+
+// Starts a new chunk of the trace window; each chunk is a complete VCD
+// starting with the value of every signal.
+static void start_window_chunk(struct caliptra_verilated* model) {
+  if (model->tfp.get()) {
+    model->tfp->close();
+  }
+  model->window_prev.swap(model->window_cur);
+  model->window_cur.clear();
+  model->window_start = model->sim_time;
+
+  model->tfp.reset(new VerilatedVcdC(&model->window_file));
+  model->v.trace(model->tfp.get(), model->window_depth);
+  model->tfp->open("caliptra_trace_window");
+}
+
 struct caliptra_verilated* caliptra_verilated_new(struct caliptra_verilated_init_args* init_args) {
   auto result = new caliptra_verilated();
   result->v.security_state = init_args->security_state;
@@ -19,19 +74,14 @@ struct caliptra_verilated* caliptra_verilated_new(struct caliptra_verilated_init
   return result;
 }
 void caliptra_verilated_destroy(struct caliptra_verilated* model) {
-  if (model->tfp.get()) {
-    model->tfp->close();
-  }
+  close_trace(model);
   delete model;
 }
 
 void caliptra_verilated_trace(struct caliptra_verilated* model,
                               const char* vcd_out_path, int depth) {
   Verilated::traceEverOn(vcd_out_path ? true : false);
-  if (model->tfp.get()) {
-    model->tfp->close();
-  }
-  model->tfp.reset(NULL);
+  close_trace(model);
 
   if (vcd_out_path) {
     model->tfp.reset(new VerilatedVcdC());
@@ -41,6 +91,104 @@ void caliptra_verilated_trace(struct caliptra_verilated* model,
   }
 }
 
+void caliptra_verilated_trace_window(struct caliptra_verilated* model,
+                                     int depth, uint64_t window_cycles) {
+  Verilated::traceEverOn(window_cycles ? true : false);
+  close_trace(model);
+
+  if (window_cycles) {
+    model->window_depth = depth;
+    // The model is evaluated (and dumped) twice per clock cycle
+    model->window_ticks = window_cycles * 2;
+    start_window_chunk(model);
+  }
+}
+
+bool caliptra_verilated_trace_window_save(struct caliptra_verilated* model,
+                                          const char* vcd_out_path) {
+  if (!model->window_ticks) {
+    return false;
+  }
+  model->tfp->flush();
+
+  // The previous chunk is a complete VCD file; the current chunk starts with
+  // the value of every signal, so it can be appended without its header.
+  std::string cur_body = model->window_cur;
+  if (!model->window_prev.empty()) {
+    size_t end_of_header = cur_body.find("$enddefinitions");
+    if (end_of_header != std::string::npos) {
+      end_of_header = cur_body.find('\n', end_of_header);
+    }
+    if (end_of_header != std::string::npos) {
+      cur_body.erase(0, end_of_header + 1);
+    }
+  }
+
+  FILE* file = fopen(vcd_out_path, "wb");
+  if (!file) {
+    return false;
+  }
+  bool ok =
+      fwrite(model->window_prev.data(), 1, model->window_prev.size(), file) ==
+          model->window_prev.size() &&
+      fwrite(cur_body.data(), 1, cur_body.size(), file) == cur_body.size();
+  return (fclose(file) == 0) && ok;
+}
+
+bool caliptra_verilated_save(struct caliptra_verilated* model,
+                             const char* path,
+                             const struct caliptra_verilated_sig_in* in,
+                             const uint8_t* user_data, uint32_t user_data_len) {
+#ifdef CALIPTRA_VERILATED_SAVABLE
+  VerilatedSave os;
+  os.open(path);
+  if (!os.isOpen()) {
+    return false;
+  }
+  os << model->sim_time;
+  os.write(in, sizeof(*in));
+  os << user_data_len;
+  os.write(user_data, user_data_len);
+  os << model->v;
+  os.close();
+  return true;
+#else
+  return false;
+#endif
+}
+
+int64_t caliptra_verilated_restore(struct caliptra_verilated* model,
+                                   const char* path,
+                                   struct caliptra_verilated_sig_in* in,
+                                   uint8_t* user_data, uint32_t user_data_len) {
+#ifdef CALIPTRA_VERILATED_SAVABLE
+  VerilatedRestore os;
+  os.open(path);
+  if (!os.isOpen()) {
+    return -1;
+  }
+  uint32_t saved_len = 0;
+  os >> model->sim_time;
+  os.read(in, sizeof(*in));
+  os >> saved_len;
+  std::string saved_user_data(saved_len, '\0');
+  os.read(&saved_user_data[0], saved_len);
+  os >> model->v;
+  os.close();
+
+  memcpy(user_data, saved_user_data.data(),
+         saved_len < user_data_len ? saved_len : user_data_len);
+  if (model->window_ticks) {
+    // Don't splice the trace from before the restore with the trace after
+    start_window_chunk(model);
+    model->window_prev.clear();
+  }
+  return saved_len;
+#else
+  return -1;
+#endif
+}
+
 void caliptra_verilated_eval(struct caliptra_verilated* model,
                              const struct caliptra_verilated_sig_in* in,
                              struct caliptra_verilated_sig_out* out) {
@@ -82,6 +230,10 @@ void caliptra_verilated_eval(struct caliptra_verilated* model,
 
   if (model->tfp.get()) {
     model->tfp->dump(model->sim_time++);
+    if (model->window_ticks &&
+        model->sim_time - model->window_start >= model->window_ticks) {
+      start_window_chunk(model);
+    }
   }
 
   out->ready_for_fuses = v->ready_for_fuses;
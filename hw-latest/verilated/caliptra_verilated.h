@@ -90,6 +90,40 @@ void caliptra_verilated_destroy(struct caliptra_verilated* model);
 void caliptra_verilated_trace(struct caliptra_verilated* model,
                               const char* vcd_out_path, int depth);
 
+// Like caliptra_verilated_trace, but keeps the trace in memory instead of
+// writing it to a file, discarding all but the last `window_cycles` to
+// `2 * window_cycles` clock cycles. Use caliptra_verilated_trace_window_save
+// to write the retained window to a VCD file. If `window_cycles` is 0, the
+// model will stop any tracing previously started.
+void caliptra_verilated_trace_window(struct caliptra_verilated* model,
+                                     int depth, uint64_t window_cycles);
+
+// Writes the trace window retained by caliptra_verilated_trace_window to a VCD
+// file at `vcd_out_path`. Returns false if windowed tracing isn't enabled or
+// the file could not be written.
+bool caliptra_verilated_trace_window_save(struct caliptra_verilated* model,
+                                          const char* vcd_out_path);
+
+// Saves the state of the model, the signals in `in`, and `user_data` to a
+// checkpoint file at `path`. Returns false if the file could not be written
+// or the model was verilated without --savable (the "savable" feature of the
+// Rust crate).
+bool caliptra_verilated_save(struct caliptra_verilated* model,
+                             const char* path,
+                             const struct caliptra_verilated_sig_in* in,
+                             const uint8_t* user_data, uint32_t user_data_len);
+
+// Restores the state of the model from a checkpoint written by
+// caliptra_verilated_save into the model and `in`, and copies up to
+// `user_data_len` bytes of its user data into `user_data`. Returns the length
+// of the saved user data, or -1 if the file could not be read or the model was
+// verilated without --savable. The checkpoint must have been saved from a
+// model built from the same RTL.
+int64_t caliptra_verilated_restore(struct caliptra_verilated* model,
+                                   const char* path,
+                                   struct caliptra_verilated_sig_in* in,
+                                   uint8_t* user_data, uint32_t user_data_len);
+
 // Evaluates the model into out, then copies all `in` signals into psuedo
 // flip-flops that will be visible to always_ff blocks in subsequent
 // evaluations.
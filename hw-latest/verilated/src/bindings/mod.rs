@@ -31,6 +31,37 @@ mod disabled {
     ) {
         panic!("{}", MSG);
     }
+    pub unsafe fn caliptra_verilated_trace_window(
+        _model: *mut caliptra_verilated,
+        _depth: ::std::os::raw::c_int,
+        _window_cycles: u64,
+    ) {
+        panic!("{}", MSG);
+    }
+    pub unsafe fn caliptra_verilated_trace_window_save(
+        _model: *mut caliptra_verilated,
+        _vcd_out_path: *const ::std::os::raw::c_char,
+    ) -> bool {
+        panic!("{}", MSG);
+    }
+    pub unsafe fn caliptra_verilated_save(
+        _model: *mut caliptra_verilated,
+        _path: *const ::std::os::raw::c_char,
+        _in_: *const caliptra_verilated_sig_in,
+        _user_data: *const u8,
+        _user_data_len: u32,
+    ) -> bool {
+        panic!("{}", MSG);
+    }
+    pub unsafe fn caliptra_verilated_restore(
+        _model: *mut caliptra_verilated,
+        _path: *const ::std::os::raw::c_char,
+        _in_: *mut caliptra_verilated_sig_in,
+        _user_data: *mut u8,
+        _user_data_len: u32,
+    ) -> i64 {
+        panic!("{}", MSG);
+    }
     pub unsafe fn caliptra_verilated_eval(
         _model: *mut caliptra_verilated,
         _in_: *const caliptra_verilated_sig_in,
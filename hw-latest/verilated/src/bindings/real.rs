@@ -78,6 +78,37 @@ extern "C" {
         depth: ::std::os::raw::c_int,
     );
 }
+extern "C" {
+    pub fn caliptra_verilated_trace_window(
+        model: *mut caliptra_verilated,
+        depth: ::std::os::raw::c_int,
+        window_cycles: u64,
+    );
+}
+extern "C" {
+    pub fn caliptra_verilated_trace_window_save(
+        model: *mut caliptra_verilated,
+        vcd_out_path: *const ::std::os::raw::c_char,
+    ) -> bool;
+}
+extern "C" {
+    pub fn caliptra_verilated_save(
+        model: *mut caliptra_verilated,
+        path: *const ::std::os::raw::c_char,
+        in_: *const caliptra_verilated_sig_in,
+        user_data: *const u8,
+        user_data_len: u32,
+    ) -> bool;
+}
+extern "C" {
+    pub fn caliptra_verilated_restore(
+        model: *mut caliptra_verilated,
+        path: *const ::std::os::raw::c_char,
+        in_: *mut caliptra_verilated_sig_in,
+        user_data: *mut u8,
+        user_data_len: u32,
+    ) -> i64;
+}
 extern "C" {
     pub fn caliptra_verilated_eval(
         model: *mut caliptra_verilated,
@@ -4,6 +4,7 @@ Licensed under the Apache-2.0 license.
 mod bindings;
 use std::ffi::CString;
 use std::ffi::NulError;
+use std::io;
 use std::ptr::null;
 
 pub use bindings::caliptra_verilated_init_args as InitArgs;
@@ -24,8 +25,22 @@ pub enum AhbTxnType {
     WriteU64,
 }
 impl AhbTxnType {
+    const ALL: [Self; 8] = [
+        Self::ReadU8,
+        Self::ReadU16,
+        Self::ReadU32,
+        Self::ReadU64,
+        Self::WriteU8,
+        Self::WriteU16,
+        Self::WriteU32,
+        Self::WriteU64,
+    ];
+
     pub fn is_write(&self) -> bool {
-        matches!(self, Self::WriteU8 | Self::WriteU16 | Self::WriteU32)
+        matches!(
+            self,
+            Self::WriteU8 | Self::WriteU16 | Self::WriteU32 | Self::WriteU64
+        )
     }
     fn from_signals(hsize: u8, hwrite: bool) -> Self {
         match (hsize, hwrite) {
@@ -63,6 +78,52 @@ impl AhbPendingTxn {
     }
 }
 
+/// The state of `CaliptraVerilated` outside the verilated model, saved with
+/// it in checkpoints.
+#[derive(Debug, Eq, PartialEq)]
+struct CheckpointState {
+    total_cycles: u64,
+    prev_generic_output_wires: Option<u64>,
+    ahb_txn: Option<(AhbTxnType, u32)>,
+}
+impl CheckpointState {
+    const LEN: usize = 22;
+
+    fn to_bytes(&self) -> [u8; Self::LEN] {
+        let mut result = [0u8; Self::LEN];
+        result[0..8].copy_from_slice(&self.total_cycles.to_le_bytes());
+        if let Some(wires) = self.prev_generic_output_wires {
+            result[8] = 1;
+            result[9..17].copy_from_slice(&wires.to_le_bytes());
+        }
+        if let Some((ty, addr)) = self.ahb_txn {
+            // 0 means no transaction
+            result[17] = 1 + AhbTxnType::ALL.iter().position(|&t| t == ty).unwrap() as u8;
+            result[18..22].copy_from_slice(&addr.to_le_bytes());
+        }
+        result
+    }
+
+    fn from_bytes(bytes: &[u8; Self::LEN]) -> Option<Self> {
+        let u64_at = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Some(Self {
+            total_cycles: u64_at(0),
+            prev_generic_output_wires: match bytes[8] {
+                0 => None,
+                1 => Some(u64_at(9)),
+                _ => return None,
+            },
+            ahb_txn: match bytes[17] {
+                0 => None,
+                ty => Some((
+                    *AhbTxnType::ALL.get(usize::from(ty) - 1)?,
+                    u32::from_le_bytes(bytes[18..22].try_into().unwrap()),
+                )),
+            },
+        })
+    }
+}
+
 pub struct CaliptraVerilated {
     v: *mut bindings::caliptra_verilated,
     pub input: SigIn,
@@ -144,6 +205,103 @@ impl CaliptraVerilated {
         }
     }
 
+    /// Starts tracing with SystemVerilog module depth `depth` into memory,
+    /// keeping only the last `window_cycles` to `2 * window_cycles` clock
+    /// cycles; use `save_trace_window()` to write them to a VCD file. This
+    /// replaces any tracing previously started.
+    pub fn start_trace_window(&mut self, depth: i32, window_cycles: u64) {
+        unsafe {
+            bindings::caliptra_verilated_trace_window(self.v, depth, window_cycles);
+        }
+    }
+
+    /// Writes the trace window kept since `start_trace_window()` to VCD file
+    /// `path`.
+    pub fn save_trace_window(&mut self, path: &str) -> io::Result<()> {
+        let path_c = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        if unsafe { bindings::caliptra_verilated_trace_window_save(self.v, path_c.as_ptr()) } {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Unable to save trace window to {path:?}; was start_trace_window() called?"
+                ),
+            ))
+        }
+    }
+
+    /// Saves the state of the model to checkpoint file `path`, to be loaded
+    /// later with `restore_checkpoint()`. Requires the "savable" feature.
+    pub fn save_checkpoint(&mut self, path: &str) -> io::Result<()> {
+        let path_c = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let state = CheckpointState {
+            total_cycles: self.total_cycles,
+            prev_generic_output_wires: self.prev_generic_output_wires,
+            ahb_txn: self.ahb_txn.as_ref().map(|txn| (txn.ty, txn.addr)),
+        }
+        .to_bytes();
+        let saved = unsafe {
+            bindings::caliptra_verilated_save(
+                self.v,
+                path_c.as_ptr(),
+                &self.input,
+                state.as_ptr(),
+                state.len() as u32,
+            )
+        };
+        if !saved {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Unable to save checkpoint to {path:?} (checkpoints require the \"savable\" feature)"
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Restores the state of the model from checkpoint file `path`, which
+    /// must have been saved by `save_checkpoint()` from a model built from the
+    /// same RTL. Requires the "savable" feature. The callbacks are not invoked
+    /// for the changes in state.
+    pub fn restore_checkpoint(&mut self, path: &str) -> io::Result<()> {
+        let path_c = CString::new(path).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let mut state = [0u8; CheckpointState::LEN];
+        let state_len = unsafe {
+            bindings::caliptra_verilated_restore(
+                self.v,
+                path_c.as_ptr(),
+                &mut self.input,
+                state.as_mut_ptr(),
+                state.len() as u32,
+            )
+        };
+        if state_len < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "Unable to restore checkpoint from {path:?} (checkpoints require the \"savable\" feature)"
+                ),
+            ));
+        }
+        let state = if state_len == CheckpointState::LEN as i64 {
+            CheckpointState::from_bytes(&state)
+        } else {
+            None
+        };
+        let Some(state) = state else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Checkpoint {path:?} has invalid state"),
+            ));
+        };
+        self.total_cycles = state.total_cycles;
+        self.prev_generic_output_wires = state.prev_generic_output_wires;
+        self.ahb_txn = state.ahb_txn.map(|(ty, addr)| AhbPendingTxn { ty, addr });
+        Ok(())
+    }
+
     /// Evaluates the model into self.output, then copies all `self.input`
     /// signals into psuedo flip-flops that will be visible to always_ff blocks
     /// in subsequent evaluations. Typically `next_cycle_high` is used instead.
@@ -318,4 +476,86 @@ mod tests {
         v.stop_tracing();
         v.next_cycle_high(2);
     }
+
+    #[test]
+    fn test_trace_window() {
+        if !cfg!(feature = "verilator") {
+            return;
+        }
+        let mut v = CaliptraVerilated::new(InitArgs {
+            security_state: 0,
+            cptra_obf_key: [0u32; 8],
+        });
+        let path = "/tmp/caliptra_verilated_test_window.vcd";
+        std::fs::remove_file(path).ok();
+
+        assert!(v.save_trace_window(path).is_err());
+        v.start_trace_window(99, 10);
+        v.next_cycle_high(100);
+        v.save_trace_window(path).unwrap();
+        let vcd = std::fs::read_to_string(path).unwrap();
+
+        // One header, and no more than the last 20 cycles (two dumps per cycle)
+        assert_eq!(vcd.matches("$enddefinitions").count(), 1);
+        let times: Vec<u64> = vcd
+            .lines()
+            .filter_map(|l| l.strip_prefix('#')?.parse().ok())
+            .collect();
+        assert!(*times.first().unwrap() >= 160);
+        assert_eq!(*times.last().unwrap(), 199);
+
+        v.stop_tracing();
+        assert!(v.save_trace_window(path).is_err());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        if !cfg!(feature = "verilator") || !cfg!(feature = "savable") {
+            return;
+        }
+        let new_model = || {
+            CaliptraVerilated::new(InitArgs {
+                security_state: 0,
+                cptra_obf_key: [0u32; 8],
+            })
+        };
+        let path = "/tmp/caliptra_verilated_test_checkpoint.bin";
+        let mut v = new_model();
+        v.input.cptra_pwrgood = true;
+        v.next_cycle_high(1);
+        v.input.cptra_rst_b = true;
+        v.next_cycle_high(1);
+        while !v.output.ready_for_fuses {
+            v.next_cycle_high(1);
+        }
+        v.save_checkpoint(path).unwrap();
+
+        let mut restored = new_model();
+        restored.restore_checkpoint(path).unwrap();
+        assert_eq!(restored.total_cycles(), v.total_cycles());
+        assert!(restored.input.cptra_rst_b);
+        restored.next_cycle_high(1);
+        assert!(restored.output.ready_for_fuses);
+    }
+
+    #[test]
+    fn test_checkpoint_state() {
+        let state = CheckpointState {
+            total_cycles: 0x1234_5678_9abc,
+            prev_generic_output_wires: Some(0x1ff),
+            ahb_txn: Some((AhbTxnType::WriteU16, 0x5000_0010)),
+        };
+        assert_eq!(CheckpointState::from_bytes(&state.to_bytes()), Some(state));
+
+        let state = CheckpointState {
+            total_cycles: 0,
+            prev_generic_output_wires: None,
+            ahb_txn: None,
+        };
+        assert_eq!(CheckpointState::from_bytes(&state.to_bytes()), Some(state));
+
+        let mut bytes = [0u8; CheckpointState::LEN];
+        bytes[17] = 9;
+        assert_eq!(CheckpointState::from_bytes(&bytes), None);
+    }
 }
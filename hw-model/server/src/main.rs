@@ -0,0 +1,64 @@
+// Licensed under the Apache-2.0 license
+
+//! Serves models over TCP or a Unix socket, so SoC simulators in other
+//! processes can drive Caliptra. See `caliptra_hw_model::remote_protocol`
+//! for the protocol.
+//!
+//! Usage: `caliptra-hw-model-server <HOST:PORT | unix:PATH>`
+
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::process::exit;
+
+use caliptra_hw_model::{remote_protocol, DefaultHwModel};
+
+fn serve_connection(stream: impl Read + Write + Send + 'static, name: String) {
+    std::thread::spawn(move || {
+        eprintln!("{name}: connected");
+        match remote_protocol::serve::<DefaultHwModel>(stream, || Box::new(io::stderr())) {
+            Ok(()) => eprintln!("{name}: disconnected"),
+            Err(e) => eprintln!("{name}: {e}"),
+        }
+    });
+}
+
+#[cfg(unix)]
+fn serve_unix(path: &str) -> io::Result<()> {
+    let listener = UnixListener::bind(path)?;
+    eprintln!("Listening on unix:{path}");
+    for (i, stream) in listener.incoming().enumerate() {
+        serve_connection(stream?, format!("connection {i}"));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_path: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Unix sockets are not supported on this platform",
+    ))
+}
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().collect();
+    let [_, addr] = args.as_slice() else {
+        eprintln!("Usage: {} <HOST:PORT | unix:PATH>", args[0]);
+        exit(1);
+    };
+    if let Some(path) = addr.strip_prefix("unix:") {
+        serve_unix(path)?;
+    } else {
+        let listener = TcpListener::bind(addr)?;
+        eprintln!("Listening on {}", listener.local_addr()?);
+        for stream in listener.incoming() {
+            let stream = stream?;
+            stream.set_nodelay(true)?;
+            let name = stream.peer_addr()?.to_string();
+            serve_connection(stream, name);
+        }
+    }
+    Ok(())
+}
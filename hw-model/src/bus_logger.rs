@@ -8,9 +8,11 @@ use std::{
     rc::Rc,
 };
 
-use caliptra_emu_bus::{Bus, BusError};
+use caliptra_emu_bus::{Bus, BusError, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
 
+use crate::equivalence::{BusTransaction, MMIO_RANGE};
+
 #[derive(Clone)]
 pub struct LogFile(Rc<RefCell<BufWriter<File>>>);
 impl LogFile {
@@ -49,10 +51,30 @@ impl Bus for NullBus {
 pub struct BusLogger<TBus: Bus> {
     pub bus: TBus,
     pub log: Option<LogFile>,
+
+    // Successful uC transactions to MMIO_RANGE, if capture is enabled
+    pub capture: Option<Vec<BusTransaction>>,
 }
 impl<TBus: Bus> BusLogger<TBus> {
     pub fn new(bus: TBus) -> Self {
-        Self { bus, log: None }
+        Self {
+            bus,
+            log: None,
+            capture: None,
+        }
+    }
+    fn capture(&mut self, bus_name: &str, write: bool, size: RvSize, addr: RvAddr, val: RvData) {
+        if bus_name != "UC" || !MMIO_RANGE.contains(&addr) {
+            return;
+        }
+        if let Some(capture) = &mut self.capture {
+            capture.push(BusTransaction {
+                write,
+                size,
+                addr,
+                val,
+            });
+        }
     }
     pub fn log_read(
         &mut self,
@@ -61,6 +83,9 @@ impl<TBus: Bus> BusLogger<TBus> {
         addr: RvAddr,
         result: Result<RvData, caliptra_emu_bus::BusError>,
     ) {
+        if let Ok(val) = result {
+            self.capture(bus_name, false, size, addr, val);
+        }
         if addr < 0x1000_0000 {
             // Don't care about memory
             return;
@@ -85,6 +110,9 @@ impl<TBus: Bus> BusLogger<TBus> {
         val: RvData,
         result: Result<(), caliptra_emu_bus::BusError>,
     ) {
+        if result.is_ok() {
+            self.capture(bus_name, true, size, addr, val);
+        }
         if addr < 0x1000_0000 {
             // Don't care about memory
             return;
@@ -104,6 +132,15 @@ impl<TBus: Bus> BusLogger<TBus> {
         }
     }
 }
+impl<TBus: Bus + Snapshot> Snapshot for BusLogger<TBus> {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.bus.save(w)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.bus.restore(r)
+    }
+}
 impl<TBus: Bus> Bus for BusLogger<TBus> {
     fn read(&mut self, size: RvSize, addr: RvAddr) -> Result<RvData, caliptra_emu_bus::BusError> {
         let result = self.bus.read(size, addr);
@@ -130,4 +167,7 @@ impl<TBus: Bus> Bus for BusLogger<TBus> {
     fn update_reset(&mut self) {
         self.bus.update_reset();
     }
+    fn instr_mem_generation(&self) -> u64 {
+        self.bus.instr_mem_generation()
+    }
 }
@@ -0,0 +1,376 @@
+// Licensed under the Apache-2.0 license
+
+//! A lock-step runner that executes the same firmware on two models (usually
+//! [`ModelEmulated`](crate::ModelEmulated) and `ModelVerilated`) and stops at
+//! the first point where the microcontroller's MMIO transactions differ.
+//!
+//! The models don't agree on timing, so transactions are compared in order
+//! rather than by cycle. Consecutive reads from the same address are merged
+//! (keeping the last value read), so polling loops that spin a different
+//! number of times on each model still compare equal. The verilated model
+//! doesn't expose the retired PC, so the comparison is limited to the bus.
+//!
+//! Both models should be created with the same [`InitParams`](crate::InitParams)
+//! (including `deterministic: true`, so they see the same TRNG inputs) and
+//! booted to the same point before calling [`run_lockstep()`]:
+//!
+//! ```ignore
+//! let mut emu = ModelEmulated::new(boot_params())?;
+//! let mut rtl = ModelVerilated::new(boot_params())?;
+//! if let Err(divergence) = equivalence::run_lockstep(&mut emu, &mut rtl, &Default::default()) {
+//!     panic!("{divergence}");
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::ops::Range;
+
+use caliptra_emu_types::{RvAddr, RvData, RvSize};
+
+use crate::{HwModel, ModelError};
+
+/// The addresses of the peripherals and mailbox SRAM on the microcontroller's
+/// AHB bus. Accesses to the ROM, ICCM, DCCM and PIC never leave the core in
+/// the RTL, so they aren't captured.
+pub const MMIO_RANGE: Range<RvAddr> = 0x1000_0000..0x4000_0000;
+
+/// A load or store executed by the microcontroller.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BusTransaction {
+    pub write: bool,
+    pub size: RvSize,
+    pub addr: RvAddr,
+
+    /// The value written, or the value returned by the read.
+    pub val: RvData,
+}
+
+impl Display for BusTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = usize::from(self.size);
+        if self.write {
+            write!(f, "write{size} *0x{:08x} <- 0x{:x}", self.addr, self.val)
+        } else {
+            write!(f, " read{size} *0x{:08x} -> 0x{:x}", self.addr, self.val)
+        }
+    }
+}
+
+pub struct LockstepOptions {
+    /// Stop stepping a model once it has run this many cycles.
+    pub max_cycles: u64,
+
+    /// Transactions to these addresses are not compared, for registers whose
+    /// values legitimately differ between models (such as timers).
+    pub ignore: Vec<Range<RvAddr>>,
+
+    /// The number of matching transactions before a divergence to include in
+    /// its report.
+    pub context: usize,
+}
+
+impl Default for LockstepOptions {
+    fn default() -> Self {
+        Self {
+            max_cycles: 40_000_000,
+            ignore: vec![],
+            context: 16,
+        }
+    }
+}
+
+/// The result of a run in which the models never diverged.
+#[derive(Debug, Eq, PartialEq)]
+pub struct LockstepSummary {
+    /// The number of (merged) transactions compared.
+    pub transactions: usize,
+    pub cycles: (u64, u64),
+}
+
+/// The first transaction that differed between the models.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Divergence {
+    /// The index of the transaction in the (merged) sequence.
+    pub index: usize,
+
+    /// The transactions of the first and second model, or None if the model
+    /// exited or ran out of cycles first.
+    pub transactions: (Option<BusTransaction>, Option<BusTransaction>),
+
+    /// The cycle count of each model when its transaction completed.
+    pub cycles: (u64, u64),
+
+    /// The matching transactions leading up to the divergence, oldest first.
+    pub context: Vec<BusTransaction>,
+
+    /// The UART output of each model that hadn't been consumed when the
+    /// models diverged.
+    pub output: (String, String),
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn txn_str(txn: &Option<BusTransaction>) -> String {
+            match txn {
+                Some(txn) => txn.to_string(),
+                None => "(none)".into(),
+            }
+        }
+        writeln!(
+            f,
+            "Models diverged at transaction {} (cycle {} / {})",
+            self.index, self.cycles.0, self.cycles.1
+        )?;
+        for txn in &self.context {
+            writeln!(f, "    {txn}")?;
+        }
+        writeln!(f, "  first:  {}", txn_str(&self.transactions.0))?;
+        writeln!(f, "  second: {}", txn_str(&self.transactions.1))?;
+        for (name, output) in [("first", &self.output.0), ("second", &self.output.1)] {
+            if !output.is_empty() {
+                writeln!(f, "  {name} output:\n{output}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Filters and merges the transactions captured from one model.
+#[derive(Default)]
+struct TransactionStream {
+    ready: VecDeque<BusTransaction>,
+
+    // A read that may still be merged with the reads after it
+    pending_read: Option<BusTransaction>,
+}
+
+impl TransactionStream {
+    fn push(&mut self, txn: BusTransaction, ignore: &[Range<RvAddr>]) {
+        if ignore.iter().any(|r| r.contains(&txn.addr)) {
+            return;
+        }
+        if let Some(pending) = self.pending_read.take() {
+            if !txn.write && txn.addr == pending.addr && txn.size == pending.size {
+                self.pending_read = Some(txn);
+                return;
+            }
+            self.ready.push_back(pending);
+        }
+        if txn.write {
+            self.ready.push_back(txn);
+        } else {
+            self.pending_read = Some(txn);
+        }
+    }
+
+    fn flush(&mut self) {
+        self.ready.extend(self.pending_read.take());
+    }
+}
+
+/// Step `model` until `stream` has a transaction ready to compare, or the
+/// model exits or reaches `max_cycles`.
+fn next_transaction<T: HwModel>(
+    model: &mut T,
+    stream: &mut TransactionStream,
+    options: &LockstepOptions,
+) -> Option<BusTransaction> {
+    while stream.ready.is_empty() {
+        if model.output().exit_requested() || model.output().sink().now() >= options.max_cycles {
+            stream.flush();
+            break;
+        }
+        model.step();
+        for txn in model.take_bus_capture() {
+            stream.push(txn, &options.ignore);
+        }
+    }
+    stream.ready.pop_front()
+}
+
+/// Step `a` and `b` in lock-step, comparing their MMIO transactions until
+/// both models exit or reach `options.max_cycles`. Returns the first
+/// divergence, if any.
+///
+/// # Panics
+///
+/// If either model doesn't support bus capture.
+pub fn run_lockstep<A: HwModel, B: HwModel>(
+    a: &mut A,
+    b: &mut B,
+    options: &LockstepOptions,
+) -> Result<LockstepSummary, Box<Divergence>> {
+    start_capture(a);
+    start_capture(b);
+    let mut streams = (TransactionStream::default(), TransactionStream::default());
+    let mut context = VecDeque::with_capacity(options.context);
+    let mut index = 0;
+    loop {
+        let txn_a = next_transaction(a, &mut streams.0, options);
+        let txn_b = next_transaction(b, &mut streams.1, options);
+        let cycles = (a.output().sink().now(), b.output().sink().now());
+        match (txn_a, txn_b) {
+            (None, None) => {
+                return Ok(LockstepSummary {
+                    transactions: index,
+                    cycles,
+                })
+            }
+            (Some(txn_a), Some(txn_b)) if txn_a == txn_b => {
+                if options.context > 0 {
+                    if context.len() == options.context {
+                        context.pop_front();
+                    }
+                    context.push_back(txn_a);
+                }
+                index += 1;
+            }
+            transactions => {
+                return Err(Box::new(Divergence {
+                    index,
+                    transactions,
+                    cycles,
+                    context: context.into(),
+                    output: (a.output().take(usize::MAX), b.output().take(usize::MAX)),
+                }))
+            }
+        }
+    }
+}
+
+fn start_capture<T: HwModel>(model: &mut T) {
+    match model.start_bus_capture() {
+        Ok(()) => {}
+        Err(ModelError::BusCaptureUnsupported) => {
+            panic!(
+                "{} does not support bus capture",
+                std::any::type_name::<T>()
+            )
+        }
+        Err(e) => panic!("Unable to start bus capture: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rv32_builder::Rv32Builder;
+    use crate::{InitParams, ModelEmulated};
+
+    // CPTRA_FW_EXTENDED_ERROR_INFO[0..2]
+    const INFO0: u32 = 0x3003_0018;
+    const INFO1: u32 = 0x3003_001c;
+
+    fn new_model(rom: &[u8]) -> ModelEmulated {
+        let mut model = ModelEmulated::new_unbooted(InitParams {
+            rom,
+            deterministic: true,
+            ..Default::default()
+        })
+        .unwrap();
+        model.soc_ifc().cptra_fuse_wr_done().write(|w| w.done(true));
+        model.soc_ifc().cptra_bootfsm_go().write(|w| w.go(true));
+        model
+    }
+
+    fn write(addr: u32, val: u32) -> BusTransaction {
+        BusTransaction {
+            write: true,
+            size: RvSize::Word,
+            addr,
+            val,
+        }
+    }
+
+    fn read(addr: u32, val: u32) -> BusTransaction {
+        BusTransaction {
+            write: false,
+            size: RvSize::Word,
+            addr,
+            val,
+        }
+    }
+
+    #[test]
+    fn test_transaction_stream() {
+        let mut stream = TransactionStream::default();
+        for txn in [
+            read(0x1000_0000, 0),
+            read(0x1000_0000, 0),
+            read(0x1000_0000, 1),
+            write(0x1000_0000, 2),
+            read(0x2000_0000, 3),
+            write(0x2000_0100, 4),
+            read(0x1000_0000, 5),
+            read(0x1000_0000, 6),
+        ] {
+            stream.push(txn, &[0x2000_0000..0x2000_0004, 0x2000_0100..0x2000_0104]);
+        }
+        stream.flush();
+        assert_eq!(
+            Vec::from(stream.ready),
+            vec![
+                read(0x1000_0000, 1),
+                write(0x1000_0000, 2),
+                read(0x1000_0000, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lockstep_equivalent() {
+        let rom = Rv32Builder::new()
+            .store(INFO0, 0x1234_5000)
+            .store(INFO1, 0x6789_a000)
+            .empty_loop()
+            .build();
+        let options = LockstepOptions {
+            max_cycles: 1000,
+            ..Default::default()
+        };
+        let summary = run_lockstep(&mut new_model(&rom), &mut new_model(&rom), &options).unwrap();
+        assert_eq!(summary.transactions, 2);
+    }
+
+    #[test]
+    fn test_lockstep_divergence() {
+        let rom_a = Rv32Builder::new()
+            .store(INFO0, 0x1234_5000)
+            .store(INFO1, 0x6789_a000)
+            .empty_loop()
+            .build();
+        let rom_b = Rv32Builder::new()
+            .store(INFO0, 0x1234_5000)
+            .store(INFO1, 0x6789_b000)
+            .empty_loop()
+            .build();
+        let options = LockstepOptions {
+            max_cycles: 1000,
+            ..Default::default()
+        };
+        let divergence =
+            run_lockstep(&mut new_model(&rom_a), &mut new_model(&rom_b), &options).unwrap_err();
+        assert_eq!(divergence.index, 1);
+        assert_eq!(
+            divergence.transactions,
+            (
+                Some(write(INFO1, 0x6789_a000)),
+                Some(write(INFO1, 0x6789_b000))
+            )
+        );
+        assert_eq!(divergence.context, vec![write(INFO0, 0x1234_5000)]);
+
+        let ignore_info1 = LockstepOptions {
+            ignore: vec![INFO1..INFO1 + 4],
+            ..options
+        };
+        let summary = run_lockstep(
+            &mut new_model(&rom_a),
+            &mut new_model(&rom_b),
+            &ignore_info1,
+        )
+        .unwrap();
+        assert_eq!(summary.transactions, 1);
+    }
+}
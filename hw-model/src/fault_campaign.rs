@@ -0,0 +1,269 @@
+// Licensed under the Apache-2.0 license
+
+//! Helpers for sweeping injected faults over firmware code and classifying
+//! how the firmware reacted to each one.
+//!
+//! For example, to check that no instruction in the ROM's image verifier
+//! can be skipped to accept an image with a bad signature (see
+//! `test_fault_campaign.rs` in the ROM integration tests):
+//!
+//! ```ignore
+//! let verify = caliptra_builder::size_report(&rom_elf)?
+//!     .functions
+//!     .into_iter()
+//!     .find(|f| f.name.ends_with(">::verify"))
+//!     .unwrap();
+//! let results = fault_campaign::run_fault_campaign(
+//!     fault_campaign::skip_faults(verify.addr as u32..(verify.addr + verify.size) as u32),
+//!     || model_about_to_verify_bad_image(),
+//!     |hw| hw.soc_ifc().cptra_boot_status().read() == IMAGE_VERIFICATION_COMPLETE,
+//!     10_000_000,
+//! )?;
+//! assert_eq!(FaultCampaignSummary::new(&results).unexpected_success, 0);
+//! ```
+
+use std::ops::Range;
+
+use caliptra_api::error::CaliptraError;
+
+use crate::{Fault, HwModel, ModelError};
+
+/// How the firmware reacted to an injected fault.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FaultOutcome {
+    /// The fault never triggered before the run ended (for example, the
+    /// instruction was never executed), so the run says nothing about it.
+    NotTriggered,
+
+    /// The firmware reported a fatal error (other than a CFI panic).
+    FatalError(u32),
+
+    /// The firmware's control-flow integrity checks caught the fault.
+    CfiPanic(u32),
+
+    /// The firmware reached the success condition in spite of the fault.
+    UnexpectedSuccess,
+
+    /// Neither an error nor success was seen within the cycle budget.
+    Hang,
+}
+
+impl FaultOutcome {
+    fn from_fatal_error(code: u32) -> Self {
+        let cfi_panics = u32::from(CaliptraError::ROM_CFI_PANIC_UNKNOWN)
+            ..=u32::from(CaliptraError::ROM_CFI_PANIC_FAKE_TRNG_USED_WITH_DEBUG_LOCK);
+        if cfi_panics.contains(&code) {
+            Self::CfiPanic(code)
+        } else {
+            Self::FatalError(code)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FaultResult {
+    pub fault: Fault,
+    pub outcome: FaultOutcome,
+}
+
+/// The number of faults with each outcome.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FaultCampaignSummary {
+    pub not_triggered: usize,
+    pub fatal_error: usize,
+    pub cfi_panic: usize,
+    pub unexpected_success: usize,
+    pub hang: usize,
+}
+
+impl FaultCampaignSummary {
+    pub fn new(results: &[FaultResult]) -> Self {
+        let mut result = Self::default();
+        for r in results {
+            match r.outcome {
+                FaultOutcome::NotTriggered => result.not_triggered += 1,
+                FaultOutcome::FatalError(_) => result.fatal_error += 1,
+                FaultOutcome::CfiPanic(_) => result.cfi_panic += 1,
+                FaultOutcome::UnexpectedSuccess => result.unexpected_success += 1,
+                FaultOutcome::Hang => result.hang += 1,
+            }
+        }
+        result
+    }
+}
+
+/// A skip fault for every halfword in `addrs` (typically a symbol's range
+/// from `caliptra_builder::elf_symbols()`). Addresses in the middle of a
+/// 32-bit instruction never trigger.
+pub fn skip_faults(addrs: Range<u32>) -> impl Iterator<Item = Fault> {
+    addrs.step_by(2).map(|pc| Fault::SkipInstr { pc })
+}
+
+/// Inject `fault` into `model` and step until the firmware reports a fatal
+/// error, `is_success` returns true, or `max_cycles` have elapsed. Fails
+/// without stepping if the model can't inject `fault`.
+pub fn run_fault<T: HwModel>(
+    model: &mut T,
+    fault: Fault,
+    mut is_success: impl FnMut(&mut T) -> bool,
+    max_cycles: u64,
+) -> Result<FaultOutcome, ModelError> {
+    model.inject_fault(fault)?;
+    let mut outcome = FaultOutcome::Hang;
+    for _ in 0..max_cycles {
+        let fatal_error = model.soc_ifc().cptra_fw_error_fatal().read();
+        if fatal_error != 0 {
+            outcome = FaultOutcome::from_fatal_error(fatal_error);
+            break;
+        }
+        if is_success(model) {
+            outcome = FaultOutcome::UnexpectedSuccess;
+            break;
+        }
+        model.step();
+    }
+    if model.pending_faults() != 0 {
+        return Ok(FaultOutcome::NotTriggered);
+    }
+    Ok(outcome)
+}
+
+/// Run each of `faults` against a fresh model from `new_model` and classify
+/// the outcome. `new_model` should return a model that hasn't yet executed
+/// the code the faults target. Stops at the first fault the model can't
+/// inject.
+pub fn run_fault_campaign<T: HwModel>(
+    faults: impl IntoIterator<Item = Fault>,
+    mut new_model: impl FnMut() -> T,
+    mut is_success: impl FnMut(&mut T) -> bool,
+    max_cycles: u64,
+) -> Result<Vec<FaultResult>, ModelError> {
+    faults
+        .into_iter()
+        .map(|fault| {
+            Ok(FaultResult {
+                fault,
+                outcome: run_fault(&mut new_model(), fault, &mut is_success, max_cycles)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{mmio::Rv32GenMmio, DefaultHwModel, InitParams};
+    use caliptra_registers::soc_ifc;
+
+    fn new_model(rom: &[u8]) -> DefaultHwModel {
+        let mut model = crate::new_unbooted(InitParams {
+            rom,
+            ..Default::default()
+        })
+        .unwrap();
+        model.soc_ifc().cptra_fuse_wr_done().write(|w| w.done(true));
+        model.soc_ifc().cptra_bootfsm_go().write(|w| w.go(true));
+        model
+    }
+
+    #[test]
+    fn test_skip_fault_campaign() {
+        // lui x5; lui x6; addi x6; sw x6 -> CPTRA_FW_ERROR_FATAL
+        let rv32_gen = Rv32GenMmio::new();
+        let soc_ifc =
+            unsafe { soc_ifc::RegisterBlock::new_with_mmio(0x3003_0000 as *mut u32, &rv32_gen) };
+        soc_ifc
+            .cptra_fw_error_fatal()
+            .write(|_| CaliptraError::ROM_CFI_PANIC_ASSERT_EQ_FAILURE.into());
+        let rom = rv32_gen.into_inner().empty_loop().build();
+
+        let results = run_fault_campaign(
+            [0, 2, 8, 12].map(|pc| Fault::SkipInstr { pc }),
+            || new_model(&rom),
+            |_| false,
+            1000,
+        )
+        .unwrap();
+        let outcomes: Vec<_> = results.iter().map(|r| r.outcome).collect();
+        assert_eq!(
+            outcomes,
+            [
+                // The store to ROM traps and the ROM starts over
+                FaultOutcome::CfiPanic(CaliptraError::ROM_CFI_PANIC_ASSERT_EQ_FAILURE.into()),
+                // Not an instruction boundary
+                FaultOutcome::NotTriggered,
+                // The low bits of the error code were never added
+                FaultOutcome::FatalError(0x0104_0000),
+                // The error is never reported
+                FaultOutcome::Hang,
+            ]
+        );
+        assert_eq!(
+            FaultCampaignSummary::new(&results),
+            FaultCampaignSummary {
+                not_triggered: 1,
+                fatal_error: 1,
+                cfi_panic: 1,
+                unexpected_success: 0,
+                hang: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_invalid_faults() {
+        let rom = Rv32GenMmio::new().into_inner().empty_loop().build();
+        let mut model = new_model(&rom);
+        for fault in [
+            Fault::FlipRegBit {
+                cycle: 0,
+                reg: 0,
+                bit: 0,
+            },
+            Fault::FlipRegBit {
+                cycle: 0,
+                reg: 1,
+                bit: 32,
+            },
+            Fault::FlipMemBit {
+                cycle: 0,
+                addr: 0,
+                bit: 0,
+            },
+            Fault::FlipMemBit {
+                cycle: 0,
+                addr: 0x5000_0000,
+                bit: 8,
+            },
+        ] {
+            assert_eq!(
+                model.inject_fault(fault),
+                Err(crate::ModelError::InvalidFault(fault))
+            );
+        }
+        assert_eq!(model.pending_faults(), 0);
+
+        let invalid = Fault::FlipMemBit {
+            cycle: 0,
+            addr: 0,
+            bit: 0,
+        };
+        assert_eq!(
+            run_fault_campaign([invalid], || new_model(&rom), |_| false, 1000),
+            Err(crate::ModelError::InvalidFault(invalid))
+        );
+
+        model
+            .inject_fault(Fault::FlipMemBit {
+                cycle: 10,
+                addr: 0x5000_0000,
+                bit: 7,
+            })
+            .unwrap();
+        assert_eq!(model.pending_faults(), 1);
+        for _ in 0..100 {
+            model.step();
+        }
+        assert_eq!(model.pending_faults(), 0);
+    }
+}
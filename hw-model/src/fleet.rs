@@ -0,0 +1,176 @@
+// Licensed under the Apache-2.0 license
+
+//! Helpers for running many independent models in parallel, for tests that
+//! need a fleet of distinct devices (such as provisioning service tests).
+//!
+//! Models aren't `Send`, so each instance is created, run and dropped on the
+//! worker thread that picked it up; only the results cross threads. For
+//! example, to collect the IDevID CSRs of 100 devices with distinct UDS seeds:
+//!
+//! ```ignore
+//! let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env())?;
+//! let csrs = fleet::collect_idevid_csrs(&rom, 100, |i| fleet::fleet_fuses(&Fuses::default(), i));
+//! ```
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{BootParams, Fuses, HwModel, InitParams};
+
+const MAX_CSR_WAIT_CYCLES: u64 = 40_000_000;
+
+/// Call `f` with each index in `0..count`, spreading the calls over one
+/// thread per available CPU. Returns the results in index order.
+pub fn run_fleet<R: Send>(count: usize, f: impl Fn(usize) -> R + Sync) -> Vec<R> {
+    let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    run_fleet_with_threads(count, threads, f)
+}
+
+/// Like [`run_fleet`], but with an explicit number of worker threads.
+pub fn run_fleet_with_threads<R: Send>(
+    count: usize,
+    threads: usize,
+    f: impl Fn(usize) -> R + Sync,
+) -> Vec<R> {
+    let next_index = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new((0..count).map(|_| None).collect());
+    std::thread::scope(|s| {
+        for _ in 0..threads.clamp(1, count.max(1)) {
+            s.spawn(|| loop {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                if index >= count {
+                    break;
+                }
+                let result = f(index);
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.unwrap())
+        .collect()
+}
+
+/// `base` with a UDS seed and field entropy unique to device `index`. The
+/// values are derived from `index`, so a fleet is the same from run to run.
+pub fn fleet_fuses(base: &Fuses, index: usize) -> Fuses {
+    let mut rng = StdRng::seed_from_u64(index as u64);
+    let mut fuses = *base;
+    rng.fill(&mut fuses.uds_seed);
+    rng.fill(&mut fuses.field_entropy);
+    fuses
+}
+
+/// Boot `count` devices from `rom` with the fuses returned by `fuses`,
+/// request the IDevID CSR from each, and return the CSRs in index order.
+///
+/// # Panics
+///
+/// If a device fails to boot, reports a fatal error or doesn't produce a
+/// CSR within the boot time budget.
+pub fn collect_idevid_csrs(
+    rom: &[u8],
+    count: usize,
+    fuses: impl Fn(usize) -> Fuses + Sync,
+) -> Vec<Vec<u8>> {
+    run_fleet(count, |index| {
+        let mut hw = crate::new(BootParams {
+            init_params: InitParams {
+                rom,
+                log_writer: Box::new(std::io::sink()),
+                ..Default::default()
+            },
+            fuses: fuses(index),
+            // Request the IDevID CSR (CPTRA_DBG_MANUF_SERVICE_REG bit 0)
+            initial_dbg_manuf_service_reg: 0x1,
+            ..Default::default()
+        })
+        .unwrap_or_else(|e| panic!("device {index} failed to boot: {e}"));
+        idevid_csr(&mut hw).unwrap_or_else(|e| panic!("device {index}: {e}"))
+    })
+}
+
+fn idevid_csr(hw: &mut impl HwModel) -> Result<Vec<u8>, String> {
+    for _ in 0..MAX_CSR_WAIT_CYCLES {
+        let fatal_error = hw.soc_ifc().cptra_fw_error_fatal().read();
+        if fatal_error != 0 {
+            return Err(format!("fatal error 0x{fatal_error:08x}"));
+        }
+        if hw.soc_ifc().cptra_flow_status().read().idevid_csr_ready() {
+            let mut txn = hw.wait_for_mailbox_receive().map_err(|e| e.to_string())?;
+            let csr = std::mem::take(&mut txn.req.data);
+            txn.respond_success();
+            hw.soc_ifc().cptra_dbg_manuf_service_reg().write(|_| 0);
+            return Ok(csr);
+        }
+        hw.step();
+    }
+    Err(format!("no IDevID CSR after {MAX_CSR_WAIT_CYCLES} cycles"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmio::Rv32GenMmio;
+    use caliptra_registers::soc_ifc;
+
+    #[test]
+    fn test_run_fleet() {
+        assert_eq!(
+            run_fleet_with_threads(100, 4, |i| i * 2),
+            (0..100).map(|i| i * 2).collect::<Vec<_>>()
+        );
+        assert_eq!(run_fleet_with_threads(0, 4, |i| i), Vec::<usize>::new());
+        assert_eq!(run_fleet_with_threads(3, 0, |i| i), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_run_fleet_models() {
+        let rv32_gen = Rv32GenMmio::new();
+        let soc_ifc =
+            unsafe { soc_ifc::RegisterBlock::new_with_mmio(0x3003_0000 as *mut u32, &rv32_gen) };
+        soc_ifc.cptra_boot_status().write(|_| 0x600d_f00d);
+        let rom = rv32_gen.into_inner().empty_loop().build();
+
+        let outputs = run_fleet_with_threads(8, 4, |index| {
+            let mut hw = crate::new(BootParams {
+                init_params: InitParams {
+                    rom: &rom,
+                    log_writer: Box::new(std::io::sink()),
+                    ..Default::default()
+                },
+                fuses: fleet_fuses(&Fuses::default(), index),
+                ..Default::default()
+            })
+            .unwrap();
+            for _ in 0..100 {
+                hw.step();
+            }
+            hw.soc_ifc().cptra_boot_status().read()
+        });
+        assert_eq!(outputs, vec![0x600d_f00d; 8]);
+    }
+
+    #[test]
+    fn test_fleet_fuses() {
+        let base = Fuses {
+            fmc_key_manifest_svn: 5,
+            ..Default::default()
+        };
+        let fleet: Vec<_> = (0..16).map(|i| fleet_fuses(&base, i)).collect();
+        for (i, fuses) in fleet.iter().enumerate() {
+            assert_eq!(fuses.fmc_key_manifest_svn, 5);
+            assert_eq!(fuses.uds_seed, fleet_fuses(&base, i).uds_seed);
+            for other in &fleet[i + 1..] {
+                assert_ne!(fuses.uds_seed, other.uds_seed);
+                assert_ne!(fuses.field_entropy, other.field_entropy);
+            }
+        }
+    }
+}
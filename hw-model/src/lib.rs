@@ -12,7 +12,7 @@ use std::{
 use api::calc_checksum;
 use api::mailbox::{MailboxReqHeader, MailboxRespHeader, Response};
 use caliptra_api as api;
-use caliptra_emu_bus::Bus;
+use caliptra_emu_bus::{Bus, SnapshotError};
 use caliptra_hw_model_types::{
     ErrorInjectionMode, EtrngResponse, RandomEtrngResponses, RandomNibbles, DEFAULT_CPTRA_OBF_KEY,
 };
@@ -26,8 +26,15 @@ use caliptra_registers::soc_ifc::regs::{
 
 use rand::{rngs::StdRng, SeedableRng};
 
+pub mod equivalence;
+pub mod fault_campaign;
+pub mod fleet;
+pub mod mailbox_conformance;
+pub mod mailbox_recording;
 pub mod mmio;
 mod model_emulated;
+mod model_remote;
+pub mod reset_sequence;
 
 mod bus_logger;
 #[cfg(feature = "verilator")]
@@ -37,14 +44,24 @@ mod model_verilated;
 mod model_fpga_realtime;
 
 mod output;
+pub mod remote_protocol;
 mod rv32_builder;
+mod timeline;
 
 pub use caliptra_emu_bus::BusMmio;
-pub use caliptra_hw_model_types::{DeviceLifecycle, Fuses, SecurityState, U4};
+pub use caliptra_hw_model_types::{
+    CryptoTiming, DeviceLifecycle, EntropySrcFault, Fault, Fuses, NoiseSourceFault, SecurityState,
+    U4,
+};
+use equivalence::BusTransaction;
+use mailbox_recording::MailboxRecorder;
 use output::ExitStatus;
 pub use output::Output;
 
 pub use model_emulated::ModelEmulated;
+pub use model_remote::ModelRemote;
+pub use remote_protocol::RunCondition;
+pub use timeline::{TimelineFormat, TimelineSymbol};
 
 #[cfg(feature = "verilator")]
 pub use model_verilated::ModelVerilated;
@@ -64,7 +81,11 @@ pub use model_fpga_realtime::ModelFpgaRealtime;
 /// (used by IDEs) can't fully resolve associated types from `impl Trait`, so
 /// such functions should use `DefaultHwModel` until they fix that. Users should
 /// treat `DefaultHwModel` as if it were `impl HwModel`.
-#[cfg(all(not(feature = "verilator"), not(feature = "fpga_realtime")))]
+#[cfg(all(
+    not(feature = "verilator"),
+    not(feature = "fpga_realtime"),
+    not(feature = "remote")
+))]
 pub type DefaultHwModel = ModelEmulated;
 
 #[cfg(feature = "verilator")]
@@ -73,6 +94,9 @@ pub type DefaultHwModel = ModelVerilated;
 #[cfg(feature = "fpga_realtime")]
 pub type DefaultHwModel = ModelFpgaRealtime;
 
+#[cfg(feature = "remote")]
+pub type DefaultHwModel = ModelRemote;
+
 /// Constructs an HwModel based on the cargo features and environment
 /// variables. Most test cases that need to construct a HwModel should use this
 /// function over HwModel::new_unbooted().
@@ -120,6 +144,8 @@ impl TrngMode {
 
 const EXPECTED_CALIPTRA_BOOT_TIME_IN_CYCLES: u64 = 40_000_000; // 40 million cycles
 
+const DEFAULT_CHECKPOINT_INTERVAL_CYCLES: u64 = 1_000_000;
+
 pub struct InitParams<'a> {
     // The contents of the boot ROM
     pub rom: &'a [u8],
@@ -141,6 +167,16 @@ pub struct InitParams<'a> {
     // peripheral).
     pub itrng_nibbles: Box<dyn Iterator<Item = u8> + Send>,
 
+    // A fault to inject into the noise source behind the internal TRNG, used
+    // to exercise the ENTROPY_SRC health tests. Only supported by the
+    // emulator.
+    pub entropy_src_fault: Option<EntropySrcFault>,
+
+    // The number of cycles each crypto engine operation takes. Defaults to
+    // CryptoTiming::FAST; use CryptoTiming::VERILATED to estimate boot time.
+    // Only supported by the emulator.
+    pub crypto_timing: CryptoTiming,
+
     // Pre-conditioned TRNG responses to return over the soc_ifc CPTRA_TRNG_DATA
     // registers in response to requests via CPTRA_TRNG_STATUS
     pub etrng_responses: Box<dyn Iterator<Item = EtrngResponse> + Send>,
@@ -158,6 +194,59 @@ pub struct InitParams<'a> {
     // A trace path to use. If None, the CPTRA_TRACE_PATH environment variable
     // will be used
     pub trace_path: Option<PathBuf>,
+
+    // If set and the trace path ends in ".vcd", only keep a rolling window of
+    // roughly the last `trace_window_cycles` cycles of the trace in memory,
+    // and write it to the trace path when the model hits a fatal error or is
+    // dropped during a panic (a failed assertion or timeout). If None, the
+    // CPTRA_TRACE_WINDOW_CYCLES environment variable will be used. Only
+    // supported by verilator.
+    pub trace_window_cycles: Option<u64>,
+
+    // A directory to periodically write checkpoints of the model state to
+    // (see ModelVerilated::restore_checkpoint()). If None, the
+    // CPTRA_CHECKPOINT_DIR environment variable will be used. Only supported
+    // by verilator built with the "savable" feature.
+    pub checkpoint_dir: Option<PathBuf>,
+
+    // The number of cycles between checkpoints written to checkpoint_dir.
+    pub checkpoint_interval_cycles: u64,
+
+    // If true, the emulator snapshots its state at power-on, which
+    // HwModel::cold_reset() restores. The snapshot holds a copy of every
+    // memory, so it is only taken when asked for; without it, the emulator's
+    // cold_reset() returns ModelError::ColdResetUnsupported. The other models
+    // support cold_reset() regardless.
+    pub save_power_on_state: bool,
+
+    // A file holding the contents of the SPI NOR flash attached to the SPI
+    // host. Writes to the flash are written back to the file. If None, no
+    // flash is attached. Only supported by the emulator.
+    pub spi_flash_path: Option<PathBuf>,
+
+    // A file to write a timeline of CPU, crypto engine, mailbox and boot
+    // status activity to. Paths ending in ".vcd" are written as VCD, all
+    // others as Chrome trace JSON. If None, the CPTRA_TIMELINE_PATH
+    // environment variable will be used. Only supported by the emulator.
+    pub timeline_path: Option<PathBuf>,
+
+    // Symbols used to label the program counter in the timeline (see
+    // caliptra_builder::elf_symbols()).
+    pub timeline_symbols: Vec<TimelineSymbol>,
+
+    // A file to record every mailbox transaction executed from the SoC to
+    // (see mailbox_recording). If None, the CPTRA_MAILBOX_RECORD_PATH
+    // environment variable will be used. Only supported by the emulator and
+    // verilator.
+    pub mailbox_record_path: Option<PathBuf>,
+
+    // If true, itrng_nibbles and etrng_responses are replaced with sequences
+    // derived from a fixed seed (whatever CPTRA_TRNG_SEED is set to), so the
+    // externally visible outputs of a run (certs, CSRs, PCRs, UART log) are
+    // reproducible bit for bit. Verilator also seeds the random SRAM PUF
+    // state from it; the emulator's memories always start zeroed.
+    // Supported by the emulator and verilator; the FPGA model returns an error.
+    pub deterministic: bool,
 }
 
 impl<'a> Default for InitParams<'a> {
@@ -185,11 +274,39 @@ impl<'a> Default for InitParams<'a> {
                 .set_device_lifecycle(DeviceLifecycle::Unprovisioned),
             cptra_obf_key: DEFAULT_CPTRA_OBF_KEY,
             itrng_nibbles,
+            entropy_src_fault: None,
+            crypto_timing: Default::default(),
             etrng_responses,
             trng_mode: Default::default(),
             wdt_timeout_cycles: EXPECTED_CALIPTRA_BOOT_TIME_IN_CYCLES,
             random_sram_puf: true,
             trace_path: None,
+            trace_window_cycles: None,
+            checkpoint_dir: None,
+            checkpoint_interval_cycles: DEFAULT_CHECKPOINT_INTERVAL_CYCLES,
+            save_power_on_state: false,
+            spi_flash_path: None,
+            timeline_path: None,
+            timeline_symbols: vec![],
+            mailbox_record_path: None,
+            deterministic: false,
+        }
+    }
+}
+
+// The seed used for all randomness when InitParams::deterministic is set.
+const DETERMINISTIC_SEED: u64 = 0;
+
+impl<'a> InitParams<'a> {
+    /// If `deterministic` is set, replace the TRNG inputs with sequences
+    /// derived from a fixed seed. Called by the models before consuming the
+    /// TRNG inputs.
+    fn pin_trng_inputs(&mut self) {
+        if self.deterministic {
+            self.itrng_nibbles = Box::new(RandomNibbles(StdRng::seed_from_u64(DETERMINISTIC_SEED)));
+            self.etrng_responses = Box::new(RandomEtrngResponses(StdRng::seed_from_u64(
+                DETERMINISTIC_SEED,
+            )));
         }
     }
 }
@@ -201,6 +318,42 @@ fn trace_path_or_env(trace_path: Option<PathBuf>) -> Option<PathBuf> {
     std::env::var("CPTRA_TRACE_PATH").ok().map(PathBuf::from)
 }
 
+#[cfg(feature = "verilator")]
+fn trace_window_cycles_or_env(trace_window_cycles: Option<u64>) -> Option<u64> {
+    if let Some(trace_window_cycles) = trace_window_cycles {
+        return Some(trace_window_cycles);
+    }
+    std::env::var("CPTRA_TRACE_WINDOW_CYCLES")
+        .ok()
+        .and_then(|s| u64::from_str(&s).ok())
+}
+
+#[cfg(feature = "verilator")]
+fn checkpoint_dir_or_env(checkpoint_dir: Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(checkpoint_dir) = checkpoint_dir {
+        return Some(checkpoint_dir);
+    }
+    std::env::var("CPTRA_CHECKPOINT_DIR")
+        .ok()
+        .map(PathBuf::from)
+}
+
+fn timeline_path_or_env(timeline_path: Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(timeline_path) = timeline_path {
+        return Some(timeline_path);
+    }
+    std::env::var("CPTRA_TIMELINE_PATH").ok().map(PathBuf::from)
+}
+
+fn mailbox_record_path_or_env(mailbox_record_path: Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(mailbox_record_path) = mailbox_record_path {
+        return Some(mailbox_record_path);
+    }
+    std::env::var("CPTRA_MAILBOX_RECORD_PATH")
+        .ok()
+        .map(PathBuf::from)
+}
+
 pub struct BootParams<'a> {
     pub init_params: InitParams<'a>,
     pub fuses: Fuses,
@@ -258,6 +411,15 @@ pub enum ModelError {
         actual: u32,
     },
     MailboxRespInvalidFipsStatus(u32),
+    SnapshotUnsupported,
+    SnapshotFailed(SnapshotError),
+    FaultInjectionUnsupported,
+    InvalidFault(Fault),
+    SequenceConditionTimeout {
+        cycles: u32,
+    },
+    ColdResetUnsupported,
+    BusCaptureUnsupported,
 }
 impl Error for ModelError {}
 impl Display for ModelError {
@@ -320,6 +482,25 @@ impl Display for ModelError {
                     "Mailbox response had non-success FIPS status: 0x{status:x}"
                 )
             }
+            ModelError::SnapshotUnsupported => {
+                write!(f, "This model does not support snapshots")
+            }
+            ModelError::SnapshotFailed(err) => write!(f, "Snapshot failed: {err}"),
+            ModelError::FaultInjectionUnsupported => {
+                write!(f, "This model does not support fault injection")
+            }
+            ModelError::InvalidFault(fault) => write!(f, "Invalid fault: {fault:?}"),
+            ModelError::SequenceConditionTimeout { cycles } => {
+                write!(f, "Sequence condition not met after {cycles} cycles")
+            }
+            ModelError::ColdResetUnsupported => write!(
+                f,
+                "This model does not support cold reset (the emulator needs \
+                 InitParams::save_power_on_state)"
+            ),
+            ModelError::BusCaptureUnsupported => {
+                write!(f, "This model does not support bus capture")
+            }
         }
     }
 }
@@ -382,6 +563,61 @@ fn mbox_read_fifo(mbox: mbox::RegisterBlock<impl MmioMut>) -> Vec<u8> {
     result
 }
 
+/// Wait for the response to a mailbox command started with
+/// `HwModel::start_mailbox_execute()`.
+fn wait_for_mailbox_response(
+    hw: &mut (impl HwModel + ?Sized),
+) -> std::result::Result<Option<Vec<u8>>, ModelError> {
+    // Wait for the microcontroller to finish executing
+    while hw.soc_mbox().status().read().status().cmd_busy() {
+        hw.step();
+    }
+    let status = hw.soc_mbox().status().read().status();
+    if status.cmd_failure() {
+        writeln!(hw.output().logger(), ">>> mbox cmd response: failed").unwrap();
+        hw.soc_mbox().execute().write(|w| w.execute(false));
+        let soc_ifc = hw.soc_ifc();
+        return Err(ModelError::MailboxCmdFailed(
+            if soc_ifc.cptra_fw_error_fatal().read() != 0 {
+                soc_ifc.cptra_fw_error_fatal().read()
+            } else {
+                soc_ifc.cptra_fw_error_non_fatal().read()
+            },
+        ));
+    }
+    if status.cmd_complete() {
+        writeln!(hw.output().logger(), ">>> mbox cmd response: success").unwrap();
+        hw.soc_mbox().execute().write(|w| w.execute(false));
+        return Ok(None);
+    }
+    if !status.data_ready() {
+        return Err(ModelError::UnknownCommandStatus(status as u32));
+    }
+
+    let dlen = hw.soc_mbox().dlen().read();
+    writeln!(
+        hw.output().logger(),
+        ">>> mbox cmd response data ({dlen} bytes)"
+    )
+    .unwrap();
+    let result = mbox_read_fifo(hw.soc_mbox());
+
+    hw.soc_mbox().execute().write(|w| w.execute(false));
+
+    if cfg!(not(feature = "fpga_realtime")) {
+        // Don't check for mbox_idle() unless the hw-model supports
+        // fine-grained timing control; the firmware may proceed to lock the
+        // mailbox shortly after the mailbox transcation finishes (for example, to
+        // test the sha384_acc peripheral).
+
+        // mbox_fsm_ps isn't updated immediately after execute is cleared (!?),
+        // so step an extra clock cycle to wait for fm_ps to update
+        hw.step();
+        assert!(hw.soc_mbox().status().read().mbox_fsm_ps().mbox_idle());
+    }
+    Ok(Some(result))
+}
+
 pub fn mbox_write_fifo(
     mbox: &mbox::RegisterBlock<impl MmioMut>,
     buf: &[u8],
@@ -483,13 +719,11 @@ pub trait HwModel {
 
         if let Some(fw_image) = run_params.fw_image {
             const MAX_WAIT_CYCLES: u32 = 20_000_000;
-            let mut cycles = 0;
-            while !hw.ready_for_fw() {
-                hw.step();
-                cycles += 1;
-                if cycles > MAX_WAIT_CYCLES {
-                    return Err(ModelError::ReadyForFirmwareTimeout { cycles }.into());
+            if !hw.step_until_ready_for_fw(MAX_WAIT_CYCLES) {
+                return Err(ModelError::ReadyForFirmwareTimeout {
+                    cycles: MAX_WAIT_CYCLES,
                 }
+                .into());
             }
             writeln!(hw.output().logger(), "ready_for_fw is high")?;
             hw.upload_firmware(fw_image)?;
@@ -527,15 +761,32 @@ pub trait HwModel {
 
     /// Toggle reset pins and wait for ready_for_fuses
     fn warm_reset(&mut self) {
-        // sw-emulator lacks support: https://github.com/chipsalliance/caliptra-sw/issues/540
         panic!("warm_reset unimplemented");
     }
 
+    /// Drop power (cptra_pwrgood) and reset, bring them back up and wait for
+    /// ready_for_fuses. All state is lost, including the fuses.
+    fn cold_reset(&mut self) -> Result<(), ModelError> {
+        Err(ModelError::ColdResetUnsupported)
+    }
+
     /// Returns true if the microcontroller has signalled that it is ready for
     /// firmware to be written to the mailbox. For RTL implementations, this
     /// should come via a caliptra_top wire rather than an APB register.
     fn ready_for_fw(&self) -> bool;
 
+    /// Step until `ready_for_fw()` returns true or `max_cycles` have elapsed.
+    /// Returns false on timeout.
+    fn step_until_ready_for_fw(&mut self, max_cycles: u32) -> bool {
+        for _ in 0..max_cycles {
+            if self.ready_for_fw() {
+                return true;
+            }
+            self.step();
+        }
+        self.ready_for_fw()
+    }
+
     /// Initializes the fuse values and locks them in until the next reset. This
     /// function can only be called during early boot, shortly after the model
     /// is created with `new_unbooted()`.
@@ -784,6 +1035,47 @@ pub trait HwModel {
 
     fn set_apb_pauser(&mut self, pauser: u32);
 
+    /// Capture the complete machine state (CPU, memories, peripherals and
+    /// pending timer events). Pass the result to [`HwModel::restore`] on a
+    /// model created with the same [`InitParams`] to resume from this point.
+    fn snapshot(&mut self) -> Result<Vec<u8>, ModelError> {
+        Err(ModelError::SnapshotUnsupported)
+    }
+
+    /// Restore machine state captured with [`HwModel::snapshot`]. Output
+    /// already produced by the model is left untouched.
+    fn restore(&mut self, _snapshot: &[u8]) -> Result<(), ModelError> {
+        Err(ModelError::SnapshotUnsupported)
+    }
+
+    /// Inject a fault to model a glitch attack. See [`fault_campaign`] for
+    /// sweeping faults over a range of firmware code.
+    fn inject_fault(&mut self, _fault: Fault) -> Result<(), ModelError> {
+        Err(ModelError::FaultInjectionUnsupported)
+    }
+
+    /// The recorder for mailbox transactions executed from the SoC, if
+    /// recording is enabled.
+    fn mailbox_recorder(&mut self) -> Option<&mut MailboxRecorder> {
+        None
+    }
+
+    /// The number of injected faults that haven't been triggered yet.
+    fn pending_faults(&self) -> usize {
+        0
+    }
+
+    /// Start capturing the microcontroller's MMIO transactions, for comparing
+    /// models with [`equivalence::run_lockstep`].
+    fn start_bus_capture(&mut self) -> Result<(), ModelError> {
+        Err(ModelError::BusCaptureUnsupported)
+    }
+
+    /// Take the transactions captured since the last call.
+    fn take_bus_capture(&mut self) -> Vec<BusTransaction> {
+        vec![]
+    }
+
     /// Executes a typed request and (if success), returns the typed response.
     /// The checksum field of the request is calculated, and the checksum of the
     /// response is validated.
@@ -882,6 +1174,14 @@ pub trait HwModel {
         self.soc_mbox().cmd().write(|_| cmd);
         mbox_write_fifo(&self.soc_mbox(), buf)?;
 
+        if self.mailbox_recorder().is_some() {
+            let pauser = self.soc_mbox().user().read();
+            let cycle = self.output().sink().now();
+            if let Some(recorder) = self.mailbox_recorder() {
+                recorder.begin(cycle, pauser, cmd, buf);
+            }
+        }
+
         // Ask the microcontroller to execute this command
         self.soc_mbox().execute().write(|w| w.execute(true));
 
@@ -890,54 +1190,11 @@ pub trait HwModel {
 
     /// Wait for the response to a previous call to `start_mailbox_execute()`.
     fn finish_mailbox_execute(&mut self) -> std::result::Result<Option<Vec<u8>>, ModelError> {
-        // Wait for the microcontroller to finish executing
-        while self.soc_mbox().status().read().status().cmd_busy() {
-            self.step();
+        let result = wait_for_mailbox_response(self);
+        if let Some(recorder) = self.mailbox_recorder() {
+            recorder.end(&result);
         }
-        let status = self.soc_mbox().status().read().status();
-        if status.cmd_failure() {
-            writeln!(self.output().logger(), ">>> mbox cmd response: failed").unwrap();
-            self.soc_mbox().execute().write(|w| w.execute(false));
-            let soc_ifc = self.soc_ifc();
-            return Err(ModelError::MailboxCmdFailed(
-                if soc_ifc.cptra_fw_error_fatal().read() != 0 {
-                    soc_ifc.cptra_fw_error_fatal().read()
-                } else {
-                    soc_ifc.cptra_fw_error_non_fatal().read()
-                },
-            ));
-        }
-        if status.cmd_complete() {
-            writeln!(self.output().logger(), ">>> mbox cmd response: success").unwrap();
-            self.soc_mbox().execute().write(|w| w.execute(false));
-            return Ok(None);
-        }
-        if !status.data_ready() {
-            return Err(ModelError::UnknownCommandStatus(status as u32));
-        }
-
-        let dlen = self.soc_mbox().dlen().read();
-        writeln!(
-            self.output().logger(),
-            ">>> mbox cmd response data ({dlen} bytes)"
-        )
-        .unwrap();
-        let result = mbox_read_fifo(self.soc_mbox());
-
-        self.soc_mbox().execute().write(|w| w.execute(false));
-
-        if cfg!(not(feature = "fpga_realtime")) {
-            // Don't check for mbox_idle() unless the hw-model supports
-            // fine-grained timing control; the firmware may proceed to lock the
-            // mailbox shortly after the mailbox transcation finishes (for example, to
-            // test the sha384_acc peripheral).
-
-            // mbox_fsm_ps isn't updated immediately after execute is cleared (!?),
-            // so step an extra clock cycle to wait for fm_ps to update
-            self.step();
-            assert!(self.soc_mbox().status().read().mbox_fsm_ps().mbox_idle());
-        }
-        Ok(Some(result))
+        result
     }
 
     /// Streams `data` to the sha512acc SoC interface. If `sha384` computes
@@ -1304,6 +1561,36 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(all(not(feature = "verilator"), not(feature = "fpga_realtime")))]
+    fn test_snapshot() {
+        let rom = gen_image_hi();
+        let init_params = || InitParams {
+            rom: &rom,
+            ..Default::default()
+        };
+        let mut model = caliptra_hw_model::new(BootParams {
+            init_params: init_params(),
+            ..Default::default()
+        })
+        .unwrap();
+        model.step_until_output_and_take("h").unwrap();
+        let snapshot = model.snapshot().unwrap();
+
+        // A fresh model resumes from the snapshot rather than from reset
+        let mut forked = caliptra_hw_model::new_unbooted(init_params()).unwrap();
+        forked.restore(&snapshot).unwrap();
+        forked.step_until_output("ii").unwrap();
+        model.step_until_output("ii").unwrap();
+
+        assert_eq!(
+            forked.restore(&snapshot[..snapshot.len() - 1]),
+            Err(ModelError::SnapshotFailed(
+                caliptra_emu_bus::SnapshotError::UnexpectedEof
+            ))
+        );
+    }
+
     #[test]
     pub fn test_mailbox_execute() {
         let message: [u8; 10] = [0x90, 0x5e, 0x1f, 0xad, 0x8b, 0x60, 0xb0, 0xbf, 0x1c, 0x7e];
@@ -1362,6 +1649,103 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn test_mailbox_record_replay() {
+        use caliptra_hw_model::mailbox_recording::{self, MailboxResponse};
+
+        let rom =
+            caliptra_builder::build_firmware_rom(&firmware::hw_model_tests::MAILBOX_RESPONDER)
+                .unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "test_mailbox_record_replay_{}.txt",
+            std::process::id()
+        ));
+
+        {
+            let mut model = caliptra_hw_model::new(BootParams {
+                init_params: InitParams {
+                    rom: &rom,
+                    mailbox_record_path: Some(path.clone()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .unwrap();
+            model.mailbox_execute(0x1000_0000, &[0x90, 0x5e]).unwrap();
+            model.mailbox_execute(0x2000_0000, &[]).unwrap();
+            model.mailbox_execute(0x4000_0000, &[]).unwrap_err();
+        }
+
+        let mut recording = mailbox_recording::read_recording(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(recording.len(), 3);
+        assert_eq!(
+            recording[0].response,
+            MailboxResponse::Data(vec![0x00, 0x00, 0x00, 0x10, 0x90, 0x5e])
+        );
+        assert_eq!(recording[1].response, MailboxResponse::Complete);
+        assert_eq!(recording[2].response, MailboxResponse::Failed(0));
+
+        let mut model = caliptra_hw_model::new(BootParams {
+            init_params: InitParams {
+                rom: &rom,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        recording[1].response = MailboxResponse::Data(vec![]);
+        let mismatches = mailbox_recording::replay(&mut model, &recording, &[]);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].index, 1);
+        assert_eq!(mismatches[0].actual, Some(MailboxResponse::Complete));
+    }
+
+    #[test]
+    // Invalid PAUSER accesses raise SIGBUS on the FPGA
+    #[cfg(not(feature = "fpga_realtime"))]
+    fn test_mailbox_conformance() {
+        let rom =
+            caliptra_builder::build_firmware_rom(&firmware::hw_model_tests::MAILBOX_RESPONDER)
+                .unwrap();
+
+        let failures = crate::mailbox_conformance::check_mailbox::<DefaultHwModel>(&rom);
+        for failure in &failures {
+            println!("{failure}");
+        }
+
+        // Known differences between the sw-emulator and the RTL
+        let expected: &[&str] = if cfg!(feature = "verilator") {
+            &[]
+        } else {
+            &["pauser_enforcement", "soc_read_during_execute_uc"]
+        };
+        assert_eq!(
+            failures.iter().map(|f| f.check).collect::<Vec<_>>(),
+            expected
+        );
+    }
+
+    #[test]
+    pub fn test_deterministic_trng_inputs() {
+        let trng_inputs = || {
+            let mut params = InitParams {
+                deterministic: true,
+                ..Default::default()
+            };
+            params.pin_trng_inputs();
+            (
+                params.itrng_nibbles.take(64).collect::<Vec<_>>(),
+                params
+                    .etrng_responses
+                    .take(4)
+                    .map(|r| r.data)
+                    .collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(trng_inputs(), trng_inputs());
+    }
+
     #[test]
     pub fn test_mailbox_receive() {
         let rom = caliptra_builder::build_firmware_rom(&firmware::hw_model_tests::MAILBOX_SENDER)
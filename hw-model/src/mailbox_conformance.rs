@@ -0,0 +1,520 @@
+// Licensed under the Apache-2.0 license
+
+//! Conformance checks for the SoC side of the mailbox protocol, written only
+//! in terms of [`HwModel`] methods so they can be run against every model to
+//! find where the sw-emulator and the RTL disagree.
+//!
+//! [`check_mailbox()`] covers the hardware protocol: lock acquisition, PAUSER
+//! enforcement, the FSM and status transitions around `execute`, dlen edge
+//! cases, and FIFO overrun and underrun. Each check runs on a freshly created
+//! model. Checks that need a uC on the other side boot `responder_rom`, which
+//! must implement the commands of the `MAILBOX_RESPONDER` test firmware:
+//!
+//! | cmd           | response                                              |
+//! |---------------|-------------------------------------------------------|
+//! | `0x2000_0000` | CMD_COMPLETE                                          |
+//! | `0x1000_1000` | DATA_READY, the 7 bytes `01 23 45 67 89 ab cd`        |
+//! | `0x3000_0000` | CMD_COMPLETE, remembers the request (up to 8192 bytes)|
+//! | `0x3000_0001` | DATA_READY, the request remembered by `0x3000_0000`   |
+//! | anything else | CMD_FAILURE                                           |
+//!
+//! [`check_api_requests()`] covers the runtime firmware's handling of
+//! malformed caliptra-api requests: bad checksums, truncated headers and
+//! variable-size requests beyond `DATA_MAX_SIZE`.
+//!
+//! ```ignore
+//! let failures = mailbox_conformance::check_mailbox::<DefaultHwModel>(&responder_rom);
+//! for failure in &failures {
+//!     println!("{failure}");
+//! }
+//! ```
+
+use std::fmt::{self, Debug, Display};
+use std::mem;
+use std::panic::{self, AssertUnwindSafe};
+
+use caliptra_api::calc_checksum;
+use caliptra_api::mailbox::{
+    CommandId, GetIdevCertReq, InvokeDpeReq, MailboxReq, MailboxReqHeader, PopulateIdevCertReq,
+};
+use caliptra_registers::mbox::enums::{MboxFsmE, MboxStatusE};
+use zerocopy::FromBytes;
+
+use crate::rv32_builder::Rv32Builder;
+use crate::{mbox_read_fifo, mbox_write_fifo, BootParams, HwModel, InitParams, ModelError};
+
+/// The default APB PAUSER of the models, given mailbox access by the checks.
+const VALID_PAUSER: u32 = 0x1;
+
+/// A PAUSER that is not in CPTRA_MBOX_VALID_PAUSER.
+const INVALID_PAUSER: u32 = 0x2;
+
+const RESPONDER_COMPLETE: u32 = 0x2000_0000;
+const RESPONDER_7_BYTES: u32 = 0x1000_1000;
+const RESPONDER_STORE: u32 = 0x3000_0000;
+const RESPONDER_REPLAY: u32 = 0x3000_0001;
+const RESPONDER_FAIL: u32 = 0xffff_0000;
+
+/// Request lengths checked by `dlen_round_trip`; the largest is the most the
+/// responder can remember.
+const ROUND_TRIP_LENGTHS: [usize; 12] = [0, 1, 2, 3, 4, 5, 7, 8, 255, 256, 4096, 8192];
+
+/// A check that the model didn't pass.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConformanceFailure {
+    /// The name of the check, such as `pauser_enforcement`.
+    pub check: &'static str,
+    pub reason: String,
+}
+
+impl Display for ConformanceFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.check, self.reason)
+    }
+}
+
+type CheckResult = Result<(), String>;
+
+/// A named check, run against a freshly created model.
+type Check<T> = (&'static str, fn(&mut T) -> CheckResult);
+
+/// Run `check`, treating a panic as a failure; models panic when asked to do
+/// something they don't support, such as changing the PAUSER on the
+/// sw-emulator.
+fn run_check(check: impl FnOnce() -> CheckResult) -> CheckResult {
+    panic::catch_unwind(AssertUnwindSafe(check)).unwrap_or_else(|err| {
+        let msg = err
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| err.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(format!("check panicked: {msg}"))
+    })
+}
+
+/// Run all the hardware protocol checks against fresh models of type `T`,
+/// returning the checks that failed. See the module docs for what
+/// `responder_rom` must do.
+pub fn check_mailbox<T: HwModel>(responder_rom: &[u8]) -> Vec<ConformanceFailure> {
+    let idle_checks: [Check<T>; 5] = [
+        ("lock_acquire", check_lock_acquire),
+        ("pauser_enforcement", check_pauser_enforcement),
+        ("execute_transitions", check_execute_transitions),
+        ("out_of_order_execute", check_out_of_order_execute),
+        (
+            "soc_read_during_execute_uc",
+            check_soc_read_during_execute_uc,
+        ),
+    ];
+    let responder_checks: [Check<T>; 5] = [
+        ("status_cmd_complete", check_status_cmd_complete),
+        ("status_cmd_failure", check_status_cmd_failure),
+        ("fifo_underrun", check_fifo_underrun),
+        ("fifo_overrun", check_fifo_overrun),
+        ("dlen_round_trip", check_dlen_round_trip),
+    ];
+
+    // The idle checks only use the SoC side of the mailbox, with a ROM that
+    // spins so the uC doesn't interfere.
+    let idle_rom = Rv32Builder::new().empty_loop().build();
+    let mut failures = vec![];
+    for (check, run) in idle_checks {
+        if let Err(reason) = run_check(|| run(&mut new_idle_model::<T>(&idle_rom)?)) {
+            failures.push(ConformanceFailure { check, reason });
+        }
+    }
+    for (check, run) in responder_checks {
+        if let Err(reason) = run_check(|| run(&mut new_responder_model::<T>(responder_rom)?)) {
+            failures.push(ConformanceFailure { check, reason });
+        }
+    }
+    failures
+}
+
+/// Send every caliptra-api request type to `model`, which must be running
+/// the runtime firmware, malformed in each way the runtime is expected to
+/// reject. Returns the requests that weren't rejected, or that left the
+/// mailbox unusable.
+pub fn check_api_requests(model: &mut impl HwModel) -> Vec<ConformanceFailure> {
+    model.step_until(|m| m.soc_mbox().status().read().mbox_fsm_ps().mbox_idle());
+
+    let mut failures = vec![];
+    let mut check = |model: &mut _, check: &'static str, cmd: u32, request: &[u8]| {
+        if let Err(reason) = expect_rejected(model, cmd, request) {
+            failures.push(ConformanceFailure {
+                check,
+                reason: format!("cmd 0x{cmd:08x}: {reason}"),
+            });
+        }
+    };
+    let header_only = HEADER_ONLY_COMMANDS
+        .iter()
+        .map(|cmd| (cmd.0, vec![0u8; mem::size_of::<MailboxReqHeader>()], None));
+    let requests = zeroed_requests().into_iter().map(|req| {
+        // Unwrap cannot panic because the variable-size requests are empty
+        let request = req.as_bytes().unwrap().to_vec();
+        (u32::from(req.cmd_code()), request, var_size(&req))
+    });
+    for (cmd, request, var_size) in requests.chain(header_only) {
+        let mut bad_checksum = request.clone();
+        set_checksum(cmd, &mut bad_checksum);
+        bad_checksum[0] ^= 1;
+        check(model, "bad_checksum", cmd, &bad_checksum);
+
+        check(model, "truncated_header", cmd, &request[..2]);
+
+        if let Some((struct_size, max_size)) = var_size {
+            let mut too_long = vec![0u8; struct_size];
+            let size_field = mem::size_of::<MailboxReqHeader>();
+            too_long[size_field..size_field + 4]
+                .copy_from_slice(&u32::try_from(max_size + 1).unwrap().to_le_bytes());
+            set_checksum(cmd, &mut too_long);
+            check(model, "size_over_data_max_size", cmd, &too_long);
+
+            let mut too_large = vec![0u8; struct_size + 4];
+            set_checksum(cmd, &mut too_large);
+            check(model, "request_larger_than_struct", cmd, &too_large);
+        }
+    }
+    failures
+}
+
+/// One zeroed request of each [`MailboxReq`] variant, with the variable-size
+/// requests empty.
+fn zeroed_requests() -> Vec<MailboxReq> {
+    vec![
+        MailboxReq::EcdsaVerify(FromBytes::new_zeroed()),
+        MailboxReq::GetLdevCert(FromBytes::new_zeroed()),
+        MailboxReq::StashMeasurement(FromBytes::new_zeroed()),
+        MailboxReq::InvokeDpeCommand(FromBytes::new_zeroed()),
+        MailboxReq::FipsVersion(FromBytes::new_zeroed()),
+        MailboxReq::FwInfo(FromBytes::new_zeroed()),
+        MailboxReq::PopulateIdevCert(FromBytes::new_zeroed()),
+        MailboxReq::GetIdevCert(FromBytes::new_zeroed()),
+        MailboxReq::TagTci(FromBytes::new_zeroed()),
+        MailboxReq::GetTaggedTci(FromBytes::new_zeroed()),
+        MailboxReq::GetFmcAliasCert(FromBytes::new_zeroed()),
+        MailboxReq::GetRtAliasCert(FromBytes::new_zeroed()),
+        MailboxReq::VerifySocImage(FromBytes::new_zeroed()),
+        MailboxReq::CommitSvn(FromBytes::new_zeroed()),
+    ]
+}
+
+/// Runtime commands whose request is just a [`MailboxReqHeader`] and that
+/// have no [`MailboxReq`] variant.
+const HEADER_ONLY_COMMANDS: [CommandId; 6] = [
+    CommandId::GET_IDEV_INFO,
+    CommandId::DISABLE_ATTESTATION,
+    CommandId::SELF_TEST_START,
+    CommandId::SELF_TEST_GET_RESULTS,
+    CommandId::SHUTDOWN,
+    CommandId::CAPABILITIES,
+];
+
+/// For variable-size requests, the size of the whole struct and the largest
+/// valid value of the length field that follows the header.
+fn var_size(req: &MailboxReq) -> Option<(usize, usize)> {
+    match req {
+        MailboxReq::InvokeDpeCommand(req) => {
+            Some((mem::size_of_val(req), InvokeDpeReq::DATA_MAX_SIZE))
+        }
+        MailboxReq::PopulateIdevCert(req) => {
+            Some((mem::size_of_val(req), PopulateIdevCertReq::MAX_CERT_SIZE))
+        }
+        MailboxReq::GetIdevCert(req) => {
+            Some((mem::size_of_val(req), GetIdevCertReq::DATA_MAX_SIZE))
+        }
+        _ => None,
+    }
+}
+
+fn set_checksum(cmd: u32, request: &mut [u8]) {
+    let checksum = calc_checksum(cmd, &request[4..]);
+    request[..4].copy_from_slice(&checksum.to_le_bytes());
+}
+
+fn expect_rejected(model: &mut impl HwModel, cmd: u32, request: &[u8]) -> CheckResult {
+    match model.mailbox_execute(cmd, request) {
+        Err(ModelError::MailboxCmdFailed(_)) => {}
+        Ok(resp) => return Err(format!("request was accepted with response {resp:02x?}")),
+        Err(err) => return Err(format!("expected the command to fail, got {err}")),
+    }
+    model.step();
+    expect_fsm(model, "after the failed command", MboxFsmE::MboxIdle)
+}
+
+fn new_idle_model<T: HwModel>(rom: &[u8]) -> Result<T, String> {
+    let mut model = T::new_unbooted(InitParams {
+        rom,
+        ..Default::default()
+    })
+    .map_err(|err| format!("unable to create model: {err}"))?;
+    model.soc_ifc().cptra_fuse_wr_done().write(|w| w.done(true));
+    model.soc_ifc().cptra_bootfsm_go().write(|w| w.go(true));
+    model
+        .soc_ifc()
+        .cptra_mbox_valid_pauser()
+        .at(0)
+        .write(|_| VALID_PAUSER);
+    model
+        .soc_ifc()
+        .cptra_mbox_pauser_lock()
+        .at(0)
+        .write(|w| w.lock(true));
+    Ok(model)
+}
+
+fn new_responder_model<T: HwModel>(rom: &[u8]) -> Result<T, String> {
+    T::new(BootParams {
+        init_params: InitParams {
+            rom,
+            ..Default::default()
+        },
+        valid_pauser: VALID_PAUSER,
+        ..Default::default()
+    })
+    .map_err(|err| format!("unable to boot responder: {err}"))
+}
+
+fn expect_eq<V: Debug + PartialEq>(what: &str, expected: V, actual: V) -> CheckResult {
+    if expected != actual {
+        return Err(format!("{what} was {actual:x?}, expected {expected:x?}"));
+    }
+    Ok(())
+}
+
+fn expect_fsm(model: &mut impl HwModel, when: &str, expected: MboxFsmE) -> CheckResult {
+    let actual = model.soc_mbox().status().read().mbox_fsm_ps();
+    expect_eq(
+        &format!("mailbox FSM state {when}"),
+        expected as u32,
+        actual as u32,
+    )
+}
+
+fn expect_status(model: &mut impl HwModel, when: &str, expected: MboxStatusE) -> CheckResult {
+    let actual = model.soc_mbox().status().read().status();
+    expect_eq(
+        &format!("mailbox status {when}"),
+        expected as u32,
+        actual as u32,
+    )
+}
+
+fn acquire_lock(model: &mut impl HwModel) -> CheckResult {
+    if model.soc_mbox().lock().read().lock() {
+        return Err("lock was already held".into());
+    }
+    if !model.soc_mbox().lock().read().lock() {
+        return Err("lock read 0 twice in a row".into());
+    }
+    Ok(())
+}
+
+fn wait_while_busy(model: &mut impl HwModel) {
+    while model.soc_mbox().status().read().status().cmd_busy() {
+        model.step();
+    }
+}
+
+/// The first lock read returns 0 and takes the lock for the reader; later
+/// reads return 1.
+fn check_lock_acquire(model: &mut impl HwModel) -> CheckResult {
+    expect_fsm(model, "before locking", MboxFsmE::MboxIdle)?;
+    acquire_lock(model)?;
+    if !model.soc_mbox().lock().read().lock() {
+        return Err("lock was released by reading it".into());
+    }
+    expect_fsm(model, "after locking", MboxFsmE::MboxRdyForCmd)?;
+    expect_eq("USER", VALID_PAUSER, model.soc_mbox().user().read())
+}
+
+/// Requests from a PAUSER not in CPTRA_MBOX_VALID_PAUSER can't take the lock
+/// or write to a mailbox locked by someone else.
+fn check_pauser_enforcement(model: &mut impl HwModel) -> CheckResult {
+    model.set_apb_pauser(INVALID_PAUSER);
+    let first = model.soc_mbox().lock().read().lock();
+    let second = model.soc_mbox().lock().read().lock();
+    if !first && second {
+        return Err(format!("lock was acquired by PAUSER 0x{INVALID_PAUSER:x}"));
+    }
+
+    model.set_apb_pauser(VALID_PAUSER);
+    acquire_lock(model)?;
+    model.soc_mbox().cmd().write(|_| 4242);
+
+    model.set_apb_pauser(INVALID_PAUSER);
+    model.soc_mbox().cmd().write(|_| 0x1234);
+    model.set_apb_pauser(VALID_PAUSER);
+    expect_eq(
+        "CMD after a write from an invalid PAUSER",
+        4242,
+        model.soc_mbox().cmd().read(),
+    )
+}
+
+/// lock -> cmd -> dlen -> execute walks the FSM from MBOX_IDLE to
+/// MBOX_EXECUTE_UC, and clearing execute returns it to MBOX_IDLE.
+fn check_execute_transitions(model: &mut impl HwModel) -> CheckResult {
+    acquire_lock(model)?;
+    expect_fsm(model, "after locking", MboxFsmE::MboxRdyForCmd)?;
+    model.soc_mbox().cmd().write(|_| 0x1234_5678);
+    expect_fsm(model, "after writing CMD", MboxFsmE::MboxRdyForDlen)?;
+    model.soc_mbox().dlen().write(|_| 4);
+    expect_fsm(model, "after writing DLEN", MboxFsmE::MboxRdyForData)?;
+    model.soc_mbox().datain().write(|_| 0xaabb_ccdd);
+    expect_fsm(model, "after writing DATAIN", MboxFsmE::MboxRdyForData)?;
+
+    model.soc_mbox().execute().write(|w| w.execute(true));
+    expect_fsm(model, "after setting EXECUTE", MboxFsmE::MboxExecuteUc)?;
+    expect_status(model, "after setting EXECUTE", MboxStatusE::CmdBusy)?;
+    expect_eq("CMD", 0x1234_5678, model.soc_mbox().cmd().read())?;
+    expect_eq("DLEN", 4, model.soc_mbox().dlen().read())?;
+
+    model.soc_mbox().execute().write(|w| w.execute(false));
+    model.step();
+    expect_fsm(model, "after clearing EXECUTE", MboxFsmE::MboxIdle)?;
+    acquire_lock(model).map_err(|err| format!("after clearing EXECUTE: {err}"))
+}
+
+/// Setting EXECUTE before DLEN is ignored, and the data written afterwards
+/// can't be read back by the SoC.
+fn check_out_of_order_execute(model: &mut impl HwModel) -> CheckResult {
+    acquire_lock(model)?;
+    model.soc_mbox().cmd().write(|_| 4242);
+    model.soc_mbox().execute().write(|w| w.execute(true));
+    model.soc_mbox().dlen().write(|_| 3);
+    expect_eq("DLEN", 3, model.soc_mbox().dlen().read())?;
+    mbox_write_fifo(&model.soc_mbox(), &[1, 2, 3]).map_err(|err| err.to_string())?;
+    expect_eq(
+        "data read back by the SoC",
+        vec![0, 0, 0],
+        mbox_read_fifo(model.soc_mbox()),
+    )
+}
+
+/// While the uC owns the mailbox, SoC reads of DATAOUT return 0 and don't
+/// consume the request.
+fn check_soc_read_during_execute_uc(model: &mut impl HwModel) -> CheckResult {
+    acquire_lock(model)?;
+    model.soc_mbox().cmd().write(|_| 0x1234_5678);
+    mbox_write_fifo(&model.soc_mbox(), &[0x11; 8]).map_err(|err| err.to_string())?;
+    model.soc_mbox().execute().write(|w| w.execute(true));
+    expect_fsm(model, "after setting EXECUTE", MboxFsmE::MboxExecuteUc)?;
+    expect_eq(
+        "DATAOUT read by the SoC during MBOX_EXECUTE_UC",
+        0,
+        model.soc_mbox().dataout().read(),
+    )?;
+
+    model.soc_mbox().execute().write(|w| w.execute(false));
+    model.step();
+    expect_fsm(model, "after clearing EXECUTE", MboxFsmE::MboxIdle)
+}
+
+fn check_status_cmd_complete(model: &mut impl HwModel) -> CheckResult {
+    match model.mailbox_execute(RESPONDER_COMPLETE, &[]) {
+        Ok(None) => Ok(()),
+        result => Err(format!("expected CMD_COMPLETE, got {result:02x?}")),
+    }
+}
+
+/// CMD_FAILURE is reported to the SoC, and the mailbox is usable afterwards.
+fn check_status_cmd_failure(model: &mut impl HwModel) -> CheckResult {
+    expect_rejected(model, RESPONDER_FAIL, &[])?;
+    check_status_cmd_complete(model).map_err(|err| format!("after a failed command: {err}"))
+}
+
+/// DATA_READY hands the mailbox to the SoC, and reading DATAOUT past the
+/// response's DLEN returns 0.
+fn check_fifo_underrun(model: &mut impl HwModel) -> CheckResult {
+    model
+        .start_mailbox_execute(RESPONDER_7_BYTES, &[])
+        .map_err(|err| err.to_string())?;
+    wait_while_busy(model);
+    expect_status(model, "after the response", MboxStatusE::DataReady)?;
+    expect_fsm(model, "after the response", MboxFsmE::MboxExecuteSoc)?;
+    expect_eq("response DLEN", 7, model.soc_mbox().dlen().read())?;
+
+    let words: Vec<u32> = (0..3).map(|_| model.soc_mbox().dataout().read()).collect();
+    expect_eq("response bytes", 0x6745_2301, words[0])?;
+    expect_eq("response bytes", 0x00cd_ab89, words[1] & 0x00ff_ffff)?;
+    expect_eq("DATAOUT read past DLEN", 0, words[2])?;
+
+    model.soc_mbox().execute().write(|w| w.execute(false));
+    model.step();
+    expect_fsm(model, "after clearing EXECUTE", MboxFsmE::MboxIdle)
+}
+
+/// Words written to DATAIN beyond DLEN aren't delivered to the uC.
+fn check_fifo_overrun(model: &mut impl HwModel) -> CheckResult {
+    let data: Vec<u8> = (1..=12).collect();
+    acquire_lock(model)?;
+    model.soc_mbox().cmd().write(|_| RESPONDER_STORE);
+    model.soc_mbox().dlen().write(|_| 5);
+    for word in data.chunks(4) {
+        let word = u32::from_le_bytes(word.try_into().unwrap());
+        model.soc_mbox().datain().write(|_| word);
+    }
+    model.soc_mbox().execute().write(|w| w.execute(true));
+    match model.finish_mailbox_execute() {
+        Ok(None) => {}
+        result => return Err(format!("expected CMD_COMPLETE, got {result:02x?}")),
+    }
+
+    let resp = model
+        .mailbox_execute(RESPONDER_REPLAY, &[])
+        .map_err(|err| err.to_string())?;
+    expect_eq("request seen by the uC", Some(data[..5].to_vec()), resp)
+}
+
+/// Requests of every length up to the responder's limit reach the uC
+/// intact, including lengths that aren't a multiple of 4.
+fn check_dlen_round_trip(model: &mut impl HwModel) -> CheckResult {
+    for len in ROUND_TRIP_LENGTHS {
+        let data: Vec<u8> = (0..len).map(|i| (i * 7 + 3) as u8).collect();
+        match model.mailbox_execute(RESPONDER_STORE, &data) {
+            Ok(None) => {}
+            result => {
+                return Err(format!(
+                    "dlen {len}: expected CMD_COMPLETE, got {result:02x?}"
+                ))
+            }
+        }
+        let resp = model
+            .mailbox_execute(RESPONDER_REPLAY, &[])
+            .map_err(|err| format!("dlen {len}: {err}"))?;
+        if resp.as_deref() != Some(&data[..]) {
+            return Err(format!(
+                "dlen {len}: the uC saw {} bytes that differ from the request",
+                resp.map_or(0, |resp| resp.len())
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The number of MailboxReq variants, not counting test-only commands.
+    // Add new variants to zeroed_requests() before bumping this.
+    const MAILBOX_REQ_VARIANTS: usize = 14;
+
+    #[test]
+    fn test_zeroed_requests_covers_every_variant() {
+        let mut cmds: Vec<u32> = zeroed_requests()
+            .iter()
+            .map(|req| req.cmd_code().into())
+            .collect();
+        assert_eq!(cmds.len(), MAILBOX_REQ_VARIANTS);
+        cmds.extend(HEADER_ONLY_COMMANDS.iter().map(|cmd| cmd.0));
+        cmds.sort();
+        cmds.dedup();
+        assert_eq!(
+            cmds.len(),
+            MAILBOX_REQ_VARIANTS + HEADER_ONLY_COMMANDS.len()
+        );
+    }
+}
@@ -0,0 +1,413 @@
+// Licensed under the Apache-2.0 license
+
+//! Recording and replay of SoC-side mailbox transactions.
+//!
+//! When `InitParams::mailbox_record_path` (or the CPTRA_MAILBOX_RECORD_PATH
+//! environment variable) is set, every transaction executed with
+//! [`HwModel::mailbox_execute()`] is appended to the file, one per line:
+//!
+//! ```text
+//! cycle=1234 pauser=0x00000001 cmd=0x46574c44 dlen=4 req=01020304 resp=data:a0a1
+//! ```
+//!
+//! `resp` is `complete` (no response data), `data:<hex>`, or
+//! `failed:<fw_error>`. Lines starting with `#` are comments. The format is
+//! meant to be easy to edit by hand, for example to trim a recording from the
+//! field down to the transactions that reproduce a bug.
+//!
+//! A recording can be turned into a regression test with [`replay()`]:
+//!
+//! ```ignore
+//! let recording = mailbox_recording::read_recording("tests/recordings/issue_1234.txt")?;
+//! let masks = [ResponseMask { cmd: Some(0x4543_4452), range: 4..100 }];
+//! assert_eq!(mailbox_recording::replay(&mut model, &recording, &masks), vec![]);
+//! ```
+
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::{HwModel, ModelError};
+
+/// How the firmware responded to a mailbox command.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MailboxResponse {
+    /// The command completed without response data.
+    Complete,
+
+    /// The command completed with response data.
+    Data(Vec<u8>),
+
+    /// The command failed with the given firmware error.
+    Failed(u32),
+}
+impl MailboxResponse {
+    fn from_result(result: &Result<Option<Vec<u8>>, ModelError>) -> Option<Self> {
+        match result {
+            Ok(None) => Some(Self::Complete),
+            Ok(Some(data)) => Some(Self::Data(data.clone())),
+            Err(ModelError::MailboxCmdFailed(fw_error)) => Some(Self::Failed(*fw_error)),
+            // The firmware never saw the command
+            Err(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MailboxTransaction {
+    /// The model cycle count when the command was executed.
+    pub cycle: u64,
+
+    /// The PAUSER of the SoC agent that held the mailbox lock.
+    pub pauser: u32,
+
+    pub cmd: u32,
+    pub request: Vec<u8>,
+    pub response: MailboxResponse,
+}
+
+impl MailboxTransaction {
+    fn write_line(&self, w: &mut impl Write) -> io::Result<()> {
+        let mut line = format!(
+            "cycle={} pauser=0x{:08x} cmd=0x{:08x} dlen={} req={} resp=",
+            self.cycle,
+            self.pauser,
+            self.cmd,
+            self.request.len(),
+            hex(&self.request),
+        );
+        match &self.response {
+            MailboxResponse::Complete => line.push_str("complete"),
+            MailboxResponse::Data(data) => write!(line, "data:{}", hex(data)).unwrap(),
+            MailboxResponse::Failed(fw_error) => write!(line, "failed:0x{fw_error:08x}").unwrap(),
+        }
+        writeln!(w, "{line}")
+    }
+
+    fn parse_line(line: &str) -> Result<Self, String> {
+        let mut cycle = None;
+        let mut pauser = None;
+        let mut cmd = None;
+        let mut dlen = None;
+        let mut request = None;
+        let mut response = None;
+        for field in line.split_whitespace() {
+            let (key, val) = field
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, found {field:?}"))?;
+            match key {
+                "cycle" => cycle = Some(val.parse().map_err(|_| format!("bad cycle {val:?}"))?),
+                "pauser" => pauser = Some(parse_u32(val)?),
+                "cmd" => cmd = Some(parse_u32(val)?),
+                "dlen" => dlen = Some(val.parse().map_err(|_| format!("bad dlen {val:?}"))?),
+                "req" => request = Some(unhex(val)?),
+                "resp" => {
+                    response = Some(match val.split_once(':') {
+                        None if val == "complete" => MailboxResponse::Complete,
+                        Some(("data", data)) => MailboxResponse::Data(unhex(data)?),
+                        Some(("failed", fw_error)) => MailboxResponse::Failed(parse_u32(fw_error)?),
+                        _ => return Err(format!("bad resp {val:?}")),
+                    })
+                }
+                _ => return Err(format!("unknown field {key:?}")),
+            }
+        }
+        let request: Vec<u8> = request.ok_or("missing req")?;
+        if dlen.is_some_and(|dlen: usize| dlen != request.len()) {
+            return Err("dlen doesn't match the length of req".into());
+        }
+        Ok(Self {
+            cycle: cycle.ok_or("missing cycle")?,
+            pauser: pauser.ok_or("missing pauser")?,
+            cmd: cmd.ok_or("missing cmd")?,
+            request,
+            response: response.ok_or("missing resp")?,
+        })
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Result<Vec<u8>, String> {
+    if s.len() % 2 != 0 {
+        return Err(format!("odd-length hex string {s:?}"));
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| format!("bad hex {s:?}")))
+        .collect()
+}
+
+fn parse_u32(s: &str) -> Result<u32, String> {
+    let result = match s.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| format!("bad integer {s:?}"))
+}
+
+/// Appends mailbox transactions to a recording file.
+pub struct MailboxRecorder {
+    file: BufWriter<File>,
+    pending: Option<MailboxTransaction>,
+}
+
+impl MailboxRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "# Caliptra mailbox recording")?;
+        file.flush()?;
+        Ok(Self {
+            file,
+            pending: None,
+        })
+    }
+
+    pub(crate) fn begin(&mut self, cycle: u64, pauser: u32, cmd: u32, request: &[u8]) {
+        self.pending = Some(MailboxTransaction {
+            cycle,
+            pauser,
+            cmd,
+            request: request.to_vec(),
+            response: MailboxResponse::Complete,
+        });
+    }
+
+    pub(crate) fn end(&mut self, result: &Result<Option<Vec<u8>>, ModelError>) {
+        let Some(mut txn) = self.pending.take() else {
+            return;
+        };
+        let Some(response) = MailboxResponse::from_result(result) else {
+            return;
+        };
+        txn.response = response;
+        // Flush every transaction so the recording survives a panicking test
+        if let Err(e) = txn
+            .write_line(&mut self.file)
+            .and_then(|_| self.file.flush())
+        {
+            eprintln!("Unable to write mailbox recording: {e}");
+        }
+    }
+}
+
+/// Read a recording written by [`MailboxRecorder`].
+pub fn read_recording(path: impl AsRef<Path>) -> io::Result<Vec<MailboxTransaction>> {
+    let mut result = vec![];
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        result.push(MailboxTransaction::parse_line(line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {e}", i + 1))
+        })?);
+    }
+    Ok(result)
+}
+
+/// Response bytes to ignore when comparing replayed responses, such as
+/// signatures, nonces and the checksums that cover them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ResponseMask {
+    /// The command the mask applies to, or None for all commands.
+    pub cmd: Option<u32>,
+
+    /// The masked byte range of the response data.
+    pub range: Range<usize>,
+}
+
+/// A replayed transaction whose response differed from the recording.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReplayMismatch {
+    /// The index of the transaction in the recording.
+    pub index: usize,
+    pub cmd: u32,
+    pub expected: MailboxResponse,
+
+    /// The response from the model, or None if the command couldn't be
+    /// executed.
+    pub actual: Option<MailboxResponse>,
+}
+
+fn responses_match(
+    cmd: u32,
+    expected: &MailboxResponse,
+    actual: &MailboxResponse,
+    masks: &[ResponseMask],
+) -> bool {
+    let (MailboxResponse::Data(expected), MailboxResponse::Data(actual)) = (expected, actual)
+    else {
+        return expected == actual;
+    };
+    if expected.len() != actual.len() {
+        return false;
+    }
+    let masked = |i: usize| {
+        masks
+            .iter()
+            .any(|m| (m.cmd.is_none() || m.cmd == Some(cmd)) && m.range.contains(&i))
+    };
+    expected
+        .iter()
+        .zip(actual)
+        .enumerate()
+        .all(|(i, (e, a))| e == a || masked(i))
+}
+
+/// Execute each transaction in `recording` against `model` and return the
+/// transactions whose responses differ outside of `masks`.
+///
+/// Before each transaction, the model is stepped until it has run at least
+/// as many cycles as when the transaction was recorded, so the firmware has
+/// had a chance to reach the same point. The APB PAUSER is only changed if
+/// it differs from that of the previous transaction, so recordings from a
+/// single SoC agent can be replayed on models that don't support
+/// `set_apb_pauser()`.
+pub fn replay<T: HwModel>(
+    model: &mut T,
+    recording: &[MailboxTransaction],
+    masks: &[ResponseMask],
+) -> Vec<ReplayMismatch> {
+    let mut mismatches = vec![];
+    let mut pauser = recording.first().map(|txn| txn.pauser);
+    for (index, txn) in recording.iter().enumerate() {
+        if pauser != Some(txn.pauser) {
+            model.set_apb_pauser(txn.pauser);
+            pauser = Some(txn.pauser);
+        }
+        while model.output().sink().now() < txn.cycle {
+            model.step();
+        }
+        let result = model.mailbox_execute(txn.cmd, &txn.request);
+        let actual = MailboxResponse::from_result(&result);
+        let matches = actual
+            .as_ref()
+            .is_some_and(|actual| responses_match(txn.cmd, &txn.response, actual, masks));
+        if !matches {
+            mismatches.push(ReplayMismatch {
+                index,
+                cmd: txn.cmd,
+                expected: txn.response.clone(),
+                actual,
+            });
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn txn(cmd: u32, request: &[u8], response: MailboxResponse) -> MailboxTransaction {
+        MailboxTransaction {
+            cycle: 1000,
+            pauser: 1,
+            cmd,
+            request: request.to_vec(),
+            response,
+        }
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let dir = std::env::temp_dir().join(format!("mbox_recording_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("recording.txt");
+
+        let mut recorder = MailboxRecorder::create(&path).unwrap();
+        recorder.begin(1000, 1, 0x1000_0000, &[0x90, 0x5e]);
+        recorder.end(&Ok(Some(vec![0xab, 0xcd])));
+        recorder.begin(1000, 1, 0x2000_0000, &[]);
+        recorder.end(&Ok(None));
+        recorder.begin(1000, 1, 0x4000_0000, &[1]);
+        recorder.end(&Err(ModelError::MailboxCmdFailed(0x0102_0003)));
+        // Not recorded; the firmware never saw the command
+        recorder.begin(1000, 1, 0x5000_0000, &[]);
+        recorder.end(&Err(ModelError::UnableToLockMailbox));
+        drop(recorder);
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "# Caliptra mailbox recording\n\
+             cycle=1000 pauser=0x00000001 cmd=0x10000000 dlen=2 req=905e resp=data:abcd\n\
+             cycle=1000 pauser=0x00000001 cmd=0x20000000 dlen=0 req= resp=complete\n\
+             cycle=1000 pauser=0x00000001 cmd=0x40000000 dlen=1 req=01 resp=failed:0x01020003\n"
+        );
+        assert_eq!(
+            read_recording(&path).unwrap(),
+            vec![
+                txn(
+                    0x1000_0000,
+                    &[0x90, 0x5e],
+                    MailboxResponse::Data(vec![0xab, 0xcd])
+                ),
+                txn(0x2000_0000, &[], MailboxResponse::Complete),
+                txn(0x4000_0000, &[1], MailboxResponse::Failed(0x0102_0003)),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (line, err) in [
+            ("cycle=1 pauser=1 cmd=1 req=0 resp=complete", "odd-length"),
+            ("cycle=1 pauser=1 cmd=1 dlen=2 req=00 resp=complete", "dlen"),
+            ("cycle=1 pauser=1 req=00 resp=complete", "missing cmd"),
+            ("cycle=1 pauser=1 cmd=1 req=00 resp=maybe", "bad resp"),
+            (
+                "cycle=1 pauser=1 cmd=1 req=00 resp=complete color=red",
+                "unknown",
+            ),
+        ] {
+            let result = MailboxTransaction::parse_line(line);
+            assert!(
+                result.as_ref().is_err_and(|e| e.contains(err)),
+                "{line}: {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_responses_match() {
+        let expected = MailboxResponse::Data(vec![1, 2, 3, 4]);
+        let actual = MailboxResponse::Data(vec![1, 9, 9, 4]);
+        assert!(!responses_match(5, &expected, &actual, &[]));
+        let masks = [ResponseMask {
+            cmd: Some(5),
+            range: 1..3,
+        }];
+        assert!(responses_match(5, &expected, &actual, &masks));
+        assert!(!responses_match(6, &expected, &actual, &masks));
+        let masks = [ResponseMask {
+            cmd: None,
+            range: 1..3,
+        }];
+        assert!(responses_match(6, &expected, &actual, &masks));
+        assert!(!responses_match(
+            5,
+            &expected,
+            &MailboxResponse::Data(vec![1, 2, 3]),
+            &masks
+        ));
+        assert!(!responses_match(
+            5,
+            &expected,
+            &MailboxResponse::Complete,
+            &masks
+        ));
+        assert!(responses_match(
+            5,
+            &MailboxResponse::Failed(1),
+            &MailboxResponse::Failed(1),
+            &[]
+        ));
+    }
+}
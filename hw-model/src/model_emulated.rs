@@ -6,17 +6,24 @@ use std::io::Write;
 use std::path::PathBuf;
 use std::rc::Rc;
 
-use caliptra_emu_bus::Clock;
-use caliptra_emu_cpu::Cpu;
+use caliptra_emu_bus::{BusError, Clock, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
+use caliptra_emu_cpu::xreg_file::XReg;
 use caliptra_emu_cpu::InstrTracer;
+use caliptra_emu_cpu::{Cpu, CpuFault};
 use caliptra_emu_periph::ActionCb;
 use caliptra_emu_periph::ReadyForFwCb;
 use caliptra_emu_periph::{CaliptraRootBus, CaliptraRootBusArgs, SocToCaliptraBus, TbServicesCb};
+use caliptra_emu_periph::{SpiDevice, SpiFlash};
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
-use caliptra_hw_model_types::ErrorInjectionMode;
+use caliptra_hw_model_types::{ErrorInjectionMode, Fault};
 
 use crate::bus_logger::BusLogger;
 use crate::bus_logger::LogFile;
+use crate::equivalence::BusTransaction;
+use crate::mailbox_record_path_or_env;
+use crate::mailbox_recording::MailboxRecorder;
+use crate::timeline::EmulatorTimeline;
+use crate::timeline_path_or_env;
 use crate::trace_path_or_env;
 use crate::InitParams;
 use crate::ModelError;
@@ -55,11 +62,26 @@ pub struct ModelEmulated {
     ready_for_fw: Rc<Cell<bool>>,
     cpu_enabled: Rc<Cell<bool>>,
     trace_path: Option<PathBuf>,
+    timeline: Option<EmulatorTimeline>,
+    mailbox_recorder: Option<MailboxRecorder>,
+
+    // Injected register and memory bit flips waiting for their cycle
+    timed_faults: Vec<Fault>,
 
     image_tag: u64,
+
+    // The machine state right after power-on, restored by cold_reset(). Only
+    // saved if InitParams::save_power_on_state is set.
+    power_on_state: Option<Vec<u8>>,
 }
 impl Drop for ModelEmulated {
     fn drop(&mut self) {
+        if let Some(timeline) = &mut self.timeline {
+            if let Err(e) = timeline.finish(self.cpu.clock.now()) {
+                eprintln!("Unable to write timeline: {e}");
+            }
+        }
+
         let cov_path =
             std::env::var(caliptra_coverage::CPTRA_COVERAGE_PATH).unwrap_or_else(|_| "".into());
         if cov_path.is_empty() {
@@ -76,18 +98,65 @@ impl ModelEmulated {
     pub fn code_coverage_bitmap(&self) -> &bit_vec::BitVec {
         self.cpu.code_coverage.code_coverage_bitmap()
     }
+
+    fn apply_timed_faults(&mut self) {
+        let now = self.cpu.clock.now();
+        let mut i = 0;
+        while i < self.timed_faults.len() {
+            match self.timed_faults[i] {
+                Fault::FlipRegBit { cycle, reg, bit } if cycle <= now => {
+                    let reg = XReg::from(u16::from(reg));
+                    // Cannot panic; the register was validated by inject_fault()
+                    let val = self.cpu.read_xreg(reg).unwrap();
+                    self.cpu.write_xreg(reg, val ^ (1 << bit)).unwrap();
+                }
+                Fault::FlipMemBit { cycle, addr, bit } if cycle <= now => {
+                    // Cannot panic; the address was validated by inject_fault()
+                    with_fault_mem(&mut self.cpu.bus.bus, addr, |mem, offset| {
+                        let val = mem.read(RvSize::Byte, offset)?;
+                        mem.write(RvSize::Byte, offset, val ^ (1 << bit))
+                    })
+                    .unwrap();
+                    self.cpu.invalidate_instr_cache();
+                }
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            }
+            self.timed_faults.remove(i);
+        }
+    }
+}
+
+/// Call `f` with the memory (ICCM, DCCM or mailbox SRAM) containing `addr`
+/// and the offset of `addr` within it. These memories are accessed directly
+/// so faults can be injected even after ICCM is locked.
+fn with_fault_mem<R>(
+    bus: &mut CaliptraRootBus,
+    addr: RvAddr,
+    f: impl FnOnce(&mut dyn Bus, RvAddr) -> Result<R, BusError>,
+) -> Result<R, BusError> {
+    match addr {
+        0x3000_0000..=0x3001_ffff => f(&mut bus.mailbox_sram, addr - 0x3000_0000),
+        0x4000_0000..=0x4fff_ffff => f(&mut *bus.iccm.ram().borrow_mut(), addr - 0x4000_0000),
+        0x5000_0000..=0x5fff_ffff => f(&mut bus.dccm, addr - 0x5000_0000),
+        _ => Err(BusError::LoadAccessFault),
+    }
 }
 
 impl crate::HwModel for ModelEmulated {
     type TBus<'a> = EmulatedApbBus<'a>;
 
-    fn new_unbooted(params: InitParams) -> Result<Self, Box<dyn Error>>
+    fn new_unbooted(mut params: InitParams) -> Result<Self, Box<dyn Error>>
     where
         Self: Sized,
     {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::Hasher;
 
+        params.pin_trng_inputs();
+
         let clock = Clock::new();
         let timer = clock.timer();
 
@@ -101,6 +170,11 @@ impl crate::HwModel for ModelEmulated {
 
         let output_sink = output.sink().clone();
 
+        let spi_device = match &params.spi_flash_path {
+            Some(path) => Some(Box::new(SpiFlash::from_file(path)?) as Box<dyn SpiDevice>),
+            None => None,
+        };
+
         let bus_args = CaliptraRootBusArgs {
             rom: params.rom.into(),
             tb_services_cb: TbServicesCb::new(move |ch| {
@@ -117,7 +191,10 @@ impl crate::HwModel for ModelEmulated {
             cptra_obf_key: params.cptra_obf_key,
 
             itrng_nibbles: Some(params.itrng_nibbles),
+            entropy_src_fault: params.entropy_src_fault,
+            crypto_timing: params.crypto_timing,
             etrng_responses: params.etrng_responses,
+            spi_device,
             ..CaliptraRootBusArgs::default()
         };
         let mut root_bus = CaliptraRootBus::new(&clock, bus_args);
@@ -141,7 +218,17 @@ impl crate::HwModel for ModelEmulated {
             dccm_dest.copy_from_slice(params.dccm);
         }
         let soc_to_caliptra_bus = root_bus.soc_to_caliptra_bus();
-        let cpu = Cpu::new(BusLogger::new(root_bus), clock);
+        let pic = root_bus.pic.clone();
+        let cpu = Cpu::new(BusLogger::new(root_bus), clock, pic);
+
+        let timeline = match timeline_path_or_env(params.timeline_path) {
+            Some(path) => Some(EmulatorTimeline::create(&path, params.timeline_symbols)?),
+            None => None,
+        };
+        let mailbox_recorder = match mailbox_record_path_or_env(params.mailbox_record_path) {
+            Some(path) => Some(MailboxRecorder::create(&path)?),
+            None => None,
+        };
 
         let mut hasher = DefaultHasher::new();
         std::hash::Hash::hash_slice(params.rom, &mut hasher);
@@ -155,8 +242,15 @@ impl crate::HwModel for ModelEmulated {
             ready_for_fw,
             cpu_enabled,
             trace_path: trace_path_or_env(params.trace_path),
+            timeline,
+            mailbox_recorder,
+            timed_faults: vec![],
             image_tag,
+            power_on_state: None,
         };
+        if params.save_power_on_state {
+            m.power_on_state = Some(m.snapshot()?);
+        }
         // Turn tracing on if the trace path was set
         m.tracing_hint(true);
 
@@ -171,11 +265,46 @@ impl crate::HwModel for ModelEmulated {
     }
 
     fn step(&mut self) {
+        if !self.timed_faults.is_empty() {
+            self.apply_timed_faults();
+        }
         if self.cpu_enabled.get() {
             self.cpu.step(self.trace_fn.as_deref_mut());
+            if let Some(timeline) = &mut self.timeline {
+                let pc = self.cpu.read_pc();
+                if let Err(e) = timeline.sample(self.cpu.clock.now(), pc, &mut self.cpu.bus.bus) {
+                    eprintln!("Unable to write timeline: {e}");
+                    self.timeline = None;
+                }
+            }
         }
     }
 
+    fn warm_reset(&mut self) {
+        self.cpu.warm_reset();
+        self.ready_for_fw.set(false);
+        self.cpu_enabled.set(false);
+    }
+
+    /// Restore the state captured at power-on. The contents of the SPI flash
+    /// are non-volatile and survive. The cycle counter restarts at zero.
+    fn cold_reset(&mut self) -> Result<(), ModelError> {
+        let Some(power_on_state) = self.power_on_state.take() else {
+            return Err(ModelError::ColdResetUnsupported);
+        };
+        let mut w = SnapshotWriter::new();
+        w.write(&self.cpu.bus.bus.spi_host).unwrap();
+        let spi_host = w.into_bytes();
+
+        crate::HwModel::restore(self, &power_on_state).unwrap();
+        self.power_on_state = Some(power_on_state);
+
+        SnapshotReader::new(&spi_host)
+            .read(&mut self.cpu.bus.bus.spi_host)
+            .unwrap();
+        Ok(())
+    }
+
     fn output(&mut self) -> &mut Output {
         // In case the caller wants to log something, make sure the log has the
         // correct time.env::
@@ -220,9 +349,89 @@ impl crate::HwModel for ModelEmulated {
                 self.cpu.bus.bus.dccm.error_injection = 8;
             }
         }
+        // Cached instructions would otherwise bypass the injected ICCM errors
+        self.cpu.invalidate_instr_cache();
     }
 
     fn set_apb_pauser(&mut self, _pauser: u32) {
         unimplemented!();
     }
+
+    fn inject_fault(&mut self, fault: Fault) -> Result<(), ModelError> {
+        match fault {
+            Fault::SkipInstr { pc } => self.cpu.inject_fault(CpuFault::SkipInstr { pc }),
+            Fault::CorruptRead { addr, mask } => {
+                self.cpu.inject_fault(CpuFault::CorruptRead { addr, mask })
+            }
+            Fault::FlipRegBit { reg, bit, .. } => {
+                if !(1..32).contains(&reg) || bit >= 32 {
+                    return Err(ModelError::InvalidFault(fault));
+                }
+                self.timed_faults.push(fault);
+            }
+            Fault::FlipMemBit { addr, bit, .. } => {
+                let readable = with_fault_mem(&mut self.cpu.bus.bus, addr, |mem, offset| {
+                    mem.read(RvSize::Byte, offset)
+                });
+                if readable.is_err() || bit >= 8 {
+                    return Err(ModelError::InvalidFault(fault));
+                }
+                self.timed_faults.push(fault);
+            }
+        }
+        Ok(())
+    }
+
+    fn mailbox_recorder(&mut self) -> Option<&mut MailboxRecorder> {
+        self.mailbox_recorder.as_mut()
+    }
+
+    fn pending_faults(&self) -> usize {
+        self.timed_faults.len() + self.cpu.pending_faults().len()
+    }
+
+    fn start_bus_capture(&mut self) -> Result<(), ModelError> {
+        self.cpu.bus.capture = Some(vec![]);
+        Ok(())
+    }
+
+    fn take_bus_capture(&mut self) -> Vec<BusTransaction> {
+        self.cpu
+            .bus
+            .capture
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn snapshot(&mut self) -> Result<Vec<u8>, ModelError> {
+        let mut w = SnapshotWriter::new();
+        w.write(self).map_err(ModelError::SnapshotFailed)?;
+        Ok(w.into_bytes())
+    }
+
+    fn restore(&mut self, snapshot: &[u8]) -> Result<(), ModelError> {
+        let mut r = SnapshotReader::new(snapshot);
+        r.read(self).map_err(ModelError::SnapshotFailed)?;
+        if !r.is_empty() {
+            return Err(ModelError::SnapshotFailed(SnapshotError::InvalidData(
+                "trailing data",
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Snapshot for ModelEmulated {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.ready_for_fw.get())?;
+        w.write(&self.cpu_enabled.get())?;
+        w.write(&self.cpu)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.ready_for_fw.set(r.read_value()?);
+        self.cpu_enabled.set(r.read_value()?);
+        r.read(&mut self.cpu)
+    }
 }
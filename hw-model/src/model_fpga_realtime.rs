@@ -231,6 +231,9 @@ impl HwModel for ModelFpgaRealtime {
     where
         Self: Sized,
     {
+        if params.deterministic {
+            return Err("The FPGA model doesn't support deterministic mode".into());
+        }
         let output = Output::new(params.log_writer);
         let uio_num = usize::from_str(&env::var("CPTRA_UIO_NUM")?)?;
         let dev = UioDevice::new(uio_num)?;
@@ -327,6 +330,18 @@ impl HwModel for ModelFpgaRealtime {
         while !self.is_ready_for_fuses() {}
     }
 
+    fn cold_reset(&mut self) -> Result<(), ModelError> {
+        // Drop power and reset
+        self.set_cptra_pwrgood(false);
+        self.set_cptra_rst_b(false);
+
+        self.set_cptra_pwrgood(true);
+        self.set_cptra_rst_b(true);
+        // Wait for ready_for_fuses
+        while !self.is_ready_for_fuses() {}
+        Ok(())
+    }
+
     fn ready_for_fw(&self) -> bool {
         unsafe {
             GpioInput(
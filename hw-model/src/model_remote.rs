@@ -0,0 +1,477 @@
+// Licensed under the Apache-2.0 license
+
+use std::error::Error;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use caliptra_emu_bus::{Bus, BusError};
+use caliptra_emu_types::{RvAddr, RvData, RvSize};
+use caliptra_hw_model_types::EtrngResponse;
+
+use crate::remote_protocol::{
+    self, FrameReader, FrameWriter, RunCondition, MBOX_COMPLETE, MBOX_DATA, MBOX_FAILED,
+    OP_APB_READ, OP_APB_WRITE, OP_COLD_RESET, OP_INIT, OP_INIT_FUSES, OP_MAILBOX_EXECUTE,
+    OP_RUN_UNTIL, OP_SET_APB_PAUSER, OP_STEP, OP_TRACING_HINT, OP_WARM_RESET, RESULT_OK,
+    TRNG_ETRNG_RESPONSES, TRNG_ITRNG_NIBBLES, TRNG_REQUEST,
+};
+use crate::{Fuses, InitParams, ModelError, Output};
+
+// The number of cycles to run per request when waiting for firmware to exit.
+const RUN_CHUNK_CYCLES: u32 = 100_000;
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+pub struct RemoteApbBus<'a> {
+    model: &'a mut ModelRemote,
+}
+impl<'a> Bus for RemoteApbBus<'a> {
+    fn read(&mut self, size: RvSize, addr: RvAddr) -> Result<RvData, BusError> {
+        if size != RvSize::Word {
+            return Err(BusError::LoadAccessFault);
+        }
+        let mut req = FrameWriter::default();
+        req.u8(OP_APB_READ).u32(addr);
+        let results = self
+            .model
+            .try_request(&req)
+            .map_err(|_| BusError::LoadAccessFault)?;
+        FrameReader(&results)
+            .u32()
+            .map_err(|_| BusError::LoadAccessFault)
+    }
+
+    fn write(&mut self, size: RvSize, addr: RvAddr, val: RvData) -> Result<(), BusError> {
+        if size != RvSize::Word {
+            return Err(BusError::StoreAccessFault);
+        }
+        let mut req = FrameWriter::default();
+        req.u8(OP_APB_WRITE).u32(addr).u32(val);
+        self.model
+            .try_request(&req)
+            .map_err(|_| BusError::StoreAccessFault)?;
+        Ok(())
+    }
+}
+
+/// A model running in another process, driven over a socket by the protocol
+/// described in [`crate::remote_protocol`]. `new_unbooted()` connects to the
+/// server at the address in the CPTRA_REMOTE_MODEL environment variable.
+pub struct ModelRemote {
+    stream: Box<dyn Stream>,
+    output: Output,
+    ready_for_fw: bool,
+    exit_reported: bool,
+
+    // Sent to the server when it asks for TRNG inputs
+    itrng_nibbles: Box<dyn Iterator<Item = u8> + Send>,
+    etrng_responses: Box<dyn Iterator<Item = EtrngResponse> + Send>,
+}
+
+impl ModelRemote {
+    /// Connect to the model server at `addr` (`unix:<path>` or
+    /// `<host>:<port>`) and create a model from `params`.
+    pub fn connect(addr: &str, params: InitParams) -> Result<Self, Box<dyn Error>> {
+        if let Some(path) = addr.strip_prefix("unix:") {
+            #[cfg(unix)]
+            return Self::from_stream(UnixStream::connect(path)?, params);
+            #[cfg(not(unix))]
+            return Err(format!("Unix sockets aren't supported on this platform: {path}").into());
+        } else {
+            let stream = TcpStream::connect(addr)?;
+            stream.set_nodelay(true)?;
+            Self::from_stream(stream, params)
+        }
+    }
+
+    /// Create a model from `params` on the server at the other end of
+    /// `stream`. The TRNG inputs are served to the model as it consumes
+    /// them. Fails if `params` asks for something that can't be done
+    /// remotely, such as a file or trace on the client's file system.
+    pub fn from_stream(
+        stream: impl Read + Write + 'static,
+        params: InitParams,
+    ) -> Result<Self, Box<dyn Error>> {
+        check_remote_params(&params)?;
+        let mut req = FrameWriter::default();
+        req.u8(OP_INIT).u32(params.security_state.into());
+        for word in params.cptra_obf_key {
+            req.u32(word);
+        }
+        req.bytes(params.rom)
+            .bytes(params.dccm)
+            .bytes(params.iccm)
+            .u8(params.deterministic.into())
+            .u8(params.random_sram_puf.into());
+        remote_protocol::encode_trng_mode(&mut req, &params.trng_mode);
+        remote_protocol::encode_entropy_src_fault(&mut req, &params.entropy_src_fault);
+        remote_protocol::encode_crypto_timing(&mut req, &params.crypto_timing);
+        // trng_from_client
+        req.u8(1).u8(params.save_power_on_state.into());
+
+        let mut m = ModelRemote {
+            stream: Box::new(stream),
+            output: Output::new(params.log_writer),
+            ready_for_fw: false,
+            exit_reported: false,
+            itrng_nibbles: params.itrng_nibbles,
+            etrng_responses: params.etrng_responses,
+        };
+        m.try_request(&req)?;
+        Ok(m)
+    }
+
+    /// Step `cycles` clock cycles in a single request.
+    pub fn step_n(&mut self, cycles: u32) {
+        let mut req = FrameWriter::default();
+        req.u8(OP_STEP).u32(cycles);
+        self.request(&req);
+    }
+
+    /// Step until `condition` is met or `max_cycles` have elapsed, in a
+    /// single request. Returns true if the condition was met.
+    pub fn run_until(&mut self, condition: RunCondition, max_cycles: u32) -> bool {
+        let (condition, arg) = condition.encode();
+        let mut req = FrameWriter::default();
+        req.u8(OP_RUN_UNTIL).u8(condition).u32(arg).u32(max_cycles);
+        let results = self.request(&req);
+        results.first() == Some(&1)
+    }
+
+    /// Send a request and return the request-specific results. Panics if
+    /// the connection fails, as the model can't continue without it.
+    fn request(&mut self, req: &FrameWriter) -> Vec<u8> {
+        self.try_request(req)
+            .unwrap_or_else(|e| panic!("Remote model request failed: {e}"))
+    }
+
+    fn try_request(&mut self, req: &FrameWriter) -> io::Result<Vec<u8>> {
+        remote_protocol::write_frame(&mut self.stream, &req.0)?;
+        let frame = loop {
+            let frame = remote_protocol::read_frame(&mut self.stream)?;
+            if frame.first() != Some(&TRNG_REQUEST) {
+                break frame;
+            }
+            let reply = self.trng_reply(&frame[1..])?;
+            remote_protocol::write_frame(&mut self.stream, &reply.0)?;
+        };
+        let mut r = FrameReader(&frame);
+        if r.u8()? != RESULT_OK {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                String::from_utf8_lossy(r.rest()).into_owned(),
+            ));
+        }
+        self.ready_for_fw = r.u8()? != 0;
+        let exit_status = r.u8()?;
+        for &ch in r.bytes()? {
+            self.output.sink().push_uart_char(ch);
+        }
+        if exit_status != 0 && !self.exit_reported {
+            const TESTCASE_PASSED: u8 = 0xff;
+            const TESTCASE_FAILED: u8 = 0x01;
+            self.output.sink().push_uart_char(if exit_status == 1 {
+                TESTCASE_PASSED
+            } else {
+                TESTCASE_FAILED
+            });
+            self.exit_reported = true;
+        }
+        Ok(r.rest().to_vec())
+    }
+
+    /// Answer a TRNG request from the server.
+    fn trng_reply(&mut self, req: &[u8]) -> io::Result<FrameWriter> {
+        let mut r = FrameReader(req);
+        let kind = r.u8()?;
+        let count = r.u32()? as usize;
+        let mut reply = FrameWriter::default();
+        match kind {
+            TRNG_ITRNG_NIBBLES => {
+                let nibbles: Vec<u8> = self.itrng_nibbles.by_ref().take(count).collect();
+                reply.bytes(&nibbles);
+            }
+            TRNG_ETRNG_RESPONSES => {
+                let responses: Vec<_> = self.etrng_responses.by_ref().take(count).collect();
+                reply.u32(responses.len() as u32);
+                for response in &responses {
+                    remote_protocol::encode_etrng_response(&mut reply, response);
+                }
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown TRNG request kind {kind}"),
+                ))
+            }
+        }
+        Ok(reply)
+    }
+}
+
+/// Fail if `params` has settings the server can't honor, rather than
+/// silently running without them.
+fn check_remote_params(params: &InitParams) -> Result<(), String> {
+    let unsupported = [
+        ("trace_path", params.trace_path.is_some()),
+        ("trace_window_cycles", params.trace_window_cycles.is_some()),
+        ("checkpoint_dir", params.checkpoint_dir.is_some()),
+        ("spi_flash_path", params.spi_flash_path.is_some()),
+        ("timeline_path", params.timeline_path.is_some()),
+        ("timeline_symbols", !params.timeline_symbols.is_empty()),
+        ("mailbox_record_path", params.mailbox_record_path.is_some()),
+    ];
+    for (name, set) in unsupported {
+        if set {
+            return Err(format!(
+                "InitParams::{name} is not supported by remote models; set it on the server"
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl crate::HwModel for ModelRemote {
+    type TBus<'a> = RemoteApbBus<'a>;
+
+    fn new_unbooted(params: InitParams) -> Result<Self, Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        let addr = std::env::var("CPTRA_REMOTE_MODEL")
+            .map_err(|_| "CPTRA_REMOTE_MODEL must be set to the model server address")?;
+        Self::connect(&addr, params)
+    }
+
+    fn apb_bus(&mut self) -> Self::TBus<'_> {
+        RemoteApbBus { model: self }
+    }
+
+    fn step(&mut self) {
+        self.step_n(1);
+    }
+
+    fn output(&mut self) -> &mut Output {
+        &mut self.output
+    }
+
+    fn warm_reset(&mut self) {
+        let mut req = FrameWriter::default();
+        req.u8(OP_WARM_RESET);
+        self.request(&req);
+        self.exit_reported = false;
+    }
+
+    fn cold_reset(&mut self) -> Result<(), ModelError> {
+        let mut req = FrameWriter::default();
+        req.u8(OP_COLD_RESET);
+        // The server fails the request if its model can't cold reset
+        self.try_request(&req)
+            .map_err(|_| ModelError::ColdResetUnsupported)?;
+        self.exit_reported = false;
+        Ok(())
+    }
+
+    fn ready_for_fw(&self) -> bool {
+        self.ready_for_fw
+    }
+
+    fn step_until_ready_for_fw(&mut self, max_cycles: u32) -> bool {
+        self.run_until(RunCondition::ReadyForFw, max_cycles)
+    }
+
+    fn init_fuses(&mut self, fuses: &Fuses) {
+        let mut req = FrameWriter::default();
+        req.u8(OP_INIT_FUSES);
+        for word in remote_protocol::encode_fuses(fuses) {
+            req.u32(word);
+        }
+        self.request(&req);
+    }
+
+    fn copy_output_until_exit_success(
+        &mut self,
+        mut w: impl std::io::Write,
+    ) -> std::io::Result<()> {
+        loop {
+            let exited = self.run_until(RunCondition::ExitRequested, RUN_CHUNK_CYCLES);
+            if !self.output().peek().is_empty() {
+                w.write_all(self.output().take(usize::MAX).as_bytes())?;
+            }
+            if exited {
+                break;
+            }
+        }
+        match self.output().exit_status() {
+            Some(crate::output::ExitStatus::Passed) => Ok(()),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "firmware exited with failure",
+            )),
+        }
+    }
+
+    fn tracing_hint(&mut self, enable: bool) {
+        let mut req = FrameWriter::default();
+        req.u8(OP_TRACING_HINT).u8(enable.into());
+        self.request(&req);
+    }
+
+    fn set_apb_pauser(&mut self, pauser: u32) {
+        let mut req = FrameWriter::default();
+        req.u8(OP_SET_APB_PAUSER).u32(pauser);
+        self.request(&req);
+    }
+
+    fn mailbox_execute(&mut self, cmd: u32, buf: &[u8]) -> Result<Option<Vec<u8>>, ModelError> {
+        let mut req = FrameWriter::default();
+        req.u8(OP_MAILBOX_EXECUTE).u32(cmd).bytes(buf);
+        let results = self
+            .try_request(&req)
+            .map_err(|_| ModelError::UnableToLockMailbox)?;
+        let mut r = FrameReader(&results);
+        match r.u8() {
+            Ok(MBOX_COMPLETE) => Ok(None),
+            Ok(MBOX_DATA) => Ok(Some(
+                r.bytes()
+                    .map_err(|_| ModelError::MailboxNoResponseData)?
+                    .to_vec(),
+            )),
+            Ok(MBOX_FAILED) => Err(ModelError::MailboxCmdFailed(r.u32().unwrap_or(0))),
+            Ok(outcome) => Err(ModelError::UnknownCommandStatus(outcome.into())),
+            Err(_) => Err(ModelError::MailboxNoResponseData),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::mmio::Rv32GenMmio;
+    use crate::{HwModel, ModelEmulated, TrngMode};
+    use caliptra_registers::{soc_ifc, soc_ifc_trng};
+
+    fn connect_with(params: InitParams) -> (ModelRemote, std::thread::JoinHandle<io::Result<()>>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || {
+            remote_protocol::serve::<ModelEmulated>(server, || Box::new(io::sink()))
+        });
+        let model = ModelRemote::from_stream(client, params).unwrap();
+        (model, server)
+    }
+
+    fn connect(rom: &[u8]) -> (ModelRemote, std::thread::JoinHandle<io::Result<()>>) {
+        connect_with(InitParams {
+            rom,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_remote_output() {
+        let rv32_gen = Rv32GenMmio::new();
+        let soc_ifc =
+            unsafe { soc_ifc::RegisterBlock::new_with_mmio(0x3003_0000 as *mut u32, &rv32_gen) };
+        soc_ifc.cptra_boot_status().write(|_| 0x42);
+        soc_ifc
+            .cptra_generic_output_wires()
+            .at(0)
+            .write(|_| b'h'.into());
+        soc_ifc
+            .cptra_generic_output_wires()
+            .at(0)
+            .write(|_| 0x100 | u32::from(b'i'));
+        soc_ifc.cptra_generic_output_wires().at(0).write(|_| 0xff);
+        let rom = rv32_gen.into_inner().empty_loop().build();
+
+        let (mut model, server) = connect(&rom);
+        model.init_fuses(&Fuses::default());
+        assert!(model.soc_ifc().cptra_fuse_wr_done().read().done());
+        model.soc_ifc().cptra_bootfsm_go().write(|w| w.go(true));
+
+        assert!(model.run_until(RunCondition::BootStatus(0x42), 1000));
+        assert_eq!(model.soc_ifc().cptra_boot_status().read(), 0x42);
+        let mut output = vec![];
+        model.copy_output_until_exit_success(&mut output).unwrap();
+        assert_eq!(output, b"hi");
+        assert!(!model.ready_for_fw());
+
+        drop(model);
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_remote_errors() {
+        let rom = Rv32GenMmio::new().into_inner().empty_loop().build();
+        let (mut model, _server) = connect(&rom);
+        assert_eq!(
+            model.apb_bus().read(RvSize::Word, 0x1000_0000),
+            Err(BusError::LoadAccessFault)
+        );
+        assert_eq!(
+            model.apb_bus().read(RvSize::Byte, 0x3003_0038),
+            Err(BusError::LoadAccessFault)
+        );
+        // The model is still usable after an error
+        model.soc_ifc().cptra_boot_status().write(|_| 0x55);
+        assert_eq!(model.soc_ifc().cptra_boot_status().read(), 0x55);
+
+        let (client, server) = UnixStream::pair().unwrap();
+        std::thread::spawn(move || {
+            remote_protocol::serve::<ModelEmulated>(server, || Box::new(io::sink()))
+        });
+        let mut stream = client;
+        let mut req = FrameWriter::default();
+        req.u8(OP_STEP).u32(1);
+        remote_protocol::write_frame(&mut stream, &req.0).unwrap();
+        let response = remote_protocol::read_frame(&mut stream).unwrap();
+        assert_eq!(response[0], remote_protocol::RESULT_ERROR);
+        assert_eq!(&response[1..], b"INIT must be the first request");
+    }
+
+    #[test]
+    fn test_remote_unsupported_params() {
+        let rom = Rv32GenMmio::new().into_inner().empty_loop().build();
+        let (client, _server) = UnixStream::pair().unwrap();
+        let err = ModelRemote::from_stream(
+            client,
+            InitParams {
+                rom: &rom,
+                spi_flash_path: Some("flash.bin".into()),
+                ..Default::default()
+            },
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("spi_flash_path"), "{err}");
+    }
+
+    #[test]
+    fn test_remote_etrng_responses() {
+        let rv32_gen = Rv32GenMmio::new();
+        let soc_ifc_trng = unsafe {
+            soc_ifc_trng::RegisterBlock::new_with_mmio(0x3003_0000 as *mut u32, &rv32_gen)
+        };
+        soc_ifc_trng.cptra_trng_status().write(|w| w.data_req(true));
+        let rom = rv32_gen.into_inner().empty_loop().build();
+
+        let data: [u32; 12] = core::array::from_fn(|i| 0x1111_1111 * i as u32);
+        let (mut model, _server) = connect_with(InitParams {
+            rom: &rom,
+            trng_mode: Some(TrngMode::External),
+            etrng_responses: Box::new(std::iter::once(EtrngResponse { delay: 10, data })),
+            ..Default::default()
+        });
+        model.init_fuses(&Fuses::default());
+        model.soc_ifc().cptra_bootfsm_go().write(|w| w.go(true));
+        model.step_until(|m| m.soc_ifc_trng().cptra_trng_status().read().data_wr_done());
+
+        // The server asked this client for the response
+        let words: Vec<u32> = (0..12)
+            .map(|i| model.soc_ifc_trng().cptra_trng_data().at(i).read())
+            .collect();
+        assert_eq!(words, data);
+    }
+}
@@ -1,16 +1,24 @@
 // Licensed under the Apache-2.0 license
 
 use crate::bus_logger::{BusLogger, LogFile, NullBus};
+use crate::checkpoint_dir_or_env;
+use crate::equivalence::BusTransaction;
+use crate::mailbox_record_path_or_env;
+use crate::mailbox_recording::MailboxRecorder;
 use crate::trace_path_or_env;
+use crate::trace_window_cycles_or_env;
 use crate::EtrngResponse;
-use crate::{HwModel, TrngMode};
+use crate::DETERMINISTIC_SEED;
+use crate::{HwModel, ModelError, TrngMode};
 use caliptra_emu_bus::Bus;
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
 use caliptra_hw_model_types::ErrorInjectionMode;
 use caliptra_verilated::{AhbTxnType, CaliptraVerilated};
+use rand::{rngs::StdRng, SeedableRng};
 use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::ffi::OsStr;
-use std::io::Write;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
@@ -21,6 +29,9 @@ const DEFAULT_APB_PAUSER: u32 = 0x1;
 // How many clock cycles before emitting a TRNG nibble
 const TRNG_DELAY: u32 = 4;
 
+// How many checkpoints to keep in the checkpoint directory
+const CHECKPOINTS_KEPT: usize = 2;
+
 pub struct VerilatedApbBus<'a> {
     model: &'a mut ModelVerilated,
 }
@@ -74,6 +85,14 @@ pub struct ModelVerilated {
     output: Output,
     trace_enabled: bool,
     trace_path: Option<PathBuf>,
+    trace_window_cycles: Option<u64>,
+    trace_window_active: bool,
+    prev_error_fatal: bool,
+
+    checkpoint_dir: Option<PathBuf>,
+    checkpoint_interval_cycles: u64,
+    next_checkpoint_cycle: u64,
+    checkpoints: VecDeque<PathBuf>,
 
     trng_mode: TrngMode,
 
@@ -87,6 +106,8 @@ pub struct ModelVerilated {
     log: Rc<RefCell<BusLogger<NullBus>>>,
 
     soc_apb_pauser: u32,
+
+    mailbox_recorder: Option<MailboxRecorder>,
 }
 
 impl ModelVerilated {
@@ -96,6 +117,72 @@ impl ModelVerilated {
     pub fn stop_tracing(&mut self) {
         self.v.stop_tracing();
     }
+
+    /// Writes the trace window (see `InitParams::trace_window_cycles`) to the
+    /// trace path. Does nothing if no trace window is being kept.
+    pub fn save_trace_window(&mut self) -> io::Result<()> {
+        if !self.trace_window_active {
+            return Ok(());
+        }
+        let Some(trace_path) = &self.trace_path else {
+            return Ok(());
+        };
+        self.v.save_trace_window(trace_path.to_str().unwrap())
+    }
+
+    /// Restores the state of the model from a checkpoint written to
+    /// `InitParams::checkpoint_dir`, by a model built from the same RTL and
+    /// booted with the same ROM. The TRNG inputs and the output log are not
+    /// part of the checkpoint, so they continue from where this model was.
+    pub fn restore_checkpoint(&mut self, path: &Path) -> io::Result<()> {
+        self.v.restore_checkpoint(path.to_str().unwrap())?;
+        self.prev_error_fatal = self.v.output.cptra_error_fatal;
+        self.next_checkpoint_cycle = self.v.total_cycles() + self.checkpoint_interval_cycles;
+        Ok(())
+    }
+
+    fn dump_trace_window(&mut self, reason: &str) {
+        if !self.trace_window_active {
+            return;
+        }
+        match self.save_trace_window() {
+            Ok(()) => eprintln!(
+                "{reason}; wrote trace window to {:?}",
+                self.trace_path.as_ref().unwrap()
+            ),
+            Err(e) => eprintln!("{reason}; unable to write trace window: {e}"),
+        }
+    }
+
+    fn process_checkpoint(&mut self) {
+        let Some(checkpoint_dir) = &self.checkpoint_dir else {
+            return;
+        };
+        let cycles = self.v.total_cycles();
+        if cycles < self.next_checkpoint_cycle {
+            return;
+        }
+        self.next_checkpoint_cycle = cycles + self.checkpoint_interval_cycles;
+        let path = checkpoint_dir.join(format!("checkpoint-{cycles}.bin"));
+        if let Err(e) = self.v.save_checkpoint(path.to_str().unwrap()) {
+            eprintln!("{e}; disabling checkpoints");
+            self.checkpoint_dir = None;
+            return;
+        }
+        self.checkpoints.push_back(path);
+        while self.checkpoints.len() > CHECKPOINTS_KEPT {
+            let old = self.checkpoints.pop_front().unwrap();
+            std::fs::remove_file(old).ok();
+        }
+    }
+}
+
+impl Drop for ModelVerilated {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.dump_trace_window("Test panicked");
+        }
+    }
 }
 
 fn ahb_txn_size(ty: AhbTxnType) -> RvSize {
@@ -107,13 +194,25 @@ fn ahb_txn_size(ty: AhbTxnType) -> RvSize {
     }
 }
 
+// The data of a transaction as the emulator sees it: byte and halfword
+// accesses are shifted down from their lane of the 32-bit word.
+fn ahb_txn_data(ty: AhbTxnType, addr: u32, data: u64) -> u32 {
+    let shift = (addr & 3) * 8;
+    match ahb_txn_size(ty) {
+        RvSize::Byte => (data as u32 >> shift) & 0xff,
+        RvSize::HalfWord => (data as u32 >> shift) & 0xffff,
+        _ => data as u32,
+    }
+}
+
 impl crate::HwModel for ModelVerilated {
     type TBus<'a> = VerilatedApbBus<'a>;
 
-    fn new_unbooted(params: crate::InitParams) -> Result<Self, Box<dyn std::error::Error>>
+    fn new_unbooted(mut params: crate::InitParams) -> Result<Self, Box<dyn std::error::Error>>
     where
         Self: Sized,
     {
+        params.pin_trng_inputs();
         let output = Output::new(params.log_writer);
 
         let output_sink = output.sink().clone();
@@ -145,7 +244,7 @@ impl crate::HwModel for ModelVerilated {
                         "UC",
                         ahb_txn_size(ty),
                         addr,
-                        data as u32,
+                        ahb_txn_data(ty, addr, data),
                         Ok(()),
                     );
                     if ty == AhbTxnType::WriteU64 {
@@ -158,10 +257,13 @@ impl crate::HwModel for ModelVerilated {
                         );
                     }
                 } else {
-                    bus_log
-                        .borrow_mut()
-                        .log_read("UC", ahb_txn_size(ty), addr, Ok(data as u32));
-                    if ty == AhbTxnType::WriteU64 {
+                    bus_log.borrow_mut().log_read(
+                        "UC",
+                        ahb_txn_size(ty),
+                        addr,
+                        Ok(ahb_txn_data(ty, addr, data)),
+                    );
+                    if ty == AhbTxnType::ReadU64 {
                         bus_log.borrow_mut().log_read(
                             "UC",
                             ahb_txn_size(ty),
@@ -200,11 +302,29 @@ impl crate::HwModel for ModelVerilated {
 
         v.write_rom_image(params.rom);
 
+        let mailbox_recorder = match mailbox_record_path_or_env(params.mailbox_record_path) {
+            Some(path) => Some(MailboxRecorder::create(&path)?),
+            None => None,
+        };
+
+        let checkpoint_dir = checkpoint_dir_or_env(params.checkpoint_dir);
+        if let Some(checkpoint_dir) = &checkpoint_dir {
+            std::fs::create_dir_all(checkpoint_dir)?;
+        }
+
         let mut m = ModelVerilated {
             v,
             output,
             trace_enabled: false,
             trace_path: trace_path_or_env(params.trace_path),
+            trace_window_cycles: trace_window_cycles_or_env(params.trace_window_cycles),
+            trace_window_active: false,
+            prev_error_fatal: false,
+
+            checkpoint_dir,
+            checkpoint_interval_cycles: params.checkpoint_interval_cycles,
+            next_checkpoint_cycle: params.checkpoint_interval_cycles,
+            checkpoints: VecDeque::new(),
 
             trng_mode: desired_trng_mode,
 
@@ -218,12 +338,18 @@ impl crate::HwModel for ModelVerilated {
             log,
 
             soc_apb_pauser: DEFAULT_APB_PAUSER,
+
+            mailbox_recorder,
         };
 
         m.tracing_hint(true);
 
         if params.random_sram_puf {
-            m.v.init_random_puf_state(&mut rand::thread_rng());
+            if params.deterministic {
+                m.v.init_random_puf_state(&mut StdRng::seed_from_u64(DETERMINISTIC_SEED));
+            } else {
+                m.v.init_random_puf_state(&mut rand::thread_rng());
+            }
         }
 
         m.v.input.cptra_pwrgood = true;
@@ -247,6 +373,13 @@ impl crate::HwModel for ModelVerilated {
         self.process_trng_start();
         self.v.next_cycle_high(1);
         self.process_trng_end();
+
+        let error_fatal = self.v.output.cptra_error_fatal;
+        if error_fatal && !self.prev_error_fatal {
+            self.dump_trace_window("Fatal error");
+        }
+        self.prev_error_fatal = error_fatal;
+        self.process_checkpoint();
     }
 
     fn output(&mut self) -> &mut crate::Output {
@@ -268,6 +401,25 @@ impl crate::HwModel for ModelVerilated {
         }
     }
 
+    fn cold_reset(&mut self) -> Result<(), ModelError> {
+        // Drop power and reset
+        self.v.input.cptra_pwrgood = false;
+        self.v.input.cptra_rst_b = false;
+        self.v.next_cycle_high(1);
+
+        self.v.input.cptra_pwrgood = true;
+        self.v.next_cycle_high(1);
+
+        self.v.input.cptra_rst_b = true;
+        self.v.next_cycle_high(1);
+
+        // Wait for ready_for_fuses
+        while !self.v.output.ready_for_fuses {
+            self.v.next_cycle_high(1);
+        }
+        Ok(())
+    }
+
     fn ready_for_fw(&self) -> bool {
         self.v.output.ready_for_fw_push
     }
@@ -278,7 +430,12 @@ impl crate::HwModel for ModelVerilated {
             if enable {
                 if let Some(trace_path) = &self.trace_path {
                     if trace_path.extension() == Some(OsStr::new("vcd")) {
-                        self.v.start_tracing(trace_path.to_str().unwrap(), 99).ok();
+                        if let Some(window_cycles) = self.trace_window_cycles {
+                            self.v.start_trace_window(99, window_cycles);
+                            self.trace_window_active = true;
+                        } else {
+                            self.v.start_tracing(trace_path.to_str().unwrap(), 99).ok();
+                        }
                     } else {
                         self.log.borrow_mut().log = match LogFile::open(Path::new(&trace_path)) {
                             Ok(file) => Some(file),
@@ -292,11 +449,30 @@ impl crate::HwModel for ModelVerilated {
             } else {
                 if self.log.borrow_mut().log.take().is_none() {
                     self.v.stop_tracing();
+                    self.trace_window_active = false;
                 }
             }
         }
     }
 
+    fn mailbox_recorder(&mut self) -> Option<&mut MailboxRecorder> {
+        self.mailbox_recorder.as_mut()
+    }
+
+    fn start_bus_capture(&mut self) -> Result<(), ModelError> {
+        self.log.borrow_mut().capture = Some(vec![]);
+        Ok(())
+    }
+
+    fn take_bus_capture(&mut self) -> Vec<BusTransaction> {
+        self.log
+            .borrow_mut()
+            .capture
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
     fn ecc_error_injection(&mut self, mode: ErrorInjectionMode) {
         match mode {
             ErrorInjectionMode::None => {
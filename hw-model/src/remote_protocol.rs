@@ -0,0 +1,723 @@
+// Licensed under the Apache-2.0 license
+
+//! A simple protocol for driving an [`HwModel`] in another process, such as
+//! a SoC simulator that can't link the Rust library. [`serve()`] implements
+//! the server side, and [`crate::ModelRemote`] the client side.
+//!
+//! # Framing
+//!
+//! All integers are little-endian. Every request and response is a frame: a
+//! `u32` byte length followed by that many bytes. Byte strings inside a frame
+//! (`bytes` below) are a `u32` length followed by the bytes.
+//!
+//! A request frame starts with a `u8` opcode followed by the opcode's
+//! arguments. The server answers every request with exactly one response
+//! frame, which starts with a `u8` result:
+//!
+//! - `0` (OK): followed by a status block and the opcode's results.
+//! - `1` (ERROR): followed by a UTF-8 error message filling the rest of the
+//!   frame. The model is still usable unless the error was for `INIT`.
+//!
+//! Frames are limited to [`MAX_FRAME_SIZE`] bytes.
+//!
+//! The status block reports the state of the model after the request, and the
+//! UART output produced while handling it, so the client never has to poll for
+//! output:
+//!
+//! | Field        | Type    | Description                                |
+//! |--------------|---------|--------------------------------------------|
+//! | ready_for_fw | `u8`    | 1 if the ready_for_fw signal is high       |
+//! | exit_status  | `u8`    | 0: running, 1: test passed, 2: test failed |
+//! | output       | `bytes` | New UART output                            |
+//!
+//! # Requests
+//!
+//! | Opcode | Name            | Arguments                                   | Results           |
+//! |--------|-----------------|---------------------------------------------|-------------------|
+//! | 0x01   | INIT            | security_state: `u32`, obf_key: `u32`×8, rom: `bytes`, dccm: `bytes`, iccm: `bytes`, deterministic: `u8`, random_sram_puf: `u8`, trng_mode: `u8`, entropy_src_fault: (see below), crypto_timing: `u64`×9, trng_from_client: `u8`, save_power_on_state: `u8` | |
+//! | 0x02   | APB_READ        | addr: `u32`                                 | val: `u32`        |
+//! | 0x03   | APB_WRITE       | addr: `u32`, val: `u32`                     |                   |
+//! | 0x04   | STEP            | cycles: `u32`                               |                   |
+//! | 0x05   | RUN_UNTIL       | condition: `u8`, arg: `u32`, max_cycles: `u32` | met: `u8`      |
+//! | 0x06   | INIT_FUSES      | fuses: `u32`×82                             |                   |
+//! | 0x07   | MAILBOX_EXECUTE | cmd: `u32`, data: `bytes`                   | outcome: `u8`, then fw_error: `u32` or data: `bytes` |
+//! | 0x08   | SET_APB_PAUSER  | pauser: `u32`                               |                   |
+//! | 0x09   | TRACING_HINT    | enable: `u8`                                |                   |
+//! | 0x0a   | WARM_RESET      |                                             |                   |
+//! | 0x0b   | COLD_RESET      |                                             |                   |
+//!
+//! `INIT` must be the first request on a connection; it creates the model.
+//! Each connection gets its own model, which is dropped when the connection
+//! is closed. Its arguments mirror the fields of [`InitParams`]:
+//!
+//! - trng_mode is `0` for the model's default, `1` for internal or `2` for
+//!   external.
+//! - entropy_src_fault is a fault `u8` (`0`: none, `1`: repetition count,
+//!   `2`: adaptive proportion high, `3`: adaptive proportion low, `4`: stuck
+//!   bit), wire: `u8` and value: `u8` for stuck bits, and after_nibbles:
+//!   `u32`.
+//! - crypto_timing is the fields of [`CryptoTiming`] in order.
+//! - If trng_from_client is `1`, the model's TRNG inputs come from the
+//!   client (see below). Otherwise the server generates them.
+//!
+//! # TRNG requests
+//!
+//! When trng_from_client is set, the server may send TRNG request frames
+//! while handling any request, before its response. A TRNG request frame
+//! starts with `2` followed by a kind: `u8` and count: `u32`, and the client
+//! must answer it with a frame holding up to count items:
+//!
+//! - kind `0`: nibbles for the internal TRNG as `bytes`, one nibble per byte.
+//! - kind `1`: a number of external TRNG responses: `u32`, each a delay:
+//!   `u32` and data: `u32`×12.
+//!
+//! Answering with fewer items than requested ends that input.
+//!
+//! `RUN_UNTIL` steps until the condition is met or `max_cycles` have elapsed:
+//!
+//! - `0`: the ready_for_fw signal is high.
+//! - `1`: the firmware has requested an exit (see exit_status).
+//! - `2`: CPTRA_BOOT_STATUS equals `arg`.
+//!
+//! `INIT_FUSES` takes the fuse words in the order of the fields of
+//! [`Fuses`]: uds_seed (12), field_entropy (8), key_manifest_pk_hash (12),
+//! key_manifest_pk_hash_mask (1), owner_pk_hash (12), fmc_key_manifest_svn
+//! (1), runtime_svn (4), anti_rollback_disable (1), idevid_cert_attr (24),
+//! idevid_manuf_hsm_id (4), life_cycle (1), lms_verify (1) and
+//! fuse_lms_revocation (1).
+//!
+//! The `MAILBOX_EXECUTE` outcome is `0` if the command completed without
+//! response data, `1` if it completed with response `data`, or `2` if it
+//! failed with the firmware error `fw_error`.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use caliptra_emu_bus::Bus;
+use caliptra_emu_types::RvSize;
+use caliptra_hw_model_types::EtrngResponse;
+
+use crate::output::ExitStatus;
+use crate::{
+    CryptoTiming, DeviceLifecycle, EntropySrcFault, Fuses, HwModel, InitParams, ModelError,
+    NoiseSourceFault, SecurityState, TrngMode, U4,
+};
+
+pub(crate) const OP_INIT: u8 = 0x01;
+pub(crate) const OP_APB_READ: u8 = 0x02;
+pub(crate) const OP_APB_WRITE: u8 = 0x03;
+pub(crate) const OP_STEP: u8 = 0x04;
+pub(crate) const OP_RUN_UNTIL: u8 = 0x05;
+pub(crate) const OP_INIT_FUSES: u8 = 0x06;
+pub(crate) const OP_MAILBOX_EXECUTE: u8 = 0x07;
+pub(crate) const OP_SET_APB_PAUSER: u8 = 0x08;
+pub(crate) const OP_TRACING_HINT: u8 = 0x09;
+pub(crate) const OP_WARM_RESET: u8 = 0x0a;
+pub(crate) const OP_COLD_RESET: u8 = 0x0b;
+
+pub(crate) const RESULT_OK: u8 = 0;
+pub(crate) const RESULT_ERROR: u8 = 1;
+pub(crate) const TRNG_REQUEST: u8 = 2;
+
+pub(crate) const TRNG_ITRNG_NIBBLES: u8 = 0;
+pub(crate) const TRNG_ETRNG_RESPONSES: u8 = 1;
+
+pub(crate) const MBOX_COMPLETE: u8 = 0;
+pub(crate) const MBOX_DATA: u8 = 1;
+pub(crate) const MBOX_FAILED: u8 = 2;
+
+const FUSE_WORDS: usize = 82;
+
+/// The largest frame either side accepts, enough for INIT with full-size
+/// ROM, ICCM and DCCM images.
+pub const MAX_FRAME_SIZE: usize = 1 << 20;
+
+// The number of internal TRNG nibbles the server requests at a time
+const ITRNG_NIBBLE_CHUNK: u32 = 4096;
+
+/// A condition for the `RUN_UNTIL` request.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RunCondition {
+    ReadyForFw,
+    ExitRequested,
+    BootStatus(u32),
+}
+impl RunCondition {
+    pub(crate) fn encode(self) -> (u8, u32) {
+        match self {
+            RunCondition::ReadyForFw => (0, 0),
+            RunCondition::ExitRequested => (1, 0),
+            RunCondition::BootStatus(val) => (2, val),
+        }
+    }
+    fn decode(condition: u8, arg: u32) -> Option<Self> {
+        match condition {
+            0 => Some(RunCondition::ReadyForFw),
+            1 => Some(RunCondition::ExitRequested),
+            2 => Some(RunCondition::BootStatus(arg)),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn read_frame(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds the {MAX_FRAME_SIZE} byte limit"),
+        ));
+    }
+    let mut frame = vec![0u8; len];
+    r.read_exact(&mut frame)?;
+    Ok(frame)
+}
+
+pub(crate) fn write_frame(w: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    w.write_all(&(frame.len() as u32).to_le_bytes())?;
+    w.write_all(frame)?;
+    w.flush()
+}
+
+/// Appends protocol values to a frame.
+#[derive(Default)]
+pub(crate) struct FrameWriter(pub Vec<u8>);
+impl FrameWriter {
+    pub fn u8(&mut self, val: u8) -> &mut Self {
+        self.0.push(val);
+        self
+    }
+    pub fn u32(&mut self, val: u32) -> &mut Self {
+        self.0.extend_from_slice(&val.to_le_bytes());
+        self
+    }
+    pub fn u64(&mut self, val: u64) -> &mut Self {
+        self.0.extend_from_slice(&val.to_le_bytes());
+        self
+    }
+    pub fn bytes(&mut self, val: &[u8]) -> &mut Self {
+        self.u32(val.len() as u32);
+        self.0.extend_from_slice(val);
+        self
+    }
+}
+
+/// Reads protocol values from a frame.
+pub(crate) struct FrameReader<'a>(pub &'a [u8]);
+impl<'a> FrameReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.0.len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "frame too short",
+            ));
+        }
+        let (result, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(result)
+    }
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+    pub fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+    pub fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+    pub fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+    pub fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.0)
+    }
+}
+
+pub(crate) fn encode_fuses(fuses: &Fuses) -> Vec<u32> {
+    let mut words = Vec::with_capacity(FUSE_WORDS);
+    words.extend_from_slice(&fuses.uds_seed);
+    words.extend_from_slice(&fuses.field_entropy);
+    words.extend_from_slice(&fuses.key_manifest_pk_hash);
+    words.push(fuses.key_manifest_pk_hash_mask.into());
+    words.extend_from_slice(&fuses.owner_pk_hash);
+    words.push(fuses.fmc_key_manifest_svn);
+    words.extend_from_slice(&fuses.runtime_svn);
+    words.push(fuses.anti_rollback_disable.into());
+    words.extend_from_slice(&fuses.idevid_cert_attr);
+    words.extend_from_slice(&fuses.idevid_manuf_hsm_id);
+    words.push(fuses.life_cycle.into());
+    words.push(fuses.lms_verify.into());
+    words.push(fuses.fuse_lms_revocation);
+    words
+}
+
+fn decode_fuses(r: &mut FrameReader) -> io::Result<Fuses> {
+    fn invalid(field: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("invalid {field}"))
+    }
+    fn words<const N: usize>(r: &mut FrameReader) -> io::Result<[u32; N]> {
+        let mut result = [0u32; N];
+        for word in result.iter_mut() {
+            *word = r.u32()?;
+        }
+        Ok(result)
+    }
+    Ok(Fuses {
+        uds_seed: words(r)?,
+        field_entropy: words(r)?,
+        key_manifest_pk_hash: words(r)?,
+        key_manifest_pk_hash_mask: U4::try_from(r.u32()?)
+            .map_err(|_| invalid("key_manifest_pk_hash_mask"))?,
+        owner_pk_hash: words(r)?,
+        fmc_key_manifest_svn: r.u32()?,
+        runtime_svn: words(r)?,
+        anti_rollback_disable: r.u32()? != 0,
+        idevid_cert_attr: words(r)?,
+        idevid_manuf_hsm_id: words(r)?,
+        life_cycle: DeviceLifecycle::try_from(r.u32()?).map_err(|_| invalid("life_cycle"))?,
+        lms_verify: r.u32()? != 0,
+        fuse_lms_revocation: r.u32()?,
+    })
+}
+
+pub(crate) fn encode_trng_mode(w: &mut FrameWriter, mode: &Option<TrngMode>) {
+    w.u8(match mode {
+        None => 0,
+        Some(TrngMode::Internal) => 1,
+        Some(TrngMode::External) => 2,
+    });
+}
+
+fn decode_trng_mode(r: &mut FrameReader) -> io::Result<Option<TrngMode>> {
+    match r.u8()? {
+        0 => Ok(None),
+        1 => Ok(Some(TrngMode::Internal)),
+        2 => Ok(Some(TrngMode::External)),
+        _ => Err(invalid_data("invalid trng_mode")),
+    }
+}
+
+pub(crate) fn encode_entropy_src_fault(w: &mut FrameWriter, fault: &Option<EntropySrcFault>) {
+    let Some(fault) = fault else {
+        w.u8(0).u8(0).u8(0).u32(0);
+        return;
+    };
+    let (kind, wire, value) = match fault.fault {
+        NoiseSourceFault::RepetitionCount => (1, 0, false),
+        NoiseSourceFault::AdaptiveProportionHi => (2, 0, false),
+        NoiseSourceFault::AdaptiveProportionLo => (3, 0, false),
+        NoiseSourceFault::StuckBit { wire, value } => (4, wire, value),
+    };
+    w.u8(kind)
+        .u8(wire)
+        .u8(value.into())
+        .u32(fault.after_nibbles as u32);
+}
+
+fn decode_entropy_src_fault(r: &mut FrameReader) -> io::Result<Option<EntropySrcFault>> {
+    let kind = r.u8()?;
+    let wire = r.u8()?;
+    let value = r.u8()? != 0;
+    let after_nibbles = r.u32()? as usize;
+    let fault = match kind {
+        0 => return Ok(None),
+        1 => NoiseSourceFault::RepetitionCount,
+        2 => NoiseSourceFault::AdaptiveProportionHi,
+        3 => NoiseSourceFault::AdaptiveProportionLo,
+        4 => NoiseSourceFault::StuckBit { wire, value },
+        _ => return Err(invalid_data("invalid entropy_src_fault")),
+    };
+    Ok(Some(EntropySrcFault {
+        fault,
+        after_nibbles,
+    }))
+}
+
+pub(crate) fn encode_crypto_timing(w: &mut FrameWriter, timing: &CryptoTiming) {
+    w.u64(timing.ecc384_keygen)
+        .u64(timing.ecc384_sign)
+        .u64(timing.ecc384_verify)
+        .u64(timing.hmac384_init)
+        .u64(timing.hmac384_next)
+        .u64(timing.sha512_block)
+        .u64(timing.sha512_pcr_hash)
+        .u64(timing.sha512_acc_op)
+        .u64(timing.sha512_acc_block);
+}
+
+fn decode_crypto_timing(r: &mut FrameReader) -> io::Result<CryptoTiming> {
+    Ok(CryptoTiming {
+        ecc384_keygen: r.u64()?,
+        ecc384_sign: r.u64()?,
+        ecc384_verify: r.u64()?,
+        hmac384_init: r.u64()?,
+        hmac384_next: r.u64()?,
+        sha512_block: r.u64()?,
+        sha512_pcr_hash: r.u64()?,
+        sha512_acc_op: r.u64()?,
+        sha512_acc_block: r.u64()?,
+    })
+}
+
+pub(crate) fn encode_etrng_response(w: &mut FrameWriter, response: &EtrngResponse) {
+    w.u32(response.delay);
+    for word in response.data {
+        w.u32(word);
+    }
+}
+
+fn decode_etrng_response(r: &mut FrameReader) -> io::Result<EtrngResponse> {
+    let delay = r.u32()?;
+    let mut data = [0u32; 12];
+    for word in data.iter_mut() {
+        *word = r.u32()?;
+    }
+    Ok(EtrngResponse { delay, data })
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// A connection shared between the request loop and the TRNG inputs, which
+/// send TRNG requests to the client while the model is stepping.
+struct Connection<S>(Arc<Mutex<S>>);
+impl<S> Clone for Connection<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+impl<S: Read + Write> Connection<S> {
+    fn trng_request(&self, kind: u8, count: u32) -> io::Result<Vec<u8>> {
+        let mut stream = self.0.lock().unwrap();
+        let mut req = FrameWriter::default();
+        req.u8(TRNG_REQUEST).u8(kind).u32(count);
+        write_frame(&mut *stream, &req.0)?;
+        read_frame(&mut *stream)
+    }
+}
+
+/// Internal TRNG nibbles requested from the client in chunks.
+struct ClientNibbles<S> {
+    conn: Connection<S>,
+    buf: VecDeque<u8>,
+    done: bool,
+}
+impl<S: Read + Write> Iterator for ClientNibbles<S> {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        if self.buf.is_empty() && !self.done {
+            let nibbles = self
+                .conn
+                .trng_request(TRNG_ITRNG_NIBBLES, ITRNG_NIBBLE_CHUNK)
+                .and_then(|frame| Ok(FrameReader(&frame).bytes()?.to_vec()))
+                .unwrap_or_default();
+            self.done = nibbles.len() < ITRNG_NIBBLE_CHUNK as usize;
+            self.buf.extend(nibbles);
+        }
+        self.buf.pop_front()
+    }
+}
+
+/// External TRNG responses requested from the client one at a time.
+struct ClientEtrngResponses<S> {
+    conn: Connection<S>,
+    done: bool,
+}
+impl<S: Read + Write> Iterator for ClientEtrngResponses<S> {
+    type Item = EtrngResponse;
+    fn next(&mut self) -> Option<EtrngResponse> {
+        if self.done {
+            return None;
+        }
+        let response = self
+            .conn
+            .trng_request(TRNG_ETRNG_RESPONSES, 1)
+            .and_then(|frame| {
+                let mut r = FrameReader(&frame);
+                match r.u32()? {
+                    0 => Ok(None),
+                    _ => Ok(Some(decode_etrng_response(&mut r)?)),
+                }
+            })
+            .unwrap_or_default();
+        self.done = response.is_none();
+        response
+    }
+}
+
+/// Serve the model protocol on `stream` until the client disconnects. Logs
+/// from the model are written to `log_writer`.
+pub fn serve<T: HwModel>(
+    stream: impl Read + Write + Send + 'static,
+    log_writer: impl Fn() -> Box<dyn Write>,
+) -> io::Result<()> {
+    let conn = Connection(Arc::new(Mutex::new(stream)));
+    let mut model: Option<T> = None;
+    loop {
+        // The lock is released while handling the request, so the TRNG
+        // inputs can use the connection.
+        let frame = match read_frame(&mut *conn.0.lock().unwrap()) {
+            Ok(frame) => frame,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let mut response = FrameWriter::default();
+        response.u8(RESULT_OK);
+        let result = handle_request(&mut model, &frame, &conn, &log_writer, &mut response);
+        if let Err(err) = result {
+            response.0.clear();
+            response.u8(RESULT_ERROR);
+            response.0.extend_from_slice(err.as_bytes());
+        }
+        write_frame(&mut *conn.0.lock().unwrap(), &response.0)?;
+    }
+}
+
+fn handle_request<T: HwModel, S: Read + Write + Send + 'static>(
+    model: &mut Option<T>,
+    frame: &[u8],
+    conn: &Connection<S>,
+    log_writer: &impl Fn() -> Box<dyn Write>,
+    response: &mut FrameWriter,
+) -> Result<(), String> {
+    let mut r = FrameReader(frame);
+    let op = r.u8().map_err(|e| e.to_string())?;
+    if op == OP_INIT {
+        *model = None;
+        let new_model = init_model(&mut r, conn, log_writer).map_err(|e| e.to_string())?;
+        write_status(model.insert(new_model), response);
+        return Ok(());
+    }
+    let Some(model) = model else {
+        return Err("INIT must be the first request".into());
+    };
+    let results = handle_model_request(model, op, &mut r).map_err(|e| e.to_string())?;
+    write_status(model, response);
+    response.0.extend_from_slice(&results.0);
+    Ok(())
+}
+
+fn init_model<T: HwModel, S: Read + Write + Send + 'static>(
+    r: &mut FrameReader,
+    conn: &Connection<S>,
+    log_writer: &impl Fn() -> Box<dyn Write>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let security_state = SecurityState::from(r.u32()?);
+    let mut cptra_obf_key = [0u32; 8];
+    for word in cptra_obf_key.iter_mut() {
+        *word = r.u32()?;
+    }
+    let rom = r.bytes()?;
+    let dccm = r.bytes()?;
+    let iccm = r.bytes()?;
+    let deterministic = r.u8()? != 0;
+    let random_sram_puf = r.u8()? != 0;
+    let trng_mode = decode_trng_mode(r)?;
+    let entropy_src_fault = decode_entropy_src_fault(r)?;
+    let crypto_timing = decode_crypto_timing(r)?;
+    let trng_from_client = r.u8()? != 0;
+    let save_power_on_state = r.u8()? != 0;
+    let mut params = InitParams {
+        rom,
+        dccm,
+        iccm,
+        log_writer: log_writer(),
+        security_state,
+        cptra_obf_key,
+        deterministic,
+        random_sram_puf,
+        trng_mode,
+        entropy_src_fault,
+        crypto_timing,
+        save_power_on_state,
+        ..Default::default()
+    };
+    if trng_from_client {
+        params.itrng_nibbles = Box::new(ClientNibbles {
+            conn: conn.clone(),
+            buf: VecDeque::new(),
+            done: false,
+        });
+        params.etrng_responses = Box::new(ClientEtrngResponses {
+            conn: conn.clone(),
+            done: false,
+        });
+    }
+    T::new_unbooted(params)
+}
+
+fn handle_model_request<T: HwModel>(
+    model: &mut T,
+    op: u8,
+    r: &mut FrameReader,
+) -> Result<FrameWriter, Box<dyn std::error::Error>> {
+    let mut results = FrameWriter::default();
+    match op {
+        OP_APB_READ => {
+            let addr = r.u32()?;
+            let val = model
+                .apb_bus()
+                .read(RvSize::Word, addr)
+                .map_err(|e| format!("APB read of 0x{addr:08x} failed: {e:?}"))?;
+            results.u32(val);
+        }
+        OP_APB_WRITE => {
+            let addr = r.u32()?;
+            let val = r.u32()?;
+            model
+                .apb_bus()
+                .write(RvSize::Word, addr, val)
+                .map_err(|e| format!("APB write of 0x{addr:08x} failed: {e:?}"))?;
+        }
+        OP_STEP => {
+            for _ in 0..r.u32()? {
+                model.step();
+            }
+        }
+        OP_RUN_UNTIL => {
+            let condition = r.u8()?;
+            let arg = r.u32()?;
+            let max_cycles = r.u32()?;
+            let condition = RunCondition::decode(condition, arg)
+                .ok_or_else(|| format!("unknown condition {condition}"))?;
+            let mut met = false;
+            for _ in 0..max_cycles {
+                met = match condition {
+                    RunCondition::ReadyForFw => model.ready_for_fw(),
+                    RunCondition::ExitRequested => model.output().exit_requested(),
+                    RunCondition::BootStatus(val) => {
+                        model.soc_ifc().cptra_boot_status().read() == val
+                    }
+                };
+                if met {
+                    break;
+                }
+                model.step();
+            }
+            results.u8(met.into());
+        }
+        OP_INIT_FUSES => {
+            let fuses = decode_fuses(r)?;
+            model.init_fuses(&fuses);
+        }
+        OP_MAILBOX_EXECUTE => {
+            let cmd = r.u32()?;
+            let data = r.bytes()?;
+            match model.mailbox_execute(cmd, data) {
+                Ok(None) => {
+                    results.u8(MBOX_COMPLETE);
+                }
+                Ok(Some(data)) => {
+                    results.u8(MBOX_DATA).bytes(&data);
+                }
+                Err(ModelError::MailboxCmdFailed(fw_error)) => {
+                    results.u8(MBOX_FAILED).u32(fw_error);
+                }
+                Err(e) => Err(e)?,
+            }
+        }
+        OP_SET_APB_PAUSER => model.set_apb_pauser(r.u32()?),
+        OP_TRACING_HINT => model.tracing_hint(r.u8()? != 0),
+        OP_WARM_RESET => model.warm_reset(),
+        OP_COLD_RESET => model.cold_reset()?,
+        _ => Err(format!("unknown opcode 0x{op:02x}"))?,
+    }
+    Ok(results)
+}
+
+fn write_status<T: HwModel>(model: &mut T, response: &mut FrameWriter) {
+    let output = model.output().take(usize::MAX);
+    let exit_status = match model.output().exit_status() {
+        None => 0,
+        Some(ExitStatus::Passed) => 1,
+        Some(ExitStatus::Failed) => 2,
+    };
+    response
+        .u8(model.ready_for_fw().into())
+        .u8(exit_status)
+        .bytes(output.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuses_round_trip() {
+        let fuses = Fuses {
+            uds_seed: [1; 12],
+            key_manifest_pk_hash_mask: U4::X5,
+            owner_pk_hash: [2; 12],
+            fmc_key_manifest_svn: 3,
+            runtime_svn: [4; 4],
+            anti_rollback_disable: true,
+            idevid_cert_attr: [5; 24],
+            life_cycle: DeviceLifecycle::Production,
+            lms_verify: true,
+            fuse_lms_revocation: 6,
+            ..Default::default()
+        };
+        let words = encode_fuses(&fuses);
+        assert_eq!(words.len(), FUSE_WORDS);
+
+        let mut frame = FrameWriter::default();
+        for word in words.iter() {
+            frame.u32(*word);
+        }
+        let decoded = decode_fuses(&mut FrameReader(&frame.0)).unwrap();
+        assert_eq!(encode_fuses(&decoded), words);
+        assert_eq!(decoded.life_cycle, DeviceLifecycle::Production);
+
+        frame.0.pop();
+        assert!(decode_fuses(&mut FrameReader(&frame.0)).is_err());
+    }
+
+    #[test]
+    fn test_init_params_round_trip() {
+        let fault = Some(EntropySrcFault {
+            fault: NoiseSourceFault::StuckBit {
+                wire: 2,
+                value: true,
+            },
+            after_nibbles: 1000,
+        });
+        let timing = CryptoTiming::VERILATED;
+        let response = EtrngResponse {
+            delay: 7,
+            data: [0x1234_5678; 12],
+        };
+        let mut frame = FrameWriter::default();
+        encode_trng_mode(&mut frame, &Some(TrngMode::External));
+        encode_entropy_src_fault(&mut frame, &fault);
+        encode_entropy_src_fault(&mut frame, &None);
+        encode_crypto_timing(&mut frame, &timing);
+        encode_etrng_response(&mut frame, &response);
+
+        let mut r = FrameReader(&frame.0);
+        assert_eq!(decode_trng_mode(&mut r).unwrap(), Some(TrngMode::External));
+        assert_eq!(decode_entropy_src_fault(&mut r).unwrap(), fault);
+        assert_eq!(decode_entropy_src_fault(&mut r).unwrap(), None);
+        assert_eq!(decode_crypto_timing(&mut r).unwrap(), timing);
+        assert!(decode_etrng_response(&mut r).unwrap() == response);
+        assert!(r.0.is_empty());
+
+        assert!(decode_trng_mode(&mut FrameReader(&[3])).is_err());
+    }
+
+    #[test]
+    fn test_frame_size_limit() {
+        let mut frame = vec![];
+        write_frame(&mut frame, &[1, 2, 3]).unwrap();
+        assert_eq!(read_frame(&mut frame.as_slice()).unwrap(), [1, 2, 3]);
+
+        // The length is rejected before anything is allocated
+        let len = (MAX_FRAME_SIZE as u32 + 1).to_le_bytes();
+        let err = read_frame(&mut len.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
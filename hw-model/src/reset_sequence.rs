@@ -0,0 +1,415 @@
+// Licensed under the Apache-2.0 license
+
+//! Scripted power and reset sequences, for testing how the firmware copes
+//! with resets at awkward times. A sequence is a list of [`SequenceStep`]s
+//! run in order by [`run_sequence()`], using only [`HwModel`] methods, so the
+//! same script runs on every model:
+//!
+//! ```ignore
+//! use caliptra_hw_model::reset_sequence::{run_sequence, Condition, SequenceStep::*};
+//!
+//! let results = run_sequence(&mut hw, &[
+//!     ColdReset,
+//!     RunUntil(Condition::ReadyForFuses, 1000),
+//!     Boot { fuses: &fuses, valid_pauser: 0x1, wdt_timeout_cycles: None },
+//!     UploadFirmware(&image),
+//!     RunUntil(Condition::BootStatus(FMC_STARTED), 20_000_000),
+//!     StepCycles(5000),
+//!     WarmReset,
+//!     Boot { fuses: &fuses, valid_pauser: 0x1, wdt_timeout_cycles: None },
+//!     RunUntil(Condition::ReadyForRuntime, 20_000_000),
+//!     StartMailboxExecute { cmd: INVOKE_DPE, data: &dpe_cmd },
+//!     StepCycles(100),
+//!     UpdateReset(&image),
+//! ])?;
+//! ```
+//!
+//! On the FPGA model, the firmware runs in real time and `StepCycles` only
+//! bounds how often the model is polled, so exact reset timing is only
+//! reproducible on the emulator and verilator.
+
+use std::fmt::{self, Display};
+use std::io::Write;
+
+use crate::{Fuses, HwModel, ModelError, FW_LOAD_CMD_OPCODE};
+
+/// A state of the device to wait for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Condition {
+    /// CPTRA_FLOW_STATUS.ready_for_fuses is set.
+    ReadyForFuses,
+
+    /// The ready_for_fw signal is high.
+    ReadyForFw,
+
+    /// CPTRA_FLOW_STATUS.ready_for_runtime is set.
+    ReadyForRuntime,
+
+    /// CPTRA_BOOT_STATUS equals the value.
+    BootStatus(u32),
+}
+
+impl Condition {
+    fn is_met(self, model: &mut impl HwModel) -> bool {
+        match self {
+            Condition::ReadyForFuses => {
+                model.soc_ifc().cptra_flow_status().read().ready_for_fuses()
+            }
+            Condition::ReadyForFw => model.ready_for_fw(),
+            Condition::ReadyForRuntime => model
+                .soc_ifc()
+                .cptra_flow_status()
+                .read()
+                .ready_for_runtime(),
+            Condition::BootStatus(status) => model.soc_ifc().cptra_boot_status().read() == status,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum SequenceStep<'a> {
+    /// Drop power and reset, and wait for ready_for_fuses (see
+    /// [`HwModel::cold_reset`]). The emulator can only do this if it was
+    /// created with [`InitParams::save_power_on_state`]; otherwise the
+    /// sequence stops with [`ModelError::ColdResetUnsupported`].
+    ///
+    /// [`InitParams::save_power_on_state`]: crate::InitParams::save_power_on_state
+    ColdReset,
+
+    /// Toggle reset and wait for ready_for_fuses (see [`HwModel::warm_reset`]).
+    WarmReset,
+
+    /// Program the fuses (unless they are still locked from before a warm
+    /// reset), make `valid_pauser` the only PAUSER allowed to use the
+    /// mailbox, and start the boot FSM. If `wdt_timeout_cycles` is set, the
+    /// watchdog timeout is configured first; otherwise it is left as is.
+    Boot {
+        fuses: &'a Fuses,
+        valid_pauser: u32,
+        wdt_timeout_cycles: Option<u64>,
+    },
+
+    /// Wait for ready_for_fw and upload a firmware image with FIRMWARE_LOAD.
+    UploadFirmware(&'a [u8]),
+
+    /// Send a new firmware image to the runtime with FIRMWARE_LOAD, which
+    /// makes it trigger an update reset, and wait for the response. This is
+    /// the only way for the SoC to cause an update reset. If a
+    /// `StartMailboxExecute` command is still in flight, the SoC can't send
+    /// FIRMWARE_LOAD until the mailbox is unlocked, so its response is
+    /// collected first.
+    UpdateReset(&'a [u8]),
+
+    /// Step the given number of clock cycles.
+    StepCycles(u64),
+
+    /// Step until the condition is met, failing if it isn't met within the
+    /// given number of clock cycles.
+    RunUntil(Condition, u32),
+
+    /// Send a mailbox command and wait for the response.
+    MailboxExecute { cmd: u32, data: &'a [u8] },
+
+    /// Send a mailbox command without waiting for the response, so the
+    /// following steps run while the firmware handles it.
+    StartMailboxExecute { cmd: u32, data: &'a [u8] },
+
+    /// Wait for the response to the last `StartMailboxExecute`. Does nothing
+    /// if that command failed to start, was dropped by a reset, or already
+    /// had its response collected by `UpdateReset`.
+    FinishMailboxExecute,
+}
+
+// Firmware images are too long to log, so only their lengths are shown.
+impl fmt::Debug for SequenceStep<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SequenceStep::ColdReset => write!(f, "ColdReset"),
+            SequenceStep::WarmReset => write!(f, "WarmReset"),
+            SequenceStep::Boot {
+                valid_pauser,
+                wdt_timeout_cycles,
+                ..
+            } => write!(
+                f,
+                "Boot {{ valid_pauser: 0x{valid_pauser:x}, wdt_timeout_cycles: {wdt_timeout_cycles:?} }}"
+            ),
+            SequenceStep::UploadFirmware(image) => {
+                write!(f, "UploadFirmware({} bytes)", image.len())
+            }
+            SequenceStep::UpdateReset(image) => write!(f, "UpdateReset({} bytes)", image.len()),
+            SequenceStep::StepCycles(cycles) => write!(f, "StepCycles({cycles})"),
+            SequenceStep::RunUntil(condition, max_cycles) => {
+                write!(f, "RunUntil({condition:?}, {max_cycles})")
+            }
+            SequenceStep::MailboxExecute { cmd, data } => {
+                write!(
+                    f,
+                    "MailboxExecute {{ cmd: 0x{cmd:08x}, {} bytes }}",
+                    data.len()
+                )
+            }
+            SequenceStep::StartMailboxExecute { cmd, data } => write!(
+                f,
+                "StartMailboxExecute {{ cmd: 0x{cmd:08x}, {} bytes }}",
+                data.len()
+            ),
+            SequenceStep::FinishMailboxExecute => write!(f, "FinishMailboxExecute"),
+        }
+    }
+}
+
+/// The result of a mailbox command sent by a sequence.
+pub type MailboxResult = Result<Option<Vec<u8>>, ModelError>;
+
+/// Why a sequence stopped early.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SequenceError {
+    /// The index of the step that failed.
+    pub step: usize,
+    pub error: ModelError,
+}
+impl std::error::Error for SequenceError {}
+impl Display for SequenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Reset sequence step {} failed: {}",
+            self.step, self.error
+        )
+    }
+}
+
+/// Run `steps` in order on `model`.
+///
+/// A failed mailbox command doesn't stop the sequence, because resets are
+/// expected to interrupt commands. Instead, the results of the
+/// `MailboxExecute`, `StartMailboxExecute` (collected by
+/// `FinishMailboxExecute` or `UpdateReset`) and `UpdateReset` commands are
+/// returned in order, for the test to check. The sequence stops if a
+/// `RunUntil` condition isn't met in time, if a firmware upload fails, or if
+/// the model can't do a `ColdReset`.
+pub fn run_sequence(
+    model: &mut impl HwModel,
+    steps: &[SequenceStep],
+) -> Result<Vec<MailboxResult>, SequenceError> {
+    let mut state = SequenceState::default();
+    for (index, step) in steps.iter().enumerate() {
+        writeln!(
+            model.output().logger(),
+            ">>> reset sequence step {index}: {step:?}"
+        )
+        .unwrap();
+        run_step(model, step, &mut state).map_err(|error| SequenceError { step: index, error })?;
+    }
+    Ok(state.results)
+}
+
+#[derive(Default)]
+struct SequenceState {
+    results: Vec<MailboxResult>,
+    // A StartMailboxExecute command is waiting for its response
+    mailbox_pending: bool,
+}
+
+fn run_step(
+    model: &mut impl HwModel,
+    step: &SequenceStep,
+    state: &mut SequenceState,
+) -> Result<(), ModelError> {
+    match *step {
+        SequenceStep::ColdReset => {
+            model.cold_reset()?;
+            state.mailbox_pending = false;
+        }
+        SequenceStep::WarmReset => {
+            model.warm_reset();
+            state.mailbox_pending = false;
+        }
+        SequenceStep::Boot {
+            fuses,
+            valid_pauser,
+            wdt_timeout_cycles,
+        } => {
+            // Fuses stay locked in place across a warm reset
+            if !model.soc_ifc().cptra_fuse_wr_done().read().done() {
+                model.init_fuses(fuses);
+            }
+            if let Some(wdt_timeout_cycles) = wdt_timeout_cycles {
+                model
+                    .soc_ifc()
+                    .cptra_wdt_cfg()
+                    .at(0)
+                    .write(|_| wdt_timeout_cycles as u32);
+                model
+                    .soc_ifc()
+                    .cptra_wdt_cfg()
+                    .at(1)
+                    .write(|_| (wdt_timeout_cycles >> 32) as u32);
+            }
+            model
+                .soc_ifc()
+                .cptra_mbox_valid_pauser()
+                .at(0)
+                .write(|_| valid_pauser);
+            model
+                .soc_ifc()
+                .cptra_mbox_pauser_lock()
+                .at(0)
+                .write(|w| w.lock(true));
+            model.soc_ifc().cptra_bootfsm_go().write(|w| w.go(true));
+            model.step();
+        }
+        SequenceStep::UploadFirmware(image) => {
+            const MAX_WAIT_CYCLES: u32 = 20_000_000;
+            if !model.step_until_ready_for_fw(MAX_WAIT_CYCLES) {
+                return Err(ModelError::ReadyForFirmwareTimeout {
+                    cycles: MAX_WAIT_CYCLES,
+                });
+            }
+            model.upload_firmware(image)?;
+        }
+        SequenceStep::UpdateReset(image) => {
+            if state.mailbox_pending {
+                state.results.push(model.finish_mailbox_execute());
+                state.mailbox_pending = false;
+            }
+            state
+                .results
+                .push(model.mailbox_execute(FW_LOAD_CMD_OPCODE, image));
+        }
+        SequenceStep::StepCycles(cycles) => {
+            for _ in 0..cycles {
+                model.step();
+            }
+        }
+        SequenceStep::RunUntil(condition, max_cycles) => {
+            let mut cycles = 0;
+            while !condition.is_met(model) {
+                if cycles == max_cycles {
+                    return Err(ModelError::SequenceConditionTimeout { cycles: max_cycles });
+                }
+                model.step();
+                cycles += 1;
+            }
+        }
+        SequenceStep::MailboxExecute { cmd, data } => {
+            state.results.push(model.mailbox_execute(cmd, data));
+        }
+        SequenceStep::StartMailboxExecute { cmd, data } => {
+            match model.start_mailbox_execute(cmd, data) {
+                Ok(()) => state.mailbox_pending = true,
+                Err(e) => state.results.push(Err(e)),
+            }
+        }
+        SequenceStep::FinishMailboxExecute => {
+            if state.mailbox_pending {
+                state.results.push(model.finish_mailbox_execute());
+                state.mailbox_pending = false;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmio::Rv32GenMmio;
+    use crate::{BootParams, InitParams, ModelEmulated};
+    use caliptra_registers::soc_ifc;
+
+    fn boot_status_rom() -> Vec<u8> {
+        let rv32_gen = Rv32GenMmio::new();
+        let soc_ifc =
+            unsafe { soc_ifc::RegisterBlock::new_with_mmio(0x3003_0000 as *mut u32, &rv32_gen) };
+        soc_ifc.cptra_boot_status().write(|_| 0x600d_f00d);
+        rv32_gen.into_inner().empty_loop().build()
+    }
+
+    #[test]
+    fn test_run_sequence() {
+        let rom = boot_status_rom();
+        let mut hw = crate::new(BootParams {
+            init_params: InitParams {
+                rom: &rom,
+                log_writer: Box::new(std::io::sink()),
+                save_power_on_state: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+        let fuses = Fuses {
+            fmc_key_manifest_svn: 3,
+            ..Default::default()
+        };
+
+        let results = run_sequence(
+            &mut hw,
+            &[
+                SequenceStep::RunUntil(Condition::BootStatus(0x600d_f00d), 1000),
+                SequenceStep::WarmReset,
+                SequenceStep::StepCycles(100),
+                SequenceStep::Boot {
+                    fuses: &fuses,
+                    valid_pauser: 0x1,
+                    wdt_timeout_cycles: None,
+                },
+                SequenceStep::RunUntil(Condition::BootStatus(0x600d_f00d), 1000),
+                SequenceStep::ColdReset,
+                SequenceStep::RunUntil(Condition::ReadyForFuses, 1000),
+            ],
+        )
+        .unwrap();
+        assert_eq!(results, vec![]);
+        // The boot status and fuses were lost in the cold reset
+        assert_eq!(hw.soc_ifc().cptra_boot_status().read(), 0);
+        assert!(!hw.soc_ifc().cptra_fuse_wr_done().read().done());
+
+        let results = run_sequence(
+            &mut hw,
+            &[
+                SequenceStep::Boot {
+                    fuses: &fuses,
+                    valid_pauser: 0x1,
+                    wdt_timeout_cycles: None,
+                },
+                SequenceStep::RunUntil(Condition::BootStatus(0x600d_f00d), 1000),
+                SequenceStep::StartMailboxExecute {
+                    cmd: 0x1000_0000,
+                    data: &[],
+                },
+                SequenceStep::WarmReset,
+                SequenceStep::RunUntil(Condition::ReadyForFw, 1000),
+            ],
+        );
+        assert_eq!(
+            results,
+            Err(SequenceError {
+                step: 4,
+                error: ModelError::SequenceConditionTimeout { cycles: 1000 }
+            })
+        );
+        assert!(hw.soc_ifc().cptra_fuse_wr_done().read().done());
+        assert_eq!(hw.soc_ifc().fuse_fmc_key_manifest_svn().read(), 3);
+    }
+    #[test]
+    fn test_cold_reset_without_power_on_state() {
+        let rom = boot_status_rom();
+        // Only the emulator needs the power-on state
+        let mut hw = ModelEmulated::new_unbooted(InitParams {
+            rom: &rom,
+            log_writer: Box::new(std::io::sink()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            run_sequence(&mut hw, &[SequenceStep::ColdReset]),
+            Err(SequenceError {
+                step: 0,
+                error: ModelError::ColdResetUnsupported,
+            })
+        );
+    }
+}
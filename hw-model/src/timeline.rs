@@ -0,0 +1,485 @@
+// Licensed under the Apache-2.0 license
+
+//! Timeline of emulator activity against clock cycles, written either as a
+//! VCD waveform or as Chrome trace JSON (which Perfetto can open).
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use caliptra_emu_bus::Bus;
+use caliptra_emu_periph::CaliptraRootBus;
+use caliptra_emu_types::RvSize;
+
+/// A function (or other code range) used to label the CPU's program counter
+/// in the timeline. Typically built from `caliptra_builder::elf_symbols()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TimelineSymbol {
+    pub name: String,
+    pub addr: u32,
+    pub size: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimelineFormat {
+    /// Value change dump, for waveform viewers such as GTKWave.
+    Vcd,
+
+    /// Chrome trace event JSON, for Perfetto or chrome://tracing. One clock
+    /// cycle is shown as one microsecond.
+    ChromeTrace,
+}
+impl TimelineFormat {
+    /// Files ending in `.vcd` are written as VCD, all others as Chrome trace
+    /// JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("vcd") => Self::Vcd,
+            _ => Self::ChromeTrace,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum SignalKind {
+    /// An interval that is either active or not, such as an engine being busy.
+    Bool,
+
+    /// A numeric value, such as a status register.
+    U32,
+
+    /// A named state; the empty string is the inactive state.
+    Str,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Value {
+    Bool(bool),
+    U32(u32),
+    Str(String),
+}
+impl Value {
+    fn initial(kind: SignalKind) -> Self {
+        match kind {
+            SignalKind::Bool => Value::Bool(false),
+            SignalKind::U32 => Value::U32(0),
+            SignalKind::Str => Value::Str(String::new()),
+        }
+    }
+}
+
+struct Signal {
+    name: &'static str,
+    value: Value,
+    since: u64,
+}
+
+/// Writes value changes of a fixed set of signals.
+struct Timeline<W: Write> {
+    out: W,
+    format: TimelineFormat,
+    signals: Vec<Signal>,
+    last_time: u64,
+}
+
+impl<W: Write> Timeline<W> {
+    fn new(
+        mut out: W,
+        format: TimelineFormat,
+        signals: &[(&'static str, SignalKind)],
+    ) -> io::Result<Self> {
+        match format {
+            TimelineFormat::Vcd => {
+                writeln!(out, "$comment one time unit is one clock cycle $end")?;
+                writeln!(out, "$timescale 1ns $end")?;
+                writeln!(out, "$scope module caliptra $end")?;
+                for (i, (name, kind)) in signals.iter().enumerate() {
+                    let (ty, width) = match kind {
+                        SignalKind::Bool => ("wire", 1),
+                        SignalKind::U32 => ("wire", 32),
+                        SignalKind::Str => ("string", 1),
+                    };
+                    writeln!(out, "$var {ty} {width} {} {name} $end", vcd_id(i))?;
+                }
+                writeln!(out, "$upscope $end")?;
+                writeln!(out, "$enddefinitions $end")?;
+                writeln!(out, "#0")?;
+                writeln!(out, "$dumpvars")?;
+                for (i, (_, kind)) in signals.iter().enumerate() {
+                    write_vcd_value(&mut out, i, &Value::initial(*kind))?;
+                }
+                writeln!(out, "$end")?;
+            }
+            TimelineFormat::ChromeTrace => {
+                write!(out, "{{\"traceEvents\":[")?;
+                write!(
+                    out,
+                    "\n{{\"ph\":\"M\",\"pid\":1,\"name\":\"process_name\",\"args\":{{\"name\":\"caliptra\"}}}}"
+                )?;
+                for (i, (name, _)) in signals.iter().enumerate() {
+                    write!(
+                        out,
+                        ",\n{{\"ph\":\"M\",\"pid\":1,\"tid\":{},\"name\":\"thread_name\",\"args\":{{\"name\":\"{}\"}}}}",
+                        i + 1,
+                        JsonStr(name)
+                    )?;
+                }
+            }
+        }
+        Ok(Self {
+            out,
+            format,
+            signals: signals
+                .iter()
+                .map(|(name, kind)| Signal {
+                    name,
+                    value: Value::initial(*kind),
+                    since: 0,
+                })
+                .collect(),
+            last_time: 0,
+        })
+    }
+
+    /// Record that signal `id` has `value` at `time`. Nothing is written
+    /// unless the value changed.
+    fn set(&mut self, time: u64, id: usize, value: Value) -> io::Result<()> {
+        if self.signals[id].value == value {
+            return Ok(());
+        }
+        match self.format {
+            TimelineFormat::Vcd => {
+                if time != self.last_time {
+                    writeln!(self.out, "#{time}")?;
+                }
+                write_vcd_value(&mut self.out, id, &value)?;
+            }
+            TimelineFormat::ChromeTrace => {
+                self.end_interval(time, id)?;
+                if let Value::U32(val) = value {
+                    write!(
+                        self.out,
+                        ",\n{{\"ph\":\"C\",\"pid\":1,\"name\":\"{}\",\"ts\":{time},\"args\":{{\"value\":{val}}}}}",
+                        JsonStr(self.signals[id].name)
+                    )?;
+                }
+            }
+        }
+        self.last_time = time;
+        let signal = &mut self.signals[id];
+        signal.value = value;
+        signal.since = time;
+        Ok(())
+    }
+
+    /// Write the Chrome trace slice for the interval signal `id` has been
+    /// active for, if any.
+    fn end_interval(&mut self, time: u64, id: usize) -> io::Result<()> {
+        let signal = &self.signals[id];
+        let label = match &signal.value {
+            Value::Bool(true) => signal.name,
+            Value::Str(s) if !s.is_empty() => s,
+            _ => return Ok(()),
+        };
+        write!(
+            self.out,
+            ",\n{{\"ph\":\"X\",\"pid\":1,\"tid\":{},\"name\":\"{}\",\"ts\":{},\"dur\":{}}}",
+            id + 1,
+            JsonStr(label),
+            signal.since,
+            time - signal.since
+        )
+    }
+
+    /// Close any open intervals at `time` and complete the file.
+    fn finish(&mut self, time: u64) -> io::Result<()> {
+        match self.format {
+            TimelineFormat::Vcd => {
+                if time != self.last_time {
+                    writeln!(self.out, "#{time}")?;
+                }
+            }
+            TimelineFormat::ChromeTrace => {
+                for id in 0..self.signals.len() {
+                    self.end_interval(time, id)?;
+                }
+                writeln!(self.out, "\n]}}")?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+fn vcd_id(index: usize) -> String {
+    // Identifiers are made of the printable ASCII characters '!' to '~'.
+    let mut index = index;
+    let mut result = String::new();
+    loop {
+        result.push(char::from(b'!' + (index % 94) as u8));
+        index /= 94;
+        if index == 0 {
+            return result;
+        }
+        index -= 1;
+    }
+}
+
+fn write_vcd_value(out: &mut impl Write, id: usize, value: &Value) -> io::Result<()> {
+    let id = vcd_id(id);
+    match value {
+        Value::Bool(val) => writeln!(out, "{}{id}", u8::from(*val)),
+        Value::U32(val) => writeln!(out, "b{val:b} {id}"),
+        // VCD values can't contain whitespace or be empty.
+        Value::Str(s) if s.is_empty() => writeln!(out, "s- {id}"),
+        Value::Str(s) => writeln!(out, "s{} {id}", s.replace(char::is_whitespace, "_")),
+    }
+}
+
+struct JsonStr<'a>(&'a str);
+impl std::fmt::Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for ch in self.0.chars() {
+            match ch {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                ch if ch.is_control() => write!(f, "\\u{:04x}", u32::from(ch))?,
+                ch => write!(f, "{ch}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A crypto engine that is busy while a status bit is clear.
+struct Engine {
+    name: &'static str,
+    status_addr: u32,
+}
+
+const ENGINES: [Engine; 5] = [
+    Engine {
+        name: "doe",
+        status_addr: 0x1000_0014,
+    },
+    Engine {
+        name: "ecc384",
+        status_addr: 0x1000_8018,
+    },
+    Engine {
+        name: "hmac",
+        status_addr: 0x1001_0018,
+    },
+    Engine {
+        name: "sha512",
+        status_addr: 0x1002_0018,
+    },
+    Engine {
+        name: "sha256",
+        status_addr: 0x1002_8018,
+    },
+];
+
+const SHA512_ACC_EXECUTE_ADDR: u32 = 0x3002_1018;
+const SHA512_ACC_STATUS_ADDR: u32 = 0x3002_101c;
+const MBOX_STATUS_ADDR: u32 = 0x3002_001c;
+const CPTRA_BOOT_STATUS_ADDR: u32 = 0x3003_0038;
+
+const SIGNAL_PC: usize = 0;
+const SIGNAL_FIRST_ENGINE: usize = 1;
+const SIGNAL_SHA512_ACC: usize = SIGNAL_FIRST_ENGINE + ENGINES.len();
+const SIGNAL_MAILBOX: usize = SIGNAL_SHA512_ACC + 1;
+const SIGNAL_BOOT_STATUS: usize = SIGNAL_MAILBOX + 1;
+
+/// Samples the CPU and root bus peripherals every step and records changes
+/// in a timeline file.
+pub(crate) struct EmulatorTimeline {
+    timeline: Timeline<BufWriter<File>>,
+
+    /// Sorted by address
+    symbols: Vec<TimelineSymbol>,
+
+    /// Index of the symbol containing the last sampled pc
+    current_symbol: Option<usize>,
+}
+
+impl EmulatorTimeline {
+    pub(crate) fn create(path: &Path, mut symbols: Vec<TimelineSymbol>) -> io::Result<Self> {
+        symbols.retain(|s| s.size > 0);
+        symbols.sort_by_key(|s| s.addr);
+
+        let mut signals = vec![("pc", SignalKind::Str)];
+        signals.extend(ENGINES.iter().map(|e| (e.name, SignalKind::Bool)));
+        signals.push(("sha512_acc", SignalKind::Bool));
+        signals.push(("mailbox", SignalKind::Str));
+        signals.push(("boot_status", SignalKind::U32));
+
+        Ok(Self {
+            timeline: Timeline::new(
+                BufWriter::new(File::create(path)?),
+                TimelineFormat::from_path(path),
+                &signals,
+            )?,
+            symbols,
+            current_symbol: None,
+        })
+    }
+
+    fn symbol_index(&self, pc: u32) -> Option<usize> {
+        let i = self
+            .symbols
+            .partition_point(|s| s.addr <= pc)
+            .checked_sub(1)?;
+        let s = &self.symbols[i];
+        (pc - s.addr < s.size).then_some(i)
+    }
+
+    /// Sample all signals at cycle `now`.
+    pub(crate) fn sample(
+        &mut self,
+        now: u64,
+        pc: u32,
+        bus: &mut CaliptraRootBus,
+    ) -> io::Result<()> {
+        let in_current = self.current_symbol.is_some_and(|i| {
+            let s = &self.symbols[i];
+            pc.wrapping_sub(s.addr) < s.size
+        });
+        if !in_current {
+            self.current_symbol = self.symbol_index(pc);
+            let name = self
+                .current_symbol
+                .map(|i| self.symbols[i].name.clone())
+                .unwrap_or_default();
+            self.timeline.set(now, SIGNAL_PC, Value::Str(name))?;
+        }
+
+        // These registers can be read without side effects.
+        let mut read = |addr| bus.read(RvSize::Word, addr).unwrap_or(0);
+        for (i, engine) in ENGINES.iter().enumerate() {
+            let ready = read(engine.status_addr) & 1 != 0;
+            self.timeline
+                .set(now, SIGNAL_FIRST_ENGINE + i, Value::Bool(!ready))?;
+        }
+        let acc_busy =
+            read(SHA512_ACC_EXECUTE_ADDR) & 1 != 0 && read(SHA512_ACC_STATUS_ADDR) & 1 == 0;
+        let mbox_state = match (read(MBOX_STATUS_ADDR) >> 6) & 7 {
+            0 => "",
+            1 => "RDY_FOR_CMD",
+            2 => "RDY_FOR_DATA",
+            3 => "RDY_FOR_DLEN",
+            4 => "EXECUTE_SOC",
+            6 => "EXECUTE_UC",
+            _ => "ERROR",
+        };
+        let boot_status = read(CPTRA_BOOT_STATUS_ADDR);
+
+        self.timeline
+            .set(now, SIGNAL_SHA512_ACC, Value::Bool(acc_busy))?;
+        self.timeline
+            .set(now, SIGNAL_MAILBOX, Value::Str(mbox_state.into()))?;
+        self.timeline
+            .set(now, SIGNAL_BOOT_STATUS, Value::U32(boot_status))
+    }
+
+    pub(crate) fn finish(&mut self, now: u64) -> io::Result<()> {
+        self.timeline.finish(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_timeline(format: TimelineFormat) -> String {
+        let mut timeline = Timeline::new(
+            vec![],
+            format,
+            &[
+                ("pc", SignalKind::Str),
+                ("sha256", SignalKind::Bool),
+                ("boot_status", SignalKind::U32),
+            ],
+        )
+        .unwrap();
+        timeline.set(0, 0, Value::Str("main".into())).unwrap();
+        timeline.set(5, 1, Value::Bool(true)).unwrap();
+        timeline.set(5, 1, Value::Bool(true)).unwrap();
+        timeline.set(8, 2, Value::U32(0x21)).unwrap();
+        timeline.set(10, 1, Value::Bool(false)).unwrap();
+        timeline
+            .set(12, 0, Value::Str("\"quoted\"".into()))
+            .unwrap();
+        timeline.finish(20).unwrap();
+        String::from_utf8(timeline.out).unwrap()
+    }
+
+    #[test]
+    fn test_vcd() {
+        assert_eq!(
+            test_timeline(TimelineFormat::Vcd),
+            "$comment one time unit is one clock cycle $end\n\
+             $timescale 1ns $end\n\
+             $scope module caliptra $end\n\
+             $var string 1 ! pc $end\n\
+             $var wire 1 \" sha256 $end\n\
+             $var wire 32 # boot_status $end\n\
+             $upscope $end\n\
+             $enddefinitions $end\n\
+             #0\n\
+             $dumpvars\n\
+             s- !\n\
+             0\"\n\
+             b0 #\n\
+             $end\n\
+             smain !\n\
+             #5\n\
+             1\"\n\
+             #8\n\
+             b100001 #\n\
+             #10\n\
+             0\"\n\
+             #12\n\
+             s\"quoted\" !\n\
+             #20\n"
+        );
+    }
+
+    #[test]
+    fn test_chrome_trace() {
+        assert_eq!(
+            test_timeline(TimelineFormat::ChromeTrace),
+            r#"{"traceEvents":[
+{"ph":"M","pid":1,"name":"process_name","args":{"name":"caliptra"}},
+{"ph":"M","pid":1,"tid":1,"name":"thread_name","args":{"name":"pc"}},
+{"ph":"M","pid":1,"tid":2,"name":"thread_name","args":{"name":"sha256"}},
+{"ph":"M","pid":1,"tid":3,"name":"thread_name","args":{"name":"boot_status"}},
+{"ph":"C","pid":1,"name":"boot_status","ts":8,"args":{"value":33}},
+{"ph":"X","pid":1,"tid":2,"name":"sha256","ts":5,"dur":5},
+{"ph":"X","pid":1,"tid":1,"name":"main","ts":0,"dur":12},
+{"ph":"X","pid":1,"tid":1,"name":"\"quoted\"","ts":12,"dur":8}
+]}
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            TimelineFormat::from_path(Path::new("boot.VCD")),
+            TimelineFormat::Vcd
+        );
+        assert_eq!(
+            TimelineFormat::from_path(Path::new("boot.json")),
+            TimelineFormat::ChromeTrace
+        );
+    }
+
+    #[test]
+    fn test_vcd_id() {
+        assert_eq!(vcd_id(0), "!");
+        assert_eq!(vcd_id(93), "~");
+        assert_eq!(vcd_id(94), "!!");
+    }
+}
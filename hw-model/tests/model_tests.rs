@@ -268,3 +268,31 @@ fn test_pcr_extend() {
 
     model.step_until_exit_success().unwrap();
 }
+
+#[test]
+#[cfg(feature = "verilator")]
+fn test_pcr_extend_emulator_matches_rtl() {
+    use caliptra_hw_model::equivalence::{self, LockstepOptions};
+    use caliptra_hw_model::{ModelEmulated, ModelVerilated};
+
+    let elf =
+        caliptra_builder::build_firmware_elf(&firmware::hw_model_tests::TEST_PCR_EXTEND).unwrap();
+    let rom = caliptra_builder::elf2rom(&elf).unwrap();
+    let boot_params = || BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            random_sram_puf: false,
+            deterministic: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut emu = ModelEmulated::new(boot_params()).unwrap();
+    let mut rtl = ModelVerilated::new(boot_params()).unwrap();
+
+    if let Err(divergence) =
+        equivalence::run_lockstep(&mut emu, &mut rtl, &LockstepOptions::default())
+    {
+        panic!("{divergence}");
+    }
+}
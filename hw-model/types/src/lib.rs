@@ -193,6 +193,16 @@ impl Default for Fuses {
         }
     }
 }
+impl Fuses {
+    /// Program the SVN fuse values returned by the runtime `COMMIT_SVN`
+    /// command. Like real fuses, bits can be set but never cleared.
+    pub fn program_svn(&mut self, fmc_key_manifest_svn: u32, runtime_svn: [u32; 4]) {
+        self.fmc_key_manifest_svn |= fmc_key_manifest_svn;
+        for (fuse, value) in self.runtime_svn.iter_mut().zip(runtime_svn) {
+            *fuse |= value;
+        }
+    }
+}
 
 pub struct RandomNibbles<R: RngCore>(pub R);
 
@@ -241,6 +251,25 @@ pub enum ErrorInjectionMode {
     DccmDoubleBitEcc,
 }
 
+/// A fault injected into the emulator to model a glitch attack. Each fault
+/// triggers once.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Fault {
+    /// Skip the next execution of the instruction at `pc`.
+    SkipInstr { pc: u32 },
+
+    /// Flip `bit` of general purpose register `reg` (1-31) at `cycle`.
+    FlipRegBit { cycle: u64, reg: u8, bit: u8 },
+
+    /// Flip `bit` (0-7) of the byte at `addr` in ICCM, DCCM or mailbox SRAM
+    /// at `cycle`.
+    FlipMemBit { cycle: u64, addr: u32, bit: u8 },
+
+    /// XOR `mask` into the value returned by the next load the CPU performs
+    /// from `addr`, such as a peripheral status register.
+    CorruptRead { addr: u32, mask: u32 },
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -257,3 +286,116 @@ mod test {
         assert_eq!(0x1u32, ss.into());
     }
 }
+
+/// A defect of the physical noise source feeding the internal TRNG, used to
+/// exercise the ENTROPY_SRC health tests.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NoiseSourceFault {
+    /// Every RNG wire repeats the bit it carried when the fault took
+    /// effect, failing the repetition count test.
+    RepetitionCount,
+
+    /// Seven of every eight bits on each RNG wire are ones, failing the
+    /// adaptive proportion test's high threshold.
+    AdaptiveProportionHi,
+
+    /// Seven of every eight bits on each RNG wire are zeros, failing the
+    /// adaptive proportion test's low threshold.
+    AdaptiveProportionLo,
+
+    /// A single RNG wire (0-3) is stuck at `value`.
+    StuckBit { wire: u8, value: bool },
+}
+
+/// A [`NoiseSourceFault`] injected after the noise source has produced
+/// `after_nibbles` healthy nibbles.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct EntropySrcFault {
+    pub fault: NoiseSourceFault,
+    pub after_nibbles: usize,
+}
+
+impl EntropySrcFault {
+    /// Inject `fault` from the first nibble, failing boot-time health
+    /// testing.
+    pub fn at_boot(fault: NoiseSourceFault) -> Self {
+        Self {
+            fault,
+            after_nibbles: 0,
+        }
+    }
+}
+
+/// How many clock cycles each emulated crypto engine operation takes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CryptoTiming {
+    /// ECC-384 key pair generation
+    pub ecc384_keygen: u64,
+
+    /// ECC-384 signing
+    pub ecc384_sign: u64,
+
+    /// ECC-384 signature verification
+    pub ecc384_verify: u64,
+
+    /// HMAC-384 of the first block, including the key schedule
+    pub hmac384_init: u64,
+
+    /// HMAC-384 of each following block
+    pub hmac384_next: u64,
+
+    /// SHA-512 (or SHA-384) of one 1024-bit block in the SHA512 engine
+    pub sha512_block: u64,
+
+    /// Hash of all PCRs and a nonce by the SHA512 engine's PCR gen hash
+    /// operation
+    pub sha512_pcr_hash: u64,
+
+    /// Fixed cost of hashing mailbox SRAM in the SHA512 accelerator
+    pub sha512_acc_op: u64,
+
+    /// SHA-384 of each 1024-bit block of mailbox SRAM in the SHA512
+    /// accelerator, including the SRAM reads, on top of `sha512_acc_op`
+    pub sha512_acc_block: u64,
+}
+
+impl CryptoTiming {
+    /// Flat delays that don't model the RTL. This is the default, as tests
+    /// (such as watchdog tests) depend on the cycle counts of the emulator.
+    pub const FAST: Self = Self {
+        ecc384_keygen: 1000,
+        ecc384_sign: 1000,
+        ecc384_verify: 1000,
+        hmac384_init: 1000,
+        hmac384_next: 1000,
+        sha512_block: 1000,
+        sha512_pcr_hash: 1000,
+        sha512_acc_op: 1000,
+        sha512_acc_block: 0,
+    };
+
+    /// Approximate cycle counts of the crypto engines in the verilated model
+    /// of caliptra_top, for estimating boot time. These are estimates from the
+    /// engine designs in hw-latest/caliptra-rtl, not yet measured on the
+    /// verilated model. To measure them, time each operation with the
+    /// `verilator` feature and record the caliptra-rtl revision here. They
+    /// should be updated when the engines in the RTL change.
+    pub const VERILATED: Self = Self {
+        ecc384_keygen: 1_040_000,
+        ecc384_sign: 1_110_000,
+        ecc384_verify: 2_160_000,
+        hmac384_init: 400,
+        hmac384_next: 300,
+        sha512_block: 100,
+        // 32 PCRs, a 32-byte nonce and the padding make 13 blocks
+        sha512_pcr_hash: 1300,
+        sha512_acc_op: 0,
+        sha512_acc_block: 140,
+    };
+}
+
+impl Default for CryptoTiming {
+    fn default() -> Self {
+        Self::FAST
+    }
+}
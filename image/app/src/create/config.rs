@@ -59,3 +59,40 @@ pub(crate) fn load_key_config(path: &PathBuf) -> anyhow::Result<KeyConfig> {
 
     Ok(config)
 }
+
+/// SoC Image Configuration
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct SocImageConfig {
+    pub id: u32,
+
+    pub path: String,
+
+    pub version: u32,
+
+    pub svn: u32,
+
+    pub min_svn: u32,
+
+    pub rev: Option<String>,
+
+    pub load_addr: u32,
+
+    pub entry_point: u32,
+}
+
+/// SoC Images Configuration
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct SocImagesConfig {
+    pub image: Vec<SocImageConfig>,
+}
+
+/// Load SoC Images Configuration from file
+pub(crate) fn load_soc_images_config(path: &PathBuf) -> anyhow::Result<SocImagesConfig> {
+    let config_str = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the config file {}", path.display()))?;
+
+    let config: SocImagesConfig = toml::from_str(&config_str)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+
+    Ok(config)
+}
@@ -28,7 +28,7 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use caliptra_image_elf::ElfExecutable;
-use config::{OwnerKeyConfig, VendorKeyConfig};
+use config::{OwnerKeyConfig, SocImagesConfig, VendorKeyConfig};
 
 use chrono::NaiveDate;
 
@@ -178,6 +178,17 @@ pub(crate) fn run_cmd(args: &ArgMatches) -> anyhow::Result<()> {
         .parent()
         .with_context(|| "Invalid parent path")?;
 
+    let soc_images = match args.get_one::<PathBuf>("soc-images") {
+        Some(soc_images_path) => {
+            let soc_images_config = config::load_soc_images_config(soc_images_path)?;
+            let soc_images_dir = soc_images_path
+                .parent()
+                .with_context(|| "Invalid parent path")?;
+            soc_images(soc_images_dir, &soc_images_config)?
+        }
+        None => Vec::new(),
+    };
+
     let gen_config = ImageGeneratorConfig::<ElfExecutable> {
         vendor_config: vendor_config(
             config_dir,
@@ -190,6 +201,8 @@ pub(crate) fn run_cmd(args: &ArgMatches) -> anyhow::Result<()> {
         owner_config: owner_config(config_dir, &config.owner, own_from_date, own_to_date)?,
         fmc,
         runtime,
+        soc_images,
+        ..Default::default()
     };
 
     let gen = ImageGenerator::new(caliptra_image_openssl::OsslCrypto::default());
@@ -208,6 +221,39 @@ pub(crate) fn run_cmd(args: &ArgMatches) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Generate SoC Image Configs
+fn soc_images(
+    path: &Path,
+    config: &SocImagesConfig,
+) -> anyhow::Result<Vec<ImageGeneratorSocImageConfig>> {
+    let mut soc_images = Vec::new();
+
+    for image in config.image.iter() {
+        let image_path = path.join(&image.path);
+        let content = std::fs::read(&image_path)
+            .with_context(|| format!("Failed to read SoC image {}", image_path.display()))?;
+
+        let mut rev = ImageRevision::default();
+        if let Some(image_rev) = &image.rev {
+            let image_rev = hex::decode(image_rev)?;
+            rev = image_rev[..IMAGE_REVISION_BYTE_SIZE].try_into()?;
+        }
+
+        soc_images.push(ImageGeneratorSocImageConfig {
+            id: image.id,
+            version: image.version,
+            svn: image.svn,
+            min_svn: image.min_svn,
+            rev,
+            load_addr: image.load_addr,
+            entry_point: image.entry_point,
+            content,
+        });
+    }
+
+    Ok(soc_images)
+}
+
 /// Generate Vendor Config
 fn vendor_config(
     path: &Path,
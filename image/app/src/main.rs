@@ -110,6 +110,11 @@ fn main() {
             arg!(--"mfg-to-date" <String> "Certificate Validity End Date By Manufacturer [YYYYMMDDHHMMSS - Zulu Time]")
                 .required(false)
                 .value_parser(value_parser!(String)),
+        )
+        .arg(
+            arg!(--"soc-images" <FILE> "SoC Image Configuration file")
+                .required(false)
+                .value_parser(value_parser!(PathBuf)),
         )];
 
     let cmd = Command::new("caliptra-image-app")
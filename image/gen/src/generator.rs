@@ -13,6 +13,7 @@ Abstract:
 --*/
 use anyhow::bail;
 use caliptra_image_types::*;
+use core::mem::size_of;
 use memoffset::offset_of;
 use zerocopy::AsBytes;
 
@@ -45,8 +46,18 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
     where
         E: ImageGenratorExecutable,
     {
-        let image_size =
-            IMAGE_MANIFEST_BYTE_SIZE as u32 + config.fmc.size() + config.runtime.size();
+        if config.soc_images.len() > MAX_SOC_TOC_ENTRY_COUNT as usize {
+            bail!(
+                "Too many SoC images; max:{MAX_SOC_TOC_ENTRY_COUNT} count:{}",
+                config.soc_images.len()
+            );
+        }
+        let soc_toc_size = (config.soc_images.len() * size_of::<ImageTocEntry>()) as u32;
+
+        let image_size = IMAGE_MANIFEST_BYTE_SIZE as u32
+            + soc_toc_size
+            + config.fmc.size()
+            + config.runtime.size();
         if image_size > IMAGE_BYTE_SIZE as u32 {
             bail!(
                 "Image larger than {IMAGE_BYTE_SIZE} bytes; image size:{} bytes",
@@ -54,9 +65,16 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
             );
         }
 
+        // Create SoC Image TOC entries
+        let soc_toc = config
+            .soc_images
+            .iter()
+            .map(|soc_image| self.gen_soc_toc_entry(soc_image))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
         // Create FMC TOC & Content
         let id = ImageTocEntryId::Fmc;
-        let offset = IMAGE_MANIFEST_BYTE_SIZE as u32;
+        let offset = IMAGE_MANIFEST_BYTE_SIZE as u32 + soc_toc_size;
         let (fmc_toc, fmc) = self.gen_image(&config.fmc, id, offset)?;
 
         // Create Runtime TOC & Content
@@ -79,7 +97,7 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
         let lms_key_idx = config.vendor_config.lms_key_idx;
 
         // Create Header
-        let toc_digest = self.toc_digest(&fmc_toc, &runtime_toc)?;
+        let toc_digest = self.toc_digest(&fmc_toc, &runtime_toc, &soc_toc)?;
         let header = self.gen_header(config, ecc_key_idx, lms_key_idx, toc_digest)?;
 
         // Create Preamable
@@ -106,6 +124,7 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
         // Create Image Bundle
         let image = ImageBundle {
             manifest,
+            soc_toc,
             fmc,
             runtime,
         };
@@ -188,7 +207,7 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
             vendor_ecc_pub_key_idx: ecc_key_idx,
             vendor_lms_pub_key_idx: lms_key_idx,
             flags: Self::DEFAULT_FLAGS,
-            toc_len: MAX_TOC_ENTRY_COUNT,
+            toc_len: CALIPTRA_TOC_ENTRY_COUNT + config.soc_images.len() as u32,
             toc_digest: digest,
             ..Default::default()
         };
@@ -206,6 +225,21 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
             header.owner_data.owner_not_after = owner_config.not_after;
         }
 
+        for extension in config.extensions.iter() {
+            let flags = if extension.critical {
+                IMAGE_EXTENSION_FLAG_CRITICAL
+            } else {
+                0
+            };
+            if header
+                .extensions
+                .push(extension.tag, flags, &extension.value)
+                .is_err()
+            {
+                bail!("Header extensions larger than {IMAGE_EXTENSIONS_BYTE_SIZE} bytes");
+            }
+        }
+
         Ok(header)
     }
 
@@ -264,15 +298,51 @@ impl<Crypto: ImageGeneratorCrypto> ImageGenerator<Crypto> {
         Ok((entry, image.content().clone()))
     }
 
+    /// Generate SoC image TOC entry
+    fn gen_soc_toc_entry(
+        &self,
+        soc_image: &ImageGeneratorSocImageConfig,
+    ) -> anyhow::Result<ImageTocEntry> {
+        if soc_image.id == u32::from(ImageTocEntryId::Fmc)
+            || soc_image.id == u32::from(ImageTocEntryId::Runtime)
+        {
+            bail!("SoC image id {:#x} is reserved", soc_image.id);
+        }
+
+        let r#type = ImageTocEntryType::SocImage;
+        let digest = self.crypto.sha384_digest(&soc_image.content)?;
+
+        let entry = ImageTocEntry {
+            id: soc_image.id,
+            r#type: r#type.into(),
+            revision: soc_image.rev,
+            version: soc_image.version,
+            svn: soc_image.svn,
+            min_svn: soc_image.min_svn,
+            load_addr: soc_image.load_addr,
+            entry_point: soc_image.entry_point,
+            // SoC images are not part of the image bundle.
+            offset: 0,
+            size: soc_image.content.len() as u32,
+            digest,
+        };
+
+        Ok(entry)
+    }
+
     /// Calculate TOC digest
     pub fn toc_digest(
         &self,
         fmc_toc: &ImageTocEntry,
         rt_toc: &ImageTocEntry,
+        soc_toc: &[ImageTocEntry],
     ) -> anyhow::Result<ImageDigest> {
         let mut toc_content: Vec<u8> = Vec::new();
         toc_content.extend_from_slice(fmc_toc.as_bytes());
         toc_content.extend_from_slice(rt_toc.as_bytes());
+        for entry in soc_toc {
+            toc_content.extend_from_slice(entry.as_bytes());
+        }
         self.crypto.sha384_digest(&toc_content)
     }
 }
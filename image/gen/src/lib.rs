@@ -96,6 +96,44 @@ pub struct ImageGeneratorOwnerConfig {
     pub not_after: [u8; 15],
 }
 
+/// Image Generator SoC Image Configuration
+///
+/// SoC images are not carried in the image bundle. Only their TOC entries,
+/// which are covered by the vendor and owner signatures, are.
+#[derive(Default, Clone)]
+pub struct ImageGeneratorSocImageConfig {
+    /// SoC-defined image identifier
+    pub id: u32,
+
+    pub version: u32,
+
+    pub svn: u32,
+
+    pub min_svn: u32,
+
+    pub rev: ImageRevision,
+
+    /// Address the SoC loads the image to
+    pub load_addr: u32,
+
+    pub entry_point: u32,
+
+    /// Image content used to calculate the digest and size
+    pub content: Vec<u8>,
+}
+
+/// Image Generator Header Extension Configuration
+#[derive(Default, Clone)]
+pub struct ImageGeneratorExtension {
+    /// Extension tag
+    pub tag: u16,
+
+    /// Reject the image on verifiers that do not understand this extension
+    pub critical: bool,
+
+    pub value: Vec<u8>,
+}
+
 /// Image Generator Configuration
 #[derive(Default)]
 pub struct ImageGeneratorConfig<T>
@@ -109,4 +147,8 @@ where
     pub fmc: T,
 
     pub runtime: T,
+
+    pub soc_images: Vec<ImageGeneratorSocImageConfig>,
+
+    pub extensions: Vec<ImageGeneratorExtension>,
 }
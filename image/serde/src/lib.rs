@@ -11,9 +11,11 @@ Abstract:
     Caliptra Image Bundle serialization & deserialization routines.
 
 --*/
+use anyhow::{bail, Context};
 use caliptra_image_types::*;
-use std::io::Write;
-use zerocopy::AsBytes;
+use std::io::{Read, Write};
+use std::mem::size_of;
+use zerocopy::{AsBytes, FromBytes};
 
 /// Image Bundle Writer
 pub struct ImageBundleWriter<W: Write> {
@@ -29,8 +31,78 @@ impl<W: Write> ImageBundleWriter<W> {
     /// Write Image Bundle
     pub fn write(&mut self, image: &ImageBundle) -> anyhow::Result<()> {
         self.writer.write_all(image.manifest.as_bytes())?;
+        for entry in image.soc_toc.iter() {
+            self.writer.write_all(entry.as_bytes())?;
+        }
         self.writer.write_all(&image.fmc)?;
         self.writer.write_all(&image.runtime)?;
         Ok(())
     }
 }
+
+/// Image Bundle Reader
+pub struct ImageBundleReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> ImageBundleReader<R> {
+    /// Create an instance of `ImageBundleReader`
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    /// Read Image Bundle
+    ///
+    /// Manifests with a different format version are rejected rather than
+    /// misinterpreted. Header extensions are carried through untouched.
+    pub fn read(&mut self) -> anyhow::Result<ImageBundle> {
+        let mut bytes = vec![];
+        self.reader.read_to_end(&mut bytes)?;
+
+        let manifest =
+            ImageManifest::read_from_prefix(bytes.as_slice()).context("Image too small")?;
+        if manifest.marker != MANIFEST_MARKER {
+            bail!("Invalid manifest marker 0x{:08x}", manifest.marker);
+        }
+        if manifest.header.format_version != IMAGE_MANIFEST_FORMAT_VERSION {
+            bail!(
+                "Unsupported manifest format version {}; expected {}",
+                manifest.header.format_version,
+                IMAGE_MANIFEST_FORMAT_VERSION
+            );
+        }
+        if manifest.size as usize != size_of::<ImageManifest>() {
+            bail!("Invalid manifest size {}", manifest.size);
+        }
+
+        let soc_toc_range = ImageManifest::soc_toc_range(manifest.header.toc_len);
+        let soc_toc = bytes
+            .get(soc_toc_range.start as usize..soc_toc_range.end as usize)
+            .context("SoC TOC entries out of bounds")?
+            .chunks_exact(size_of::<ImageTocEntry>())
+            .map(|entry| ImageTocEntry::read_from(entry).unwrap())
+            .collect();
+
+        let fmc = manifest
+            .fmc
+            .image_range()
+            .ok()
+            .and_then(|range| bytes.get(range.start as usize..range.end as usize))
+            .context("FMC out of bounds")?
+            .to_vec();
+        let runtime = manifest
+            .runtime
+            .image_range()
+            .ok()
+            .and_then(|range| bytes.get(range.start as usize..range.end as usize))
+            .context("Runtime out of bounds")?
+            .to_vec();
+
+        Ok(ImageBundle {
+            manifest,
+            soc_toc,
+            fmc,
+            runtime,
+        })
+    }
+}
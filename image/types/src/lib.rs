@@ -26,9 +26,14 @@ use memoffset::{offset_of, span_of};
 use zerocopy::{AsBytes, FromBytes};
 
 pub const MANIFEST_MARKER: u32 = 0x4E414D43;
+pub const IMAGE_MANIFEST_FORMAT_VERSION: u32 = 1;
+pub const IMAGE_EXTENSIONS_BYTE_SIZE: usize = 128;
+pub const IMAGE_EXTENSION_FLAG_CRITICAL: u16 = 0x0001;
 pub const VENDOR_ECC_KEY_COUNT: u32 = 4;
 pub const VENDOR_LMS_KEY_COUNT: u32 = 32;
-pub const MAX_TOC_ENTRY_COUNT: u32 = 2;
+pub const CALIPTRA_TOC_ENTRY_COUNT: u32 = 2;
+pub const MAX_SOC_TOC_ENTRY_COUNT: u32 = 8;
+pub const MAX_TOC_ENTRY_COUNT: u32 = CALIPTRA_TOC_ENTRY_COUNT + MAX_SOC_TOC_ENTRY_COUNT;
 pub const IMAGE_REVISION_BYTE_SIZE: usize = 20;
 pub const ECC384_SCALAR_WORD_SIZE: usize = 12;
 pub const ECC384_SCALAR_BYTE_SIZE: usize = 48;
@@ -86,6 +91,9 @@ pub struct ImageBundle {
     /// Manifest
     pub manifest: ImageManifest,
 
+    /// SoC Image TOC Entries
+    pub soc_toc: Vec<ImageTocEntry>,
+
     /// FMC
     pub fmc: Vec<u8>,
 
@@ -99,6 +107,9 @@ impl ImageBundle {
         use std::io::ErrorKind;
         let mut result = vec![];
         result.extend_from_slice(self.manifest.as_bytes());
+        for entry in self.soc_toc.iter() {
+            result.extend_from_slice(entry.as_bytes());
+        }
         if self.manifest.fmc.offset as usize != result.len() {
             return Err(std::io::Error::new(
                 ErrorKind::Other,
@@ -191,6 +202,20 @@ impl ImageManifest {
         let span = span_of!(ImageManifest, fmc..=runtime);
         span.start as u32..span.end as u32
     }
+
+    /// Returns `Range<u32>` containing the SoC image TOC entries. These
+    /// immediately follow the manifest in the image bundle, so together with
+    /// `toc_range()` they form a single contiguous table of contents.
+    ///
+    /// # Arguments
+    ///
+    /// * `toc_len` - Total TOC entry count from the image header
+    pub fn soc_toc_range(toc_len: u32) -> Range<u32> {
+        let start = size_of::<ImageManifest>() as u32;
+        let count = toc_len.saturating_sub(CALIPTRA_TOC_ENTRY_COUNT);
+        let len = count.saturating_mul(size_of::<ImageTocEntry>() as u32);
+        start..start.saturating_add(len)
+    }
 }
 
 #[repr(C)]
@@ -290,9 +315,12 @@ pub struct OwnerSignedData {
 
 /// Caliptra Image header
 #[repr(C)]
-#[derive(AsBytes, Clone, Copy, FromBytes, Default, Debug, Zeroize)]
+#[derive(AsBytes, Clone, Copy, FromBytes, Debug, Zeroize)]
 #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct ImageHeader {
+    /// Manifest Format Version
+    pub format_version: u32,
+
     /// Revision
     pub revision: [u32; 2],
 
@@ -316,6 +344,9 @@ pub struct ImageHeader {
     /// TOC Digest
     pub toc_digest: ImageDigest,
 
+    /// Extension blocks, signed by both the vendor and the owner
+    pub extensions: ImageExtensions,
+
     /// Vendor Data
     pub vendor_data: VendorSignedData,
 
@@ -323,10 +354,177 @@ pub struct ImageHeader {
     pub owner_data: OwnerSignedData,
 }
 
-/// Caliptra table contents entry id
+impl Default for ImageHeader {
+    fn default() -> Self {
+        Self {
+            format_version: IMAGE_MANIFEST_FORMAT_VERSION,
+            revision: Default::default(),
+            vendor_ecc_pub_key_idx: Default::default(),
+            vendor_lms_pub_key_idx: Default::default(),
+            flags: Default::default(),
+            toc_len: Default::default(),
+            pl0_pauser: Default::default(),
+            toc_digest: Default::default(),
+            extensions: Default::default(),
+            vendor_data: Default::default(),
+            owner_data: Default::default(),
+        }
+    }
+}
+
+/// Caliptra Image header extension blocks
+///
+/// `data` holds `len` bytes of back-to-back TLV blocks. Each block is an
+/// `ImageExtensionHeader` followed by its value, padded to a multiple of
+/// four bytes. New manifest fields are added as extension blocks so the
+/// header layout does not change.
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy, FromBytes, Debug, Zeroize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ImageExtensions {
+    /// Length of the extension blocks in bytes
+    pub len: u32,
+
+    /// Extension blocks
+    pub data: [u8; IMAGE_EXTENSIONS_BYTE_SIZE],
+}
+
+impl Default for ImageExtensions {
+    fn default() -> Self {
+        Self {
+            len: 0,
+            data: [0u8; IMAGE_EXTENSIONS_BYTE_SIZE],
+        }
+    }
+}
+
+impl ImageExtensions {
+    /// Append an extension block
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`   - Extension tag
+    /// * `flags` - Extension flags
+    /// * `value` - Extension value
+    pub fn push(&mut self, tag: u16, flags: u16, value: &[u8]) -> CaliptraResult<()> {
+        let err = CaliptraError::IMAGE_VERIFIER_ERR_EXTENSIONS_LEN_INVALID;
+        let hdr = ImageExtensionHeader {
+            tag,
+            flags,
+            len: u32::try_from(value.len()).map_err(|_| err)?,
+        };
+        let start = self.len as usize;
+        let value_start = start
+            .checked_add(size_of::<ImageExtensionHeader>())
+            .ok_or(err)?;
+        let value_end = value_start.checked_add(value.len()).ok_or(err)?;
+        let padded_end = value_end.checked_add(3).ok_or(err)? & !3;
+        if padded_end > self.data.len() {
+            return Err(err);
+        }
+        self.data[start..value_start].copy_from_slice(hdr.as_bytes());
+        self.data[value_start..value_end].copy_from_slice(value);
+        self.data[value_end..padded_end].fill(0);
+        self.len = padded_end as u32;
+        Ok(())
+    }
+
+    /// Returns an iterator over the extension blocks
+    pub fn iter(&self) -> ImageExtensionIter {
+        ImageExtensionIter {
+            data: self.data.get(..self.len as usize),
+        }
+    }
+}
+
+/// Caliptra Image header extension block header
+#[repr(C)]
+#[derive(AsBytes, Clone, Copy, FromBytes, Default, Debug, Zeroize)]
+pub struct ImageExtensionHeader {
+    /// Extension tag
+    pub tag: u16,
+
+    /// Flags
+    /// Bit 0: Critical. Images with unknown critical extensions are rejected.
+    pub flags: u16,
+
+    /// Length of the value in bytes, excluding padding
+    pub len: u32,
+}
+
+/// Caliptra Image header extension block
+#[derive(Clone, Copy, Debug)]
+pub struct ImageExtension<'a> {
+    /// Extension tag
+    pub tag: u16,
+
+    /// Flags
+    pub flags: u16,
+
+    /// Value
+    pub value: &'a [u8],
+}
+
+impl ImageExtension<'_> {
+    /// Returns true if the extension must be understood to accept the image
+    pub fn is_critical(&self) -> bool {
+        self.flags & IMAGE_EXTENSION_FLAG_CRITICAL != 0
+    }
+}
+
+/// Iterator over the extension blocks in an `ImageExtensions`
+pub struct ImageExtensionIter<'a> {
+    // None if the extensions length is out of bounds
+    data: Option<&'a [u8]>,
+}
+
+impl<'a> Iterator for ImageExtensionIter<'a> {
+    type Item = CaliptraResult<ImageExtension<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let data = match self.data {
+            Some([]) => return None,
+            Some(data) => data,
+            None => {
+                self.data = Some(&[]);
+                return Some(Err(
+                    CaliptraError::IMAGE_VERIFIER_ERR_EXTENSIONS_LEN_INVALID,
+                ));
+            }
+        };
+
+        // Stop iterating after the first malformed block
+        self.data = Some(&[]);
+
+        let hdr = ImageExtensionHeader::read_from_prefix(data);
+        let body = data.get(size_of::<ImageExtensionHeader>()..);
+        let (Some(hdr), Some(body)) = (hdr, body) else {
+            return Some(Err(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSION_MALFORMED));
+        };
+        let value_len = hdr.len as usize;
+        let padded_len = value_len.checked_add(3).map(|len| len & !3);
+        let value = body.get(..value_len);
+        let rest = padded_len.and_then(|len| body.get(len..));
+        let (Some(value), Some(rest)) = (value, rest) else {
+            return Some(Err(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSION_MALFORMED));
+        };
+
+        self.data = Some(rest);
+        Some(Ok(ImageExtension {
+            tag: hdr.tag,
+            flags: hdr.flags,
+            value,
+        }))
+    }
+}
+
+/// Caliptra table contents entry type
 pub enum ImageTocEntryType {
-    /// First mutable code
+    /// Caliptra executable (FMC or Runtime)
     Executable = 1,
+
+    /// SoC image authenticated by Caliptra but loaded and executed by the SoC
+    SocImage = 2,
 }
 
 impl From<ImageTocEntryType> for u32 {
@@ -428,6 +626,83 @@ mod tests {
         assert_eq!(std::mem::size_of::<ImageManifest>() % 4, 0);
     }
 
+    #[test]
+    fn test_soc_toc_range() {
+        let manifest_size = std::mem::size_of::<ImageManifest>() as u32;
+        let entry_size = std::mem::size_of::<ImageTocEntry>() as u32;
+
+        // The SoC TOC entries must directly follow the FMC and runtime entries.
+        assert_eq!(ImageManifest::toc_range().end, manifest_size);
+
+        assert!(ImageManifest::soc_toc_range(CALIPTRA_TOC_ENTRY_COUNT).is_empty());
+        assert_eq!(
+            ImageManifest::soc_toc_range(CALIPTRA_TOC_ENTRY_COUNT + 3),
+            manifest_size..manifest_size + 3 * entry_size
+        );
+    }
+
+    #[test]
+    fn test_extensions() {
+        let mut extensions = ImageExtensions::default();
+        assert!(extensions.iter().next().is_none());
+
+        extensions.push(0x10, 0, &[1, 2, 3]).unwrap();
+        extensions
+            .push(0x20, IMAGE_EXTENSION_FLAG_CRITICAL, &[4, 5, 6, 7])
+            .unwrap();
+        assert_eq!(extensions.len, 20);
+
+        let mut iter = extensions.iter();
+        let ext = iter.next().unwrap().unwrap();
+        assert_eq!(
+            (ext.tag, ext.is_critical(), ext.value),
+            (0x10, false, &[1, 2, 3][..])
+        );
+        let ext = iter.next().unwrap().unwrap();
+        assert_eq!(
+            (ext.tag, ext.is_critical(), ext.value),
+            (0x20, true, &[4, 5, 6, 7][..])
+        );
+        assert!(iter.next().is_none());
+
+        // Values that don't fit are rejected.
+        assert_eq!(
+            extensions.push(0x30, 0, &[0u8; IMAGE_EXTENSIONS_BYTE_SIZE]),
+            Err(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSIONS_LEN_INVALID)
+        );
+    }
+
+    #[test]
+    fn test_extensions_malformed() {
+        let mut extensions = ImageExtensions::default();
+        extensions.push(0x10, 0, &[1, 2, 3, 4]).unwrap();
+
+        // Length beyond the extensions area
+        extensions.len = IMAGE_EXTENSIONS_BYTE_SIZE as u32 + 4;
+        let mut iter = extensions.iter();
+        assert_eq!(
+            iter.next().unwrap().err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSIONS_LEN_INVALID)
+        );
+        assert!(iter.next().is_none());
+
+        // Truncated block header
+        extensions.len = 4;
+        let mut iter = extensions.iter();
+        assert_eq!(
+            iter.next().unwrap().err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSION_MALFORMED)
+        );
+        assert!(iter.next().is_none());
+
+        // Value running past the end of the blocks
+        extensions.len = 8;
+        assert_eq!(
+            extensions.iter().next().unwrap().err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSION_MALFORMED)
+        );
+    }
+
     #[test]
     fn test_image_overlap() {
         let mut image1 = ImageTocEntry::default();
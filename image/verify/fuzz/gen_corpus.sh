@@ -0,0 +1,16 @@
+# Licensed under the Apache-2.0 license
+
+#!/bin/bash
+
+# Generates the seed corpus for the image verifier fuzz targets: signed image
+# bundles with and without SoC image TOC entries and header extensions.
+
+set -e
+
+cd "$(dirname "${BASH_SOURCE[0]}")"
+
+cargo run \
+  --manifest-path=../../../builder/Cargo.toml \
+  --release \
+  --bin image \
+  -- --fuzz-corpus "$(pwd)/common_corpus"
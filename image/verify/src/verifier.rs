@@ -26,6 +26,9 @@ use memoffset::offset_of;
 
 const ZERO_DIGEST: ImageDigest = [0u32; SHA384_DIGEST_WORD_SIZE];
 
+/// Tags of the critical header extensions understood by this verifier
+const SUPPORTED_CRITICAL_EXTENSIONS: &[u16] = &[];
+
 /// Header Info
 struct HeaderInfo<'a> {
     vendor_ecc_pub_key_idx: u32,
@@ -442,6 +445,9 @@ impl<Env: ImageVerificationEnv> ImageVerifier<Env> {
             cfi_assert!(info.owner_lms_info.is_none());
         }
 
+        // The header is now authenticated; check it is in a format we understand.
+        self.verify_header_format(header)?;
+
         let verif_info = TocInfo {
             len: header.toc_len,
             digest: &header.toc_digest,
@@ -450,6 +456,28 @@ impl<Env: ImageVerificationEnv> ImageVerifier<Env> {
         Ok(verif_info)
     }
 
+    /// Verify the header format version and extension blocks
+    ///
+    /// Unknown non-critical extensions are ignored so that newer images can
+    /// still boot on this ROM. Unknown critical extensions cause the image to
+    /// be rejected.
+    fn verify_header_format(&mut self, header: &ImageHeader) -> CaliptraResult<()> {
+        if cfi_launder(header.format_version) != IMAGE_MANIFEST_FORMAT_VERSION {
+            Err(CaliptraError::IMAGE_VERIFIER_ERR_MANIFEST_FORMAT_VERSION_MISMATCH)?;
+        } else {
+            cfi_assert_eq(header.format_version, IMAGE_MANIFEST_FORMAT_VERSION);
+        }
+
+        for extension in header.extensions.iter() {
+            let extension = extension?;
+            if extension.is_critical() && !SUPPORTED_CRITICAL_EXTENSIONS.contains(&extension.tag) {
+                Err(CaliptraError::IMAGE_VERIFIER_ERR_UNKNOWN_CRITICAL_EXTENSION)?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Verify Owner Signature
     // Inlined to reduce ROM size
     #[inline(always)]
@@ -569,13 +597,19 @@ impl<Env: ImageVerificationEnv> ImageVerifier<Env> {
         verify_info: &TocInfo,
         img_bundle_sz: u32,
     ) -> CaliptraResult<ImageInfo<'a>> {
-        if cfi_launder(verify_info.len) != MAX_TOC_ENTRY_COUNT {
+        if cfi_launder(verify_info.len) < CALIPTRA_TOC_ENTRY_COUNT
+            || verify_info.len > MAX_TOC_ENTRY_COUNT
+        {
             Err(CaliptraError::IMAGE_VERIFIER_ERR_TOC_ENTRY_COUNT_INVALID)?;
         } else {
-            cfi_assert_eq(verify_info.len, MAX_TOC_ENTRY_COUNT);
+            cfi_assert_ge(verify_info.len, CALIPTRA_TOC_ENTRY_COUNT);
+            cfi_assert_le(verify_info.len, MAX_TOC_ENTRY_COUNT);
         }
 
-        let range = ImageManifest::toc_range();
+        // The TOC digest covers the FMC & Runtime entries in the manifest
+        // followed by the SoC image entries that trail the manifest.
+        let soc_toc_range = ImageManifest::soc_toc_range(verify_info.len);
+        let range = ImageManifest::toc_range().start..soc_toc_range.end;
 
         let actual = self
             .env
@@ -603,6 +637,7 @@ impl<Env: ImageVerificationEnv> ImageVerifier<Env> {
 
         // Image length does not exceed the Image Bundle size
         let img_len: u64 = manifest.size as u64
+            + soc_toc_range.len() as u64
             + manifest.fmc.image_size() as u64
             + manifest.runtime.image_size() as u64;
 
@@ -613,6 +648,16 @@ impl<Env: ImageVerificationEnv> ImageVerifier<Env> {
         // Check if fmc and runtime sections overlap in the image.
         let fmc_range = manifest.fmc.image_range()?;
         let runtime_range = manifest.runtime.image_range()?;
+
+        // Ensure the fmc section does not overlap the SoC image TOC entries.
+        if !soc_toc_range.is_empty() && fmc_range.start < soc_toc_range.end {
+            Err(CaliptraError::IMAGE_VERIFIER_ERR_FMC_SOC_TOC_OVERLAP)?;
+        }
+
+        // Ensure the runtime section does not overlap the SoC image TOC entries.
+        if !soc_toc_range.is_empty() && runtime_range.start < soc_toc_range.end {
+            Err(CaliptraError::IMAGE_VERIFIER_ERR_RUNTIME_SOC_TOC_OVERLAP)?;
+        }
         if fmc_range.start < runtime_range.end && fmc_range.end > runtime_range.start {
             Err(CaliptraError::IMAGE_VERIFIER_ERR_FMC_RUNTIME_OVERLAP)?;
         }
@@ -1378,13 +1423,53 @@ mod tests {
         assert_eq!(toc_info.digest, &DUMMY_DATA);
     }
 
+    #[test]
+    fn test_header_format_version_mismatch() {
+        let mut verifier = ImageVerifier::new(TestEnv::default());
+        let header = ImageHeader {
+            format_version: IMAGE_MANIFEST_FORMAT_VERSION + 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            verifier.verify_header_format(&header).err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_MANIFEST_FORMAT_VERSION_MISMATCH)
+        );
+    }
+
+    #[test]
+    fn test_header_extensions() {
+        let mut verifier = ImageVerifier::new(TestEnv::default());
+        let mut header = ImageHeader::default();
+
+        // Unknown non-critical extensions are ignored.
+        header.extensions.push(0x1234, 0, &[1, 2, 3]).unwrap();
+        assert!(verifier.verify_header_format(&header).is_ok());
+
+        // Unknown critical extensions are rejected.
+        header
+            .extensions
+            .push(0x5678, IMAGE_EXTENSION_FLAG_CRITICAL, &[4, 5])
+            .unwrap();
+        assert_eq!(
+            verifier.verify_header_format(&header).err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_UNKNOWN_CRITICAL_EXTENSION)
+        );
+
+        // Malformed extension blocks are rejected.
+        header.extensions.len = IMAGE_EXTENSIONS_BYTE_SIZE as u32 + 1;
+        assert_eq!(
+            verifier.verify_header_format(&header).err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_EXTENSIONS_LEN_INVALID)
+        );
+    }
+
     #[test]
     fn test_toc_incorrect_length() {
         let manifest = ImageManifest::default();
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT / 2,
+            len: CALIPTRA_TOC_ENTRY_COUNT - 1,
             digest: &ImageDigest::default(),
         };
         let result = verifier.verify_toc(&manifest, &toc_info, manifest.size);
@@ -1394,13 +1479,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_toc_too_many_soc_entries() {
+        let manifest = ImageManifest::default();
+        let test_env = TestEnv::default();
+        let mut verifier = ImageVerifier::new(test_env);
+        let toc_info = TocInfo {
+            len: MAX_TOC_ENTRY_COUNT + 1,
+            digest: &ImageDigest::default(),
+        };
+        let result = verifier.verify_toc(&manifest, &toc_info, manifest.size);
+        assert_eq!(
+            result.err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_TOC_ENTRY_COUNT_INVALID)
+        );
+    }
+
+    #[test]
+    fn test_toc_fmc_soc_toc_overlap() {
+        let mut manifest = ImageManifest::default();
+        let test_env = TestEnv::default();
+        let mut verifier = ImageVerifier::new(test_env);
+        let toc_info = TocInfo {
+            len: CALIPTRA_TOC_ENTRY_COUNT + 1,
+            digest: &ImageDigest::default(),
+        };
+        manifest.fmc.offset = manifest.size;
+        manifest.fmc.size = 100;
+        manifest.runtime.offset = manifest.fmc.offset + 100;
+        manifest.runtime.size = 100;
+        let result = verifier.verify_toc(&manifest, &toc_info, 0x10000);
+        assert_eq!(
+            result.err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_FMC_SOC_TOC_OVERLAP)
+        );
+    }
+
+    #[test]
+    fn test_toc_runtime_soc_toc_overlap() {
+        let mut manifest = ImageManifest::default();
+        let test_env = TestEnv::default();
+        let mut verifier = ImageVerifier::new(test_env);
+        let toc_info = TocInfo {
+            len: CALIPTRA_TOC_ENTRY_COUNT + 1,
+            digest: &ImageDigest::default(),
+        };
+        manifest.fmc.offset = manifest.size + 0x1000;
+        manifest.fmc.size = 100;
+        manifest.runtime.offset = manifest.size;
+        manifest.runtime.size = 100;
+        let result = verifier.verify_toc(&manifest, &toc_info, 0x10000);
+        assert_eq!(
+            result.err(),
+            Some(CaliptraError::IMAGE_VERIFIER_ERR_RUNTIME_SOC_TOC_OVERLAP)
+        );
+    }
+
     #[test]
     fn test_toc_digest_mismatch() {
         let manifest = ImageManifest::default();
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT,
+            len: CALIPTRA_TOC_ENTRY_COUNT,
             digest: &DUMMY_DATA,
         };
         let result = verifier.verify_toc(&manifest, &toc_info, manifest.size);
@@ -1416,7 +1557,7 @@ mod tests {
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT,
+            len: CALIPTRA_TOC_ENTRY_COUNT,
             digest: &ImageDigest::default(),
         };
 
@@ -1546,7 +1687,7 @@ mod tests {
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT,
+            len: CALIPTRA_TOC_ENTRY_COUNT,
             digest: &ImageDigest::default(),
         };
 
@@ -1591,7 +1732,7 @@ mod tests {
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT,
+            len: CALIPTRA_TOC_ENTRY_COUNT,
             digest: &ImageDigest::default(),
         };
 
@@ -1618,7 +1759,7 @@ mod tests {
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT,
+            len: CALIPTRA_TOC_ENTRY_COUNT,
             digest: &ImageDigest::default(),
         };
 
@@ -1645,7 +1786,7 @@ mod tests {
         let test_env = TestEnv::default();
         let mut verifier = ImageVerifier::new(test_env);
         let toc_info = TocInfo {
-            len: MAX_TOC_ENTRY_COUNT,
+            len: CALIPTRA_TOC_ENTRY_COUNT,
             digest: &ImageDigest::default(),
         };
 
@@ -132,6 +132,9 @@ struct caliptra_fw_info_resp {
     uint32_t min_runtime_svn;
     uint32_t fmc_manifest_svn;
     uint32_t attestation_disabled;
+    uint32_t runtime_fuse_svn;
+    uint32_t fmc_fuse_svn;
+    uint32_t svn_commit_pending;
 };
 
 struct caliptra_capabilities_resp {
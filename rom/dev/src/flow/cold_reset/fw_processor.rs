@@ -32,7 +32,7 @@ use caliptra_common::PcrLogEntryId;
 use caliptra_common::{FuseLogEntryId, RomBootStatus::*};
 use caliptra_drivers::pcr_log::MeasurementLogEntry;
 use caliptra_drivers::*;
-use caliptra_image_types::{ImageManifest, IMAGE_BYTE_SIZE};
+use caliptra_image_types::{ImageManifest, CALIPTRA_TOC_ENTRY_COUNT, IMAGE_BYTE_SIZE};
 use caliptra_image_verify::{ImageVerificationInfo, ImageVerificationLogInfo, ImageVerifier};
 use caliptra_kat::KatsEnv;
 use caliptra_x509::{NotAfter, NotBefore};
@@ -120,7 +120,11 @@ impl FirmwareProcessor {
         report_boot_status(FwProcessorExtendPcrComplete.into());
 
         // Load the image
-        Self::load_image(manifest, &mut txn)?;
+        Self::load_image(
+            manifest,
+            &mut env.persistent_data.get_mut().soc_toc,
+            &mut txn,
+        )?;
 
         // Complete the mailbox transaction indicating success.
         txn.complete(true)?;
@@ -452,11 +456,27 @@ impl FirmwareProcessor {
     ///
     /// * `env`      - ROM Environment
     /// * `manifest` - Manifest
+    /// * `soc_toc`  - SoC Image TOC entries
     /// * `txn`      - Mailbox Receive Transaction
     // Inlined to reduce ROM size
     #[inline(always)]
     #[cfg_attr(not(feature = "no-cfi"), cfi_impl_fn)]
-    fn load_image(manifest: &ImageManifest, txn: &mut MailboxRecvTxn) -> CaliptraResult<()> {
+    fn load_image(
+        manifest: &ImageManifest,
+        soc_toc: &mut SocTocArray,
+        txn: &mut MailboxRecvTxn,
+    ) -> CaliptraResult<()> {
+        let soc_toc_len = manifest
+            .header
+            .toc_len
+            .saturating_sub(CALIPTRA_TOC_ENTRY_COUNT) as usize;
+        let soc_toc_dest = soc_toc
+            .get_mut(..soc_toc_len)
+            .ok_or(CaliptraError::IMAGE_VERIFIER_ERR_TOC_ENTRY_COUNT_INVALID)?;
+
+        cprintln!("[fwproc] Loading {} SoC Image TOC entries", soc_toc_len);
+        txn.copy_request(soc_toc_dest.as_bytes_mut())?;
+
         cprintln!(
             "[fwproc] Loading FMC at address 0x{:08x} len {}",
             manifest.fmc.load_addr,
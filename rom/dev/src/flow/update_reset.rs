@@ -13,7 +13,7 @@ Abstract:
 --*/
 #[cfg(feature = "fake-rom")]
 use crate::flow::fake::FakeRomImageVerificationEnv;
-use crate::{cprintln, pcr, rom_env::RomEnv};
+use crate::{cprintln, handle_fatal_error, pcr, rom_env::RomEnv};
 use caliptra_common::verifier::FirmwareImageVerificationEnv;
 
 use caliptra_cfi_derive::cfi_impl_fn;
@@ -23,15 +23,25 @@ use caliptra_drivers::report_fw_error_non_fatal;
 use caliptra_drivers::{
     okref, report_boot_status, MailboxRecvTxn, ResetReason, WarmResetEntry4, WarmResetEntry48,
 };
-use caliptra_drivers::{DataVault, PersistentData};
+use caliptra_drivers::{DataVault, PersistentData, SocTocArray};
 use caliptra_error::{CaliptraError, CaliptraResult};
-use caliptra_image_types::ImageManifest;
+use caliptra_image_types::{
+    ImageManifest, ImageTocEntry, CALIPTRA_TOC_ENTRY_COUNT, MAX_SOC_TOC_ENTRY_COUNT,
+};
 use caliptra_image_verify::{ImageVerificationInfo, ImageVerifier};
+use core::mem::size_of;
 use zerocopy::AsBytes;
 
 #[derive(Default)]
 pub struct UpdateResetFlow {}
 
+/// The parts of an image bundle staged in the mailbox SRAM that an update
+/// reset loads
+struct StagedImage<'a> {
+    soc_toc: &'a [u8],
+    runtime: &'a [u8],
+}
+
 impl UpdateResetFlow {
     /// Execute update reset flow
     ///
@@ -56,6 +66,14 @@ impl UpdateResetFlow {
             return Err(CaliptraError::ROM_UPDATE_RESET_FLOW_MAILBOX_ACCESS_FAILURE);
         };
 
+        // Set once PCR0 and PCR1 start being extended with the measurements
+        // of the new image. Until then, only the staged manifest in MAN_2
+        // has been modified.
+        let mut pcrs_extended = false;
+
+        // Set once the new image starts overwriting the previous one.
+        let mut image_loaded = false;
+
         let mut process_txn = || -> CaliptraResult<()> {
             if recv_txn.cmd() != CommandId::FIRMWARE_LOAD.into() {
                 cprintln!("Invalid command 0x{:08x} received", recv_txn.cmd());
@@ -79,27 +97,56 @@ impl UpdateResetFlow {
             let info = okref(&info)?;
             report_boot_status(UpdateResetImageVerificationComplete.into());
 
-            // Populate data vault
-            Self::populate_data_vault(venv.data_vault, info);
+            cprintln!(
+                "[update-reset] Image verified using Vendor ECC Key Index {}",
+                info.vendor_ecc_pub_key_idx
+            );
+
+            // Check everything needed to load the image before the PCRs are
+            // extended, so a bad image leaves the previous image and its
+            // measurements intact.
+            let image = venv.image;
+            let staged = Self::stage_image(&manifest, image.get(..recv_txn.dlen() as usize))?;
 
-            // Extend PCR0 and PCR1
+            // Extend PCR0 and PCR1. The measurements don't depend on the
+            // runtime image, so this is done before the previous image is
+            // overwritten.
+            pcrs_extended = true;
             pcr::extend_pcrs(&mut venv, info, &mut env.persistent_data)?;
             report_boot_status(UpdateResetExtendPcrComplete.into());
 
-            cprintln!(
-                "[update-reset] Image verified using Vendor ECC Key Index {}",
-                info.vendor_ecc_pub_key_idx
+            image_loaded = true;
+            Self::load_image(
+                &manifest,
+                &mut env.persistent_data.get_mut().soc_toc,
+                staged,
             );
 
-            Self::load_image(&manifest, &mut recv_txn)?;
+            // Populate data vault
+            Self::populate_data_vault(venv.data_vault, info);
+
+            //Call the complete here to reset the execute bit
+            recv_txn.complete(true)?;
             Ok(())
         };
-        if let Err(e) = process_txn() {
+        let result = process_txn();
+        if let Err(e) = result {
             // To prevent a race condition where the SoC sees the mailbox
             // transaction fail and reads the non-fatal error register before it
             // gets populated, report the non-fatal error code now.
             report_fw_error_non_fatal(e.into());
-            return Err(e);
+            if !pcrs_extended {
+                Self::retain_previous_image(env.persistent_data.get_mut(), &mut env.data_vault);
+                return Err(e);
+            }
+            if !image_loaded {
+                // The PCRs may already hold measurements of the new image,
+                // so the previous image can't be booted with them.
+                handle_fatal_error(e.into());
+            }
+            // Only completing the mailbox transaction can fail once the new
+            // image has been loaded. By then the image has been verified and
+            // measured, and the Data Vault describes it, so switch to it.
         }
 
         // Drop the transaction and release the Mailbox lock after the image
@@ -124,7 +171,7 @@ impl UpdateResetFlow {
         cprintln!("[update-reset Success] --");
         report_boot_status(UpdateResetComplete.into());
 
-        Ok(())
+        result
     }
 
     /// Verify the image
@@ -157,35 +204,100 @@ impl UpdateResetFlow {
         Ok(info)
     }
 
+    /// Check that the SoC image TOC and the runtime image can be loaded from
+    /// the image bundle
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest` - Manifest
+    /// * `image`    - Image bundle staged in the mailbox SRAM
+    ///
+    /// # Returns
+    ///
+    /// * `StagedImage` - The parts of the image bundle to load
+    #[cfg_attr(not(feature = "no-cfi"), cfi_impl_fn)]
+    fn stage_image<'a>(
+        manifest: &ImageManifest,
+        image: Option<&'a [u8]>,
+    ) -> CaliptraResult<StagedImage<'a>> {
+        let err = CaliptraError::ROM_UPDATE_RESET_FLOW_IMAGE_NOT_STAGED;
+        let image = image.ok_or(err)?;
+
+        let soc_toc_len = manifest
+            .header
+            .toc_len
+            .saturating_sub(CALIPTRA_TOC_ENTRY_COUNT);
+        if soc_toc_len > MAX_SOC_TOC_ENTRY_COUNT {
+            return Err(CaliptraError::IMAGE_VERIFIER_ERR_TOC_ENTRY_COUNT_INVALID);
+        }
+        let soc_toc_range = ImageManifest::soc_toc_range(manifest.header.toc_len);
+        let soc_toc = image
+            .get(soc_toc_range.start as usize..soc_toc_range.end as usize)
+            .ok_or(err)?;
+
+        let runtime_range = manifest.runtime.image_range()?;
+        let runtime = image
+            .get(runtime_range.start as usize..runtime_range.end as usize)
+            .ok_or(err)?;
+
+        Ok(StagedImage { soc_toc, runtime })
+    }
+
     /// Load the image to ICCM & DCCM
     ///
+    /// The image is copied from the bundle staged in the mailbox SRAM, i.e.
+    /// the same bytes that were hashed during verification. Everything that
+    /// can fail was checked by `stage_image`.
+    ///
     /// # Arguments
     ///
-    /// * `env`      - ROM Environment
     /// * `manifest` - Manifest
-    /// * `txn`      - Mailbox Receive Transaction
+    /// * `soc_toc`  - SoC Image TOC entries
+    /// * `staged`   - Image checked by `stage_image`
     #[cfg_attr(not(feature = "no-cfi"), cfi_impl_fn)]
-    fn load_image(manifest: &ImageManifest, txn: &mut MailboxRecvTxn) -> CaliptraResult<()> {
+    fn load_image(manifest: &ImageManifest, soc_toc: &mut SocTocArray, staged: StagedImage) {
+        cprintln!(
+            "[update-reset] Loading {} SoC Image TOC entries",
+            staged.soc_toc.len() / size_of::<ImageTocEntry>()
+        );
+        for (dest, src) in soc_toc.as_bytes_mut().iter_mut().zip(staged.soc_toc) {
+            *dest = *src;
+        }
+
         cprintln!(
             "[update-reset] Loading Runtime at address 0x{:08x} len {}",
             manifest.runtime.load_addr,
             manifest.runtime.size
         );
 
-        // Throw away the FMC portion of the image
-        txn.drop_words(manifest.fmc.size as usize / 4)?;
-
         let runtime_dest = unsafe {
             let addr = (manifest.runtime.load_addr) as *mut u32;
             core::slice::from_raw_parts_mut(addr, manifest.runtime.size as usize / 4)
         };
 
-        txn.copy_request(runtime_dest.as_bytes_mut())?;
+        for (dest, src) in runtime_dest.iter_mut().zip(staged.runtime.chunks_exact(4)) {
+            *dest = u32::from_le_bytes([src[0], src[1], src[2], src[3]]);
+        }
+    }
 
-        //Call the complete here to reset the execute bit
-        txn.complete(true)?;
+    /// Discard the staged manifest and keep the previous image
+    ///
+    /// Nothing outside of MAN_2 is modified before the PCRs are extended, so
+    /// restoring MAN_2 from MAN_1 is sufficient to keep booting the previous
+    /// runtime image.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistent_data` - Persistent Data
+    /// * `data_vault`      - Data Vault
+    fn retain_previous_image(persistent_data: &mut PersistentData, data_vault: &mut DataVault) {
+        cprintln!("[update-reset] Update failed, retaining previous image");
+        persistent_data.manifest2 = persistent_data.manifest1;
 
-        Ok(())
+        data_vault.write_warm_reset_entry4(
+            WarmResetEntry4::RomUpdateResetStatus,
+            UpdateResetPreviousImageRetained.into(),
+        );
     }
 
     /// Load the manifest
@@ -100,6 +100,8 @@ fn lock_common_reg_set(env: &mut RomEnv) {
 
     // Do not lock Runtime minimum SVN; FMC will manage this.
 
+    // Do not lock Runtime boot failure count; FMC and Runtime will manage this.
+
     // Lock the Runtime entry point in data vault until next reset
     env.data_vault
         .lock_warm_reset_entry4(WarmResetEntry4::RtEntryPoint);
@@ -159,7 +159,11 @@ pub extern "C" fn rom_entry() -> ! {
         //
         // For the update reset case, when we fail the image validation
         // we will need to continue to jump to the FMC after
-        // reporting the error in the registers.
+        // reporting the error in the registers. The update reset flow
+        // doesn't extend the PCRs or overwrite the previous runtime image
+        // until every check on the new image has passed, so the FMC boots
+        // either the intact previous image or the fully loaded new one.
+        // Failures after the PCRs start being extended are fatal.
         //
         if reset_reason == ResetReason::UpdateReset {
             handle_non_fatal_error(err.into());
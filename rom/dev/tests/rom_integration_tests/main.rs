@@ -8,13 +8,16 @@ mod test_cfi;
 mod test_cpu_fault;
 mod test_dice_derivations;
 mod test_fake_rom;
+mod test_fault_campaign;
 mod test_fmcalias_derivation;
 mod test_idevid_derivation;
 mod test_image_validation;
 mod test_mailbox_errors;
 mod test_panic_missing;
 mod test_rom_integrity;
+mod test_stack_usage;
 mod test_symbols;
+mod test_trng_health;
 mod test_update_reset;
 mod test_warm_reset;
 mod test_wdt_activation_and_stoppage;
@@ -0,0 +1,97 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::{
+    firmware::{self, APP_WITH_UART, FMC_WITH_UART},
+    ImageOptions, SymbolType,
+};
+use caliptra_common::mailbox_api::CommandId;
+use caliptra_common::RomBootStatus::*;
+use caliptra_error::CaliptraError;
+use caliptra_hw_model::fault_campaign::{
+    run_fault_campaign, skip_faults, FaultCampaignSummary, FaultOutcome,
+};
+use caliptra_hw_model::{BootParams, DefaultHwModel, HwModel, InitParams};
+
+fn image_verified(hw: &mut DefaultHwModel) -> bool {
+    let status = hw.soc_ifc().cptra_boot_status().read();
+    (u32::from(FwProcessorImageVerificationComplete)..=u32::from(ColdResetComplete))
+        .contains(&status)
+}
+
+#[test]
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_skip_faults_in_image_verifier() {
+    // Faults and snapshots are only supported by the emulator.
+    let elf = caliptra_builder::build_firmware_elf(firmware::rom_from_env()).unwrap();
+    let rom = caliptra_builder::elf2rom(&elf).unwrap();
+    // Whether ImageVerifier::verify keeps its own symbol is up to LTO, so
+    // fault every function of the verifier crate and of the firmware
+    // processor it gets inlined into.
+    let symbols = caliptra_builder::elf_symbols(&elf).unwrap();
+    let verify_ranges: Vec<_> = symbols
+        .iter()
+        .filter(|s| s.ty == SymbolType::Func && s.size > 0)
+        .filter(|s| s.name.contains("caliptra_image_verify") || s.name.contains("fw_processor"))
+        .map(|s| s.value as u32..(s.value + s.size) as u32)
+        .collect();
+    assert!(
+        !verify_ranges.is_empty(),
+        "image verifier functions not found"
+    );
+
+    let mut image_bundle = caliptra_builder::build_and_sign_image(
+        &FMC_WITH_UART,
+        &APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap();
+    image_bundle.manifest.preamble.vendor_sigs.ecc_sig.r[0] ^= 1;
+
+    // Boot to the point where the ROM is about to verify the image, and
+    // start every run from there.
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+    hw.step_until(|m| m.ready_for_fw());
+    hw.start_mailbox_execute(
+        CommandId::FIRMWARE_LOAD.into(),
+        &image_bundle.to_bytes().unwrap(),
+    )
+    .unwrap();
+    let snapshot = hw.snapshot().unwrap();
+    let start = hw.output().sink().now();
+
+    // Without a fault, the ROM rejects the signature.
+    hw.step_until(|m| m.soc_ifc().cptra_fw_error_fatal().read() != 0);
+    assert_eq!(
+        hw.soc_ifc().cptra_fw_error_fatal().read(),
+        u32::from(CaliptraError::IMAGE_VERIFIER_ERR_VENDOR_ECC_SIGNATURE_INVALID)
+    );
+    let max_cycles = 2 * (hw.output().sink().now() - start);
+
+    let new_model = || {
+        let mut hw = caliptra_hw_model::new_unbooted(InitParams {
+            rom: &rom,
+            ..Default::default()
+        })
+        .unwrap();
+        hw.restore(&snapshot).unwrap();
+        hw
+    };
+    let faults = verify_ranges.into_iter().flat_map(skip_faults);
+    let results = run_fault_campaign(faults, new_model, image_verified, max_cycles).unwrap();
+
+    let summary = FaultCampaignSummary::new(&results);
+    println!("{summary:?}");
+    let accepted: Vec<_> = results
+        .iter()
+        .filter(|r| r.outcome == FaultOutcome::UnexpectedSuccess)
+        .collect();
+    assert!(accepted.is_empty(), "Bad image accepted: {accepted:?}");
+    assert!(summary.fatal_error + summary.cfi_panic > 0);
+}
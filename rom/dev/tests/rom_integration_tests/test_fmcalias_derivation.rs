@@ -596,6 +596,8 @@ fn test_fuse_log() {
         app_svn: FMC_SVN,
         app_min_svn: FMC_MIN_SVN,
         app_version: 0,
+        soc_images: vec![],
+        extensions: vec![],
     };
     let image_bundle =
         caliptra_builder::build_and_sign_image(&TEST_FMC_WITH_UART, &APP_WITH_UART, image_options)
@@ -3,7 +3,7 @@
 use caliptra_builder::{firmware, ImageOptions};
 use caliptra_common::mailbox_api::{CommandId, GetLdevCertResp, MailboxReqHeader};
 use caliptra_drivers::{IdevidCertAttr, MfgFlags, X509KeyIdAlgo};
-use caliptra_hw_model::{DefaultHwModel, Fuses, HwModel};
+use caliptra_hw_model::{fleet, DefaultHwModel, Fuses, HwModel};
 use caliptra_image_types::ImageBundle;
 use openssl::pkey::{PKey, Public};
 use openssl::x509::X509;
@@ -127,6 +127,31 @@ fn test_generate_csr_stress() {
     }
 }
 
+#[test]
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_fleet_idevid_csrs() {
+    // The fleet runs several models at once, which only the emulator
+    // supports.
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let fleet_fuses: Vec<_> = (0..2)
+        .map(|i| fleet::fleet_fuses(&Fuses::default(), i))
+        .collect();
+    let csrs = fleet::collect_idevid_csrs(&rom, fleet_fuses.len(), |i| fleet_fuses[i]);
+
+    assert_eq!(csrs.len(), 2);
+    assert_ne!(csrs[0], csrs[1]);
+    let mut pubkeys = vec![];
+    for (csr, fuses) in csrs.iter().zip(&fleet_fuses) {
+        let req = X509Req::from_der(csr).unwrap_or_else(|_| {
+            panic!("Invalid CSR with UDS seed {:?}", fuses.uds_seed);
+        });
+        let idevid_pubkey = req.public_key().unwrap();
+        assert!(req.verify(&idevid_pubkey).unwrap());
+        pubkeys.push(idevid_pubkey.public_key_to_der().unwrap());
+    }
+    assert_ne!(pubkeys[0], pubkeys[1]);
+}
+
 fn verify_key(
     hw: &mut DefaultHwModel,
     cmd_id: u32,
@@ -20,7 +20,9 @@ use caliptra_image_elf::ElfExecutable;
 use caliptra_image_fake_keys::{
     VENDOR_CONFIG_KEY_0, VENDOR_CONFIG_KEY_1, VENDOR_CONFIG_KEY_2, VENDOR_CONFIG_KEY_3,
 };
-use caliptra_image_gen::{ImageGenerator, ImageGeneratorConfig, ImageGeneratorVendorConfig};
+use caliptra_image_gen::{
+    ImageGenerator, ImageGeneratorConfig, ImageGeneratorExtension, ImageGeneratorVendorConfig,
+};
 use caliptra_image_openssl::OsslCrypto;
 use caliptra_image_types::{
     ImageBundle, ImageManifest, VENDOR_ECC_KEY_COUNT, VENDOR_LMS_KEY_COUNT,
@@ -1141,6 +1143,51 @@ fn test_toc_invalid_entry_count() {
     );
 }
 
+#[test]
+fn test_header_unknown_noncritical_extension() {
+    let image_options = ImageOptions {
+        extensions: vec![ImageGeneratorExtension {
+            tag: 0x7fff,
+            critical: false,
+            value: vec![0xa5; 16],
+        }],
+        ..Default::default()
+    };
+    let (mut hw, image_bundle) =
+        helpers::build_hw_model_and_image_bundle(Fuses::default(), image_options);
+
+    hw.upload_firmware(&image_bundle.to_bytes().unwrap())
+        .unwrap();
+    hw.step_until_boot_status(u32::from(ColdResetComplete), true);
+}
+
+#[test]
+fn test_header_unknown_critical_extension() {
+    let image_options = ImageOptions {
+        extensions: vec![ImageGeneratorExtension {
+            tag: 0x7fff,
+            critical: true,
+            value: vec![0xa5; 16],
+        }],
+        ..Default::default()
+    };
+    let (mut hw, image_bundle) =
+        helpers::build_hw_model_and_image_bundle(Fuses::default(), image_options);
+
+    assert_eq!(
+        ModelError::MailboxCmdFailed(
+            CaliptraError::IMAGE_VERIFIER_ERR_UNKNOWN_CRITICAL_EXTENSION.into()
+        ),
+        hw.upload_firmware(&image_bundle.to_bytes().unwrap())
+            .unwrap_err()
+    );
+
+    assert_eq!(
+        hw.soc_ifc().cptra_boot_status().read(),
+        u32::from(FwProcessorManifestLoadComplete)
+    );
+}
+
 #[test]
 fn test_toc_invalid_toc_digest() {
     let (mut hw, mut image_bundle) =
@@ -2064,6 +2111,8 @@ fn update_header(image_bundle: &mut ImageBundle) {
         runtime: ElfExecutable::default(),
         vendor_config: opts.vendor_config,
         owner_config: opts.owner_config,
+        soc_images: opts.soc_images,
+        extensions: opts.extensions,
     };
 
     let gen = ImageGenerator::new(OsslCrypto::default());
@@ -2101,7 +2150,11 @@ fn update_fmc_runtime_ranges(
 
     // Update TOC digest.
     image_bundle.manifest.header.toc_digest = gen
-        .toc_digest(&image_bundle.manifest.fmc, &image_bundle.manifest.runtime)
+        .toc_digest(
+            &image_bundle.manifest.fmc,
+            &image_bundle.manifest.runtime,
+            &image_bundle.soc_toc,
+        )
         .unwrap();
 
     // Update Header.
@@ -2122,7 +2175,11 @@ fn update_load_addr(image_bundle: &mut ImageBundle, is_fmc: bool, new_load_addr:
 
     // Update TOC digest.
     image_bundle.manifest.header.toc_digest = gen
-        .toc_digest(&image_bundle.manifest.fmc, &image_bundle.manifest.runtime)
+        .toc_digest(
+            &image_bundle.manifest.fmc,
+            &image_bundle.manifest.runtime,
+            &image_bundle.soc_toc,
+        )
         .unwrap();
 
     // Update Header.
@@ -2147,7 +2204,11 @@ fn update_entry_point(
 
     // Update TOC digest.
     image_bundle.manifest.header.toc_digest = gen
-        .toc_digest(&image_bundle.manifest.fmc, &image_bundle.manifest.runtime)
+        .toc_digest(
+            &image_bundle.manifest.fmc,
+            &image_bundle.manifest.runtime,
+            &image_bundle.soc_toc,
+        )
         .unwrap();
 
     // Update Header.
@@ -2160,6 +2221,9 @@ fn update_entry_point(
 fn generate_image_bytes(image_bundle: &mut ImageBundle) -> Vec<u8> {
     let mut image = vec![];
     image.extend_from_slice(image_bundle.manifest.as_bytes());
+    for entry in image_bundle.soc_toc.iter() {
+        image.extend_from_slice(entry.as_bytes());
+    }
     image.extend_from_slice(&image_bundle.fmc);
     image.extend_from_slice(&image_bundle.runtime);
     image
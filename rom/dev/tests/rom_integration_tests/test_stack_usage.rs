@@ -0,0 +1,20 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::firmware;
+use caliptra_drivers::memory_layout;
+
+#[test]
+fn test_stack_usage_within_limits() {
+    let elf_bytes = caliptra_builder::build_firmware_elf(firmware::rom_from_env()).unwrap();
+    caliptra_builder::stack_usage(&elf_bytes)
+        .unwrap()
+        .check_limits(
+            &[
+                ("_start", memory_layout::ROM_STACK_SIZE),
+                ("_exception_handler", memory_layout::ESTACK_SIZE),
+                ("_nmi_handler", memory_layout::NSTACK_SIZE),
+            ],
+            &[],
+        )
+        .unwrap();
+}
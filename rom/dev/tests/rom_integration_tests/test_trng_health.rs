@@ -0,0 +1,89 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::firmware;
+use caliptra_drivers::{CaliptraError, MfgFlags};
+use caliptra_hw_model::{
+    BootParams, EntropySrcFault, HwModel, InitParams, NoiseSourceFault, TrngMode,
+};
+
+#[test]
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_itrng_boot_health_test_failure() {
+    // Noise source faults can only be injected into the emulator.
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+
+    for (fault, expected_err) in [
+        (
+            NoiseSourceFault::RepetitionCount,
+            CaliptraError::DRIVER_CSRNG_REPCNT_HEALTH_CHECK_FAILED,
+        ),
+        (
+            NoiseSourceFault::StuckBit {
+                wire: 3,
+                value: false,
+            },
+            CaliptraError::DRIVER_CSRNG_REPCNT_HEALTH_CHECK_FAILED,
+        ),
+        (
+            NoiseSourceFault::AdaptiveProportionHi,
+            CaliptraError::DRIVER_CSRNG_ADAPTP_HEALTH_CHECK_FAILED,
+        ),
+        (
+            NoiseSourceFault::AdaptiveProportionLo,
+            CaliptraError::DRIVER_CSRNG_ADAPTP_HEALTH_CHECK_FAILED,
+        ),
+    ] {
+        let mut hw = caliptra_hw_model::new(BootParams {
+            init_params: InitParams {
+                rom: &rom,
+                trng_mode: Some(TrngMode::Internal),
+                entropy_src_fault: Some(EntropySrcFault::at_boot(fault)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+        // The ROM cannot construct its TRNG, so it fails before validating
+        // the TRNG configuration.
+        hw.step_until(|m| m.soc_ifc().cptra_fw_error_fatal().read() != 0);
+        assert_eq!(
+            hw.soc_ifc().cptra_fw_error_fatal().read(),
+            u32::from(expected_err),
+            "{fault:?}"
+        );
+    }
+}
+
+#[test]
+#[cfg_attr(feature = "fpga_realtime", ignore)]
+fn test_trng_config_mismatch() {
+    // The ROM runs ahead of the test on the FPGA, so the flag may be set too
+    // late.
+    let rom = caliptra_builder::build_firmware_rom(&firmware::ROM_WITH_UART).unwrap();
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            trng_mode: Some(TrngMode::External),
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+    .unwrap();
+
+    // The ROM has created its external TRNG by the time it reports the CFI
+    // state. Claiming RNG support is unavailable from then on makes the
+    // debug-unlocked ROM expect the manufacturing-mode TRNG when it
+    // validates the TRNG configuration.
+    hw.step_until_output_contains("[state] CFI Enabled")
+        .unwrap();
+    hw.soc_ifc()
+        .cptra_dbg_manuf_service_reg()
+        .write(|_| MfgFlags::RNG_SUPPORT_UNAVAILABLE.bits());
+
+    hw.step_until(|m| m.soc_ifc().cptra_fw_error_fatal().read() != 0);
+    assert_eq!(
+        hw.soc_ifc().cptra_fw_error_fatal().read(),
+        u32::from(CaliptraError::ROM_CFI_PANIC_ASSERT_EQ_FAILURE)
+    );
+}
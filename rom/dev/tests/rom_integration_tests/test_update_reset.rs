@@ -12,7 +12,7 @@ use caliptra_common::mailbox_api::CommandId;
 use caliptra_common::RomBootStatus::*;
 use caliptra_drivers::WarmResetEntry4;
 use caliptra_error::CaliptraError;
-use caliptra_hw_model::{BootParams, HwModel, InitParams};
+use caliptra_hw_model::{BootParams, Fault, HwModel, InitParams};
 use caliptra_image_fake_keys::VENDOR_CONFIG_KEY_0;
 use caliptra_image_gen::ImageGeneratorVendorConfig;
 use zerocopy::{AsBytes, FromBytes};
@@ -238,8 +238,8 @@ fn test_update_reset_boot_status() {
         hw.step_until_boot_status(UpdateResetStarted.into(), false);
         hw.step_until_boot_status(UpdateResetLoadManifestComplete.into(), false);
         hw.step_until_boot_status(UpdateResetImageVerificationComplete.into(), false);
-        hw.step_until_boot_status(UpdateResetPopulateDataVaultComplete.into(), false);
         hw.step_until_boot_status(UpdateResetExtendPcrComplete.into(), false);
+        hw.step_until_boot_status(UpdateResetPopulateDataVaultComplete.into(), false);
         hw.step_until_boot_status(UpdateResetLoadImageComplete.into(), false);
         hw.step_until_boot_status(UpdateResetOverwriteManifestComplete.into(), false);
         hw.step_until_boot_status(UpdateResetComplete.into(), false);
@@ -448,6 +448,68 @@ fn test_check_rom_update_reset_status_reg() {
     assert_eq!(warmresetentry4_value, u32::from(UpdateResetComplete));
 }
 
+#[test]
+fn test_update_reset_retains_previous_image() {
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let image_bundle = caliptra_builder::build_and_sign_image(
+        &TEST_FMC_INTERACTIVE,
+        &APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap();
+
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            ..Default::default()
+        },
+        fw_image: Some(&image_bundle.to_bytes().unwrap()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    hw.step_until_boot_status(ColdResetComplete.into(), true);
+
+    // Trigger an update reset with a corrupted runtime image
+    let mut image_bytes = image_bundle.to_bytes().unwrap();
+    image_bytes[image_bundle.manifest.runtime.offset as usize] ^= 0xff;
+    hw.start_mailbox_execute(CommandId::FIRMWARE_LOAD.into(), &image_bytes)
+        .unwrap();
+
+    if cfg!(not(feature = "fpga_realtime")) {
+        hw.step_until_boot_status(KatStarted.into(), true);
+        hw.step_until_boot_status(KatComplete.into(), true);
+        hw.step_until_boot_status(UpdateResetStarted.into(), false);
+    }
+
+    assert_eq!(
+        hw.finish_mailbox_execute(),
+        Err(caliptra_hw_model::ModelError::MailboxCmdFailed(
+            CaliptraError::IMAGE_VERIFIER_ERR_RUNTIME_DIGEST_MISMATCH.into()
+        ))
+    );
+
+    // The previous FMC keeps running and reports that the previous image was retained
+    let warmresetentry4_array = hw.mailbox_execute(0x1000_000D, &[]).unwrap().unwrap();
+    let warmresetentry4_offset = core::mem::size_of::<u32>() * 9; // Skip to the RomUpdateResetStatus value
+    let warmresetentry4_value =
+        u32::read_from_prefix(warmresetentry4_array[warmresetentry4_offset..].as_bytes()).unwrap();
+    assert_eq!(
+        warmresetentry4_value,
+        u32::from(UpdateResetPreviousImageRetained)
+    );
+
+    assert_eq!(
+        hw.soc_ifc().cptra_fw_error_non_fatal().read(),
+        u32::from(CaliptraError::IMAGE_VERIFIER_ERR_RUNTIME_DIGEST_MISMATCH)
+    );
+
+    // Exit test-fmc with success
+    hw.mailbox_execute(0x1000_000C, &[]).unwrap();
+
+    hw.step_until_exit_success().unwrap();
+}
+
 #[test]
 fn test_update_reset_max_fw_image() {
     let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
@@ -517,3 +579,57 @@ fn test_update_reset_max_fw_image() {
     assert_eq!(iccm_cmp.len(), 1);
     assert_eq!(iccm_cmp[0], 0);
 }
+
+#[test]
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_update_reset_pcr_failure_is_fatal() {
+    // Read faults can only be injected into the emulator.
+    // PCR_CTRL[PCR_ID_FMC_CURRENT]
+    const PCR_CTRL_FMC_CURRENT: u32 = 0x1001_a004;
+
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let image_bundle = caliptra_builder::build_and_sign_image(
+        &TEST_FMC_INTERACTIVE,
+        &APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap();
+
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            ..Default::default()
+        },
+        fw_image: Some(&image_bundle.to_bytes().unwrap()),
+        ..Default::default()
+    })
+    .unwrap();
+
+    hw.step_until_boot_status(ColdResetComplete.into(), true);
+
+    // Trigger an update reset with a valid image, but make the PCR0 lock
+    // appear set so clearing PCR0 fails after the image has been verified.
+    // The PCRs no longer match the previous image, so it can't be booted.
+    let updated_image_bundle = caliptra_builder::build_and_sign_image(
+        &TEST_FMC_INTERACTIVE,
+        &TEST_RT_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap();
+    hw.inject_fault(Fault::CorruptRead {
+        addr: PCR_CTRL_FMC_CURRENT,
+        mask: 0x1,
+    })
+    .unwrap();
+    hw.start_mailbox_execute(
+        CommandId::FIRMWARE_LOAD.into(),
+        &updated_image_bundle.to_bytes().unwrap(),
+    )
+    .unwrap();
+
+    hw.step_until(|m| m.soc_ifc().cptra_fw_error_fatal().read() != 0);
+    assert_eq!(
+        hw.soc_ifc().cptra_fw_error_fatal().read(),
+        u32::from(CaliptraError::DRIVER_PCR_BANK_ERASE_WRITE_LOCK_SET_FAILURE)
+    );
+}
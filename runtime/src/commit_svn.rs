@@ -0,0 +1,42 @@
+// Licensed under the Apache-2.0 license
+
+use crate::{handoff::RtHandoff, Drivers};
+use caliptra_common::mailbox_api::{CommitSvnResp, MailboxResp, MailboxRespHeader};
+use caliptra_drivers::{CaliptraError, CaliptraResult, FuseBank};
+
+pub struct CommitSvnCmd;
+impl CommitSvnCmd {
+    /// Compute the SVN fuse values that make the running image's SVNs the
+    /// new floor.
+    ///
+    /// Caliptra cannot program fuses itself, so the values are returned to
+    /// the SoC to be burned by the fuse controller. SVNs below the values
+    /// already in fuses are never lowered.
+    pub(crate) fn execute(drivers: &Drivers) -> CaliptraResult<MailboxResp> {
+        let pdata = drivers.persistent_data.get();
+        let pl0_pauser = pdata.manifest1.header.pl0_pauser;
+        let flags = pdata.manifest1.header.flags;
+
+        // PL1 cannot call this mailbox command
+        if Drivers::is_caller_pl1(pl0_pauser, flags, drivers.mbox.user()) {
+            return Err(CaliptraError::RUNTIME_INCORRECT_PAUSER_PRIVILEGE_LEVEL);
+        }
+
+        let handoff = RtHandoff {
+            data_vault: &drivers.data_vault,
+            fht: &pdata.fht,
+        };
+        let runtime_svn = handoff.rt_svn()?;
+        let fmc_svn = handoff.fmc_svn()?;
+
+        let fuse_bank = drivers.soc_ifc.fuse_bank();
+        let runtime_svn = runtime_svn.max(fuse_bank.runtime_fuse_svn());
+        let fmc_svn = fmc_svn.max(fuse_bank.fmc_fuse_svn());
+
+        Ok(MailboxResp::CommitSvn(CommitSvnResp {
+            hdr: MailboxRespHeader::default(),
+            fmc_key_manifest_svn: FuseBank::fmc_svn_fuse_value(fmc_svn),
+            runtime_svn: FuseBank::runtime_svn_fuse_value(runtime_svn),
+        }))
+    }
+}
@@ -18,6 +18,11 @@ impl FwInfoCmd {
         let min_runtime_svn = handoff.rt_min_svn()?;
         let fmc_manifest_svn = handoff.fmc_svn()?;
 
+        let fuse_bank = drivers.soc_ifc.fuse_bank();
+        let runtime_fuse_svn = fuse_bank.runtime_fuse_svn();
+        let fmc_fuse_svn = fuse_bank.fmc_fuse_svn();
+        let svn_commit_pending = runtime_svn > runtime_fuse_svn || fmc_manifest_svn > fmc_fuse_svn;
+
         Ok(MailboxResp::FwInfo(FwInfoResp {
             hdr: MailboxRespHeader::default(),
             pl0_pauser: pdata.manifest1.header.pl0_pauser,
@@ -25,6 +30,9 @@ impl FwInfoCmd {
             min_runtime_svn,
             fmc_manifest_svn,
             attestation_disabled: drivers.attestation_disabled.into(),
+            runtime_fuse_svn,
+            fmc_fuse_svn,
+            svn_commit_pending: svn_commit_pending.into(),
         }))
     }
 }
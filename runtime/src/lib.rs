@@ -1,6 +1,7 @@
 // Licensed under the Apache-2.0 license
 #![cfg_attr(not(feature = "fip-self-test"), allow(unused))]
 #![no_std]
+mod commit_svn;
 pub mod dice;
 mod disable;
 mod dpe_crypto;
@@ -11,6 +12,7 @@ pub mod handoff;
 pub mod info;
 mod invoke_dpe;
 mod populate_idev;
+mod soc_image;
 mod stash_measurement;
 mod update;
 mod verify;
@@ -21,6 +23,7 @@ pub use drivers::Drivers;
 use mailbox::Mailbox;
 
 pub use caliptra_common::fips::FipsVersionCmd;
+pub use commit_svn::CommitSvnCmd;
 pub use dice::{GetFmcAliasCertCmd, GetLdevCertCmd, IDevIdCertCmd};
 pub use disable::DisableAttestationCmd;
 use dpe_crypto::DpeCrypto;
@@ -32,6 +35,7 @@ pub use populate_idev::PopulateIDevIdCertCmd;
 
 pub use info::{FwInfoCmd, IDevIdInfoCmd};
 pub use invoke_dpe::InvokeDpeCmd;
+pub use soc_image::VerifySocImageCmd;
 pub use stash_measurement::StashMeasurementCmd;
 pub use verify::EcdsaVerifyCmd;
 pub mod packet;
@@ -122,6 +126,13 @@ fn handle_command(drivers: &mut Drivers) -> CaliptraResult<MboxStatusE> {
         return Err(CaliptraError::RUNTIME_UNEXPECTED_UPDATE_RETURN);
     }
 
+    // SoC images may not fit in a packet, so read them from the mailbox directly
+    if drivers.mbox.cmd() == CommandId::VERIFY_SOC_IMAGE {
+        let mut resp = VerifySocImageCmd::execute(drivers)?;
+        Packet::copy_to_mbox(drivers, &mut resp)?;
+        return Ok(MboxStatusE::DataReady);
+    }
+
     // Get the command bytes
     let req_packet = Packet::copy_from_mbox(drivers)?;
     let cmd_bytes = req_packet.as_bytes()?;
@@ -143,6 +154,7 @@ fn handle_command(drivers: &mut Drivers) -> CaliptraResult<MboxStatusE> {
         CommandId::STASH_MEASUREMENT => StashMeasurementCmd::execute(drivers, cmd_bytes),
         CommandId::DISABLE_ATTESTATION => DisableAttestationCmd::execute(drivers),
         CommandId::FW_INFO => FwInfoCmd::execute(drivers),
+        CommandId::COMMIT_SVN => CommitSvnCmd::execute(drivers),
         CommandId::DPE_TAG_TCI => TagTciCmd::execute(drivers, cmd_bytes),
         CommandId::DPE_GET_TAGGED_TCI => GetTaggedTciCmd::execute(drivers, cmd_bytes),
         CommandId::POPULATE_IDEV_CERT => PopulateIDevIdCertCmd::execute(drivers, cmd_bytes),
@@ -183,6 +195,8 @@ pub fn handle_mailbox_commands(drivers: &mut Drivers) -> CaliptraResult<()> {
     // Indicator to SOC that RT firmware is ready
     drivers.soc_ifc.assert_ready_for_runtime();
     caliptra_drivers::report_boot_status(RtBootStatus::RtReadyForCommands.into());
+    // Runtime booted successfully; reset the count FMC uses to detect boot failures
+    drivers.data_vault.set_rt_boot_failure_count(0);
     // Disable attestation if in the middle of executing an mbox cmd during warm reset
     if drivers.mbox.cmd_busy() {
         let reset_reason = drivers.soc_ifc.reset_reason();
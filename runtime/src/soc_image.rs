@@ -0,0 +1,85 @@
+// Licensed under the Apache-2.0 license
+
+use crate::{stash_measurement::StashMeasurementCmd, Drivers};
+use caliptra_common::mailbox_api::{
+    CommandId, MailboxResp, MailboxRespHeader, VerifySocImageReq, VerifySocImageResp,
+};
+use caliptra_drivers::{Array4x12, CaliptraError, CaliptraResult};
+use caliptra_image_types::{ImageTocEntryType, CALIPTRA_TOC_ENTRY_COUNT};
+use core::mem::size_of;
+use zerocopy::FromBytes;
+
+pub struct VerifySocImageCmd;
+impl VerifySocImageCmd {
+    /// Verify a SoC image against its TOC entry in the current manifest.
+    ///
+    /// SoC images may be larger than a `Packet`, so the request is read
+    /// directly from the mailbox SRAM. If `image_size` is zero, the digest
+    /// in the request is used in place of the image content.
+    pub(crate) fn execute(drivers: &mut Drivers) -> CaliptraResult<MailboxResp> {
+        let dlen = drivers.mbox.dlen() as usize;
+        let (cmd, digest) = {
+            let cmd_bytes = drivers
+                .mbox
+                .raw_mailbox_contents()
+                .get(..dlen)
+                .ok_or(CaliptraError::RUNTIME_MAILBOX_INVALID_PARAMS)?;
+
+            let cmd = VerifySocImageReq::read_from_prefix(cmd_bytes)
+                .ok_or(CaliptraError::RUNTIME_MAILBOX_INVALID_PARAMS)?;
+
+            // Assumes chksum is always offset 0
+            if !caliptra_common::checksum::verify_checksum(
+                cmd.hdr.chksum,
+                CommandId::VERIFY_SOC_IMAGE.into(),
+                &cmd_bytes[size_of::<u32>()..],
+            ) {
+                return Err(CaliptraError::RUNTIME_INVALID_CHECKSUM);
+            }
+
+            let digest = if cmd.image_size == 0 {
+                cmd.digest
+            } else {
+                let image = cmd_bytes
+                    .get(size_of::<VerifySocImageReq>()..)
+                    .and_then(|image| image.get(..cmd.image_size as usize))
+                    .ok_or(CaliptraError::RUNTIME_MAILBOX_INVALID_PARAMS)?;
+                drivers.sha384.digest(image)?.into()
+            };
+            (cmd, digest)
+        };
+
+        let pdata = drivers.persistent_data.get();
+        let soc_toc_len = pdata
+            .manifest1
+            .header
+            .toc_len
+            .saturating_sub(CALIPTRA_TOC_ENTRY_COUNT) as usize;
+        let entry = pdata
+            .soc_toc
+            .get(..soc_toc_len)
+            .ok_or(CaliptraError::RUNTIME_SOC_IMAGE_NOT_FOUND)?
+            .iter()
+            .find(|entry| {
+                entry.id == cmd.image_id && entry.r#type == u32::from(ImageTocEntryType::SocImage)
+            })
+            .ok_or(CaliptraError::RUNTIME_SOC_IMAGE_NOT_FOUND)?;
+
+        if <[u8; 48]>::from(Array4x12::from(&entry.digest)) != digest {
+            return Err(CaliptraError::RUNTIME_SOC_IMAGE_DIGEST_MISMATCH);
+        }
+        // There are no SVN fuses for SoC images, so the minimum SVN comes
+        // from the signed TOC entry.
+        if !drivers.soc_ifc.fuse_bank().anti_rollback_disable() && entry.svn < entry.min_svn {
+            return Err(CaliptraError::RUNTIME_SOC_IMAGE_SVN_LESS_THAN_MIN_SVN);
+        }
+
+        let dpe_result =
+            StashMeasurementCmd::stash_measurement(drivers, &cmd.image_id.to_be_bytes(), &digest)?;
+
+        Ok(MailboxResp::VerifySocImage(VerifySocImageResp {
+            hdr: MailboxRespHeader::default(),
+            dpe_result: dpe_result.get_error_code(),
+        }))
+    }
+}
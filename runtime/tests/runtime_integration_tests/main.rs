@@ -3,6 +3,7 @@
 mod common;
 mod test_boot;
 mod test_certs;
+mod test_commit_svn;
 mod test_disable;
 mod test_ecdsa;
 mod test_fips;
@@ -13,6 +14,8 @@ mod test_mailbox;
 mod test_panic_missing;
 mod test_pauser_privilege_levels;
 mod test_populate_idev;
+mod test_soc_image;
+mod test_stack_usage;
 mod test_stash_measurement;
 mod test_tagging;
 mod test_update_reset;
@@ -0,0 +1,129 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::{
+    firmware::{self, APP_WITH_UART, FMC_WITH_UART},
+    ImageOptions,
+};
+use caliptra_common::mailbox_api::{
+    CommandId, CommitSvnResp, FwInfoResp, MailboxReq, MailboxReqHeader,
+};
+use caliptra_error::CaliptraError;
+use caliptra_hw_model::{BootParams, DefaultHwModel, Fuses, HwModel, InitParams};
+use zerocopy::{AsBytes, FromBytes};
+
+use crate::common::{assert_error, run_rt_test};
+
+fn image_options() -> ImageOptions {
+    let mut opts = ImageOptions::default();
+    opts.vendor_config.pl0_pauser = Some(0x1);
+    opts.fmc_svn = 3;
+    opts.app_svn = 5;
+    opts
+}
+
+fn boot_with_fuses(opts: ImageOptions, fuses: Fuses) -> DefaultHwModel {
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let image = caliptra_builder::build_and_sign_image(&FMC_WITH_UART, &APP_WITH_UART, opts)
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+
+    let mut model = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            ..Default::default()
+        },
+        fuses,
+        fw_image: Some(&image),
+        ..Default::default()
+    })
+    .unwrap();
+    model
+        .step_until_output_contains("Caliptra RT listening for mailbox commands...")
+        .unwrap();
+    model
+}
+
+fn execute(model: &mut DefaultHwModel, cmd: CommandId) -> Vec<u8> {
+    let payload = MailboxReqHeader {
+        chksum: caliptra_common::checksum::calc_checksum(u32::from(cmd), &[]),
+    };
+    model
+        .mailbox_execute(u32::from(cmd), payload.as_bytes())
+        .unwrap()
+        .expect("We should have received a response")
+}
+
+#[test]
+fn test_commit_svn() {
+    let mut model = boot_with_fuses(image_options(), Fuses::default());
+
+    let info = FwInfoResp::read_from(execute(&mut model, CommandId::FW_INFO).as_slice()).unwrap();
+    assert_eq!(info.fmc_fuse_svn, 0);
+    assert_eq!(info.runtime_fuse_svn, 0);
+    assert_eq!(info.svn_commit_pending, 1);
+
+    let resp =
+        CommitSvnResp::read_from(execute(&mut model, CommandId::COMMIT_SVN).as_slice()).unwrap();
+    assert!(caliptra_common::checksum::verify_checksum(
+        resp.hdr.chksum,
+        0x0,
+        &resp.as_bytes()[core::mem::size_of_val(&resp.hdr.chksum)..],
+    ));
+    assert_eq!(resp.fmc_key_manifest_svn, 0b111);
+    assert_eq!(resp.runtime_svn, [0b11111, 0, 0, 0]);
+
+    // Cold boot with the committed fuses
+    let mut fuses = Fuses::default();
+    fuses.program_svn(resp.fmc_key_manifest_svn, resp.runtime_svn);
+    let mut model = boot_with_fuses(image_options(), fuses);
+
+    let info = FwInfoResp::read_from(execute(&mut model, CommandId::FW_INFO).as_slice()).unwrap();
+    assert_eq!(info.fmc_fuse_svn, 3);
+    assert_eq!(info.runtime_fuse_svn, 5);
+    assert_eq!(info.svn_commit_pending, 0);
+}
+
+#[test]
+fn test_commit_svn_does_not_lower_fuses() {
+    // Anti-rollback is disabled so an image older than the fuses still boots
+    let mut fuses = Fuses {
+        anti_rollback_disable: true,
+        ..Default::default()
+    };
+    fuses.program_svn(0b111, [0xffff_ffff, 0b1, 0, 0]);
+    let mut opts = image_options();
+    opts.fmc_svn = 1;
+    opts.app_svn = 5;
+    let mut model = boot_with_fuses(opts, fuses);
+
+    let info = FwInfoResp::read_from(execute(&mut model, CommandId::FW_INFO).as_slice()).unwrap();
+    assert_eq!(info.fmc_manifest_svn, 1);
+    assert_eq!(info.runtime_svn, 5);
+    assert_eq!(info.fmc_fuse_svn, 3);
+    assert_eq!(info.runtime_fuse_svn, 33);
+    assert_eq!(info.svn_commit_pending, 0);
+
+    let resp =
+        CommitSvnResp::read_from(execute(&mut model, CommandId::COMMIT_SVN).as_slice()).unwrap();
+    assert_eq!(resp.fmc_key_manifest_svn, fuses.fmc_key_manifest_svn);
+    assert_eq!(resp.runtime_svn, fuses.runtime_svn);
+}
+
+#[test]
+fn test_commit_svn_pl1() {
+    let mut opts = image_options();
+    opts.vendor_config.pl0_pauser = None;
+    let mut model = run_rt_test(None, Some(opts), None);
+
+    let mut cmd = MailboxReq::CommitSvn(MailboxReqHeader { chksum: 0 });
+    cmd.populate_chksum().unwrap();
+    let resp = model
+        .mailbox_execute(u32::from(CommandId::COMMIT_SVN), cmd.as_bytes().unwrap())
+        .unwrap_err();
+    assert_error(
+        &mut model,
+        CaliptraError::RUNTIME_INCORRECT_PAUSER_PRIVILEGE_LEVEL,
+        resp,
+    );
+}
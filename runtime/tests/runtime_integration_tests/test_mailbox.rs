@@ -69,3 +69,14 @@ fn test_unimplemented_cmds() {
         resp,
     );
 }
+
+#[test]
+fn test_malformed_requests_rejected() {
+    let mut model = run_rt_test(None, None, None);
+
+    let failures = caliptra_hw_model::mailbox_conformance::check_api_requests(&mut model);
+    for failure in &failures {
+        println!("{failure}");
+    }
+    assert!(failures.is_empty());
+}
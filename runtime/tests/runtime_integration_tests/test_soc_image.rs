@@ -0,0 +1,176 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::{
+    firmware::{self, APP_WITH_UART, FMC_WITH_UART},
+    ImageOptions,
+};
+use caliptra_common::mailbox_api::{
+    CommandId, MailboxReq, MailboxReqHeader, VerifySocImageReq, VerifySocImageResp,
+};
+use caliptra_error::CaliptraError;
+use caliptra_hw_model::{BootParams, DefaultHwModel, Fuses, HwModel, InitParams};
+use caliptra_image_gen::ImageGeneratorSocImageConfig;
+use caliptra_runtime::RtBootStatus;
+use openssl::sha::sha384;
+use zerocopy::{AsBytes, LayoutVerified};
+
+use crate::common::assert_error;
+
+const SOC_IMAGE_ID: u32 = 0x1000;
+
+fn soc_image_content() -> Vec<u8> {
+    (0..1024u32).map(|i| i as u8).collect()
+}
+
+fn boot_with_soc_image() -> DefaultHwModel {
+    boot_with_soc_image_and_fuses(1, Fuses::default())
+}
+
+fn boot_with_soc_image_and_fuses(min_svn: u32, fuses: Fuses) -> DefaultHwModel {
+    let mut opts = ImageOptions::default();
+    opts.vendor_config.pl0_pauser = Some(0x1);
+    opts.soc_images = vec![ImageGeneratorSocImageConfig {
+        id: SOC_IMAGE_ID,
+        svn: 1,
+        min_svn,
+        load_addr: 0x8000_0000,
+        content: soc_image_content(),
+        ..Default::default()
+    }];
+
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let image = caliptra_builder::build_and_sign_image(&FMC_WITH_UART, &APP_WITH_UART, opts)
+        .unwrap()
+        .to_bytes()
+        .unwrap();
+    let mut model = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            ..Default::default()
+        },
+        fuses,
+        fw_image: Some(&image),
+        ..Default::default()
+    })
+    .unwrap();
+    model.step_until(|m| {
+        m.soc_ifc().cptra_boot_status().read() == u32::from(RtBootStatus::RtReadyForCommands)
+    });
+    model
+}
+
+fn verify_soc_image_cmd(image_id: u32, digest: [u8; 48], image: &[u8]) -> Vec<u8> {
+    let req = VerifySocImageReq {
+        hdr: MailboxReqHeader { chksum: 0 },
+        image_id,
+        image_size: image.len() as u32,
+        digest,
+    };
+    let mut cmd = [req.as_bytes(), image].concat();
+    let chksum = caliptra_common::checksum::calc_checksum(
+        u32::from(CommandId::VERIFY_SOC_IMAGE),
+        &cmd[core::mem::size_of::<u32>()..],
+    );
+    cmd[..core::mem::size_of::<u32>()].copy_from_slice(&chksum.to_le_bytes());
+    cmd
+}
+
+#[test]
+fn test_verify_soc_image() {
+    let mut model = boot_with_soc_image();
+
+    let cmd = verify_soc_image_cmd(SOC_IMAGE_ID, [0u8; 48], &soc_image_content());
+    let resp = model
+        .mailbox_execute(u32::from(CommandId::VERIFY_SOC_IMAGE), &cmd)
+        .unwrap()
+        .expect("We should have received a response");
+
+    let resp_hdr: &VerifySocImageResp =
+        LayoutVerified::<&[u8], VerifySocImageResp>::new(resp.as_bytes())
+            .unwrap()
+            .into_ref();
+    assert_eq!(resp_hdr.dpe_result, 0);
+}
+
+#[test]
+fn test_verify_soc_image_digest_only() {
+    let mut model = boot_with_soc_image();
+
+    let mut cmd = MailboxReq::VerifySocImage(VerifySocImageReq {
+        hdr: MailboxReqHeader { chksum: 0 },
+        image_id: SOC_IMAGE_ID,
+        image_size: 0,
+        digest: sha384(&soc_image_content()),
+    });
+    cmd.populate_chksum().unwrap();
+
+    let resp = model
+        .mailbox_execute(
+            u32::from(CommandId::VERIFY_SOC_IMAGE),
+            cmd.as_bytes().unwrap(),
+        )
+        .unwrap()
+        .expect("We should have received a response");
+
+    let resp_hdr: &VerifySocImageResp =
+        LayoutVerified::<&[u8], VerifySocImageResp>::new(resp.as_bytes())
+            .unwrap()
+            .into_ref();
+    assert_eq!(resp_hdr.dpe_result, 0);
+}
+
+#[test]
+fn test_verify_soc_image_digest_mismatch() {
+    let mut model = boot_with_soc_image();
+
+    let mut image = soc_image_content();
+    image[0] ^= 0xff;
+    let cmd = verify_soc_image_cmd(SOC_IMAGE_ID, [0u8; 48], &image);
+    let resp = model
+        .mailbox_execute(u32::from(CommandId::VERIFY_SOC_IMAGE), &cmd)
+        .unwrap_err();
+    assert_error(
+        &mut model,
+        CaliptraError::RUNTIME_SOC_IMAGE_DIGEST_MISMATCH,
+        resp,
+    );
+}
+
+#[test]
+fn test_verify_soc_image_not_found() {
+    let mut model = boot_with_soc_image();
+
+    let cmd = verify_soc_image_cmd(SOC_IMAGE_ID + 1, [0u8; 48], &soc_image_content());
+    let resp = model
+        .mailbox_execute(u32::from(CommandId::VERIFY_SOC_IMAGE), &cmd)
+        .unwrap_err();
+    assert_error(&mut model, CaliptraError::RUNTIME_SOC_IMAGE_NOT_FOUND, resp);
+}
+
+#[test]
+fn test_verify_soc_image_svn_less_than_min_svn() {
+    let mut fuses = Fuses::default();
+    let mut model = boot_with_soc_image_and_fuses(2, fuses);
+
+    let cmd = verify_soc_image_cmd(SOC_IMAGE_ID, [0u8; 48], &soc_image_content());
+    let resp = model
+        .mailbox_execute(u32::from(CommandId::VERIFY_SOC_IMAGE), &cmd)
+        .unwrap_err();
+    assert_error(
+        &mut model,
+        CaliptraError::RUNTIME_SOC_IMAGE_SVN_LESS_THAN_MIN_SVN,
+        resp,
+    );
+
+    fuses.anti_rollback_disable = true;
+    let mut model = boot_with_soc_image_and_fuses(2, fuses);
+    let resp = model
+        .mailbox_execute(u32::from(CommandId::VERIFY_SOC_IMAGE), &cmd)
+        .unwrap()
+        .expect("We should have received a response");
+    let resp_hdr: &VerifySocImageResp =
+        LayoutVerified::<&[u8], VerifySocImageResp>::new(resp.as_bytes())
+            .unwrap()
+            .into_ref();
+    assert_eq!(resp_hdr.dpe_result, 0);
+}
@@ -7,12 +7,16 @@ use caliptra_builder::{
     ImageOptions,
 };
 use caliptra_common::mailbox_api::{
-    CommandId, FwInfoResp, MailboxReq, MailboxReqHeader, TagTciReq,
+    CommandId, FwInfoResp, InvokeDpeReq, MailboxReq, MailboxReqHeader, TagTciReq,
 };
 use caliptra_error::CaliptraError;
+use caliptra_hw_model::reset_sequence::{run_sequence, Condition, SequenceStep};
 use caliptra_hw_model::HwModel;
 use caliptra_runtime::RtBootStatus;
-use dpe::{DpeInstance, U8Bool, MAX_HANDLES};
+use dpe::{
+    commands::{Command, CommandHdr},
+    DpeInstance, U8Bool, MAX_HANDLES,
+};
 use zerocopy::{AsBytes, FromBytes};
 
 use crate::common::run_rt_test;
@@ -210,3 +214,62 @@ fn test_dpe_validation() {
     let info = FwInfoResp::read_from(resp.as_slice()).unwrap();
     assert_eq!(info.attestation_disabled, 1);
 }
+
+#[test]
+fn test_update_reset_during_dpe_command() {
+    let mut model = run_rt_test(None, None, None);
+
+    model.step_until(|m| {
+        m.soc_ifc().cptra_boot_status().read() == u32::from(RtBootStatus::RtReadyForCommands)
+    });
+
+    let cmd_hdr = CommandHdr::new_for_test(Command::GET_PROFILE);
+    let mut data = [0u8; InvokeDpeReq::DATA_MAX_SIZE];
+    data[..cmd_hdr.as_bytes().len()].copy_from_slice(cmd_hdr.as_bytes());
+    let mut cmd = MailboxReq::InvokeDpeCommand(InvokeDpeReq {
+        hdr: MailboxReqHeader { chksum: 0 },
+        data,
+        data_size: cmd_hdr.as_bytes().len() as u32,
+    });
+    cmd.populate_chksum().unwrap();
+    let dpe_cmd = cmd.as_bytes().unwrap();
+
+    let updated_fw_image = caliptra_builder::build_and_sign_image(
+        &FMC_WITH_UART,
+        &APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap()
+    .to_bytes()
+    .unwrap();
+
+    // Ask for an update reset while the mailbox is still locked by the DPE
+    // command
+    let results = run_sequence(
+        &mut model,
+        &[
+            SequenceStep::StartMailboxExecute {
+                cmd: u32::from(CommandId::INVOKE_DPE),
+                data: dpe_cmd,
+            },
+            SequenceStep::StepCycles(100),
+            SequenceStep::UpdateReset(&updated_fw_image),
+            SequenceStep::RunUntil(
+                Condition::BootStatus(u32::from(RtBootStatus::RtReadyForCommands)),
+                20_000_000,
+            ),
+            SequenceStep::MailboxExecute {
+                cmd: u32::from(CommandId::INVOKE_DPE),
+                data: dpe_cmd,
+            },
+        ],
+    )
+    .unwrap();
+
+    // The DPE command finished before the firmware was loaded, and DPE still
+    // works after the update reset
+    assert_eq!(results.len(), 3);
+    assert!(matches!(results[0], Ok(Some(_))), "{:?}", results[0]);
+    assert_eq!(results[1], Ok(None));
+    assert_eq!(results[2], results[0]);
+}
@@ -0,0 +1,44 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    build.rs
+
+Abstract:
+
+    Cargo build file. Generates the table of CaliptraError names used by
+    the GDB monitor from the constants in caliptra-error.
+
+--*/
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let error_src = manifest_dir.join("../../error/src/lib.rs");
+    println!("cargo:rerun-if-changed={}", error_src.display());
+
+    let src = fs::read_to_string(&error_src).unwrap();
+    // Constants are declared as (modulo line breaks):
+    //   pub const NAME: CaliptraError = CaliptraError::new_const(0x0001_0002);
+    let src: String = src.split_whitespace().collect();
+    let mut table = String::from("static ERROR_NAMES: &[(u32, &str)] = &[\n");
+    for decl in src.split("pubconst").skip(1) {
+        let Some((name, rest)) = decl.split_once(":CaliptraError=CaliptraError::new_const(")
+        else {
+            continue;
+        };
+        let code = rest.split(')').next().unwrap().replace('_', "");
+        let code = u32::from_str_radix(code.trim_start_matches("0x"), 16)
+            .unwrap_or_else(|_| panic!("Bad code for CaliptraError::{name}"));
+        table.push_str(&format!("    (0x{code:08x}, \"{name}\"),\n"));
+    }
+    table.push_str("];\n");
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("error_names.rs"), table).unwrap();
+}
@@ -0,0 +1,349 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    gdb_monitor.rs
+
+Abstract:
+
+    File contains the Caliptra-specific GDB monitor commands.
+
+--*/
+
+use caliptra_emu_bus::{Bus, TimerAction};
+use caliptra_emu_cpu::Cpu;
+use caliptra_emu_periph::{CaliptraRootBus, KeyUsage, KeyVault};
+use caliptra_emu_types::{RvAddr, RvSize};
+use std::fmt::{self, Write};
+
+const KEY_VAULT_BASE: RvAddr = 0x1001_8000;
+const PCR_CTRL_BASE: RvAddr = KEY_VAULT_BASE + 0x2000;
+const PCR_COUNT: u32 = 32;
+const STICKY_DV_CTRL_BASE: RvAddr = KEY_VAULT_BASE + 0x4000;
+const STICKY_DV_ENTRY_BASE: RvAddr = KEY_VAULT_BASE + 0x4028;
+const DV_CTRL_BASE: RvAddr = KEY_VAULT_BASE + 0x4208;
+const DV_ENTRY_BASE: RvAddr = KEY_VAULT_BASE + 0x4230;
+const DV_ENTRY_COUNT: u32 = 10;
+const DV_ENTRY_SIZE: u32 = 48;
+const LOCKABLE_SCRATCH_CTRL_BASE: RvAddr = KEY_VAULT_BASE + 0x4410;
+const LOCKABLE_SCRATCH_BASE: RvAddr = KEY_VAULT_BASE + 0x4438;
+const LOCKABLE_SCRATCH_COUNT: u32 = 10;
+const STICKY_LOCKABLE_SCRATCH_CTRL_BASE: RvAddr = KEY_VAULT_BASE + 0x4480;
+const STICKY_LOCKABLE_SCRATCH_BASE: RvAddr = KEY_VAULT_BASE + 0x44a0;
+const STICKY_LOCKABLE_SCRATCH_COUNT: u32 = 8;
+
+// The LOCK register (offset 0) and DATAOUT (offset 0x14) have read side
+// effects, so they are never read here.
+const MBOX_BASE: RvAddr = 0x3002_0000;
+const MBOX_USER: RvAddr = MBOX_BASE + 0x04;
+const MBOX_CMD: RvAddr = MBOX_BASE + 0x08;
+const MBOX_DLEN: RvAddr = MBOX_BASE + 0x0c;
+const MBOX_STATUS: RvAddr = MBOX_BASE + 0x1c;
+
+const SOC_IFC_BASE: RvAddr = 0x3003_0000;
+const HW_ERROR_FATAL: RvAddr = SOC_IFC_BASE;
+const HW_ERROR_NON_FATAL: RvAddr = SOC_IFC_BASE + 0x04;
+const FW_ERROR_FATAL: RvAddr = SOC_IFC_BASE + 0x08;
+const FW_ERROR_NON_FATAL: RvAddr = SOC_IFC_BASE + 0x0c;
+const BOOT_STATUS: RvAddr = SOC_IFC_BASE + 0x38;
+const FLOW_STATUS: RvAddr = SOC_IFC_BASE + 0x3c;
+const RESET_REASON: RvAddr = SOC_IFC_BASE + 0x40;
+
+const HELP: &str = "\
+Caliptra monitor commands:
+  kv              key vault slot locks and usage (key material if debug is unlocked)
+  pcr             PCR values and locks
+  dv              data vault entries and scratch registers
+  mbox            mailbox state
+  boot-status     boot status, flow status and reset reason
+  errors          HW/FW error registers
+  reset warm      trigger a warm reset
+  reset update    trigger an update reset";
+
+/// Execute the GDB `monitor` command `cmd`, writing its output to `out`.
+pub fn exec_monitor_cmd(
+    cpu: &mut Cpu<CaliptraRootBus>,
+    cmd: &str,
+    out: &mut impl Write,
+) -> fmt::Result {
+    let args: Vec<&str> = cmd.split_whitespace().collect();
+    match args.as_slice() {
+        [] | ["help"] => writeln!(out, "{HELP}"),
+        ["kv"] => dump_key_vault(cpu, out),
+        ["pcr"] => dump_pcrs(cpu, out),
+        ["dv"] => dump_data_vault(cpu, out),
+        ["mbox"] => dump_mailbox(cpu, out),
+        ["boot-status"] => dump_boot_status(cpu, out),
+        ["errors"] => dump_errors(cpu, out),
+        ["reset", "warm"] => {
+            cpu.clock
+                .timer()
+                .schedule_action_in(0, TimerAction::WarmReset);
+            writeln!(out, "Warm reset will occur on the next step")
+        }
+        ["reset", "update"] => {
+            cpu.clock
+                .timer()
+                .schedule_action_in(0, TimerAction::UpdateReset);
+            writeln!(out, "Update reset will occur on the next step")
+        }
+        _ => writeln!(out, "Unknown monitor command {cmd:?}\n{HELP}"),
+    }
+}
+
+fn read_word(cpu: &mut Cpu<CaliptraRootBus>, addr: RvAddr) -> u32 {
+    cpu.bus.read(RvSize::Word, addr).unwrap_or(0)
+}
+
+fn write_hex(out: &mut impl Write, bytes: &[u8]) -> fmt::Result {
+    for b in bytes {
+        write!(out, "{b:02x}")?;
+    }
+    Ok(())
+}
+
+fn write_words(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write, addr: RvAddr) -> fmt::Result {
+    for i in 0..DV_ENTRY_SIZE / 4 {
+        write!(out, "{:08x}", read_word(cpu, addr + i * 4))?;
+    }
+    Ok(())
+}
+
+fn lock_str(locked: bool) -> &'static str {
+    if locked {
+        "locked"
+    } else {
+        "unlocked"
+    }
+}
+
+fn dump_key_vault(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write) -> fmt::Result {
+    const USAGE_NAMES: [&str; 5] = [
+        "hmac_key",
+        "hmac_data",
+        "sha_data",
+        "ecc_private_key",
+        "ecc_key_gen_seed",
+    ];
+    let debug_locked = cpu.bus.soc_reg.is_debug_locked();
+    let mut all_usage = KeyUsage::default();
+    all_usage.set_hmac_key(true);
+    all_usage.set_hmac_data(true);
+    all_usage.set_sha_data(true);
+    all_usage.set_ecc_private_key(true);
+    all_usage.set_ecc_key_gen_seed(true);
+
+    for id in 0..KeyVault::KEY_COUNT {
+        let ctrl = read_word(cpu, KEY_VAULT_BASE + id * 4);
+        let usage = (ctrl >> 9) & 0x3f;
+        write!(
+            out,
+            "key[{id:2}]: write_lock={} use_lock={} last_dword={} usage=[",
+            ctrl & 1,
+            (ctrl >> 1) & 1,
+            (ctrl >> 15) & 0xf
+        )?;
+        let mut names = USAGE_NAMES
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| usage & (1 << i) != 0)
+            .map(|(_, name)| *name);
+        if let Some(name) = names.next() {
+            write!(out, "{name}")?;
+        }
+        for name in names {
+            write!(out, ",{name}")?;
+        }
+        write!(out, "]")?;
+        if !debug_locked {
+            if let Ok(key) = cpu.bus.key_vault.read_key_locked(id, all_usage) {
+                write!(out, " key=")?;
+                write_hex(out, &key)?;
+            }
+        }
+        writeln!(out)?;
+    }
+    if debug_locked {
+        writeln!(out, "(debug locked; key material not shown)")?;
+    }
+    Ok(())
+}
+
+fn dump_pcrs(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write) -> fmt::Result {
+    for id in 0..PCR_COUNT {
+        let ctrl = read_word(cpu, PCR_CTRL_BASE + id * 4);
+        write!(out, "pcr[{id:2}]: lock={} ", ctrl & 1)?;
+        write_hex(out, &cpu.bus.key_vault.read_pcr(id))?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn dump_data_vault(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write) -> fmt::Result {
+    for (name, ctrl_base, entry_base) in [
+        ("sticky_dv", STICKY_DV_CTRL_BASE, STICKY_DV_ENTRY_BASE),
+        ("dv", DV_CTRL_BASE, DV_ENTRY_BASE),
+    ] {
+        for id in 0..DV_ENTRY_COUNT {
+            let ctrl = read_word(cpu, ctrl_base + id * 4);
+            write!(out, "{name}[{id}]: {} ", lock_str(ctrl & 1 != 0))?;
+            write_words(cpu, out, entry_base + id * DV_ENTRY_SIZE)?;
+            writeln!(out)?;
+        }
+    }
+    for (name, ctrl_base, base, count) in [
+        (
+            "scratch",
+            LOCKABLE_SCRATCH_CTRL_BASE,
+            LOCKABLE_SCRATCH_BASE,
+            LOCKABLE_SCRATCH_COUNT,
+        ),
+        (
+            "sticky_scratch",
+            STICKY_LOCKABLE_SCRATCH_CTRL_BASE,
+            STICKY_LOCKABLE_SCRATCH_BASE,
+            STICKY_LOCKABLE_SCRATCH_COUNT,
+        ),
+    ] {
+        for id in 0..count {
+            let ctrl = read_word(cpu, ctrl_base + id * 4);
+            let val = read_word(cpu, base + id * 4);
+            writeln!(out, "{name}[{id}]: {} 0x{val:08x}", lock_str(ctrl & 1 != 0))?;
+        }
+    }
+    Ok(())
+}
+
+fn dump_mailbox(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write) -> fmt::Result {
+    let status = read_word(cpu, MBOX_STATUS);
+    let fsm = match (status >> 6) & 0x7 {
+        0x0 => "IDLE",
+        0x1 => "RDY_FOR_CMD",
+        0x2 => "RDY_FOR_DATA",
+        0x3 => "RDY_FOR_DLEN",
+        0x4 => "EXECUTE_SOC",
+        0x6 => "EXECUTE_UC",
+        0x7 => "ERROR",
+        _ => "UNKNOWN",
+    };
+    let status_name = match status & 0xf {
+        0x0 => "CMD_BUSY",
+        0x1 => "DATA_READY",
+        0x2 => "CMD_COMPLETE",
+        0x3 => "CMD_FAILURE",
+        _ => "UNKNOWN",
+    };
+    writeln!(out, "fsm:          {fsm}")?;
+    writeln!(out, "status:       {status_name}")?;
+    writeln!(out, "soc_has_lock: {}", (status >> 9) & 1)?;
+    writeln!(out, "user:         0x{:08x}", read_word(cpu, MBOX_USER))?;
+    writeln!(out, "cmd:          0x{:08x}", read_word(cpu, MBOX_CMD))?;
+    writeln!(out, "dlen:         {}", read_word(cpu, MBOX_DLEN))
+}
+
+fn dump_boot_status(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write) -> fmt::Result {
+    let flow_status = read_word(cpu, FLOW_STATUS);
+    let reset_reason = read_word(cpu, RESET_REASON);
+    writeln!(
+        out,
+        "boot_status:     0x{:08x}",
+        read_word(cpu, BOOT_STATUS)
+    )?;
+    writeln!(out, "flow_status:     0x{flow_status:08x}")?;
+    writeln!(out, "  status:        0x{:06x}", flow_status & 0x7f_ffff)?;
+    writeln!(out, "  ready_for_fw:  {}", (flow_status >> 28) & 1)?;
+    writeln!(out, "  ready_for_rt:  {}", (flow_status >> 29) & 1)?;
+    writeln!(out, "  ready_for_fuses: {}", (flow_status >> 30) & 1)?;
+    writeln!(
+        out,
+        "reset_reason:    {}",
+        match reset_reason & 0x3 {
+            0 => "cold",
+            1 => "update",
+            _ => "warm",
+        }
+    )
+}
+
+fn dump_errors(cpu: &mut Cpu<CaliptraRootBus>, out: &mut impl Write) -> fmt::Result {
+    writeln!(
+        out,
+        "hw_error_fatal:     0x{:08x}",
+        read_word(cpu, HW_ERROR_FATAL)
+    )?;
+    writeln!(
+        out,
+        "hw_error_non_fatal: 0x{:08x}",
+        read_word(cpu, HW_ERROR_NON_FATAL)
+    )?;
+    for (name, addr) in [
+        ("fw_error_fatal:    ", FW_ERROR_FATAL),
+        ("fw_error_non_fatal:", FW_ERROR_NON_FATAL),
+    ] {
+        let code = read_word(cpu, addr);
+        write!(out, "{name} 0x{code:08x}")?;
+        if code != 0 {
+            write!(out, " ({})", error_name(code))?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// The name of each `CaliptraError` constant, generated by build.rs.
+include!(concat!(env!("OUT_DIR"), "/error_names.rs"));
+
+/// The name of the `CaliptraError` constant for `code`. Codes shared by
+/// several constants return the first one.
+fn error_name(code: u32) -> &'static str {
+    ERROR_NAMES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or("unknown", |(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use caliptra_emu_bus::Clock;
+    use caliptra_emu_periph::CaliptraRootBusArgs;
+    use caliptra_error::CaliptraError;
+
+    #[test]
+    fn test_error_name() {
+        assert_eq!(
+            error_name(CaliptraError::ROM_CFI_PANIC_ASSERT_EQ_FAILURE.into()),
+            "ROM_CFI_PANIC_ASSERT_EQ_FAILURE"
+        );
+        assert_eq!(
+            error_name(CaliptraError::RUNTIME_MAILBOX_INVALID_PARAMS.into()),
+            "RUNTIME_MAILBOX_INVALID_PARAMS"
+        );
+        assert_eq!(error_name(0x7fff_ffff), "unknown");
+    }
+
+    #[test]
+    fn test_dump_errors() {
+        let clock = Clock::new();
+        let root_bus = CaliptraRootBus::new(&clock, CaliptraRootBusArgs::default());
+        let pic = root_bus.pic.clone();
+        let mut cpu = Cpu::new(root_bus, clock, pic);
+        cpu.bus
+            .write(
+                RvSize::Word,
+                FW_ERROR_FATAL,
+                CaliptraError::ROM_CFI_PANIC_ASSERT_EQ_FAILURE.into(),
+            )
+            .unwrap();
+        cpu.bus
+            .write(RvSize::Word, FW_ERROR_NON_FATAL, 0x7fff_ffff)
+            .unwrap();
+
+        let mut out = String::new();
+        dump_errors(&mut cpu, &mut out).unwrap();
+        assert!(out.contains("fw_error_fatal:     0x01040055 (ROM_CFI_PANIC_ASSERT_EQ_FAILURE)"));
+        assert!(out.contains("fw_error_non_fatal: 0x7fffffff (unknown)"));
+    }
+}
@@ -12,6 +12,7 @@ Abstract:
 
 --*/
 
+use super::gdb_monitor;
 use caliptra_emu_cpu::xreg_file::XReg;
 use caliptra_emu_cpu::StepAction;
 use caliptra_emu_cpu::{Cpu, WatchPtrKind};
@@ -24,6 +25,7 @@ use gdbstub::target;
 use gdbstub::target::ext::base::singlethread::{SingleThreadBase, SingleThreadResume};
 use gdbstub::target::ext::base::BaseOps;
 use gdbstub::target::ext::breakpoints::WatchKind;
+use gdbstub::target::ext::monitor_cmd::ConsoleOutput;
 use gdbstub::target::Target;
 use gdbstub::target::TargetResult;
 use gdbstub_arch;
@@ -109,6 +111,10 @@ impl Target for GdbTarget {
     ) -> Option<target::ext::breakpoints::BreakpointsOps<'_, Self>> {
         Some(self)
     }
+
+    fn support_monitor_cmd(&mut self) -> Option<target::ext::monitor_cmd::MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
 }
 
 impl SingleThreadBase for GdbTarget {
@@ -267,3 +273,21 @@ impl target::ext::breakpoints::HwWatchpoint for GdbTarget {
         Ok(true)
     }
 }
+
+impl target::ext::monitor_cmd::MonitorCmd for GdbTarget {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let Ok(cmd) = std::str::from_utf8(cmd) else {
+            gdbstub::outputln!(out, "Monitor command is not valid UTF-8");
+            return Ok(());
+        };
+        let mut output = String::new();
+        gdb_monitor::exec_monitor_cmd(&mut self.cpu, cmd, &mut output)
+            .map_err(|_| "Failed to format monitor command output")?;
+        gdbstub::output!(out, "{}", output);
+        Ok(())
+    }
+}
@@ -17,7 +17,7 @@ use caliptra_emu_cpu::{Cpu, RvInstr, StepAction};
 use caliptra_emu_periph::soc_reg::DebugManufService;
 use caliptra_emu_periph::{
     CaliptraRootBus, CaliptraRootBusArgs, DownloadIdevidCsrCb, MailboxInternal, ReadyForFwCb,
-    TbServicesCb, UploadUpdateFwCb,
+    SpiDevice, SpiFlash, TbServicesCb, UploadUpdateFwCb,
 };
 use caliptra_hw_model::BusMmio;
 use caliptra_hw_model_types::{DeviceLifecycle, SecurityState};
@@ -152,6 +152,11 @@ fn main() -> io::Result<()> {
                 .value_parser(value_parser!(u64))
                 .default_value(&(EXPECTED_CALIPTRA_BOOT_TIME_IN_CYCLES.to_string()))
         )
+        .arg(
+            arg!(--"spi-flash" <FILE> "SPI NOR flash image file. Writes to the flash are saved to the file.")
+                .required(false)
+                .value_parser(value_parser!(PathBuf)),
+        )
         .get_matches();
 
     let args_rom = args.get_one::<PathBuf>("rom").unwrap();
@@ -176,6 +181,7 @@ fn main() -> io::Result<()> {
         }
     };
     let args_device_lifecycle = args.get_one::<String>("device-lifecycle").unwrap();
+    let args_spi_flash = args.get_one::<PathBuf>("spi-flash");
 
     if !Path::new(&args_rom).exists() {
         println!("ROM File {:?} does not exist", args_rom);
@@ -229,6 +235,15 @@ fn main() -> io::Result<()> {
     }
     let update_fw_buf = Rc::new(update_fw_buf);
 
+    let mut spi_device: Option<Box<dyn SpiDevice>> = None;
+    if let Some(path) = args_spi_flash {
+        if !Path::new(&path).exists() {
+            println!("SPI flash file {:?} does not exist", path);
+            exit(-1);
+        }
+        spi_device = Some(Box::new(SpiFlash::from_file(path)?));
+    }
+
     let log_dir = Rc::new(args_log_dir.to_path_buf());
 
     let clock = Clock::new();
@@ -276,6 +291,7 @@ fn main() -> io::Result<()> {
                 download_idev_id_csr(mailbox, log_dir.clone(), cptra_dbg_manuf_service_reg);
             },
         ),
+        spi_device,
         ..Default::default()
     };
 
@@ -369,7 +385,8 @@ fn main() -> io::Result<()> {
             .write(|_| (*wdt_timeout >> 32) as u32);
     }
 
-    let cpu = Cpu::new(root_bus, clock);
+    let pic = root_bus.pic.clone();
+    let cpu = Cpu::new(root_bus, clock, pic);
 
     // Check if Optional GDB Port is passed
     match args.get_one::<String>("gdb-port") {
@@ -74,4 +74,12 @@ pub trait Bus {
     fn update_reset(&mut self) {
         // By default, do nothing
     }
+
+    /// Returns a counter that changes whenever memory the CPU may fetch
+    /// instructions from is modified other than by `write()`, or when its
+    /// write protection changes. The CPU discards its decoded instructions
+    /// when this value changes.
+    fn instr_mem_generation(&self) -> u64 {
+        0
+    }
 }
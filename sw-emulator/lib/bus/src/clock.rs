@@ -19,7 +19,7 @@ use std::{
     rc::Rc,
 };
 
-use crate::Bus;
+use crate::{Bus, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 
 /// Peripherals that want to use timer-based deferred execution will typically
 /// store a clone of Timer inside themselves, and use it to schedule future
@@ -125,6 +125,39 @@ impl Timer {
     }
 }
 
+impl Timer {
+    /// Save a scheduled action handle to a snapshot.
+    pub fn save_action(
+        &self,
+        action: &Option<ActionHandle>,
+        w: &mut SnapshotWriter,
+    ) -> Result<(), SnapshotError> {
+        match action {
+            Some(action) => {
+                w.write(&true)?;
+                action.0.save(w)
+            }
+            None => w.write(&false),
+        }
+    }
+
+    /// Restore an action handle saved with [`Timer::save_action`]. The
+    /// clock must be restored from the same snapshot for the handle to be
+    /// valid.
+    pub fn restore_action(
+        &self,
+        r: &mut SnapshotReader,
+    ) -> Result<Option<ActionHandle>, SnapshotError> {
+        if !r.read_value::<bool>()? {
+            return Ok(None);
+        }
+        let mut action = ActionHandleImpl::default();
+        action.restore(r)?;
+        action.id.timer_ptr = Rc::as_ptr(&self.clock);
+        Ok(Some(action.into()))
+    }
+}
+
 pub struct Clock {
     clock: Rc<ClockImpl>,
 }
@@ -188,6 +221,32 @@ impl Clock {
     }
 }
 
+impl Snapshot for Clock {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.clock.now.get())?;
+        w.write(&self.clock.next_action_id.get())?;
+        let actions = self.clock.action_handles.borrow();
+        w.write(&actions.len())?;
+        actions.iter().try_for_each(|action| action.save(w))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.clock.now.set(r.read_value()?);
+        self.clock.next_action_id.set(r.read_value()?);
+        let count: usize = r.read_value()?;
+        let mut actions = self.clock.action_handles.borrow_mut();
+        actions.clear();
+        for _ in 0..count {
+            let mut action = ActionHandleImpl::default();
+            action.restore(r)?;
+            action.id.timer_ptr = Rc::as_ptr(&self.clock);
+            actions.insert(action);
+        }
+        self.clock.recompute_next_action_time(&actions);
+        Ok(())
+    }
+}
+
 /// Represents an action scheduled with a `Timer`. Returned by
 /// [`Timer::schedule_poll_at`] and passed to [`Timer::has_fired()`] or
 /// [`Timer::cancel`].
@@ -209,6 +268,30 @@ struct ActionHandleImpl {
 
     action: TimerAction,
 }
+impl Default for ActionHandleImpl {
+    fn default() -> Self {
+        Self {
+            time: 0,
+            id: TimerActionId::default(),
+            action: TimerAction::Poll,
+        }
+    }
+}
+impl ActionHandleImpl {
+    /// Save the action. The timer pointer is not saved; it is replaced with
+    /// the restoring clock's pointer.
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.time)?;
+        w.write(&self.id.id)?;
+        w.write(&self.action)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.time)?;
+        r.read(&mut self.id.id)?;
+        r.read(&mut self.action)
+    }
+}
 impl From<ActionHandle> for ActionHandleImpl {
     fn from(val: ActionHandle) -> Self {
         val.0
@@ -243,6 +326,40 @@ pub enum TimerAction {
     SetNmiVec { addr: u32 },
 }
 
+impl Snapshot for TimerAction {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        match self {
+            TimerAction::Poll => w.write(&0u8),
+            TimerAction::WarmReset => w.write(&1u8),
+            TimerAction::UpdateReset => w.write(&2u8),
+            TimerAction::Nmi { mcause } => {
+                w.write(&3u8)?;
+                w.write(mcause)
+            }
+            TimerAction::SetNmiVec { addr } => {
+                w.write(&4u8)?;
+                w.write(addr)
+            }
+        }
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        *self = match r.read_value::<u8>()? {
+            0 => TimerAction::Poll,
+            1 => TimerAction::WarmReset,
+            2 => TimerAction::UpdateReset,
+            3 => TimerAction::Nmi {
+                mcause: r.read_value()?,
+            },
+            4 => TimerAction::SetNmiVec {
+                addr: r.read_value()?,
+            },
+            _ => return Err(SnapshotError::InvalidData("TimerAction")),
+        };
+        Ok(())
+    }
+}
+
 struct ClockImpl {
     now: Cell<u64>,
     next_action_time: Cell<Option<u64>>,
@@ -492,6 +609,41 @@ mod tests {
         assert!(timer.fired(&mut action0));
     }
 
+    #[test]
+    fn test_snapshot() {
+        let clock = Clock::new();
+        let timer = clock.timer();
+        clock.increment(100);
+        let action0 = Some(timer.schedule_poll_in(10));
+        let _action1 = timer.schedule_action_in(20, TimerAction::Nmi { mcause: 5 });
+
+        let mut w = SnapshotWriter::new();
+        clock.save(&mut w).unwrap();
+        timer.save_action(&action0, &mut w).unwrap();
+        let bytes = w.into_bytes();
+
+        let mut restored = Clock::new();
+        let restored_timer = restored.timer();
+        let mut r = SnapshotReader::new(&bytes);
+        restored.restore(&mut r).unwrap();
+        let mut action0 = restored_timer.restore_action(&mut r).unwrap();
+        assert!(r.is_empty());
+
+        assert_eq!(restored.now(), 100);
+        assert!(restored.increment(9).is_empty());
+        assert!(!restored_timer.fired(&mut action0));
+        assert_eq!(restored.increment(1), HashSet::from([TimerAction::Poll]));
+        assert!(restored_timer.fired(&mut action0));
+        assert_eq!(
+            restored.increment(10),
+            HashSet::from([TimerAction::Nmi { mcause: 5 }])
+        );
+
+        // New actions must not collide with restored ones
+        let action2 = restored_timer.schedule_poll_in(1);
+        restored_timer.cancel(action2);
+    }
+
     #[test]
     #[should_panic(
         expected = "Cannot schedule a timer action more than 9223372036854775807 clock cycles from now."
@@ -94,6 +94,12 @@ impl Bus for DynamicBus {
             dev.bus.poll();
         }
     }
+
+    fn instr_mem_generation(&self) -> u64 {
+        self.devs.iter().fold(0, |acc, dev| {
+            acc.wrapping_add(dev.bus.instr_mem_generation())
+        })
+    }
 }
 
 #[cfg(test)]
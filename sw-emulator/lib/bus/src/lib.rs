@@ -20,6 +20,7 @@ mod ram;
 mod register;
 mod register_array;
 mod rom;
+mod snapshot;
 pub mod testing;
 
 pub use crate::bus::{Bus, BusError};
@@ -33,3 +34,4 @@ pub use crate::register::{
 };
 pub use crate::register_array::{ReadWriteRegisterArray, RegisterArray};
 pub use crate::rom::Rom;
+pub use crate::snapshot::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
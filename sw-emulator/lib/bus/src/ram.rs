@@ -12,7 +12,7 @@ Abstract:
 
 --*/
 
-use crate::{mem::Mem, Bus, BusError};
+use crate::{mem::Mem, Bus, BusError, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
 
 /// Read Only Memory Device
@@ -53,6 +53,25 @@ impl Ram {
     }
 }
 
+impl Snapshot for Ram {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.error_injection)?;
+        w.write(&self.data().len())?;
+        w.write_bytes(self.data());
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.error_injection)?;
+        let len: usize = r.read_value()?;
+        if len != self.data().len() {
+            return Err(SnapshotError::InvalidData("RAM size mismatch"));
+        }
+        self.data_mut().copy_from_slice(r.read_bytes(len)?);
+        Ok(())
+    }
+}
+
 impl Bus for Ram {
     /// Read data of specified size from given address
     ///
@@ -0,0 +1,337 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    snapshot.rs
+
+Abstract:
+
+    File contains types used to save and restore emulator state.
+
+--*/
+
+use std::fmt;
+
+use tock_registers::interfaces::{Readable, Writeable};
+use tock_registers::{LocalRegisterCopy, RegisterLongName, UIntLike};
+
+use crate::{
+    ReadOnlyMemory, ReadOnlyRegister, ReadWriteMemory, ReadWriteRegister, ReadWriteRegisterArray,
+    WriteOnlyMemory, WriteOnlyRegister,
+};
+use caliptra_emu_types::RvData;
+
+/// Snapshot Error
+#[derive(Debug, Eq, PartialEq)]
+pub enum SnapshotError {
+    /// The snapshot ended before all state was restored
+    UnexpectedEof,
+
+    /// The snapshot contains a value that is not valid for this device
+    InvalidData(&'static str),
+
+    /// The device is in a state that cannot be saved (for example, an
+    /// operation that completes through a host callback is in flight)
+    Busy(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::UnexpectedEof => write!(f, "Unexpected end of snapshot"),
+            SnapshotError::InvalidData(what) => write!(f, "Invalid snapshot data: {what}"),
+            SnapshotError::Busy(what) => write!(f, "Unable to snapshot: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Serializes device state into a byte stream
+#[derive(Default)]
+pub struct SnapshotWriter {
+    data: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    /// Create an empty writer
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append raw bytes to the snapshot
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.data.extend_from_slice(bytes);
+    }
+
+    /// Append the state of `val` to the snapshot
+    pub fn write<T: Snapshot + ?Sized>(&mut self, val: &T) -> Result<(), SnapshotError> {
+        val.save(self)
+    }
+
+    /// Consume the writer and return the snapshot bytes
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Deserializes device state from a byte stream
+pub struct SnapshotReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> SnapshotReader<'a> {
+    /// Create a reader over `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Read `len` raw bytes from the snapshot
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if len > self.data.len() {
+            return Err(SnapshotError::UnexpectedEof);
+        }
+        let (result, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(result)
+    }
+
+    /// Restore the state of `val` from the snapshot
+    pub fn read<T: Snapshot + ?Sized>(&mut self, val: &mut T) -> Result<(), SnapshotError> {
+        val.restore(self)
+    }
+
+    /// Read a value that implements `Default` from the snapshot
+    pub fn read_value<T: Snapshot + Default>(&mut self) -> Result<T, SnapshotError> {
+        let mut val = T::default();
+        val.restore(self)?;
+        Ok(val)
+    }
+
+    /// Returns true if all bytes have been consumed
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// Device state that can be saved and restored
+///
+/// `restore` must read exactly what `save` wrote. State that is not part of
+/// the machine (host callbacks, log files, entropy sources) is left alone so
+/// a snapshot can be restored into a freshly constructed device.
+pub trait Snapshot {
+    /// Save the state to `w`
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError>;
+
+    /// Restore the state from `r`
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError>;
+}
+
+macro_rules! impl_snapshot_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Snapshot for $ty {
+                fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+                    w.write_bytes(&self.to_le_bytes());
+                    Ok(())
+                }
+
+                fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+                    let bytes = r.read_bytes(std::mem::size_of::<$ty>())?;
+                    *self = <$ty>::from_le_bytes(bytes.try_into().unwrap());
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_snapshot_int!(u8, u16, u32, u64);
+
+impl Snapshot for usize {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&(*self as u64))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        let val: u64 = r.read_value()?;
+        *self = usize::try_from(val).map_err(|_| SnapshotError::InvalidData("usize"))?;
+        Ok(())
+    }
+}
+
+impl Snapshot for bool {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&u8::from(*self))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        *self = match r.read_value::<u8>()? {
+            0 => false,
+            1 => true,
+            _ => return Err(SnapshotError::InvalidData("bool")),
+        };
+        Ok(())
+    }
+}
+
+impl<T: Snapshot, const N: usize> Snapshot for [T; N] {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.iter().try_for_each(|val| w.write(val))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.iter_mut().try_for_each(|val| r.read(val))
+    }
+}
+
+impl<T: Snapshot + Default> Snapshot for Vec<T> {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.len())?;
+        self.iter().try_for_each(|val| w.write(val))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        let len: usize = r.read_value()?;
+        self.clear();
+        for _ in 0..len {
+            self.push(r.read_value()?);
+        }
+        Ok(())
+    }
+}
+
+impl<T: Snapshot + Default> Snapshot for Option<T> {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        match self {
+            Some(val) => {
+                w.write(&true)?;
+                w.write(val)
+            }
+            None => w.write(&false),
+        }
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        *self = if r.read_value::<bool>()? {
+            Some(r.read_value()?)
+        } else {
+            None
+        };
+        Ok(())
+    }
+}
+
+impl<T: UIntLike + Snapshot + Default, R: RegisterLongName> Snapshot for LocalRegisterCopy<T, R> {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.get())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.set(r.read_value()?);
+        Ok(())
+    }
+}
+
+macro_rules! impl_snapshot_register {
+    ($($reg:ident),*) => {
+        $(
+            impl<T: UIntLike + Snapshot + Default, R: RegisterLongName> Snapshot for $reg<T, R> {
+                fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+                    w.write(&self.reg.get())
+                }
+
+                fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+                    self.reg.set(r.read_value()?);
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_snapshot_register!(ReadWriteRegister, ReadOnlyRegister, WriteOnlyRegister);
+
+impl<T, const SIZE: usize, R> Snapshot for ReadWriteRegisterArray<T, SIZE, R>
+where
+    T: UIntLike + Into<RvData> + TryFrom<RvData> + Snapshot + Default,
+    R: RegisterLongName,
+{
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.iter().try_for_each(|reg| w.write(reg))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.iter_mut().try_for_each(|reg| r.read(reg))
+    }
+}
+
+macro_rules! impl_snapshot_memory {
+    ($($mem:ident),*) => {
+        $(
+            impl<const N: usize> Snapshot for $mem<N> {
+                fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+                    w.write_bytes(self.data());
+                    Ok(())
+                }
+
+                fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+                    self.data_mut().copy_from_slice(r.read_bytes(N)?);
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_snapshot_memory!(ReadWriteMemory, ReadOnlyMemory, WriteOnlyMemory);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut w = SnapshotWriter::new();
+        w.write(&0x12u8).unwrap();
+        w.write(&0x1234_5678u32).unwrap();
+        w.write(&u64::MAX).unwrap();
+        w.write(&true).unwrap();
+        w.write(&[1u32, 2, 3]).unwrap();
+        w.write(&vec![4u8, 5]).unwrap();
+        w.write(&Some(6u16)).unwrap();
+        w.write(&Option::<u16>::None).unwrap();
+        w.write(&ReadWriteRegister::<u32>::new(7)).unwrap();
+        let bytes = w.into_bytes();
+
+        let mut r = SnapshotReader::new(&bytes);
+        assert_eq!(r.read_value::<u8>().unwrap(), 0x12);
+        assert_eq!(r.read_value::<u32>().unwrap(), 0x1234_5678);
+        assert_eq!(r.read_value::<u64>().unwrap(), u64::MAX);
+        assert!(r.read_value::<bool>().unwrap());
+        assert_eq!(r.read_value::<[u32; 3]>().unwrap(), [1, 2, 3]);
+        assert_eq!(r.read_value::<Vec<u8>>().unwrap(), vec![4, 5]);
+        assert_eq!(r.read_value::<Option<u16>>().unwrap(), Some(6));
+        assert_eq!(r.read_value::<Option<u16>>().unwrap(), None);
+        let mut reg = ReadWriteRegister::<u32>::new(0);
+        r.read(&mut reg).unwrap();
+        assert_eq!(reg.reg.get(), 7);
+        assert!(r.is_empty());
+
+        assert_eq!(
+            r.read_value::<u32>().err(),
+            Some(SnapshotError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn test_invalid_bool() {
+        let mut r = SnapshotReader::new(&[2]);
+        assert_eq!(
+            r.read_value::<bool>().err(),
+            Some(SnapshotError::InvalidData("bool"))
+        );
+    }
+}
@@ -0,0 +1,70 @@
+// Licensed under the Apache-2.0 license
+
+//! Compares the CPU's step rate on a tight loop with and without the decoded
+//! instruction cache:
+//!
+//! ```shell
+//! cargo bench -p caliptra-emu-cpu
+//! ```
+
+use caliptra_emu_bus::{Clock, DynamicBus, Ram};
+use caliptra_emu_cpu::{Cpu, Pic, StepAction};
+use std::time::{Duration, Instant};
+
+const STEPS: u32 = 10_000_000;
+
+fn program() -> Vec<u8> {
+    const ADDI_X1_1: u32 = 0x00108093;
+    const ADDI_X2_3: u32 = 0x00310113;
+    const XOR_X3_X1_X2: u32 = 0x0020c1b3;
+    const C_NOP: u16 = 0x0001;
+    const JAL_X0_MINUS_16: u32 = 0xff1ff06f;
+
+    let mut program = vec![];
+    for instr in [ADDI_X1_1, ADDI_X2_3, XOR_X3_X1_X2] {
+        program.extend_from_slice(&instr.to_le_bytes());
+    }
+    program.extend_from_slice(&C_NOP.to_le_bytes());
+    program.extend_from_slice(&C_NOP.to_le_bytes());
+    program.extend_from_slice(&JAL_X0_MINUS_16.to_le_bytes());
+    program
+}
+
+fn run(cache_enabled: bool) -> Duration {
+    let program = program();
+    let mut bus = DynamicBus::new();
+    let len = program.len() as u32;
+    bus.attach_dev("RAM", 0..=len - 1, Box::new(Ram::new(program)))
+        .unwrap();
+    let mut cpu = Cpu::new(bus, Clock::new(), Pic::new());
+    cpu.set_instr_cache_enabled(cache_enabled);
+
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        assert_eq!(cpu.step(None), StepAction::Continue);
+    }
+    start.elapsed()
+}
+
+fn main() {
+    // Warm up
+    run(true);
+
+    let uncached = run(false);
+    let cached = run(true);
+    let rate = |d: Duration| f64::from(STEPS) / d.as_secs_f64() / 1e6;
+    println!(
+        "instr cache disabled: {:>8.2?} ({:.1} Minstr/s)",
+        uncached,
+        rate(uncached)
+    );
+    println!(
+        "instr cache enabled:  {:>8.2?} ({:.1} Minstr/s)",
+        cached,
+        rate(cached)
+    );
+    println!(
+        "speedup: {:.2}x",
+        uncached.as_secs_f64() / cached.as_secs_f64()
+    );
+}
@@ -14,14 +14,45 @@ Abstract:
 
 use crate::csr_file::{Csr, CsrFile};
 use crate::instr::Instr;
+use crate::instr_cache::InstrCache;
+use crate::internal_timer::InternalTimer;
+use crate::pic::Pic;
 use crate::types::{RvInstr, RvMStatus};
 use crate::xreg_file::{XReg, XRegFile};
 use bit_vec::BitVec;
-use caliptra_emu_bus::{Bus, BusError, Clock, TimerAction};
+use caliptra_emu_bus::{
+    Bus, BusError, Clock, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter, TimerAction,
+};
 use caliptra_emu_types::{RvAddr, RvData, RvException, RvSize};
 
 pub type InstrTracer<'a> = dyn FnMut(u32, RvInstr) + 'a;
 
+/// Machine interrupt pending and enable bits (MIP/MIE)
+const MIP_MTIP: u32 = 1 << 7;
+const MIP_MEIP: u32 = 1 << 11;
+const MIP_MITIP1: u32 = 1 << 28;
+const MIP_MITIP0: u32 = 1 << 29;
+
+/// MCAUSE interrupt flag and exception codes
+const MCAUSE_INTERRUPT: u32 = 1 << 31;
+const MCAUSE_MTI: u32 = 7;
+const MCAUSE_MEI: u32 = 11;
+const MCAUSE_MITI1: u32 = 28;
+const MCAUSE_MITI0: u32 = 29;
+
+/// Interrupts in the order VeeR takes them when several are pending, with
+/// their MIP bit and exception code.
+const INTERRUPT_PRIORITY: [(u32, u32); 4] = [
+    (MIP_MEIP, MCAUSE_MEI),
+    (MIP_MTIP, MCAUSE_MTI),
+    (MIP_MITIP0, MCAUSE_MITI0),
+    (MIP_MITIP1, MCAUSE_MITI1),
+];
+
+/// NMI cause raised when fetching a fast external interrupt handler address
+/// fails (from RISC-V_VeeR_EL2_PRM.pdf)
+const NMI_CAUSE_FAST_INT_ACCESS_ERROR: u32 = 0xF000_1001;
+
 #[derive(Clone)]
 pub struct CodeCoverage {
     bit_vec: BitVec,
@@ -88,6 +119,18 @@ impl Default for WatchPtrCfg {
         Self::new()
     }
 }
+
+/// A fault injected into the CPU to model a glitch attack. Each fault
+/// triggers once and is then removed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CpuFault {
+    /// Skip the next execution of the instruction at `pc`, as if it were a
+    /// NOP.
+    SkipInstr { pc: RvAddr },
+
+    /// XOR `mask` into the value returned by the next data load from `addr`.
+    CorruptRead { addr: RvAddr, mask: RvData },
+}
 /// RISCV CPU
 pub struct Cpu<TBus: Bus> {
     /// General Purpose register file
@@ -117,6 +160,24 @@ pub struct Cpu<TBus: Bus> {
     pub(crate) watch_ptr_cfg: WatchPtrCfg,
 
     pub code_coverage: CodeCoverage,
+
+    // Cache of decoded instructions
+    pub(crate) instr_cache: InstrCache<TBus>,
+
+    /// The programmable interrupt controller
+    pic: Pic,
+
+    /// VeeR internal timers 0 and 1
+    internal_timers: [InternalTimer; 2],
+
+    /// The external interrupt id captured in MEIHAP
+    meihap_claim_id: u8,
+
+    /// Set by WFI; the core stays halted until an enabled interrupt is pending.
+    halted: bool,
+
+    /// Injected faults that haven't been triggered yet
+    pub(crate) faults: Vec<CpuFault>,
 }
 
 /// Cpu instruction step action
@@ -137,7 +198,7 @@ impl<TBus: Bus> Cpu<TBus> {
     const PC_RESET_VAL: RvData = 0;
 
     /// Create a new RISCV CPU
-    pub fn new(bus: TBus, clock: Clock) -> Self {
+    pub fn new(bus: TBus, clock: Clock, pic: Pic) -> Self {
         Self {
             xregs: XRegFile::new(),
             csrs: CsrFile::new(),
@@ -151,6 +212,12 @@ impl<TBus: Bus> Cpu<TBus> {
             // TODO: Pass in code_coverage from the outside (as caliptra-emu-cpu
             // isn't supposed to know anything about the caliptra memory map)
             code_coverage: CodeCoverage::new(48 * 1024),
+            instr_cache: InstrCache::new(),
+            pic,
+            internal_timers: [InternalTimer::new(); 2],
+            meihap_claim_id: 0,
+            halted: false,
+            faults: Vec::new(),
         }
     }
 
@@ -176,6 +243,15 @@ impl<TBus: Bus> Cpu<TBus> {
         self.pc = 0;
     }
 
+    /// Warm reset the core and everything on the bus, as if the SoC had
+    /// toggled cptra_rst_b.
+    pub fn warm_reset(&mut self) {
+        self.bus.warm_reset();
+        self.reset_pc();
+        self.halted = false;
+        self.instr_cache.flush();
+    }
+
     /// Returns the next program counter after the current instruction is finished executing.
     pub fn next_pc(&self) -> RvData {
         self.next_pc
@@ -233,7 +309,17 @@ impl<TBus: Bus> Cpu<TBus> {
     ///
     /// * `RvException` - Exception with cause `RvExceptionCause::IllegalRegister`
     pub fn read_csr(&self, csr: RvAddr) -> Result<RvData, RvException> {
-        self.csrs.read(csr)
+        match csr {
+            Csr::MIP => Ok(self.mip()),
+            Csr::MITCNT0 => Ok(self.internal_timers[0].count),
+            Csr::MITB0 => Ok(self.internal_timers[0].bound),
+            Csr::MITCTL0 => Ok(self.internal_timers[0].ctl()),
+            Csr::MITCNT1 => Ok(self.internal_timers[1].count),
+            Csr::MITB1 => Ok(self.internal_timers[1].bound),
+            Csr::MITCTL1 => Ok(self.internal_timers[1].ctl()),
+            Csr::MEIHAP => Ok(self.csrs.read(Csr::MEIVT)? | (u32::from(self.meihap_claim_id) << 2)),
+            _ => self.csrs.read(csr),
+        }
     }
 
     /// Write the specified Configuration status register
@@ -247,7 +333,69 @@ impl<TBus: Bus> Cpu<TBus> {
     ///
     /// * `RvException` - Exception with cause `RvExceptionCause::IllegalRegister`
     pub fn write_csr(&mut self, csr: RvAddr, val: RvData) -> Result<(), RvException> {
-        self.csrs.write(csr, val)
+        match csr {
+            Csr::MITCNT0 => self.internal_timers[0].count = val,
+            Csr::MITB0 => self.internal_timers[0].bound = val,
+            Csr::MITCTL0 => self.internal_timers[0].set_ctl(val, false),
+            Csr::MITCNT1 => self.internal_timers[1].count = val,
+            Csr::MITB1 => self.internal_timers[1].bound = val,
+            Csr::MITCTL1 => self.internal_timers[1].set_ctl(val, true),
+            Csr::MEICPCT => self.capture_external_interrupt()?,
+            _ => self.csrs.write(csr, val)?,
+        }
+        Ok(())
+    }
+
+    /// Returns the interrupts currently pending (MIP)
+    fn mip(&self) -> RvData {
+        let mut mip = 0;
+        if let Some((_, priority)) = self.pic.highest_priority_irq() {
+            if priority > self.external_interrupt_threshold() {
+                mip |= MIP_MEIP;
+            }
+        }
+        if self.pic.timer_int() {
+            mip |= MIP_MTIP;
+        }
+        if self.internal_timers[0].pending() {
+            mip |= MIP_MITIP0;
+        }
+        if self.internal_timers[1].pending() {
+            mip |= MIP_MITIP1;
+        }
+        mip
+    }
+
+    /// Returns the normalized priority an external interrupt must exceed to
+    /// be signaled: the greater of MEIPT and MEICURPL.
+    fn external_interrupt_threshold(&self) -> u8 {
+        // Cannot panic; MEIPT and MEICURPL are valid CSRs
+        let meipt = self.csrs.read(Csr::MEIPT).unwrap() as u8;
+        let meicurpl = self.csrs.read(Csr::MEICURPL).unwrap() as u8;
+        self.pic
+            .normalize_priority(meipt)
+            .max(self.pic.normalize_priority(meicurpl))
+    }
+
+    /// Capture the id and priority of the highest priority external
+    /// interrupt into MEIHAP and MEICIDPL.
+    fn capture_external_interrupt(&mut self) -> Result<(), RvException> {
+        let (id, priority) = self.pic.highest_priority_irq().unwrap_or((0, 0));
+        self.meihap_claim_id = id;
+        self.csrs
+            .write(Csr::MEICIDPL, self.pic.normalize_priority(priority).into())
+    }
+
+    /// Halt the core until an enabled interrupt is pending (WFI)
+    pub(crate) fn wait_for_interrupt(&mut self) {
+        self.halted = true;
+    }
+
+    fn tick_internal_timers(&mut self) {
+        let wrapped = self.internal_timers[0].tick(self.halted);
+        if !self.internal_timers[1].cascaded() || wrapped {
+            self.internal_timers[1].tick(self.halted);
+        }
     }
 
     /// Read from bus
@@ -273,7 +421,13 @@ impl<TBus: Bus> Cpu<TBus> {
             }
         }
 
-        match self.bus.read(size, addr) {
+        let mut result = self.bus.read(size, addr);
+        let corrupt_read =
+            |f: &CpuFault| matches!(f, CpuFault::CorruptRead { addr: a, .. } if *a == addr);
+        if let Some(CpuFault::CorruptRead { mask, .. }) = self.take_fault(corrupt_read) {
+            result = result.map(|val| val ^ mask);
+        }
+        match result {
             Ok(val) => Ok(val),
             Err(exception) => match exception {
                 BusError::InstrAccessFault => Err(RvException::instr_access_fault(addr)),
@@ -313,6 +467,9 @@ impl<TBus: Bus> Cpu<TBus> {
                 false => None,
             }
         }
+        // Drop any cached instructions overlapping the written bytes
+        self.instr_cache.invalidate(addr, usize::from(size) as u32);
+
         match self.bus.write(size, addr, val) {
             Ok(val) => Ok(val),
             Err(exception) => match exception {
@@ -361,10 +518,12 @@ impl<TBus: Bus> Cpu<TBus> {
             match action_type {
                 TimerAction::WarmReset => {
                     self.reset_pc();
+                    self.instr_cache.flush();
                     break;
                 }
                 TimerAction::UpdateReset => {
                     self.reset_pc();
+                    self.instr_cache.flush();
                     break;
                 }
                 TimerAction::Nmi { mcause } => return self.handle_nmi(*mcause, 0),
@@ -373,6 +532,25 @@ impl<TBus: Bus> Cpu<TBus> {
             }
         }
 
+        self.tick_internal_timers();
+
+        // Interrupts are taken between instructions. A pending interrupt
+        // wakes the core from WFI even while MSTATUS.MIE is clear.
+        // Cannot panic; MIE and MSTATUS are valid CSRs
+        let mie = self.read_csr(Csr::MIE).unwrap();
+        if mie != 0 {
+            let pending = self.mip() & mie;
+            if pending != 0 {
+                self.halted = false;
+                if RvMStatus(self.read_csr(Csr::MSTATUS).unwrap()).mie() != 0 {
+                    return self.handle_interrupt(pending);
+                }
+            }
+        }
+        if self.halted {
+            return StepAction::Continue;
+        }
+
         match self.exec_instr(instr_tracer) {
             Ok(result) => result,
             Err(exception) => self.handle_exception(exception),
@@ -382,7 +560,6 @@ impl<TBus: Bus> Cpu<TBus> {
     /// Handle synchronous exception
     fn handle_exception(&mut self, exception: RvException) -> StepAction {
         let ret = self.handle_trap(
-            false,
             self.read_pc(),
             exception.cause().into(),
             exception.info(),
@@ -397,7 +574,45 @@ impl<TBus: Bus> Cpu<TBus> {
 
     /// Handle non-maskable interrupt (VeeR-specific)
     fn handle_nmi(&mut self, cause: u32, info: u32) -> StepAction {
-        let ret = self.handle_trap(false, self.read_pc(), cause, info, self.nmivec);
+        let ret = self.handle_trap(self.read_pc(), cause, info, self.nmivec);
+        match ret {
+            Ok(_) => StepAction::Continue,
+            Err(_) => StepAction::Fatal,
+        }
+    }
+
+    /// Handle asynchronous interrupt
+    ///
+    /// External interrupts use VeeR fast interrupt redirection: the claim id
+    /// and priority are captured and the handler address is loaded from the
+    /// vector table entry MEIHAP points to. Other interrupts go through
+    /// MTVEC, in direct or vectored mode.
+    fn handle_interrupt(&mut self, pending: u32) -> StepAction {
+        // Cannot panic; pending only contains bits of MIP
+        let (_, code) = INTERRUPT_PRIORITY
+            .iter()
+            .find(|(bit, _)| pending & bit != 0)
+            .unwrap();
+
+        let next_pc = if *code == MCAUSE_MEI {
+            // Cannot panic; MEICIDPL and MEIHAP are valid CSRs
+            self.capture_external_interrupt().unwrap();
+            let meihap = self.read_csr(Csr::MEIHAP).unwrap();
+            match self.bus.read(RvSize::Word, meihap) {
+                Ok(handler) => handler,
+                Err(_) => return self.handle_nmi(NMI_CAUSE_FAST_INT_ACCESS_ERROR, 0),
+            }
+        } else {
+            // Cannot panic; mtvec is a valid CSR
+            let mtvec = self.read_csr(Csr::MTVEC).unwrap();
+            if mtvec & 0b11 == 1 {
+                (mtvec & !0b11).wrapping_add(4 * code)
+            } else {
+                mtvec & !0b11
+            }
+        };
+
+        let ret = self.handle_trap(self.read_pc(), MCAUSE_INTERRUPT | code, 0, next_pc);
         match ret {
             Ok(_) => StepAction::Continue,
             Err(_) => StepAction::Fatal,
@@ -411,17 +626,11 @@ impl<TBus: Bus> Cpu<TBus> {
     /// * `RvException` - Exception
     fn handle_trap(
         &mut self,
-        _intr: bool,
         pc: RvAddr,
         cause: u32,
         info: u32,
         next_pc: u32,
     ) -> Result<(), RvException> {
-        // TODO: Implement Interrupt Handling
-        // 1. Support for vectored asynchronous interrupts
-        // 2. Veer fast external interrupt support
-        assert!(!_intr);
-
         self.write_csr(Csr::MEPC, pc)?;
         self.write_csr(Csr::MCAUSE, cause)?;
         self.write_csr(Csr::MTVAL, info)?;
@@ -439,6 +648,47 @@ impl<TBus: Bus> Cpu<TBus> {
         Ok(())
     }
 
+    /// Invalidate all decoded instructions
+    ///
+    /// Must be called after instruction memory is modified without going
+    /// through the CPU, unless the bus reports the change through
+    /// `Bus::instr_mem_generation()`.
+    pub fn invalidate_instr_cache(&mut self) {
+        self.instr_cache.flush();
+    }
+
+    /// Enable or disable the decoded instruction cache
+    ///
+    /// When disabled, every instruction is fetched from the bus and decoded
+    /// on each step.
+    pub fn set_instr_cache_enabled(&mut self, enabled: bool) {
+        self.instr_cache.set_enabled(enabled);
+    }
+
+    /// Inject a fault to model a glitch attack
+    pub fn inject_fault(&mut self, fault: CpuFault) {
+        self.faults.push(fault);
+    }
+
+    /// Injected faults that haven't been triggered yet
+    pub fn pending_faults(&self) -> &[CpuFault] {
+        &self.faults
+    }
+
+    /// Remove all faults that haven't been triggered yet
+    pub fn clear_faults(&mut self) {
+        self.faults.clear();
+    }
+
+    /// Remove and return the first pending fault matching `pred`
+    pub(crate) fn take_fault(&mut self, pred: impl Fn(&CpuFault) -> bool) -> Option<CpuFault> {
+        if self.faults.is_empty() {
+            return None;
+        }
+        let index = self.faults.iter().position(pred)?;
+        Some(self.faults.remove(index))
+    }
+
     //// Append WatchPointer
     pub fn add_watchptr(&mut self, addr: u32, len: u32, kind: WatchPtrKind) {
         for addr in addr..(addr + len) {
@@ -464,27 +714,65 @@ impl<TBus: Bus> Cpu<TBus> {
     }
 }
 
+impl<TBus: Bus + Snapshot> Snapshot for Cpu<TBus> {
+    /// Save the architectural state, the clock and everything on the bus.
+    ///
+    /// Code coverage and watchpoints belong to the debugging session and are
+    /// not saved.
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.xregs)?;
+        w.write(&self.csrs)?;
+        w.write(&self.pc)?;
+        w.write(&self.next_pc)?;
+        w.write(&self.nmivec)?;
+        w.write(&self.internal_timers)?;
+        w.write(&self.meihap_claim_id)?;
+        w.write(&self.halted)?;
+        // The clock must be restored before the bus so that peripherals can
+        // re-attach their pending timer actions.
+        w.write(&self.clock)?;
+        w.write(&self.pic)?;
+        w.write(&self.bus)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.xregs)?;
+        r.read(&mut self.csrs)?;
+        r.read(&mut self.pc)?;
+        r.read(&mut self.next_pc)?;
+        r.read(&mut self.nmivec)?;
+        r.read(&mut self.internal_timers)?;
+        r.read(&mut self.meihap_claim_id)?;
+        r.read(&mut self.halted)?;
+        r.read(&mut self.clock)?;
+        r.read(&mut self.pic)?;
+        r.read(&mut self.bus)?;
+        self.instr_cache.flush();
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use caliptra_emu_bus::{testing::FakeBus, DynamicBus, Rom, Timer};
+    use caliptra_emu_bus::{testing::FakeBus, DynamicBus, Ram, Rom, Timer};
 
     #[test]
     fn test_new() {
-        let cpu = Cpu::new(DynamicBus::new(), Clock::new());
+        let cpu = Cpu::new(DynamicBus::new(), Clock::new(), Pic::new());
         assert_eq!(cpu.read_pc(), 0);
     }
 
     #[test]
     fn test_pc() {
-        let mut cpu = Cpu::new(DynamicBus::new(), Clock::new());
+        let mut cpu = Cpu::new(DynamicBus::new(), Clock::new(), Pic::new());
         cpu.write_pc(0xFF);
         assert_eq!(cpu.read_pc(), 0xFF);
     }
 
     #[test]
     fn test_xreg() {
-        let mut cpu = Cpu::new(DynamicBus::new(), Clock::new());
+        let mut cpu = Cpu::new(DynamicBus::new(), Clock::new(), Pic::new());
         for reg in 1..32u32 {
             assert_eq!(cpu.write_xreg(reg.into(), 0xFF).ok(), Some(()));
             assert_eq!(cpu.read_xreg(reg.into()).ok(), Some(0xFF));
@@ -514,7 +802,7 @@ mod tests {
 
         let mut action0 = Some(timer.schedule_poll_in(31));
 
-        let mut cpu = Cpu::new(bus, clock);
+        let mut cpu = Cpu::new(bus, clock, Pic::new());
         for i in 0..30 {
             assert_eq!(cpu.clock.now(), i);
             assert_eq!(cpu.step(None), StepAction::Continue);
@@ -529,6 +817,300 @@ mod tests {
         assert_eq!(cpu.read_pc(), 31 * 4);
     }
 
+    #[test]
+    fn test_instr_cache_invalidation() {
+        const ADDI_X1_1: u32 = 0x00100093;
+        const ADDI_X1_2: u32 = 0x00200093;
+        const ADDI_X1_3: u32 = 0x00300093;
+
+        let mut bus = DynamicBus::new();
+        let ram = Ram::new(ADDI_X1_1.to_le_bytes().to_vec());
+        bus.attach_dev("RAM", 0..=0x3, Box::new(ram)).unwrap();
+        let mut cpu = Cpu::new(bus, Clock::new(), Pic::new());
+
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 1);
+
+        // Writes through the CPU invalidate the cached instruction
+        cpu.write_bus(RvSize::Word, 0, ADDI_X1_2).unwrap();
+        cpu.write_pc(0);
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 2);
+
+        // Writes behind the CPU's back require an explicit invalidation
+        cpu.bus.write(RvSize::Word, 0, ADDI_X1_3).unwrap();
+        cpu.write_pc(0);
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 2);
+
+        cpu.invalidate_instr_cache();
+        cpu.write_pc(0);
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_instr_cache_bus_generation() {
+        const ADDI_X1_1: u32 = 0x00100093;
+        const ADDI_X1_2: u32 = 0x00200093;
+
+        struct GenerationBus {
+            ram: Ram,
+            generation: u64,
+        }
+        impl Bus for GenerationBus {
+            fn read(&mut self, size: RvSize, addr: RvAddr) -> Result<RvData, BusError> {
+                self.ram.read(size, addr)
+            }
+            fn write(&mut self, size: RvSize, addr: RvAddr, val: RvData) -> Result<(), BusError> {
+                self.ram.write(size, addr, val)
+            }
+            fn instr_mem_generation(&self) -> u64 {
+                self.generation
+            }
+        }
+
+        let bus = GenerationBus {
+            ram: Ram::new(ADDI_X1_1.to_le_bytes().to_vec()),
+            generation: 0,
+        };
+        let mut cpu = Cpu::new(bus, Clock::new(), Pic::new());
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 1);
+
+        // The cached instruction is used until the bus reports a change
+        cpu.bus.ram.write(RvSize::Word, 0, ADDI_X1_2).unwrap();
+        cpu.write_pc(0);
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 1);
+
+        cpu.bus.generation += 1;
+        cpu.write_pc(0);
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        const ADDI_X1_1: u32 = 0x00108093;
+        const JAL_X0_MINUS_4: u32 = 0xffdff06f;
+
+        let program: Vec<u8> = [ADDI_X1_1, JAL_X0_MINUS_4]
+            .into_iter()
+            .flat_map(u32::to_le_bytes)
+            .collect();
+        let new_cpu = || Cpu::new(Ram::new(program.clone()), Clock::new(), Pic::new());
+
+        let mut cpu = new_cpu();
+        for _ in 0..6 {
+            cpu.step(None);
+        }
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 3);
+
+        let mut w = SnapshotWriter::new();
+        w.write(&cpu).unwrap();
+        let snapshot = w.into_bytes();
+
+        // Restore into a fresh CPU and verify both continue identically
+        let mut forked = new_cpu();
+        let mut r = SnapshotReader::new(&snapshot);
+        r.read(&mut forked).unwrap();
+        assert!(r.is_empty());
+        assert_eq!(forked.read_pc(), cpu.read_pc());
+        assert_eq!(forked.clock.now(), cpu.clock.now());
+
+        for _ in 0..4 {
+            cpu.step(None);
+            forked.step(None);
+        }
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 5);
+        assert_eq!(forked.read_xreg(XReg::X1).unwrap(), 5);
+        assert_eq!(forked.read_pc(), cpu.read_pc());
+
+        // Truncated snapshots are rejected
+        let mut r = SnapshotReader::new(&snapshot[..snapshot.len() - 1]);
+        assert_eq!(
+            r.read(&mut new_cpu()).err(),
+            Some(SnapshotError::UnexpectedEof)
+        );
+    }
+
+    /// Returns a bus with 1 KiB of NOPs at 0 and 1 KiB of RAM at 0x1000
+    fn interrupt_test_bus(program: &[u32]) -> DynamicBus {
+        const RV32_NO_OP: u32 = 0x00000013;
+
+        let mut rom: Vec<u32> = program.to_vec();
+        rom.resize(256, RV32_NO_OP);
+        let mut bus = DynamicBus::new();
+        bus.attach_dev(
+            "ROM",
+            0..=0x3ff,
+            Box::new(Rom::new(
+                rom.into_iter().flat_map(u32::to_le_bytes).collect(),
+            )),
+        )
+        .unwrap();
+        bus.attach_dev("RAM", 0x1000..=0x13ff, Box::new(Ram::new(vec![0; 0x400])))
+            .unwrap();
+        bus
+    }
+
+    fn enable_interrupts(cpu: &mut Cpu<DynamicBus>, mie: u32) {
+        cpu.write_csr(Csr::MIE, mie).unwrap();
+        let mut status = RvMStatus(cpu.read_csr(Csr::MSTATUS).unwrap());
+        status.set_mie(1);
+        cpu.write_csr(Csr::MSTATUS, status.0).unwrap();
+    }
+
+    #[test]
+    fn test_internal_timer_interrupt() {
+        let mut cpu = Cpu::new(interrupt_test_bus(&[]), Clock::new(), Pic::new());
+        cpu.write_csr(Csr::MTVEC, 0x200).unwrap();
+        cpu.write_csr(Csr::MITB0, 5).unwrap();
+        enable_interrupts(&mut cpu, MIP_MITIP0);
+
+        for _ in 0..4 {
+            assert_eq!(cpu.step(None), StepAction::Continue);
+        }
+        assert_eq!(cpu.read_pc(), 4 * 4);
+        assert_eq!(cpu.read_csr(Csr::MIP).unwrap(), 0);
+
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_pc(), 0x200);
+        assert_eq!(cpu.read_csr(Csr::MIP).unwrap(), MIP_MITIP0);
+        assert_eq!(cpu.read_csr(Csr::MCAUSE).unwrap(), 0x8000_001D);
+        assert_eq!(cpu.read_csr(Csr::MEPC).unwrap(), 4 * 4);
+        assert_eq!(RvMStatus(cpu.read_csr(Csr::MSTATUS).unwrap()).mie(), 0);
+
+        // The counter restarts after reaching the bound
+        cpu.step(None);
+        assert_eq!(cpu.read_csr(Csr::MITCNT0).unwrap(), 0);
+        assert_eq!(cpu.read_csr(Csr::MIP).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_vectored_timer_interrupt() {
+        let pic = Pic::new();
+        let timer_irq = pic.register_timer_irq();
+        let mut cpu = Cpu::new(interrupt_test_bus(&[]), Clock::new(), pic);
+        cpu.write_csr(Csr::MTVEC, 0x100 | 1).unwrap();
+        enable_interrupts(&mut cpu, MIP_MTIP);
+
+        cpu.step(None);
+        timer_irq.set_level(true);
+        cpu.step(None);
+        assert_eq!(cpu.read_pc(), 0x100 + 4 * 7);
+        assert_eq!(cpu.read_csr(Csr::MCAUSE).unwrap(), 0x8000_0007);
+    }
+
+    #[test]
+    fn test_external_interrupt() {
+        const HANDLER: u32 = 0x300;
+        const MEIVT: u32 = 0x1000;
+
+        let pic = Pic::new();
+        let irq = pic.register_irq(3);
+        let mut pic_regs = pic.mmio();
+        pic_regs.write(RvSize::Word, 3 * 4, 5).unwrap(); // meipl3
+        pic_regs.write(RvSize::Word, 0x2000 + 3 * 4, 1).unwrap(); // meie3
+
+        let mut cpu = Cpu::new(interrupt_test_bus(&[]), Clock::new(), pic);
+        cpu.write_bus(RvSize::Word, MEIVT + 3 * 4, HANDLER).unwrap();
+        cpu.write_csr(Csr::MEIVT, MEIVT).unwrap();
+        enable_interrupts(&mut cpu, MIP_MEIP);
+
+        // The priority must exceed the threshold
+        cpu.write_csr(Csr::MEIPT, 5).unwrap();
+        irq.set_level(true);
+        cpu.step(None);
+        assert_eq!(cpu.read_pc(), 4);
+        assert_eq!(cpu.read_csr(Csr::MIP).unwrap(), 0);
+
+        cpu.write_csr(Csr::MEIPT, 4).unwrap();
+        cpu.step(None);
+        assert_eq!(cpu.read_pc(), HANDLER);
+        assert_eq!(cpu.read_csr(Csr::MCAUSE).unwrap(), 0x8000_000B);
+        assert_eq!(cpu.read_csr(Csr::MEPC).unwrap(), 4);
+        assert_eq!(cpu.read_csr(Csr::MEIHAP).unwrap(), MEIVT + 3 * 4);
+        assert_eq!(cpu.read_csr(Csr::MEICIDPL).unwrap(), 5);
+
+        // Software capture of the claim id
+        irq.set_level(false);
+        cpu.write_csr(Csr::MEICPCT, 0).unwrap();
+        assert_eq!(cpu.read_csr(Csr::MEIHAP).unwrap(), MEIVT);
+        assert_eq!(cpu.read_csr(Csr::MEICIDPL).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_wfi() {
+        const WFI: u32 = 0x10500073;
+
+        let mut cpu = Cpu::new(interrupt_test_bus(&[WFI]), Clock::new(), Pic::new());
+        cpu.write_csr(Csr::MITB0, 10).unwrap();
+        // Keep counting while halted
+        cpu.write_csr(Csr::MITCTL0, 0b11).unwrap();
+        cpu.write_csr(Csr::MIE, MIP_MITIP0).unwrap();
+
+        cpu.step(None);
+        for _ in 0..8 {
+            assert_eq!(cpu.read_pc(), 4);
+            assert_eq!(cpu.step(None), StepAction::Continue);
+        }
+
+        // The pending interrupt wakes the core even though MSTATUS.MIE is
+        // clear, and execution resumes after the WFI.
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_pc(), 8);
+        assert_eq!(cpu.read_csr(Csr::MCAUSE).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_fault_skip_instr() {
+        const ADDI_X1_1: u32 = 0x00108093;
+
+        let mut bus = DynamicBus::new();
+        let ram = Ram::new(
+            [ADDI_X1_1; 3]
+                .iter()
+                .flat_map(|i| i.to_le_bytes())
+                .collect(),
+        );
+        bus.attach_dev("RAM", 0..=0xb, Box::new(ram)).unwrap();
+        let mut cpu = Cpu::new(bus, Clock::new(), Pic::new());
+
+        cpu.inject_fault(CpuFault::SkipInstr { pc: 4 });
+        for _ in 0..3 {
+            assert_eq!(cpu.step(None), StepAction::Continue);
+        }
+        assert_eq!(cpu.read_pc(), 12);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 2);
+        assert!(cpu.pending_faults().is_empty());
+
+        // The fault only triggers once
+        cpu.write_pc(4);
+        assert_eq!(cpu.step(None), StepAction::Continue);
+        assert_eq!(cpu.read_xreg(XReg::X1).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_fault_corrupt_read() {
+        let mut bus = DynamicBus::new();
+        let ram = Ram::new(vec![0; 8]);
+        bus.attach_dev("RAM", 0..=0x7, Box::new(ram)).unwrap();
+        let mut cpu = Cpu::new(bus, Clock::new(), Pic::new());
+        cpu.write_bus(RvSize::Word, 4, 0x1234_5678).unwrap();
+
+        cpu.inject_fault(CpuFault::CorruptRead {
+            addr: 4,
+            mask: 0x8000_0001,
+        });
+        assert_eq!(cpu.read_bus(RvSize::Word, 0).unwrap(), 0);
+        assert_eq!(cpu.pending_faults().len(), 1);
+        assert_eq!(cpu.read_bus(RvSize::Word, 4).unwrap(), 0x9234_5679);
+        assert_eq!(cpu.read_bus(RvSize::Word, 4).unwrap(), 0x1234_5678);
+        assert!(cpu.pending_faults().is_empty());
+    }
+
     pub fn count_executed(coverage: &CodeCoverage) -> usize {
         coverage.bit_vec.iter().filter(|&executed| executed).count()
     }
@@ -12,6 +12,7 @@ Abstract:
 
 --*/
 
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_emu_types::{RvAddr, RvData, RvException};
 
 /// Configuration & Status Register
@@ -76,6 +77,42 @@ impl Csr {
     /// Instruction Retired High Counter CSR
     pub const MINSTRETH: RvAddr = 0xB82;
 
+    /// Internal Timer Counter 0 CSR (VeeR-specific)
+    pub const MITCNT0: RvAddr = 0x7D2;
+
+    /// Internal Timer Bound 0 CSR (VeeR-specific)
+    pub const MITB0: RvAddr = 0x7D3;
+
+    /// Internal Timer Control 0 CSR (VeeR-specific)
+    pub const MITCTL0: RvAddr = 0x7D4;
+
+    /// Internal Timer Counter 1 CSR (VeeR-specific)
+    pub const MITCNT1: RvAddr = 0x7D5;
+
+    /// Internal Timer Bound 1 CSR (VeeR-specific)
+    pub const MITB1: RvAddr = 0x7D6;
+
+    /// Internal Timer Control 1 CSR (VeeR-specific)
+    pub const MITCTL1: RvAddr = 0x7D7;
+
+    /// External Interrupt Vector Table CSR (VeeR-specific)
+    pub const MEIVT: RvAddr = 0xBC8;
+
+    /// External Interrupt Priority Threshold CSR (VeeR-specific)
+    pub const MEIPT: RvAddr = 0xBC9;
+
+    /// External Interrupt Claim ID / Priority Level Capture Trigger CSR (VeeR-specific)
+    pub const MEICPCT: RvAddr = 0xBCA;
+
+    /// External Interrupt Claim ID's Priority Level CSR (VeeR-specific)
+    pub const MEICIDPL: RvAddr = 0xBCB;
+
+    /// External Interrupt Current Priority Level CSR (VeeR-specific)
+    pub const MEICURPL: RvAddr = 0xBCC;
+
+    /// External Interrupt Handler Address Pointer CSR (VeeR-specific)
+    pub const MEIHAP: RvAddr = 0xFC8;
+
     /// Create a new Configurations and Status register
     ///
     /// # Arguments
@@ -123,11 +160,17 @@ impl CsrFile {
         self.csrs[Csr::MEPC as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
         self.csrs[Csr::MCAUSE as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
         self.csrs[Csr::MTVAL as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
-        self.csrs[Csr::MIP as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
+        self.csrs[Csr::MIP as usize] = Csr::new(0x0000_0000, 0x0000_0000);
         self.csrs[Csr::MCYCLE as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
         self.csrs[Csr::MCYCLEH as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
         self.csrs[Csr::MINSTRET as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
         self.csrs[Csr::MINSTRETH as usize] = Csr::new(0x0000_0000, 0xFFFF_FFFF);
+        self.csrs[Csr::MEIVT as usize] = Csr::new(0x0000_0000, 0xFFFF_FC00);
+        self.csrs[Csr::MEIPT as usize] = Csr::new(0x0000_0000, 0x0000_000F);
+        self.csrs[Csr::MEICPCT as usize] = Csr::new(0x0000_0000, 0x0000_0000);
+        self.csrs[Csr::MEICIDPL as usize] = Csr::new(0x0000_0000, 0x0000_000F);
+        self.csrs[Csr::MEICURPL as usize] = Csr::new(0x0000_0000, 0x0000_000F);
+        self.csrs[Csr::MEIHAP as usize] = Csr::new(0x0000_0000, 0x0000_0000);
     }
 
     /// Read the specified configuration status register
@@ -176,6 +219,19 @@ impl CsrFile {
     }
 }
 
+impl Snapshot for CsrFile {
+    /// Only CSR values are saved; write masks are fixed at reset.
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.csrs.iter().try_for_each(|csr| w.write(&csr.val))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.csrs
+            .iter_mut()
+            .try_for_each(|csr| r.read(&mut csr.val))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
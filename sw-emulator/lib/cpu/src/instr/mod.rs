@@ -30,12 +30,14 @@ mod system;
 mod test_encoder;
 mod test_macros;
 
-use crate::cpu::{Cpu, InstrTracer, StepAction};
+use crate::cpu::{Cpu, CpuFault, InstrTracer, StepAction};
+use crate::instr_cache::{DecodedInstr, ExecFn};
 use crate::types::{RvInstr, RvInstr32, RvInstr32Opcode};
 use caliptra_emu_bus::Bus;
 use caliptra_emu_types::{RvException, RvSize};
 
 /// Instruction
+#[derive(Clone, Copy)]
 pub enum Instr {
     Compressed(u16),
     General(u32),
@@ -59,20 +61,51 @@ impl<TBus: Bus> Cpu<TBus> {
         self.is_execute_instr = true;
         self.watch_ptr_cfg.hit = None;
 
-        let instr = self.fetch()?;
+        let pc = self.read_pc();
+        self.instr_cache
+            .sync_generation(self.bus.instr_mem_generation());
+        let cached = self.instr_cache.get(pc);
+        let instr = match &cached {
+            Some(decoded) => decoded.instr,
+            None => self.fetch()?,
+        };
+        let skip = self.take_fault(|f| *f == CpuFault::SkipInstr { pc });
+        if skip.is_some() {
+            let len = match instr {
+                Instr::Compressed(_) => 2,
+                Instr::General(_) => 4,
+            };
+            self.write_pc(pc.wrapping_add(len));
+            self.is_execute_instr = false;
+            return Ok(StepAction::Continue);
+        }
+
         // Code coverage here.
-        self.code_coverage.log_execution(self.read_pc(), &instr);
+        self.code_coverage.log_execution(pc, &instr);
 
         match instr {
-            Instr::Compressed(instr) => {
-                self.set_next_pc(self.read_pc().wrapping_add(2));
-                self.exec_instr16(instr, instr_tracer)?;
+            Instr::Compressed(instr16) => {
+                self.set_next_pc(pc.wrapping_add(2));
+                if let Some(instr_tracer) = instr_tracer {
+                    instr_tracer(pc, RvInstr::Instr16(instr16))
+                }
             }
-            Instr::General(instr) => {
-                self.set_next_pc(self.read_pc().wrapping_add(4));
-                self.exec_instr32(instr, instr_tracer)?;
+            Instr::General(instr32) => {
+                self.set_next_pc(pc.wrapping_add(4));
+                if let Some(instr_tracer) = instr_tracer {
+                    instr_tracer(pc, RvInstr::Instr32(instr32))
+                }
             }
         }
+        let decoded = match cached {
+            Some(decoded) => decoded,
+            None => {
+                let decoded = Self::decode(instr)?;
+                self.instr_cache.insert(pc, decoded);
+                decoded
+            }
+        };
+        (decoded.exec)(self, decoded.instr32)?;
         self.write_pc(self.next_pc());
 
         self.is_execute_instr = false;
@@ -99,55 +132,38 @@ impl<TBus: Bus> Cpu<TBus> {
         }
     }
 
-    /// Execute a single 16-bit instruction `instr`, tracing instructions to
-    /// `instr_tracer` if it exists.
-    ///
-    /// # Error
-    ///
-    /// * `RvException` - Exception encountered during instruction execution
-    fn exec_instr16(
-        &mut self,
-        instr: u16,
-        instr_tracer: Option<&mut InstrTracer>,
-    ) -> Result<(), RvException> {
-        if let Some(instr_tracer) = instr_tracer {
-            instr_tracer(self.read_pc(), RvInstr::Instr16(instr))
-        }
-        self.exec_instr32(compression::decompress_instr(instr)?, None)
-    }
-
-    /// Execute single 32-bit instruction
+    /// Decode an instruction, decompressing it if necessary
     ///
     /// # Arguments
     ///
-    /// * `instr_tracer` - Instruction tracer
+    /// * `instr` - Instruction as fetched from memory
     ///
     /// # Error
     ///
-    /// * `RvException` - Exception encountered during instruction execution
-    fn exec_instr32(
-        &mut self,
-        instr: u32,
-        instr_tracer: Option<&mut InstrTracer>,
-    ) -> Result<(), RvException> {
-        if let Some(instr_tracer) = instr_tracer {
-            instr_tracer(self.read_pc(), RvInstr::Instr32(instr))
-        }
-
-        match RvInstr32(instr).opcode() {
-            RvInstr32Opcode::Load => self.exec_load_instr(instr)?,
-            RvInstr32Opcode::OpImm => self.exec_op_imm_instr(instr)?,
-            RvInstr32Opcode::Auipc => self.exec_auipc_instr(instr)?,
-            RvInstr32Opcode::Store => self.exec_store_instr(instr)?,
-            RvInstr32Opcode::Op => self.exec_op_instr(instr)?,
-            RvInstr32Opcode::Lui => self.exec_lui_instr(instr)?,
-            RvInstr32Opcode::Branch => self.exec_branch_instr(instr)?,
-            RvInstr32Opcode::Jalr => self.exec_jalr_instr(instr)?,
-            RvInstr32Opcode::Jal => self.exec_jal_instr(instr)?,
-            RvInstr32Opcode::System => self.exec_system_instr(instr)?,
-            RvInstr32Opcode::Fence => self.exec_fence_instr(instr)?,
-            _ => Err(RvException::illegal_instr(instr))?,
-        }
-        Ok(())
+    /// * `RvException` - Exception with cause `RvExceptionCause::IllegalInstr`
+    fn decode(instr: Instr) -> Result<DecodedInstr<TBus>, RvException> {
+        let instr32 = match instr {
+            Instr::Compressed(instr16) => compression::decompress_instr(instr16)?,
+            Instr::General(instr32) => instr32,
+        };
+        let exec: ExecFn<TBus> = match RvInstr32(instr32).opcode() {
+            RvInstr32Opcode::Load => Self::exec_load_instr,
+            RvInstr32Opcode::OpImm => Self::exec_op_imm_instr,
+            RvInstr32Opcode::Auipc => Self::exec_auipc_instr,
+            RvInstr32Opcode::Store => Self::exec_store_instr,
+            RvInstr32Opcode::Op => Self::exec_op_instr,
+            RvInstr32Opcode::Lui => Self::exec_lui_instr,
+            RvInstr32Opcode::Branch => Self::exec_branch_instr,
+            RvInstr32Opcode::Jalr => Self::exec_jalr_instr,
+            RvInstr32Opcode::Jal => Self::exec_jal_instr,
+            RvInstr32Opcode::System => Self::exec_system_instr,
+            RvInstr32Opcode::Fence => Self::exec_fence_instr,
+            _ => Err(RvException::illegal_instr(instr32))?,
+        };
+        Ok(DecodedInstr {
+            instr,
+            instr32,
+            exec,
+        })
     }
 }
@@ -49,6 +49,10 @@ impl<TBus: Bus> Cpu<TBus> {
                     self.set_next_pc(self.read_csr(Csr::MEPC)?);
                     Ok(())
                 }
+                RvInstr32SystemImm::Wfi => {
+                    self.wait_for_interrupt();
+                    Ok(())
+                }
                 _ => Err(RvException::illegal_instr(instr.0)),
             },
             RvInstr32SystemFunct3::Csrrw => {
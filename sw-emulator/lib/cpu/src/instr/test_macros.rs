@@ -466,11 +466,12 @@ mod test {
         ) => {{
             use caliptra_emu_bus::{Clock, DynamicBus, Ram, Rom};
             use $crate::cpu::Cpu;
+            use $crate::pic::Pic;
 
             let text_range = $text_addr..=u32::try_from($text_addr + $text.len() - 1).unwrap();
             let data_range = $data_addr..=u32::try_from($data_addr + $data.len() - 1).unwrap();
 
-            let mut cpu = Cpu::new(DynamicBus::new(), Clock::new());
+            let mut cpu = Cpu::new(DynamicBus::new(), Clock::new(), Pic::new());
             let rom = Rom::new($text.clone());
             cpu.bus
                 .attach_dev("ROM", text_range, Box::new(rom))
@@ -0,0 +1,218 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    instr_cache.rs
+
+Abstract:
+
+    File contains the implementation of the decoded instruction cache.
+
+--*/
+
+use crate::cpu::Cpu;
+use crate::instr::Instr;
+use caliptra_emu_bus::Bus;
+use caliptra_emu_types::{RvAddr, RvException};
+
+/// Handler that executes a decoded 32-bit instruction
+pub(crate) type ExecFn<TBus> = fn(&mut Cpu<TBus>, u32) -> Result<(), RvException>;
+
+/// Instruction fetched from memory along with its decoded operation
+pub(crate) struct DecodedInstr<TBus: Bus> {
+    /// Instruction as fetched from memory
+    pub instr: Instr,
+
+    /// Instruction to execute; compressed instructions are decompressed
+    pub instr32: u32,
+
+    /// Handler for the instruction's opcode
+    pub exec: ExecFn<TBus>,
+}
+
+// Derived Clone/Copy would require TBus: Copy
+impl<TBus: Bus> Clone for DecodedInstr<TBus> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<TBus: Bus> Copy for DecodedInstr<TBus> {}
+
+struct Entry<TBus: Bus> {
+    pc: RvAddr,
+    decoded: DecodedInstr<TBus>,
+}
+
+impl<TBus: Bus> Clone for Entry<TBus> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<TBus: Bus> Copy for Entry<TBus> {}
+
+/// Direct-mapped cache of decoded instructions keyed by program counter
+///
+/// Entries must be invalidated whenever the memory they were fetched from
+/// changes. Writes made by the CPU are handled by `Cpu::write_bus`; other
+/// changes are reported by the bus through `Bus::instr_mem_generation()`,
+/// or can be signalled with `Cpu::invalidate_instr_cache`.
+pub(crate) struct InstrCache<TBus: Bus> {
+    entries: Vec<Option<Entry<TBus>>>,
+    enabled: bool,
+
+    // The bus's instruction memory generation when the cache was last flushed
+    generation: u64,
+}
+
+impl<TBus: Bus> InstrCache<TBus> {
+    /// Number of cache entries. Must be a power of two.
+    const SIZE: usize = 64 * 1024;
+
+    pub fn new() -> Self {
+        Self {
+            entries: vec![None; Self::SIZE],
+            enabled: true,
+            generation: 0,
+        }
+    }
+
+    #[inline]
+    fn index(pc: RvAddr) -> usize {
+        (pc as usize >> 1) & (Self::SIZE - 1)
+    }
+
+    /// Lookup the decoded instruction at `pc`
+    #[inline]
+    pub fn get(&self, pc: RvAddr) -> Option<DecodedInstr<TBus>> {
+        match self.entries[Self::index(pc)] {
+            Some(entry) if entry.pc == pc => Some(entry.decoded),
+            _ => None,
+        }
+    }
+
+    /// Insert the decoded instruction at `pc`
+    #[inline]
+    pub fn insert(&mut self, pc: RvAddr, decoded: DecodedInstr<TBus>) {
+        if self.enabled {
+            self.entries[Self::index(pc)] = Some(Entry { pc, decoded });
+        }
+    }
+
+    /// Invalidate all instructions overlapping `len` bytes starting at `addr`
+    pub fn invalidate(&mut self, addr: RvAddr, len: u32) {
+        // A 32-bit instruction starting a halfword before `addr` overlaps it.
+        let start = addr.wrapping_sub(2) & !1;
+        let count = addr.wrapping_add(len).wrapping_sub(start).wrapping_add(1) / 2;
+        for i in 0..count {
+            let pc = start.wrapping_add(i * 2);
+            let entry = &mut self.entries[Self::index(pc)];
+            if matches!(entry, Some(e) if e.pc == pc) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// Invalidate all instructions
+    pub fn flush(&mut self) {
+        self.entries.fill(None);
+    }
+
+    /// Invalidate all instructions if the bus reports that instruction
+    /// memory changed since the last call
+    #[inline]
+    pub fn sync_generation(&mut self, generation: u64) {
+        if generation != self.generation {
+            self.generation = generation;
+            self.flush();
+        }
+    }
+
+    /// Enable or disable caching. Disabling the cache flushes it.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use caliptra_emu_bus::testing::FakeBus;
+
+    fn nop(_: &mut Cpu<FakeBus>, _: u32) -> Result<(), RvException> {
+        Ok(())
+    }
+
+    fn decoded(instr32: u32) -> DecodedInstr<FakeBus> {
+        DecodedInstr {
+            instr: Instr::General(instr32),
+            instr32,
+            exec: nop,
+        }
+    }
+
+    #[test]
+    fn test_get_insert() {
+        let mut cache = InstrCache::<FakeBus>::new();
+        assert!(cache.get(0x100).is_none());
+
+        cache.insert(0x100, decoded(0x13));
+        assert_eq!(cache.get(0x100).unwrap().instr32, 0x13);
+
+        // An aliasing pc must not hit
+        let alias = 0x100 + (InstrCache::<FakeBus>::SIZE as u32 * 2);
+        assert!(cache.get(alias).is_none());
+        cache.insert(alias, decoded(0x33));
+        assert!(cache.get(0x100).is_none());
+        assert_eq!(cache.get(alias).unwrap().instr32, 0x33);
+    }
+
+    #[test]
+    fn test_invalidate() {
+        let mut cache = InstrCache::<FakeBus>::new();
+        for pc in (0xfc..0x10c).step_by(2) {
+            cache.insert(pc, decoded(pc));
+        }
+
+        // Word write at 0x100 touches instructions starting at 0xfe..=0x102
+        cache.invalidate(0x100, 4);
+        assert!(cache.get(0xfc).is_some());
+        assert!(cache.get(0xfe).is_none());
+        assert!(cache.get(0x100).is_none());
+        assert!(cache.get(0x102).is_none());
+        assert!(cache.get(0x104).is_some());
+
+        // Byte write at 0x107 touches instructions starting at 0x104..=0x106
+        cache.invalidate(0x107, 1);
+        assert!(cache.get(0x104).is_none());
+        assert!(cache.get(0x106).is_none());
+        assert!(cache.get(0x108).is_some());
+
+        cache.flush();
+        assert!(cache.get(0x108).is_none());
+    }
+
+    #[test]
+    fn test_sync_generation() {
+        let mut cache = InstrCache::<FakeBus>::new();
+        cache.insert(0x100, decoded(0x13));
+        cache.sync_generation(0);
+        assert!(cache.get(0x100).is_some());
+        cache.sync_generation(1);
+        assert!(cache.get(0x100).is_none());
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut cache = InstrCache::<FakeBus>::new();
+        cache.insert(0x100, decoded(0x13));
+        cache.set_enabled(false);
+        assert!(cache.get(0x100).is_none());
+        cache.insert(0x100, decoded(0x13));
+        assert!(cache.get(0x100).is_none());
+    }
+}
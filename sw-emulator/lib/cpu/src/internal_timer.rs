@@ -0,0 +1,154 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    internal_timer.rs
+
+Abstract:
+
+    File contains implementation of the VeeR EL2 internal timers.
+
+--*/
+
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
+
+/// MITCTL fields
+const MITCTL_ENABLE: u32 = 1 << 0;
+const MITCTL_HALT_EN: u32 = 1 << 1;
+const MITCTL_PAUSE_EN: u32 = 1 << 2;
+const MITCTL_CASCADE: u32 = 1 << 3;
+
+/// One of the two VeeR EL2 internal timers (MITCNTn/MITBn/MITCTLn).
+///
+/// The counter increments every core clock cycle while enabled. When it
+/// reaches the bound the timer signals its interrupt, and the counter
+/// restarts from zero on the next increment.
+#[derive(Clone, Copy)]
+pub(crate) struct InternalTimer {
+    /// Counter (MITCNTn)
+    pub(crate) count: u32,
+
+    /// Bound (MITBn)
+    pub(crate) bound: u32,
+
+    /// Control (MITCTLn)
+    ctl: u32,
+}
+
+impl InternalTimer {
+    pub(crate) fn new() -> Self {
+        Self {
+            count: 0,
+            bound: u32::MAX,
+            ctl: MITCTL_ENABLE,
+        }
+    }
+
+    pub(crate) fn ctl(&self) -> u32 {
+        self.ctl
+    }
+
+    /// Write MITCTLn. Only timer 1 may be cascaded from timer 0.
+    pub(crate) fn set_ctl(&mut self, val: u32, cascade_allowed: bool) {
+        let mut mask = MITCTL_ENABLE | MITCTL_HALT_EN | MITCTL_PAUSE_EN;
+        if cascade_allowed {
+            mask |= MITCTL_CASCADE;
+        }
+        self.ctl = val & mask;
+    }
+
+    /// Whether the timer is cascaded, incrementing only when timer 0 wraps
+    pub(crate) fn cascaded(&self) -> bool {
+        self.ctl & MITCTL_CASCADE != 0
+    }
+
+    /// Advance the timer by one tick.
+    ///
+    /// # Arguments
+    ///
+    /// * `halted` - The core is halted waiting for an interrupt
+    ///
+    /// # Return
+    ///
+    /// * `bool` - Whether the counter restarted from zero
+    pub(crate) fn tick(&mut self, halted: bool) -> bool {
+        if self.ctl & MITCTL_ENABLE == 0 {
+            return false;
+        }
+        // The pause state (mpmc) is not emulated; a paused core is treated
+        // the same as a halted one.
+        if halted && self.ctl & (MITCTL_HALT_EN | MITCTL_PAUSE_EN) == 0 {
+            return false;
+        }
+        if self.count >= self.bound {
+            self.count = 0;
+            true
+        } else {
+            self.count += 1;
+            false
+        }
+    }
+
+    /// Whether the timer is signaling its interrupt
+    pub(crate) fn pending(&self) -> bool {
+        self.ctl & MITCTL_ENABLE != 0 && self.count >= self.bound
+    }
+}
+
+impl Snapshot for InternalTimer {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.count)?;
+        w.write(&self.bound)?;
+        w.write(&self.ctl)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.count)?;
+        r.read(&mut self.bound)?;
+        r.read(&mut self.ctl)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick() {
+        let mut timer = InternalTimer::new();
+        timer.bound = 2;
+        assert!(!timer.tick(false));
+        assert!(!timer.pending());
+        assert!(!timer.tick(false));
+        assert!(timer.pending());
+        assert!(timer.tick(false));
+        assert_eq!(timer.count, 0);
+        assert!(!timer.pending());
+    }
+
+    #[test]
+    fn test_halted() {
+        let mut timer = InternalTimer::new();
+        timer.tick(true);
+        assert_eq!(timer.count, 0);
+
+        timer.set_ctl(MITCTL_ENABLE | MITCTL_HALT_EN, false);
+        timer.tick(true);
+        assert_eq!(timer.count, 1);
+
+        timer.set_ctl(0, false);
+        timer.tick(false);
+        assert_eq!(timer.count, 1);
+    }
+
+    #[test]
+    fn test_cascade() {
+        let mut timer = InternalTimer::new();
+        timer.set_ctl(MITCTL_ENABLE | MITCTL_CASCADE, false);
+        assert!(!timer.cascaded());
+        timer.set_ctl(MITCTL_ENABLE | MITCTL_CASCADE, true);
+        assert!(timer.cascaded());
+    }
+}
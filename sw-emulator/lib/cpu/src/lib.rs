@@ -15,11 +15,15 @@ Abstract:
 pub mod cpu;
 mod csr_file;
 mod instr;
+mod instr_cache;
+mod internal_timer;
+mod pic;
 mod types;
 pub mod xreg_file;
 
 pub use cpu::StepAction;
 pub use cpu::WatchPtrHit;
 pub use cpu::WatchPtrKind;
-pub use cpu::{Cpu, InstrTracer};
+pub use cpu::{Cpu, CpuFault, InstrTracer};
+pub use pic::{Irq, Pic, PicMmio};
 pub use types::RvInstr;
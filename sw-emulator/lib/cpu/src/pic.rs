@@ -0,0 +1,428 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    pic.rs
+
+Abstract:
+
+    File contains implementation of the VeeR EL2 Programmable Interrupt
+    Controller (PIC).
+
+--*/
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use caliptra_emu_bus::{Bus, BusError, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
+use caliptra_emu_types::{RvAddr, RvData, RvSize};
+
+/// Number of external interrupt sources, including the reserved source 0.
+const SOURCE_COUNT: usize = 32;
+
+/// Lowest and highest interrupt priority levels
+const PRIORITY_MIN: u8 = 0;
+const PRIORITY_MAX: u8 = 15;
+
+/// Memory-mapped register offsets from the PIC base address
+const MEIPL_OFFSET: RvAddr = 0x0000;
+const MEIP_OFFSET: RvAddr = 0x1000;
+const MEIE_OFFSET: RvAddr = 0x2000;
+const MPICCFG_OFFSET: RvAddr = 0x3000;
+const MEIGWCTRL_OFFSET: RvAddr = 0x4000;
+const MEIGWCLR_OFFSET: RvAddr = 0x5000;
+
+/// MEIGWCTRL fields
+const MEIGWCTRL_POLARITY: u32 = 1 << 0;
+const MEIGWCTRL_TYPE_EDGE: u32 = 1 << 1;
+
+/// MPICCFG fields
+const MPICCFG_PRIORD_REVERSE: u32 = 1 << 0;
+
+#[derive(Clone, Copy, Default)]
+struct PicRegs {
+    /// External interrupt priority level, one per source
+    meipl: [u32; SOURCE_COUNT],
+
+    /// External interrupt enable, one per source
+    meie: [u32; SOURCE_COUNT],
+
+    /// Gateway configuration, one per source
+    meigwctrl: [u32; SOURCE_COUNT],
+
+    /// PIC configuration
+    mpiccfg: u32,
+}
+
+struct PicImpl {
+    regs: RefCell<PicRegs>,
+
+    /// Input level of each interrupt source, as driven by the peripherals.
+    levels: Cell<u32>,
+
+    /// Interrupts latched by edge-triggered gateways.
+    latched: Cell<u32>,
+
+    /// The core's timer_int input.
+    timer_int: Cell<bool>,
+}
+
+impl PicImpl {
+    fn gateway_output(&self) -> u32 {
+        let regs = self.regs.borrow();
+        let levels = self.levels.get();
+        let latched = self.latched.get();
+        (1..SOURCE_COUNT).fold(0, |meip, id| {
+            let ctrl = regs.meigwctrl[id];
+            let pending = if ctrl & MEIGWCTRL_TYPE_EDGE != 0 {
+                latched & (1 << id) != 0
+            } else {
+                let level = levels & (1 << id) != 0;
+                level != (ctrl & MEIGWCTRL_POLARITY != 0)
+            };
+            meip | (u32::from(pending) << id)
+        })
+    }
+
+    fn set_level(&self, id: usize, level: bool) {
+        let mask = 1 << id;
+        let old_level = self.levels.get() & mask != 0;
+        if level {
+            self.levels.set(self.levels.get() | mask);
+        } else {
+            self.levels.set(self.levels.get() & !mask);
+        }
+
+        // Edge-triggered gateways latch the active edge until software
+        // clears them through MEIGWCLR.
+        let ctrl = self.regs.borrow().meigwctrl[id];
+        let active_low = ctrl & MEIGWCTRL_POLARITY != 0;
+        if ctrl & MEIGWCTRL_TYPE_EDGE != 0 && old_level != level && level != active_low {
+            self.latched.set(self.latched.get() | mask);
+        }
+    }
+}
+
+/// The VeeR EL2 Programmable Interrupt Controller.
+///
+/// The PIC is part of the core, but its configuration registers are memory
+/// mapped: the CPU consults the `Pic` to take external interrupts, while
+/// [`Pic::mmio`] exposes the registers to be placed on the bus. Peripherals
+/// drive their interrupt lines through an [`Irq`] handle.
+///
+/// The core's `timer_int` input is routed through the PIC as well, as it is
+/// driven by a peripheral on Caliptra.
+#[derive(Clone)]
+pub struct Pic {
+    pic: Rc<PicImpl>,
+}
+
+impl Default for Pic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pic {
+    /// Create a new PIC with all sources disabled.
+    pub fn new() -> Self {
+        Self {
+            pic: Rc::new(PicImpl {
+                regs: RefCell::new(PicRegs::default()),
+                levels: Cell::new(0),
+                latched: Cell::new(0),
+                timer_int: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Returns a handle used to drive external interrupt source `id`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `id` is 0 or greater than 31.
+    pub fn register_irq(&self, id: u8) -> Irq {
+        assert!(
+            (1..SOURCE_COUNT).contains(&usize::from(id)),
+            "PIC interrupt source {id} does not exist"
+        );
+        Irq {
+            pic: self.pic.clone(),
+            line: IrqLine::External(id),
+        }
+    }
+
+    /// Returns a handle used to drive the core's `timer_int` input.
+    pub fn register_timer_irq(&self) -> Irq {
+        Irq {
+            pic: self.pic.clone(),
+            line: IrqLine::Timer,
+        }
+    }
+
+    /// Returns the memory-mapped PIC registers.
+    pub fn mmio(&self) -> PicMmio {
+        PicMmio {
+            pic: self.pic.clone(),
+        }
+    }
+
+    /// Returns the enabled external interrupt with the highest priority, as
+    /// `(id, priority)`. Ties are broken in favor of the lowest id.
+    ///
+    /// Priorities are normalized so that a higher number is more urgent even
+    /// when MPICCFG selects the reverse priority order.
+    pub(crate) fn highest_priority_irq(&self) -> Option<(u8, u8)> {
+        let meip = self.pic.gateway_output();
+        let regs = self.pic.regs.borrow();
+        let mut best: Option<(u8, u8)> = None;
+        for id in 1..SOURCE_COUNT {
+            if meip & (1 << id) == 0 || regs.meie[id] & 1 == 0 {
+                continue;
+            }
+            let priority = self.normalize_priority(regs.meipl[id] as u8);
+            if priority == PRIORITY_MIN {
+                continue;
+            }
+            if best.map_or(true, |(_, best_priority)| priority > best_priority) {
+                best = Some((id as u8, priority));
+            }
+        }
+        best
+    }
+
+    /// Converts between the software-visible priority (MEIPL, MEIPT,
+    /// MEICIDPL, MEICURPL) and the normalized priority, where 0 never
+    /// interrupts and 15 is the most urgent.
+    pub(crate) fn normalize_priority(&self, priority: u8) -> u8 {
+        let priority = priority & PRIORITY_MAX;
+        if self.pic.regs.borrow().mpiccfg & MPICCFG_PRIORD_REVERSE != 0 {
+            PRIORITY_MAX - priority
+        } else {
+            priority
+        }
+    }
+
+    /// Returns the level of the core's `timer_int` input.
+    pub(crate) fn timer_int(&self) -> bool {
+        self.pic.timer_int.get()
+    }
+}
+
+impl Snapshot for Pic {
+    /// Input levels are saved along with the registers, as peripherals only
+    /// drive their lines when their interrupt state changes.
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        let regs = self.pic.regs.borrow();
+        w.write(&regs.meipl)?;
+        w.write(&regs.meie)?;
+        w.write(&regs.meigwctrl)?;
+        w.write(&regs.mpiccfg)?;
+        w.write(&self.pic.levels.get())?;
+        w.write(&self.pic.latched.get())?;
+        w.write(&self.pic.timer_int.get())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        let mut regs = self.pic.regs.borrow_mut();
+        r.read(&mut regs.meipl)?;
+        r.read(&mut regs.meie)?;
+        r.read(&mut regs.meigwctrl)?;
+        r.read(&mut regs.mpiccfg)?;
+        self.pic.levels.set(r.read_value()?);
+        self.pic.latched.set(r.read_value()?);
+        self.pic.timer_int.set(r.read_value()?);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum IrqLine {
+    External(u8),
+    Timer,
+}
+
+/// An interrupt line into the PIC, held by the peripheral driving it.
+#[derive(Clone)]
+pub struct Irq {
+    pic: Rc<PicImpl>,
+    line: IrqLine,
+}
+
+impl Irq {
+    /// Drive the interrupt line high (`true`) or low (`false`).
+    pub fn set_level(&self, level: bool) {
+        match self.line {
+            IrqLine::External(id) => self.pic.set_level(usize::from(id), level),
+            IrqLine::Timer => self.pic.timer_int.set(level),
+        }
+    }
+
+    /// Returns the level the line is currently driven at.
+    pub fn level(&self) -> bool {
+        match self.line {
+            IrqLine::External(id) => self.pic.levels.get() & (1 << id) != 0,
+            IrqLine::Timer => self.pic.timer_int.get(),
+        }
+    }
+}
+
+/// Memory-mapped PIC registers
+///
+/// Only word accesses are supported, as on VeeR.
+pub struct PicMmio {
+    pic: Rc<PicImpl>,
+}
+
+impl PicMmio {
+    /// Returns the source id for a per-source register at `addr` inside the
+    /// register array starting at `base`.
+    fn source_id(addr: RvAddr, base: RvAddr) -> Option<usize> {
+        let id = ((addr - base) / 4) as usize;
+        (id < SOURCE_COUNT).then_some(id)
+    }
+}
+
+impl Bus for PicMmio {
+    fn read(&mut self, size: RvSize, addr: RvAddr) -> Result<RvData, BusError> {
+        if size != RvSize::Word || addr & 0b11 != 0 {
+            return Err(BusError::LoadAccessFault);
+        }
+        let regs = self.pic.regs.borrow();
+        match addr & 0xf000 {
+            MEIPL_OFFSET => Ok(Self::source_id(addr, MEIPL_OFFSET).map_or(0, |id| regs.meipl[id])),
+            MEIP_OFFSET if addr == MEIP_OFFSET => {
+                drop(regs);
+                Ok(self.pic.gateway_output())
+            }
+            MEIE_OFFSET => Ok(Self::source_id(addr, MEIE_OFFSET).map_or(0, |id| regs.meie[id])),
+            MPICCFG_OFFSET if addr == MPICCFG_OFFSET => Ok(regs.mpiccfg),
+            MEIGWCTRL_OFFSET => {
+                Ok(Self::source_id(addr, MEIGWCTRL_OFFSET).map_or(0, |id| regs.meigwctrl[id]))
+            }
+            MEIGWCLR_OFFSET => Ok(0),
+            _ => Err(BusError::LoadAccessFault),
+        }
+    }
+
+    fn write(&mut self, size: RvSize, addr: RvAddr, val: RvData) -> Result<(), BusError> {
+        if size != RvSize::Word || addr & 0b11 != 0 {
+            return Err(BusError::StoreAccessFault);
+        }
+        let mut regs = self.pic.regs.borrow_mut();
+        // Source 0 is reserved; its registers are hardwired to 0.
+        match addr & 0xf000 {
+            MEIPL_OFFSET => {
+                if let Some(id @ 1..) = Self::source_id(addr, MEIPL_OFFSET) {
+                    regs.meipl[id] = val & u32::from(PRIORITY_MAX);
+                }
+            }
+            MEIP_OFFSET if addr == MEIP_OFFSET => {}
+            MEIE_OFFSET => {
+                if let Some(id @ 1..) = Self::source_id(addr, MEIE_OFFSET) {
+                    regs.meie[id] = val & 1;
+                }
+            }
+            MPICCFG_OFFSET if addr == MPICCFG_OFFSET => regs.mpiccfg = val & MPICCFG_PRIORD_REVERSE,
+            MEIGWCTRL_OFFSET => {
+                if let Some(id @ 1..) = Self::source_id(addr, MEIGWCTRL_OFFSET) {
+                    regs.meigwctrl[id] = val & (MEIGWCTRL_POLARITY | MEIGWCTRL_TYPE_EDGE);
+                }
+            }
+            MEIGWCLR_OFFSET => {
+                if let Some(id @ 1..) = Self::source_id(addr, MEIGWCLR_OFFSET) {
+                    self.pic.latched.set(self.pic.latched.get() & !(1 << id));
+                }
+            }
+            _ => return Err(BusError::StoreAccessFault),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configure(mmio: &mut PicMmio, id: u32, priority: u32, gwctrl: u32) {
+        mmio.write(RvSize::Word, MEIPL_OFFSET + id * 4, priority)
+            .unwrap();
+        mmio.write(RvSize::Word, MEIGWCTRL_OFFSET + id * 4, gwctrl)
+            .unwrap();
+        mmio.write(RvSize::Word, MEIE_OFFSET + id * 4, 1).unwrap();
+    }
+
+    #[test]
+    fn test_level_triggered() {
+        let pic = Pic::new();
+        let mut mmio = pic.mmio();
+        let irq = pic.register_irq(5);
+        configure(&mut mmio, 5, 3, 0);
+
+        assert_eq!(pic.highest_priority_irq(), None);
+        irq.set_level(true);
+        assert_eq!(mmio.read(RvSize::Word, MEIP_OFFSET).unwrap(), 1 << 5);
+        assert_eq!(pic.highest_priority_irq(), Some((5, 3)));
+        irq.set_level(false);
+        assert_eq!(pic.highest_priority_irq(), None);
+
+        // Active-low gateway
+        configure(&mut mmio, 5, 3, MEIGWCTRL_POLARITY);
+        assert_eq!(pic.highest_priority_irq(), Some((5, 3)));
+    }
+
+    #[test]
+    fn test_edge_triggered() {
+        let pic = Pic::new();
+        let mut mmio = pic.mmio();
+        let irq = pic.register_irq(2);
+        configure(&mut mmio, 2, 1, MEIGWCTRL_TYPE_EDGE);
+
+        irq.set_level(true);
+        irq.set_level(false);
+        assert_eq!(pic.highest_priority_irq(), Some((2, 1)));
+
+        mmio.write(RvSize::Word, MEIGWCLR_OFFSET + 2 * 4, 0)
+            .unwrap();
+        assert_eq!(pic.highest_priority_irq(), None);
+    }
+
+    #[test]
+    fn test_priority() {
+        let pic = Pic::new();
+        let mut mmio = pic.mmio();
+        let irqs: Vec<_> = (1..=3).map(|id| pic.register_irq(id)).collect();
+        configure(&mut mmio, 1, 2, 0);
+        configure(&mut mmio, 2, 7, 0);
+        configure(&mut mmio, 3, 7, 0);
+        irqs.iter().for_each(|irq| irq.set_level(true));
+
+        assert_eq!(pic.highest_priority_irq(), Some((2, 7)));
+
+        // Disabled sources and sources at priority 0 never interrupt.
+        mmio.write(RvSize::Word, MEIE_OFFSET + 2 * 4, 0).unwrap();
+        mmio.write(RvSize::Word, MEIPL_OFFSET + 3 * 4, 0).unwrap();
+        assert_eq!(pic.highest_priority_irq(), Some((1, 2)));
+
+        // In reverse priority order, 0 is the most urgent level.
+        mmio.write(RvSize::Word, MPICCFG_OFFSET, MPICCFG_PRIORD_REVERSE)
+            .unwrap();
+        assert_eq!(pic.highest_priority_irq(), Some((3, 15)));
+    }
+
+    #[test]
+    fn test_source_zero_reserved() {
+        let pic = Pic::new();
+        let mut mmio = pic.mmio();
+        mmio.write(RvSize::Word, MEIPL_OFFSET, 5).unwrap();
+        assert_eq!(mmio.read(RvSize::Word, MEIPL_OFFSET).unwrap(), 0);
+        assert_eq!(
+            mmio.read(RvSize::Byte, MEIPL_OFFSET + 4).err(),
+            Some(BusError::LoadAccessFault)
+        );
+    }
+}
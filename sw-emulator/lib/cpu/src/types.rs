@@ -389,6 +389,9 @@ emu_enum! {
 
         /// Mret
         Mret = 0b0011_0000_0010,
+
+        /// Wait for interrupt
+        Wfi = 0b0001_0000_0101,
     };
     Invalid
 }
@@ -12,6 +12,7 @@ Abstract:
 
 --*/
 
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_emu_types::{emu_enum, RvAddr, RvData, RvException};
 
 emu_enum!(
@@ -134,6 +135,16 @@ impl XRegFile {
         }
     }
 }
+impl Snapshot for XRegFile {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.reg)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.reg)
+    }
+}
+
 impl Default for XRegFile {
     fn default() -> Self {
         Self::new()
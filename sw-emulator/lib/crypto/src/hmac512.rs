@@ -13,6 +13,7 @@ Abstract:
 --*/
 
 use crate::{helpers::EndianessTransform, Sha512, Sha512Mode};
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 
 /// HMAC-512 Mode
 #[derive(Debug, Copy, Clone)]
@@ -184,6 +185,28 @@ impl<const KEY_SIZE: usize> Hmac512<KEY_SIZE> {
     }
 }
 
+impl<const KEY_SIZE: usize> Snapshot for Hmac512<KEY_SIZE> {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.hash1)?;
+        w.write(&self.hash2)?;
+        w.write(&Sha512Mode::from(self.mode))?;
+        w.write(&self.opad)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.hash1)?;
+        r.read(&mut self.hash2)?;
+        self.mode = match r.read_value::<u32>()?.try_into() {
+            Ok(Sha512Mode::Sha224) => Hmac512Mode::Sha224,
+            Ok(Sha512Mode::Sha256) => Hmac512Mode::Sha256,
+            Ok(Sha512Mode::Sha384) => Hmac512Mode::Sha384,
+            Ok(Sha512Mode::Sha512) => Hmac512Mode::Sha512,
+            Err(()) => return Err(SnapshotError::InvalidData("Hmac512Mode")),
+        };
+        r.read(&mut self.opad)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -13,6 +13,7 @@ Abstract:
 --*/
 
 use crate::helpers::EndianessTransform;
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use sha2::digest::block_buffer::Block;
 use sha2::digest::consts::U64;
 
@@ -125,6 +126,23 @@ impl Sha256 {
     }
 }
 
+impl Snapshot for Sha256 {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.hash)?;
+        w.write(&matches!(self.mode, Sha256Mode::Sha256))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.hash)?;
+        self.mode = if r.read_value()? {
+            Sha256Mode::Sha256
+        } else {
+            Sha256Mode::Sha224
+        };
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
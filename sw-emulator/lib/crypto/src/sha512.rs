@@ -13,6 +13,7 @@ Abstract:
 --*/
 
 use crate::helpers::EndianessTransform;
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use sha2::digest::block_buffer::Block;
 use sha2::digest::consts::U128;
 
@@ -32,6 +33,35 @@ impl From<Sha512Mode> for u32 {
     }
 }
 
+impl TryFrom<u32> for Sha512Mode {
+    type Error = ();
+
+    /// Performs the conversion.
+    fn try_from(val: u32) -> Result<Self, Self::Error> {
+        match val {
+            0 => Ok(Sha512Mode::Sha224),
+            1 => Ok(Sha512Mode::Sha256),
+            2 => Ok(Sha512Mode::Sha384),
+            3 => Ok(Sha512Mode::Sha512),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Snapshot for Sha512Mode {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&u32::from(*self))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        *self = r
+            .read_value::<u32>()?
+            .try_into()
+            .map_err(|_| SnapshotError::InvalidData("Sha512Mode"))?;
+        Ok(())
+    }
+}
+
 /// SHA-512
 pub struct Sha512 {
     /// Hash
@@ -143,7 +173,9 @@ impl Sha512 {
     pub fn update_bytes(&mut self, bytes: &[u8]) {
         self.partial_block.extend_from_slice(bytes);
 
-        while self.partial_block.len() >= Self::BLOCK_SIZE {
+        // Hold back the last full block until finalize(), as it may
+        // contain padding bytes past the end of the message.
+        while self.partial_block.len() > Self::BLOCK_SIZE {
             // Safe to unwrap becasue slice is guaranteed to be correct size
             self.partial_block[..Self::BLOCK_SIZE].to_little_endian();
             self.update(&self.partial_block[..Self::BLOCK_SIZE].try_into().unwrap());
@@ -157,7 +189,7 @@ impl Sha512 {
         // TODO: What to do if dlen is less than the blocks we've already processed?
         let bytes_of_blocks = self.blocks_processed * Self::BLOCK_SIZE;
         let partial = if (dlen as usize) < bytes_of_blocks + self.partial_block.len() {
-            (dlen as usize) - bytes_of_blocks
+            (dlen as usize).saturating_sub(bytes_of_blocks)
         } else {
             self.partial_block.len()
         };
@@ -166,7 +198,8 @@ impl Sha512 {
         self.partial_block.resize(partial, 0);
 
         self.partial_block.push(0b1000_0000);
-        let zeros: usize = Self::BLOCK_SIZE - ((msg_len + 1 + 16) % 128);
+        let zeros: usize =
+            (Self::BLOCK_SIZE - (msg_len + 1 + 16) % Self::BLOCK_SIZE) % Self::BLOCK_SIZE;
         self.partial_block.extend_from_slice(&vec![0u8; zeros]);
 
         // Add bit length of hashed data
@@ -230,6 +263,22 @@ impl Sha512 {
     }
 }
 
+impl Snapshot for Sha512 {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.hash)?;
+        w.write(&self.mode)?;
+        w.write(&self.partial_block)?;
+        w.write(&self.blocks_processed)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.hash)?;
+        r.read(&mut self.mode)?;
+        r.read(&mut self.partial_block)?;
+        r.read(&mut self.blocks_processed)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -330,4 +379,29 @@ mod tests {
 
         assert_eq!(&hash, &expected);
     }
+
+    #[test]
+    fn test_sha384_update_bytes() {
+        use sha2::Digest;
+
+        // Stream each message rounded up to whole words, as the accelerator
+        // does, and finalize with the exact length. Lengths of 111 mod 128
+        // leave no room for padding in the last block.
+        let data: Vec<u8> = (0..=300u32).map(|i| (i * 7) as u8).collect();
+        for len in 0..data.len() - 4 {
+            let mut sha = Sha512::new(Sha512Mode::Sha384);
+            sha.update_bytes(&data[..(len + 3) / 4 * 4]);
+            sha.finalize(len as u32);
+
+            let mut hash = [0u8; 48];
+            sha.copy_hash(&mut hash);
+            hash.to_little_endian();
+
+            assert_eq!(
+                hash[..],
+                sha2::Sha384::digest(&data[..len])[..],
+                "len {len}"
+            );
+        }
+    }
 }
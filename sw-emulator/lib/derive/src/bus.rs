@@ -98,6 +98,9 @@ pub fn derive_bus(input: TokenStream) -> TokenStream {
                 #(self.#field_idents.update_reset();)*
                 #self_update_reset_tokens
             }
+            fn instr_mem_generation(&self) -> u64 {
+                0u64 #(.wrapping_add(self.#field_idents.instr_mem_generation()))*
+            }
 
         }
     }
@@ -795,6 +798,18 @@ mod tests {
                         self.i2c2.update_reset();
                         self.spi0.update_reset();
                     }
+                    fn instr_mem_generation(&self) -> u64 {
+                        0u64
+                            .wrapping_add(self.rom.instr_mem_generation())
+                            .wrapping_add(self.sram.instr_mem_generation())
+                            .wrapping_add(self.dram.instr_mem_generation())
+                            .wrapping_add(self.uart0.instr_mem_generation())
+                            .wrapping_add(self.uart1.instr_mem_generation())
+                            .wrapping_add(self.i2c0.instr_mem_generation())
+                            .wrapping_add(self.i2c1.instr_mem_generation())
+                            .wrapping_add(self.i2c2.instr_mem_generation())
+                            .wrapping_add(self.spi0.instr_mem_generation())
+                    }
                 }
             }.to_string()
         );
@@ -821,6 +836,9 @@ mod tests {
                     }
                     fn update_reset(&mut self) {
                     }
+                    fn instr_mem_generation(&self) -> u64 {
+                        0u64
+                    }
                 }
             }.to_string()
         );
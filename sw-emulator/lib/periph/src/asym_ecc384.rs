@@ -13,11 +13,17 @@ Abstract:
 --*/
 
 use crate::helpers::{bytes_from_words_le, words_from_bytes_le};
-use crate::{HashSha512, KeyUsage, KeyVault};
-use caliptra_emu_bus::{ActionHandle, BusError, Clock, ReadOnlyRegister, ReadWriteRegister, Timer};
+use crate::intr_block::{vector, NOTIF_CMD_DONE_STS};
+use crate::{HashSha512, IntrBlock, KeyUsage, KeyVault};
+use caliptra_emu_bus::{
+    ActionHandle, BusError, Clock, ReadOnlyRegister, ReadWriteRegister, Snapshot, SnapshotError,
+    SnapshotReader, SnapshotWriter, Timer,
+};
+use caliptra_emu_cpu::Pic;
 use caliptra_emu_crypto::{Ecc384, Ecc384PubKey, Ecc384Signature};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvData, RvSize};
+use caliptra_hw_model_types::CryptoTiming;
 use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 use tock_registers::register_bitfields;
 use tock_registers::registers::InMemoryRegister;
@@ -34,9 +40,6 @@ const ECC384_IV_SIZE: usize = 48;
 /// ECC384 Nonce size
 const ECC384_NONCE_SIZE: usize = 48;
 
-/// The number of CPU clock cycles it takes to perform ECC operation
-const ECC384_OP_TICKS: u64 = 1000;
-
 /// The number of CPU clock cycles read and write keys from key vault
 const KEY_RW_TICKS: u64 = 100;
 
@@ -204,6 +207,10 @@ pub struct AsymEcc384 {
     #[register(offset = 0x0000_0614)]
     key_write_status: ReadOnlyRegister<u32, KeyWriteStatus::Register>,
 
+    /// Interrupt Block
+    #[peripheral(offset = 0x0000_0800, mask = 0x0000_07ff)]
+    intr: IntrBlock,
+
     /// Key Vault
     key_vault: KeyVault,
 
@@ -212,6 +219,9 @@ pub struct AsymEcc384 {
     /// Timer
     timer: Timer,
 
+    /// Cycles taken by each operation
+    timing: CryptoTiming,
+
     /// Operation complete callback
     op_complete_action: Option<ActionHandle>,
 
@@ -239,7 +249,7 @@ impl AsymEcc384 {
     const VERSION1_VAL: RvData = 0x00000000;
 
     /// Create a new instance of ECC-384 Engine
-    pub fn new(clock: &Clock, key_vault: KeyVault, hash_sha512: HashSha512) -> Self {
+    pub fn new(clock: &Clock, key_vault: KeyVault, hash_sha512: HashSha512, pic: &Pic) -> Self {
         Self {
             name0: ReadOnlyRegister::new(Self::NAME0_VAL),
             name1: ReadOnlyRegister::new(Self::NAME1_VAL),
@@ -265,9 +275,11 @@ impl AsymEcc384 {
             seed_read_status: ReadOnlyRegister::new(KeyReadStatus::READY::SET.value),
             key_write_ctrl: ReadWriteRegister::new(0),
             key_write_status: ReadOnlyRegister::new(KeyWriteStatus::READY::SET.value),
+            intr: IntrBlock::new(pic, vector::ECC_ERROR),
             key_vault,
             hash_sha512,
             timer: Timer::new(clock),
+            timing: CryptoTiming::default(),
             op_complete_action: None,
             op_key_read_complete_action: None,
             op_seed_read_complete_action: None,
@@ -275,6 +287,11 @@ impl AsymEcc384 {
         }
     }
 
+    /// Set the number of cycles each operation takes
+    pub fn set_timing(&mut self, timing: CryptoTiming) {
+        self.timing = timing;
+    }
+
     /// On Write callback for `control` register
     ///
     /// # Arguments
@@ -294,18 +311,19 @@ impl AsymEcc384 {
         // Set the control register
         self.control.reg.set(val);
 
-        match self.control.reg.read_as_enum(Control::CTRL) {
-            Some(Control::CTRL::Value::GEN_KEY)
-            | Some(Control::CTRL::Value::SIGN)
-            | Some(Control::CTRL::Value::VERIFY) => {
-                // Reset the Ready and Valid status bits
-                self.status
-                    .reg
-                    .modify(Status::READY::CLEAR + Status::VALID::CLEAR);
-
-                self.op_complete_action = Some(self.timer.schedule_poll_in(ECC384_OP_TICKS));
-            }
-            _ => {}
+        let ticks = match self.control.reg.read_as_enum(Control::CTRL) {
+            Some(Control::CTRL::Value::GEN_KEY) => Some(self.timing.ecc384_keygen),
+            Some(Control::CTRL::Value::SIGN) => Some(self.timing.ecc384_sign),
+            Some(Control::CTRL::Value::VERIFY) => Some(self.timing.ecc384_verify),
+            _ => None,
+        };
+        if let Some(ticks) = ticks {
+            // Reset the Ready and Valid status bits
+            self.status
+                .reg
+                .modify(Status::READY::CLEAR + Status::VALID::CLEAR);
+
+            self.op_complete_action = Some(self.timer.schedule_poll_in(ticks));
         }
 
         if self.control.reg.is_set(Control::ZEROIZE) {
@@ -442,6 +460,7 @@ impl AsymEcc384 {
     fn poll(&mut self) {
         if self.timer.fired(&mut self.op_complete_action) {
             self.op_complete();
+            self.intr.set_notif(NOTIF_CMD_DONE_STS);
         } else if self.timer.fired(&mut self.op_key_read_complete_action) {
             self.key_read_complete();
         } else if self.timer.fired(&mut self.op_seed_read_complete_action) {
@@ -665,6 +684,70 @@ impl AsymEcc384 {
     }
 }
 
+/// The key vault and SHA-512 engine are shared and saved by their owner.
+impl Snapshot for AsymEcc384 {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.control)?;
+        w.write(&self.status)?;
+        w.write(&self.sca_cfg)?;
+        w.write(&self.seed)?;
+        w.write(&self.hash)?;
+        w.write(&self.priv_key_out)?;
+        w.write(&self.priv_key_in)?;
+        w.write(&self.pub_key_x)?;
+        w.write(&self.pub_key_y)?;
+        w.write(&self.sig_r)?;
+        w.write(&self.sig_s)?;
+        w.write(&self.verify_r)?;
+        w.write(&self.iv)?;
+        w.write(&self.nonce)?;
+        w.write(&self.key_read_ctrl)?;
+        w.write(&self.key_read_status)?;
+        w.write(&self.seed_read_ctrl)?;
+        w.write(&self.seed_read_status)?;
+        w.write(&self.key_write_ctrl)?;
+        w.write(&self.key_write_status)?;
+        w.write(&self.intr)?;
+        self.timer.save_action(&self.op_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_key_read_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_seed_read_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_key_write_complete_action, w)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.control)?;
+        r.read(&mut self.status)?;
+        r.read(&mut self.sca_cfg)?;
+        r.read(&mut self.seed)?;
+        r.read(&mut self.hash)?;
+        r.read(&mut self.priv_key_out)?;
+        r.read(&mut self.priv_key_in)?;
+        r.read(&mut self.pub_key_x)?;
+        r.read(&mut self.pub_key_y)?;
+        r.read(&mut self.sig_r)?;
+        r.read(&mut self.sig_s)?;
+        r.read(&mut self.verify_r)?;
+        r.read(&mut self.iv)?;
+        r.read(&mut self.nonce)?;
+        r.read(&mut self.key_read_ctrl)?;
+        r.read(&mut self.key_read_status)?;
+        r.read(&mut self.seed_read_ctrl)?;
+        r.read(&mut self.seed_read_status)?;
+        r.read(&mut self.key_write_ctrl)?;
+        r.read(&mut self.key_write_status)?;
+        r.read(&mut self.intr)?;
+        self.op_complete_action = self.timer.restore_action(r)?;
+        self.op_key_read_complete_action = self.timer.restore_action(r)?;
+        self.op_seed_read_complete_action = self.timer.restore_action(r)?;
+        self.op_key_write_complete_action = self.timer.restore_action(r)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -742,9 +825,9 @@ mod tests {
     fn test_name() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
         let name0 = ecc.read(RvSize::Word, OFFSET_NAME0).unwrap();
         let name0 = String::from_utf8_lossy(&name0.to_be_bytes()).to_string();
@@ -759,9 +842,9 @@ mod tests {
     fn test_version() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
         let version0 = ecc.read(RvSize::Word, OFFSET_VERSION0).unwrap();
         let version0 = String::from_utf8_lossy(&version0.to_le_bytes()).to_string();
@@ -776,9 +859,9 @@ mod tests {
     fn test_control() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
         assert_eq!(ecc.read(RvSize::Word, OFFSET_CONTROL).unwrap(), 0);
     }
 
@@ -786,9 +869,9 @@ mod tests {
     fn test_status() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
         assert_eq!(ecc.read(RvSize::Word, OFFSET_STATUS).unwrap(), 1);
     }
 
@@ -796,9 +879,9 @@ mod tests {
     fn test_gen_key() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
         let mut seed = [0u8; 48];
         seed.to_big_endian(); // Change DWORDs to big-endian.
@@ -856,6 +939,36 @@ mod tests {
         assert_eq!(&pub_key_y, &PUB_KEY_Y);
     }
 
+    #[test]
+    fn test_gen_key_timing() {
+        let clock = Clock::new();
+        let key_vault = KeyVault::new();
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
+
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
+        ecc.set_timing(CryptoTiming {
+            ecc384_keygen: 500,
+            ..CryptoTiming::FAST
+        });
+
+        assert_eq!(
+            ecc.write(RvSize::Word, OFFSET_CONTROL, Control::CTRL::GEN_KEY.into())
+                .ok(),
+            Some(())
+        );
+
+        let is_ready = |ecc: &mut AsymEcc384| {
+            InMemoryRegister::<u32, Status::Register>::new(
+                ecc.read(RvSize::Word, OFFSET_STATUS).unwrap(),
+            )
+            .is_set(Status::READY)
+        };
+        clock.increment_and_process_timer_actions(499, &mut ecc);
+        assert!(!is_ready(&mut ecc));
+        clock.increment_and_process_timer_actions(1, &mut ecc);
+        assert!(is_ready(&mut ecc));
+    }
+
     #[test]
     fn test_gen_key_kv_seed() {
         // Test for getting the seed from the key-vault.
@@ -872,8 +985,8 @@ mod tests {
                 .write_key(key_id, &seed, u32::from(key_usage))
                 .unwrap();
 
-            let sha512 = HashSha512::new(&clock, key_vault.clone());
-            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+            let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
+            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
             // Instruct seed to be read from key-vault.
             let seed_ctrl = InMemoryRegister::<u32, KeyReadControl::Register>::new(0);
@@ -942,9 +1055,9 @@ mod tests {
             seed.to_big_endian(); // Change DWORDs to big-endian.
 
             let key_vault = KeyVault::new();
-            let sha512 = HashSha512::new(&clock, key_vault.clone());
+            let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
             for i in (0..seed.len()).step_by(4) {
                 assert_eq!(
@@ -1023,9 +1136,9 @@ mod tests {
     fn test_sign() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
         let mut hash = [0u8; KeyVault::KEY_SIZE];
         hash.to_big_endian(); // Change DWORDs to big-endian.
@@ -1096,8 +1209,8 @@ mod tests {
                 .write_key(key_id, &priv_key, u32::from(key_usage))
                 .unwrap();
 
-            let sha512 = HashSha512::new(&clock, key_vault.clone());
-            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+            let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
+            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
             let mut hash = [0u8; 48];
             hash.to_big_endian(); // Change DWORDs to big-endian.
@@ -1178,8 +1291,8 @@ mod tests {
                 .write_key(key_id, &priv_key, !(u32::from(key_usage)))
                 .unwrap();
 
-            let sha512 = HashSha512::new(&clock, key_vault.clone());
-            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+            let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
+            let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
             let mut hash = [0u8; 48];
             hash.to_big_endian(); // Change DWORDs to big-endian.
@@ -1224,9 +1337,9 @@ mod tests {
     fn test_verify() {
         let clock = Clock::new();
         let key_vault = KeyVault::new();
-        let sha512 = HashSha512::new(&clock, key_vault.clone());
+        let sha512 = HashSha512::new(&clock, key_vault.clone(), &Pic::new());
 
-        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512);
+        let mut ecc = AsymEcc384::new(&clock, key_vault, sha512, &Pic::new());
 
         let hash = [0u8; KeyVault::KEY_SIZE];
         for i in (0..hash.len()).step_by(4) {
@@ -1,25 +1,20 @@
 // Licensed under the Apache-2.0 license
 
-use caliptra_emu_bus::{BusError, ReadOnlyRegister, WriteOnlyRegister};
+use crate::entropy_src::{EntropySrc, MultiBitBool, BITS_PER_NIBBLE};
+use caliptra_emu_bus::{
+    BusError, ReadOnlyRegister, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter,
+    WriteOnlyRegister,
+};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvData, RvSize};
-use caliptra_registers::entropy_src::regs::{
-    AdaptpHiThresholdsReadVal, AdaptpLoThresholdsReadVal, ConfReadVal, HealthTestWindowsReadVal,
-    RepcntThresholdsReadVal,
-};
 use sha3::{Digest, Sha3_384};
 use std::mem;
-use tock_registers::interfaces::Readable;
-
-mod health_test;
-use health_test::HealthTester;
 
 mod ctr_drbg;
 use ctr_drbg::{Block, CtrDrbg, Instantiate, Seed};
 
 type Word = u32;
 
-const BITS_PER_NIBBLE: usize = 4;
 const WORD_SIZE_BYTES: usize = mem::size_of::<Word>();
 
 #[derive(Bus)]
@@ -43,43 +38,17 @@ pub struct Csrng {
     #[register(offset = 0x38)]
     err_code: ReadOnlyRegister<u32>,
 
-    // Entropy Source registers
-    #[register(offset = 0x1020, write_fn = module_enable_write)]
-    module_enable: u32,
-
-    #[register(offset = 0x1024)]
-    conf: u32,
-
-    #[register(offset = 0x1030)]
-    health_test_windows: ReadOnlyRegister<u32>,
-
-    #[register(offset = 0x1034, write_fn = repcnt_thresholds_write)]
-    repcnt_thresholds: u32,
-
-    #[register(offset = 0x103c, write_fn = adaptp_hi_thresholds_write)]
-    adaptp_hi_thresholds: u32,
-
-    #[register(offset = 0x1040, write_fn = adaptp_lo_thresholds_write)]
-    adaptp_lo_thresholds: u32,
-
-    #[register(offset = 0x10a4, read_fn = alert_summary_fail_counts_read)]
-    alert_summary_fail_counts: ReadOnlyRegister<u32>,
-
-    #[register(offset = 0x10a8, read_fn = alert_fail_counts_read)]
-    alert_fail_counts: ReadOnlyRegister<u32>,
-
-    #[register(offset = 0x10e0, read_fn = main_sm_state_read)]
-    main_sm_state: ReadOnlyRegister<u32>,
+    #[peripheral(offset = 0x1000, mask = 0x0fff)]
+    entropy_src: EntropySrc,
 
     cmd_req_state: CmdReqState,
     seed: Vec<u32>,
     ctr_drbg: CtrDrbg,
     words: Words,
-    health_tester: HealthTester,
 }
 
 impl Csrng {
-    pub fn new(itrng_nibbles: Box<dyn Iterator<Item = u8>>) -> Self {
+    pub fn new(entropy_src: EntropySrc) -> Self {
         Self {
             // These reset values come from register definitions
             ctrl: 0x999,
@@ -88,24 +57,19 @@ impl Csrng {
             genbits_vld: ReadOnlyRegister::new(0b01),
             genbits: ReadOnlyRegister::new(0),
             err_code: ReadOnlyRegister::new(0),
-            module_enable: 0x9,
-            conf: 0x909099,
-            health_test_windows: ReadOnlyRegister::new(0x600200),
-            repcnt_thresholds: 0xffffffff,
-            adaptp_hi_thresholds: 0xffffffff,
-            adaptp_lo_thresholds: 0,
-            alert_summary_fail_counts: ReadOnlyRegister::new(0),
-            alert_fail_counts: ReadOnlyRegister::new(0),
-            main_sm_state: ReadOnlyRegister::new(0x2c), // StartupHTStart, entropy_src_main_sm_pkg.sv
+            entropy_src,
 
             cmd_req_state: CmdReqState::ExpectNewCommand,
             seed: vec![],
             ctr_drbg: CtrDrbg::new(),
             words: Words::default(),
-            health_tester: HealthTester::new(itrng_nibbles),
         }
     }
 
+    pub fn entropy_src_mut(&mut self) -> &mut EntropySrc {
+        &mut self.entropy_src
+    }
+
     fn cmd_req_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
         // Since the CMD_REQ register can be used to initiate new commands or
         // supply words to an existing command, we need to track which "state"
@@ -143,76 +107,6 @@ impl Csrng {
         Ok(self.words.next().unwrap_or(0xCAFE_F00D))
     }
 
-    fn module_enable_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
-        self.module_enable = data;
-
-        if data == MultiBitBool::False as u32 {
-            return Ok(());
-        }
-
-        if ConfReadVal::from(self.conf).fips_enable() == MultiBitBool::False as u32 {
-            unimplemented!("emulation of non-FIPS mode");
-        }
-
-        self.health_tester.test_boot_window();
-
-        Ok(())
-    }
-
-    fn repcnt_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
-        self.health_tester
-            .repcnt
-            .set_threshold(RepcntThresholdsReadVal::from(data));
-        Ok(())
-    }
-
-    fn adaptp_hi_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
-        self.health_tester
-            .adaptp
-            .set_hi_threshold(AdaptpHiThresholdsReadVal::from(data));
-        Ok(())
-    }
-
-    fn adaptp_lo_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
-        self.health_tester
-            .adaptp
-            .set_lo_threshold(AdaptpLoThresholdsReadVal::from(data));
-        Ok(())
-    }
-
-    fn alert_summary_fail_counts_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
-        let failures = self.health_tester.failures();
-        self.alert_summary_fail_counts = ReadOnlyRegister::new(failures);
-        Ok(failures)
-    }
-
-    fn alert_fail_counts_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
-        // Don't have a `AlertFailCountsWriteVal` from ureg, so let's  pack counts manually.
-        let adapt_lo = self.health_tester.adaptp.lo_failures().min(0xf) & 0xf;
-        let adapt_hi = self.health_tester.adaptp.hi_failures().min(0xf) & 0xf;
-        let repcnt = self.health_tester.repcnt.failures().min(0xf) & 0xf;
-        let fail_counts = (adapt_lo << 12) | (adapt_hi << 8) | (repcnt << 4);
-
-        self.alert_fail_counts = ReadOnlyRegister::new(fail_counts);
-        Ok(fail_counts)
-    }
-
-    fn main_sm_state_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
-        // https://opentitan.org/book/hw/ip/entropy_src/doc/theory_of_operation.html#main-state-machine-diagram
-        // https://github.com/chipsalliance/caliptra-rtl/blob/main/src/entropy_src/rtl/entropy_src_main_sm_pkg.sv
-        const ALERT_HANG: u32 = 0x15c;
-        const CONT_HT_RUNNING: u32 = 0x1a2;
-
-        let state = if self.health_tester.failures() > 0 {
-            ALERT_HANG
-        } else {
-            CONT_HT_RUNNING
-        };
-
-        self.main_sm_state = ReadOnlyRegister::new(state);
-        Ok(state)
-    }
-
     fn process_new_cmd(&mut self, data: RvData) {
         const INSTANTIATE: u32 = 1;
         const GENERATE: u32 = 3;
@@ -270,13 +164,9 @@ impl Csrng {
     fn get_conditioned_seed(&mut self) -> Seed {
         // Replicate the logic in caliptra-rtl/src/entropy_src/rtl/entropy_src_core.sv.
         const NUM_TEST_WINDOWS: usize = 2;
-        const BITS_PER_CYCLE: usize = 4;
         const BITS_PER_BLOCK: usize = 8 * mem::size_of::<u64>();
 
-        let window_size_bits = {
-            let w = HealthTestWindowsReadVal::from(self.health_test_windows.reg.get());
-            BITS_PER_CYCLE * w.fips_window() as usize
-        };
+        let window_size_bits = self.entropy_src.fips_window_bits();
         let num_blocks = NUM_TEST_WINDOWS * window_size_bits / BITS_PER_BLOCK;
 
         let mut hasher = Sha3_384::new();
@@ -286,7 +176,7 @@ impl Csrng {
             const NUM_NIBBLES: usize = BITS_PER_BLOCK / BITS_PER_NIBBLE;
 
             let packed_entropy = (0..NUM_NIBBLES).fold(0, |packed, i| {
-                let nibble = self.health_tester.next().expect(
+                let nibble = self.entropy_src.next().expect(
                     "itrng iterator should provide at least two 2048 bit windows in FIPS mode",
                 );
                 packed | u64::from(nibble) << (i * BITS_PER_NIBBLE)
@@ -303,6 +193,38 @@ impl Csrng {
     }
 }
 
+impl Snapshot for Csrng {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.ctrl)?;
+        w.write(&self.cmd_req)?;
+        w.write(&self.sw_cmd_sts)?;
+        w.write(&self.genbits_vld)?;
+        w.write(&self.genbits)?;
+        w.write(&self.err_code)?;
+        w.write(&self.cmd_req_state)?;
+        w.write(&self.seed)?;
+        w.write(&self.ctr_drbg)?;
+        w.write(&self.words)?;
+        w.write(&self.entropy_src)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.ctrl)?;
+        r.read(&mut self.cmd_req)?;
+        r.read(&mut self.sw_cmd_sts)?;
+        r.read(&mut self.genbits_vld)?;
+        r.read(&mut self.genbits)?;
+        r.read(&mut self.err_code)?;
+        r.read(&mut self.cmd_req_state)?;
+        r.read(&mut self.seed)?;
+        r.read(&mut self.ctr_drbg)?;
+        r.read(&mut self.words)?;
+        r.read(&mut self.entropy_src)?;
+        Ok(())
+    }
+}
+
 #[derive(Default)]
 struct Words {
     block: Block,
@@ -322,6 +244,20 @@ impl Words {
     }
 }
 
+impl Snapshot for Words {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.block)?;
+        w.write(&self.cursor)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.block)?;
+        r.read(&mut self.cursor)?;
+        Ok(())
+    }
+}
+
 impl Iterator for Words {
     type Item = Word;
 
@@ -355,8 +291,19 @@ enum CmdReqState {
     ExpectSeedWords { num_words: usize },
 }
 
-#[repr(u32)]
-enum MultiBitBool {
-    False = 9,
-    True = 6,
+impl Snapshot for CmdReqState {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        match self {
+            CmdReqState::ExpectNewCommand => w.write(&None::<usize>),
+            CmdReqState::ExpectSeedWords { num_words } => w.write(&Some(*num_words)),
+        }
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        *self = match r.read_value()? {
+            None => CmdReqState::ExpectNewCommand,
+            Some(num_words) => CmdReqState::ExpectSeedWords { num_words },
+        };
+        Ok(())
+    }
 }
@@ -6,6 +6,7 @@
 use std::iter;
 
 use super::WORD_SIZE_BYTES;
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 
 // Table 3 of Section 10.2.1 (page 49).
 const BLOCK_LEN_BYTES: usize = 128 / 8;
@@ -34,6 +35,22 @@ pub struct CtrDrbg {
     generated_bytes: Vec<Block>,
 }
 
+impl Snapshot for CtrDrbg {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.v)?;
+        w.write(&self.key)?;
+        w.write(&self.generated_bytes)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.v)?;
+        r.read(&mut self.key)?;
+        r.read(&mut self.generated_bytes)?;
+        Ok(())
+    }
+}
+
 impl CtrDrbg {
     pub fn new() -> Self {
         Self {
@@ -15,7 +15,8 @@ Abstract:
 use crate::helpers::bytes_swap_word_endian;
 use crate::{KeyVault, SocRegistersInternal};
 use caliptra_emu_bus::{
-    ActionHandle, BusError, Clock, ReadOnlyRegister, ReadWriteMemory, ReadWriteRegister, Timer,
+    ActionHandle, BusError, Clock, ReadOnlyRegister, ReadWriteMemory, ReadWriteRegister, Snapshot,
+    SnapshotError, SnapshotReader, SnapshotWriter, Timer,
 };
 use caliptra_emu_crypto::Aes256Cbc;
 use caliptra_emu_derive::Bus;
@@ -209,11 +210,31 @@ impl Doe {
     }
 }
 
+/// The key vault and SoC registers are shared and saved by their owner.
+impl Snapshot for Doe {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.iv)?;
+        w.write(&self.control)?;
+        w.write(&self.status)?;
+        self.timer.save_action(&self.op_complete_action, w)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.iv)?;
+        r.read(&mut self.control)?;
+        r.read(&mut self.status)?;
+        self.op_complete_action = self.timer.restore_action(r)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{CaliptraRootBusArgs, Iccm, KeyUsage, MailboxInternal, MailboxRam};
     use caliptra_emu_bus::Bus;
+    use caliptra_emu_cpu::Pic;
     use caliptra_emu_crypto::EndianessTransform;
     use caliptra_emu_types::RvAddr;
     use caliptra_hw_model_types::SecurityState;
@@ -252,6 +273,7 @@ mod tests {
             &clock,
             MailboxInternal::new(MailboxRam::new()),
             Iccm::new(&clock),
+            &Pic::new(),
             CaliptraRootBusArgs {
                 security_state: *SecurityState::default().set_debug_locked(true),
                 ..CaliptraRootBusArgs::default()
@@ -313,6 +335,7 @@ mod tests {
             &clock,
             MailboxInternal::new(MailboxRam::new()),
             Iccm::new(&clock),
+            &Pic::new(),
             CaliptraRootBusArgs {
                 security_state: *SecurityState::default().set_debug_locked(true),
                 ..CaliptraRootBusArgs::default()
@@ -374,6 +397,7 @@ mod tests {
             &clock,
             MailboxInternal::new(MailboxRam::new()),
             Iccm::new(&clock),
+            &Pic::new(),
             CaliptraRootBusArgs {
                 security_state: *SecurityState::default().set_debug_locked(true),
                 ..CaliptraRootBusArgs::default()
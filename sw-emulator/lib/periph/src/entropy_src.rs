@@ -0,0 +1,829 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    entropy_src.rs
+
+Abstract:
+
+    File contains ENTROPY_SRC peripheral implementation.
+
+--*/
+
+use caliptra_emu_bus::{
+    BusError, ReadOnlyRegister, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter,
+    WriteOnlyRegister,
+};
+use caliptra_emu_derive::Bus;
+use caliptra_emu_types::{RvData, RvSize};
+use caliptra_hw_model_types::EntropySrcFault;
+use caliptra_registers::entropy_src::regs::{
+    AdaptpHiThresholdsReadVal, AdaptpLoThresholdsReadVal, ConfReadVal, EntropyControlReadVal,
+    HealthTestWindowsReadVal, RepcntThresholdsReadVal,
+};
+
+mod health_test;
+use health_test::HealthTester;
+
+mod noise_source;
+use noise_source::NoiseSource;
+
+pub(crate) const BITS_PER_NIBBLE: usize = 4;
+
+/// Interrupt raised when a health test fails
+const INTR_ES_HEALTH_TEST_FAILED: u32 = 1 << 1;
+
+/// Main state machine states
+/// https://github.com/chipsalliance/caliptra-rtl/blob/main/src/entropy_src/rtl/entropy_src_main_sm_pkg.sv
+const MAIN_SM_IDLE: u32 = 0xf5;
+const MAIN_SM_CONT_HT_RUNNING: u32 = 0x1a2;
+const MAIN_SM_ALERT_HANG: u32 = 0x15c;
+
+/// DEBUG_STATUS fields
+const DEBUG_STATUS_MAIN_SM_IDLE: u32 = 1 << 16;
+const DEBUG_STATUS_MAIN_SM_BOOT_DONE: u32 = 1 << 17;
+
+#[repr(u32)]
+pub(crate) enum MultiBitBool {
+    False = 9,
+    True = 6,
+}
+
+/// ENTROPY_SRC peripheral
+///
+/// Only FIPS mode is emulated: the repetition count and adaptive proportion
+/// tests run over the raw nibbles of the noise source, while the bucket,
+/// Markov and external health tests, the observe FIFO and the firmware
+/// override path are register stubs.
+#[derive(Bus)]
+pub struct EntropySrc {
+    #[register(offset = 0x00, read_fn = interrupt_state_read, write_fn = interrupt_state_write)]
+    interrupt_state: u32,
+
+    #[register(offset = 0x04)]
+    interrupt_enable: u32,
+
+    #[register(offset = 0x08, write_fn = interrupt_test_write)]
+    interrupt_test: WriteOnlyRegister<u32>,
+
+    #[register(offset = 0x0c)]
+    alert_test: WriteOnlyRegister<u32>,
+
+    #[register(offset = 0x10, write_fn = me_regwen_write)]
+    me_regwen: u32,
+
+    #[register(offset = 0x14, write_fn = sw_regupd_write)]
+    sw_regupd: u32,
+
+    #[register(offset = 0x18, read_fn = regwen_read)]
+    regwen: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x1c)]
+    rev: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x20, write_fn = module_enable_write)]
+    module_enable: u32,
+
+    #[register(offset = 0x24, write_fn = conf_write)]
+    conf: u32,
+
+    #[register(offset = 0x28, write_fn = entropy_control_write)]
+    entropy_control: u32,
+
+    #[register(offset = 0x2c, read_fn = entropy_data_read)]
+    entropy_data: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x30, write_fn = health_test_windows_write)]
+    health_test_windows: u32,
+
+    #[register(offset = 0x34, write_fn = repcnt_thresholds_write)]
+    repcnt_thresholds: u32,
+
+    #[register(offset = 0x38, write_fn = repcnts_thresholds_write)]
+    repcnts_thresholds: u32,
+
+    #[register(offset = 0x3c, write_fn = adaptp_hi_thresholds_write)]
+    adaptp_hi_thresholds: u32,
+
+    #[register(offset = 0x40, write_fn = adaptp_lo_thresholds_write)]
+    adaptp_lo_thresholds: u32,
+
+    #[register(offset = 0x44, write_fn = bucket_thresholds_write)]
+    bucket_thresholds: u32,
+
+    #[register(offset = 0x48, write_fn = markov_hi_thresholds_write)]
+    markov_hi_thresholds: u32,
+
+    #[register(offset = 0x4c, write_fn = markov_lo_thresholds_write)]
+    markov_lo_thresholds: u32,
+
+    #[register(offset = 0x50, write_fn = extht_hi_thresholds_write)]
+    extht_hi_thresholds: u32,
+
+    #[register(offset = 0x54, write_fn = extht_lo_thresholds_write)]
+    extht_lo_thresholds: u32,
+
+    #[register(offset = 0x58, read_fn = repcnt_hi_watermarks_read)]
+    repcnt_hi_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x5c)]
+    repcnts_hi_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x60, read_fn = adaptp_hi_watermarks_read)]
+    adaptp_hi_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x64, read_fn = adaptp_lo_watermarks_read)]
+    adaptp_lo_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x68)]
+    extht_hi_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x6c)]
+    extht_lo_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x70)]
+    bucket_hi_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x74)]
+    markov_hi_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x78)]
+    markov_lo_watermarks: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x7c, read_fn = repcnt_total_fails_read)]
+    repcnt_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x80)]
+    repcnts_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x84, read_fn = adaptp_hi_total_fails_read)]
+    adaptp_hi_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x88, read_fn = adaptp_lo_total_fails_read)]
+    adaptp_lo_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x8c)]
+    bucket_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x90)]
+    markov_hi_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x94)]
+    markov_lo_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x98)]
+    extht_hi_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0x9c)]
+    extht_lo_total_fails: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xa0, write_fn = alert_threshold_write)]
+    alert_threshold: u32,
+
+    #[register(offset = 0xa4, read_fn = alert_summary_fail_counts_read)]
+    alert_summary_fail_counts: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xa8, read_fn = alert_fail_counts_read)]
+    alert_fail_counts: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xac)]
+    extht_fail_counts: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xb0, write_fn = fw_ov_control_write)]
+    fw_ov_control: u32,
+
+    #[register(offset = 0xb4)]
+    fw_ov_sha3_start: u32,
+
+    #[register(offset = 0xb8)]
+    fw_ov_wr_fifo_full: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xbc)]
+    fw_ov_rd_fifo_overflow: u32,
+
+    #[register(offset = 0xc0)]
+    fw_ov_rd_data: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xc4)]
+    fw_ov_wr_data: WriteOnlyRegister<u32>,
+
+    #[register(offset = 0xc8, write_fn = observe_fifo_thresh_write)]
+    observe_fifo_thresh: u32,
+
+    #[register(offset = 0xcc)]
+    observe_fifo_depth: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xd0, read_fn = debug_status_read)]
+    debug_status: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xd4, write_fn = recov_alert_sts_write)]
+    recov_alert_sts: u32,
+
+    #[register(offset = 0xd8)]
+    err_code: ReadOnlyRegister<u32>,
+
+    #[register(offset = 0xdc)]
+    err_code_test: u32,
+
+    #[register(offset = 0xe0, read_fn = main_sm_state_read)]
+    main_sm_state: ReadOnlyRegister<u32>,
+
+    /// Health test failures already reported through `interrupt_state`
+    reported_failures: u32,
+
+    health_tester: HealthTester,
+}
+
+impl EntropySrc {
+    /// Create a new ENTROPY_SRC
+    ///
+    /// # Arguments
+    ///
+    /// * `itrng_nibbles` - Raw nibbles of the physical noise source
+    /// * `fault` - Fault to inject into the noise source
+    pub fn new(
+        itrng_nibbles: Box<dyn Iterator<Item = u8>>,
+        fault: Option<EntropySrcFault>,
+    ) -> Self {
+        let mut noise_source = NoiseSource::new(itrng_nibbles);
+        noise_source.inject_fault(fault);
+
+        Self {
+            // These reset values come from register definitions
+            interrupt_state: 0,
+            interrupt_enable: 0,
+            interrupt_test: WriteOnlyRegister::new(0),
+            alert_test: WriteOnlyRegister::new(0),
+            me_regwen: 1,
+            sw_regupd: 1,
+            regwen: ReadOnlyRegister::new(1),
+            rev: ReadOnlyRegister::new(0x10303),
+            module_enable: MultiBitBool::False as u32,
+            conf: 0x909099,
+            entropy_control: 0x99,
+            entropy_data: ReadOnlyRegister::new(0),
+            health_test_windows: 0x600200,
+            repcnt_thresholds: 0xffffffff,
+            repcnts_thresholds: 0xffffffff,
+            adaptp_hi_thresholds: 0xffffffff,
+            adaptp_lo_thresholds: 0,
+            bucket_thresholds: 0xffffffff,
+            markov_hi_thresholds: 0xffffffff,
+            markov_lo_thresholds: 0,
+            extht_hi_thresholds: 0xffffffff,
+            extht_lo_thresholds: 0,
+            repcnt_hi_watermarks: ReadOnlyRegister::new(0),
+            repcnts_hi_watermarks: ReadOnlyRegister::new(0),
+            adaptp_hi_watermarks: ReadOnlyRegister::new(0),
+            adaptp_lo_watermarks: ReadOnlyRegister::new(0xffffffff),
+            extht_hi_watermarks: ReadOnlyRegister::new(0),
+            extht_lo_watermarks: ReadOnlyRegister::new(0xffffffff),
+            bucket_hi_watermarks: ReadOnlyRegister::new(0),
+            markov_hi_watermarks: ReadOnlyRegister::new(0),
+            markov_lo_watermarks: ReadOnlyRegister::new(0xffffffff),
+            repcnt_total_fails: ReadOnlyRegister::new(0),
+            repcnts_total_fails: ReadOnlyRegister::new(0),
+            adaptp_hi_total_fails: ReadOnlyRegister::new(0),
+            adaptp_lo_total_fails: ReadOnlyRegister::new(0),
+            bucket_total_fails: ReadOnlyRegister::new(0),
+            markov_hi_total_fails: ReadOnlyRegister::new(0),
+            markov_lo_total_fails: ReadOnlyRegister::new(0),
+            extht_hi_total_fails: ReadOnlyRegister::new(0),
+            extht_lo_total_fails: ReadOnlyRegister::new(0),
+            alert_threshold: 0xfffd0002,
+            alert_summary_fail_counts: ReadOnlyRegister::new(0),
+            alert_fail_counts: ReadOnlyRegister::new(0),
+            extht_fail_counts: ReadOnlyRegister::new(0),
+            fw_ov_control: 0x99,
+            fw_ov_sha3_start: MultiBitBool::False as u32,
+            fw_ov_wr_fifo_full: ReadOnlyRegister::new(0),
+            fw_ov_rd_fifo_overflow: 0,
+            fw_ov_rd_data: ReadOnlyRegister::new(0),
+            fw_ov_wr_data: WriteOnlyRegister::new(0),
+            observe_fifo_thresh: 0x20,
+            observe_fifo_depth: ReadOnlyRegister::new(0),
+            debug_status: ReadOnlyRegister::new(DEBUG_STATUS_MAIN_SM_IDLE),
+            recov_alert_sts: 0,
+            err_code: ReadOnlyRegister::new(0),
+            err_code_test: 0,
+            main_sm_state: ReadOnlyRegister::new(MAIN_SM_IDLE),
+            reported_failures: 0,
+            health_tester: HealthTester::new(noise_source),
+        }
+    }
+
+    /// Inject a fault into the noise source, counting its delay from the
+    /// next nibble drawn. Pass `None` to repair the noise source.
+    ///
+    /// # Arguments
+    ///
+    /// * `fault` - Fault to inject
+    pub fn inject_fault(&mut self, fault: Option<EntropySrcFault>) {
+        self.health_tester.noise_source_mut().inject_fault(fault);
+    }
+
+    /// Size of the FIPS health test window in bits
+    pub(crate) fn fips_window_bits(&self) -> usize {
+        BITS_PER_NIBBLE
+            * HealthTestWindowsReadVal::from(self.health_test_windows).fips_window() as usize
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.module_enable == MultiBitBool::True as u32
+    }
+
+    /// Registers other than MODULE_ENABLE may only be changed while the
+    /// module is disabled and SW_REGUPD is set.
+    fn is_writable(&self) -> bool {
+        self.sw_regupd & 1 != 0 && !self.is_enabled()
+    }
+
+    fn interrupt_state_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        if self.health_tester.failures() > self.reported_failures {
+            self.interrupt_state |= INTR_ES_HEALTH_TEST_FAILED;
+        }
+        self.reported_failures = self.health_tester.failures();
+        Ok(self.interrupt_state)
+    }
+
+    fn interrupt_state_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        self.interrupt_state_read(RvSize::Word)?;
+        self.interrupt_state &= !data;
+        Ok(())
+    }
+
+    fn interrupt_test_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        self.interrupt_state |= data & 0xf;
+        Ok(())
+    }
+
+    fn me_regwen_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        self.me_regwen &= data;
+        Ok(())
+    }
+
+    fn sw_regupd_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        self.sw_regupd &= data;
+        Ok(())
+    }
+
+    fn regwen_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.is_writable().into())
+    }
+
+    fn module_enable_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.me_regwen & 1 == 0 {
+            return Ok(());
+        }
+        let was_enabled = self.is_enabled();
+        self.module_enable = data & 0xf;
+
+        if !self.is_enabled() {
+            // Disabling the module clears the health test state and statistics.
+            if was_enabled {
+                self.health_tester.clear();
+                self.reported_failures = 0;
+            }
+            return Ok(());
+        }
+        if was_enabled {
+            return Ok(());
+        }
+
+        if ConfReadVal::from(self.conf).fips_enable() == MultiBitBool::False as u32 {
+            unimplemented!("emulation of non-FIPS mode");
+        }
+
+        self.health_tester.test_boot_window();
+
+        Ok(())
+    }
+
+    fn conf_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.conf = data;
+        }
+        Ok(())
+    }
+
+    fn entropy_control_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.entropy_control = data;
+        }
+        Ok(())
+    }
+
+    fn entropy_data_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        // Entropy is only routed to this register when firmware asks for it;
+        // otherwise it goes to the CSRNG.
+        const TRUE: u32 = MultiBitBool::True as u32;
+
+        let routed_to_sw = ConfReadVal::from(self.conf).entropy_data_reg_enable() == TRUE
+            && EntropyControlReadVal::from(self.entropy_control).es_route() == TRUE;
+        if !self.is_enabled() || !routed_to_sw {
+            return Ok(0);
+        }
+
+        const NUM_NIBBLES: usize = u32::BITS as usize / BITS_PER_NIBBLE;
+        let data = (0..NUM_NIBBLES).fold(0, |packed, i| {
+            let nibble = self.next().unwrap_or(0);
+            packed | u32::from(nibble) << (i * BITS_PER_NIBBLE)
+        });
+        Ok(data)
+    }
+
+    fn health_test_windows_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.health_test_windows = data;
+            let window_bits = self.fips_window_bits();
+            self.health_tester.adaptp.set_window_bits(window_bits);
+        }
+        Ok(())
+    }
+
+    fn repcnt_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.repcnt_thresholds = data;
+            self.health_tester
+                .repcnt
+                .set_threshold(RepcntThresholdsReadVal::from(data));
+        }
+        Ok(())
+    }
+
+    fn repcnts_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.repcnts_thresholds = data;
+        }
+        Ok(())
+    }
+
+    fn adaptp_hi_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.adaptp_hi_thresholds = data;
+            self.health_tester
+                .adaptp
+                .set_hi_threshold(AdaptpHiThresholdsReadVal::from(data));
+        }
+        Ok(())
+    }
+
+    fn adaptp_lo_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.adaptp_lo_thresholds = data;
+            self.health_tester
+                .adaptp
+                .set_lo_threshold(AdaptpLoThresholdsReadVal::from(data));
+        }
+        Ok(())
+    }
+
+    fn bucket_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.bucket_thresholds = data;
+        }
+        Ok(())
+    }
+
+    fn markov_hi_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.markov_hi_thresholds = data;
+        }
+        Ok(())
+    }
+
+    fn markov_lo_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.markov_lo_thresholds = data;
+        }
+        Ok(())
+    }
+
+    fn extht_hi_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.extht_hi_thresholds = data;
+        }
+        Ok(())
+    }
+
+    fn extht_lo_thresholds_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.extht_lo_thresholds = data;
+        }
+        Ok(())
+    }
+
+    fn repcnt_hi_watermarks_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.health_tester.repcnt.hi_watermark().min(0xffff))
+    }
+
+    fn adaptp_hi_watermarks_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.health_tester.adaptp.hi_watermark().min(0xffff))
+    }
+
+    fn adaptp_lo_watermarks_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        // The bypass watermark is never updated.
+        Ok(0xffff_0000 | self.health_tester.adaptp.lo_watermark().min(0xffff))
+    }
+
+    fn repcnt_total_fails_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.health_tester.repcnt.failures())
+    }
+
+    fn adaptp_hi_total_fails_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.health_tester.adaptp.hi_failures())
+    }
+
+    fn adaptp_lo_total_fails_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.health_tester.adaptp.lo_failures())
+    }
+
+    fn alert_threshold_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.alert_threshold = data;
+        }
+        Ok(())
+    }
+
+    fn alert_summary_fail_counts_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(self.health_tester.failures().min(0xffff))
+    }
+
+    fn alert_fail_counts_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        // Don't have a `AlertFailCountsWriteVal` from ureg, so let's  pack counts manually.
+        let adapt_lo = self.health_tester.adaptp.lo_failures().min(0xf);
+        let adapt_hi = self.health_tester.adaptp.hi_failures().min(0xf);
+        let repcnt = self.health_tester.repcnt.failures().min(0xf);
+        Ok((adapt_lo << 12) | (adapt_hi << 8) | (repcnt << 4))
+    }
+
+    fn fw_ov_control_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.fw_ov_control = data;
+        }
+        Ok(())
+    }
+
+    fn observe_fifo_thresh_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        if self.is_writable() {
+            self.observe_fifo_thresh = data;
+        }
+        Ok(())
+    }
+
+    fn debug_status_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(match self.main_sm_state_read(RvSize::Word)? {
+            MAIN_SM_IDLE => DEBUG_STATUS_MAIN_SM_IDLE,
+            MAIN_SM_CONT_HT_RUNNING => DEBUG_STATUS_MAIN_SM_BOOT_DONE,
+            _ => 0,
+        })
+    }
+
+    fn recov_alert_sts_write(&mut self, _: RvSize, data: RvData) -> Result<(), BusError> {
+        self.recov_alert_sts &= data;
+        Ok(())
+    }
+
+    fn main_sm_state_read(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        // https://opentitan.org/book/hw/ip/entropy_src/doc/theory_of_operation.html#main-state-machine-diagram
+        Ok(if !self.is_enabled() {
+            MAIN_SM_IDLE
+        } else if self.health_tester.failures() > 0 {
+            MAIN_SM_ALERT_HANG
+        } else {
+            MAIN_SM_CONT_HT_RUNNING
+        })
+    }
+}
+
+/// Yields health-tested nibbles for the CSRNG
+impl Iterator for EntropySrc {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.health_tester.next()
+    }
+}
+
+impl Snapshot for EntropySrc {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.interrupt_state)?;
+        w.write(&self.interrupt_enable)?;
+        w.write(&self.me_regwen)?;
+        w.write(&self.sw_regupd)?;
+        w.write(&self.module_enable)?;
+        w.write(&self.conf)?;
+        w.write(&self.entropy_control)?;
+        w.write(&self.health_test_windows)?;
+        w.write(&self.repcnt_thresholds)?;
+        w.write(&self.repcnts_thresholds)?;
+        w.write(&self.adaptp_hi_thresholds)?;
+        w.write(&self.adaptp_lo_thresholds)?;
+        w.write(&self.bucket_thresholds)?;
+        w.write(&self.markov_hi_thresholds)?;
+        w.write(&self.markov_lo_thresholds)?;
+        w.write(&self.extht_hi_thresholds)?;
+        w.write(&self.extht_lo_thresholds)?;
+        w.write(&self.alert_threshold)?;
+        w.write(&self.fw_ov_control)?;
+        w.write(&self.fw_ov_sha3_start)?;
+        w.write(&self.fw_ov_rd_fifo_overflow)?;
+        w.write(&self.observe_fifo_thresh)?;
+        w.write(&self.recov_alert_sts)?;
+        w.write(&self.err_code_test)?;
+        w.write(&self.reported_failures)?;
+        w.write(&self.health_tester)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.interrupt_state)?;
+        r.read(&mut self.interrupt_enable)?;
+        r.read(&mut self.me_regwen)?;
+        r.read(&mut self.sw_regupd)?;
+        r.read(&mut self.module_enable)?;
+        r.read(&mut self.conf)?;
+        r.read(&mut self.entropy_control)?;
+        r.read(&mut self.health_test_windows)?;
+        r.read(&mut self.repcnt_thresholds)?;
+        r.read(&mut self.repcnts_thresholds)?;
+        r.read(&mut self.adaptp_hi_thresholds)?;
+        r.read(&mut self.adaptp_lo_thresholds)?;
+        r.read(&mut self.bucket_thresholds)?;
+        r.read(&mut self.markov_hi_thresholds)?;
+        r.read(&mut self.markov_lo_thresholds)?;
+        r.read(&mut self.extht_hi_thresholds)?;
+        r.read(&mut self.extht_lo_thresholds)?;
+        r.read(&mut self.alert_threshold)?;
+        r.read(&mut self.fw_ov_control)?;
+        r.read(&mut self.fw_ov_sha3_start)?;
+        r.read(&mut self.fw_ov_rd_fifo_overflow)?;
+        r.read(&mut self.observe_fifo_thresh)?;
+        r.read(&mut self.recov_alert_sts)?;
+        r.read(&mut self.err_code_test)?;
+        r.read(&mut self.reported_failures)?;
+        r.read(&mut self.health_tester)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use caliptra_emu_bus::Bus;
+    use caliptra_hw_model_types::NoiseSourceFault;
+
+    const MODULE_ENABLE: u32 = 0x20;
+    const CONF: u32 = 0x24;
+    const REPCNT_THRESHOLDS: u32 = 0x34;
+    const ADAPTP_HI_THRESHOLDS: u32 = 0x3c;
+    const ADAPTP_LO_THRESHOLDS: u32 = 0x40;
+    const REPCNT_HI_WATERMARKS: u32 = 0x58;
+    const ADAPTP_HI_WATERMARKS: u32 = 0x60;
+    const REPCNT_TOTAL_FAILS: u32 = 0x7c;
+    const ALERT_SUMMARY_FAIL_COUNTS: u32 = 0xa4;
+    const ALERT_FAIL_COUNTS: u32 = 0xa8;
+    const MAIN_SM_STATE: u32 = 0xe0;
+    const INTERRUPT_STATE: u32 = 0x00;
+
+    /// Nibbles that toggle every wire on every nibble
+    fn healthy_nibbles() -> Box<dyn Iterator<Item = u8>> {
+        Box::new([0b0101, 0b1010].into_iter().cycle())
+    }
+
+    fn read(entropy_src: &mut EntropySrc, addr: u32) -> u32 {
+        entropy_src.read(RvSize::Word, addr).unwrap()
+    }
+
+    fn write(entropy_src: &mut EntropySrc, addr: u32, val: u32) {
+        entropy_src.write(RvSize::Word, addr, val).unwrap()
+    }
+
+    /// Configure the thresholds the same way as the CSRNG driver and enable
+    /// the module.
+    fn enable(entropy_src: &mut EntropySrc) {
+        write(entropy_src, REPCNT_THRESHOLDS, 41);
+        write(entropy_src, ADAPTP_HI_THRESHOLDS, 1536);
+        write(entropy_src, ADAPTP_LO_THRESHOLDS, 512);
+        write(entropy_src, CONF, 0x909096);
+        write(entropy_src, MODULE_ENABLE, MultiBitBool::True as u32);
+    }
+
+    #[test]
+    fn test_healthy_boot() {
+        let mut entropy_src = EntropySrc::new(healthy_nibbles(), None);
+        assert_eq!(read(&mut entropy_src, MAIN_SM_STATE), MAIN_SM_IDLE);
+
+        enable(&mut entropy_src);
+        assert_eq!(
+            read(&mut entropy_src, MAIN_SM_STATE),
+            MAIN_SM_CONT_HT_RUNNING
+        );
+        assert_eq!(read(&mut entropy_src, ALERT_SUMMARY_FAIL_COUNTS), 0);
+        assert_eq!(read(&mut entropy_src, ADAPTP_HI_WATERMARKS), 1024);
+        assert_eq!(read(&mut entropy_src, INTERRUPT_STATE), 0);
+
+        // Configuration is locked while enabled.
+        write(&mut entropy_src, REPCNT_THRESHOLDS, 2);
+        assert_eq!(read(&mut entropy_src, REPCNT_THRESHOLDS), 41);
+    }
+
+    #[test]
+    fn test_boot_faults() {
+        // Which ALERT_FAIL_COUNTS field each fault should trip
+        const REPCNT: u32 = 0x00f0;
+        const ADAPTP_HI: u32 = 0x0f00;
+        const ADAPTP_LO: u32 = 0xf000;
+
+        for (fault, failing_test) in [
+            (NoiseSourceFault::RepetitionCount, REPCNT),
+            (NoiseSourceFault::AdaptiveProportionHi, ADAPTP_HI),
+            (NoiseSourceFault::AdaptiveProportionLo, ADAPTP_LO),
+            (
+                NoiseSourceFault::StuckBit {
+                    wire: 2,
+                    value: false,
+                },
+                REPCNT,
+            ),
+        ] {
+            let mut entropy_src =
+                EntropySrc::new(healthy_nibbles(), Some(EntropySrcFault::at_boot(fault)));
+            enable(&mut entropy_src);
+
+            assert_eq!(
+                read(&mut entropy_src, MAIN_SM_STATE),
+                MAIN_SM_ALERT_HANG,
+                "{fault:?}"
+            );
+            let fail_counts = read(&mut entropy_src, ALERT_FAIL_COUNTS);
+            assert_ne!(fail_counts & failing_test, 0, "{fault:?}");
+            assert_eq!(fail_counts & !failing_test, 0, "{fault:?}");
+            assert_ne!(read(&mut entropy_src, ALERT_SUMMARY_FAIL_COUNTS), 0);
+            assert_eq!(
+                read(&mut entropy_src, INTERRUPT_STATE),
+                INTR_ES_HEALTH_TEST_FAILED
+            );
+        }
+    }
+
+    #[test]
+    fn test_continuous_fault() {
+        let mut entropy_src = EntropySrc::new(healthy_nibbles(), None);
+        enable(&mut entropy_src);
+
+        entropy_src.inject_fault(Some(EntropySrcFault {
+            fault: NoiseSourceFault::RepetitionCount,
+            after_nibbles: 16,
+        }));
+        // The boot-time nibbles are already tested; draw them first.
+        for _ in 0..512 + 16 {
+            entropy_src.next();
+        }
+        assert_eq!(
+            read(&mut entropy_src, MAIN_SM_STATE),
+            MAIN_SM_CONT_HT_RUNNING
+        );
+
+        for _ in 0..64 {
+            entropy_src.next();
+        }
+        assert_eq!(read(&mut entropy_src, MAIN_SM_STATE), MAIN_SM_ALERT_HANG);
+        assert_ne!(read(&mut entropy_src, REPCNT_TOTAL_FAILS), 0);
+        assert!(read(&mut entropy_src, REPCNT_HI_WATERMARKS) >= 41);
+
+        // Disabling the module clears the statistics.
+        write(&mut entropy_src, MODULE_ENABLE, MultiBitBool::False as u32);
+        assert_eq!(read(&mut entropy_src, MAIN_SM_STATE), MAIN_SM_IDLE);
+        assert_eq!(read(&mut entropy_src, REPCNT_TOTAL_FAILS), 0);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let fault = EntropySrcFault {
+            fault: NoiseSourceFault::AdaptiveProportionHi,
+            after_nibbles: 1024,
+        };
+        let mut entropy_src = EntropySrc::new(healthy_nibbles(), Some(fault));
+        enable(&mut entropy_src);
+
+        let mut w = SnapshotWriter::new();
+        entropy_src.save(&mut w).unwrap();
+        let snapshot = w.into_bytes();
+
+        let mut restored = EntropySrc::new(healthy_nibbles(), None);
+        restored
+            .restore(&mut SnapshotReader::new(&snapshot))
+            .unwrap();
+        assert_eq!(read(&mut restored, MAIN_SM_STATE), MAIN_SM_CONT_HT_RUNNING);
+        assert_eq!(read(&mut restored, REPCNT_THRESHOLDS), 41);
+
+        // The fault carries over with the rest of the state.
+        for _ in 0..1024 + 512 {
+            restored.next();
+        }
+        assert_eq!(read(&mut restored, MAIN_SM_STATE), MAIN_SM_ALERT_HANG);
+    }
+}
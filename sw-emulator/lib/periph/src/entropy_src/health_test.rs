@@ -1,43 +1,46 @@
 // Licensed under the Apache-2.0 license
 
-use super::BITS_PER_NIBBLE;
+use super::{NoiseSource, BITS_PER_NIBBLE};
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_registers::entropy_src::regs::{
     AdaptpHiThresholdsReadVal, AdaptpLoThresholdsReadVal, RepcntThresholdsReadVal,
 };
 
-const HEALTH_TEST_WINDOW_BITS: usize = 2048;
-
 pub struct HealthTester {
-    itrng_nibbles: Box<dyn Iterator<Item = u8>>,
+    noise_source: NoiseSource,
     pub repcnt: RepetitionCountTester,
     pub adaptp: AdaptiveProportionTester,
     boot_time_nibbles: Vec<u8>,
 }
 
 impl HealthTester {
-    pub fn new(itrng_nibbles: Box<dyn Iterator<Item = u8>>) -> Self {
+    pub fn new(noise_source: NoiseSource) -> Self {
         Self {
-            itrng_nibbles,
+            noise_source,
             repcnt: RepetitionCountTester::new(),
             adaptp: AdaptiveProportionTester::new(),
             boot_time_nibbles: Vec::new(),
         }
     }
 
+    pub fn noise_source_mut(&mut self) -> &mut NoiseSource {
+        &mut self.noise_source
+    }
+
     pub fn test_boot_window(&mut self) {
-        const NUM_NIBBLES: usize = HEALTH_TEST_WINDOW_BITS / BITS_PER_NIBBLE;
+        let num_nibbles = self.adaptp.window_bits() / BITS_PER_NIBBLE;
 
         self.boot_time_nibbles = self
-            .itrng_nibbles
+            .noise_source
             .by_ref()
-            .take(NUM_NIBBLES)
+            .take(num_nibbles)
             .inspect(|nibble| {
                 self.repcnt.feed(*nibble);
                 self.adaptp.feed(*nibble);
             })
             .collect();
 
-        assert_eq!(self.boot_time_nibbles.len(), NUM_NIBBLES, "itrng iterator should provide at least {NUM_NIBBLES} nibbles for boot-time health testing");
+        assert_eq!(self.boot_time_nibbles.len(), num_nibbles, "itrng iterator should provide at least {num_nibbles} nibbles for boot-time health testing");
 
         // We'll want to pull these FIFO.
         self.boot_time_nibbles.reverse();
@@ -46,6 +49,33 @@ impl HealthTester {
     pub fn failures(&self) -> u32 {
         self.repcnt.failures() + self.adaptp.lo_failures() + self.adaptp.hi_failures()
     }
+
+    /// Discard the test state, statistics and any undelivered boot-time
+    /// nibbles, as the hardware does when the module is disabled. Thresholds
+    /// are kept.
+    pub fn clear(&mut self) {
+        self.repcnt.clear();
+        self.adaptp.clear();
+        self.boot_time_nibbles.clear();
+    }
+}
+
+impl Snapshot for HealthTester {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.noise_source)?;
+        w.write(&self.repcnt)?;
+        w.write(&self.adaptp)?;
+        w.write(&self.boot_time_nibbles)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.noise_source)?;
+        r.read(&mut self.repcnt)?;
+        r.read(&mut self.adaptp)?;
+        r.read(&mut self.boot_time_nibbles)?;
+        Ok(())
+    }
 }
 
 impl Iterator for HealthTester {
@@ -58,7 +88,7 @@ impl Iterator for HealthTester {
         } else {
             // Then yield directly from the TRNG. Feed nibbles through health checks
             // for continuous testing.
-            let nibble = self.itrng_nibbles.next()?;
+            let nibble = self.noise_source.next()?;
             self.repcnt.feed(nibble);
             self.adaptp.feed(nibble);
             Some(nibble)
@@ -66,17 +96,50 @@ impl Iterator for HealthTester {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 enum Bit {
+    #[default]
     Zero,
     One,
 }
 
+impl Snapshot for Bit {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&(*self == Bit::One))
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        *self = if r.read_value()? { Bit::One } else { Bit::Zero };
+        Ok(())
+    }
+}
+
 pub struct RepetitionCountTester {
     threshold: u32,
     prev_nibble: [Option<Bit>; BITS_PER_NIBBLE],
     repetition_count: [u32; BITS_PER_NIBBLE],
     failures: u32,
+    hi_watermark: u32,
+}
+
+impl Snapshot for RepetitionCountTester {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.threshold)?;
+        w.write(&self.prev_nibble)?;
+        w.write(&self.repetition_count)?;
+        w.write(&self.failures)?;
+        w.write(&self.hi_watermark)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.threshold)?;
+        r.read(&mut self.prev_nibble)?;
+        r.read(&mut self.repetition_count)?;
+        r.read(&mut self.failures)?;
+        r.read(&mut self.hi_watermark)?;
+        Ok(())
+    }
 }
 
 impl RepetitionCountTester {
@@ -86,6 +149,7 @@ impl RepetitionCountTester {
             prev_nibble: [None; BITS_PER_NIBBLE],
             repetition_count: [1; BITS_PER_NIBBLE], // the hardware starts the counter at 1
             failures: 0,
+            hi_watermark: 0,
         }
     }
 
@@ -97,6 +161,18 @@ impl RepetitionCountTester {
         self.failures
     }
 
+    /// The longest run of a repeated bit seen on any RNG wire.
+    pub fn hi_watermark(&self) -> u32 {
+        self.hi_watermark
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self {
+            threshold: self.threshold,
+            ..Self::new()
+        };
+    }
+
     pub fn feed(&mut self, nibble: u8) {
         // Replicate the logic in caliptra-rtl/src/entropy_src/rtl/entropy_src_repcnt_ht.sv.
         // If any of the four RNG wires repeats a bit, increment a wire-specific repetition counter.
@@ -114,6 +190,7 @@ impl RepetitionCountTester {
 
             if is_repeat {
                 self.repetition_count[i] += 1;
+                self.hi_watermark = self.hi_watermark.max(self.repetition_count[i]);
 
                 if self.repetition_count[i] >= self.threshold {
                     self.failures += 1;
@@ -127,26 +204,68 @@ impl RepetitionCountTester {
 }
 
 pub struct AdaptiveProportionTester {
+    window_bits: usize,
     lo_threshold: u32,
     hi_threshold: u32,
     lo_failures: u32,
     hi_failures: u32,
+    lo_watermark: u32,
+    hi_watermark: u32,
     num_ones_seen: u32,
     num_bits_seen: usize,
 }
 
+impl Snapshot for AdaptiveProportionTester {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.window_bits)?;
+        w.write(&self.lo_threshold)?;
+        w.write(&self.hi_threshold)?;
+        w.write(&self.lo_failures)?;
+        w.write(&self.hi_failures)?;
+        w.write(&self.lo_watermark)?;
+        w.write(&self.hi_watermark)?;
+        w.write(&self.num_ones_seen)?;
+        w.write(&self.num_bits_seen)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.window_bits)?;
+        r.read(&mut self.lo_threshold)?;
+        r.read(&mut self.hi_threshold)?;
+        r.read(&mut self.lo_failures)?;
+        r.read(&mut self.hi_failures)?;
+        r.read(&mut self.lo_watermark)?;
+        r.read(&mut self.hi_watermark)?;
+        r.read(&mut self.num_ones_seen)?;
+        r.read(&mut self.num_bits_seen)?;
+        Ok(())
+    }
+}
+
 impl AdaptiveProportionTester {
     pub fn new() -> Self {
         Self {
+            window_bits: 2048,
             lo_threshold: 0,
             hi_threshold: 0xffff,
             lo_failures: 0,
             hi_failures: 0,
+            lo_watermark: 0xffff,
+            hi_watermark: 0,
             num_ones_seen: 0,
             num_bits_seen: 0,
         }
     }
 
+    pub fn window_bits(&self) -> usize {
+        self.window_bits
+    }
+
+    pub fn set_window_bits(&mut self, window_bits: usize) {
+        self.window_bits = window_bits;
+    }
+
     pub fn set_lo_threshold(&mut self, threshold: AdaptpLoThresholdsReadVal) {
         self.lo_threshold = threshold.fips_thresh();
     }
@@ -163,6 +282,25 @@ impl AdaptiveProportionTester {
         self.hi_failures
     }
 
+    /// The fewest ones seen in a complete window.
+    pub fn lo_watermark(&self) -> u32 {
+        self.lo_watermark
+    }
+
+    /// The most ones seen in a complete window.
+    pub fn hi_watermark(&self) -> u32 {
+        self.hi_watermark
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self {
+            window_bits: self.window_bits,
+            lo_threshold: self.lo_threshold,
+            hi_threshold: self.hi_threshold,
+            ..Self::new()
+        };
+    }
+
     pub fn feed(&mut self, nibble: u8) {
         // Replicate the logic in caliptra-rtl/src/entropy_src/rtl/entropy_src_adaptp_ht.sv.
         assert!(
@@ -172,7 +310,10 @@ impl AdaptiveProportionTester {
         self.num_ones_seen += nibble.count_ones();
         self.num_bits_seen += BITS_PER_NIBBLE;
 
-        if self.num_bits_seen >= HEALTH_TEST_WINDOW_BITS {
+        if self.num_bits_seen >= self.window_bits {
+            self.lo_watermark = self.lo_watermark.min(self.num_ones_seen);
+            self.hi_watermark = self.hi_watermark.max(self.num_ones_seen);
+
             if self.num_ones_seen < self.lo_threshold {
                 self.lo_failures += 1;
             }
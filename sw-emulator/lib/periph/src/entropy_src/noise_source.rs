@@ -0,0 +1,133 @@
+// Licensed under the Apache-2.0 license
+
+use super::BITS_PER_NIBBLE;
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
+use caliptra_hw_model_types::{EntropySrcFault, NoiseSourceFault};
+
+/// The physical noise source feeding the four RNG wires, with an optional
+/// injected fault.
+pub struct NoiseSource {
+    itrng_nibbles: Box<dyn Iterator<Item = u8>>,
+
+    /// The injected fault and the index of the first faulty nibble.
+    fault: Option<(NoiseSourceFault, usize)>,
+
+    /// Number of nibbles produced so far.
+    num_nibbles: usize,
+
+    /// The nibble the RNG wires carried when the fault took effect.
+    fault_nibble: u8,
+}
+
+impl NoiseSource {
+    pub fn new(itrng_nibbles: Box<dyn Iterator<Item = u8>>) -> Self {
+        Self {
+            itrng_nibbles,
+            fault: None,
+            num_nibbles: 0,
+            fault_nibble: 0,
+        }
+    }
+
+    /// Inject `fault`, counting its delay from the next nibble produced. Pass
+    /// `None` to repair the noise source.
+    pub fn inject_fault(&mut self, fault: Option<EntropySrcFault>) {
+        if let Some(EntropySrcFault {
+            fault: NoiseSourceFault::StuckBit { wire, .. },
+            ..
+        }) = fault
+        {
+            assert!(
+                usize::from(wire) < BITS_PER_NIBBLE,
+                "RNG wire {wire} does not exist"
+            );
+        }
+        self.fault = fault.map(|f| (f.fault, self.num_nibbles + f.after_nibbles));
+    }
+
+    fn apply_fault(&self, fault: NoiseSourceFault, index: usize, nibble: u8) -> u8 {
+        const ALL_ONES: u8 = 0b1111;
+        const ALL_ZEROS: u8 = 0b0000;
+
+        // Flip every wire once per 8 nibbles so the biased faults stay well
+        // under any sensible repetition count threshold.
+        let minority = index % 8 == 7;
+        match fault {
+            NoiseSourceFault::RepetitionCount => self.fault_nibble,
+            NoiseSourceFault::AdaptiveProportionHi if minority => ALL_ZEROS,
+            NoiseSourceFault::AdaptiveProportionHi => ALL_ONES,
+            NoiseSourceFault::AdaptiveProportionLo if minority => ALL_ONES,
+            NoiseSourceFault::AdaptiveProportionLo => ALL_ZEROS,
+            NoiseSourceFault::StuckBit { wire, value } => {
+                let mask = 1 << wire;
+                if value {
+                    nibble | mask
+                } else {
+                    nibble & !mask
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for NoiseSource {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let nibble = self.itrng_nibbles.next()?;
+        let index = self.num_nibbles;
+        self.num_nibbles += 1;
+
+        match self.fault {
+            Some((fault, start)) if index >= start => {
+                if index == start {
+                    self.fault_nibble = nibble;
+                }
+                Some(self.apply_fault(fault, index - start, nibble))
+            }
+            _ => Some(nibble),
+        }
+    }
+}
+
+/// The iTRNG source is not saved; nibbles drawn after a restore come from the
+/// source the noise source was constructed with.
+impl Snapshot for NoiseSource {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        let (kind, wire, value, start) = match self.fault {
+            None => (0u8, 0, false, 0),
+            Some((fault, start)) => match fault {
+                NoiseSourceFault::RepetitionCount => (1, 0, false, start),
+                NoiseSourceFault::AdaptiveProportionHi => (2, 0, false, start),
+                NoiseSourceFault::AdaptiveProportionLo => (3, 0, false, start),
+                NoiseSourceFault::StuckBit { wire, value } => (4, wire, value, start),
+            },
+        };
+        w.write(&kind)?;
+        w.write(&wire)?;
+        w.write(&value)?;
+        w.write(&start)?;
+        w.write(&self.num_nibbles)?;
+        w.write(&self.fault_nibble)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        let kind = r.read_value::<u8>()?;
+        let wire = r.read_value::<u8>()?;
+        let value = r.read_value::<bool>()?;
+        let start = r.read_value::<usize>()?;
+        let fault = match kind {
+            0 => None,
+            1 => Some(NoiseSourceFault::RepetitionCount),
+            2 => Some(NoiseSourceFault::AdaptiveProportionHi),
+            3 => Some(NoiseSourceFault::AdaptiveProportionLo),
+            4 => Some(NoiseSourceFault::StuckBit { wire, value }),
+            _ => return Err(SnapshotError::InvalidData("unknown noise source fault")),
+        };
+        self.fault = fault.map(|fault| (fault, start));
+        r.read(&mut self.num_nibbles)?;
+        r.read(&mut self.fault_nibble)?;
+        Ok(())
+    }
+}
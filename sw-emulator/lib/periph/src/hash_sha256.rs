@@ -12,10 +12,13 @@ Abstract:
 
 --*/
 
+use crate::intr_block::{vector, NOTIF_CMD_DONE_STS};
+use crate::IntrBlock;
 use caliptra_emu_bus::{
     ActionHandle, BusError, Clock, ReadOnlyMemory, ReadOnlyRegister, ReadWriteMemory,
-    ReadWriteRegister, Timer,
+    ReadWriteRegister, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter, Timer,
 };
+use caliptra_emu_cpu::Pic;
 use caliptra_emu_crypto::{Sha256, Sha256Mode};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvData, RvSize};
@@ -92,6 +95,10 @@ pub struct HashSha256 {
     #[peripheral(offset = 0x0000_0100, mask = 0x0000_00ff)]
     hash: ReadOnlyMemory<SHA256_HASH_SIZE>,
 
+    /// Interrupt Block
+    #[peripheral(offset = 0x0000_0800, mask = 0x0000_07ff)]
+    intr: IntrBlock,
+
     /// SHA256 engine
     sha256: Sha256,
 
@@ -114,7 +121,7 @@ impl HashSha256 {
     const VERSION1_VAL: RvData = 0x00000000;
 
     /// Create a new instance of SHA-512 Engine
-    pub fn new(clock: &Clock) -> Self {
+    pub fn new(clock: &Clock, pic: &Pic) -> Self {
         Self {
             sha256: Sha256::new(Sha256Mode::Sha256), // Default SHA256 mode
             name0: ReadOnlyRegister::new(Self::NAME0_VAL),
@@ -125,6 +132,7 @@ impl HashSha256 {
             status: ReadOnlyRegister::new(Status::READY::SET.value),
             block: ReadWriteMemory::new(),
             hash: ReadOnlyMemory::new(),
+            intr: IntrBlock::new(pic, vector::SHA256_ERROR),
             timer: Timer::new(clock),
             op_complete_action: None,
         }
@@ -203,6 +211,8 @@ impl HashSha256 {
             self.status
                 .reg
                 .modify(Status::READY::SET + Status::VALID::SET);
+
+            self.intr.set_notif(NOTIF_CMD_DONE_STS);
         }
     }
 
@@ -226,6 +236,30 @@ impl HashSha256 {
     }
 }
 
+impl Snapshot for HashSha256 {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.control)?;
+        w.write(&self.status)?;
+        w.write(&self.block)?;
+        w.write(&self.hash)?;
+        w.write(&self.sha256)?;
+        w.write(&self.intr)?;
+        self.timer.save_action(&self.op_complete_action, w)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.control)?;
+        r.read(&mut self.status)?;
+        r.read(&mut self.block)?;
+        r.read(&mut self.hash)?;
+        r.read(&mut self.sha256)?;
+        r.read(&mut self.intr)?;
+        self.op_complete_action = self.timer.restore_action(r)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,7 +279,7 @@ mod tests {
 
     #[test]
     fn test_name_read() {
-        let mut sha256 = HashSha256::new(&Clock::new());
+        let mut sha256 = HashSha256::new(&Clock::new(), &Pic::new());
 
         let name0 = sha256.read(RvSize::Word, OFFSET_NAME0).unwrap();
         let mut name0 = String::from_utf8_lossy(&name0.to_le_bytes()).to_string();
@@ -259,7 +293,7 @@ mod tests {
 
     #[test]
     fn test_version_read() {
-        let mut sha256 = HashSha256::new(&Clock::new());
+        let mut sha256 = HashSha256::new(&Clock::new(), &Pic::new());
 
         let version0 = sha256.read(RvSize::Word, OFFSET_VERSION0).unwrap();
         let version0 = String::from_utf8_lossy(&version0.to_le_bytes()).to_string();
@@ -272,19 +306,19 @@ mod tests {
 
     #[test]
     fn test_control_read() {
-        let mut sha256 = HashSha256::new(&Clock::new());
+        let mut sha256 = HashSha256::new(&Clock::new(), &Pic::new());
         assert_eq!(sha256.read(RvSize::Word, OFFSET_CONTROL).unwrap(), 0);
     }
 
     #[test]
     fn test_status_read() {
-        let mut sha256 = HashSha256::new(&Clock::new());
+        let mut sha256 = HashSha256::new(&Clock::new(), &Pic::new());
         assert_eq!(sha256.read(RvSize::Word, OFFSET_STATUS).unwrap(), 1);
     }
 
     #[test]
     fn test_block_read_write() {
-        let mut sha256 = HashSha256::new(&Clock::new());
+        let mut sha256 = HashSha256::new(&Clock::new(), &Pic::new());
         for addr in (OFFSET_BLOCK..(OFFSET_BLOCK + SHA256_BLOCK_SIZE as u32)).step_by(4) {
             assert_eq!(sha256.write(RvSize::Word, addr, u32::MAX).ok(), Some(()));
             assert_eq!(sha256.read(RvSize::Word, addr).ok(), Some(u32::MAX));
@@ -293,7 +327,7 @@ mod tests {
 
     #[test]
     fn test_hash_read_write() {
-        let mut sha256 = HashSha256::new(&Clock::new());
+        let mut sha256 = HashSha256::new(&Clock::new(), &Pic::new());
         for addr in (OFFSET_HASH..(OFFSET_HASH + SHA256_HASH_SIZE as u32)).step_by(4) {
             assert_eq!(sha256.read(RvSize::Word, addr).ok(), Some(0));
             assert_eq!(
@@ -328,7 +362,7 @@ mod tests {
         block_arr.to_big_endian();
 
         let clock = Clock::new();
-        let mut sha256 = HashSha256::new(&clock);
+        let mut sha256 = HashSha256::new(&clock, &Pic::new());
 
         // Process each block via the SHA engine.
         for idx in 0..totalblocks {
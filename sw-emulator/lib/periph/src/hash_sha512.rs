@@ -13,16 +13,19 @@ Abstract:
 --*/
 
 use crate::helpers::words_from_bytes_le;
+use crate::intr_block::{vector, NOTIF_CMD_DONE_STS};
 use crate::key_vault::KeyUsage;
-use crate::KeyVault;
+use crate::{IntrBlock, KeyVault};
 use caliptra_emu_bus::{
-    ActionHandle, Bus, BusError, Clock, ReadOnlyMemory, ReadOnlyRegister, ReadWriteRegister, Timer,
-    WriteOnlyRegister,
+    ActionHandle, Bus, BusError, Clock, ReadOnlyMemory, ReadOnlyRegister, ReadWriteRegister,
+    Snapshot, SnapshotError, SnapshotReader, SnapshotWriter, Timer, WriteOnlyRegister,
 };
+use caliptra_emu_cpu::Pic;
 use caliptra_emu_crypto::EndianessTransform;
 use caliptra_emu_crypto::{Sha512, Sha512Mode};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
+use caliptra_hw_model_types::CryptoTiming;
 use std::cell::RefCell;
 use std::rc::Rc;
 use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
@@ -107,12 +110,6 @@ const SHA512_BLOCK_SIZE_WORDS: usize = 128 >> 2;
 const SHA512_HASH_SIZE: usize = 64;
 const SHA384_HASH_SIZE: usize = 48;
 
-/// The number of CPU clock cycles it takes to perform initialization action.
-const INIT_TICKS: u64 = 1000;
-
-/// The number of CPU clock cycles it takes to perform the hash update action.
-const UPDATE_TICKS: u64 = 1000;
-
 /// The number of CPU clock cycles read and write keys from key vault
 const KEY_RW_TICKS: u64 = 100;
 
@@ -205,6 +202,10 @@ pub struct HashSha512Regs {
     #[register_array(offset = 0x0000_0638, write_fn = write_access_fault)]
     pcr_hash_digest: [u32; SHA384_HASH_SIZE / 4],
 
+    /// Interrupt Block
+    #[peripheral(offset = 0x0000_0800, mask = 0x0000_07ff)]
+    intr: IntrBlock,
+
     /// SHA512 engine
     sha512: Sha512,
 
@@ -213,6 +214,9 @@ pub struct HashSha512Regs {
 
     timer: Timer,
 
+    /// Cycles taken by each operation
+    timing: CryptoTiming,
+
     /// Operation complete action
     op_complete_action: Option<ActionHandle>,
 
@@ -242,7 +246,7 @@ impl HashSha512Regs {
     const VERSION1_VAL: RvData = 0x00000000;
 
     /// Create a new instance of SHA-512 Engine
-    pub fn new(clock: &Clock, key_vault: KeyVault) -> Self {
+    pub fn new(clock: &Clock, key_vault: KeyVault, pic: &Pic) -> Self {
         Self {
             sha512: Sha512::new(Sha512Mode::Sha512), // Default SHA512 mode
             name0: ReadOnlyRegister::new(Self::NAME0_VAL),
@@ -259,10 +263,12 @@ impl HashSha512Regs {
             pcr_hash_control: WriteOnlyRegister::new(0),
             pcr_hash_status: ReadOnlyRegister::new(PcrHashStatus::READY::SET.value),
             pcr_hash_digest: Default::default(),
+            intr: IntrBlock::new(pic, vector::SHA512_ERROR),
             block: Default::default(),
             hash: ReadOnlyMemory::new(),
             key_vault,
             timer: Timer::new(clock),
+            timing: CryptoTiming::default(),
             op_complete_action: None,
             op_block_read_complete_action: None,
             op_hash_write_complete_action: None,
@@ -327,14 +333,16 @@ impl HashSha512Regs {
                     .update(&sha512_block_bytes_from_words_le(&self.block));
 
                 // Schedule a future call to poll() complete the operation.
-                self.op_complete_action = Some(self.timer.schedule_poll_in(INIT_TICKS));
+                self.op_complete_action =
+                    Some(self.timer.schedule_poll_in(self.timing.sha512_block));
             } else if self.control.reg.is_set(Control::NEXT) {
                 // Update the SHA512 engine with a new block
                 self.sha512
                     .update(&sha512_block_bytes_from_words_le(&self.block));
 
                 // Schedule a future call to poll() complete the operation.
-                self.op_complete_action = Some(self.timer.schedule_poll_in(UPDATE_TICKS));
+                self.op_complete_action =
+                    Some(self.timer.schedule_poll_in(self.timing.sha512_block));
             }
         }
 
@@ -469,7 +477,8 @@ impl HashSha512Regs {
             .reg
             .modify(PcrHashStatus::VALID::CLEAR + PcrHashStatus::READY::CLEAR);
 
-        self.op_pcr_gen_hash_complete_action = Some(self.timer.schedule_poll_in(UPDATE_TICKS));
+        self.op_pcr_gen_hash_complete_action =
+            Some(self.timer.schedule_poll_in(self.timing.sha512_pcr_hash));
 
         Ok(())
     }
@@ -491,6 +500,7 @@ impl HashSha512Regs {
     fn poll(&mut self) {
         if self.timer.fired(&mut self.op_complete_action) {
             self.op_complete();
+            self.intr.set_notif(NOTIF_CMD_DONE_STS);
         } else if self.timer.fired(&mut self.op_block_read_complete_action) {
             self.block_read_complete();
         } else if self.timer.fired(&mut self.op_hash_write_complete_action) {
@@ -694,12 +704,17 @@ pub struct HashSha512 {
 
 impl HashSha512 {
     /// Create a new instance of Hash SHA-512
-    pub fn new(clock: &Clock, key_vault: KeyVault) -> Self {
+    pub fn new(clock: &Clock, key_vault: KeyVault, pic: &Pic) -> Self {
         Self {
-            regs: Rc::new(RefCell::new(HashSha512Regs::new(clock, key_vault))),
+            regs: Rc::new(RefCell::new(HashSha512Regs::new(clock, key_vault, pic))),
         }
     }
 
+    /// Set the number of cycles each operation takes
+    pub fn set_timing(&mut self, timing: CryptoTiming) {
+        self.regs.borrow_mut().timing = timing;
+    }
+
     /// Export the PCR hash digest
     pub fn pcr_hash_digest(&self) -> [u8; 48] {
         self.regs
@@ -727,6 +742,65 @@ impl Bus for HashSha512 {
     }
 }
 
+impl Snapshot for HashSha512Regs {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.control)?;
+        w.write(&self.status)?;
+        w.write(&self.block)?;
+        w.write(&self.hash)?;
+        w.write(&self.block_read_ctrl)?;
+        w.write(&self.block_read_status)?;
+        w.write(&self.hash_write_ctrl)?;
+        w.write(&self.hash_write_status)?;
+        w.write(&self.pcr_gen_hash_nonce)?;
+        w.write(&self.pcr_hash_control)?;
+        w.write(&self.pcr_hash_status)?;
+        w.write(&self.pcr_hash_digest)?;
+        w.write(&self.sha512)?;
+        w.write(&self.intr)?;
+        self.timer.save_action(&self.op_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_block_read_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_hash_write_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_pcr_gen_hash_complete_action, w)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.control)?;
+        r.read(&mut self.status)?;
+        r.read(&mut self.block)?;
+        r.read(&mut self.hash)?;
+        r.read(&mut self.block_read_ctrl)?;
+        r.read(&mut self.block_read_status)?;
+        r.read(&mut self.hash_write_ctrl)?;
+        r.read(&mut self.hash_write_status)?;
+        r.read(&mut self.pcr_gen_hash_nonce)?;
+        r.read(&mut self.pcr_hash_control)?;
+        r.read(&mut self.pcr_hash_status)?;
+        r.read(&mut self.pcr_hash_digest)?;
+        r.read(&mut self.sha512)?;
+        r.read(&mut self.intr)?;
+        self.op_complete_action = self.timer.restore_action(r)?;
+        self.op_block_read_complete_action = self.timer.restore_action(r)?;
+        self.op_hash_write_complete_action = self.timer.restore_action(r)?;
+        self.op_pcr_gen_hash_complete_action = self.timer.restore_action(r)?;
+        Ok(())
+    }
+}
+
+impl Snapshot for HashSha512 {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.regs.borrow().save(w)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.regs.borrow_mut().restore(r)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -754,7 +828,7 @@ mod tests {
 
     #[test]
     fn test_name_read() {
-        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new());
+        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new(), &Pic::new());
 
         let name0 = sha512.read(RvSize::Word, OFFSET_NAME0).unwrap();
         let mut name0 = String::from_utf8_lossy(&name0.to_le_bytes()).to_string();
@@ -768,7 +842,7 @@ mod tests {
 
     #[test]
     fn test_version_read() {
-        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new());
+        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new(), &Pic::new());
 
         let version0 = sha512.read(RvSize::Word, OFFSET_VERSION0).unwrap();
         let version0 = String::from_utf8_lossy(&version0.to_le_bytes()).to_string();
@@ -781,19 +855,19 @@ mod tests {
 
     #[test]
     fn test_control_read() {
-        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new());
+        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new(), &Pic::new());
         assert_eq!(sha512.read(RvSize::Word, OFFSET_CONTROL).unwrap(), 0);
     }
 
     #[test]
     fn test_status_read() {
-        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new());
+        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new(), &Pic::new());
         assert_eq!(sha512.read(RvSize::Word, OFFSET_STATUS).unwrap(), 1);
     }
 
     #[test]
     fn test_block_read_write() {
-        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new());
+        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new(), &Pic::new());
         for addr in (OFFSET_BLOCK..(OFFSET_BLOCK + SHA512_BLOCK_SIZE as u32)).step_by(4) {
             assert_eq!(sha512.write(RvSize::Word, addr, u32::MAX).ok(), Some(()));
             assert_eq!(sha512.read(RvSize::Word, addr).ok(), Some(u32::MAX));
@@ -802,7 +876,7 @@ mod tests {
 
     #[test]
     fn test_hash_read_write() {
-        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new());
+        let mut sha512 = HashSha512Regs::new(&Clock::new(), KeyVault::new(), &Pic::new());
         for addr in (OFFSET_HASH..(OFFSET_HASH + SHA512_HASH_SIZE as u32)).step_by(4) {
             assert_eq!(sha512.read(RvSize::Word, addr).ok(), Some(0));
             assert_eq!(
@@ -946,7 +1020,7 @@ mod tests {
             );
         }
 
-        let mut sha512 = HashSha512Regs::new(&clock, key_vault);
+        let mut sha512 = HashSha512Regs::new(&clock, key_vault, &Pic::new());
 
         if hash_to_kv {
             // Instruct hash to be written to the key-vault.
@@ -1330,7 +1404,7 @@ mod tests {
         assert!(key_vault.write_pcr(pcr_id, pcr_data).is_ok());
         pcr_data.change_endianess();
 
-        let mut sha512 = HashSha512Regs::new(&clock, key_vault);
+        let mut sha512 = HashSha512Regs::new(&clock, key_vault, &Pic::new());
         // Enable pcr hash extend.
         let block_ctrl = InMemoryRegister::<u32, BlockReadControl::Register>::new(0);
         block_ctrl.modify(
@@ -13,12 +13,18 @@ Abstract:
 --*/
 
 use crate::helpers::bytes_from_words_le;
-use crate::{KeyUsage, KeyVault};
-use caliptra_emu_bus::{ActionHandle, BusError, Clock, ReadOnlyRegister, ReadWriteRegister, Timer};
+use crate::intr_block::{vector, NOTIF_CMD_DONE_STS};
+use crate::{IntrBlock, KeyUsage, KeyVault};
+use caliptra_emu_bus::{
+    ActionHandle, BusError, Clock, ReadOnlyRegister, ReadWriteRegister, Snapshot, SnapshotError,
+    SnapshotReader, SnapshotWriter, Timer,
+};
+use caliptra_emu_cpu::Pic;
 use caliptra_emu_crypto::EndianessTransform;
 use caliptra_emu_crypto::{Hmac512, Hmac512Mode};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvData, RvSize};
+use caliptra_hw_model_types::CryptoTiming;
 use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 use tock_registers::register_bitfields;
 use tock_registers::registers::InMemoryRegister;
@@ -93,12 +99,6 @@ const HMAC_BLOCK_SIZE: usize = 128;
 /// HMAC Tag Size
 const HMAC_TAG_SIZE: usize = 48;
 
-/// The number of CPU clock cycles it takes to perform initialization action.
-const INIT_TICKS: u64 = 1000;
-
-/// The number of CPU clock cycles it takes to perform the hash update action.
-const UPDATE_TICKS: u64 = 1000;
-
 /// The number of CPU clock cycles read and write keys from key vault
 const KEY_RW_TICKS: u64 = 100;
 
@@ -175,6 +175,10 @@ pub struct HmacSha384 {
     #[register(offset = 0x0000_0614)]
     tag_write_status: ReadOnlyRegister<u32, TagWriteStatus::Register>,
 
+    /// Interrupt Block
+    #[peripheral(offset = 0x0000_0800, mask = 0x0000_07ff)]
+    intr: IntrBlock,
+
     // True if the current key was read from the key-vault
     key_from_kv: bool,
 
@@ -193,6 +197,9 @@ pub struct HmacSha384 {
     /// Timer
     timer: Timer,
 
+    /// Cycles taken by each operation
+    timing: CryptoTiming,
+
     /// Operation complete action
     op_complete_action: Option<ActionHandle>,
 
@@ -225,11 +232,12 @@ impl HmacSha384 {
     ///
     /// * `clock` - Clock
     /// * `key_vault` - Key Vault
+    /// * `pic` - Programmable interrupt controller
     ///
     /// # Returns
     ///
     /// * `Self` - Instance of HMAC-SHA-384 Engine
-    pub fn new(clock: &Clock, key_vault: KeyVault) -> Self {
+    pub fn new(clock: &Clock, key_vault: KeyVault, pic: &Pic) -> Self {
         Self {
             hmac: Hmac512::<HMAC_KEY_SIZE>::new(Hmac512Mode::Sha384),
             name0: ReadOnlyRegister::new(Self::NAME0_VAL),
@@ -248,8 +256,10 @@ impl HmacSha384 {
             block_read_status: ReadOnlyRegister::new(KeyReadStatus::READY::SET.value),
             tag_write_ctrl: ReadWriteRegister::new(0),
             tag_write_status: ReadOnlyRegister::new(TagWriteStatus::READY::SET.value),
+            intr: IntrBlock::new(pic, vector::HMAC_ERROR),
             key_vault,
             timer: Timer::new(clock),
+            timing: CryptoTiming::default(),
             key_from_kv: false,
             block_from_kv: false,
             hide_tag_from_cpu: false,
@@ -260,6 +270,11 @@ impl HmacSha384 {
         }
     }
 
+    /// Set the number of cycles each operation takes
+    pub fn set_timing(&mut self, timing: CryptoTiming) {
+        self.timing = timing;
+    }
+
     fn read_access_fault(&mut self, _size: RvSize, _index: usize) -> Result<u32, BusError> {
         Err(BusError::LoadAccessFault)
     }
@@ -331,13 +346,15 @@ impl HmacSha384 {
                 );
 
                 // Schedule a future call to poll() complete the operation.
-                self.op_complete_action = Some(self.timer.schedule_poll_in(INIT_TICKS));
+                self.op_complete_action =
+                    Some(self.timer.schedule_poll_in(self.timing.hmac384_init));
             } else if self.control.reg.is_set(Control::NEXT) {
                 // Update a HMAC engine with a new block
                 self.hmac.update(&bytes_from_words_le(&self.block));
 
                 // Schedule a future call to poll() complete the operation.
-                self.op_complete_action = Some(self.timer.schedule_poll_in(UPDATE_TICKS));
+                self.op_complete_action =
+                    Some(self.timer.schedule_poll_in(self.timing.hmac384_next));
             }
         }
 
@@ -463,6 +480,7 @@ impl HmacSha384 {
     fn poll(&mut self) {
         if self.timer.fired(&mut self.op_complete_action) {
             self.op_complete();
+            self.intr.set_notif(NOTIF_CMD_DONE_STS);
         } else if self.timer.fired(&mut self.op_key_read_complete_action) {
             self.key_read_complete();
         } else if self.timer.fired(&mut self.op_block_read_complete_action) {
@@ -638,6 +656,61 @@ impl HmacSha384 {
     }
 }
 
+impl Snapshot for HmacSha384 {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.control)?;
+        w.write(&self.status)?;
+        w.write(&self.key)?;
+        w.write(&self.block)?;
+        w.write(&self.tag)?;
+        w.write(&self.lfsr_seed)?;
+        w.write(&self.key_read_ctrl)?;
+        w.write(&self.key_read_status)?;
+        w.write(&self.block_read_ctrl)?;
+        w.write(&self.block_read_status)?;
+        w.write(&self.tag_write_ctrl)?;
+        w.write(&self.tag_write_status)?;
+        w.write(&self.key_from_kv)?;
+        w.write(&self.block_from_kv)?;
+        w.write(&self.hide_tag_from_cpu)?;
+        w.write(&self.hmac)?;
+        w.write(&self.intr)?;
+        self.timer.save_action(&self.op_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_key_read_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_block_read_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_tag_write_complete_action, w)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.control)?;
+        r.read(&mut self.status)?;
+        r.read(&mut self.key)?;
+        r.read(&mut self.block)?;
+        r.read(&mut self.tag)?;
+        r.read(&mut self.lfsr_seed)?;
+        r.read(&mut self.key_read_ctrl)?;
+        r.read(&mut self.key_read_status)?;
+        r.read(&mut self.block_read_ctrl)?;
+        r.read(&mut self.block_read_status)?;
+        r.read(&mut self.tag_write_ctrl)?;
+        r.read(&mut self.tag_write_status)?;
+        r.read(&mut self.key_from_kv)?;
+        r.read(&mut self.block_from_kv)?;
+        r.read(&mut self.hide_tag_from_cpu)?;
+        r.read(&mut self.hmac)?;
+        r.read(&mut self.intr)?;
+        self.op_complete_action = self.timer.restore_action(r)?;
+        self.op_key_read_complete_action = self.timer.restore_action(r)?;
+        self.op_block_read_complete_action = self.timer.restore_action(r)?;
+        self.op_tag_write_complete_action = self.timer.restore_action(r)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -666,7 +739,7 @@ mod tests {
 
     #[test]
     fn test_name() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
 
         let name0 = hmac.read(RvSize::Word, OFFSET_NAME0).unwrap();
         let name0 = String::from_utf8_lossy(&name0.to_le_bytes()).to_string();
@@ -679,7 +752,7 @@ mod tests {
 
     #[test]
     fn test_version() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
 
         let version0 = hmac.read(RvSize::Word, OFFSET_VERSION0).unwrap();
         let version0 = String::from_utf8_lossy(&version0.to_le_bytes()).to_string();
@@ -692,19 +765,19 @@ mod tests {
 
     #[test]
     fn test_control() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
         assert_eq!(hmac.read(RvSize::Word, OFFSET_CONTROL).unwrap(), 0);
     }
 
     #[test]
     fn test_status() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
         assert_eq!(hmac.read(RvSize::Word, OFFSET_STATUS).unwrap(), 1);
     }
 
     #[test]
     fn test_key() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
         for addr in (OFFSET_KEY..(OFFSET_KEY + HMAC_KEY_SIZE as u32)).step_by(4) {
             assert_eq!(hmac.write(RvSize::Word, addr, 0xFF).ok(), Some(()));
             assert_eq!(
@@ -716,7 +789,7 @@ mod tests {
 
     #[test]
     fn test_block() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
         for addr in (OFFSET_BLOCK..(OFFSET_BLOCK + HMAC_BLOCK_SIZE as u32)).step_by(4) {
             assert_eq!(hmac.write(RvSize::Word, addr, u32::MAX).ok(), Some(()));
             assert_eq!(
@@ -728,7 +801,7 @@ mod tests {
 
     #[test]
     fn test_tag() {
-        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new());
+        let mut hmac = HmacSha384::new(&Clock::new(), KeyVault::new(), &Pic::new());
         for addr in (OFFSET_TAG..(OFFSET_TAG + HMAC_TAG_SIZE as u32)).step_by(4) {
             assert_eq!(hmac.read(RvSize::Word, addr).ok(), Some(0));
             assert_eq!(
@@ -924,7 +997,7 @@ mod tests {
             );
         }
 
-        let mut hmac = HmacSha384::new(&clock, key_vault);
+        let mut hmac = HmacSha384::new(&clock, key_vault, &Pic::new());
 
         if tag_to_kv {
             // Instruct tag to be read from key-vault.
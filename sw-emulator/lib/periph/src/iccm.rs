@@ -17,6 +17,7 @@ use caliptra_emu_bus::Clock;
 use caliptra_emu_bus::Ram;
 use caliptra_emu_bus::Timer;
 use caliptra_emu_bus::TimerAction;
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_emu_types::RvAddr;
 use caliptra_emu_types::RvData;
 use caliptra_emu_types::RvSize;
@@ -31,11 +32,17 @@ const ICCM_SIZE_BYTES: usize = 128 * 1024;
 
 impl Iccm {
     pub fn lock(&mut self) {
-        self.iccm.locked.set(true);
+        self.set_locked(true);
     }
 
     pub fn unlock(&mut self) {
-        self.iccm.locked.set(false);
+        self.set_locked(false);
+    }
+
+    fn set_locked(&mut self, locked: bool) {
+        if self.iccm.locked.replace(locked) != locked {
+            self.iccm.bump_generation();
+        }
     }
 
     pub fn new(clock: &Clock) -> Self {
@@ -44,7 +51,10 @@ impl Iccm {
         }
     }
 
+    /// Direct access to the ICCM contents. The CPU assumes the contents are
+    /// modified, so it discards its decoded instructions.
     pub fn ram(&self) -> &RefCell<Ram> {
+        self.iccm.bump_generation();
         &self.iccm.ram
     }
 }
@@ -53,6 +63,10 @@ struct IccmImpl {
     ram: RefCell<Ram>,
     locked: Cell<bool>,
     timer: Timer,
+
+    // Incremented when the contents change behind the CPU's back or the
+    // lock changes; see Bus::instr_mem_generation().
+    generation: Cell<u64>,
 }
 
 impl IccmImpl {
@@ -61,8 +75,13 @@ impl IccmImpl {
             ram: RefCell::new(Ram::new(vec![0; ICCM_SIZE_BYTES])),
             locked: Cell::new(false),
             timer: clock.timer(),
+            generation: Cell::new(0),
         }
     }
+
+    fn bump_generation(&self) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+    }
 }
 
 impl Bus for Iccm {
@@ -99,6 +118,23 @@ impl Bus for Iccm {
         }
         self.iccm.ram.borrow_mut().write(size, addr, val)
     }
+
+    fn instr_mem_generation(&self) -> u64 {
+        self.iccm.generation.get()
+    }
+}
+
+impl Snapshot for Iccm {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.iccm.locked.get())?;
+        w.write(&*self.iccm.ram.borrow())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.iccm.bump_generation();
+        self.iccm.locked.set(r.read_value()?);
+        r.read(&mut *self.iccm.ram.borrow_mut())
+    }
 }
 
 #[cfg(test)]
@@ -148,6 +184,29 @@ mod tests {
         assert_eq!(next_action(&clock), None);
     }
 
+    #[test]
+    fn test_instr_mem_generation() {
+        let clock = Clock::new();
+        let mut iccm = Iccm::new(&clock);
+        let gen0 = iccm.instr_mem_generation();
+
+        // Writes over the bus are seen by the CPU itself
+        iccm.write(RvSize::Word, 0, 0x13).unwrap();
+        assert_eq!(iccm.instr_mem_generation(), gen0);
+
+        iccm.lock();
+        let gen1 = iccm.instr_mem_generation();
+        assert_ne!(gen1, gen0);
+        iccm.lock();
+        assert_eq!(iccm.instr_mem_generation(), gen1);
+        iccm.unlock();
+        let gen2 = iccm.instr_mem_generation();
+        assert_ne!(gen2, gen1);
+
+        iccm.ram().borrow_mut().data_mut()[0] = 0x33;
+        assert_ne!(iccm.instr_mem_generation(), gen2);
+    }
+
     #[test]
     fn test_byte_write() {
         let clock = Clock::new();
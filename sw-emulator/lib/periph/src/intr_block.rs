@@ -0,0 +1,281 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    intr_block.rs
+
+Abstract:
+
+    File contains the interrupt register block shared by Caliptra peripherals.
+
+--*/
+
+use caliptra_emu_bus::{
+    BusError, ReadOnlyRegister, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter,
+    WriteOnlyRegister,
+};
+use caliptra_emu_cpu::{Irq, Pic};
+use caliptra_emu_derive::Bus;
+use caliptra_emu_types::{RvData, RvSize};
+
+/// GLOBAL_INTR_EN_R fields
+const GLOBAL_INTR_EN_ERROR: u32 = 1 << 0;
+const GLOBAL_INTR_EN_NOTIF: u32 = 1 << 1;
+
+/// NOTIF_INTERNAL_INTR_R command done status of the crypto engines
+pub(crate) const NOTIF_CMD_DONE_STS: u32 = 1 << 0;
+
+/// PIC interrupt source ids of the Caliptra peripherals. Each peripheral's
+/// notification interrupt uses the source following its error interrupt.
+pub mod vector {
+    pub const ECC_ERROR: u8 = 3;
+    pub const HMAC_ERROR: u8 = 5;
+    pub const SHA512_ERROR: u8 = 9;
+    pub const SHA256_ERROR: u8 = 11;
+    pub const SOC_IFC_ERROR: u8 = 19;
+    pub const SHA512_ACC_ERROR: u8 = 21;
+}
+
+/// Interrupt register block (`intr_block_rf`)
+///
+/// Peripheral events set bits in the error and notification status
+/// registers, which software clears by writing 1. While any enabled status
+/// bit is set, the block drives the corresponding interrupt line into the
+/// PIC. The per-event counters are not emulated.
+#[derive(Bus)]
+pub struct IntrBlock {
+    /// GLOBAL_INTR_EN_R register
+    #[register(offset = 0x00, write_fn = write_global_intr_en)]
+    global_intr_en: u32,
+
+    /// ERROR_INTR_EN_R register
+    #[register(offset = 0x04, write_fn = write_error_intr_en)]
+    error_intr_en: u32,
+
+    /// NOTIF_INTR_EN_R register
+    #[register(offset = 0x08, write_fn = write_notif_intr_en)]
+    notif_intr_en: u32,
+
+    /// ERROR_GLOBAL_INTR_R register
+    #[register(offset = 0x0c, read_fn = read_error_global_intr)]
+    error_global_intr: ReadOnlyRegister<u32>,
+
+    /// NOTIF_GLOBAL_INTR_R register
+    #[register(offset = 0x10, read_fn = read_notif_global_intr)]
+    notif_global_intr: ReadOnlyRegister<u32>,
+
+    /// ERROR_INTERNAL_INTR_R register
+    #[register(offset = 0x14, write_fn = write_error_internal_intr)]
+    error_internal_intr: u32,
+
+    /// NOTIF_INTERNAL_INTR_R register
+    #[register(offset = 0x18, write_fn = write_notif_internal_intr)]
+    notif_internal_intr: u32,
+
+    /// ERROR_INTR_TRIG_R register
+    #[register(offset = 0x1c, write_fn = write_error_intr_trig)]
+    error_intr_trig: WriteOnlyRegister<u32>,
+
+    /// NOTIF_INTR_TRIG_R register
+    #[register(offset = 0x20, write_fn = write_notif_intr_trig)]
+    notif_intr_trig: WriteOnlyRegister<u32>,
+
+    error_irq: Irq,
+
+    notif_irq: Irq,
+}
+
+impl IntrBlock {
+    /// Create a new interrupt block driving PIC source `error_vector` and
+    /// the following notification source.
+    pub fn new(pic: &Pic, error_vector: u8) -> Self {
+        Self {
+            global_intr_en: 0,
+            error_intr_en: 0,
+            notif_intr_en: 0,
+            error_global_intr: ReadOnlyRegister::new(0),
+            notif_global_intr: ReadOnlyRegister::new(0),
+            error_internal_intr: 0,
+            notif_internal_intr: 0,
+            error_intr_trig: WriteOnlyRegister::new(0),
+            notif_intr_trig: WriteOnlyRegister::new(0),
+            error_irq: pic.register_irq(error_vector),
+            notif_irq: pic.register_irq(error_vector + 1),
+        }
+    }
+
+    /// Set error status bits
+    pub fn set_error(&mut self, sts: u32) {
+        self.error_internal_intr |= sts;
+        self.update_irqs();
+    }
+
+    /// Set notification status bits
+    pub fn set_notif(&mut self, sts: u32) {
+        self.notif_internal_intr |= sts;
+        self.update_irqs();
+    }
+
+    /// Clear error status bits
+    pub fn clear_error(&mut self, sts: u32) {
+        self.error_internal_intr &= !sts;
+        self.update_irqs();
+    }
+
+    /// Returns the error status bits
+    pub fn error(&self) -> u32 {
+        self.error_internal_intr
+    }
+
+    /// Returns the notification status bits
+    pub fn notif(&self) -> u32 {
+        self.notif_internal_intr
+    }
+
+    /// Reset all registers, as on a warm reset
+    pub fn reset(&mut self) {
+        self.global_intr_en = 0;
+        self.error_intr_en = 0;
+        self.notif_intr_en = 0;
+        self.error_internal_intr = 0;
+        self.notif_internal_intr = 0;
+        self.update_irqs();
+    }
+
+    fn update_irqs(&mut self) {
+        self.error_irq.set_level(
+            self.global_intr_en & GLOBAL_INTR_EN_ERROR != 0
+                && self.error_internal_intr & self.error_intr_en != 0,
+        );
+        self.notif_irq.set_level(
+            self.global_intr_en & GLOBAL_INTR_EN_NOTIF != 0
+                && self.notif_internal_intr & self.notif_intr_en != 0,
+        );
+    }
+
+    fn write_global_intr_en(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.global_intr_en = val & (GLOBAL_INTR_EN_ERROR | GLOBAL_INTR_EN_NOTIF);
+        self.update_irqs();
+        Ok(())
+    }
+
+    fn write_error_intr_en(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.error_intr_en = val;
+        self.update_irqs();
+        Ok(())
+    }
+
+    fn write_notif_intr_en(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.notif_intr_en = val;
+        self.update_irqs();
+        Ok(())
+    }
+
+    fn read_error_global_intr(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(u32::from(
+            self.error_internal_intr & self.error_intr_en != 0,
+        ))
+    }
+
+    fn read_notif_global_intr(&mut self, _: RvSize) -> Result<RvData, BusError> {
+        Ok(u32::from(
+            self.notif_internal_intr & self.notif_intr_en != 0,
+        ))
+    }
+
+    fn write_error_internal_intr(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.clear_error(val);
+        Ok(())
+    }
+
+    fn write_notif_internal_intr(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.notif_internal_intr &= !val;
+        self.update_irqs();
+        Ok(())
+    }
+
+    fn write_error_intr_trig(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.set_error(val);
+        Ok(())
+    }
+
+    fn write_notif_intr_trig(&mut self, _: RvSize, val: RvData) -> Result<(), BusError> {
+        self.set_notif(val);
+        Ok(())
+    }
+}
+
+impl Snapshot for IntrBlock {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.global_intr_en)?;
+        w.write(&self.error_intr_en)?;
+        w.write(&self.notif_intr_en)?;
+        w.write(&self.error_internal_intr)?;
+        w.write(&self.notif_internal_intr)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.global_intr_en)?;
+        r.read(&mut self.error_intr_en)?;
+        r.read(&mut self.notif_intr_en)?;
+        r.read(&mut self.error_internal_intr)?;
+        r.read(&mut self.notif_internal_intr)?;
+        self.update_irqs();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use caliptra_emu_bus::Bus;
+    use caliptra_emu_types::RvAddr;
+
+    const OFFSET_GLOBAL_INTR_EN: RvAddr = 0x00;
+    const OFFSET_NOTIF_INTR_EN: RvAddr = 0x08;
+    const OFFSET_NOTIF_GLOBAL_INTR: RvAddr = 0x10;
+    const OFFSET_NOTIF_INTERNAL_INTR: RvAddr = 0x18;
+    const OFFSET_NOTIF_INTR_TRIG: RvAddr = 0x20;
+
+    #[test]
+    fn test_notif_irq() {
+        let pic = Pic::new();
+        let mut intr = IntrBlock::new(&pic, vector::SHA256_ERROR);
+        intr.set_notif(1);
+        assert!(!intr.notif_irq.level());
+        assert_eq!(
+            intr.read(RvSize::Word, OFFSET_NOTIF_GLOBAL_INTR).unwrap(),
+            0
+        );
+
+        intr.write(RvSize::Word, OFFSET_NOTIF_INTR_EN, 1).unwrap();
+        assert_eq!(
+            intr.read(RvSize::Word, OFFSET_NOTIF_GLOBAL_INTR).unwrap(),
+            1
+        );
+        assert!(!intr.notif_irq.level());
+
+        intr.write(RvSize::Word, OFFSET_GLOBAL_INTR_EN, GLOBAL_INTR_EN_NOTIF)
+            .unwrap();
+        assert!(intr.notif_irq.level());
+        assert!(!intr.error_irq.level());
+
+        // Status bits are write-1-to-clear
+        intr.write(RvSize::Word, OFFSET_NOTIF_INTERNAL_INTR, 0)
+            .unwrap();
+        assert!(intr.notif_irq.level());
+        intr.write(RvSize::Word, OFFSET_NOTIF_INTERNAL_INTR, 1)
+            .unwrap();
+        assert!(!intr.notif_irq.level());
+
+        // Software can trigger an interrupt
+        intr.write(RvSize::Word, OFFSET_NOTIF_INTR_TRIG, 1).unwrap();
+        assert_eq!(
+            intr.read(RvSize::Word, OFFSET_NOTIF_INTERNAL_INTR).unwrap(),
+            1
+        );
+        assert!(intr.notif_irq.level());
+    }
+}
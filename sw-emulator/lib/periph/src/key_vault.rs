@@ -13,7 +13,10 @@ Abstract:
 --*/
 
 use bitfield::bitfield;
-use caliptra_emu_bus::{Bus, BusError, ReadWriteMemory, ReadWriteRegisterArray};
+use caliptra_emu_bus::{
+    Bus, BusError, ReadWriteMemory, ReadWriteRegisterArray, Snapshot, SnapshotError,
+    SnapshotReader, SnapshotWriter,
+};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
 use std::cell::RefCell;
@@ -255,6 +258,16 @@ bitfield! {
     pub ecc_key_gen_seed, set_ecc_key_gen_seed: 4;
 }
 
+impl Snapshot for KeyVault {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.regs.borrow().save(w)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.regs.borrow_mut().restore(r)
+    }
+}
+
 impl From<KeyUsage> for u32 {
     /// Converts to this type from the input type.
     fn from(key_usage: KeyUsage) -> Self {
@@ -693,6 +706,42 @@ impl KeyVaultRegs {
     }
 }
 
+impl Snapshot for KeyVaultRegs {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.key_control)?;
+        w.write(&self.keys)?;
+        w.write(&self.pcr_control)?;
+        w.write(&self.pcrs)?;
+        w.write(&self.sticky_datavault_control)?;
+        w.write(&self.sticky_datavault_entry)?;
+        w.write(&self.datavault_control)?;
+        w.write(&self.datavault_entry)?;
+        w.write(&self.lockable_scratch_control)?;
+        w.write(&self.lockable_scratch)?;
+        w.write(&self.nonsticky_generic_scratch)?;
+        w.write(&self.sticky_lockable_scratch_control)?;
+        w.write(&self.sticky_lockable_scratch)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.key_control)?;
+        r.read(&mut self.keys)?;
+        r.read(&mut self.pcr_control)?;
+        r.read(&mut self.pcrs)?;
+        r.read(&mut self.sticky_datavault_control)?;
+        r.read(&mut self.sticky_datavault_entry)?;
+        r.read(&mut self.datavault_control)?;
+        r.read(&mut self.datavault_entry)?;
+        r.read(&mut self.lockable_scratch_control)?;
+        r.read(&mut self.lockable_scratch)?;
+        r.read(&mut self.nonsticky_generic_scratch)?;
+        r.read(&mut self.sticky_lockable_scratch_control)?;
+        r.read(&mut self.sticky_lockable_scratch)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -18,26 +18,32 @@ mod asym_ecc384;
 mod csrng;
 mod doe;
 mod emu_ctrl;
+mod entropy_src;
 mod hash_sha256;
 mod hash_sha512;
 mod helpers;
 mod hmac_sha384;
 mod iccm;
+mod intr_block;
 mod key_vault;
 mod mailbox;
 mod root_bus;
 mod sha512_acc;
 pub mod soc_reg;
+mod spi_flash;
+mod spi_host;
 mod uart;
 
 pub use asym_ecc384::AsymEcc384;
 pub use csrng::Csrng;
 pub use doe::Doe;
 pub use emu_ctrl::EmuCtrl;
+pub use entropy_src::EntropySrc;
 pub use hash_sha256::HashSha256;
 pub use hash_sha512::HashSha512;
 pub use hmac_sha384::HmacSha384;
 pub use iccm::Iccm;
+pub use intr_block::IntrBlock;
 pub use key_vault::KeyUsage;
 pub use key_vault::KeyVault;
 pub use mailbox::{MailboxExternal, MailboxInternal, MailboxRam};
@@ -47,4 +53,6 @@ pub use root_bus::{
 };
 pub use sha512_acc::Sha512Accelerator;
 pub use soc_reg::SocRegistersInternal;
+pub use spi_flash::SpiFlash;
+pub use spi_host::{SpiDevice, SpiHost};
 pub use uart::Uart;
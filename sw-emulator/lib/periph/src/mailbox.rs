@@ -13,7 +13,9 @@ Abstract:
 --*/
 use smlang::statemachine;
 
-use caliptra_emu_bus::{Bus, BusMmio, Ram};
+use caliptra_emu_bus::{
+    Bus, BusMmio, Ram, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter,
+};
 use caliptra_emu_bus::{BusError, ReadOnlyRegister, ReadWriteRegister, WriteOnlyRegister};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
@@ -82,6 +84,16 @@ impl Bus for MailboxRam {
         Ok(())
     }
 }
+impl Snapshot for MailboxRam {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&*self.ram.borrow())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut *self.ram.borrow_mut())
+    }
+}
+
 impl Default for MailboxRam {
     fn default() -> Self {
         Self::new()
@@ -123,6 +135,17 @@ impl Bus for MailboxExternal {
     }
 }
 
+/// The mailbox SRAM is shared and saved by its owner.
+impl Snapshot for MailboxInternal {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.regs.borrow().save(w)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.regs.borrow_mut().restore(r)
+    }
+}
+
 #[derive(Clone)]
 pub struct MailboxInternal {
     regs: Rc<RefCell<MailboxRegs>>,
@@ -151,6 +174,11 @@ impl MailboxInternal {
             regs: self.regs.clone(),
         }
     }
+
+    /// Register a callback invoked when the SoC hands a command to the uC
+    pub fn set_cmd_avail_cb(&mut self, cb: impl FnMut() + 'static) {
+        self.regs.borrow_mut().cmd_avail_cb = Some(Box::new(cb));
+    }
 }
 
 impl Bus for MailboxInternal {
@@ -229,6 +257,9 @@ pub struct MailboxRegs {
     state_machine: StateMachine<Context>,
 
     pub requester: MailboxRequester,
+
+    /// Command available callback
+    cmd_avail_cb: Option<Box<dyn FnMut()>>,
 }
 
 impl MailboxRegs {
@@ -257,6 +288,7 @@ impl MailboxRegs {
             _unlock: ReadWriteRegister::new(Self::UNLOCK_VAL),
             state_machine: StateMachine::new(Context::new(ram)),
             requester: MailboxRequester::Caliptra,
+            cmd_avail_cb: None,
         }
     }
     pub fn set_request(&mut self, requester: MailboxRequester) {
@@ -349,7 +381,15 @@ impl MailboxRegs {
             }
         };
 
-        let _ = self.state_machine.process_event(event);
+        let cmd_avail = matches!(event, Events::SocExecSet);
+        if self.state_machine.process_event(event).is_ok()
+            && cmd_avail
+            && matches!(self.state_machine.state(), States::ExecUc)
+        {
+            if let Some(cb) = self.cmd_avail_cb.as_mut() {
+                cb();
+            }
+        }
         self.execute.reg.set(val);
         Ok(())
     }
@@ -556,6 +596,68 @@ impl StateMachineContext for Context {
     }
 }
 
+impl Snapshot for MailboxRegs {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        let state: u8 = match self.state_machine.state {
+            States::Idle => 0,
+            States::RdyForCmd => 1,
+            States::RdyForDlen => 2,
+            States::RdyForData => 3,
+            States::ExecUc => 4,
+            States::ExecSoc => 5,
+            States::Error => 6,
+        };
+        w.write(&state)?;
+        w.write(&self.execute)?;
+        w.write(&self.state_machine.context)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.state_machine.state = match r.read_value::<u8>()? {
+            0 => States::Idle,
+            1 => States::RdyForCmd,
+            2 => States::RdyForDlen,
+            3 => States::RdyForData,
+            4 => States::ExecUc,
+            5 => States::ExecSoc,
+            6 => States::Error,
+            _ => return Err(SnapshotError::InvalidData("mailbox state")),
+        };
+        r.read(&mut self.execute)?;
+        r.read(&mut self.state_machine.context)
+    }
+}
+
+impl Snapshot for Context {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.locked)?;
+        w.write(&(self.user == MailboxRequester::Soc))?;
+        w.write(&self.exec)?;
+        w.write(&self.dlen)?;
+        w.write(&self.fifo)?;
+        w.write(&self.status)?;
+        w.write(&self.cmd)?;
+        w.write(&self.data_out)?;
+        w.write(&self.unlock)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.locked)?;
+        self.user = if r.read_value()? {
+            MailboxRequester::Soc
+        } else {
+            MailboxRequester::Caliptra
+        };
+        r.read(&mut self.exec)?;
+        r.read(&mut self.dlen)?;
+        r.read(&mut self.fifo)?;
+        r.read(&mut self.status)?;
+        r.read(&mut self.cmd)?;
+        r.read(&mut self.data_out)?;
+        r.read(&mut self.unlock)
+    }
+}
+
 pub struct Fifo {
     latched_dlen: u32,
     capacity: usize,
@@ -611,6 +713,22 @@ impl Fifo {
     }
 }
 
+impl Snapshot for Fifo {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.latched_dlen)?;
+        w.write(&self.read_index)?;
+        w.write(&self.write_index)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.latched_dlen)?;
+        r.read(&mut self.read_index)?;
+        r.read(&mut self.write_index)?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -16,12 +16,17 @@ use crate::{
     helpers::words_from_bytes_be,
     iccm::Iccm,
     soc_reg::{DebugManufService, SocRegistersExternal},
-    AsymEcc384, Csrng, Doe, EmuCtrl, HashSha256, HashSha512, HmacSha384, KeyVault, MailboxExternal,
-    MailboxInternal, MailboxRam, Sha512Accelerator, SocRegistersInternal, Uart,
+    AsymEcc384, Csrng, Doe, EmuCtrl, EntropySrc, HashSha256, HashSha512, HmacSha384, KeyVault,
+    MailboxExternal, MailboxInternal, MailboxRam, Sha512Accelerator, SocRegistersInternal,
+    SpiDevice, SpiHost, Uart,
 };
-use caliptra_emu_bus::{Clock, Ram, Rom};
+use caliptra_emu_bus::{Clock, Ram, Rom, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
+use caliptra_emu_cpu::{Pic, PicMmio};
 use caliptra_emu_derive::Bus;
-use caliptra_hw_model_types::{EtrngResponse, RandomEtrngResponses, RandomNibbles, SecurityState};
+use caliptra_hw_model_types::{
+    CryptoTiming, EntropySrcFault, EtrngResponse, RandomEtrngResponses, RandomNibbles,
+    SecurityState,
+};
 use std::path::PathBuf;
 use tock_registers::registers::InMemoryRegister;
 
@@ -221,7 +226,18 @@ pub struct CaliptraRootBusArgs {
     pub cptra_obf_key: [u32; 8],
 
     pub itrng_nibbles: Option<Box<dyn Iterator<Item = u8>>>,
+
+    /// Fault to inject into the noise source behind the internal TRNG.
+    pub entropy_src_fault: Option<EntropySrcFault>,
+
     pub etrng_responses: Box<dyn Iterator<Item = EtrngResponse>>,
+
+    /// Device attached to chip-select 0 of the SPI host, typically a
+    /// `SpiFlash` holding the firmware image.
+    pub spi_device: Option<Box<dyn SpiDevice>>,
+
+    /// Number of cycles taken by crypto engine operations.
+    pub crypto_timing: CryptoTiming,
 }
 impl Default for CaliptraRootBusArgs {
     fn default() -> Self {
@@ -236,7 +252,10 @@ impl Default for CaliptraRootBusArgs {
             download_idevid_csr_cb: Default::default(),
             cptra_obf_key: words_from_bytes_be(&DEFAULT_DOE_KEY),
             itrng_nibbles: Some(Box::new(RandomNibbles::new_from_thread_rng())),
+            entropy_src_fault: None,
             etrng_responses: Box::new(RandomEtrngResponses::new_from_stdrng()),
+            spi_device: None,
+            crypto_timing: Default::default(),
         }
     }
 }
@@ -252,7 +271,7 @@ pub struct CaliptraRootBus {
     #[peripheral(offset = 0x1000_8000, mask = 0x0000_7fff)]
     pub ecc384: AsymEcc384,
 
-    #[peripheral(offset = 0x1001_0000, mask = 0x0000_07ff)]
+    #[peripheral(offset = 0x1001_0000, mask = 0x0000_0fff)]
     pub hmac: HmacSha384,
 
     #[peripheral(offset = 0x1001_8000, mask = 0x0000_7fff)]
@@ -267,6 +286,9 @@ pub struct CaliptraRootBus {
     #[peripheral(offset = 0x4000_0000, mask = 0x0fff_ffff)]
     pub iccm: Iccm,
 
+    #[peripheral(offset = 0x2000_0000, mask = 0x0000_0fff)]
+    pub spi_host: SpiHost,
+
     #[peripheral(offset = 0x2000_1000, mask = 0x0000_0fff)]
     pub uart: Uart,
 
@@ -290,6 +312,13 @@ pub struct CaliptraRootBus {
 
     #[peripheral(offset = 0x5000_0000, mask = 0x0fff_ffff)]
     pub dccm: Ram,
+
+    #[peripheral(offset = 0x6000_0000, mask = 0x0000_7fff)]
+    pub pic_regs: PicMmio,
+
+    /// Programmable interrupt controller; pass to the CPU so peripheral
+    /// interrupts reach it.
+    pub pic: Pic,
 }
 
 impl CaliptraRootBus {
@@ -303,33 +332,50 @@ impl CaliptraRootBus {
         let mailbox = MailboxInternal::new(mailbox_ram.clone());
         let rom = Rom::new(std::mem::take(&mut args.rom));
         let iccm = Iccm::new(clock);
-        let itrng_nibbles = args.itrng_nibbles.take();
-        let soc_reg = SocRegistersInternal::new(clock, mailbox.clone(), iccm.clone(), args);
+        let entropy_src =
+            EntropySrc::new(args.itrng_nibbles.take().unwrap(), args.entropy_src_fault);
+        let mut spi_host = SpiHost::new();
+        if let Some(spi_device) = args.spi_device.take() {
+            spi_host.attach(0, spi_device);
+        }
+        let pic = Pic::new();
+        let crypto_timing = args.crypto_timing;
+        let soc_reg = SocRegistersInternal::new(clock, mailbox.clone(), iccm.clone(), &pic, args);
         if !soc_reg.is_debug_locked() {
             // When debug is possible, the key-vault is initialized with a debug value...
             // This is necessary to match the behavior of the RTL.
             key_vault.clear_keys_with_debug_values(false);
         }
 
-        let sha512 = HashSha512::new(clock, key_vault.clone());
+        let mut sha512 = HashSha512::new(clock, key_vault.clone(), &pic);
+        sha512.set_timing(crypto_timing);
+        let mut ecc384 = AsymEcc384::new(clock, key_vault.clone(), sha512.clone(), &pic);
+        ecc384.set_timing(crypto_timing);
+        let mut hmac = HmacSha384::new(clock, key_vault.clone(), &pic);
+        hmac.set_timing(crypto_timing);
+        let mut sha512_acc = Sha512Accelerator::new(clock, mailbox_ram.clone(), &pic);
+        sha512_acc.set_timing(crypto_timing);
 
         Self {
             rom,
             doe: Doe::new(clock, key_vault.clone(), soc_reg.clone()),
-            ecc384: AsymEcc384::new(clock, key_vault.clone(), sha512.clone()),
-            hmac: HmacSha384::new(clock, key_vault.clone()),
+            ecc384,
+            hmac,
             key_vault: key_vault.clone(),
             sha512,
-            sha256: HashSha256::new(clock),
+            sha256: HashSha256::new(clock, &pic),
             iccm,
             dccm: Ram::new(vec![0; Self::DCCM_SIZE]),
+            spi_host,
             uart: Uart::new(),
             ctrl: EmuCtrl::new(),
             soc_reg,
-            mailbox_sram: mailbox_ram.clone(),
+            mailbox_sram: mailbox_ram,
             mailbox,
-            sha512_acc: Sha512Accelerator::new(clock, mailbox_ram),
-            csrng: Csrng::new(itrng_nibbles.unwrap()),
+            sha512_acc,
+            csrng: Csrng::new(entropy_src),
+            pic_regs: pic.mmio(),
+            pic,
         }
     }
 
@@ -342,6 +388,49 @@ impl CaliptraRootBus {
     }
 }
 
+/// Saves every peripheral except the ROM. Shared state (key vault, mailbox
+/// SRAM, ICCM, SoC registers) is saved once here rather than by each user.
+///
+/// A snapshot must be restored into a bus constructed with the same ROM and
+/// arguments; host callbacks and entropy sources come from the new bus.
+impl Snapshot for CaliptraRootBus {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.key_vault)?;
+        w.write(&self.mailbox_sram)?;
+        w.write(&self.iccm)?;
+        w.write(&self.soc_reg)?;
+        w.write(&self.doe)?;
+        w.write(&self.ecc384)?;
+        w.write(&self.hmac)?;
+        w.write(&self.sha512)?;
+        w.write(&self.sha256)?;
+        w.write(&self.spi_host)?;
+        w.write(&self.uart)?;
+        w.write(&self.csrng)?;
+        w.write(&self.mailbox)?;
+        w.write(&self.sha512_acc)?;
+        w.write(&self.dccm)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.key_vault)?;
+        r.read(&mut self.mailbox_sram)?;
+        r.read(&mut self.iccm)?;
+        r.read(&mut self.soc_reg)?;
+        r.read(&mut self.doe)?;
+        r.read(&mut self.ecc384)?;
+        r.read(&mut self.hmac)?;
+        r.read(&mut self.sha512)?;
+        r.read(&mut self.sha256)?;
+        r.read(&mut self.spi_host)?;
+        r.read(&mut self.uart)?;
+        r.read(&mut self.csrng)?;
+        r.read(&mut self.mailbox)?;
+        r.read(&mut self.sha512_acc)?;
+        r.read(&mut self.dccm)
+    }
+}
+
 #[derive(Bus)]
 pub struct SocToCaliptraBus {
     #[peripheral(offset = 0x3002_0000, mask = 0x0000_0fff)]
@@ -391,6 +480,128 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_snapshot() {
+        use caliptra_emu_bus::Bus;
+        use caliptra_emu_types::RvSize;
+
+        let clock = Clock::new();
+        let mut root_bus = CaliptraRootBus::new(&clock, CaliptraRootBusArgs::default());
+        let mut key_usage = KeyUsage::default();
+        key_usage.set_hmac_key(true);
+        root_bus
+            .key_vault
+            .write_key(3, &[0x12; 48], key_usage.into())
+            .unwrap();
+        root_bus
+            .write(RvSize::Word, 0x5000_0100, 0xdead_beef)
+            .unwrap();
+        root_bus
+            .write(RvSize::Word, 0x3000_0000, 0xcafe_f00d)
+            .unwrap();
+        // Acquire the mailbox lock
+        assert_eq!(root_bus.read(RvSize::Word, 0x3002_0000).unwrap(), 0);
+
+        let mut w = SnapshotWriter::new();
+        root_bus.save(&mut w).unwrap();
+        let snapshot = w.into_bytes();
+
+        let clock = Clock::new();
+        let mut restored = CaliptraRootBus::new(&clock, CaliptraRootBusArgs::default());
+        let mut r = SnapshotReader::new(&snapshot);
+        restored.restore(&mut r).unwrap();
+        assert!(r.is_empty());
+
+        assert_eq!(
+            restored.key_vault.read_key(3, key_usage).unwrap(),
+            [0x12; 48]
+        );
+        assert_eq!(
+            restored.read(RvSize::Word, 0x5000_0100).unwrap(),
+            0xdead_beef
+        );
+        assert_eq!(
+            restored.read(RvSize::Word, 0x3000_0000).unwrap(),
+            0xcafe_f00d
+        );
+        // The mailbox is still locked
+        assert_eq!(restored.read(RvSize::Word, 0x3002_0000).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_mailbox_cmd_avail_interrupt() {
+        use caliptra_emu_bus::Bus;
+        use caliptra_emu_types::RvSize;
+
+        const SOC_IFC_NOTIF_VECTOR: u32 = 20;
+
+        let clock = Clock::new();
+        let mut root_bus = CaliptraRootBus::new(&clock, CaliptraRootBusArgs::default());
+        let mut soc = root_bus.soc_to_caliptra_bus();
+
+        // Enable the soc_ifc notification interrupt in the PIC
+        root_bus
+            .write(RvSize::Word, 0x6000_0000 + 4 * SOC_IFC_NOTIF_VECTOR, 1)
+            .unwrap();
+        root_bus
+            .write(RvSize::Word, 0x6000_2000 + 4 * SOC_IFC_NOTIF_VECTOR, 1)
+            .unwrap();
+        // Enable the cmd_avail notification in soc_ifc
+        root_bus.write(RvSize::Word, 0x3003_0800, 1 << 1).unwrap();
+        root_bus.write(RvSize::Word, 0x3003_0808, 1).unwrap();
+
+        // The SoC sends a mailbox command
+        assert_eq!(soc.read(RvSize::Word, 0x3002_0000).unwrap(), 0);
+        soc.write(RvSize::Word, 0x3002_0008, 0x1234).unwrap();
+        soc.write(RvSize::Word, 0x3002_000c, 0).unwrap();
+        soc.write(RvSize::Word, 0x3002_0018, 1).unwrap();
+        assert_eq!(root_bus.read(RvSize::Word, 0x6000_1000).unwrap(), 0);
+
+        clock.increment_and_process_timer_actions(1, &mut root_bus);
+        assert_eq!(root_bus.read(RvSize::Word, 0x3003_0818).unwrap(), 1);
+        assert_eq!(
+            root_bus.read(RvSize::Word, 0x6000_1000).unwrap(),
+            1 << SOC_IFC_NOTIF_VECTOR
+        );
+
+        // Clearing the status deasserts the interrupt
+        root_bus.write(RvSize::Word, 0x3003_0818, 1).unwrap();
+        assert_eq!(root_bus.read(RvSize::Word, 0x6000_1000).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_spi_flash_read() {
+        use crate::SpiFlash;
+        use caliptra_emu_bus::Bus;
+        use caliptra_emu_types::RvSize;
+
+        let clock = Clock::new();
+        let mut root_bus = CaliptraRootBus::new(
+            &clock,
+            CaliptraRootBusArgs {
+                spi_device: Some(Box::new(SpiFlash::new((0..=255).collect()))),
+                ..CaliptraRootBusArgs::default()
+            },
+        );
+        // Enable the SPI host
+        root_bus.write(RvSize::Word, 0x2000_0010, 1 << 31).unwrap();
+        // READ from address 0x000040
+        root_bus
+            .write(RvSize::Word, 0x2000_002c, 0x4000_0003)
+            .unwrap();
+        // 4-byte TX segment with CSAAT, then a 4-byte RX segment
+        root_bus
+            .write(RvSize::Word, 0x2000_0024, (2 << 12) | (1 << 9) | 3)
+            .unwrap();
+        root_bus
+            .write(RvSize::Word, 0x2000_0024, (1 << 12) | 3)
+            .unwrap();
+        assert_eq!(
+            root_bus.read(RvSize::Word, 0x2000_0028).unwrap(),
+            0x4342_4140
+        );
+    }
+
     #[test]
     fn test_keyvault_init_val_in_debug_locked_mode() {
         let clock = Clock::new();
@@ -11,13 +11,17 @@ Abstract:
     File contains SHA accelerator implementation.
 
 --*/
-use crate::MailboxRam;
+use crate::intr_block::{vector, NOTIF_CMD_DONE_STS};
+use crate::{IntrBlock, MailboxRam};
 use caliptra_emu_bus::{
-    ActionHandle, Bus, BusError, Clock, ReadOnlyMemory, ReadOnlyRegister, ReadWriteRegister, Timer,
+    ActionHandle, Bus, BusError, Clock, ReadOnlyMemory, ReadOnlyRegister, ReadWriteRegister,
+    Snapshot, SnapshotError, SnapshotReader, SnapshotWriter, Timer,
 };
+use caliptra_emu_cpu::Pic;
 use caliptra_emu_crypto::{EndianessTransform, Sha512, Sha512Mode};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
+use caliptra_hw_model_types::CryptoTiming;
 use smlang::statemachine;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -31,9 +35,6 @@ const MAX_MAILBOX_CAPACITY_WORDS: usize = (128 << 10) >> 2;
 /// Maximum mailbox capacity in bytes.
 const MAX_MAILBOX_CAPACITY_BYTES: usize = MAX_MAILBOX_CAPACITY_WORDS * RvSize::Word as usize;
 
-/// The number of CPU clock cycles it takes to perform sha operation.
-const SHA_ACC_OP_TICKS: u64 = 1000;
-
 const SHA512_BLOCK_SIZE: usize = 128;
 const SHA512_HASH_SIZE: usize = 64;
 
@@ -129,12 +130,19 @@ pub struct Sha512AcceleratorRegs {
     #[register(offset = 0x0000_0060, write_fn = on_write_control)]
     control: ReadWriteRegister<u32, Control::Register>,
 
+    /// Interrupt Block
+    #[peripheral(offset = 0x0000_0800, mask = 0x0000_07ff)]
+    intr: IntrBlock,
+
     /// Mailbox Memory
     mailbox_ram: MailboxRam,
 
     /// Timer
     timer: Timer,
 
+    /// Cycles taken by each operation
+    timing: CryptoTiming,
+
     /// State Machine
     state_machine: StateMachine<Context>,
 
@@ -146,13 +154,14 @@ pub struct Sha512AcceleratorRegs {
 }
 
 impl Sha512AcceleratorRegs {
-    pub fn new(clock: &Clock, mailbox_ram: MailboxRam) -> Self {
+    pub fn new(clock: &Clock, mailbox_ram: MailboxRam, pic: &Pic) -> Self {
         let mut result = Self {
             status: ReadOnlyRegister::new(Status::VALID::CLEAR.value),
             hash_lower: ReadOnlyMemory::new(),
             hash_upper: ReadOnlyMemory::new(),
             mailbox_ram,
             timer: Timer::new(clock),
+            timing: CryptoTiming::default(),
             _lock: ReadWriteRegister::new(0),
             user: ReadOnlyRegister::new(0),
             dlen: ReadWriteRegister::new(0),
@@ -163,6 +172,7 @@ impl Sha512AcceleratorRegs {
             op_complete_action: None,
             state_machine: StateMachine::new(Context::new()),
             control: ReadWriteRegister::new(0),
+            intr: IntrBlock::new(pic, vector::SHA512_ACC_ERROR),
             sha_stream: Sha512::new(Sha512Mode::Sha512),
         };
         // The peripheral needs to be locked at boot by the uC.
@@ -352,10 +362,12 @@ impl Sha512AcceleratorRegs {
         if self.execute.reg.read(Execute::EXECUTE) == 1 {
             let mode = self.mode.reg.read(ShaMode::MODE);
             if mode == ShaMode::MODE::SHA512_ACC_MODE_MBOX_384.value {
-                self.compute_mbox_hash();
+                let blocks = self.compute_mbox_hash();
 
                 // Schedule a future call to poll() complete the operation.
-                self.op_complete_action = Some(self.timer.schedule_poll_in(SHA_ACC_OP_TICKS));
+                self.op_complete_action = Some(self.timer.schedule_poll_in(
+                    self.timing.sha512_acc_op + blocks * self.timing.sha512_acc_block,
+                ));
             } else if mode == ShaMode::MODE::SHA512_ACC_MODE_SHA_STREAM_384.value
                 || mode == ShaMode::MODE::SHA512_ACC_MODE_SHA_STREAM_512.value
             {
@@ -397,6 +409,7 @@ impl Sha512AcceleratorRegs {
     }
 
     /// Function to retrieve data from the mailbox and compute it's hash.
+    /// Returns the number of blocks hashed.
     ///
     /// # Arguments
     ///
@@ -405,7 +418,7 @@ impl Sha512AcceleratorRegs {
     /// # Error
     ///
     /// * `BusError` - Exception with cause `BusError::StoreAccessFault` or `BusError::StoreAddrMisaligned`
-    fn compute_mbox_hash(&mut self) {
+    fn compute_mbox_hash(&mut self) -> u64 {
         let data_len = self.dlen.reg.get() as usize;
         let totaldwords = (data_len + (RvSize::Word as usize - 1)) / (RvSize::Word as usize);
         let totalblocks = ((data_len + 16) + SHA512_BLOCK_SIZE) / SHA512_BLOCK_SIZE;
@@ -429,7 +442,9 @@ impl Sha512AcceleratorRegs {
             block_arr.to_little_endian();
         }
 
-        // Add block padding.
+        // Drop the bytes of the last word past the end of the data, and add
+        // block padding.
+        block_arr[data_len..].fill(0);
         block_arr[data_len] = 0b1000_0000;
 
         // Add block length.
@@ -457,6 +472,8 @@ impl Sha512AcceleratorRegs {
         self.hash_upper
             .data_mut()
             .copy_from_slice(&hash[SHA512_HASH_HALF_SIZE..]);
+
+        totalblocks as u64
     }
 
     fn finalize_stream_hash(&mut self) {
@@ -497,6 +514,7 @@ impl Sha512AcceleratorRegs {
     fn op_complete(&mut self) {
         // Update the 'Valid' status bit
         self.status.reg.modify(Status::VALID::SET);
+        self.intr.set_notif(NOTIF_CMD_DONE_STS);
     }
 
     /// Get the length of the hash
@@ -532,6 +550,53 @@ impl Sha512AcceleratorRegs {
     }
 }
 
+/// The mailbox SRAM is shared and saved by its owner.
+impl Snapshot for Sha512AcceleratorRegs {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self._lock)?;
+        w.write(&self.user)?;
+        w.write(&self.mode)?;
+        w.write(&self.start_address)?;
+        w.write(&self.dlen)?;
+        w.write(&self.data_in)?;
+        w.write(&self.execute)?;
+        w.write(&self.status)?;
+        w.write(&self.hash_lower)?;
+        w.write(&self.hash_upper)?;
+        w.write(&self.control)?;
+        w.write(&self.intr)?;
+        w.write(&matches!(self.state_machine.state, States::RdyForExc))?;
+        w.write(&self.state_machine.context.locked)?;
+        w.write(&self.state_machine.context.user)?;
+        self.timer.save_action(&self.op_complete_action, w)?;
+        w.write(&self.sha_stream)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self._lock)?;
+        r.read(&mut self.user)?;
+        r.read(&mut self.mode)?;
+        r.read(&mut self.start_address)?;
+        r.read(&mut self.dlen)?;
+        r.read(&mut self.data_in)?;
+        r.read(&mut self.execute)?;
+        r.read(&mut self.status)?;
+        r.read(&mut self.hash_lower)?;
+        r.read(&mut self.hash_upper)?;
+        r.read(&mut self.control)?;
+        r.read(&mut self.intr)?;
+        self.state_machine.state = if r.read_value()? {
+            States::RdyForExc
+        } else {
+            States::Idle
+        };
+        r.read(&mut self.state_machine.context.locked)?;
+        r.read(&mut self.state_machine.context.user)?;
+        self.op_complete_action = self.timer.restore_action(r)?;
+        r.read(&mut self.sha_stream)
+    }
+}
+
 #[derive(Clone)]
 pub struct Sha512Accelerator {
     regs: Rc<RefCell<Sha512AcceleratorRegs>>,
@@ -539,11 +604,30 @@ pub struct Sha512Accelerator {
 
 impl Sha512Accelerator {
     /// Create a new instance of SHA-512 Accelerator
-    pub fn new(clock: &Clock, mailbox_ram: MailboxRam) -> Self {
+    pub fn new(clock: &Clock, mailbox_ram: MailboxRam, pic: &Pic) -> Self {
         Self {
-            regs: Rc::new(RefCell::new(Sha512AcceleratorRegs::new(clock, mailbox_ram))),
+            regs: Rc::new(RefCell::new(Sha512AcceleratorRegs::new(
+                clock,
+                mailbox_ram,
+                pic,
+            ))),
         }
     }
+
+    /// Set the number of cycles each operation takes
+    pub fn set_timing(&mut self, timing: CryptoTiming) {
+        self.regs.borrow_mut().timing = timing;
+    }
+}
+
+impl Snapshot for Sha512Accelerator {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.regs.borrow().save(w)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.regs.borrow_mut().restore(r)
+    }
 }
 
 impl Bus for Sha512Accelerator {
@@ -629,11 +713,22 @@ mod tests {
     const OFFSET_STATUS: RvAddr = 0x1c;
 
     fn test_sha_accelerator(data: &[u8], expected: &[u8], start_address: usize) {
+        test_sha_accelerator_with_fill(data, expected, start_address, 0);
+    }
+
+    /// Like `test_sha_accelerator`, but fills the bytes of the mailbox past
+    /// the end of the data with `fill`.
+    fn test_sha_accelerator_with_fill(
+        data: &[u8],
+        expected: &[u8],
+        start_address: usize,
+        fill: u8,
+    ) {
         // Write to the mailbox.
         let mut mb_ram = MailboxRam::new();
         if !data.is_empty() {
             assert!((start_address % 4) == 0);
-            let mut data_word_multiples = vec![0u8; ((start_address + data.len() + 3) / 4) * 4];
+            let mut data_word_multiples = vec![fill; ((start_address + data.len() + 3) / 4) * 4];
             data_word_multiples[start_address..start_address + data.len()].copy_from_slice(data);
 
             for idx in (0..data_word_multiples.len()).step_by(4) {
@@ -648,7 +743,7 @@ mod tests {
         }
 
         let clock = Clock::new();
-        let mut sha_accl = Sha512Accelerator::new(&clock, mb_ram.clone());
+        let mut sha_accl = Sha512Accelerator::new(&clock, mb_ram.clone(), &Pic::new());
         // Unlock the initial state
         sha_accl.write(RvSize::Word, OFFSET_LOCK, 1).unwrap();
 
@@ -741,6 +836,20 @@ mod tests {
         test_sha_accelerator(data, &expected, 0);
     }
 
+    #[test]
+    fn test_accelerator_sha384_partial_word() {
+        // The unused bytes of the last mailbox word must not be hashed.
+        let data = "ab".as_bytes();
+        let expected: [u8; SHA384_HASH_SIZE] = [
+            0xC7, 0xBE, 0x03, 0xBA, 0x5B, 0xCA, 0xA3, 0x84, 0x72, 0x70, 0x76, 0xDB, 0x00, 0x18,
+            0xE9, 0x92, 0x48, 0xE1, 0xA6, 0xE8, 0xBD, 0x1B, 0x9E, 0xF5, 0x8A, 0x9E, 0xC9, 0xDD,
+            0x4E, 0xEE, 0xBB, 0x3F, 0x48, 0xB8, 0x36, 0x20, 0x12, 0x21, 0x17, 0x5B, 0xEF, 0xA7,
+            0x4D, 0xDC, 0x3D, 0x35, 0xAF, 0xDD,
+        ];
+        test_sha_accelerator_with_fill(data, &expected, 0, 0xff);
+        test_sha_accelerator_with_fill(data, &expected, 4, 0xff);
+    }
+
     #[test]
     fn test_accelerator_sha384_2() {
         let expected: [u8; SHA384_HASH_SIZE] = [
@@ -828,7 +937,7 @@ mod tests {
     #[test]
     fn test_sm_lock() {
         let clock = Clock::new();
-        let mut sha_accl = Sha512Accelerator::new(&clock, MailboxRam::new());
+        let mut sha_accl = Sha512Accelerator::new(&clock, MailboxRam::new(), &Pic::new());
         assert_eq!(sha_accl.regs.borrow().state_machine.context.locked, 1);
         // Unlock the initial state
         sha_accl.write(RvSize::Word, OFFSET_LOCK, 1).unwrap();
@@ -860,7 +969,7 @@ mod tests {
     #[test]
     fn test_sha_acc_check_state() {
         let clock = Clock::new();
-        let mut sha_accl = Sha512Accelerator::new(&clock, MailboxRam::new());
+        let mut sha_accl = Sha512Accelerator::new(&clock, MailboxRam::new(), &Pic::new());
 
         // Check init state.
         assert_eq!(
@@ -944,4 +1053,44 @@ mod tests {
         assert_eq!(sha_accl.read(RvSize::Word, OFFSET_STATUS).unwrap(), 0);
         assert_eq!(sha_accl.read(RvSize::Word, OFFSET_EXECUTE).unwrap(), 0);
     }
+
+    #[test]
+    fn test_mbox_hash_timing() {
+        // Cycles until VALID is set after hashing `dlen` bytes of mailbox SRAM
+        fn cycles(timing: CryptoTiming, dlen: u32) -> u64 {
+            let clock = Clock::new();
+            let mut sha_accl = Sha512Accelerator::new(&clock, MailboxRam::new(), &Pic::new());
+            sha_accl.set_timing(timing);
+            sha_accl.write(RvSize::Word, OFFSET_LOCK, 1).unwrap();
+            assert_eq!(sha_accl.read(RvSize::Word, OFFSET_LOCK).unwrap(), 0);
+            sha_accl
+                .write(
+                    RvSize::Word,
+                    OFFSET_MODE,
+                    ShaMode::MODE::SHA512_ACC_MODE_MBOX_384.value,
+                )
+                .unwrap();
+            sha_accl.write(RvSize::Word, OFFSET_DLEN, dlen).unwrap();
+            sha_accl.write(RvSize::Word, OFFSET_EXECUTE, 1).unwrap();
+            let start = clock.now();
+            loop {
+                let status = InMemoryRegister::<u32, Status::Register>::new(
+                    sha_accl.read(RvSize::Word, OFFSET_STATUS).unwrap(),
+                );
+                if status.is_set(Status::VALID) {
+                    return clock.now() - start;
+                }
+                clock.increment_and_process_timer_actions(1, &mut sha_accl);
+            }
+        }
+
+        // FAST takes the same time regardless of length
+        assert_eq!(cycles(CryptoTiming::FAST, 4), 1000);
+        assert_eq!(cycles(CryptoTiming::FAST, 1024), 1000);
+
+        // VERILATED scales with the number of blocks, including padding
+        let block = CryptoTiming::VERILATED.sha512_acc_block;
+        assert_eq!(cycles(CryptoTiming::VERILATED, 4), block);
+        assert_eq!(cycles(CryptoTiming::VERILATED, 1024), 9 * block);
+    }
 }
@@ -13,19 +13,21 @@ Abstract:
 --*/
 
 use crate::helpers::{bytes_from_words_be, words_from_bytes_be};
+use crate::intr_block::vector;
 use crate::root_bus::ReadyForFwCbArgs;
-use crate::{CaliptraRootBusArgs, Iccm, MailboxInternal};
+use crate::{CaliptraRootBusArgs, Iccm, IntrBlock, MailboxInternal};
 use caliptra_emu_bus::BusError::{LoadAccessFault, StoreAccessFault};
 use caliptra_emu_bus::{
-    ActionHandle, Bus, BusError, Clock, ReadOnlyRegister, ReadWriteRegister, Register, Timer,
-    TimerAction,
+    ActionHandle, Bus, BusError, Clock, ReadOnlyRegister, ReadWriteRegister, Register, Snapshot,
+    SnapshotError, SnapshotReader, SnapshotWriter, Timer, TimerAction,
 };
+use caliptra_emu_cpu::{Irq, Pic};
 use caliptra_emu_derive::Bus;
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
 use caliptra_hw_model_types::EtrngResponse;
 use caliptra_registers::soc_ifc::regs::CptraHwConfigReadVal;
 use caliptra_registers::soc_ifc_trng::regs::{CptraTrngStatusReadVal, CptraTrngStatusWriteVal};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use tock_registers::interfaces::{ReadWriteable, Readable, Writeable};
 use tock_registers::register_bitfields;
@@ -103,6 +105,10 @@ mod constants {
     pub const INTERNAL_FW_UPDATE_RESET_START: u32 = 0x624;
     pub const INTERNAL_FW_UPDATE_RESET_WAIT_CYCLES_START: u32 = 0x628;
     pub const INTERNAL_NMI_VECTOR_START: u32 = 0x62c;
+    pub const INTERNAL_RV_MTIME_L_START: u32 = 0x640;
+    pub const INTERNAL_RV_MTIME_H_START: u32 = 0x644;
+    pub const INTERNAL_RV_MTIMECMP_L_START: u32 = 0x648;
+    pub const INTERNAL_RV_MTIMECMP_H_START: u32 = 0x64c;
 }
 use constants::*;
 
@@ -213,6 +219,17 @@ register_bitfields! [
         ERROR_WDT_TIMER1_TIMEOUT_STS OFFSET(6) NUMBITS(1) [],
         ERROR_WDT_TIMER2_TIMEOUT_STS OFFSET(7) NUMBITS(1) [],
         RSVD OFFSET(8) NUMBITS(24) [],
+    ],
+
+    /// NotifIntrT
+    NotifIntrT [
+        NOTIF_CMD_AVAIL_STS OFFSET(0) NUMBITS(1) [],
+        NOTIF_MBOX_ECC_COR_STS OFFSET(1) NUMBITS(1) [],
+        NOTIF_DEBUG_LOCKED_STS OFFSET(2) NUMBITS(1) [],
+        NOTIF_SCAN_MODE_STS OFFSET(3) NUMBITS(1) [],
+        NOTIF_SOC_REQ_LOCK_STS OFFSET(4) NUMBITS(1) [],
+        NOTIF_GEN_IN_TOGGLE_STS OFFSET(5) NUMBITS(1) [],
+        RSVD OFFSET(6) NUMBITS(26) [],
     ]
 ];
 
@@ -226,7 +243,7 @@ pub struct SocRegistersInternal {
 const CALIPTRA_REG_START_ADDR: u32 = 0x00;
 
 /// Caliptra Register End Address
-const CALIPTRA_REG_END_ADDR: u32 = 0x81c;
+const CALIPTRA_REG_END_ADDR: u32 = 0x820;
 
 /// Caliptra Fuse start address
 const FUSE_START_ADDR: u32 = 0x200;
@@ -239,11 +256,12 @@ impl SocRegistersInternal {
         clock: &Clock,
         mailbox: MailboxInternal,
         iccm: Iccm,
+        pic: &Pic,
         args: CaliptraRootBusArgs,
     ) -> Self {
         Self {
             regs: Rc::new(RefCell::new(SocRegistersImpl::new(
-                clock, mailbox, iccm, args,
+                clock, mailbox, iccm, pic, args,
             ))),
         }
     }
@@ -333,6 +351,16 @@ impl Bus for SocRegistersInternal {
     }
 }
 
+impl Snapshot for SocRegistersInternal {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        self.regs.borrow().save(w)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        self.regs.borrow_mut().restore(r)
+    }
+}
+
 pub struct SocRegistersExternal {
     regs: Rc<RefCell<SocRegistersImpl>>,
 }
@@ -565,29 +593,29 @@ struct SocRegistersImpl {
     #[register(offset = 0x062c, write_fn = on_write_internal_nmi_vector)]
     internal_nmi_vector: ReadWriteRegister<u32>,
 
-    /// GLOBAL_INTR_EN_R Register
-    #[register(offset = 0x0800)]
-    global_intr_en_r: ReadWriteRegister<u32>,
+    /// INTERNAL_RV_MTIME_L, INTERNAL_RV_MTIME_H, INTERNAL_RV_MTIMECMP_L and
+    /// INTERNAL_RV_MTIMECMP_H Registers
+    #[register_array(offset = 0x0640, item_size = 4, len = 4, read_fn = read_mtime, write_fn = write_mtime)]
+    _mtime_regs: (),
+
+    /// Interrupt Block
+    #[peripheral(offset = 0x0800, mask = 0x07ff)]
+    intr: IntrBlock,
 
-    /// ERROR_INTR_EN_R Register
-    #[register(offset = 0x0804)]
-    error_intr_en_r: ReadWriteRegister<u32>,
+    /// Machine timer interrupt line
+    mtime_irq: Irq,
 
-    /// NOTIF_INTR_EN_R Register
-    #[register(offset = 0x0808)]
-    notif_intr_en_r: ReadWriteRegister<u32>,
+    /// Clock cycle at which mtime was zero
+    mtime_base: u64,
 
-    /// ERROR_GLOBAL_INTR_R Register
-    #[register(offset = 0x080c)]
-    error_global_intr_r: ReadWriteRegister<u32>,
+    /// Machine timer compare value
+    mtimecmp: u64,
 
-    /// NOTIF_GLOBAL_INTR_R Register
-    #[register(offset = 0x0810)]
-    notif_global_intr_r: ReadWriteRegister<u32>,
+    /// Machine timer compare action
+    op_mtimecmp_action: Option<ActionHandle>,
 
-    /// ERROR_INTERNAL_INTR_R Register
-    #[register(offset = 0x0814)]
-    error_internal_intr_r: ReadWriteRegister<u32, ErrorIntrT::Register>,
+    /// Set by the mailbox when a command is available for the uC
+    cmd_avail: Rc<Cell<bool>>,
 
     /// Mailbox
     mailbox: MailboxInternal,
@@ -655,12 +683,13 @@ impl SocRegistersImpl {
         clock: &Clock,
         mailbox: MailboxInternal,
         iccm: Iccm,
+        pic: &Pic,
         mut args: CaliptraRootBusArgs,
     ) -> Self {
         let flow_status = InMemoryRegister::<u32, FlowStatus::Register>::new(0);
         flow_status.write(FlowStatus::READY_FOR_FUSES.val(1));
 
-        let regs = Self {
+        let mut regs = Self {
             cptra_hw_error_fatal: ReadWriteRegister::new(0),
             cptra_hw_error_non_fatal: ReadWriteRegister::new(0),
             cptra_fw_error_fatal: ReadWriteRegister::new(0),
@@ -707,12 +736,13 @@ impl SocRegistersImpl {
             internal_fw_update_reset: ReadWriteRegister::new(0),
             internal_fw_update_reset_wait_cycles: ReadWriteRegister::new(5),
             internal_nmi_vector: ReadWriteRegister::new(0),
-            global_intr_en_r: ReadWriteRegister::new(0),
-            error_intr_en_r: ReadWriteRegister::new(0),
-            notif_intr_en_r: ReadWriteRegister::new(0),
-            error_global_intr_r: ReadWriteRegister::new(0),
-            notif_global_intr_r: ReadWriteRegister::new(0),
-            error_internal_intr_r: ReadWriteRegister::new(0),
+            _mtime_regs: (),
+            intr: IntrBlock::new(pic, vector::SOC_IFC_ERROR),
+            mtime_irq: pic.register_timer_irq(),
+            mtime_base: 0,
+            mtimecmp: 0,
+            op_mtimecmp_action: None,
+            cmd_avail: Rc::new(Cell::new(false)),
             mailbox,
             iccm,
             timer: Timer::new(clock),
@@ -747,6 +777,15 @@ impl SocRegistersImpl {
             cptra_fuse_pauser_lock: ReadWriteRegister::new(0),
         };
 
+        let cmd_avail = regs.cmd_avail.clone();
+        let timer = regs.timer.clone();
+        regs.mailbox.set_cmd_avail_cb(move || {
+            cmd_avail.set(true);
+            // The notification is raised from poll(), as the mailbox may be
+            // written while these registers are borrowed.
+            let _ = timer.schedule_poll_in(1);
+        });
+
         regs
     }
 
@@ -1016,6 +1055,46 @@ impl SocRegistersImpl {
         Ok(())
     }
 
+    /// Current value of the machine timer
+    fn mtime(&self) -> u64 {
+        self.timer.now().wrapping_sub(self.mtime_base)
+    }
+
+    /// Drive the machine timer interrupt and schedule a poll for when
+    /// mtime reaches mtimecmp.
+    fn update_mtime_irq(&mut self) {
+        let mtime = self.mtime();
+        self.mtime_irq.set_level(mtime >= self.mtimecmp);
+        if let Some(action) = self.op_mtimecmp_action.take() {
+            self.timer.cancel(action);
+        }
+        if mtime < self.mtimecmp {
+            self.op_mtimecmp_action = Some(self.timer.schedule_poll_in(self.mtimecmp - mtime));
+        }
+    }
+
+    fn read_mtime(&mut self, _size: RvSize, index: usize) -> Result<RvData, BusError> {
+        let val = if index < 2 {
+            self.mtime()
+        } else {
+            self.mtimecmp
+        };
+        Ok((val >> (32 * (index & 1))) as u32)
+    }
+
+    fn write_mtime(&mut self, _size: RvSize, index: usize, val: RvData) -> Result<(), BusError> {
+        let shift = 32 * (index & 1);
+        let update = |old: u64| old & !(0xffff_ffff << shift) | u64::from(val) << shift;
+        if index < 2 {
+            let mtime = update(self.mtime());
+            self.mtime_base = self.timer.now().wrapping_sub(mtime);
+        } else {
+            self.mtimecmp = update(self.mtimecmp);
+        }
+        self.update_mtime_irq();
+        Ok(())
+    }
+
     fn reset_common(&mut self) {
         // Unlock the ICCM.
         self.iccm.unlock();
@@ -1023,6 +1102,15 @@ impl SocRegistersImpl {
 
     /// Called by Bus::poll() to indicate that time has passed
     fn bus_poll(&mut self) {
+        if self.timer.fired(&mut self.op_mtimecmp_action) {
+            self.update_mtime_irq();
+        }
+
+        if self.cmd_avail.take() {
+            self.intr
+                .set_notif(NotifIntrT::NOTIF_CMD_AVAIL_STS::SET.value);
+        }
+
         if self.timer.fired(&mut self.op_fw_write_complete_action) {
             if let Some(cb) = self.op_fw_write_complete_cb.take() {
                 (cb)(&mut self.mailbox);
@@ -1067,18 +1155,16 @@ impl SocRegistersImpl {
 
         if self.timer.fired(&mut self.op_wdt_timer1_expired_action) {
             self.cptra_wdt_status.reg.modify(WdtStatus::T1_TIMEOUT::SET);
-            self.error_internal_intr_r
-                .reg
-                .modify(ErrorIntrT::ERROR_WDT_TIMER1_TIMEOUT_STS::SET);
+            self.intr
+                .set_error(ErrorIntrT::ERROR_WDT_TIMER1_TIMEOUT_STS::SET.value);
 
             // If WDT2 is disabled, schedule a callback on it's expiry.
             if !self.cptra_wdt_timer2_en.reg.is_set(WdtEnable::TIMER_EN) {
                 self.cptra_wdt_status
                     .reg
                     .modify(WdtStatus::T2_TIMEOUT::CLEAR);
-                self.error_internal_intr_r
-                    .reg
-                    .modify(ErrorIntrT::ERROR_WDT_TIMER2_TIMEOUT_STS::CLEAR);
+                self.intr
+                    .clear_error(ErrorIntrT::ERROR_WDT_TIMER2_TIMEOUT_STS::SET.value);
 
                 let timer_period: u64 = (self.cptra_wdt_timer2_timeout_period[1] as u64) << 32
                     | self.cptra_wdt_timer2_timeout_period[0] as u64;
@@ -1093,9 +1179,8 @@ impl SocRegistersImpl {
             // If WDT2 was not scheduled due to WDT1 expiry (i.e WDT2 is disabled), schedule an NMI.
             // Else, do nothing.
             if self.cptra_wdt_timer2_en.reg.is_set(WdtEnable::TIMER_EN) {
-                self.error_internal_intr_r
-                    .reg
-                    .modify(ErrorIntrT::ERROR_WDT_TIMER2_TIMEOUT_STS::SET);
+                self.intr
+                    .set_error(ErrorIntrT::ERROR_WDT_TIMER2_TIMEOUT_STS::SET.value);
                 return;
             }
 
@@ -1121,6 +1206,11 @@ impl SocRegistersImpl {
             .reg
             .write(ResetReason::WARM_RESET::SET);
 
+        self.intr.reset();
+
+        // The boot FSM waits for CPTRA_BOOTFSM_GO again after a warm reset
+        self.cptra_bootfsm_go = 0;
+
         self.reset_common();
     }
 
@@ -1138,6 +1228,188 @@ impl SocRegistersImpl {
     }
 }
 
+/// The mailbox and ICCM are shared and saved by their owner. Host callbacks
+/// and the eTRNG response source are not saved.
+impl Snapshot for SocRegistersImpl {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        if self.op_fw_write_complete_cb.is_some() {
+            return Err(SnapshotError::Busy("firmware upload in progress"));
+        }
+        w.write(&self.cptra_hw_error_fatal)?;
+        w.write(&self.cptra_hw_error_non_fatal)?;
+        w.write(&self.cptra_fw_error_fatal)?;
+        w.write(&self.cptra_fw_error_non_fatal)?;
+        w.write(&self.cptra_hw_error_enc)?;
+        w.write(&self.cptra_fw_error_enc)?;
+        w.write(&self.cptra_fw_extended_error_info)?;
+        w.write(&self.cptra_boot_status)?;
+        w.write(&self.cptra_flow_status)?;
+        w.write(&self.cptra_reset_reason)?;
+        w.write(&self.cptra_security_state)?;
+        w.write(&self.cptra_mbox_valid_pauser)?;
+        w.write(&self.cptra_mbox_pauser_lock)?;
+        w.write(&self.cptra_trng_valid_pauser)?;
+        w.write(&self.cptra_trng_pauser_lock)?;
+        w.write(&self.cptra_trng_data)?;
+        w.write(&self.cptra_trng_ctrl)?;
+        w.write(&self.cptra_trng_status)?;
+        w.write(&self.cptra_fuse_wr_done)?;
+        w.write(&self.cptra_timer_config)?;
+        w.write(&self.cptra_bootfsm_go)?;
+        w.write(&self.cptra_dbg_manuf_service_reg)?;
+        w.write(&self.cptra_clk_gating_en)?;
+        w.write(&self.cptra_generic_input_wires)?;
+        w.write(&self.cptra_generic_output_wires)?;
+        w.write(&self.cptra_hw_rev_id)?;
+        w.write(&self.cptra_fw_rev_id)?;
+        w.write(&self.cptra_hw_config)?;
+        w.write(&self.cptra_wdt_timer1_en)?;
+        w.write(&self.cptra_wdt_timer1_ctrl)?;
+        w.write(&self.cptra_wdt_timer1_timeout_period)?;
+        w.write(&self.cptra_wdt_timer2_en)?;
+        w.write(&self.cptra_wdt_timer2_ctrl)?;
+        w.write(&self.cptra_wdt_timer2_timeout_period)?;
+        w.write(&self.cptra_wdt_status)?;
+        w.write(&self.cptra_fuse_valid_pauser)?;
+        w.write(&self.cptra_fuse_pauser_lock)?;
+        w.write(&self.cptra_i_trng_entropy_config_0)?;
+        w.write(&self.cptra_i_trng_entropy_config_1)?;
+        w.write(&self.cptra_rsvd_reg)?;
+        w.write(&self.fuse_uds_seed)?;
+        w.write(&self.cptra_wdt_cfg)?;
+        w.write(&self.fuse_field_entropy)?;
+        w.write(&self.fuse_vendor_pk_hash)?;
+        w.write(&self.fuse_vendor_pk_hash_mask)?;
+        w.write(&self.fuse_owner_pk_hash)?;
+        w.write(&self.fuse_fmc_svn)?;
+        w.write(&self.fuse_runtime_svn)?;
+        w.write(&self.fuse_anti_rollback_disable)?;
+        w.write(&self.fuse_idevid_cert_attr)?;
+        w.write(&self.fuse_idevid_manuf_hsm_id)?;
+        w.write(&self.fuse_life_cycle)?;
+        w.write(&self.fuse_lms_verify)?;
+        w.write(&self.fuse_lms_revocation)?;
+        w.write(&self.internal_obf_key)?;
+        w.write(&self.internal_iccm_lock)?;
+        w.write(&self.internal_fw_update_reset)?;
+        w.write(&self.internal_fw_update_reset_wait_cycles)?;
+        w.write(&self.internal_nmi_vector)?;
+        self.intr.save(w)?;
+        w.write(&self.mtime_base)?;
+        w.write(&self.mtimecmp)?;
+        w.write(&self.cmd_avail.get())?;
+        w.write(&self.fuses_can_be_written)?;
+        self.timer
+            .save_action(&self.op_fw_write_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_fw_read_complete_action, w)?;
+        self.timer
+            .save_action(&self.op_idevid_csr_read_complete_action, w)?;
+        self.timer.save_action(&self.op_reset_trigger_action, w)?;
+        self.timer
+            .save_action(&self.op_wdt_timer1_expired_action, w)?;
+        self.timer
+            .save_action(&self.op_wdt_timer2_expired_action, w)?;
+        self.timer
+            .save_action(&self.op_pending_etrng_response_action, w)?;
+        self.timer.save_action(&self.op_mtimecmp_action, w)?;
+        match &self.pending_etrng_response {
+            Some(response) => {
+                w.write(&true)?;
+                w.write(&response.delay)?;
+                w.write(&response.data)?;
+            }
+            None => w.write(&false)?,
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.cptra_hw_error_fatal)?;
+        r.read(&mut self.cptra_hw_error_non_fatal)?;
+        r.read(&mut self.cptra_fw_error_fatal)?;
+        r.read(&mut self.cptra_fw_error_non_fatal)?;
+        r.read(&mut self.cptra_hw_error_enc)?;
+        r.read(&mut self.cptra_fw_error_enc)?;
+        r.read(&mut self.cptra_fw_extended_error_info)?;
+        r.read(&mut self.cptra_boot_status)?;
+        r.read(&mut self.cptra_flow_status)?;
+        r.read(&mut self.cptra_reset_reason)?;
+        r.read(&mut self.cptra_security_state)?;
+        r.read(&mut self.cptra_mbox_valid_pauser)?;
+        r.read(&mut self.cptra_mbox_pauser_lock)?;
+        r.read(&mut self.cptra_trng_valid_pauser)?;
+        r.read(&mut self.cptra_trng_pauser_lock)?;
+        r.read(&mut self.cptra_trng_data)?;
+        r.read(&mut self.cptra_trng_ctrl)?;
+        r.read(&mut self.cptra_trng_status)?;
+        r.read(&mut self.cptra_fuse_wr_done)?;
+        r.read(&mut self.cptra_timer_config)?;
+        r.read(&mut self.cptra_bootfsm_go)?;
+        r.read(&mut self.cptra_dbg_manuf_service_reg)?;
+        r.read(&mut self.cptra_clk_gating_en)?;
+        r.read(&mut self.cptra_generic_input_wires)?;
+        r.read(&mut self.cptra_generic_output_wires)?;
+        r.read(&mut self.cptra_hw_rev_id)?;
+        r.read(&mut self.cptra_fw_rev_id)?;
+        r.read(&mut self.cptra_hw_config)?;
+        r.read(&mut self.cptra_wdt_timer1_en)?;
+        r.read(&mut self.cptra_wdt_timer1_ctrl)?;
+        r.read(&mut self.cptra_wdt_timer1_timeout_period)?;
+        r.read(&mut self.cptra_wdt_timer2_en)?;
+        r.read(&mut self.cptra_wdt_timer2_ctrl)?;
+        r.read(&mut self.cptra_wdt_timer2_timeout_period)?;
+        r.read(&mut self.cptra_wdt_status)?;
+        r.read(&mut self.cptra_fuse_valid_pauser)?;
+        r.read(&mut self.cptra_fuse_pauser_lock)?;
+        r.read(&mut self.cptra_i_trng_entropy_config_0)?;
+        r.read(&mut self.cptra_i_trng_entropy_config_1)?;
+        r.read(&mut self.cptra_rsvd_reg)?;
+        r.read(&mut self.fuse_uds_seed)?;
+        r.read(&mut self.cptra_wdt_cfg)?;
+        r.read(&mut self.fuse_field_entropy)?;
+        r.read(&mut self.fuse_vendor_pk_hash)?;
+        r.read(&mut self.fuse_vendor_pk_hash_mask)?;
+        r.read(&mut self.fuse_owner_pk_hash)?;
+        r.read(&mut self.fuse_fmc_svn)?;
+        r.read(&mut self.fuse_runtime_svn)?;
+        r.read(&mut self.fuse_anti_rollback_disable)?;
+        r.read(&mut self.fuse_idevid_cert_attr)?;
+        r.read(&mut self.fuse_idevid_manuf_hsm_id)?;
+        r.read(&mut self.fuse_life_cycle)?;
+        r.read(&mut self.fuse_lms_verify)?;
+        r.read(&mut self.fuse_lms_revocation)?;
+        r.read(&mut self.internal_obf_key)?;
+        r.read(&mut self.internal_iccm_lock)?;
+        r.read(&mut self.internal_fw_update_reset)?;
+        r.read(&mut self.internal_fw_update_reset_wait_cycles)?;
+        r.read(&mut self.internal_nmi_vector)?;
+        self.intr.restore(r)?;
+        r.read(&mut self.mtime_base)?;
+        r.read(&mut self.mtimecmp)?;
+        self.cmd_avail.set(r.read_value()?);
+        r.read(&mut self.fuses_can_be_written)?;
+        self.op_fw_write_complete_action = self.timer.restore_action(r)?;
+        self.op_fw_read_complete_action = self.timer.restore_action(r)?;
+        self.op_idevid_csr_read_complete_action = self.timer.restore_action(r)?;
+        self.op_reset_trigger_action = self.timer.restore_action(r)?;
+        self.op_wdt_timer1_expired_action = self.timer.restore_action(r)?;
+        self.op_wdt_timer2_expired_action = self.timer.restore_action(r)?;
+        self.op_pending_etrng_response_action = self.timer.restore_action(r)?;
+        self.op_mtimecmp_action = self.timer.restore_action(r)?;
+        self.mtime_irq.set_level(self.mtime() >= self.mtimecmp);
+        self.pending_etrng_response = if r.read_value()? {
+            Some(EtrngResponse {
+                delay: r.read_value()?,
+                data: r.read_value()?,
+            })
+        } else {
+            None
+        };
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1245,8 +1517,13 @@ mod tests {
         log_dir.push("/tmp");
         let args = CaliptraRootBusArgs::default();
         let args = CaliptraRootBusArgs { log_dir, ..args };
-        let mut soc_reg: SocRegistersInternal =
-            SocRegistersInternal::new(&clock, mailbox.clone(), Iccm::new(&clock), args);
+        let mut soc_reg: SocRegistersInternal = SocRegistersInternal::new(
+            &clock,
+            mailbox.clone(),
+            Iccm::new(&clock),
+            &Pic::new(),
+            args,
+        );
 
         soc_reg
             .write(RvSize::Word, CPTRA_DBG_MANUF_SERVICE_REG_START, 1)
@@ -1307,8 +1584,13 @@ mod tests {
         log_dir.push("/tmp");
         let args = CaliptraRootBusArgs::default();
         let args = CaliptraRootBusArgs { log_dir, ..args };
-        let mut soc_reg: SocRegistersInternal =
-            SocRegistersInternal::new(&clock, mailbox.clone(), Iccm::new(&clock), args);
+        let mut soc_reg: SocRegistersInternal = SocRegistersInternal::new(
+            &clock,
+            mailbox.clone(),
+            Iccm::new(&clock),
+            &Pic::new(),
+            args,
+        );
         soc_reg
             .write(RvSize::Word, CPTRA_DBG_MANUF_SERVICE_REG_START, 2)
             .unwrap();
@@ -1368,7 +1650,7 @@ mod tests {
             ..Default::default()
         };
         let mut soc_reg: SocRegistersInternal =
-            SocRegistersInternal::new(&clock, mailbox, Iccm::new(&clock), args);
+            SocRegistersInternal::new(&clock, mailbox, Iccm::new(&clock), &Pic::new(), args);
 
         let _ = soc_reg.write(RvSize::Word, CPTRA_GENERIC_OUTPUT_WIRES_START, b'h'.into());
 
@@ -1387,6 +1669,7 @@ mod tests {
             &clock,
             MailboxInternal::new(MailboxRam::new()),
             Iccm::new(&clock),
+            &Pic::new(),
             CaliptraRootBusArgs {
                 security_state: *SecurityState::default().set_debug_locked(false),
                 ..CaliptraRootBusArgs::default()
@@ -1406,6 +1689,7 @@ mod tests {
             &clock,
             MailboxInternal::new(MailboxRam::new()),
             Iccm::new(&clock),
+            &Pic::new(),
             CaliptraRootBusArgs {
                 security_state: *SecurityState::default().set_debug_locked(true),
                 ..CaliptraRootBusArgs::default()
@@ -1436,6 +1720,7 @@ mod tests {
             &clock,
             mailbox,
             Iccm::new(&clock),
+            &Pic::new(),
             CaliptraRootBusArgs::default(),
         );
         soc_reg
@@ -1472,4 +1757,50 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_mtime() {
+        let clock = Clock::new();
+        let mut soc_reg = SocRegistersInternal::new(
+            &clock,
+            MailboxInternal::new(MailboxRam::new()),
+            Iccm::new(&clock),
+            &Pic::new(),
+            CaliptraRootBusArgs::default(),
+        );
+        let mtime_irq_level =
+            |soc_reg: &SocRegistersInternal| soc_reg.regs.borrow().mtime_irq.level();
+
+        clock.increment_and_process_timer_actions(10, &mut soc_reg);
+        assert_eq!(
+            soc_reg
+                .read(RvSize::Word, INTERNAL_RV_MTIME_L_START)
+                .unwrap(),
+            10
+        );
+        soc_reg
+            .write(RvSize::Word, INTERNAL_RV_MTIME_H_START, 1)
+            .unwrap();
+        assert_eq!(
+            soc_reg
+                .read(RvSize::Word, INTERNAL_RV_MTIME_H_START)
+                .unwrap(),
+            1
+        );
+        soc_reg
+            .write(RvSize::Word, INTERNAL_RV_MTIME_H_START, 0)
+            .unwrap();
+
+        // mtimecmp resets to zero, so the timer interrupt is pending.
+        assert!(mtime_irq_level(&soc_reg));
+
+        soc_reg
+            .write(RvSize::Word, INTERNAL_RV_MTIMECMP_L_START, 20)
+            .unwrap();
+        assert!(!mtime_irq_level(&soc_reg));
+        clock.increment_and_process_timer_actions(9, &mut soc_reg);
+        assert!(!mtime_irq_level(&soc_reg));
+        clock.increment_and_process_timer_actions(1, &mut soc_reg);
+        assert!(mtime_irq_level(&soc_reg));
+    }
 }
@@ -0,0 +1,358 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    spi_flash.rs
+
+Abstract:
+
+    File contains SPI NOR flash device implementation.
+
+--*/
+
+use crate::SpiDevice;
+use caliptra_emu_bus::{Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+/// SPI NOR flash opcodes
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_READ: u8 = 0x03;
+const CMD_WRITE_DISABLE: u8 = 0x04;
+const CMD_READ_STATUS: u8 = 0x05;
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_FAST_READ: u8 = 0x0b;
+const CMD_SECTOR_ERASE: u8 = 0x20;
+const CMD_READ_JEDEC_ID: u8 = 0x9f;
+
+/// Write Enable Latch status bit
+const STATUS_WEL: u8 = 1 << 1;
+
+/// Opcode plus a 3-byte address
+const ADDR_HEADER_LEN: usize = 4;
+
+/// Dummy cycles between the address and data phases of FAST_READ
+const FAST_READ_DUMMY_CYCLES: u32 = 8;
+
+/// SPI NOR flash with 3-byte addressing
+///
+/// Programs and erases complete immediately, so the status register never
+/// reports a write in progress. When backed by a file, every program or erase
+/// is written through to the file.
+pub struct SpiFlash {
+    /// Flash contents
+    data: Vec<u8>,
+
+    /// Backing file
+    file: Option<File>,
+
+    /// Write Enable Latch
+    write_enabled: bool,
+
+    /// Opcode and address bytes received in the current transaction
+    header: Vec<u8>,
+
+    /// Dummy cycles received in the current transaction
+    dummy_cycles: u32,
+
+    /// Data bytes transferred in the current transaction
+    offset: u32,
+}
+
+impl SpiFlash {
+    /// JEDEC manufacturer and device ID
+    pub const JEDEC_ID: [u8; 3] = [0xef, 0x40, 0x18];
+
+    /// Page size in bytes
+    pub const PAGE_SIZE: usize = 256;
+
+    /// Sector size in bytes
+    pub const SECTOR_SIZE: usize = 4096;
+
+    /// Create a flash device with the given contents that is not backed by a
+    /// file.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Flash contents
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            file: None,
+            write_enabled: false,
+            header: Vec::with_capacity(ADDR_HEADER_LEN),
+            dummy_cycles: 0,
+            offset: 0,
+        }
+    }
+
+    /// Create a flash device whose contents are read from and written back
+    /// to `path`. The flash size is the size of the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Backing file
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        Ok(Self {
+            file: Some(file),
+            ..Self::new(data)
+        })
+    }
+
+    /// Flash contents
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Translate a device address into an index into the contents. Addresses
+    /// wrap around at the end of the device.
+    fn index(&self, addr: u32) -> Option<usize> {
+        if self.data.is_empty() {
+            None
+        } else {
+            Some(addr as usize % self.data.len())
+        }
+    }
+
+    fn addr(&self) -> u32 {
+        u32::from_be_bytes([0, self.header[1], self.header[2], self.header[3]])
+    }
+
+    fn status(&self) -> u8 {
+        if self.write_enabled {
+            STATUS_WEL
+        } else {
+            0
+        }
+    }
+
+    fn read_byte(&self, addr: u32) -> u8 {
+        self.index(addr).map_or(0xff, |i| self.data[i])
+    }
+
+    fn program_byte(&mut self, offset: u32, val: u8) {
+        // Page program wraps around within the page.
+        let addr = self.addr();
+        let page_offset = (addr as usize + offset as usize) % Self::PAGE_SIZE;
+        let addr = (addr as usize & !(Self::PAGE_SIZE - 1)) + page_offset;
+        if let Some(i) = self.index(addr as u32) {
+            // NOR flash programming can only clear bits.
+            self.data[i] &= val;
+        }
+    }
+
+    fn erase_sector(&mut self) {
+        let Some(start) = self.index(self.addr()) else {
+            return;
+        };
+        let start = start & !(Self::SECTOR_SIZE - 1);
+        let end = usize::min(start + Self::SECTOR_SIZE, self.data.len());
+        self.data[start..end].fill(0xff);
+        self.persist(start..end);
+    }
+
+    fn persist(&mut self, range: Range<usize>) {
+        if let Some(file) = self.file.as_mut() {
+            file.seek(SeekFrom::Start(range.start as u64))
+                .and_then(|_| file.write_all(&self.data[range]))
+                .expect("Failed to write SPI flash backing file");
+        }
+    }
+
+    fn reset_transaction(&mut self) {
+        self.header.clear();
+        self.dummy_cycles = 0;
+        self.offset = 0;
+    }
+}
+
+impl SpiDevice for SpiFlash {
+    fn select(&mut self) {
+        self.reset_transaction();
+    }
+
+    fn transfer(&mut self, tx: u8) -> u8 {
+        let Some(&opcode) = self.header.first() else {
+            self.header.push(tx);
+            return 0xff;
+        };
+        let has_addr = matches!(
+            opcode,
+            CMD_READ | CMD_FAST_READ | CMD_PAGE_PROGRAM | CMD_SECTOR_ERASE
+        );
+        if has_addr && self.header.len() < ADDR_HEADER_LEN {
+            self.header.push(tx);
+            return 0xff;
+        }
+        if opcode == CMD_FAST_READ && self.dummy_cycles < FAST_READ_DUMMY_CYCLES {
+            // The dummy byte may be clocked as data rather than as a dummy
+            // segment.
+            self.dummy_cycles += 8;
+            return 0xff;
+        }
+
+        let offset = self.offset;
+        self.offset = self.offset.wrapping_add(1);
+        match opcode {
+            CMD_READ | CMD_FAST_READ => self.read_byte(self.addr().wrapping_add(offset)),
+            CMD_READ_STATUS => self.status(),
+            CMD_READ_JEDEC_ID => Self::JEDEC_ID.get(offset as usize).copied().unwrap_or(0),
+            CMD_PAGE_PROGRAM => {
+                if self.write_enabled {
+                    self.program_byte(offset, tx);
+                }
+                0xff
+            }
+            _ => 0xff,
+        }
+    }
+
+    fn dummy_cycles(&mut self, cycles: u32) {
+        self.dummy_cycles = self.dummy_cycles.saturating_add(cycles);
+    }
+
+    fn deselect(&mut self) {
+        let opcode = self.header.first().copied();
+        let addressed = self.header.len() == ADDR_HEADER_LEN;
+        match opcode {
+            Some(CMD_WRITE_ENABLE) => self.write_enabled = true,
+            Some(CMD_WRITE_DISABLE) => self.write_enabled = false,
+            Some(CMD_PAGE_PROGRAM) if addressed && self.write_enabled => {
+                if let Some(i) = self.index(self.addr()) {
+                    let start = i & !(Self::PAGE_SIZE - 1);
+                    let end = usize::min(start + Self::PAGE_SIZE, self.data.len());
+                    self.persist(start..end);
+                }
+                self.write_enabled = false;
+            }
+            Some(CMD_SECTOR_ERASE) if addressed && self.write_enabled => {
+                self.erase_sector();
+                self.write_enabled = false;
+            }
+            _ => {}
+        }
+        self.reset_transaction();
+    }
+}
+
+/// Restoring a snapshot also rewrites the backing file, if any.
+impl Snapshot for SpiFlash {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.data)?;
+        w.write(&self.write_enabled)?;
+        w.write(&self.header)?;
+        w.write(&self.dummy_cycles)?;
+        w.write(&self.offset)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        let data = r.read_value::<Vec<u8>>()?;
+        if data.len() != self.data.len() {
+            return Err(SnapshotError::InvalidData("SPI flash size mismatch"));
+        }
+        self.data = data;
+        r.read(&mut self.write_enabled)?;
+        r.read(&mut self.header)?;
+        r.read(&mut self.dummy_cycles)?;
+        r.read(&mut self.offset)?;
+        self.persist(0..self.data.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transaction(flash: &mut SpiFlash, tx: &[u8], rx_len: usize) -> Vec<u8> {
+        flash.select();
+        for &b in tx {
+            flash.transfer(b);
+        }
+        let rx = (0..rx_len).map(|_| flash.transfer(0xff)).collect();
+        flash.deselect();
+        rx
+    }
+
+    #[test]
+    fn test_read() {
+        let mut flash = SpiFlash::new((0..=255).collect());
+        assert_eq!(
+            transaction(&mut flash, &[CMD_READ, 0x00, 0x00, 0xfe], 4),
+            vec![0xfe, 0xff, 0x00, 0x01]
+        );
+        assert_eq!(
+            transaction(&mut flash, &[CMD_FAST_READ, 0x00, 0x00, 0x10, 0x00], 2),
+            vec![0x10, 0x11]
+        );
+
+        flash.select();
+        for b in [CMD_FAST_READ, 0x00, 0x00, 0x20] {
+            flash.transfer(b);
+        }
+        flash.dummy_cycles(8);
+        assert_eq!(flash.transfer(0xff), 0x20);
+        flash.deselect();
+
+        assert_eq!(
+            transaction(&mut flash, &[CMD_READ_JEDEC_ID], 3),
+            SpiFlash::JEDEC_ID.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_program_and_erase() {
+        let mut flash = SpiFlash::new(vec![0xff; 2 * SpiFlash::SECTOR_SIZE]);
+
+        // Programming without the write enable latch is ignored.
+        transaction(&mut flash, &[CMD_PAGE_PROGRAM, 0x00, 0x00, 0x00, 0x12], 0);
+        assert_eq!(flash.data()[0], 0xff);
+
+        transaction(&mut flash, &[CMD_WRITE_ENABLE], 0);
+        assert_eq!(
+            transaction(&mut flash, &[CMD_READ_STATUS], 1),
+            vec![STATUS_WEL]
+        );
+        transaction(
+            &mut flash,
+            &[CMD_PAGE_PROGRAM, 0x00, 0x10, 0xff, 0x12, 0x34],
+            0,
+        );
+        assert_eq!(flash.data()[0x10ff], 0x12);
+        // Wrapped around to the start of the page
+        assert_eq!(flash.data()[0x1000], 0x34);
+        assert_eq!(transaction(&mut flash, &[CMD_READ_STATUS], 1), vec![0]);
+
+        transaction(&mut flash, &[CMD_WRITE_ENABLE], 0);
+        transaction(&mut flash, &[CMD_SECTOR_ERASE, 0x00, 0x1a, 0xbc], 0);
+        assert!(flash.data().iter().all(|&b| b == 0xff));
+    }
+
+    #[test]
+    fn test_file_backed() {
+        let path =
+            std::env::temp_dir().join(format!("caliptra-emu-spi-flash-{}.bin", std::process::id()));
+        std::fs::write(&path, vec![0xff; SpiFlash::SECTOR_SIZE]).unwrap();
+
+        let mut flash = SpiFlash::from_file(&path).unwrap();
+        transaction(&mut flash, &[CMD_WRITE_ENABLE], 0);
+        transaction(
+            &mut flash,
+            &[CMD_PAGE_PROGRAM, 0x00, 0x01, 0x00, 0xaa, 0x55],
+            0,
+        );
+        drop(flash);
+
+        let contents = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents.len(), SpiFlash::SECTOR_SIZE);
+        assert_eq!(&contents[0x100..0x103], &[0xaa, 0x55, 0xff]);
+    }
+}
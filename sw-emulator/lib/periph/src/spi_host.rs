@@ -0,0 +1,662 @@
+/*++
+
+Licensed under the Apache-2.0 license.
+
+File Name:
+
+    spi_host.rs
+
+Abstract:
+
+    File contains SPI host peripheral implementation.
+
+--*/
+
+use caliptra_emu_bus::{
+    BusError, ReadOnlyRegister, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter,
+    WriteOnlyRegister,
+};
+use caliptra_emu_derive::Bus;
+use caliptra_emu_types::{RvData, RvSize};
+use caliptra_registers::spi_host::regs::{ControlReadVal, EventEnableReadVal};
+use std::collections::VecDeque;
+
+/// A device attached to one of the SPI host's chip-select lines.
+///
+/// Transfers happen one byte at a time, in standard (single lane) SPI mode.
+pub trait SpiDevice: Snapshot {
+    /// Called when the chip-select line is asserted at the start of a
+    /// transaction.
+    fn select(&mut self) {}
+
+    /// Shift `tx` out to the device and return the byte shifted in.
+    fn transfer(&mut self, tx: u8) -> u8;
+
+    /// Called for a dummy segment of `cycles` clock cycles, during which no
+    /// data is exchanged.
+    fn dummy_cycles(&mut self, _cycles: u32) {}
+
+    /// Called when the chip-select line is released at the end of a
+    /// transaction.
+    fn deselect(&mut self) {}
+}
+
+/// Depth of the transmit FIFO in bytes
+const TX_FIFO_SIZE: usize = 72 * 4;
+
+/// Depth of the receive FIFO in bytes
+const RX_FIFO_SIZE: usize = 64 * 4;
+
+/// Interrupt bits
+const INTR_ERROR: u32 = 1 << 0;
+const INTR_SPI_EVENT: u32 = 1 << 1;
+
+/// Error status bits
+const ERR_CMDBUSY: u32 = 1 << 0;
+const ERR_OVERFLOW: u32 = 1 << 1;
+const ERR_UNDERFLOW: u32 = 1 << 2;
+const ERR_CMDINVAL: u32 = 1 << 3;
+const ERR_CSIDINVAL: u32 = 1 << 4;
+const ERR_MASK: u32 = 0x3f;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+enum Direction {
+    #[default]
+    Dummy = 0,
+    Rx = 1,
+    Tx = 2,
+    Bidirectional = 3,
+}
+
+impl From<u32> for Direction {
+    fn from(val: u32) -> Self {
+        match val & 3 {
+            1 => Direction::Rx,
+            2 => Direction::Tx,
+            3 => Direction::Bidirectional,
+            _ => Direction::Dummy,
+        }
+    }
+}
+
+/// Command segment being executed
+#[derive(Clone, Copy, Debug, Default)]
+struct Segment {
+    /// Bytes (or dummy cycles) left to transfer
+    remaining: u32,
+
+    direction: Direction,
+
+    /// Keep chip-select asserted after the segment completes
+    csaat: bool,
+}
+
+impl Snapshot for Segment {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.remaining)?;
+        w.write(&(self.direction as u32))?;
+        w.write(&self.csaat)
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.remaining)?;
+        self.direction = Direction::from(r.read_value::<u32>()?);
+        r.read(&mut self.csaat)
+    }
+}
+
+/// SPI host controller
+///
+/// Command segments execute as soon as the FIFOs allow, so from the
+/// firmware's point of view transfers complete instantly unless they stall
+/// on an empty TX FIFO or a full RX FIFO.
+#[derive(Bus)]
+pub struct SpiHost {
+    /// Interrupt State Register
+    #[register(offset = 0x00, read_fn = intr_state_read, write_fn = intr_state_write)]
+    intr_state: u32,
+
+    /// Interrupt Enable Register
+    #[register(offset = 0x04)]
+    intr_enable: u32,
+
+    /// Interrupt Test Register
+    #[register(offset = 0x08, write_fn = intr_test_write)]
+    intr_test: WriteOnlyRegister<u32>,
+
+    /// Alert Test Register
+    #[register(offset = 0x0c)]
+    alert_test: WriteOnlyRegister<u32>,
+
+    /// Control Register
+    #[register(offset = 0x10, write_fn = control_write)]
+    control: u32,
+
+    /// Status Register
+    #[register(offset = 0x14, read_fn = status_read)]
+    status: ReadOnlyRegister<u32>,
+
+    /// Configuration Options Registers (one per chip-select)
+    #[register_array(offset = 0x18)]
+    configopts: [u32; SpiHost::NUM_CS],
+
+    /// Chip-Select ID Register
+    #[register(offset = 0x20)]
+    csid: u32,
+
+    /// Command Register
+    #[register(offset = 0x24, write_fn = command_write)]
+    command: WriteOnlyRegister<u32>,
+
+    /// Receive Data Register
+    #[register(offset = 0x28, read_fn = rxdata_read)]
+    rxdata: ReadOnlyRegister<u32>,
+
+    /// Transmit Data Register
+    #[register(offset = 0x2c, write_fn = txdata_write)]
+    txdata: WriteOnlyRegister<u32>,
+
+    /// Error Enable Register
+    #[register(offset = 0x30)]
+    error_enable: u32,
+
+    /// Error Status Register
+    #[register(offset = 0x34, write_fn = error_status_write)]
+    error_status: u32,
+
+    /// Event Enable Register
+    #[register(offset = 0x38)]
+    event_enable: u32,
+
+    tx_fifo: VecDeque<u8>,
+    rx_fifo: VecDeque<u8>,
+
+    /// Segment currently being executed
+    segment: Option<Segment>,
+
+    /// Chip-select line currently asserted
+    selected: Option<usize>,
+
+    /// Devices attached to each chip-select line
+    devices: [Option<Box<dyn SpiDevice>>; SpiHost::NUM_CS],
+}
+
+impl SpiHost {
+    /// Number of chip-select lines
+    pub const NUM_CS: usize = 2;
+
+    /// Control register reset value
+    const CONTROL_RESET_VAL: u32 = 0x7f;
+
+    /// Error enable register reset value
+    const ERROR_ENABLE_RESET_VAL: u32 = 0x1f;
+
+    pub fn new() -> Self {
+        Self {
+            intr_state: 0,
+            intr_enable: 0,
+            intr_test: WriteOnlyRegister::new(0),
+            alert_test: WriteOnlyRegister::new(0),
+            control: Self::CONTROL_RESET_VAL,
+            status: ReadOnlyRegister::new(0),
+            configopts: [0; Self::NUM_CS],
+            csid: 0,
+            command: WriteOnlyRegister::new(0),
+            rxdata: ReadOnlyRegister::new(0),
+            txdata: WriteOnlyRegister::new(0),
+            error_enable: Self::ERROR_ENABLE_RESET_VAL,
+            error_status: 0,
+            event_enable: 0,
+            tx_fifo: VecDeque::new(),
+            rx_fifo: VecDeque::new(),
+            segment: None,
+            selected: None,
+            devices: Default::default(),
+        }
+    }
+
+    /// Attach a device to a chip-select line, replacing any device already
+    /// attached to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `csid` - Chip-select line
+    /// * `device` - Device to attach
+    pub fn attach(&mut self, csid: usize, device: Box<dyn SpiDevice>) {
+        self.devices[csid] = Some(device);
+    }
+
+    fn intr_state_read(&mut self, _size: RvSize) -> Result<RvData, BusError> {
+        // spi_event is a status interrupt; it reflects the current state of
+        // the enabled events rather than latching.
+        let mut state = self.intr_state;
+        if self.spi_event() {
+            state |= INTR_SPI_EVENT;
+        }
+        Ok(state)
+    }
+
+    fn intr_state_write(&mut self, _size: RvSize, val: RvData) -> Result<(), BusError> {
+        self.intr_state &= !(val & (INTR_ERROR | INTR_SPI_EVENT));
+        Ok(())
+    }
+
+    fn intr_test_write(&mut self, _size: RvSize, val: RvData) -> Result<(), BusError> {
+        self.intr_state |= val & (INTR_ERROR | INTR_SPI_EVENT);
+        Ok(())
+    }
+
+    fn control_write(&mut self, _size: RvSize, val: RvData) -> Result<(), BusError> {
+        self.control = val;
+        if ControlReadVal::from(val).sw_rst() {
+            self.tx_fifo.clear();
+            self.rx_fifo.clear();
+            self.segment = None;
+            self.release_cs();
+        }
+        Ok(())
+    }
+
+    fn status_read(&mut self, _size: RvSize) -> Result<RvData, BusError> {
+        let control = ControlReadVal::from(self.control);
+        let txqd = self.tx_queue_depth();
+        let rxqd = self.rx_queue_depth();
+        let direction = self.segment.map(|s| s.direction);
+        let rx_active = matches!(direction, Some(Direction::Rx | Direction::Bidirectional));
+        let tx_active = matches!(direction, Some(Direction::Tx | Direction::Bidirectional));
+
+        let mut status = (txqd & 0xff) | ((rxqd & 0xff) << 8);
+        status |= u32::from(rxqd >= control.rx_watermark()) << 20;
+        status |= u32::from(rx_active && self.rx_fifo.len() >= RX_FIFO_SIZE) << 23;
+        status |= u32::from(rxqd == 0) << 24;
+        status |= u32::from(self.rx_fifo.len() >= RX_FIFO_SIZE) << 25;
+        status |= u32::from(txqd < control.tx_watermark()) << 26;
+        status |= u32::from(tx_active && self.tx_fifo.is_empty()) << 27;
+        status |= u32::from(self.tx_fifo.is_empty()) << 28;
+        // Full once another word would not fit
+        status |= u32::from(self.tx_fifo.len() + 4 > TX_FIFO_SIZE) << 29;
+        status |= u32::from(self.segment.is_some()) << 30;
+        status |= u32::from(self.segment.is_none()) << 31;
+        Ok(status)
+    }
+
+    fn command_write(&mut self, _size: RvSize, val: RvData) -> Result<(), BusError> {
+        if self.segment.is_some() {
+            self.raise_error(ERR_CMDBUSY);
+            return Ok(());
+        }
+        let csid = self.csid as usize;
+        if csid >= Self::NUM_CS {
+            self.raise_error(ERR_CSIDINVAL);
+            return Ok(());
+        }
+        let speed = (val >> 10) & 3;
+        let direction = Direction::from(val >> 12);
+        if speed != 0 {
+            // Only standard SPI is emulated.
+            self.raise_error(ERR_CMDINVAL);
+            return Ok(());
+        }
+        if !ControlReadVal::from(self.control).spien() {
+            return Ok(());
+        }
+
+        if self.selected.is_some_and(|selected| selected != csid) {
+            self.release_cs();
+        }
+        if self.selected.is_none() {
+            self.selected = Some(csid);
+            if let Some(device) = self.devices[csid].as_mut() {
+                device.select();
+            }
+        }
+        self.segment = Some(Segment {
+            remaining: (val & 0x1ff) + 1,
+            direction,
+            csaat: (val >> 9) & 1 != 0,
+        });
+        self.process();
+        Ok(())
+    }
+
+    fn rxdata_read(&mut self, _size: RvSize) -> Result<RvData, BusError> {
+        if self.rx_fifo.len() < 4 {
+            self.raise_error(ERR_UNDERFLOW);
+            return Ok(0);
+        }
+        let bytes: Vec<u8> = self.rx_fifo.drain(..4).collect();
+        self.process();
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn txdata_write(&mut self, size: RvSize, val: RvData) -> Result<(), BusError> {
+        let len = match size {
+            RvSize::Byte => 1,
+            RvSize::HalfWord => 2,
+            RvSize::Word => 4,
+            RvSize::Invalid => return Err(BusError::StoreAccessFault),
+        };
+        if self.tx_fifo.len() + len > TX_FIFO_SIZE {
+            self.raise_error(ERR_OVERFLOW);
+            return Ok(());
+        }
+        self.tx_fifo.extend(&val.to_le_bytes()[..len]);
+        self.process();
+        Ok(())
+    }
+
+    fn error_status_write(&mut self, _size: RvSize, val: RvData) -> Result<(), BusError> {
+        self.error_status &= !(val & ERR_MASK);
+        Ok(())
+    }
+
+    /// Number of words, including partial words, in the TX FIFO
+    fn tx_queue_depth(&self) -> u32 {
+        ((self.tx_fifo.len() + 3) / 4) as u32
+    }
+
+    /// Number of complete words in the RX FIFO
+    fn rx_queue_depth(&self) -> u32 {
+        (self.rx_fifo.len() / 4) as u32
+    }
+
+    /// Record an error and raise the error interrupt if it is enabled.
+    fn raise_error(&mut self, err: u32) {
+        self.error_status |= err;
+        if self.error_enable & err != 0 {
+            self.intr_state |= INTR_ERROR;
+        }
+    }
+
+    /// Returns true if any enabled SPI event condition currently holds.
+    fn spi_event(&mut self) -> bool {
+        let events = EventEnableReadVal::from(self.event_enable);
+        let control = ControlReadVal::from(self.control);
+        let rxqd = self.rx_queue_depth();
+        let txqd = self.tx_queue_depth();
+        (events.rxfull() && self.rx_fifo.len() >= RX_FIFO_SIZE)
+            || (events.txempty() && self.tx_fifo.is_empty())
+            || (events.rxwm() && rxqd >= control.rx_watermark())
+            || (events.txwm() && txqd < control.tx_watermark())
+            || (events.ready() && self.segment.is_none())
+            || (events.idle() && self.segment.is_none())
+    }
+
+    fn release_cs(&mut self) {
+        if let Some(csid) = self.selected.take() {
+            if let Some(device) = self.devices[csid].as_mut() {
+                device.deselect();
+            }
+        }
+    }
+
+    /// Advance the current segment as far as the FIFOs allow.
+    fn process(&mut self) {
+        let Some(mut segment) = self.segment else {
+            return;
+        };
+        let Some(csid) = self.selected else {
+            return;
+        };
+        let mut device = self.devices[csid].as_mut();
+
+        if segment.direction == Direction::Dummy {
+            if let Some(device) = device.as_mut() {
+                device.dummy_cycles(segment.remaining);
+            }
+            segment.remaining = 0;
+        }
+        while segment.remaining > 0 {
+            let rx = matches!(segment.direction, Direction::Rx | Direction::Bidirectional);
+            let tx = matches!(segment.direction, Direction::Tx | Direction::Bidirectional);
+            if (rx && self.rx_fifo.len() >= RX_FIFO_SIZE) || (tx && self.tx_fifo.is_empty()) {
+                // Stall until firmware drains or fills the FIFO.
+                self.segment = Some(segment);
+                return;
+            }
+            let out = if tx {
+                self.tx_fifo.pop_front().unwrap()
+            } else {
+                0xff
+            };
+            let data = match device.as_mut() {
+                Some(device) => device.transfer(out),
+                None => 0xff,
+            };
+            if rx {
+                self.rx_fifo.push_back(data);
+            }
+            segment.remaining -= 1;
+        }
+
+        // Pad any partial word so firmware can read the last bytes of the
+        // segment.
+        while self.rx_fifo.len() % 4 != 0 {
+            self.rx_fifo.push_back(0);
+        }
+        self.segment = None;
+        if !segment.csaat {
+            self.release_cs();
+        }
+    }
+}
+
+impl Default for SpiHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Saves the registers, FIFOs and attached devices. The snapshot must be
+/// restored into a host with devices attached to the same chip-select lines.
+impl Snapshot for SpiHost {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.intr_state)?;
+        w.write(&self.intr_enable)?;
+        w.write(&self.control)?;
+        w.write(&self.configopts)?;
+        w.write(&self.csid)?;
+        w.write(&self.error_enable)?;
+        w.write(&self.error_status)?;
+        w.write(&self.event_enable)?;
+        w.write(&self.tx_fifo.iter().copied().collect::<Vec<u8>>())?;
+        w.write(&self.rx_fifo.iter().copied().collect::<Vec<u8>>())?;
+        w.write(&self.segment)?;
+        w.write(&self.selected.map(|csid| csid as u32))?;
+        for device in self.devices.iter() {
+            w.write(&device.is_some())?;
+            if let Some(device) = device {
+                device.save(w)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.intr_state)?;
+        r.read(&mut self.intr_enable)?;
+        r.read(&mut self.control)?;
+        r.read(&mut self.configopts)?;
+        r.read(&mut self.csid)?;
+        r.read(&mut self.error_enable)?;
+        r.read(&mut self.error_status)?;
+        r.read(&mut self.event_enable)?;
+        self.tx_fifo = r.read_value::<Vec<u8>>()?.into();
+        self.rx_fifo = r.read_value::<Vec<u8>>()?.into();
+        self.segment = r.read_value()?;
+        self.selected = match r.read_value::<Option<u32>>()? {
+            Some(csid) if csid as usize >= Self::NUM_CS => {
+                return Err(SnapshotError::InvalidData("invalid SPI chip-select"))
+            }
+            csid => csid.map(|csid| csid as usize),
+        };
+        for device in self.devices.iter_mut() {
+            let present = r.read_value::<bool>()?;
+            match device {
+                Some(device) if present => device.restore(r)?,
+                None if !present => {}
+                _ => return Err(SnapshotError::InvalidData("SPI device mismatch")),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use caliptra_emu_bus::Bus;
+    use caliptra_emu_types::RvAddr;
+
+    const OFFSET_CONTROL: RvAddr = 0x10;
+    const OFFSET_STATUS: RvAddr = 0x14;
+    const OFFSET_CSID: RvAddr = 0x20;
+    const OFFSET_COMMAND: RvAddr = 0x24;
+    const OFFSET_RXDATA: RvAddr = 0x28;
+    const OFFSET_TXDATA: RvAddr = 0x2c;
+    const OFFSET_ERROR_STATUS: RvAddr = 0x34;
+
+    const DIR_RX: u32 = 1 << 12;
+    const DIR_TX: u32 = 2 << 12;
+    const CSAAT: u32 = 1 << 9;
+
+    /// Echoes back each byte it receives, incremented by one.
+    #[derive(Default)]
+    struct Loopback {
+        selected: bool,
+        last: u8,
+        transactions: u32,
+    }
+
+    impl Snapshot for Loopback {
+        fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+            w.write(&self.selected)?;
+            w.write(&self.last)
+        }
+
+        fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+            r.read(&mut self.selected)?;
+            r.read(&mut self.last)
+        }
+    }
+
+    impl SpiDevice for Loopback {
+        fn select(&mut self) {
+            self.selected = true;
+        }
+
+        fn transfer(&mut self, tx: u8) -> u8 {
+            assert!(self.selected);
+            let rx = self.last;
+            self.last = tx.wrapping_add(1);
+            rx
+        }
+
+        fn deselect(&mut self) {
+            self.selected = false;
+            self.transactions += 1;
+        }
+    }
+
+    fn enabled_host() -> SpiHost {
+        let mut host = SpiHost::new();
+        host.attach(0, Box::<Loopback>::default());
+        host.write(RvSize::Word, OFFSET_CONTROL, 1 << 31).unwrap();
+        host
+    }
+
+    #[test]
+    fn test_tx_then_rx() {
+        let mut host = enabled_host();
+        host.write(RvSize::Word, OFFSET_TXDATA, 0x0403_0201)
+            .unwrap();
+        host.write(RvSize::Byte, OFFSET_TXDATA, 0x05).unwrap();
+        host.write(RvSize::Word, OFFSET_COMMAND, DIR_TX | CSAAT | 4)
+            .unwrap();
+        host.write(RvSize::Word, OFFSET_COMMAND, DIR_RX | 2)
+            .unwrap();
+
+        let status = host.read(RvSize::Word, OFFSET_STATUS).unwrap();
+        assert_eq!(status & (1 << 31), 1 << 31);
+        assert_eq!((status >> 8) & 0xff, 1);
+        assert_eq!(status & (1 << 28), 1 << 28);
+
+        // The first RX byte is the last TX byte plus one; the loopback then
+        // echoes 0xff + 1 for each idle byte.
+        assert_eq!(host.read(RvSize::Word, OFFSET_RXDATA).unwrap(), 0x0000_0006);
+        assert_eq!(host.read(RvSize::Word, OFFSET_ERROR_STATUS).unwrap(), 0);
+
+        let status = host.read(RvSize::Word, OFFSET_STATUS).unwrap();
+        assert_eq!(status & (1 << 24), 1 << 24);
+    }
+
+    #[test]
+    fn test_tx_stalls_until_data() {
+        let mut host = enabled_host();
+        host.write(RvSize::Word, OFFSET_COMMAND, DIR_TX | 7)
+            .unwrap();
+        let status = host.read(RvSize::Word, OFFSET_STATUS).unwrap();
+        assert_eq!(status & (1 << 30), 1 << 30);
+        assert_eq!(status & (1 << 27), 1 << 27);
+
+        host.write(RvSize::Word, OFFSET_TXDATA, 0).unwrap();
+        assert_eq!(
+            host.read(RvSize::Word, OFFSET_STATUS).unwrap() & (1 << 30),
+            1 << 30
+        );
+        host.write(RvSize::Word, OFFSET_TXDATA, 0).unwrap();
+        assert_eq!(
+            host.read(RvSize::Word, OFFSET_STATUS).unwrap() & (1 << 31),
+            1 << 31
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut host = enabled_host();
+        host.read(RvSize::Word, OFFSET_RXDATA).unwrap();
+        assert_eq!(
+            host.read(RvSize::Word, OFFSET_ERROR_STATUS).unwrap(),
+            ERR_UNDERFLOW
+        );
+        host.write(RvSize::Word, OFFSET_ERROR_STATUS, ERR_UNDERFLOW)
+            .unwrap();
+
+        host.write(RvSize::Word, OFFSET_CSID, 2).unwrap();
+        host.write(RvSize::Word, OFFSET_COMMAND, DIR_RX).unwrap();
+        assert_eq!(
+            host.read(RvSize::Word, OFFSET_ERROR_STATUS).unwrap(),
+            ERR_CSIDINVAL
+        );
+        assert_eq!(host.read(RvSize::Word, 0x00).unwrap() & INTR_ERROR, 1);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut host = enabled_host();
+        host.write(RvSize::Word, OFFSET_TXDATA, 0x0000_0041)
+            .unwrap();
+        host.write(RvSize::Word, OFFSET_COMMAND, DIR_TX | CSAAT)
+            .unwrap();
+
+        let mut w = SnapshotWriter::new();
+        w.write(&host).unwrap();
+        let snapshot = w.into_bytes();
+
+        let mut restored = SpiHost::new();
+        restored.attach(0, Box::<Loopback>::default());
+        restored
+            .restore(&mut SnapshotReader::new(&snapshot))
+            .unwrap();
+        restored
+            .write(RvSize::Word, OFFSET_COMMAND, DIR_RX)
+            .unwrap();
+        assert_eq!(restored.read(RvSize::Word, OFFSET_RXDATA).unwrap(), 0x42);
+
+        let mut detached = SpiHost::new();
+        assert_eq!(
+            detached.restore(&mut SnapshotReader::new(&snapshot)),
+            Err(SnapshotError::InvalidData("SPI device mismatch"))
+        );
+    }
+}
@@ -12,7 +12,7 @@ Abstract:
 
 --*/
 
-use caliptra_emu_bus::{Bus, BusError};
+use caliptra_emu_bus::{Bus, BusError, Snapshot, SnapshotError, SnapshotReader, SnapshotWriter};
 use caliptra_emu_types::{RvAddr, RvData, RvSize};
 
 pub struct Uart {
@@ -56,6 +56,22 @@ impl Default for Uart {
     }
 }
 
+impl Snapshot for Uart {
+    fn save(&self, w: &mut SnapshotWriter) -> Result<(), SnapshotError> {
+        w.write(&self.bit_rate)?;
+        w.write(&self.data_bits)?;
+        w.write(&self.stop_bits)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, r: &mut SnapshotReader) -> Result<(), SnapshotError> {
+        r.read(&mut self.bit_rate)?;
+        r.read(&mut self.data_bits)?;
+        r.read(&mut self.stop_bits)?;
+        Ok(())
+    }
+}
+
 impl Bus for Uart {
     /// Read data of specified size from given address
     ///
@@ -0,0 +1,755 @@
+// Licensed under the Apache-2.0 license
+
+//! Differential tests of the emulated crypto peripherals.
+//!
+//! Random keys and messages are driven through `AsymEcc384`, `HmacSha384`,
+//! `HashSha512` and `Sha512Accelerator` at the register level, the way
+//! firmware drives them, and the results are compared with OpenSSL. OpenSSL
+//! is used as the reference because the emulated engines are themselves
+//! built on RustCrypto.
+
+use caliptra_emu_bus::{Bus, Clock};
+use caliptra_emu_cpu::Pic;
+use caliptra_emu_periph::{
+    AsymEcc384, HashSha512, HmacSha384, KeyUsage, KeyVault, MailboxRam, Sha512Accelerator,
+};
+use caliptra_emu_types::{RvAddr, RvSize};
+use caliptra_hw_model_types::CryptoTiming;
+use openssl::bn::{BigNum, BigNumContext};
+use openssl::ec::{EcGroup, EcKey, EcPoint};
+use openssl::ecdsa::EcdsaSig;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use proptest::prelude::*;
+
+/// Registers shared by the SHA512, HMAC and ECC engines.
+const OFFSET_CONTROL: RvAddr = 0x10;
+const OFFSET_STATUS: RvAddr = 0x18;
+const STATUS_READY: u32 = 1 << 0;
+const STATUS_VALID: u32 = 1 << 1;
+
+/// Key vault read and write control fields, common to all engines.
+const KV_EN: u32 = 1 << 0;
+const KV_KEY_ID_SHIFT: u32 = 1;
+const KV_USAGE_SHIFT: u32 = 6;
+const KV_ERROR_SHIFT: u32 = 2;
+
+const SHA512_OFFSET_BLOCK: RvAddr = 0x80;
+const SHA512_OFFSET_HASH: RvAddr = 0x100;
+const SHA512_OFFSET_BLOCK_CONTROL: RvAddr = 0x600;
+const SHA512_OFFSET_BLOCK_STATUS: RvAddr = 0x604;
+const SHA512_OFFSET_HASH_CONTROL: RvAddr = 0x608;
+const SHA512_OFFSET_HASH_STATUS: RvAddr = 0x60c;
+const SHA512_CONTROL_INIT: u32 = 1 << 0;
+const SHA512_CONTROL_NEXT: u32 = 1 << 1;
+const SHA512_CONTROL_MODE_SHIFT: u32 = 2;
+
+const HMAC_OFFSET_KEY: RvAddr = 0x40;
+const HMAC_OFFSET_BLOCK: RvAddr = 0x80;
+const HMAC_OFFSET_TAG: RvAddr = 0x100;
+const HMAC_OFFSET_KEY_CONTROL: RvAddr = 0x600;
+const HMAC_OFFSET_KEY_STATUS: RvAddr = 0x604;
+const HMAC_OFFSET_BLOCK_CONTROL: RvAddr = 0x608;
+const HMAC_OFFSET_BLOCK_STATUS: RvAddr = 0x60c;
+const HMAC_OFFSET_TAG_CONTROL: RvAddr = 0x610;
+const HMAC_OFFSET_TAG_STATUS: RvAddr = 0x614;
+const HMAC_CONTROL_INIT: u32 = 1 << 0;
+const HMAC_CONTROL_NEXT: u32 = 1 << 1;
+
+const ECC_OFFSET_SEED: RvAddr = 0x80;
+const ECC_OFFSET_HASH: RvAddr = 0x100;
+const ECC_OFFSET_PRIV_KEY_OUT: RvAddr = 0x180;
+const ECC_OFFSET_PUB_KEY_X: RvAddr = 0x200;
+const ECC_OFFSET_PUB_KEY_Y: RvAddr = 0x280;
+const ECC_OFFSET_SIG_R: RvAddr = 0x300;
+const ECC_OFFSET_SIG_S: RvAddr = 0x380;
+const ECC_OFFSET_VERIFY_R: RvAddr = 0x400;
+const ECC_OFFSET_NONCE: RvAddr = 0x500;
+const ECC_OFFSET_PRIV_KEY_IN: RvAddr = 0x580;
+const ECC_OFFSET_KEY_READ_CONTROL: RvAddr = 0x600;
+const ECC_OFFSET_KEY_READ_STATUS: RvAddr = 0x604;
+const ECC_OFFSET_SEED_READ_CONTROL: RvAddr = 0x608;
+const ECC_OFFSET_SEED_READ_STATUS: RvAddr = 0x60c;
+const ECC_OFFSET_KEY_WRITE_CONTROL: RvAddr = 0x610;
+const ECC_OFFSET_KEY_WRITE_STATUS: RvAddr = 0x614;
+const ECC_CONTROL_GEN_KEY: u32 = 1;
+const ECC_CONTROL_SIGN: u32 = 2;
+const ECC_CONTROL_VERIFY: u32 = 3;
+
+const ACC_OFFSET_MODE: RvAddr = 0x08;
+const ACC_OFFSET_START_ADDRESS: RvAddr = 0x0c;
+const ACC_OFFSET_DLEN: RvAddr = 0x10;
+const ACC_OFFSET_DATAIN: RvAddr = 0x14;
+const ACC_OFFSET_EXECUTE: RvAddr = 0x18;
+const ACC_OFFSET_STATUS: RvAddr = 0x1c;
+const ACC_OFFSET_DIGEST: RvAddr = 0x20;
+const ACC_MODE_STREAM_384: u32 = 0;
+const ACC_MODE_STREAM_512: u32 = 1;
+const ACC_MODE_MBOX_384: u32 = 2;
+const ACC_MODE_ENDIAN_TOGGLE: u32 = 1 << 2;
+const ACC_STATUS_VALID: u32 = 1 << 0;
+
+const SHA512_BLOCK_SIZE: usize = 128;
+const MAX_TICKS: usize = 1_000_000;
+
+/// SHA512 engine modes, as written to the MODE field of CONTROL.
+#[derive(Clone, Copy, Debug)]
+enum ShaMode {
+    Sha384 = 2,
+    Sha512 = 3,
+}
+
+impl ShaMode {
+    fn digest(self) -> MessageDigest {
+        match self {
+            ShaMode::Sha384 => MessageDigest::sha384(),
+            ShaMode::Sha512 => MessageDigest::sha512(),
+        }
+    }
+
+    fn hash_len(self) -> usize {
+        self.digest().size()
+    }
+}
+
+fn sha_mode() -> impl Strategy<Value = ShaMode> {
+    prop_oneof![Just(ShaMode::Sha384), Just(ShaMode::Sha512)]
+}
+
+fn bytes48() -> impl Strategy<Value = [u8; 48]> {
+    prop::collection::vec(any::<u8>(), 48).prop_map(|v| v.try_into().unwrap())
+}
+
+fn key_id() -> impl Strategy<Value = u32> {
+    0..KeyVault::KEY_COUNT
+}
+
+fn write_words(bus: &mut impl Bus, addr: RvAddr, data: &[u8]) {
+    assert_eq!(data.len() % 4, 0);
+    for (i, chunk) in data.chunks_exact(4).enumerate() {
+        let word = u32::from_be_bytes(chunk.try_into().unwrap());
+        bus.write(RvSize::Word, addr + (i * 4) as RvAddr, word)
+            .unwrap();
+    }
+}
+
+fn read_words(bus: &mut impl Bus, addr: RvAddr, len: usize) -> Vec<u8> {
+    (0..len / 4)
+        .flat_map(|i| {
+            bus.read(RvSize::Word, addr + (i * 4) as RvAddr)
+                .unwrap()
+                .to_be_bytes()
+        })
+        .collect()
+}
+
+/// Keys are stored in the vault as the little-endian bytes of the register
+/// words, so each 4-byte word of a big-endian value is reversed.
+fn swap_words(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|chunk| {
+            let mut word: [u8; 4] = chunk.try_into().unwrap();
+            word.reverse();
+            word
+        })
+        .collect()
+}
+
+fn write_kv(kv: &mut KeyVault, id: u32, data: &[u8], usage: KeyUsage) {
+    kv.write_key(id, &swap_words(data), usage.into()).unwrap();
+}
+
+fn read_kv(kv: &KeyVault, id: u32, usage: KeyUsage) -> Vec<u8> {
+    swap_words(&kv.read_key(id, usage).unwrap())
+}
+
+fn usage(f: impl FnOnce(&mut KeyUsage)) -> KeyUsage {
+    let mut usage = KeyUsage::default();
+    f(&mut usage);
+    usage
+}
+
+fn kv_read_ctrl(id: u32) -> u32 {
+    KV_EN | (id << KV_KEY_ID_SHIFT)
+}
+
+fn kv_write_ctrl(id: u32, usage: KeyUsage) -> u32 {
+    KV_EN | (id << KV_KEY_ID_SHIFT) | (u32::from(usage) << KV_USAGE_SHIFT)
+}
+
+/// Advance the clock until all bits of `mask` are set in the register at
+/// `addr`.
+fn wait_for(clock: &Clock, bus: &mut impl Bus, addr: RvAddr, mask: u32) -> u32 {
+    for _ in 0..MAX_TICKS {
+        let val = bus.read(RvSize::Word, addr).unwrap();
+        if val & mask == mask {
+            return val;
+        }
+        clock.increment_and_process_timer_actions(1, bus);
+    }
+    panic!("timed out waiting for {mask:#x} at {addr:#x}");
+}
+
+/// Wait for a key vault transfer and check it succeeded.
+fn wait_for_kv(clock: &Clock, bus: &mut impl Bus, status_addr: RvAddr) {
+    let status = wait_for(clock, bus, status_addr, STATUS_VALID);
+    assert_eq!(status >> KV_ERROR_SHIFT, 0, "key vault transfer failed");
+}
+
+/// Pad `data` the way firmware does before writing it to the SHA512 or HMAC
+/// block registers; `prefix_len` is the length of data already hashed
+/// elsewhere (the HMAC inner key block).
+fn sha512_pad(data: &[u8], prefix_len: usize) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % SHA512_BLOCK_SIZE != SHA512_BLOCK_SIZE - 16 {
+        padded.push(0);
+    }
+    let bit_len = ((prefix_len + data.len()) as u128) * 8;
+    padded.extend_from_slice(&bit_len.to_be_bytes());
+    padded
+}
+
+fn openssl_hash(mode: ShaMode, data: &[u8]) -> Vec<u8> {
+    openssl::hash::hash(mode.digest(), data).unwrap().to_vec()
+}
+
+fn openssl_hmac384(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let key = PKey::hmac(key).unwrap();
+    let mut signer = Signer::new(MessageDigest::sha384(), &key).unwrap();
+    signer.update(data).unwrap();
+    signer.sign_to_vec().unwrap()
+}
+
+fn p384() -> EcGroup {
+    EcGroup::from_curve_name(Nid::SECP384R1).unwrap()
+}
+
+/// The public key for `priv_key`, as big-endian x and y coordinates.
+fn openssl_pub_key(priv_key: &[u8]) -> ([u8; 48], [u8; 48]) {
+    let group = p384();
+    let mut ctx = BigNumContext::new().unwrap();
+    let priv_key = BigNum::from_slice(priv_key).unwrap();
+    let mut point = EcPoint::new(&group).unwrap();
+    point.mul_generator(&group, &priv_key, &ctx).unwrap();
+    let mut x = BigNum::new().unwrap();
+    let mut y = BigNum::new().unwrap();
+    point
+        .affine_coordinates(&group, &mut x, &mut y, &mut ctx)
+        .unwrap();
+    (
+        x.to_vec_padded(48).unwrap().try_into().unwrap(),
+        y.to_vec_padded(48).unwrap().try_into().unwrap(),
+    )
+}
+
+fn openssl_verify(pub_x: &[u8], pub_y: &[u8], hash: &[u8], r: &[u8], s: &[u8]) -> bool {
+    let key = EcKey::from_public_key_affine_coordinates(
+        &p384(),
+        &BigNum::from_slice(pub_x).unwrap(),
+        &BigNum::from_slice(pub_y).unwrap(),
+    )
+    .unwrap();
+    let sig = EcdsaSig::from_private_components(
+        BigNum::from_slice(r).unwrap(),
+        BigNum::from_slice(s).unwrap(),
+    )
+    .unwrap();
+    sig.verify(hash, &key).unwrap()
+}
+
+struct Sha512Engine {
+    clock: Clock,
+    kv: KeyVault,
+    sha: HashSha512,
+}
+
+impl Sha512Engine {
+    fn new() -> Self {
+        let clock = Clock::new();
+        let kv = KeyVault::new();
+        let mut sha = HashSha512::new(&clock, kv.clone(), &Pic::new());
+        sha.set_timing(CryptoTiming::FAST);
+        Self { clock, kv, sha }
+    }
+
+    fn process_block(&mut self, mode: ShaMode, first: bool) {
+        let op = if first {
+            SHA512_CONTROL_INIT
+        } else {
+            SHA512_CONTROL_NEXT
+        };
+        self.sha
+            .write(
+                RvSize::Word,
+                OFFSET_CONTROL,
+                op | ((mode as u32) << SHA512_CONTROL_MODE_SHIFT),
+            )
+            .unwrap();
+        wait_for(
+            &self.clock,
+            &mut self.sha,
+            OFFSET_STATUS,
+            STATUS_READY | STATUS_VALID,
+        );
+    }
+
+    fn hash(&mut self, mode: ShaMode, data: &[u8]) -> Vec<u8> {
+        let padded = sha512_pad(data, 0);
+        for (i, block) in padded.chunks_exact(SHA512_BLOCK_SIZE).enumerate() {
+            write_words(&mut self.sha, SHA512_OFFSET_BLOCK, block);
+            self.process_block(mode, i == 0);
+        }
+        read_words(&mut self.sha, SHA512_OFFSET_HASH, mode.hash_len())
+    }
+
+    /// Hash the key in slot `id`, which the engine pads itself.
+    fn hash_kv(&mut self, mode: ShaMode, id: u32) -> Vec<u8> {
+        self.sha
+            .write(RvSize::Word, SHA512_OFFSET_BLOCK_CONTROL, kv_read_ctrl(id))
+            .unwrap();
+        wait_for_kv(&self.clock, &mut self.sha, SHA512_OFFSET_BLOCK_STATUS);
+        self.process_block(mode, true);
+        read_words(&mut self.sha, SHA512_OFFSET_HASH, mode.hash_len())
+    }
+}
+
+struct HmacEngine {
+    clock: Clock,
+    kv: KeyVault,
+    hmac: HmacSha384,
+}
+
+/// Where the HMAC engine gets an input from.
+#[derive(Clone, Debug)]
+enum Source {
+    Regs(Vec<u8>),
+    KeyVault(u32, Vec<u8>),
+}
+
+impl Source {
+    fn data(&self) -> &[u8] {
+        match self {
+            Source::Regs(data) | Source::KeyVault(_, data) => data,
+        }
+    }
+}
+
+impl HmacEngine {
+    fn new() -> Self {
+        let clock = Clock::new();
+        let kv = KeyVault::new();
+        let mut hmac = HmacSha384::new(&clock, kv.clone(), &Pic::new());
+        hmac.set_timing(CryptoTiming::FAST);
+        Self { clock, kv, hmac }
+    }
+
+    fn run(&mut self, op: u32) {
+        self.hmac.write(RvSize::Word, OFFSET_CONTROL, op).unwrap();
+        wait_for(
+            &self.clock,
+            &mut self.hmac,
+            OFFSET_STATUS,
+            STATUS_READY | STATUS_VALID,
+        );
+    }
+
+    /// Compute the tag, writing it to key vault slot `tag_id` if set.
+    fn hmac(&mut self, key: &Source, data: &Source, tag_id: Option<u32>) -> Vec<u8> {
+        match key {
+            Source::Regs(key) => write_words(&mut self.hmac, HMAC_OFFSET_KEY, key),
+            Source::KeyVault(id, key) => {
+                write_kv(&mut self.kv, *id, key, usage(|u| u.set_hmac_key(true)));
+                self.hmac
+                    .write(RvSize::Word, HMAC_OFFSET_KEY_CONTROL, kv_read_ctrl(*id))
+                    .unwrap();
+                wait_for_kv(&self.clock, &mut self.hmac, HMAC_OFFSET_KEY_STATUS);
+            }
+        }
+        let tag_usage = usage(|u| u.set_hmac_data(true));
+        if let Some(tag_id) = tag_id {
+            self.hmac
+                .write(
+                    RvSize::Word,
+                    HMAC_OFFSET_TAG_CONTROL,
+                    kv_write_ctrl(tag_id, tag_usage),
+                )
+                .unwrap();
+        }
+        match data {
+            Source::Regs(data) => {
+                let padded = sha512_pad(data, SHA512_BLOCK_SIZE);
+                for (i, block) in padded.chunks_exact(SHA512_BLOCK_SIZE).enumerate() {
+                    write_words(&mut self.hmac, HMAC_OFFSET_BLOCK, block);
+                    self.run(if i == 0 {
+                        HMAC_CONTROL_INIT
+                    } else {
+                        HMAC_CONTROL_NEXT
+                    });
+                }
+            }
+            Source::KeyVault(id, data) => {
+                write_kv(&mut self.kv, *id, data, usage(|u| u.set_hmac_data(true)));
+                self.hmac
+                    .write(RvSize::Word, HMAC_OFFSET_BLOCK_CONTROL, kv_read_ctrl(*id))
+                    .unwrap();
+                wait_for_kv(&self.clock, &mut self.hmac, HMAC_OFFSET_BLOCK_STATUS);
+                self.run(HMAC_CONTROL_INIT);
+            }
+        }
+        match tag_id {
+            Some(tag_id) => {
+                wait_for_kv(&self.clock, &mut self.hmac, HMAC_OFFSET_TAG_STATUS);
+                read_kv(&self.kv, tag_id, tag_usage)
+            }
+            None => read_words(&mut self.hmac, HMAC_OFFSET_TAG, 48),
+        }
+    }
+}
+
+struct EccEngine {
+    clock: Clock,
+    kv: KeyVault,
+    ecc: AsymEcc384,
+}
+
+impl EccEngine {
+    fn new() -> Self {
+        let clock = Clock::new();
+        let kv = KeyVault::new();
+        let sha = HashSha512::new(&clock, kv.clone(), &Pic::new());
+        let mut ecc = AsymEcc384::new(&clock, kv.clone(), sha, &Pic::new());
+        ecc.set_timing(CryptoTiming::FAST);
+        Self { clock, kv, ecc }
+    }
+
+    fn run(&mut self, op: u32) {
+        self.ecc.write(RvSize::Word, OFFSET_CONTROL, op).unwrap();
+        wait_for(
+            &self.clock,
+            &mut self.ecc,
+            OFFSET_STATUS,
+            STATUS_READY | STATUS_VALID,
+        );
+    }
+
+    fn read(&mut self, addr: RvAddr) -> Vec<u8> {
+        read_words(&mut self.ecc, addr, 48)
+    }
+
+    fn pub_key(&mut self) -> (Vec<u8>, Vec<u8>) {
+        (
+            self.read(ECC_OFFSET_PUB_KEY_X),
+            self.read(ECC_OFFSET_PUB_KEY_Y),
+        )
+    }
+
+    fn sign(&mut self, hash: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        write_words(&mut self.ecc, ECC_OFFSET_HASH, hash);
+        self.run(ECC_CONTROL_SIGN);
+        (self.read(ECC_OFFSET_SIG_R), self.read(ECC_OFFSET_SIG_S))
+    }
+}
+
+fn acc_digest(clock: &Clock, acc: &mut Sha512Accelerator, hash_len: usize) -> Vec<u8> {
+    acc.write(RvSize::Word, ACC_OFFSET_EXECUTE, 1).unwrap();
+    wait_for(clock, acc, ACC_OFFSET_STATUS, ACC_STATUS_VALID);
+    read_words(acc, ACC_OFFSET_DIGEST, hash_len)
+}
+
+/// Hash `data` in one of the accelerator's streaming modes. `data` is
+/// rounded up to whole words with `fill`, which the accelerator must
+/// ignore.
+fn acc_stream(mode: ShaMode, data: &[u8], fill: u8) -> Vec<u8> {
+    let clock = Clock::new();
+    let mut acc = Sha512Accelerator::new(&clock, MailboxRam::new(), &Pic::new());
+    acc.set_timing(CryptoTiming::FAST);
+    let acc_mode = match mode {
+        ShaMode::Sha384 => ACC_MODE_STREAM_384,
+        ShaMode::Sha512 => ACC_MODE_STREAM_512,
+    };
+    acc.write(RvSize::Word, ACC_OFFSET_MODE, acc_mode).unwrap();
+    acc.write(RvSize::Word, ACC_OFFSET_DLEN, data.len() as u32)
+        .unwrap();
+    for chunk in fill_words(data, fill).chunks_exact(4) {
+        let word = u32::from_be_bytes(chunk.try_into().unwrap());
+        acc.write(RvSize::Word, ACC_OFFSET_DATAIN, word).unwrap();
+    }
+    acc_digest(&clock, &mut acc, mode.hash_len())
+}
+
+/// Round `data` up to whole words with `fill`.
+fn fill_words(data: &[u8], fill: u8) -> Vec<u8> {
+    let mut words = data.to_vec();
+    while words.len() % 4 != 0 {
+        words.push(fill);
+    }
+    words
+}
+
+/// Hash `data` stored in the mailbox at `start_address`, in the byte order
+/// the SoC wrote it. The rest of the last word is set to `fill`.
+fn acc_mbox(data: &[u8], start_address: u32, endian_toggle: bool, fill: u8) -> Vec<u8> {
+    let clock = Clock::new();
+    let mut ram = MailboxRam::new();
+    for (i, chunk) in fill_words(data, fill).chunks_exact(4).enumerate() {
+        let word = u32::from_le_bytes(chunk.try_into().unwrap());
+        ram.write(RvSize::Word, start_address + (i * 4) as u32, word)
+            .unwrap();
+    }
+    let mut acc = Sha512Accelerator::new(&clock, ram, &Pic::new());
+    acc.set_timing(CryptoTiming::FAST);
+    let mut mode = ACC_MODE_MBOX_384;
+    if endian_toggle {
+        mode |= ACC_MODE_ENDIAN_TOGGLE;
+    }
+    acc.write(RvSize::Word, ACC_OFFSET_MODE, mode).unwrap();
+    acc.write(RvSize::Word, ACC_OFFSET_START_ADDRESS, start_address)
+        .unwrap();
+    acc.write(RvSize::Word, ACC_OFFSET_DLEN, data.len() as u32)
+        .unwrap();
+    acc_digest(&clock, &mut acc, 48)
+}
+
+/// The message the accelerator hashes for [`acc_mbox`]: the mailbox bytes
+/// as written, or with ENDIAN_TOGGLE, each word byte-swapped first. Either
+/// way only the first `dlen` bytes count.
+fn acc_mbox_message(data: &[u8], endian_toggle: bool, fill: u8) -> Vec<u8> {
+    if endian_toggle {
+        swap_words(&fill_words(data, fill))[..data.len()].to_vec()
+    } else {
+        data.to_vec()
+    }
+}
+
+/// Every message length up to a few blocks, so each padding boundary is
+/// exercised at least once regardless of what proptest picks.
+#[test]
+fn test_sha512_all_partial_blocks() {
+    for len in 0..=3 * SHA512_BLOCK_SIZE {
+        let data: Vec<u8> = (0..len).map(|i| (i * 7 + len) as u8).collect();
+        for mode in [ShaMode::Sha384, ShaMode::Sha512] {
+            let expected = openssl_hash(mode, &data);
+            assert_eq!(
+                Sha512Engine::new().hash(mode, &data),
+                expected,
+                "SHA512 engine, {mode:?}, len {len}"
+            );
+            assert_eq!(
+                acc_stream(mode, &data, 0xa5),
+                expected,
+                "accelerator stream, {mode:?}, len {len}"
+            );
+        }
+        assert_eq!(
+            acc_mbox(&data, 0, false, 0xa5),
+            openssl_hash(ShaMode::Sha384, &data),
+            "accelerator mailbox, len {len}"
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_sha512(mode in sha_mode(), data in prop::collection::vec(any::<u8>(), 0..1024)) {
+        prop_assert_eq!(Sha512Engine::new().hash(mode, &data), openssl_hash(mode, &data));
+    }
+
+    #[test]
+    fn test_sha512_block_from_kv(mode in sha_mode(), id in key_id(), key in bytes48()) {
+        let mut engine = Sha512Engine::new();
+        write_kv(&mut engine.kv, id, &key, usage(|u| u.set_sha_data(true)));
+        prop_assert_eq!(engine.hash_kv(mode, id), openssl_hash(mode, &key));
+    }
+
+    #[test]
+    fn test_sha384_hash_to_kv(
+        id in key_id(),
+        data in prop::collection::vec(any::<u8>(), 0..512),
+    ) {
+        let mut engine = Sha512Engine::new();
+        let hash_usage = usage(|u| u.set_hmac_key(true));
+        engine
+            .sha
+            .write(
+                RvSize::Word,
+                SHA512_OFFSET_HASH_CONTROL,
+                kv_write_ctrl(id, hash_usage),
+            )
+            .unwrap();
+        engine.hash(ShaMode::Sha384, &data);
+        wait_for_kv(&engine.clock, &mut engine.sha, SHA512_OFFSET_HASH_STATUS);
+        prop_assert_eq!(
+            read_kv(&engine.kv, id, hash_usage),
+            openssl_hash(ShaMode::Sha384, &data)
+        );
+    }
+
+    #[test]
+    fn test_hmac384(key in bytes48(), data in prop::collection::vec(any::<u8>(), 0..1024)) {
+        let tag = HmacEngine::new().hmac(&Source::Regs(key.to_vec()), &Source::Regs(data.clone()), None);
+        prop_assert_eq!(tag, openssl_hmac384(&key, &data));
+    }
+
+    #[test]
+    fn test_hmac384_key_vault(
+        key_id in key_id(),
+        key in bytes48(),
+        key_from_kv in any::<bool>(),
+        data_id in key_id(),
+        data_words in 1usize..=12,
+        data_from_kv in any::<bool>(),
+        data in bytes48(),
+        tag_id in prop::option::of(key_id()),
+    ) {
+        // Outputs derived from key vault inputs can only go back to the key
+        // vault.
+        let tag_id = tag_id.or((key_from_kv || data_from_kv).then_some(31));
+        let data_id = if data_from_kv && key_from_kv && data_id == key_id {
+            (key_id + 1) % KeyVault::KEY_COUNT
+        } else {
+            data_id
+        };
+        let data = data[..data_words * 4].to_vec();
+        let key_src = if key_from_kv {
+            Source::KeyVault(key_id, key.to_vec())
+        } else {
+            Source::Regs(key.to_vec())
+        };
+        let data_src = if data_from_kv {
+            Source::KeyVault(data_id, data)
+        } else {
+            Source::Regs(data)
+        };
+        let tag = HmacEngine::new().hmac(&key_src, &data_src, tag_id);
+        prop_assert_eq!(tag, openssl_hmac384(key_src.data(), data_src.data()));
+    }
+}
+
+proptest! {
+    // Every case runs several P-384 operations in a debug build.
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_ecc384_keygen(seed in bytes48(), nonce in bytes48()) {
+        let mut engine = EccEngine::new();
+        write_words(&mut engine.ecc, ECC_OFFSET_SEED, &seed);
+        write_words(&mut engine.ecc, ECC_OFFSET_NONCE, &nonce);
+        engine.run(ECC_CONTROL_GEN_KEY);
+        let priv_key = engine.read(ECC_OFFSET_PRIV_KEY_OUT);
+        let (x, y) = openssl_pub_key(&priv_key);
+        prop_assert_eq!(engine.pub_key(), (x.to_vec(), y.to_vec()));
+    }
+
+    #[test]
+    fn test_ecc384_sign(priv_key in bytes48(), hash in bytes48()) {
+        let mut engine = EccEngine::new();
+        write_words(&mut engine.ecc, ECC_OFFSET_PRIV_KEY_IN, &priv_key);
+        let (r, s) = engine.sign(&hash);
+        let (x, y) = openssl_pub_key(&priv_key);
+        prop_assert!(openssl_verify(&x, &y, &hash, &r, &s));
+    }
+
+    #[test]
+    fn test_ecc384_verify(hash in bytes48(), tamper in prop::option::of(0usize..48)) {
+        let key = EcKey::generate(&p384()).unwrap();
+        let sig = EcdsaSig::sign(&hash, &key).unwrap();
+        let r = sig.r().to_vec_padded(48).unwrap();
+        let s = sig.s().to_vec_padded(48).unwrap();
+        let (x, y) = openssl_pub_key(&key.private_key().to_vec_padded(48).unwrap());
+
+        let mut signed_hash = hash;
+        if let Some(i) = tamper {
+            signed_hash[i] ^= 1;
+        }
+        let mut engine = EccEngine::new();
+        write_words(&mut engine.ecc, ECC_OFFSET_PUB_KEY_X, &x);
+        write_words(&mut engine.ecc, ECC_OFFSET_PUB_KEY_Y, &y);
+        write_words(&mut engine.ecc, ECC_OFFSET_HASH, &signed_hash);
+        write_words(&mut engine.ecc, ECC_OFFSET_SIG_R, &r);
+        write_words(&mut engine.ecc, ECC_OFFSET_SIG_S, &s);
+        engine.run(ECC_CONTROL_VERIFY);
+        prop_assert_eq!(engine.read(ECC_OFFSET_VERIFY_R) == r, tamper.is_none());
+    }
+
+    #[test]
+    fn test_ecc384_key_vault(
+        seed_id in key_id(),
+        priv_id in key_id(),
+        seed in bytes48(),
+        nonce in bytes48(),
+        hash in bytes48(),
+    ) {
+        // Generate a key pair from a seed in the vault, with the private key
+        // going back to the vault, then sign with it.
+        let mut engine = EccEngine::new();
+        write_kv(&mut engine.kv, seed_id, &seed, usage(|u| u.set_ecc_key_gen_seed(true)));
+        engine
+            .ecc
+            .write(RvSize::Word, ECC_OFFSET_SEED_READ_CONTROL, kv_read_ctrl(seed_id))
+            .unwrap();
+        wait_for_kv(&engine.clock, &mut engine.ecc, ECC_OFFSET_SEED_READ_STATUS);
+        let priv_usage = usage(|u| u.set_ecc_private_key(true));
+        engine
+            .ecc
+            .write(
+                RvSize::Word,
+                ECC_OFFSET_KEY_WRITE_CONTROL,
+                kv_write_ctrl(priv_id, priv_usage),
+            )
+            .unwrap();
+        write_words(&mut engine.ecc, ECC_OFFSET_NONCE, &nonce);
+        engine.run(ECC_CONTROL_GEN_KEY);
+        wait_for_kv(&engine.clock, &mut engine.ecc, ECC_OFFSET_KEY_WRITE_STATUS);
+        let (x, y) = engine.pub_key();
+
+        // The private key never leaves the vault.
+        prop_assert_eq!(engine.read(ECC_OFFSET_PRIV_KEY_OUT), vec![0u8; 48]);
+        let priv_key = read_kv(&engine.kv, priv_id, priv_usage);
+        let (expected_x, expected_y) = openssl_pub_key(&priv_key);
+        prop_assert_eq!((&x[..], &y[..]), (&expected_x[..], &expected_y[..]));
+
+        // The same seed in the registers gives the same key pair.
+        let mut reference = EccEngine::new();
+        write_words(&mut reference.ecc, ECC_OFFSET_SEED, &seed);
+        write_words(&mut reference.ecc, ECC_OFFSET_NONCE, &nonce);
+        reference.run(ECC_CONTROL_GEN_KEY);
+        prop_assert_eq!(reference.read(ECC_OFFSET_PRIV_KEY_OUT), priv_key);
+
+        engine
+            .ecc
+            .write(RvSize::Word, ECC_OFFSET_KEY_READ_CONTROL, kv_read_ctrl(priv_id))
+            .unwrap();
+        wait_for_kv(&engine.clock, &mut engine.ecc, ECC_OFFSET_KEY_READ_STATUS);
+        let (r, s) = engine.sign(&hash);
+        prop_assert!(openssl_verify(&x, &y, &hash, &r, &s));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn test_sha512_acc_stream(
+        mode in sha_mode(),
+        data in prop::collection::vec(any::<u8>(), 0..1024),
+        fill in any::<u8>(),
+    ) {
+        prop_assert_eq!(acc_stream(mode, &data, fill), openssl_hash(mode, &data));
+    }
+
+    #[test]
+    fn test_sha512_acc_mbox(
+        data in prop::collection::vec(any::<u8>(), 0..2048),
+        start_word in 0u32..1024,
+        endian_toggle in any::<bool>(),
+        fill in any::<u8>(),
+    ) {
+        let expected = openssl_hash(ShaMode::Sha384, &acc_mbox_message(&data, endian_toggle, fill));
+        prop_assert_eq!(acc_mbox(&data, start_word * 4, endian_toggle, fill), expected);
+    }
+}
@@ -0,0 +1,195 @@
+// Licensed under the Apache-2.0 license
+
+//! Golden files for the externally visible outputs of a device: certs,
+//! CSRs, PCRs and the UART log.
+//!
+//! Combined with `InitParams::deterministic`, these outputs are reproducible
+//! bit for bit, so they can be compared without redaction. When a firmware
+//! change is expected to alter them, run the tests with
+//! `CPTRA_UPDATE_GOLDEN=1` to rewrite the golden files, and review the
+//! resulting `git diff`. Certs and CSRs are stored both as DER and as
+//! `openssl x509 -text` output, so the diff is readable.
+
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// If this environment variable is set, golden files are written instead of
+/// compared.
+pub const UPDATE_GOLDEN_ENV: &str = "CPTRA_UPDATE_GOLDEN";
+
+pub struct GoldenDir {
+    dir: PathBuf,
+    update: bool,
+}
+
+impl GoldenDir {
+    /// Golden files stored in `dir`. Relative paths are resolved against the
+    /// working directory, which for `cargo test` is the crate's directory.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            update: std::env::var_os(UPDATE_GOLDEN_ENV).is_some(),
+        }
+    }
+
+    /// Compare `actual` with the golden file `name`.
+    ///
+    /// # Panics
+    ///
+    /// If the contents differ or the golden file doesn't exist. `actual` is
+    /// written next to the golden file as `<name>.actual` for inspection.
+    pub fn check_bytes(&self, name: &str, actual: &[u8]) {
+        let path = self.dir.join(name);
+        if self.update {
+            std::fs::create_dir_all(&self.dir).unwrap();
+            std::fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read(&path).unwrap_or_default();
+        if expected == actual {
+            return;
+        }
+        let actual_path = self.dir.join(format!("{name}.actual"));
+        std::fs::write(&actual_path, actual).unwrap();
+        panic!(
+            "{} doesn't match the golden file ({}); the actual output was written to {}. \
+             If the change is expected, rerun with {UPDATE_GOLDEN_ENV}=1.",
+            name,
+            describe_mismatch(&expected, actual),
+            actual_path.display(),
+        );
+    }
+
+    /// Like [`Self::check_bytes`], for text such as a UART log.
+    pub fn check_text(&self, name: &str, actual: &str) {
+        self.check_bytes(name, actual.as_bytes());
+    }
+
+    /// Check an X.509 certificate against `<name>.der` and `<name>.txt`.
+    pub fn check_cert(&self, name: &str, der: &[u8]) {
+        let cert = openssl::x509::X509::from_der(der).unwrap();
+        self.check_bytes(&format!("{name}.txt"), &cert.to_text().unwrap());
+        self.check_bytes(&format!("{name}.der"), der);
+    }
+
+    /// Check a PKCS#10 CSR against `<name>.der` and `<name>.txt`.
+    pub fn check_csr(&self, name: &str, der: &[u8]) {
+        let csr = openssl::x509::X509Req::from_der(der).unwrap();
+        self.check_bytes(&format!("{name}.txt"), &csr.to_text().unwrap());
+        self.check_bytes(&format!("{name}.der"), der);
+    }
+
+    /// Check PCR values against the text file `name`, one PCR per line.
+    pub fn check_pcrs(&self, name: &str, pcrs: &[[u8; 48]]) {
+        self.check_text(name, &pcrs_to_text(pcrs));
+    }
+}
+
+fn pcrs_to_text(pcrs: &[[u8; 48]]) -> String {
+    let mut result = String::new();
+    for (i, pcr) in pcrs.iter().enumerate() {
+        write!(result, "PCR{i:02}: ").unwrap();
+        for b in pcr {
+            write!(result, "{b:02x}").unwrap();
+        }
+        result.push('\n');
+    }
+    result
+}
+
+fn describe_mismatch(expected: &[u8], actual: &[u8]) -> String {
+    if expected.is_empty() {
+        return "golden file is missing or empty".into();
+    }
+    if let (Ok(expected), Ok(actual)) = (std::str::from_utf8(expected), std::str::from_utf8(actual))
+    {
+        let mut expected_lines = expected.lines();
+        let mut actual_lines = actual.lines();
+        for line in 1.. {
+            match (expected_lines.next(), actual_lines.next()) {
+                (Some(e), Some(a)) if e == a => continue,
+                (e, a) => {
+                    return format!(
+                        "first difference at line {line}: expected {:?}, got {:?}",
+                        e.unwrap_or("<end of file>"),
+                        a.unwrap_or("<end of file>")
+                    )
+                }
+            }
+        }
+    }
+    let offset = expected
+        .iter()
+        .zip(actual)
+        .position(|(e, a)| e != a)
+        .unwrap_or(expected.len().min(actual.len()));
+    format!(
+        "first difference at byte offset {offset}; expected {} bytes, got {}",
+        expected.len(),
+        actual.len()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcrs_to_text() {
+        let mut pcr1 = [0u8; 48];
+        pcr1[0] = 0xab;
+        assert_eq!(
+            pcrs_to_text(&[[0u8; 48], pcr1]),
+            format!("PCR00: {}\nPCR01: ab{}\n", "0".repeat(96), "0".repeat(94))
+        );
+    }
+
+    #[test]
+    fn test_describe_mismatch() {
+        assert_eq!(
+            describe_mismatch(b"", b"hi"),
+            "golden file is missing or empty"
+        );
+        assert_eq!(
+            describe_mismatch(b"a\nb\nc\n", b"a\nx\nc\n"),
+            "first difference at line 2: expected \"b\", got \"x\""
+        );
+        assert_eq!(
+            describe_mismatch(b"a\n", b"a\nb\n"),
+            "first difference at line 2: expected \"<end of file>\", got \"b\""
+        );
+        assert_eq!(
+            describe_mismatch(&[0x30, 0x82, 0xff], &[0x30, 0x83]),
+            "first difference at byte offset 1; expected 3 bytes, got 2"
+        );
+    }
+
+    #[test]
+    fn test_check_bytes() {
+        let dir = std::env::temp_dir().join(format!("golden_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("log.txt"), "hello\n").unwrap();
+        let golden = GoldenDir {
+            dir: dir.clone(),
+            update: false,
+        };
+        golden.check_text("log.txt", "hello\n");
+        let result = std::panic::catch_unwind(|| golden.check_text("log.txt", "goodbye\n"));
+        assert!(result.is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("log.txt.actual")).unwrap(),
+            "goodbye\n"
+        );
+
+        let golden = GoldenDir {
+            dir: dir.clone(),
+            update: true,
+        };
+        golden.check_text("log.txt", "goodbye\n");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("log.txt")).unwrap(),
+            "goodbye\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
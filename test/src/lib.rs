@@ -8,10 +8,12 @@ use caliptra_hw_model::{BootParams, DefaultHwModel, HwModel, InitParams};
 
 pub mod crypto;
 pub mod derive;
+pub mod golden;
 mod redact;
 mod unwrap_single;
 pub mod x509;
 
+pub use golden::GoldenDir;
 pub use redact::{redact_cert, RedactOpts};
 pub use unwrap_single::UnwrapSingle;
 
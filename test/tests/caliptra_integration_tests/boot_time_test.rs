@@ -0,0 +1,51 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::{
+    firmware::{self, APP_WITH_UART, FMC_WITH_UART},
+    ImageOptions,
+};
+use caliptra_common::RomBootStatus::ColdResetComplete;
+use caliptra_hw_model::{BootParams, CryptoTiming, HwModel, InitParams};
+
+// The number of cycles from power-on until the runtime is ready for commands
+// that the firmware must stay within. This is the boot time the emulator
+// app's default watchdog timeout is based on.
+const BOOT_TIME_BUDGET_CYCLES: u64 = 20_000_000;
+
+#[test]
+// The crypto timing profile only applies to the emulator; the other models
+// are as slow as their crypto engines.
+#[cfg_attr(any(feature = "verilator", feature = "fpga_realtime"), ignore)]
+fn test_boot_time_budget() {
+    let rom = caliptra_builder::build_firmware_rom(firmware::rom_from_env()).unwrap();
+    let image = caliptra_builder::build_and_sign_image(
+        &FMC_WITH_UART,
+        &APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap()
+    .to_bytes()
+    .unwrap();
+
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom: &rom,
+            crypto_timing: CryptoTiming::VERILATED,
+            ..Default::default()
+        },
+        fw_image: Some(&image),
+        ..Default::default()
+    })
+    .unwrap();
+
+    hw.step_until_boot_status(ColdResetComplete.into(), true);
+    let rom_cycles = hw.output().sink().now();
+    hw.step_until(|m| m.soc_ifc().cptra_flow_status().read().ready_for_runtime());
+    let boot_cycles = hw.output().sink().now();
+    println!("ROM: {rom_cycles} cycles, ready for runtime: {boot_cycles} cycles");
+
+    assert!(
+        boot_cycles <= BOOT_TIME_BUDGET_CYCLES,
+        "Boot took {boot_cycles} cycles, over the budget of {BOOT_TIME_BUDGET_CYCLES}"
+    );
+}
@@ -0,0 +1,123 @@
+// Licensed under the Apache-2.0 license
+
+use caliptra_builder::{firmware, ImageOptions};
+use caliptra_common::mailbox_api::{
+    GetFmcAliasCertReq, GetLdevCertReq, GetRtAliasCertReq, ResponseVarSize,
+};
+use caliptra_common::RomBootStatus::ColdResetComplete;
+use caliptra_hw_model::{BootParams, HwModel, InitParams};
+use caliptra_test::GoldenDir;
+use std::mem;
+
+const GENERATE_IDEVID_CSR: u32 = 1;
+
+// Reads all the PCRs from TEST_FMC_INTERACTIVE
+const TEST_FMC_READ_PCRS: u32 = 0x1000_0006;
+
+#[derive(Debug, Eq, PartialEq)]
+struct BootOutputs {
+    idevid_csr: Vec<u8>,
+    ldevid_cert: Vec<u8>,
+    fmc_alias_cert: Vec<u8>,
+    rt_alias_cert: Vec<u8>,
+    log: String,
+    pcrs: Vec<[u8; 48]>,
+}
+
+fn boot(rom: &[u8], image: &[u8], pcr_image: &[u8]) -> BootOutputs {
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom,
+            deterministic: true,
+            ..Default::default()
+        },
+        initial_dbg_manuf_service_reg: GENERATE_IDEVID_CSR,
+        ..Default::default()
+    })
+    .unwrap();
+
+    let mut txn = hw.wait_for_mailbox_receive().unwrap();
+    let idevid_csr = mem::take(&mut txn.req.data);
+    txn.respond_success();
+    hw.soc_ifc().cptra_dbg_manuf_service_reg().write(|_| 0);
+
+    hw.step_until(|m| m.ready_for_fw());
+    hw.upload_firmware(image).unwrap();
+    hw.step_until_output_contains("Caliptra RT listening for mailbox commands...\n")
+        .unwrap();
+
+    let ldevid_cert = hw.mailbox_execute_req(GetLdevCertReq::default()).unwrap();
+    let fmc_alias_cert = hw
+        .mailbox_execute_req(GetFmcAliasCertReq::default())
+        .unwrap();
+    let rt_alias_cert = hw
+        .mailbox_execute_req(GetRtAliasCertReq::default())
+        .unwrap();
+    let log = hw.output().take(usize::MAX);
+
+    // The production runtime doesn't expose the PCRs, so boot the test FMC
+    // to read them.
+    let mut hw = caliptra_hw_model::new(BootParams {
+        init_params: InitParams {
+            rom,
+            deterministic: true,
+            ..Default::default()
+        },
+        fw_image: Some(pcr_image),
+        ..Default::default()
+    })
+    .unwrap();
+    hw.step_until_boot_status(u32::from(ColdResetComplete), true);
+    let pcrs = hw
+        .mailbox_execute(TEST_FMC_READ_PCRS, &[])
+        .unwrap()
+        .unwrap();
+
+    BootOutputs {
+        idevid_csr,
+        ldevid_cert: ldevid_cert.data().unwrap().to_vec(),
+        fmc_alias_cert: fmc_alias_cert.data().unwrap().to_vec(),
+        rt_alias_cert: rt_alias_cert.data().unwrap().to_vec(),
+        log,
+        pcrs: pcrs
+            .chunks_exact(48)
+            .map(|pcr| pcr.try_into().unwrap())
+            .collect(),
+    }
+}
+
+#[test]
+fn test_golden_boot_outputs() {
+    #![cfg_attr(feature = "fpga_realtime", ignore)]
+
+    let rom = caliptra_builder::build_firmware_rom(&firmware::ROM_WITH_UART).unwrap();
+    let image = caliptra_builder::build_and_sign_image(
+        &firmware::FMC_WITH_UART,
+        &firmware::APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap()
+    .to_bytes()
+    .unwrap();
+    let pcr_image = caliptra_builder::build_and_sign_image(
+        &firmware::rom_tests::TEST_FMC_INTERACTIVE,
+        &firmware::APP_WITH_UART,
+        ImageOptions::default(),
+    )
+    .unwrap()
+    .to_bytes()
+    .unwrap();
+
+    let outputs = boot(&rom, &image, &pcr_image);
+    assert_eq!(outputs, boot(&rom, &image, &pcr_image));
+
+    // If a firmware change alters these, rerun with CPTRA_UPDATE_GOLDEN=1 and
+    // review the diff.
+    let golden = GoldenDir::new("tests/caliptra_integration_tests/golden_testdata");
+    golden.check_csr("idevid_csr", &outputs.idevid_csr);
+    golden.check_cert("ldevid_cert", &outputs.ldevid_cert);
+    golden.check_cert("fmc_alias_cert", &outputs.fmc_alias_cert);
+    golden.check_cert("rt_alias_cert", &outputs.rt_alias_cert);
+    golden.check_pcrs("pcrs.txt", &outputs.pcrs);
+    golden.check_text("uart_log.txt", &outputs.log);
+}
@@ -1,6 +1,8 @@
 // Licensed under the Apache-2.0 license
 
+mod boot_time_test;
 mod fake_collateral_boot_test;
+mod golden_test;
 mod smoke_test;
 mod test_code_coverage;
 mod warm_reset;